@@ -0,0 +1,338 @@
+//! Locations saved by the user to return to later, with the settings they were found with
+
+use std::fmt::Write;
+use thiserror::Error;
+
+use crate::fractal::{FractalKind, FractalParams, POWER_RANGE};
+use crate::primitives::{CoordError, Coordinates, Dimensions};
+use crate::session::{encode_julia, encode_wide, parse_julia, parse_wide};
+use crate::view_state::Precision;
+
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bookmark {
+    pub name: String,
+    pub coords: Coordinates,
+    /// Size of the view the coordinates were saved from
+    pub dimensions: Dimensions,
+    /// View scale factor the coordinate step applies to
+    pub scale_factor: f64,
+    pub fractal: FractalParams,
+    pub precision: Precision,
+    pub max_depth: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum BookmarkError {
+    #[error("Unsupported bookmarks version {0}")]
+    Version(String),
+    #[error("Missing field {0}")]
+    MissingField(&'static str),
+    #[error("Invalid field {0}")]
+    InvalidField(&'static str),
+    #[error("Invalid coordinates: {0}")]
+    Coordinates(#[from] CoordError),
+}
+
+impl Bookmark {
+    pub(crate) fn encode(&self, out: &mut String) {
+        // Names are single line, a line break would start a field
+        writeln!(out, "name {}", self.name.replace(['\n', '\r'], " ")).unwrap();
+        writeln!(out, "x {}", encode_wide(&self.coords.x)).unwrap();
+        writeln!(out, "y {}", encode_wide(&self.coords.y)).unwrap();
+        writeln!(out, "step {}", encode_wide(&self.coords.step)).unwrap();
+        writeln!(out, "precision {}", self.coords.precision()).unwrap();
+        writeln!(out, "rotation {}", self.coords.rotation()).unwrap();
+        writeln!(
+            out,
+            "size {} {}",
+            self.dimensions.width, self.dimensions.height
+        )
+        .unwrap();
+        writeln!(out, "scale_factor {}", self.scale_factor).unwrap();
+        writeln!(out, "fractal {}", self.fractal.kind.name()).unwrap();
+        writeln!(out, "inverted {}", self.fractal.inverted).unwrap();
+        writeln!(out, "power {}", self.fractal.power).unwrap();
+        if let Some(seed) = &self.fractal.julia {
+            writeln!(out, "julia {}", encode_julia(seed)).unwrap();
+        }
+        writeln!(out, "precision_mode {}", self.precision).unwrap();
+        writeln!(out, "max_depth {}", self.max_depth).unwrap();
+    }
+
+    pub(crate) fn decode(lines: &[&str]) -> Result<Self, BookmarkError> {
+        let field = |name: &'static str| {
+            lines
+                .iter()
+                .find_map(|line| {
+                    let (key, value) = line.split_once(' ')?;
+                    (key == name).then_some(value.trim())
+                })
+                .ok_or(BookmarkError::MissingField(name))
+        };
+        fn parse<T: std::str::FromStr>(
+            name: &'static str,
+            value: &str,
+        ) -> Result<T, BookmarkError> {
+            value.parse().map_err(|_| BookmarkError::InvalidField(name))
+        }
+        let wide = |name| parse_wide(field(name)?).ok_or(BookmarkError::InvalidField(name));
+
+        let mut coords = Coordinates::from_parts(
+            wide("x")?,
+            wide("y")?,
+            wide("step")?,
+            parse("precision", field("precision")?)?,
+        )?;
+        coords.set_rotation(parse("rotation", field("rotation")?)?)?;
+
+        let (width, height) = field("size")?
+            .split_once(' ')
+            .ok_or(BookmarkError::InvalidField("size"))?;
+        let (width, height) = (parse("size", width)?, parse("size", height)?);
+        if width == 0 || height == 0 {
+            return Err(BookmarkError::InvalidField("size"));
+        }
+
+        let scale_factor: f64 = parse("scale_factor", field("scale_factor")?)?;
+        if !(scale_factor.is_finite() && scale_factor > 0.0) {
+            return Err(BookmarkError::InvalidField("scale_factor"));
+        }
+
+        Ok(Bookmark {
+            name: field("name")?.to_owned(),
+            coords,
+            dimensions: Dimensions { width, height },
+            scale_factor,
+            fractal: FractalParams {
+                kind: FractalKind::from_name(field("fractal")?)
+                    .ok_or(BookmarkError::InvalidField("fractal"))?,
+                inverted: parse("inverted", field("inverted")?)?,
+                // Bookmarks saved before multibrots square z
+                power: match field("power") {
+                    Ok(value) => Some(parse::<u32>("power", value)?)
+                        .filter(|power| POWER_RANGE.contains(power))
+                        .ok_or(BookmarkError::InvalidField("power"))?,
+                    Err(_) => 2,
+                },
+                julia: match field("julia") {
+                    Ok(value) => {
+                        Some(parse_julia(value).ok_or(BookmarkError::InvalidField("julia"))?)
+                    }
+                    Err(_) => None,
+                },
+            },
+            precision: parse("precision_mode", field("precision_mode")?)?,
+            max_depth: parse("max_depth", field("max_depth")?)?,
+        })
+    }
+}
+
+/// Serializes bookmarks into line based `key value` text, every bookmark starting with its name
+pub fn encode(bookmarks: &[Bookmark]) -> String {
+    let mut out = String::new();
+    writeln!(out, "version {}", VERSION).unwrap();
+    for bookmark in bookmarks {
+        writeln!(out).unwrap();
+        bookmark.encode(&mut out);
+    }
+    out
+}
+
+/// Parses bookmarks written by [`encode`]. Corrupt bookmarks are skipped, so that one of them
+/// doesn't lose the others
+pub fn decode(text: &str) -> Result<Vec<Bookmark>, BookmarkError> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let version = lines
+        .next()
+        .and_then(|line| line.strip_prefix("version "))
+        .ok_or(BookmarkError::MissingField("version"))?
+        .trim();
+    if version.parse::<u32>().ok() != Some(VERSION) {
+        return Err(BookmarkError::Version(version.to_owned()));
+    }
+
+    let mut entries: Vec<Vec<&str>> = vec![];
+    for line in lines {
+        match entries.last_mut() {
+            Some(entry) if !line.starts_with("name ") => entry.push(line),
+            _ => entries.push(vec![line]),
+        }
+    }
+    Ok(entries
+        .iter()
+        .filter_map(|lines| match Bookmark::decode(lines) {
+            Ok(bookmark) => Some(bookmark),
+            Err(e) => {
+                log::warn!("Skipping corrupt bookmark: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Loads the saved bookmarks. A missing or unreadable list is empty
+pub fn load() -> Vec<Bookmark> {
+    let Some(text) = storage::read() else {
+        return vec![];
+    };
+    match decode(&text) {
+        Ok(bookmarks) => bookmarks,
+        Err(e) => {
+            log::warn!("Ignoring corrupt bookmarks: {}", e);
+            vec![]
+        }
+    }
+}
+
+pub fn save(bookmarks: &[Bookmark]) {
+    storage::write(&encode(bookmarks));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::path::PathBuf;
+
+    fn path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("mandelbrot").join("bookmarks.txt"))
+    }
+
+    pub fn read() -> Option<String> {
+        let path = path()?;
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Some(text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("Unable to read bookmarks {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn write(text: &str) {
+        let Some(path) = path() else {
+            log::warn!("No config directory to save the bookmarks to");
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, text));
+        if let Err(e) = result {
+            log::warn!("Unable to save bookmarks {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    const KEY: &str = "mandelbrot-bookmarks";
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok().flatten()
+    }
+
+    pub fn read() -> Option<String> {
+        local_storage()?.get_item(KEY).ok().flatten()
+    }
+
+    pub fn write(text: &str) {
+        if let Some(Err(e)) = local_storage().map(|s| s.set_item(KEY, text)) {
+            log::warn!("Unable to save bookmarks: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::JuliaSeed;
+
+    fn bookmarks() -> Vec<Bookmark> {
+        let mut coords = Coordinates::new(-0.7436, 0.1318, 1e-3, 10).unwrap();
+        for _ in 0..40 {
+            coords.zoom_with_anchor(0.5, 400.0, 250.0, 1.0).unwrap();
+        }
+        coords.set_rotation(45.0).unwrap();
+        vec![
+            Bookmark {
+                name: "Seahorse valley".to_owned(),
+                coords,
+                dimensions: Dimensions::new_nonzero(1280, 720),
+                scale_factor: 1.5,
+                fractal: FractalParams {
+                    julia: JuliaSeed::from_f64(-0.123, 0.745),
+                    ..Default::default()
+                },
+                precision: Precision::Auto,
+                max_depth: 20_000,
+            },
+            Bookmark {
+                name: "Ship".to_owned(),
+                coords: Coordinates::new(-1.75, -0.03, 1e-4, 24).unwrap(),
+                dimensions: Dimensions::new_nonzero(800, 600),
+                scale_factor: 1.0,
+                fractal: FractalParams {
+                    kind: FractalKind::PerpendicularBurningShip,
+                    inverted: true,
+                    power: 4,
+                    julia: None,
+                },
+                precision: Precision::Manual(64),
+                max_depth: 500,
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let bookmarks = bookmarks();
+        assert!(bookmarks[0].coords.size() > 2);
+        let encoded = encode(&bookmarks);
+        assert_eq!(decode(&encoded).unwrap(), bookmarks);
+        assert_eq!(decode(&encode(&[])).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn names_stay_on_one_line() {
+        let mut bookmarks = bookmarks();
+        bookmarks[0].name = "Two\nlines".to_owned();
+        let decoded = decode(&encode(&bookmarks)).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].name, "Two lines");
+    }
+
+    #[test]
+    fn corrupt_bookmarks_are_skipped() {
+        let encoded = encode(&bookmarks());
+        assert!(decode("").is_err());
+        assert!(decode(&encoded.replace("version 1", "version 2")).is_err());
+
+        for (from, to) in [
+            ("fractal mandelbrot", "fractal julia"),
+            ("size 1280 720", "size 1280"),
+            ("size 1280 720", "size 0 720"),
+            ("scale_factor 1.5", "scale_factor -1"),
+            ("rotation 45", "rotation NaN"),
+            ("max_depth 20000", "max_depth lots"),
+            ("julia ", "julia zz "),
+            ("power 2", "power 0"),
+        ] {
+            let decoded = decode(&encoded.replace(from, to)).unwrap();
+            assert_eq!(decoded, bookmarks()[1..], "{}", to);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let bookmarks = bookmarks();
+        let json = serde_json::to_string(&bookmarks).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<Bookmark>>(&json).unwrap(),
+            bookmarks
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! Opt-in disk cache of the calculation progress, so that very deep renders survive a restart.
+//! Snapshots of the compute buffers are keyed by the exact calculation, and the least recently
+//! used ones are evicted once the cache grows past its size limit
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use thiserror::Error;
+use winit::event_loop::EventLoopProxy;
+
+use crate::gpu::{GpuContext, Snapshot, SnapshotKey};
+use crate::i18n::Language;
+use crate::timer::{Clock, SystemClock};
+use crate::UserEvent;
+
+/// Version of the entry layout. Changes of the compute shader invalidate entries through their
+/// keys, bump this when the file layout itself changes
+const FORMAT_VERSION: u32 = 1;
+const MAGIC: &[u8; 4] = b"MBIC";
+const EXTENSION: &str = "bin";
+
+/// Cache size limit in bytes
+const MAX_BYTES: u64 = 2 << 30;
+/// Time between saves of views in progress in milliseconds
+const SAVE_INTERVAL_MS: f64 = 120_000.0;
+/// Shallower calculations are quick to redo and aren't saved
+const MIN_DEPTH: u32 = 100_000;
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("Cache I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Cache entry is corrupt or made by another version")]
+    Format,
+    #[error("Snapshot is larger than the whole cache")]
+    TooLarge,
+}
+
+/// Directory with cache entries
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl DiskCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self { dir, max_bytes }
+    }
+
+    /// Cache in the user cache directory
+    pub fn open() -> Option<Self> {
+        let dir = dirs::cache_dir()?.join("mandelbrot").join("iterations");
+        Some(Self::new(dir, MAX_BYTES))
+    }
+
+    fn path(&self, key: &SnapshotKey) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}", key.fingerprint()))
+            .with_extension(EXTENSION)
+    }
+
+    /// Writes a snapshot, evicting old entries to make room. Returns the size of the cache
+    pub fn save(&self, snapshot: &Snapshot) -> Result<u64, CacheError> {
+        let bytes = encode(snapshot)?;
+        if bytes.len() as u64 > self.max_bytes {
+            return Err(CacheError::TooLarge);
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        // Written in full before it replaces the previous entry
+        let path = self.path(&snapshot.key);
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, bytes)?;
+        std::fs::rename(&partial, &path)?;
+        self.evict()
+    }
+
+    /// Reads the snapshot of a calculation. Entries of other versions are removed
+    pub fn load(&self, key: &SnapshotKey) -> Result<Option<Snapshot>, CacheError> {
+        let path = self.path(key);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let snapshot = match decode(&bytes) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                std::fs::remove_file(&path)?;
+                return Err(e);
+            }
+        };
+        // Fingerprints of different keys may collide
+        if snapshot.key != *key {
+            return Ok(None);
+        }
+        // Modification time orders entries for eviction
+        std::fs::File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(SystemTime::now())?;
+        Ok(Some(snapshot))
+    }
+
+    /// Returns the total size of the entries in bytes
+    pub fn usage(&self) -> u64 {
+        self.entries()
+            .map_or(0, |entries| entries.iter().map(|e| e.1).sum())
+    }
+
+    /// Removes the least recently used entries until the cache fits its limit. Returns the size
+    /// of the cache
+    fn evict(&self) -> Result<u64, CacheError> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|entry| entry.2);
+        let mut usage: u64 = entries.iter().map(|entry| entry.1).sum();
+        for (path, size, _) in entries {
+            if usage <= self.max_bytes {
+                break;
+            }
+            log::info!("Evicting cache entry {}", path.display());
+            std::fs::remove_file(&path)?;
+            usage -= size;
+        }
+        Ok(usage)
+    }
+
+    /// Returns the path, the size and the last use of every entry
+    fn entries(&self) -> std::io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut entries = Vec::new();
+        let dir = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+            Err(e) => return Err(e),
+        };
+        for entry in dir {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            let metadata = std::fs::metadata(&path)?;
+            entries.push((path, metadata.len(), metadata.modified()?));
+        }
+        Ok(entries)
+    }
+}
+
+/// Serializes a snapshot into a versioned header followed by the compressed buffers
+fn encode(snapshot: &Snapshot) -> std::io::Result<Vec<u8>> {
+    let key = snapshot.key.as_bytes();
+    let mut payload = Vec::with_capacity(
+        16 + key.len() + snapshot.iterations.len() + snapshot.intermediate.len(),
+    );
+    payload.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    payload.extend_from_slice(key);
+    payload.extend_from_slice(&snapshot.depth.to_le_bytes());
+    payload.extend_from_slice(&(snapshot.iterations.len() as u64).to_le_bytes());
+    payload.extend_from_slice(&snapshot.iterations);
+    payload.extend_from_slice(&snapshot.intermediate);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&zstd::encode_all(&payload[..], 0)?);
+    Ok(out)
+}
+
+fn decode(bytes: &[u8]) -> Result<Snapshot, CacheError> {
+    let (header, compressed) = bytes.split_at_checked(8).ok_or(CacheError::Format)?;
+    if header[..4] != MAGIC[..] || header[4..] != FORMAT_VERSION.to_le_bytes() {
+        return Err(CacheError::Format);
+    }
+    let mut payload = Vec::new();
+    zstd::Decoder::new(compressed)?.read_to_end(&mut payload)?;
+
+    let mut rest = &payload[..];
+    let mut take = |len: usize| {
+        let (taken, remaining) = rest.split_at_checked(len).ok_or(CacheError::Format)?;
+        rest = remaining;
+        Ok::<_, CacheError>(taken)
+    };
+    let key_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+    let key = SnapshotKey::from_bytes(take(key_len)?.to_vec());
+    let depth = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    let iterations_len = u64::from_le_bytes(take(8)?.try_into().unwrap()) as usize;
+    let iterations = take(iterations_len)?.to_vec();
+    Ok(Snapshot {
+        key,
+        depth,
+        iterations,
+        intermediate: rest.to_vec(),
+    })
+}
+
+/// Outcome of the work done in the background
+#[derive(Debug)]
+pub enum Response {
+    Saved { depth: u32, usage: u64 },
+    Loaded(usize, Snapshot),
+    Failed(String),
+}
+
+enum Request {
+    Save(Snapshot),
+    Load(usize, SnapshotKey),
+}
+
+/// Decides when views are saved and looked up, and keeps disk access off the event loop
+pub struct Cache {
+    disk: DiskCache,
+    requests: flume::Sender<Request>,
+    schedule: Schedule,
+    usage: u64,
+}
+
+impl Cache {
+    /// Starts the background thread that reports to the event loop
+    pub fn start(disk: DiskCache, event_loop_proxy: EventLoopProxy<UserEvent>) -> Self {
+        let (requests, receiver) = flume::unbounded();
+        let worker = disk.clone();
+        std::thread::Builder::new()
+            .name("cache".to_owned())
+            .spawn(move || {
+                for request in receiver {
+                    let response = match request {
+                        Request::Save(snapshot) => match worker.save(&snapshot) {
+                            Ok(usage) => Response::Saved {
+                                depth: snapshot.depth,
+                                usage,
+                            },
+                            Err(e) => Response::Failed(e.to_string()),
+                        },
+                        Request::Load(view, key) => match worker.load(&key) {
+                            Ok(Some(snapshot)) => Response::Loaded(view, snapshot),
+                            Ok(None) => continue,
+                            Err(e) => Response::Failed(e.to_string()),
+                        },
+                    };
+                    if event_loop_proxy
+                        .send_event(UserEvent::CacheResponded(response))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            })
+            .expect("Unable to start the cache thread");
+        Self {
+            usage: disk.usage(),
+            disk,
+            requests,
+            schedule: Schedule::default(),
+        }
+    }
+
+    /// Looks up new calculations of the views, and reads back and saves the deep ones from time
+    /// to time. Called when the GPU is idle
+    pub fn update(&mut self, gpu_context: &mut GpuContext, view_count: usize) {
+        for view in 0..view_count {
+            if let Some(key) = gpu_context.snapshot_key(view) {
+                if self.schedule.needs_lookup(view, key) {
+                    let _ = self.requests.send(Request::Load(view, key.clone()));
+                }
+            }
+        }
+
+        if self.schedule.is_due() {
+            for view in 0..view_count {
+                if self.needs_save(gpu_context, view) {
+                    gpu_context.start_readback(view);
+                }
+            }
+        }
+
+        while let Some((_, snapshot)) = gpu_context.take_snapshot() {
+            self.schedule.saved(&snapshot.key, snapshot.depth);
+            let _ = self.requests.send(Request::Save(snapshot));
+        }
+    }
+
+    /// Applies the response of the background thread. Returns the notice to display
+    pub fn handle(
+        &mut self,
+        response: Response,
+        gpu_context: &mut GpuContext,
+        language: Language,
+    ) -> Result<Option<String>, String> {
+        match response {
+            Response::Saved { depth, usage } => {
+                self.usage = usage;
+                Ok(Some(language.format("notice-cache-saved", &[&depth])))
+            }
+            Response::Loaded(view, snapshot) => {
+                let (key, depth) = (snapshot.key.clone(), snapshot.depth);
+                if !gpu_context.restore(view, snapshot) {
+                    return Ok(None);
+                }
+                self.schedule.saved(&key, depth);
+                Ok(Some(language.format("notice-cache-resumed", &[&depth])))
+            }
+            Response::Failed(e) => Err(e),
+        }
+    }
+
+    /// Saves the views that progressed since the last save, blocking until done
+    pub fn save_now(&mut self, gpu_context: &mut GpuContext, view_count: usize) {
+        for view in 0..view_count {
+            if !self.needs_save(gpu_context, view) {
+                continue;
+            }
+            let Some(snapshot) = gpu_context.read_back_now(view) else {
+                continue;
+            };
+            if let Err(e) = self.disk.save(&snapshot) {
+                log::error!("Unable to save view {} to the cache: {}", view, e);
+            }
+        }
+    }
+
+    /// Size of the cache in bytes as of the last save
+    pub fn usage(&self) -> u64 {
+        self.usage
+    }
+
+    fn needs_save(&self, gpu_context: &GpuContext, view: usize) -> bool {
+        let depth = gpu_context.current_depth(view);
+        gpu_context
+            .snapshot_key(view)
+            .is_some_and(|key| self.schedule.needs_save(key, depth))
+    }
+}
+
+/// Timing and bookkeeping of the cache access
+struct Schedule {
+    clock: SystemClock,
+    last_save_ms: f64,
+    /// Calculation last looked up for each view
+    looked_up: Vec<Option<SnapshotKey>>,
+    /// Depth each calculation is saved or loaded at
+    saved: HashMap<SnapshotKey, u32>,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        let clock = SystemClock::default();
+        Self {
+            last_save_ms: clock.now(),
+            clock,
+            looked_up: Vec::new(),
+            saved: HashMap::new(),
+        }
+    }
+}
+
+impl Schedule {
+    /// Returns true once per calculation of a view
+    fn needs_lookup(&mut self, view: usize, key: &SnapshotKey) -> bool {
+        if self.looked_up.len() <= view {
+            self.looked_up.resize(view + 1, None);
+        }
+        if self.looked_up[view].as_ref() == Some(key) {
+            return false;
+        }
+        self.looked_up[view] = Some(key.clone());
+        true
+    }
+
+    fn needs_save(&self, key: &SnapshotKey, depth: u32) -> bool {
+        depth >= MIN_DEPTH && self.saved.get(key).is_none_or(|saved| depth > *saved)
+    }
+
+    fn saved(&mut self, key: &SnapshotKey, depth: u32) {
+        self.saved.insert(key.clone(), depth);
+    }
+
+    /// Returns true once per save interval
+    fn is_due(&mut self) -> bool {
+        let now = self.clock.now();
+        if now - self.last_save_ms >= SAVE_INTERVAL_MS {
+            self.last_save_ms = now;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(key: &[u8], depth: u32) -> Snapshot {
+        Snapshot {
+            key: SnapshotKey::from_bytes(key.to_vec()),
+            depth,
+            iterations: (0..4096u32).flat_map(|i| (i % 7).to_le_bytes()).collect(),
+            intermediate: (0..8192u32).flat_map(|i| i.to_le_bytes()).collect(),
+        }
+    }
+
+    /// Empty directory unique to the test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let snapshot = snapshot(b"key", 123_456);
+        let encoded = encode(&snapshot).unwrap();
+        assert_eq!(decode(&encoded).unwrap(), snapshot);
+
+        let mut newer = encoded.clone();
+        newer[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(decode(&newer), Err(CacheError::Format)));
+        assert!(decode(&encoded[..6]).is_err());
+        assert!(decode(&encoded[..encoded.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let dir = temp_dir("eviction");
+        let entry_size = encode(&snapshot(b"a", 1)).unwrap().len() as u64;
+        // Room for two entries, their sizes differ slightly
+        let max_bytes = entry_size * 5 / 2;
+        let cache = DiskCache::new(dir.clone(), max_bytes);
+
+        cache.save(&snapshot(b"a", 1)).unwrap();
+        cache.save(&snapshot(b"b", 2)).unwrap();
+        // Loading makes the first entry the most recently used one
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let a = SnapshotKey::from_bytes(b"a".to_vec());
+        assert_eq!(cache.load(&a).unwrap().map(|s| s.depth), Some(1));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(cache.save(&snapshot(b"c", 3)).unwrap() <= max_bytes);
+
+        let b = SnapshotKey::from_bytes(b"b".to_vec());
+        let c = SnapshotKey::from_bytes(b"c".to_vec());
+        assert!(cache.load(&b).unwrap().is_none());
+        assert!(cache.load(&a).unwrap().is_some());
+        assert!(cache.load(&c).unwrap().is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_progress_is_saved() {
+        let mut schedule = Schedule::default();
+        let key = SnapshotKey::from_bytes(b"key".to_vec());
+        assert!(schedule.needs_lookup(0, &key));
+        assert!(!schedule.needs_lookup(0, &key));
+        assert!(schedule.needs_lookup(1, &key));
+
+        assert!(!schedule.needs_save(&key, MIN_DEPTH - 1));
+        assert!(schedule.needs_save(&key, MIN_DEPTH));
+        schedule.saved(&key, MIN_DEPTH * 2);
+        assert!(!schedule.needs_save(&key, MIN_DEPTH * 2));
+        assert!(schedule.needs_save(&key, MIN_DEPTH * 2 + 1));
+    }
+}
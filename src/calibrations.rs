@@ -0,0 +1,203 @@
+//! Calibrated speeds of the GPUs used so far, kept across runs so that a known adapter doesn't
+//! run the calibration frames again
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::fps_balancer::Workload;
+
+const VERSION: u32 = 1;
+
+/// Milliseconds per iteration of a pixel of every calibrated workload, by adapter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Calibrations {
+    speeds: BTreeMap<(String, Workload), f64>,
+}
+
+impl Calibrations {
+    /// Returns the calibrated speeds of `adapter`
+    pub fn of(&self, adapter: &str) -> Vec<(Workload, f64)> {
+        self.speeds
+            .iter()
+            .filter(|((name, _), _)| name == adapter)
+            .map(|((_, workload), &ms)| (*workload, ms))
+            .collect()
+    }
+
+    /// Replaces the speeds of the workloads of `adapter` that have been calibrated again
+    pub fn update(&mut self, adapter: &str, speeds: impl IntoIterator<Item = (Workload, f64)>) {
+        for (workload, ms) in speeds {
+            self.speeds.insert((adapter.to_owned(), workload), ms);
+        }
+    }
+
+    /// Serializes the speeds into `word_count distance_estimation ms adapter` lines
+    pub fn encode(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "version {}", VERSION).unwrap();
+        for ((adapter, workload), ms) in &self.speeds {
+            // Adapter names are single line, a line break would start an entry
+            writeln!(
+                out,
+                "{} {} {} {}",
+                workload.word_count,
+                workload.distance_estimation,
+                ms,
+                adapter.replace(['\n', '\r'], " ")
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    /// Parses saved speeds. Corrupt entries are skipped, the ones of other versions are all
+    /// dropped, as they may have been measured differently
+    pub fn decode(text: &str) -> Self {
+        let mut lines = text.lines();
+        let version = lines.next().and_then(|line| line.strip_prefix("version "));
+        if version.and_then(|v| v.trim().parse::<u32>().ok()) != Some(VERSION) {
+            log::warn!("Ignoring calibrations of another version");
+            return Self::default();
+        }
+        let speeds = lines
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| {
+                let entry = decode_entry(line);
+                if entry.is_none() {
+                    log::warn!("Skipping corrupt calibration: {}", line);
+                }
+                entry
+            })
+            .collect();
+        Self { speeds }
+    }
+}
+
+fn decode_entry(line: &str) -> Option<((String, Workload), f64)> {
+    let mut fields = line.splitn(4, ' ');
+    let workload = Workload {
+        word_count: fields.next()?.parse().ok()?,
+        distance_estimation: fields.next()?.parse().ok()?,
+    };
+    let ms: f64 = fields.next()?.parse().ok()?;
+    let adapter = fields.next()?.trim();
+    (ms.is_finite() && ms > 0.0 && !adapter.is_empty())
+        .then(|| ((adapter.to_owned(), workload), ms))
+}
+
+/// Loads the saved speeds. Missing ones are empty
+pub fn load() -> Calibrations {
+    storage::read()
+        .map(|text| Calibrations::decode(&text))
+        .unwrap_or_default()
+}
+
+pub fn save(calibrations: &Calibrations) {
+    storage::write(&calibrations.encode());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::path::PathBuf;
+
+    fn path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join("mandelbrot")
+                .join("calibration.txt"),
+        )
+    }
+
+    pub fn read() -> Option<String> {
+        let path = path()?;
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Some(text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("Unable to read calibrations {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn write(text: &str) {
+        let Some(path) = path() else {
+            log::warn!("No config directory to save the calibrations to");
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, text));
+        if let Err(e) = result {
+            log::warn!("Unable to save calibrations {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    const KEY: &str = "mandelbrot-calibration";
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok().flatten()
+    }
+
+    pub fn read() -> Option<String> {
+        local_storage()?.get_item(KEY).ok().flatten()
+    }
+
+    pub fn write(text: &str) {
+        if let Some(Err(e)) = local_storage().map(|s| s.set_item(KEY, text)) {
+            log::warn!("Unable to save calibrations: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(word_count: usize, distance_estimation: bool) -> Workload {
+        Workload {
+            word_count,
+            distance_estimation,
+        }
+    }
+
+    #[test]
+    fn speeds_are_kept_per_adapter() {
+        let mut calibrations = Calibrations::default();
+        calibrations.update(
+            "10de:2684 Vulkan GeForce RTX 4090",
+            [(words(2, false), 1e-7)],
+        );
+        calibrations.update(
+            "8086:9a49 Gl Intel Xe",
+            [(words(2, false), 4e-6), (words(3, true), 9e-6)],
+        );
+        calibrations.update("8086:9a49 Gl Intel Xe", [(words(2, false), 5e-6)]);
+
+        let decoded = Calibrations::decode(&calibrations.encode());
+        assert_eq!(decoded, calibrations);
+        assert_eq!(
+            decoded.of("8086:9a49 Gl Intel Xe"),
+            [(words(2, false), 5e-6), (words(3, true), 9e-6)]
+        );
+        assert_eq!(decoded.of("unknown"), []);
+    }
+
+    #[test]
+    fn corrupt_entries_are_skipped() {
+        let text = "version 1\n2 false 1e-6 GPU\n2 maybe 1e-6 GPU\n3 false -1 GPU\n4 true 1e-6\n";
+        assert_eq!(
+            Calibrations::decode(text).of("GPU"),
+            [(words(2, false), 1e-6)]
+        );
+        assert_eq!(
+            Calibrations::decode("version 2\n2 false 1e-6 GPU\n"),
+            Calibrations::default()
+        );
+        assert_eq!(Calibrations::decode(""), Calibrations::default());
+    }
+}
@@ -0,0 +1,570 @@
+//! Command line options of the native app. The web page takes the same options as parameters of
+//! its URL query, like `?x=-0.75&y=0.1&step=1e-9&max-depth=5000`
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::float::WideFloat;
+use crate::fps_balancer::TARGET_FPS_RANGE;
+use crate::i18n::Language;
+use crate::location::Location;
+use crate::primitives::{MAX_WORD_COUNT, MIN_WORD_COUNT};
+use crate::settings::MAX_PRECISION_BITS;
+use crate::view_state::{Precision, ViewState};
+
+/// Bits of the center beyond the ones of the step, enough to tell apart the pixels of any window
+const CENTER_EXTRA_BITS: i32 = 64;
+
+pub const USAGE: &str = "\
+Usage: mandelbrot [OPTIONS]
+
+Options:
+  --fresh             Start at the default position instead of resuming the last session
+  --cache             Save the progress of deep calculations to disk and resume it later
+  --exact             Calculate deep views exactly instead of by perturbation
+  --demo [FILE]       Cycle through showcase locations, or the playlist in FILE, until any input
+  --x <RE>            Center the view on the real coordinate RE, a decimal like -0.75
+  --y <IM>            Center the view on the imaginary coordinate IM
+  --step <STEP>       Show STEP of the plane between neighbouring pixels, like 1e-9
+  --max-depth <N>     Calculate up to N iterations
+  --precision <BITS>  Keep BITS significant bits of the step (1..=128), or auto
+  --width <PIXELS>    Open a window PIXELS wide, needs --height
+  --height <PIXELS>   Open a window PIXELS high, needs --width
+  --fullscreen        Start in fullscreen
+  --fps <FPS>         Aim for FPS frames per second (5..=120), 30 by default
+  --lang <CODE>       Use the language with CODE (en, de) instead of the system one
+  --history <STEPS>   Keep up to STEPS views to go back to, 100 by default
+  --double-click <MS> Take presses up to MS milliseconds apart as a double click, 400 by default
+  --band-budget <MS>  Split frames into bands of rows that take up to MS milliseconds each, 500 by
+                      default
+  --gpu-trace <DIR>   Record a wgpu API trace into DIR
+  --gpu-debug         Enable GPU validation and debug information
+  --help              Print this message";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    /// Don't resume the last session
+    pub fresh: bool,
+    /// Cache the calculation progress on disk
+    pub cache: bool,
+    /// Calculate deep views exactly, without perturbation
+    pub exact: bool,
+    /// Start cycling through a playlist of locations
+    pub demo: bool,
+    /// Playlist to use instead of the built-in one
+    pub demo_playlist: Option<PathBuf>,
+    /// View to start at instead of the last session
+    pub position: Position,
+    /// Iteration limit, overriding the last session
+    pub max_depth: Option<u32>,
+    /// Precision of the view, overriding the last session
+    pub precision: Option<Precision>,
+    /// Inner size of the window in physical pixels, overriding the last session
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub window_size: Option<(u32, u32)>,
+    /// Start in borderless fullscreen
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub fullscreen: bool,
+    /// Frame rate to aim for, overriding the default
+    pub fps: Option<f64>,
+    /// Language of the interface, overriding the saved and the system one
+    pub lang: Option<Language>,
+    /// Number of navigation steps that can be undone, overriding the default
+    pub history_depth: Option<usize>,
+    /// Longest time between the presses of a double click in milliseconds, overriding the default
+    pub double_click_ms: Option<u32>,
+    /// Longest expected time of a band of rows in milliseconds, overriding the default
+    pub band_budget_ms: Option<u32>,
+    /// Directory to record a wgpu API trace into
+    pub gpu_trace: Option<PathBuf>,
+    /// Enable validation and debug information of the GPU backend
+    pub gpu_debug: bool,
+    pub help: bool,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ArgsError {
+    #[error("Unknown option {0}")]
+    Unknown(String),
+    #[error("Missing value of {0}")]
+    MissingValue(&'static str),
+    #[error("Invalid value {1} of {0}")]
+    InvalidValue(&'static str, String),
+}
+
+/// Start view given by `--x`, `--y` and `--step`, the missing parts are kept from the last session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Position {
+    /// Real coordinate of the view center
+    pub x: Option<WideFloat>,
+    /// Imaginary coordinate of the view center
+    pub y: Option<WideFloat>,
+    /// Plane distance between neighbouring physical pixels
+    pub step: Option<WideFloat>,
+}
+
+impl Position {
+    /// Parses the numbers of a position. The step decides their word count, so that the center
+    /// has bits to spare at its zoom
+    fn parse(
+        x: Option<String>,
+        y: Option<String>,
+        step: Option<String>,
+    ) -> Result<Self, ArgsError> {
+        let step = step
+            .map(
+                |text| match WideFloat::from_decimal_str(&text, MAX_WORD_COUNT) {
+                    // Steps too small for the largest numbers are parsed as 0
+                    Ok(step) if step > 0 => Ok(step),
+                    _ => Err(ArgsError::InvalidValue("--step", text)),
+                },
+            )
+            .transpose()?;
+        let size = step.as_ref().map_or(MAX_WORD_COUNT, word_count);
+        let number = |name, text: Option<String>| {
+            text.map(|text| {
+                WideFloat::from_decimal_str(&text, size)
+                    .map_err(|_| ArgsError::InvalidValue(name, text))
+            })
+            .transpose()
+        };
+        Ok(Self {
+            x: number("--x", x)?,
+            y: number("--y", y)?,
+            step: step.map(|mut step| {
+                step.change_precision(size as isize - MAX_WORD_COUNT as isize);
+                step
+            }),
+        })
+    }
+
+    /// Returns the location to show in `view` for the position, none if it has no parts
+    pub fn location(&self, view: &ViewState) -> Option<Location> {
+        if *self == Position::default() {
+            return None;
+        }
+        let current = view.location();
+        let size = [&self.x, &self.y, &self.step]
+            .into_iter()
+            .flatten()
+            .map(WideFloat::word_count)
+            .fold(current.re.word_count(), usize::max);
+        let resized = |number: &WideFloat| {
+            let mut number = number.clone();
+            number.change_precision(size as isize - number.word_count() as isize);
+            number
+        };
+        let shortest_side = WideFloat::from_f32(
+            view.dimensions().shortest_side() as f32 / view.scale_factor() as f32,
+            size,
+        )
+        .expect("Window size must be representable");
+        Some(Location {
+            re: resized(self.x.as_ref().unwrap_or(&current.re)),
+            im: resized(self.y.as_ref().unwrap_or(&current.im)),
+            span: self
+                .step
+                .as_ref()
+                .map(|step| &resized(step) * &shortest_side),
+        })
+    }
+}
+
+/// Returns the word count of the numbers of a view with `step`, which must be positive
+fn word_count(step: &WideFloat) -> usize {
+    let ilog2 = step.ilog2().expect("Step is positive");
+    let fraction_bits = (CENTER_EXTRA_BITS - ilog2).max(0) as usize;
+    // The last word holds the whole part
+    (fraction_bits.div_ceil(32) + 1).clamp(MIN_WORD_COUNT, MAX_WORD_COUNT)
+}
+
+/// Returns the value following the option `name`
+fn value(args: &mut impl Iterator<Item = String>, name: &'static str) -> Result<String, ArgsError> {
+    args.next().ok_or(ArgsError::MissingValue(name))
+}
+
+/// Parses the value `text` of the option `name`, rejecting the ones `valid` doesn't accept
+fn parse_value<T: std::str::FromStr>(
+    name: &'static str,
+    text: String,
+    valid: impl FnOnce(&T) -> bool,
+) -> Result<T, ArgsError> {
+    match text.parse() {
+        Ok(value) if valid(&value) => Ok(value),
+        _ => Err(ArgsError::InvalidValue(name, text)),
+    }
+}
+
+impl Args {
+    /// Parses options, not including the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = Args::default();
+        let (mut x, mut y, mut step) = (None, None, None);
+        let (mut width, mut height) = (None, None);
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--fresh" => parsed.fresh = true,
+                "--cache" => parsed.cache = true,
+                "--exact" => parsed.exact = true,
+                "--demo" => {
+                    parsed.demo = true;
+                    parsed.demo_playlist =
+                        args.next_if(|arg| !arg.starts_with('-')).map(Into::into);
+                }
+                "--x" => x = Some(value(&mut args, "--x")?),
+                "--y" => y = Some(value(&mut args, "--y")?),
+                "--step" => step = Some(value(&mut args, "--step")?),
+                "--max-depth" => {
+                    let depth = value(&mut args, "--max-depth")?;
+                    parsed.max_depth = Some(parse_value("--max-depth", depth, |&d| d > 0)?);
+                }
+                "--precision" => {
+                    let bits = value(&mut args, "--precision")?;
+                    let valid = |precision: &Precision| match *precision {
+                        Precision::Auto => true,
+                        Precision::Manual(bits) => (1..=MAX_PRECISION_BITS).contains(&bits),
+                    };
+                    parsed.precision = Some(parse_value("--precision", bits, valid)?);
+                }
+                "--width" => {
+                    let pixels = value(&mut args, "--width")?;
+                    width = Some(parse_value("--width", pixels, |&w: &u32| w > 0)?);
+                }
+                "--height" => {
+                    let pixels = value(&mut args, "--height")?;
+                    height = Some(parse_value("--height", pixels, |&h: &u32| h > 0)?);
+                }
+                "--fullscreen" => parsed.fullscreen = true,
+                "--fps" => {
+                    let fps = value(&mut args, "--fps")?;
+                    let valid = |fps: &f64| TARGET_FPS_RANGE.contains(fps);
+                    parsed.fps = Some(parse_value("--fps", fps, valid)?);
+                }
+                "--lang" => {
+                    let code = args.next().ok_or(ArgsError::MissingValue("--lang"))?;
+                    let lang = Language::from_code(&code)
+                        .ok_or(ArgsError::InvalidValue("--lang", code))?;
+                    parsed.lang = Some(lang);
+                }
+                "--history" => {
+                    let steps = args.next().ok_or(ArgsError::MissingValue("--history"))?;
+                    let depth = steps
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue("--history", steps))?;
+                    parsed.history_depth = Some(depth);
+                }
+                "--double-click" => {
+                    let ms = args
+                        .next()
+                        .ok_or(ArgsError::MissingValue("--double-click"))?;
+                    let interval = ms
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue("--double-click", ms))?;
+                    parsed.double_click_ms = Some(interval);
+                }
+                "--band-budget" => {
+                    let ms = args
+                        .next()
+                        .ok_or(ArgsError::MissingValue("--band-budget"))?;
+                    let budget = ms
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue("--band-budget", ms))?;
+                    parsed.band_budget_ms = Some(budget);
+                }
+                "--gpu-trace" => {
+                    let dir = args.next().ok_or(ArgsError::MissingValue("--gpu-trace"))?;
+                    parsed.gpu_trace = Some(dir.into());
+                }
+                "--gpu-debug" => parsed.gpu_debug = true,
+                "--help" | "-h" => parsed.help = true,
+                _ => return Err(ArgsError::Unknown(arg)),
+            }
+        }
+        parsed.position = Position::parse(x, y, step)?;
+        parsed.window_size = match (width, height) {
+            (Some(width), Some(height)) => Some((width, height)),
+            (Some(_), None) => return Err(ArgsError::MissingValue("--height")),
+            (None, Some(_)) => return Err(ArgsError::MissingValue("--width")),
+            (None, None) => None,
+        };
+        Ok(parsed)
+    }
+
+    /// Parses the query of a URL, with or without the `?`. Parameters are options without the
+    /// dashes, `key=value` for the ones with a value
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn from_query(query: &str) -> Result<Self, ArgsError> {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        let args = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .flat_map(|param| match param.split_once('=') {
+                Some((key, value)) => vec![format!("--{}", key), value.to_owned()],
+                None => vec![format!("--{}", param)],
+            });
+        Self::parse(args)
+    }
+}
+
+/// Returns the options in the query of the page URL. All of them are ignored if any is invalid
+#[cfg(target_arch = "wasm32")]
+pub fn page_args() -> Args {
+    let query = web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .unwrap_or_default();
+    let query = js_sys::decode_uri_component(&query)
+        .map(String::from)
+        .unwrap_or(query);
+    Args::from_query(&query).unwrap_or_else(|e| {
+        log::error!("Ignoring the page URL options: {}\n\n{}", e, USAGE);
+        Args::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::Dimensions;
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        assert_eq!(parse(&[]), Ok(Args::default()));
+        assert_eq!(
+            parse(&[
+                "--gpu-debug",
+                "--gpu-trace",
+                "trace",
+                "--fresh",
+                "--cache",
+                "--exact"
+            ]),
+            Ok(Args {
+                fresh: true,
+                cache: true,
+                exact: true,
+                demo: false,
+                demo_playlist: None,
+                position: Position::default(),
+                max_depth: None,
+                precision: None,
+                window_size: None,
+                fullscreen: false,
+                fps: None,
+                lang: None,
+                history_depth: None,
+                double_click_ms: None,
+                band_budget_ms: None,
+                gpu_trace: Some("trace".into()),
+                gpu_debug: true,
+                help: false,
+            })
+        );
+    }
+
+    #[test]
+    fn demo_playlist_is_optional() {
+        let demo = parse(&["--demo", "--fresh"]).unwrap();
+        assert!(demo.demo && demo.fresh);
+        assert_eq!(demo.demo_playlist, None);
+        let demo = parse(&["--demo", "booth.txt"]).unwrap();
+        assert_eq!(demo.demo_playlist, Some("booth.txt".into()));
+    }
+
+    #[test]
+    fn language_is_parsed() {
+        assert_eq!(
+            parse(&["--lang", "de"]).unwrap().lang,
+            Some(Language::German)
+        );
+        assert_eq!(
+            parse(&["--lang", "en_GB"]).unwrap().lang,
+            Some(Language::English)
+        );
+    }
+
+    #[test]
+    fn history_depth_is_parsed() {
+        assert_eq!(parse(&["--history", "0"]).unwrap().history_depth, Some(0));
+        assert_eq!(parse(&["--fresh"]).unwrap().history_depth, None);
+    }
+
+    #[test]
+    fn double_click_interval_is_parsed() {
+        assert_eq!(
+            parse(&["--double-click", "250"]).unwrap().double_click_ms,
+            Some(250)
+        );
+        assert_eq!(
+            parse(&["--double-click", "0.5"]),
+            Err(ArgsError::InvalidValue("--double-click", "0.5".to_owned()))
+        );
+    }
+
+    #[test]
+    fn band_budget_is_parsed() {
+        assert_eq!(
+            parse(&["--band-budget", "200"]).unwrap().band_budget_ms,
+            Some(200)
+        );
+        assert_eq!(
+            parse(&["--band-budget"]),
+            Err(ArgsError::MissingValue("--band-budget"))
+        );
+    }
+
+    #[test]
+    fn position_is_parsed_at_the_precision_of_the_step() {
+        let shallow = parse(&["--step", "0.001", "--x", "-0.75"])
+            .unwrap()
+            .position;
+        assert_eq!(shallow.step.as_ref().unwrap().word_count(), 4);
+        assert_eq!(shallow.x.unwrap().word_count(), 4);
+        assert_eq!(shallow.y, None);
+        let deep = parse(&["--x", "-1.74", "--y", "0.028", "--step", "1e-40"])
+            .unwrap()
+            .position;
+        assert_eq!(deep.x.as_ref().unwrap().word_count(), 8);
+        assert_eq!(
+            deep.x.unwrap(),
+            WideFloat::from_decimal_str("-1.74", 8).unwrap()
+        );
+        // Steps beyond the largest numbers can't be shown
+        for step in ["0", "-1e-3", "1e-200", "tiny"] {
+            assert_eq!(
+                parse(&["--step", step]),
+                Err(ArgsError::InvalidValue("--step", step.to_owned()))
+            );
+        }
+        assert_eq!(
+            parse(&["--y", "0,5"]),
+            Err(ArgsError::InvalidValue("--y", "0,5".to_owned()))
+        );
+    }
+
+    #[test]
+    fn position_overrides_parts_of_the_view() {
+        let view = ViewState::default(Dimensions::new_nonzero(400, 200), 2.0, Precision::Auto);
+        assert_eq!(Args::default().position.location(&view), None);
+
+        let position = parse(&["--x", "-0.75", "--step", "1e-9"]).unwrap().position;
+        let location = position.location(&view).unwrap();
+        assert_eq!(location.re.as_f64_round(), -0.75);
+        assert_eq!(
+            location.im.as_f64_round(),
+            view.location().im.as_f64_round()
+        );
+        // The span covers the logical shortest side
+        let span = location.span.clone().unwrap().as_f64_round();
+        assert!((span / 1e-7 - 1.0).abs() < 1e-9);
+
+        let mut jumped = view.clone();
+        jumped.jump_to(&location).unwrap();
+        // The step keeps the bits of the automatic precision
+        assert!((jumped.coords().step.as_f64_round() / 1e-9 - 1.0).abs() < 1e-4);
+        assert_eq!(jumped.location().re.as_f64_round(), -0.75);
+    }
+
+    #[test]
+    fn view_and_window_options_are_parsed() {
+        let args = parse(&[
+            "--max-depth",
+            "5000",
+            "--precision",
+            "96",
+            "--width",
+            "1920",
+            "--height",
+            "1080",
+            "--fullscreen",
+            "--fps",
+            "60",
+        ])
+        .unwrap();
+        assert_eq!(args.max_depth, Some(5000));
+        assert_eq!(args.precision, Some(Precision::Manual(96)));
+        assert_eq!(args.window_size, Some((1920, 1080)));
+        assert!(args.fullscreen);
+        assert_eq!(args.fps, Some(60.0));
+        assert_eq!(
+            parse(&["--precision", "auto"]).unwrap().precision,
+            Some(Precision::Auto)
+        );
+
+        for (option, value) in [
+            ("--max-depth", "0"),
+            ("--precision", "0"),
+            ("--precision", "129"),
+            ("--width", "0"),
+            ("--fps", "1000"),
+            ("--fps", "fast"),
+        ] {
+            assert_eq!(
+                parse(&[option, value]),
+                Err(ArgsError::InvalidValue(option, value.to_owned()))
+            );
+        }
+        assert_eq!(
+            parse(&["--width", "800"]),
+            Err(ArgsError::MissingValue("--height"))
+        );
+        assert_eq!(
+            parse(&["--height", "600"]),
+            Err(ArgsError::MissingValue("--width"))
+        );
+    }
+
+    #[test]
+    fn query_parameters_are_options() {
+        assert_eq!(Args::from_query(""), Ok(Args::default()));
+        assert_eq!(Args::from_query("?"), Ok(Args::default()));
+        assert_eq!(
+            Args::from_query("?x=-0.75&y=0.1&step=1e-9&max-depth=5000&fresh&fps=60"),
+            parse(&[
+                "--x",
+                "-0.75",
+                "--y",
+                "0.1",
+                "--step",
+                "1e-9",
+                "--max-depth",
+                "5000",
+                "--fresh",
+                "--fps",
+                "60"
+            ])
+        );
+        assert_eq!(
+            Args::from_query("precision=7&&width=5"),
+            Err(ArgsError::MissingValue("--height"))
+        );
+        assert_eq!(
+            Args::from_query("zoom=5"),
+            Err(ArgsError::Unknown("--zoom".to_owned()))
+        );
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        assert_eq!(
+            parse(&["--gpu-trace"]),
+            Err(ArgsError::MissingValue("--gpu-trace"))
+        );
+        assert_eq!(
+            parse(&["--lang", "xx"]),
+            Err(ArgsError::InvalidValue("--lang", "xx".to_owned()))
+        );
+        assert_eq!(
+            parse(&["--history", "-1"]),
+            Err(ArgsError::InvalidValue("--history", "-1".to_owned()))
+        );
+        assert_eq!(
+            parse(&["--fresh", "--verbose"]),
+            Err(ArgsError::Unknown("--verbose".to_owned()))
+        );
+    }
+}
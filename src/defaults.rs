@@ -1,2 +1,17 @@
 pub const MAX_DEPTH: u32 = u32::MAX;
-pub const PRECISION_BITS: usize = 10;
+/// Frame rate the iterations are balanced for, within [`crate::fps_balancer::TARGET_FPS_RANGE`]
+pub const TARGET_FPS: f64 = 30.0;
+/// Environment variable that enables deterministic mode with the given amount of iterations per frame
+pub const DETERMINISTIC_ENV: &str = "MANDELBROT_DETERMINISTIC";
+/// GPU work is considered stalled once it takes this many times longer than the target frame time
+pub const WATCHDOG_FRAME_MULTIPLIER: f64 = 10.0;
+/// Lower bound of the stalled GPU work timeout in milliseconds
+pub const WATCHDOG_MIN_TIMEOUT_MS: f64 = 2000.0;
+/// Longest time in milliseconds a single compute submission is expected to take, frames that take
+/// longer are split into bands of rows. Well below the two seconds after which Windows resets the
+/// GPU
+pub const BAND_BUDGET_MS: f64 = 500.0;
+/// Iteration limit of the Julia set preview
+pub const JULIA_PREVIEW_DEPTH: u32 = 200;
+/// Longest time between the presses of a double click in milliseconds
+pub const DOUBLE_CLICK_MS: f64 = 400.0;
@@ -0,0 +1,178 @@
+//! Unattended mode that cycles through a playlist of locations, e.g. for an ambient display.
+//! Playlists are lines of `<dwell seconds> <location>`, with locations in any single line format
+//! pasting understands. Empty lines and lines starting with `#` are skipped
+
+use thiserror::Error;
+
+use crate::location::{self, Location};
+use crate::timer::{Clock, SystemClock};
+
+/// Playlist shown when none is given
+const SHOWCASE: &str = "\
+# Whole set
+10 mandelbrot re=-0.75 im=0 span=3
+# Seahorse valley
+30 mandelbrot re=-0.7453 im=0.1127 span=0.00065
+# Spiral off the top bulb
+30 mandelbrot re=-0.16 im=-1.0405 span=0.026
+# Elephant valley
+30 mandelbrot re=0.2925 im=0.0149 span=0.003
+# Deep zoom to a minibrot
+60 mandelbrot re=-1.25066 im=-0.02012 span=0.00017
+60 mandelbrot re=0.001643721971153 im=0.822467633298876 span=0.00000000001
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub location: Location,
+    /// Time to let the location refine in milliseconds
+    pub dwell_ms: f64,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PlaylistError {
+    #[error("Playlist is empty")]
+    Empty,
+    #[error("Playlist line {0} has an invalid dwell time")]
+    Dwell(usize),
+    #[error("Playlist line {0} has an unrecognized location")]
+    Location(usize),
+}
+
+/// Parses a playlist
+pub fn parse(text: &str) -> Result<Vec<Entry>, PlaylistError> {
+    let mut entries = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let number = index + 1;
+        let (dwell, location) = line.split_once(' ').ok_or(PlaylistError::Dwell(number))?;
+        let dwell_s: f64 = dwell.parse().map_err(|_| PlaylistError::Dwell(number))?;
+        if !(dwell_s.is_finite() && dwell_s > 0.0) {
+            return Err(PlaylistError::Dwell(number));
+        }
+        let (_, location) =
+            location::sniff(location).map_err(|_| PlaylistError::Location(number))?;
+        entries.push(Entry {
+            location,
+            dwell_ms: dwell_s * 1000.0,
+        });
+    }
+    if entries.is_empty() {
+        return Err(PlaylistError::Empty);
+    }
+    Ok(entries)
+}
+
+/// The built-in playlist
+pub fn showcase() -> Vec<Entry> {
+    parse(SHOWCASE).expect("Built-in playlist is valid")
+}
+
+/// Position in the playlist, looping forever
+pub struct Demo<C: Clock = SystemClock> {
+    playlist: Vec<Entry>,
+    /// Entry shown, none before the first one
+    current: Option<usize>,
+    shown_at_ms: f64,
+    clock: C,
+}
+
+impl Demo {
+    pub fn new(playlist: Vec<Entry>) -> Self {
+        Self::with_clock(playlist, SystemClock::default())
+    }
+}
+
+impl<C: Clock> Demo<C> {
+    pub fn with_clock(playlist: Vec<Entry>, clock: C) -> Self {
+        assert!(!playlist.is_empty(), "Playlist can't be empty");
+        Self {
+            playlist,
+            current: None,
+            shown_at_ms: 0.0,
+            clock,
+        }
+    }
+
+    /// Returns the location to show next once the current one has been shown long enough
+    pub fn advance(&mut self) -> Option<&Location> {
+        let now = self.clock.now();
+        let next = match self.current {
+            None => 0,
+            Some(current) if now - self.shown_at_ms >= self.playlist[current].dwell_ms => {
+                (current + 1) % self.playlist.len()
+            }
+            Some(_) => return None,
+        };
+        self.current = Some(next);
+        self.shown_at_ms = now;
+        Some(&self.playlist[next].location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Clock that only moves when told to
+    #[derive(Clone, Default)]
+    struct FakeClock(Rc<Cell<f64>>);
+
+    impl FakeClock {
+        fn advance(&self, ms: f64) {
+            self.0.set(self.0.get() + ms);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> f64 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn showcase_is_valid() {
+        assert!(showcase().len() > 1);
+    }
+
+    #[test]
+    fn playlists_are_parsed() {
+        let playlist = parse("# comment\n\n 2.5 mandelbrot re=1 im=2\n1 -0.5, 0.25\n").unwrap();
+        assert_eq!(playlist.len(), 2);
+        assert_eq!(playlist[0].dwell_ms, 2500.0);
+        assert_eq!(
+            playlist[1].location,
+            location::sniff("-0.5, 0.25").unwrap().1
+        );
+
+        assert_eq!(parse("# nothing\n"), Err(PlaylistError::Empty));
+        assert_eq!(parse("mandelbrot re=1 im=2"), Err(PlaylistError::Dwell(1)));
+        assert_eq!(parse("\n-1 0, 0"), Err(PlaylistError::Dwell(2)));
+        assert_eq!(parse("5 somewhere"), Err(PlaylistError::Location(1)));
+    }
+
+    #[test]
+    fn playlist_loops() {
+        let clock = FakeClock::default();
+        let playlist = parse("1 0, 0\n2 1, 1\n").unwrap();
+        let first = playlist[0].location.clone();
+        let second = playlist[1].location.clone();
+        let mut demo = Demo::with_clock(playlist, clock.clone());
+
+        assert_eq!(demo.advance(), Some(&first));
+        assert_eq!(demo.advance(), None);
+        clock.advance(999.0);
+        assert_eq!(demo.advance(), None);
+        clock.advance(1.0);
+        assert_eq!(demo.advance(), Some(&second));
+        clock.advance(1999.0);
+        assert_eq!(demo.advance(), None);
+        clock.advance(1.0);
+        assert_eq!(demo.advance(), Some(&first));
+    }
+}
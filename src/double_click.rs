@@ -0,0 +1,79 @@
+//! Detection of double clicks, which winit doesn't report
+
+use crate::defaults;
+use crate::primitives::Point;
+
+/// Furthest distance between the presses of a double click in physical pixels
+const MAX_DISTANCE: f32 = 5.0;
+
+#[derive(Debug, Clone)]
+pub struct DoubleClick {
+    /// Longest time between the presses in milliseconds
+    interval_ms: f64,
+    /// Time and position of the last single press
+    last: Option<(f64, Point)>,
+}
+
+impl Default for DoubleClick {
+    fn default() -> Self {
+        Self::new(defaults::DOUBLE_CLICK_MS)
+    }
+}
+
+impl DoubleClick {
+    pub fn new(interval_ms: f64) -> Self {
+        Self {
+            interval_ms,
+            last: None,
+        }
+    }
+
+    /// Handles a press at `position`. Returns true if it completes a double click, a third press
+    /// starts over
+    pub fn press(&mut self, now_ms: f64, position: Point) -> bool {
+        let double = self.last.take().is_some_and(|(last_ms, last)| {
+            now_ms - last_ms <= self.interval_ms
+                && (position.x - last.x).hypot(position.y - last.y) <= MAX_DISTANCE
+        });
+        if !double {
+            self.last = Some((now_ms, position));
+        }
+        double
+    }
+
+    /// Forgets the last press, so that the next one can't complete a double click
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AT: Point = Point { x: 100.0, y: 50.0 };
+
+    #[test]
+    fn quick_presses_in_place_are_double_clicks() {
+        let mut clicks = DoubleClick::new(400.0);
+        assert!(!clicks.press(0.0, AT));
+        assert!(clicks.press(300.0, Point { x: 103.0, y: 54.0 }));
+        // The third press is the first one of the next double click
+        assert!(!clicks.press(400.0, AT));
+        assert!(clicks.press(500.0, AT));
+    }
+
+    #[test]
+    fn slow_or_distant_presses_are_single_clicks() {
+        let mut clicks = DoubleClick::new(400.0);
+        assert!(!clicks.press(0.0, AT));
+        assert!(!clicks.press(401.0, AT));
+        assert!(!clicks.press(500.0, Point { x: 106.0, y: 50.0 }));
+        // The distant press still counts as the first one
+        assert!(clicks.press(600.0, Point { x: 106.0, y: 50.0 }));
+
+        assert!(!clicks.press(700.0, AT));
+        clicks.reset();
+        assert!(!clicks.press(750.0, AT));
+    }
+}
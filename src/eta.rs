@@ -0,0 +1,197 @@
+//! Time left until the calculation reaches the depth limit, estimated from the recent depth rate.
+//! Per-pass cost drops as pixels escape, and the balancer turns that into more iterations per
+//! frame, so only the recent rate is relevant
+
+use crate::i18n::Language;
+use crate::timer::{Clock, SystemClock};
+
+/// Depth progress older than this is ignored, in milliseconds
+const WINDOW_MS: f64 = 10_000.0;
+/// Passes needed before the rate is trusted
+const MIN_SAMPLES: usize = 4;
+/// Time the samples have to span before the rate is trusted, in milliseconds
+const MIN_SPAN_MS: f64 = 1_000.0;
+/// Halves of the window with rates further apart than this make the estimate too uncertain
+const MAX_RATE_RATIO: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Estimate {
+    /// Too early or too erratic to tell
+    #[default]
+    Unknown,
+    /// Seconds left
+    Remaining(f64),
+    /// Depth limit is reached
+    Done,
+}
+
+impl Estimate {
+    pub fn localized(&self, lang: Language) -> String {
+        match *self {
+            Estimate::Unknown => String::new(),
+            Estimate::Done => lang.tr("eta-done").to_owned(),
+            Estimate::Remaining(s) if s < 1.0 => lang.tr("eta-under-second").to_owned(),
+            Estimate::Remaining(s) => {
+                let s = s.round() as u64;
+                match (s / 3600, s / 60 % 60, s % 60) {
+                    (0, 0, s) => lang.format("eta-seconds", &[&s]),
+                    (0, m, s) => lang.format("eta-minutes", &[&m, &s]),
+                    (h, m, _) => lang.format("eta-hours", &[&h, &m]),
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localized(Language::English))
+    }
+}
+
+/// Estimates the time to reach `target` from `(time in milliseconds, depth)` samples, oldest
+/// first
+pub fn estimate(samples: &[(f64, u32)], target: u32) -> Estimate {
+    let Some(&(last_ms, last_depth)) = samples.last() else {
+        return Estimate::Unknown;
+    };
+    if last_depth >= target {
+        return Estimate::Done;
+    }
+
+    let recent: Vec<_> = samples
+        .iter()
+        .copied()
+        .filter(|(ms, _)| last_ms - ms <= WINDOW_MS)
+        .collect();
+    let (first_ms, _) = recent[0];
+    if recent.len() < MIN_SAMPLES || last_ms - first_ms < MIN_SPAN_MS {
+        return Estimate::Unknown;
+    }
+
+    let rate = |samples: &[(f64, u32)]| {
+        let (start_ms, start_depth) = samples[0];
+        let (end_ms, end_depth) = samples[samples.len() - 1];
+        (end_depth - start_depth) as f64 / (end_ms - start_ms)
+    };
+    let middle = recent.len() / 2;
+    let (older, newer) = (rate(&recent[..=middle]), rate(&recent[middle..]));
+    if older <= 0.0
+        || newer <= 0.0
+        || newer / older > MAX_RATE_RATIO
+        || older / newer > MAX_RATE_RATIO
+    {
+        return Estimate::Unknown;
+    }
+
+    let remaining = (target - last_depth) as f64;
+    Estimate::Remaining(remaining / newer / 1000.0)
+}
+
+/// Depth samples of the calculation in progress
+#[derive(Default)]
+pub struct Tracker {
+    clock: SystemClock,
+    /// View the samples belong to
+    view: usize,
+    samples: Vec<(f64, u32)>,
+}
+
+impl Tracker {
+    /// Records the current depth of a view. Samples are discarded when the calculation starts
+    /// over or another view is tracked
+    pub fn record(&mut self, view: usize, depth: u32) {
+        let now = self.clock.now();
+        if view != self.view || self.samples.last().is_some_and(|&(_, last)| depth < last) {
+            self.view = view;
+            self.samples.clear();
+        }
+        if self.samples.last().map(|&(_, last)| last) != Some(depth) {
+            self.samples.push((now, depth));
+        }
+        self.samples.retain(|&(ms, _)| now - ms <= WINDOW_MS);
+    }
+
+    pub fn estimate(&self, target: u32) -> Estimate {
+        estimate(&self.samples, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Samples of one pass per `frame_ms` with depths given by `depth(pass)`
+    fn curve(passes: u32, frame_ms: f64, depth: impl Fn(u32) -> u32) -> Vec<(f64, u32)> {
+        (0..passes)
+            .map(|p| (p as f64 * frame_ms, depth(p)))
+            .collect()
+    }
+
+    fn seconds(estimate: Estimate) -> f64 {
+        match estimate {
+            Estimate::Remaining(s) => s,
+            e => panic!("Expected an estimate, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn constant_rate() {
+        // 1000 iterations per second
+        let samples = curve(60, 100.0, |p| p * 100);
+        let left = seconds(estimate(&samples, 5900 + 30_000));
+        assert!((left - 30.0).abs() < 0.01, "{}", left);
+    }
+
+    #[test]
+    fn speedup_as_pixels_escape() {
+        // Every pass is cheaper than the previous one, the balancer does more iterations per frame
+        let samples = curve(200, 100.0, |p| p * p);
+        let (last_ms, last_depth) = *samples.last().unwrap();
+        let target = last_depth + 100_000;
+        let left = seconds(estimate(&samples, target));
+
+        let average = last_depth as f64 / last_ms * 1000.0;
+        let current = (2 * 199 - 1) as f64 * 10.0;
+        assert!(left < 100_000.0 / average);
+        assert!((left - 100_000.0 / current).abs() / left < 0.3, "{}", left);
+    }
+
+    #[test]
+    fn first_passes_are_unknown() {
+        assert_eq!(estimate(&[], 1000), Estimate::Unknown);
+        let samples = curve(3, 500.0, |p| p * 10);
+        assert_eq!(estimate(&samples, 1000), Estimate::Unknown);
+        // Enough passes, but too quick to tell
+        let samples = curve(10, 10.0, |p| p * 10);
+        assert_eq!(estimate(&samples, 1000), Estimate::Unknown);
+    }
+
+    #[test]
+    fn erratic_progress_is_unknown() {
+        let samples = curve(20, 100.0, |p| if p < 10 { p } else { 10 + (p - 10) * 100 });
+        assert_eq!(estimate(&samples, 100_000), Estimate::Unknown);
+    }
+
+    #[test]
+    fn done_at_the_limit() {
+        let samples = curve(2, 100.0, |p| p * 100);
+        assert_eq!(estimate(&samples, 100), Estimate::Done);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Estimate::Unknown.to_string(), "");
+        assert_eq!(Estimate::Done.to_string(), "done");
+        assert_eq!(Estimate::Remaining(0.3).to_string(), "<1 s remaining");
+        assert_eq!(Estimate::Remaining(42.4).to_string(), "~42 s remaining");
+        assert_eq!(
+            Estimate::Remaining(160.0).to_string(),
+            "~2 m 40 s remaining"
+        );
+        assert_eq!(
+            Estimate::Remaining(3.0 * 3600.0 + 5.0 * 60.0).to_string(),
+            "~3 h 5 m remaining"
+        );
+    }
+}
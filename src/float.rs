@@ -1,10 +1,77 @@
-use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, ShlAssign, ShrAssign, Sub, SubAssign};
+use smallvec::{smallvec, SmallVec};
+use std::fmt;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Shl, ShlAssign, Shr, ShrAssign, Sub,
+    SubAssign,
+};
+use thiserror::Error;
 
 const WORD_WIDTH: usize = 32;
 
+/// Fraction digits past this limit are below any supported precision and are ignored
+const MAX_FRACTION_DIGITS: usize = 2000;
+
+/// Word count of the numbers kept inline, longer ones are stored on the heap
+const INLINE_WORDS: usize = 8;
+
+type Words = SmallVec<[u32; INLINE_WORDS]>;
+/// Exact products of numbers with up to `INLINE_WORDS` words
+type ProductWords = SmallVec<[u32; 2 * INLINE_WORDS]>;
+
 /// Wide float specialized for use in Mandelbrot calculations.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct WideFloat(Vec<u32>);
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SerializedWideFloat", into = "SerializedWideFloat")
+)]
+pub struct WideFloat(Words);
+
+/// Version of the serialized layout, bumped whenever the meaning of the words changes
+#[cfg(feature = "serde")]
+const SERIALIZED_VERSION: u32 = 1;
+
+/// Raw words, least significant first, tagged with the layout version
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedWideFloat {
+    version: u32,
+    words: Vec<u32>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum DeserializeError {
+    #[error("Unsupported number version {0}")]
+    Version(u32),
+    #[error("Number has no words")]
+    Empty,
+}
+
+#[cfg(feature = "serde")]
+impl From<WideFloat> for SerializedWideFloat {
+    fn from(value: WideFloat) -> Self {
+        Self {
+            version: SERIALIZED_VERSION,
+            words: value.0.into_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedWideFloat> for WideFloat {
+    type Error = DeserializeError;
+
+    fn try_from(value: SerializedWideFloat) -> Result<Self, Self::Error> {
+        if value.version != SERIALIZED_VERSION {
+            return Err(DeserializeError::Version(value.version));
+        }
+        if value.words.is_empty() {
+            return Err(DeserializeError::Empty);
+        }
+        Ok(Self::from_words(value.words))
+    }
+}
 
 fn isolate_mantissa(f: f32) -> u32 {
     f.to_bits() & 0x7f_ffff
@@ -20,9 +87,22 @@ pub enum FromFloatError {
     OutOfRange,
 }
 
+/// Decimal that can't be parsed, with the byte position of the problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum ParseDecimalError {
+    #[error("Number has no digits")]
+    NoDigits,
+    #[error("Unexpected character at {0}")]
+    InvalidCharacter(usize),
+    #[error("Invalid exponent at {0}")]
+    InvalidExponent(usize),
+    #[error("Whole part at {0} is too large")]
+    OutOfRange(usize),
+}
+
 impl WideFloat {
     pub fn zero(size: usize) -> Self {
-        Self(vec![0; size])
+        Self(smallvec![0; size])
     }
 
     /// Returns minimal positive non-zero value with given number size and precision
@@ -32,18 +112,21 @@ impl WideFloat {
     pub fn min_positive(size: usize, precision: usize) -> Self {
         let idx = precision / 32;
         let v = 1 << (precision % 32);
-        let mut buffer = vec![0; size];
+        let mut buffer = smallvec![0; size];
         buffer[idx] = v;
         Self(buffer)
     }
 
     pub fn from_i32(value: i32, size: usize) -> Self {
-        let mut buffer = vec![0; size];
+        let mut buffer = smallvec![0; size];
         buffer[size - 1] = u32::from_ne_bytes(i32::to_ne_bytes(value));
         Self(buffer)
     }
 
     pub fn from_f32(value: f32, size: usize) -> Result<Self, FromFloatError> {
+        if value.is_nan() {
+            return Err(FromFloatError::IsNan);
+        }
         let (neg, value) = if value < 0.0 {
             (true, -value)
         } else {
@@ -58,6 +141,12 @@ impl WideFloat {
             | 1 << (WORD_WIDTH - 1);
 
         let shift = 0x7e_i32 - e as i32 + WORD_WIDTH as i32;
+        // Value doesn't fit into the whole part, also covers infinities. The only value of the
+        // exponent that fills the top word is the whole part minimum of -2^31
+        let is_min = neg && shift == 0 && isolate_mantissa(value) == 0;
+        if shift < 0 || shift == 0 && !is_min {
+            return Err(FromFloatError::OutOfRange);
+        }
         let offset = shift as usize / WORD_WIDTH;
 
         let left = v >> (shift % WORD_WIDTH as i32);
@@ -67,7 +156,7 @@ impl WideFloat {
             0
         };
 
-        let mut buffer = vec![0; size];
+        let mut buffer = smallvec![0; size];
 
         if let Some(v) = buffer.get_mut(offset) {
             *v = left;
@@ -76,8 +165,6 @@ impl WideFloat {
             *v = right;
         }
 
-        // TODO: return error on unsupported values
-
         buffer.reverse();
         if neg {
             Ok(-Self(buffer))
@@ -86,7 +173,179 @@ impl WideFloat {
         }
     }
 
-    // TODO: bring back f64 conversions
+    /// Parses a decimal like `-1.25`, `.5` or `3e-20` into a number of `size` words, rounded to the
+    /// nearest representable value
+    pub fn from_decimal_str(text: &str, size: usize) -> Result<Self, ParseDecimalError> {
+        let start = text.len() - text.trim_start().len();
+        let text = text.trim();
+        let (neg, sign_len) = match text.as_bytes().first() {
+            Some(b'-') => (true, 1),
+            Some(b'+') => (false, 1),
+            _ => (false, 0),
+        };
+        let start = start + sign_len;
+        let text = &text[sign_len..];
+
+        let (mantissa, exponent, exponent_pos) = match text.find(['e', 'E']) {
+            Some(idx) => {
+                let exponent = text[idx + 1..]
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|e| e.unsigned_abs() as usize <= MAX_FRACTION_DIGITS)
+                    .ok_or(ParseDecimalError::InvalidExponent(start + idx))?;
+                (&text[..idx], exponent, start + idx)
+            }
+            None => (text, 0, start),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let fraction_start = start + whole.len() + 1;
+        let check_digits =
+            |digits: &str, start: usize| match digits.bytes().position(|b| !b.is_ascii_digit()) {
+                Some(idx) => Err(ParseDecimalError::InvalidCharacter(start + idx)),
+                None => Ok(()),
+            };
+        check_digits(whole, start)?;
+        check_digits(fraction, fraction_start)?;
+        if whole.len() + fraction.len() == 0 {
+            return Err(ParseDecimalError::NoDigits);
+        }
+
+        // Split the digits at the decimal point moved by the exponent
+        let digits = || {
+            whole
+                .bytes()
+                .chain(fraction.bytes())
+                .map(|b| (b - b'0') as u32)
+        };
+        let digit_pos = |idx: usize| match idx.checked_sub(whole.len()) {
+            Some(idx) => fraction_start + idx,
+            None => start + idx,
+        };
+        let point = whole.len() as i64 + exponent as i64;
+
+        let shift_digit = |whole: u32, digit: u32| {
+            whole
+                .checked_mul(10)
+                .and_then(|whole| whole.checked_add(digit))
+                .filter(|whole| *whole <= i32::MAX as u32)
+        };
+        let mut whole_part = 0u32;
+        for (idx, digit) in digits().enumerate().take(point.max(0) as usize) {
+            whole_part = shift_digit(whole_part, digit)
+                .ok_or(ParseDecimalError::OutOfRange(digit_pos(idx)))?;
+        }
+        if point > (whole.len() + fraction.len()) as i64 {
+            for _ in 0..point - (whole.len() + fraction.len()) as i64 {
+                whole_part = shift_digit(whole_part, 0)
+                    .ok_or(ParseDecimalError::OutOfRange(exponent_pos))?;
+            }
+        }
+
+        let leading_zeros = (-point).max(0) as usize;
+        // The fraction is calculated with an extra word for rounding
+        let mut fraction = vec![0u32; size];
+        if leading_zeros < MAX_FRACTION_DIGITS {
+            let fraction_digits: Vec<_> = digits()
+                .skip(point.max(0) as usize)
+                .take(MAX_FRACTION_DIGITS - leading_zeros)
+                .collect();
+            // Horner's scheme from the least significant digit: fraction = (fraction + digit) / 10
+            for digit in fraction_digits
+                .into_iter()
+                .rev()
+                .chain(std::iter::repeat_n(0, leading_zeros))
+            {
+                let mut remainder = digit as u64;
+                for word in fraction.iter_mut().rev() {
+                    let current = remainder << 32 | *word as u64;
+                    *word = (current / 10) as u32;
+                    remainder = current % 10;
+                }
+            }
+        }
+
+        let round_up = fraction.remove(0) >= 1 << 31;
+        let mut words = fraction;
+        words.push(whole_part);
+        if round_up {
+            for word in words.iter_mut() {
+                let carry;
+                (*word, carry) = word.overflowing_add(1);
+                if !carry {
+                    break;
+                }
+            }
+        }
+        // Rounded up past the largest whole part
+        if words[size - 1] > i32::MAX as u32 {
+            return Err(ParseDecimalError::OutOfRange(start));
+        }
+
+        let value = WideFloat(words.into());
+        Ok(if neg { -value } else { value })
+    }
+
+    /// Converts the float, truncating bits below the least significant word towards zero
+    pub fn from_f64(value: f64, size: usize) -> Result<Self, FromFloatError> {
+        if value.is_nan() {
+            return Err(FromFloatError::IsNan);
+        }
+        // Value doesn't fit into the whole part, also covers infinities
+        let limit = (1u64 << (WORD_WIDTH - 1)) as f64;
+        if !(-limit..limit).contains(&value) {
+            return Err(FromFloatError::OutOfRange);
+        }
+        let bits = value.abs().to_bits();
+        let exponent = (bits >> (f64::MANTISSA_DIGITS - 1)) as i32;
+        let mantissa = bits & ((1 << (f64::MANTISSA_DIGITS - 1)) - 1);
+        // Subnormal numbers have no implicit bit and the exponent of the smallest normal one
+        let (mantissa, exponent) = if exponent == 0 {
+            (mantissa, 1)
+        } else {
+            (mantissa | 1 << (f64::MANTISSA_DIGITS - 1), exponent)
+        };
+
+        // Position of the least significant mantissa bit in the words
+        let shift =
+            exponent - 0x3ff - (f64::MANTISSA_DIGITS as i32 - 1) + ((size - 1) * WORD_WIDTH) as i32;
+        let (mantissa, shift) = match usize::try_from(shift) {
+            Ok(shift) => (mantissa, shift),
+            Err(_) => (mantissa.checked_shr(shift.unsigned_abs()).unwrap_or(0), 0),
+        };
+
+        let offset = shift / WORD_WIDTH;
+        let wide = (mantissa as u128) << (shift % WORD_WIDTH);
+        let mut buffer = smallvec![0; size];
+        for (idx, word) in buffer.iter_mut().skip(offset).enumerate().take(3) {
+            *word = (wide >> (idx * WORD_WIDTH)) as u32;
+        }
+
+        if value < 0.0 {
+            Ok(-Self(buffer))
+        } else {
+            Ok(Self(buffer))
+        }
+    }
+
+    /// Converts the number to the nearest float, bits below the top 96 are ignored
+    pub fn as_f64_round(&self) -> f64 {
+        let neg = self < &0;
+        // Unsigned, which also covers the negated minimum of the whole part
+        let magnitude = if neg { -self.clone() } else { self.clone() };
+        let Some(top) = magnitude.0.iter().rposition(|w| *w != 0) else {
+            return 0.0;
+        };
+        let word = |idx: usize| idx.checked_sub(2).map_or(0, |i| magnitude.0[i]) as u128;
+        let window = word(top + 2) << (2 * WORD_WIDTH) | word(top + 1) << WORD_WIDTH | word(top);
+        let exponent = (top as i32 - 2 - (self.0.len() as i32 - 1)) * WORD_WIDTH as i32;
+        let f = window as f64 * 2f64.powi(exponent);
+        if neg {
+            -f
+        } else {
+            f
+        }
+    }
+
     pub fn as_f32_round(&self) -> f32 {
         if self.0.iter().all(|w| *w == 0) {
             return 0.0;
@@ -127,6 +386,13 @@ impl WideFloat {
         }
     }
 
+    /// Converts the number using only the whole part and the most significant fraction word. Less
+    /// precise than [`Self::as_f32_round`], but works for numbers of any magnitude
+    pub fn as_f32_approx(&self) -> f32 {
+        let fraction = self.0.len().checked_sub(2).map_or(0, |i| self.0[i]);
+        self.floor() as f32 + fraction as f32 / (1u64 << WORD_WIDTH) as f32
+    }
+
     pub fn floor(&self) -> i32 {
         i32::from_ne_bytes(self.0.last().unwrap().to_ne_bytes())
     }
@@ -139,35 +405,193 @@ impl WideFloat {
         self.0.len()
     }
 
+    /// Returns the position of the most significant bit of the magnitude relative to the binary
+    /// point, e.g. 0 for 1.5 and -2 for -0.25. Returns `None` for zero
+    pub fn ilog2(&self) -> Option<i32> {
+        let magnitude = self.magnitude();
+        let top = magnitude.0.iter().rposition(|w| *w != 0)?;
+        let bit = WORD_WIDTH as i32 - 1 - magnitude.0[top].leading_zeros() as i32;
+        Some((top as i32 - (self.0.len() as i32 - 1)) * WORD_WIDTH as i32 + bit)
+    }
+
+    /// Returns the approximate decimal logarithm of the magnitude. Unlike a conversion to a float,
+    /// it works down to the smallest number. Returns `None` for zero
+    pub fn magnitude_log10(&self) -> Option<f64> {
+        let exponent = self.ilog2()?;
+        // The magnitude scaled to 1..2 is exact enough for a float
+        let mut scaled = self.magnitude();
+        if exponent > 0 {
+            scaled.logical_shr(exponent as usize);
+        } else {
+            scaled <<= -exponent as usize;
+        }
+        Some((exponent as f64 + scaled.as_f64_round().log2()) * std::f64::consts::LOG10_2)
+    }
+
     /// Returns the amount of words that need to be trimmed/added for the number to accomodate at least `extra_bits`
     /// bits after the first non-zero bit
     pub fn precision_diff(&self, extra_bits: usize) -> isize {
+        // Leading words of negative numbers are all ones, so only the magnitude can be counted. Its
+        // words are counted as unsigned, the negated minimum of the whole part stays negative
+        let magnitude = self.magnitude();
         let extra_words = (extra_bits / WORD_WIDTH) + 1;
         let ls_word_threshold = (extra_bits as u32 % WORD_WIDTH as u32)
             .checked_sub(1)
             .map(|shift| 1 << shift)
             .unwrap_or(0);
-        let words = self.0.iter().rev().skip_while(|w| **w == 0).count();
+        let words = magnitude.0.iter().rev().skip_while(|w| **w == 0).count();
         let word_diff = extra_words as isize - words as isize;
-        let ls_word = self.0.iter().rfind(|w| **w != 0).copied().unwrap_or(0);
+        let ls_word = magnitude.0.iter().rfind(|w| **w != 0).copied().unwrap_or(0);
         word_diff + (ls_word <= ls_word_threshold) as isize
     }
 
-    /// Changes the word count of this number by `word_diff`
+    /// Changes the word count of this number by `word_diff`. Trimmed words are truncated towards
+    /// zero, for negative numbers as well
     pub fn change_precision(&mut self, word_diff: isize) {
         if word_diff > 0 {
             for _ in 0..word_diff {
                 self.0.insert(0, 0);
             }
-        } else {
+        } else if word_diff < 0 {
+            // Trimming the words of negative numbers would round them down, so their magnitude is
+            // trimmed as unsigned words instead
+            let neg = self.floor() < 0;
+            let mut magnitude = self.magnitude();
             for _ in 0..-word_diff {
-                self.0.remove(0);
+                magnitude.0.remove(0);
             }
+            *self = if neg { -magnitude } else { magnitude };
         }
     }
 
+    /// Creates a number from raw words, least significant first. The last word holds the signed
+    /// whole part
+    pub fn from_words(words: Vec<u32>) -> Self {
+        Self(words.into())
+    }
+
+    /// Returns raw words of the number, least significant first
+    pub fn words(&self) -> &[u32] {
+        &self.0
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
-        bytemuck::cast_slice(&self.0)
+        bytemuck::cast_slice(&self.0[..])
+    }
+
+    /// Divides the numbers, truncating towards zero. Returns `None` if `rhs` is zero. Quotients
+    /// that don't fit into the whole part wrap around, like products do
+    pub fn checked_div(&self, rhs: &Self) -> Option<Self> {
+        let len = self.0.len();
+        assert_eq!(len, rhs.0.len());
+        if rhs.0.iter().all(|w| *w == 0) {
+            return None;
+        }
+
+        let lneg = self.floor() < 0;
+        let rneg = rhs.floor() < 0;
+        let dividend = self.magnitude();
+        let divisor = rhs.magnitude();
+
+        // Restoring long division of the dividend shifted up by the fraction words. The remainder
+        // stays below twice the divisor, so one extra word holds it
+        let fraction_bits = (len - 1) * WORD_WIDTH;
+        let mut quotient = WideFloat::zero(len);
+        let mut remainder: ProductWords = smallvec![0; len + 1];
+        for bit in (0..len * WORD_WIDTH + fraction_bits).rev() {
+            let mut carry = bit.checked_sub(fraction_bits).map_or(0, |bit| {
+                dividend.0[bit / WORD_WIDTH] >> (bit % WORD_WIDTH) & 1
+            });
+            for word in remainder.iter_mut() {
+                (*word, carry) = (*word << 1 | carry, *word >> (WORD_WIDTH - 1));
+            }
+
+            let fits =
+                remainder[len] != 0 || remainder[..len].iter().rev().ge(divisor.0.iter().rev());
+            if fits {
+                let mut borrow = false;
+                for (idx, word) in remainder.iter_mut().enumerate() {
+                    let sub = divisor.0.get(idx).copied().unwrap_or(0);
+                    (*word, borrow) = word.borrowing_sub(sub, borrow);
+                }
+                if let Some(word) = quotient.0.get_mut(bit / WORD_WIDTH) {
+                    *word |= 1 << (bit % WORD_WIDTH);
+                }
+            }
+        }
+
+        if lneg ^ rneg {
+            quotient = -quotient;
+        }
+        Some(quotient)
+    }
+}
+
+/// Formats the number with enough digits to be parsed back exactly
+impl fmt::Display for WideFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let neg = self < &0;
+        let magnitude = if neg { -self.clone() } else { self.clone() };
+        let (whole, fraction) = magnitude.0.split_last().expect("Numbers aren't empty");
+
+        write!(f, "{}{}", if neg { "-" } else { "" }, whole)?;
+        let mut fraction = fraction.to_vec();
+        if fraction.iter().all(|w| *w == 0) {
+            return Ok(());
+        }
+
+        // Truncation error below a quarter of the last bit still rounds back to the same number
+        let bits = fraction.len() * WORD_WIDTH + 2;
+        let digits = (bits as f64 * std::f64::consts::LOG10_2).ceil() as usize;
+        let mut out = String::with_capacity(digits + 1);
+        out.push('.');
+        for _ in 0..digits {
+            // The digit is what overflows the fraction when it's multiplied by 10
+            let mut carry = 0u64;
+            for word in fraction.iter_mut() {
+                let current = *word as u64 * 10 + carry;
+                *word = current as u32;
+                carry = current >> 32;
+            }
+            out.push(char::from(b'0' + carry as u8));
+        }
+        f.write_str(out.trim_end_matches('0'))
+    }
+}
+
+impl WideFloat {
+    /// Writes the raw words, the whole part first and then the fraction words most significant
+    /// first, e.g. `fffffffe.80000000_00000000` for -1.5
+    fn fmt_words(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        prefix: &str,
+        word: impl Fn(u32) -> String,
+    ) -> fmt::Result {
+        let (whole, fraction) = self.0.split_last().expect("Numbers aren't empty");
+        if f.alternate() {
+            f.write_str(prefix)?;
+        }
+        f.write_str(&word(*whole))?;
+        for (idx, w) in fraction.iter().rev().enumerate() {
+            f.write_str(if idx == 0 { "." } else { "_" })?;
+            f.write_str(&word(*w))?;
+        }
+        Ok(())
+    }
+}
+
+/// Raw two's complement words in hex, the whole part first
+impl fmt::LowerHex for WideFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_words(f, "0x", |w| format!("{:08x}", w))
+    }
+}
+
+/// Raw two's complement words in binary, the whole part first
+impl fmt::Binary for WideFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_words(f, "0b", |w| format!("{:032b}", w))
     }
 }
 
@@ -188,16 +612,67 @@ impl PartialOrd<i32> for WideFloat {
     }
 }
 
+/// Numbers of this many words fit into a native integer, which is faster than word loops
+const NATIVE_WORDS: std::ops::RangeInclusive<usize> = 2..=3;
+
+fn to_native(words: &[u32]) -> u128 {
+    match *words {
+        [w0, w1] => w0 as u128 | (w1 as u128) << WORD_WIDTH,
+        [w0, w1, w2] => w0 as u128 | (w1 as u128) << WORD_WIDTH | (w2 as u128) << (2 * WORD_WIDTH),
+        _ => unreachable!("Only numbers of {:?} words are native", NATIVE_WORDS),
+    }
+}
+
+/// Writes the low words of `value`, dropping the rest
+fn write_native(words: &mut [u32], value: u128) {
+    match words {
+        [w0, w1] => (*w0, *w1) = (value as u32, (value >> WORD_WIDTH) as u32),
+        [w0, w1, w2] => {
+            (*w0, *w1, *w2) = (
+                value as u32,
+                (value >> WORD_WIDTH) as u32,
+                (value >> (2 * WORD_WIDTH)) as u32,
+            )
+        }
+        _ => unreachable!("Only numbers of {:?} words are native", NATIVE_WORDS),
+    }
+}
+
+fn add_words(left: &mut [u32], right: &[u32]) {
+    if NATIVE_WORDS.contains(&left.len()) {
+        write_native(left, to_native(left).wrapping_add(to_native(right)));
+    } else {
+        add_words_generic(left, right);
+    }
+}
+
+fn add_words_generic(left: &mut [u32], right: &[u32]) {
+    let mut carry = false;
+    for (lhs_part, rhs_part) in left.iter_mut().zip(right) {
+        (*lhs_part, carry) = lhs_part.carrying_add(*rhs_part, carry);
+    }
+}
+
+fn sub_words(left: &mut [u32], right: &[u32]) {
+    if NATIVE_WORDS.contains(&left.len()) {
+        write_native(left, to_native(left).wrapping_sub(to_native(right)));
+    } else {
+        sub_words_generic(left, right);
+    }
+}
+
+fn sub_words_generic(left: &mut [u32], right: &[u32]) {
+    let mut borrow = false;
+    for (lhs_part, rhs_part) in left.iter_mut().zip(right) {
+        (*lhs_part, borrow) = lhs_part.borrowing_sub(*rhs_part, borrow);
+    }
+}
+
 impl Add<&Self> for WideFloat {
     type Output = WideFloat;
 
     fn add(mut self, rhs: &Self) -> Self::Output {
-        assert_eq!(self.0.len(), rhs.0.len());
-
-        let mut carry = false;
-        for (lhs_part, rhs_part) in self.0.iter_mut().zip(&rhs.0) {
-            (*lhs_part, carry) = lhs_part.carrying_add(*rhs_part, carry);
-        }
+        self += rhs;
         self
     }
 }
@@ -205,11 +680,7 @@ impl Add<&Self> for WideFloat {
 impl AddAssign<&Self> for WideFloat {
     fn add_assign(&mut self, rhs: &Self) {
         assert_eq!(self.0.len(), rhs.0.len());
-
-        let mut carry = false;
-        for (lhs_part, rhs_part) in self.0.iter_mut().zip(&rhs.0) {
-            (*lhs_part, carry) = lhs_part.carrying_add(*rhs_part, carry);
-        }
+        add_words(&mut self.0, &rhs.0);
     }
 }
 
@@ -217,12 +688,7 @@ impl Sub<&Self> for WideFloat {
     type Output = Self;
 
     fn sub(mut self, rhs: &Self) -> Self {
-        assert_eq!(self.0.len(), rhs.0.len());
-
-        let mut borrow = false;
-        for (lhs_part, rhs_part) in self.0.iter_mut().zip(&rhs.0) {
-            (*lhs_part, borrow) = lhs_part.borrowing_sub(*rhs_part, borrow);
-        }
+        self -= rhs;
         self
     }
 }
@@ -230,12 +696,138 @@ impl Sub<&Self> for WideFloat {
 impl SubAssign<&Self> for WideFloat {
     fn sub_assign(&mut self, rhs: &Self) {
         assert_eq!(self.0.len(), rhs.0.len());
+        sub_words(&mut self.0, &rhs.0);
+    }
+}
+
+/// Exact product of unsigned numbers, least significant word first
+fn product(left: &[u32], right: &[u32]) -> ProductWords {
+    let mut out = smallvec![0; left.len() + right.len()];
+    for (l_idx, l_word) in left.iter().copied().enumerate() {
+        let mut carry = 0;
+        for (r_idx, r_word) in right.iter().copied().enumerate() {
+            let current = l_word as u64 * r_word as u64 + out[l_idx + r_idx] as u64 + carry;
+            out[l_idx + r_idx] = current as u32;
+            carry = current >> WORD_WIDTH;
+        }
+        out[l_idx + right.len()] = carry as u32;
+    }
+    out
+}
+
+/// Magnitude of a native number
+fn native_magnitude(words: &[u32]) -> u128 {
+    let value = to_native(words);
+    if (words[words.len() - 1] as i32) < 0 {
+        value.wrapping_neg() & ((1 << (words.len() * WORD_WIDTH)) - 1)
+    } else {
+        value
+    }
+}
+
+/// Product of native magnitudes of `len` words shifted down by the fraction words. Bits above
+/// `len` words are garbage
+fn native_product(left: u128, right: u128, len: usize) -> u128 {
+    if len == 2 {
+        return (left * right) >> WORD_WIDTH;
+    }
+    // Halves of 64 and 32 bits, so that every partial product fits
+    let (l_lo, l_hi) = (left as u64 as u128, left >> (2 * WORD_WIDTH));
+    let (r_lo, r_hi) = (right as u64 as u128, right >> (2 * WORD_WIDTH));
+    ((l_lo * r_lo) >> (2 * WORD_WIDTH))
+        .wrapping_add(l_lo * r_hi + l_hi * r_lo)
+        .wrapping_add((l_hi * r_hi) << (2 * WORD_WIDTH))
+}
+
+/// Exact square of an unsigned number, least significant word first. Products of distinct words
+/// come in pairs, so each one is calculated once and doubled
+fn square_product(num: &[u32]) -> ProductWords {
+    let len = num.len();
+    let mut out = smallvec![0; 2 * len];
+    for (i, ni) in num.iter().copied().enumerate() {
+        let mut carry = 0;
+        for (j, nj) in num.iter().copied().enumerate().skip(i + 1) {
+            let current = ni as u64 * nj as u64 + out[i + j] as u64 + carry;
+            out[i + j] = current as u32;
+            carry = current >> WORD_WIDTH;
+        }
+        out[i + len] = carry as u32;
+    }
+
+    let mut carry = 0;
+    for word in out.iter_mut() {
+        (*word, carry) = (*word << 1 | carry, *word >> (WORD_WIDTH - 1));
+    }
 
-        let mut borrow = false;
-        for (lhs_part, rhs_part) in self.0.iter_mut().zip(&rhs.0) {
-            (*lhs_part, borrow) = lhs_part.borrowing_sub(*rhs_part, borrow);
+    let mut carry = 0;
+    for (i, ni) in num.iter().copied().enumerate() {
+        let square = ni as u64 * ni as u64;
+        let lo = out[2 * i] as u64 + (square as u32) as u64 + carry;
+        out[2 * i] = lo as u32;
+        let hi = out[2 * i + 1] as u64 + (square >> WORD_WIDTH) + (lo >> WORD_WIDTH);
+        out[2 * i + 1] = hi as u32;
+        carry = hi >> WORD_WIDTH;
+    }
+    out
+}
+
+impl WideFloat {
+    /// Magnitude as unsigned words. The negated minimum of the whole part is its own negation, so
+    /// the result only reads as a signed number below it
+    fn magnitude(&self) -> Self {
+        if self.floor() < 0 {
+            -self.clone()
+        } else {
+            self.clone()
         }
     }
+
+    /// Number from the exact product of magnitudes, truncated towards zero. Products that don't
+    /// fit into the whole part wrap around
+    fn from_product(product: &[u32], len: usize, neg: bool) -> Self {
+        let result = WideFloat(Words::from_slice(&product[len - 1..2 * len - 1]));
+        if neg {
+            -result
+        } else {
+            result
+        }
+    }
+
+    /// Returns `self * self`, the same as multiplication but with about half the word products
+    pub fn square(&self) -> Self {
+        let len = self.0.len();
+        Self::from_product(&square_product(&self.magnitude().0), len, false)
+    }
+
+    /// Adds the numbers. Returns `None` if the sum doesn't fit into the whole part
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        let sum = self.clone() + rhs;
+        let lneg = self.floor() < 0;
+        (lneg != (rhs.floor() < 0) || lneg == (sum.floor() < 0)).then_some(sum)
+    }
+
+    /// Subtracts the numbers. Returns `None` if the difference doesn't fit into the whole part
+    pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        let difference = self.clone() - rhs;
+        let lneg = self.floor() < 0;
+        (lneg == (rhs.floor() < 0) || lneg == (difference.floor() < 0)).then_some(difference)
+    }
+
+    /// Multiplies the numbers, truncating towards zero. Returns `None` if the product doesn't fit
+    /// into the whole part
+    pub fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        let len = self.0.len();
+        assert_eq!(len, rhs.0.len());
+
+        let neg = (self.floor() < 0) ^ (rhs.floor() < 0);
+        let product = product(&self.magnitude().0, &rhs.magnitude().0);
+        // Only the whole part minimum has a magnitude with the top bit set
+        let (whole, fraction) = (product[2 * len - 2], &product[len - 1..2 * len - 2]);
+        let fits = product[2 * len - 1] == 0
+            && (whole <= i32::MAX as u32
+                || neg && whole == 1 << 31 && fraction.iter().all(|w| *w == 0));
+        fits.then(|| Self::from_product(&product, len, neg))
+    }
 }
 
 impl Mul for &WideFloat {
@@ -245,44 +837,18 @@ impl Mul for &WideFloat {
         let len = self.0.len();
         assert_eq!(len, rhs.0.len());
 
-        let lneg = self.floor() < 0;
-        let rneg = rhs.floor() < 0;
-        let mut result = WideFloat::zero(len);
-        let mut carry = true;
-        for (l_idx, l_word) in self
-            .0
-            .iter()
-            .copied()
-            .map(|w| {
-                if lneg {
-                    let neg_w;
-                    (neg_w, carry) = (!w).overflowing_add(carry as u32);
-                    neg_w
-                } else {
-                    w
-                }
-            })
-            .enumerate()
-        {
-            let mut part = rhs.clone();
-            if rneg {
-                part = -part
-            }
-            let mut carry = 0;
-            for r_word in part.0.iter_mut() {
-                (*r_word, carry) = l_word.carrying_mul(*r_word, carry);
-            }
-            let shift = len - l_idx - 1;
-            part >>= shift * WORD_WIDTH;
-            if carry != 0 {
-                part.0[l_idx + 1] = carry;
-            }
-            result += &part;
+        let neg = (self.floor() < 0) ^ (rhs.floor() < 0);
+        if NATIVE_WORDS.contains(&len) {
+            let product = native_product(native_magnitude(&self.0), native_magnitude(&rhs.0), len);
+            let mut result = WideFloat::zero(len);
+            write_native(
+                &mut result.0,
+                if neg { product.wrapping_neg() } else { product },
+            );
+            return result;
         }
-        if rneg ^ lneg {
-            result = -result;
-        }
-        result
+        let product = product(&self.magnitude().0, &rhs.magnitude().0);
+        WideFloat::from_product(&product, len, neg)
     }
 }
 
@@ -292,38 +858,94 @@ impl MulAssign<&WideFloat> for WideFloat {
     }
 }
 
-impl ShrAssign<usize> for WideFloat {
-    fn shr_assign(&mut self, rhs: usize) {
+impl Div for &WideFloat {
+    type Output = WideFloat;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs)
+            .expect("Division of WideFloat by zero")
+    }
+}
+
+impl DivAssign<&WideFloat> for WideFloat {
+    fn div_assign(&mut self, rhs: &Self) {
+        *self = &*self / rhs;
+    }
+}
+
+impl WideFloat {
+    /// Shifts right, filling the vacated bits with the bits of `fill`
+    fn shift_right(&mut self, rhs: usize, fill: u32) {
         let len = self.0.len();
 
-        let rotate = rhs / WORD_WIDTH;
+        // Shifts past the number leave only the fill
+        let rotate = (rhs / WORD_WIDTH).min(len);
         self.0.copy_within(rotate.., 0);
-        self.0.iter_mut().skip(len - rotate).for_each(|w| *w = 0);
+        self.0.iter_mut().skip(len - rotate).for_each(|w| *w = fill);
 
         let shift = rhs % WORD_WIDTH;
         if shift != 0 {
-            let mut carry = 0;
-            for w in self.0.iter_mut().rev().take(len - rotate) {
+            let mut carry = fill << (WORD_WIDTH - shift);
+            for w in self.0.iter_mut().take(len - rotate).rev() {
                 let tmp = (*w >> shift) + carry;
                 carry = *w << (WORD_WIDTH - shift);
                 *w = tmp;
             }
         }
     }
+
+    /// Shifts right, filling with zero bits regardless of the sign
+    pub fn logical_shr(&mut self, rhs: usize) {
+        self.shift_right(rhs, 0);
+    }
+
+    /// Shifts left. Returns `None` if bits of the whole part are lost or the sign changes
+    pub fn checked_shl(&self, rhs: usize) -> Option<Self> {
+        let shifted = self.clone() << rhs;
+        (shifted.clone() >> rhs == *self).then_some(shifted)
+    }
+}
+
+/// Arithmetic shift, negative numbers stay negative and round down
+impl Shr<usize> for WideFloat {
+    type Output = Self;
+
+    fn shr(mut self, rhs: usize) -> Self::Output {
+        self >>= rhs;
+        self
+    }
+}
+
+impl ShrAssign<usize> for WideFloat {
+    fn shr_assign(&mut self, rhs: usize) {
+        let fill = if self.floor() < 0 { u32::MAX } else { 0 };
+        self.shift_right(rhs, fill);
+    }
+}
+
+/// Bits shifted out of the whole part are lost, like products that don't fit wrap around. See
+/// [`WideFloat::checked_shl`]
+impl Shl<usize> for WideFloat {
+    type Output = Self;
+
+    fn shl(mut self, rhs: usize) -> Self::Output {
+        self <<= rhs;
+        self
+    }
 }
 
 impl ShlAssign<usize> for WideFloat {
     fn shl_assign(&mut self, rhs: usize) {
         let len = self.0.len();
 
-        let rotate = rhs / WORD_WIDTH;
+        let rotate = (rhs / WORD_WIDTH).min(len);
         self.0.copy_within(..len - rotate, rotate);
         self.0.iter_mut().take(rotate).for_each(|w| *w = 0);
 
         let shift = rhs % WORD_WIDTH;
         if shift != 0 {
             let mut carry = 0;
-            for w in self.0.iter_mut().take(len - rotate) {
+            for w in self.0.iter_mut().skip(rotate) {
                 let tmp = (*w << shift) + carry;
                 carry = *w >> (WORD_WIDTH - shift);
                 *w = tmp;
@@ -366,13 +988,755 @@ impl Ord for WideFloat {
     }
 }
 
+#[cfg(test)]
+impl WideFloat {
+    /// Whole part magnitude limit of [`WideFloat::arbitrary`], keeps sums and products of any two
+    /// generated numbers from overflowing
+    pub(crate) const ARBITRARY_WHOLE_LIMIT: i32 = 1 << 12;
+
+    /// Generates numbers of `size` words with the whole part in
+    /// `-ARBITRARY_WHOLE_LIMIT..ARBITRARY_WHOLE_LIMIT`, biased towards all-zero and all-one words
+    pub(crate) fn arbitrary(size: usize) -> impl proptest::strategy::Strategy<Value = Self> {
+        use proptest::prelude::*;
+
+        let word = prop_oneof![any::<u32>(), Just(0), Just(u32::MAX), Just(1 << 31)];
+        let whole = -Self::ARBITRARY_WHOLE_LIMIT..Self::ARBITRARY_WHOLE_LIMIT;
+        (proptest::collection::vec(word, size - 1), whole).prop_map(|(mut words, whole)| {
+            words.push(u32::from_ne_bytes(whole.to_ne_bytes()));
+            WideFloat::from_words(words)
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num_bigint::{BigInt, Sign};
+    use proptest::prelude::*;
+
+    /// Raw value of the number as a signed integer, the real value is `raw / 2^fraction_bits`
+    fn raw(w: &WideFloat) -> BigInt {
+        let unsigned = BigInt::from_slice(Sign::Plus, &w.0);
+        if w.floor() < 0 {
+            unsigned - (BigInt::from(1) << (w.0.len() * WORD_WIDTH))
+        } else {
+            unsigned
+        }
+    }
+
+    fn fraction_bits(w: &WideFloat) -> usize {
+        (w.0.len() - 1) * WORD_WIDTH
+    }
+
+    /// Wraps a raw value around like a number of `size` words would
+    fn wrap(value: BigInt, size: usize) -> BigInt {
+        let bits = size * WORD_WIDTH;
+        let wrapped = value & ((BigInt::from(1) << bits) - 1);
+        if wrapped >= BigInt::from(1) << (bits - 1) {
+            wrapped - (BigInt::from(1) << bits)
+        } else {
+            wrapped
+        }
+    }
+
+    fn sized_pair() -> impl Strategy<Value = (WideFloat, WideFloat)> {
+        (2..=12usize).prop_flat_map(|size| (WideFloat::arbitrary(size), WideFloat::arbitrary(size)))
+    }
+
+    fn sized() -> impl Strategy<Value = WideFloat> {
+        (2..=12usize).prop_flat_map(WideFloat::arbitrary)
+    }
+
+    /// Raw value of `value` truncated towards zero to a number of `size` words
+    fn raw_f64(value: f64, size: usize) -> BigInt {
+        let bits = value.to_bits();
+        let biased = (bits >> 52 & 0x7ff) as i32;
+        let fraction = bits & ((1 << 52) - 1);
+        let (mantissa, exp) = match biased {
+            0 => (fraction, -1074),
+            _ => (fraction | 1 << 52, biased - 1075),
+        };
+        let shift = exp + ((size - 1) * WORD_WIDTH) as i32;
+        let magnitude = if shift >= 0 {
+            BigInt::from(mantissa) << shift as usize
+        } else {
+            BigInt::from(mantissa) >> (-shift) as usize
+        };
+        if value < 0.0 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Add(f64),
+        Sub(f64),
+        Mul(f64),
+        Neg,
+        Shr(usize),
+        LogicalShr(usize),
+        Shl(usize),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        let value = prop_oneof![-4.0..4.0f64, -1e-30..1e-30f64];
+        prop_oneof![
+            value.clone().prop_map(Op::Add),
+            value.clone().prop_map(Op::Sub),
+            value.prop_map(Op::Mul),
+            Just(Op::Neg),
+            (0..=WORD_WIDTH * 3).prop_map(Op::Shr),
+            (0..=WORD_WIDTH * 3).prop_map(Op::LogicalShr),
+            (0..=WORD_WIDTH * 3).prop_map(Op::Shl),
+        ]
+    }
+
+    /// Floats with all mantissa bits representable in a number of `size` words
+    fn exact_f32(size: usize) -> impl Strategy<Value = f32> {
+        let min_exp = -((WORD_WIDTH * (size - 1)) as i32).min(100);
+        (-(1i32 << 24)..(1 << 24), min_exp..=-12)
+            .prop_map(|(mantissa, exp)| mantissa as f32 * 2f32.powi(exp))
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(256))]
+
+        #[test]
+        fn add_sub_round_trip((a, b) in sized_pair()) {
+            prop_assert_eq!(a.clone() + &b - &b, a);
+        }
+
+        #[test]
+        fn add_sub_match_reference((a, b) in sized_pair()) {
+            prop_assert_eq!(raw(&(a.clone() + &b)), raw(&a) + raw(&b));
+            prop_assert_eq!(raw(&(a.clone() - &b)), raw(&a) - raw(&b));
+
+            let mut assigned = a.clone();
+            assigned += &b;
+            prop_assert_eq!(&assigned, &(a.clone() + &b));
+            assigned -= &b;
+            prop_assert_eq!(assigned, a);
+        }
+
+        #[test]
+        fn neg_matches_reference(a in sized()) {
+            prop_assert_eq!(raw(&-a.clone()), -raw(&a));
+            prop_assert_eq!(-(-a.clone()), a);
+        }
+
+        #[test]
+        fn mul_matches_reference((a, b) in sized_pair()) {
+            // Exact product truncated towards zero, which integer division of the raw value does
+            let exact = raw(&a) * raw(&b) / (BigInt::from(1) << fraction_bits(&a));
+            prop_assert_eq!(raw(&(&a * &b)), exact);
+        }
+
+        #[test]
+        fn checked_ops_match_reference((a, b) in sized_pair()) {
+            let fits = |value: BigInt| wrap(value.clone(), a.0.len()) == value;
+            prop_assert_eq!(a.checked_add(&b).is_some(), fits(raw(&a) + raw(&b)));
+            prop_assert_eq!(a.checked_sub(&b).is_some(), fits(raw(&a) - raw(&b)));
+            let product = raw(&a) * raw(&b) / (BigInt::from(1) << fraction_bits(&a));
+            prop_assert_eq!(a.checked_mul(&b).is_some(), fits(product));
+            if let Some(product) = a.checked_mul(&b) {
+                prop_assert_eq!(product, &a * &b);
+            }
+        }
+
+        #[test]
+        fn native_words_match_generic((a, b) in NATIVE_WORDS.prop_flat_map(|size| (WideFloat::arbitrary(size), WideFloat::arbitrary(size)))) {
+            let mut native = a.0.clone();
+            add_words(&mut native, &b.0);
+            let mut generic = a.0.clone();
+            add_words_generic(&mut generic, &b.0);
+            prop_assert_eq!(native, generic);
+
+            let mut native = a.0.clone();
+            sub_words(&mut native, &b.0);
+            let mut generic = a.0.clone();
+            sub_words_generic(&mut generic, &b.0);
+            prop_assert_eq!(native, generic);
+
+            let len = a.0.len();
+            let neg = (a < 0) ^ (b < 0);
+            let generic = WideFloat::from_product(&product(&a.magnitude().0, &b.magnitude().0), len, neg);
+            prop_assert_eq!(&a * &b, generic);
+        }
+
+        #[test]
+        fn square_matches_mul(a in (2..=16usize).prop_flat_map(WideFloat::arbitrary)) {
+            prop_assert_eq!(a.square(), &a * &a);
+        }
+
+        #[test]
+        fn mul_sign((a, b) in sized_pair()) {
+            let product = &a * &b;
+            if product != 0 {
+                prop_assert_eq!(product < 0, (a < 0) ^ (b < 0));
+            }
+            let mut assigned = a.clone();
+            assigned *= &b;
+            prop_assert_eq!(assigned, product);
+        }
+
+        #[test]
+        fn div_matches_reference((a, b) in sized_pair()) {
+            prop_assume!(b != 0);
+            let quotient = &a / &b;
+            // Integer division of the raw values truncates towards zero as well
+            let exact = wrap((raw(&a) << fraction_bits(&a)) / raw(&b), a.0.len());
+            prop_assert_eq!(raw(&quotient), exact);
+
+            let mut assigned = a.clone();
+            assigned /= &b;
+            prop_assert_eq!(assigned, quotient);
+        }
+
+        #[test]
+        fn div_mul_round_trip((a, b) in sized_pair()) {
+            // The truncated quotient and product are each less than an ulp off, the quotient error
+            // stays below an ulp after multiplying by a divisor below 1. Dividing a product of the
+            // divisor can't overflow
+            let mut b = b;
+            *b.0.last_mut().unwrap() = 0;
+            prop_assume!(b != 0);
+            let a = &a * &b;
+            let error = raw(&(&(&a / &b) * &b)) - raw(&a);
+            prop_assert!(error.magnitude() <= BigInt::from(2).magnitude(), "error: {}", error);
+        }
+
+        #[test]
+        fn cmp_matches_reference((a, b) in sized_pair()) {
+            prop_assert_eq!(a.cmp(&b), raw(&a).cmp(&raw(&b)));
+            prop_assert_eq!(a.cmp(&a), std::cmp::Ordering::Equal);
+            prop_assert_eq!(
+                a.partial_cmp(&a.floor()),
+                raw(&a).partial_cmp(&(BigInt::from(a.floor()) << fraction_bits(&a)))
+            );
+        }
+
+        #[test]
+        fn cmp_consistent_with_f32((a, b) in sized_pair()) {
+            if a < b {
+                prop_assert!(a.as_f32_round() <= b.as_f32_round());
+            }
+        }
+
+        #[test]
+        fn shift_round_trip(a in sized(), shift in 0..=(WORD_WIDTH * 2)) {
+            // Only test non-negative numbers that keep their top bits
+            let a = if a < 0 { -a } else { a };
+            let mut shifted = a.clone();
+            shifted >>= shift;
+            prop_assert_eq!(raw(&shifted), raw(&a) >> shift);
+            let shift = shift.min(18);
+            let mut shifted = a.clone();
+            shifted <<= shift;
+            prop_assert_eq!(raw(&shifted), raw(&a) << shift);
+            shifted >>= shift;
+            prop_assert_eq!(shifted, a);
+        }
+
+        #[test]
+        fn f64_round_trip(
+            size in 7..=16usize,
+            mantissa in (1u64 << 52)..(1 << 53),
+            exp in -172..=-52,
+            neg: bool,
+        ) {
+            // Values from 2^-120 to 2 with every mantissa bit representable
+            let f = mantissa as f64 * 2f64.powi(exp) * if neg { -1.0 } else { 1.0 };
+            let wide = WideFloat::from_f64(f, size).unwrap();
+            prop_assert_eq!(wide.as_f64_round(), f);
+            prop_assert_eq!(wide < 0, neg);
+            prop_assert_eq!(raw(&wide), BigInt::from(mantissa as i64 * if neg { -1 } else { 1 }) << (fraction_bits(&wide) as i32 + exp) as usize);
+        }
+
+        #[test]
+        fn f64_matches_f32((size, f) in (2..=12usize).prop_flat_map(|size| (Just(size), exact_f32(size)))) {
+            prop_assert_eq!(WideFloat::from_f64(f as f64, size).unwrap(), WideFloat::from_f32(f, size).unwrap());
+        }
+
+        #[test]
+        fn f64_round_trip_is_close(a in sized()) {
+            let rounded = WideFloat::from_f64(a.as_f64_round(), a.0.len()).unwrap();
+            // Rounding to the mantissa, then truncating to the least significant word
+            let error: BigInt = (raw(&rounded) - raw(&a)) << 53;
+            let max_error = raw(&a).magnitude() + (BigInt::from(1) << 53u32).magnitude();
+            prop_assert!(error.magnitude() <= &max_error, "error: {}", error);
+        }
+
+        #[test]
+        fn display_round_trip(a in (6..=16usize).prop_flat_map(WideFloat::arbitrary)) {
+            let text = a.to_string();
+            prop_assert_eq!(WideFloat::from_decimal_str(&text, a.0.len()), Ok(a), "{}", text);
+        }
+
+        #[test]
+        fn op_sequences_match_reference(
+            size in 2..=16usize,
+            start in -4.0..4.0f64,
+            ops in proptest::collection::vec(op(), 1..16),
+        ) {
+            let mut wide = WideFloat::from_f64(start, size).unwrap();
+            let mut exact = raw_f64(start, size);
+            prop_assert_eq!(raw(&wide), exact.clone());
+            let unsigned = |value: BigInt| value & ((BigInt::from(1) << (size * WORD_WIDTH)) - 1);
+            for op in ops {
+                let operand = |value| WideFloat::from_f64(value, size).unwrap();
+                exact = match op {
+                    Op::Add(value) => {
+                        wide += &operand(value);
+                        exact + raw_f64(value, size)
+                    }
+                    Op::Sub(value) => {
+                        wide -= &operand(value);
+                        exact - raw_f64(value, size)
+                    }
+                    Op::Mul(value) => {
+                        wide *= &operand(value);
+                        exact * raw_f64(value, size) / (BigInt::from(1) << fraction_bits(&wide))
+                    }
+                    Op::Neg => {
+                        wide = -wide;
+                        -exact
+                    }
+                    Op::Shr(shift) => {
+                        wide >>= shift;
+                        exact >> shift
+                    }
+                    Op::LogicalShr(shift) => {
+                        wide.logical_shr(shift);
+                        unsigned(exact) >> shift
+                    }
+                    Op::Shl(shift) => {
+                        wide <<= shift;
+                        exact << shift
+                    }
+                };
+                exact = wrap(exact, size);
+                prop_assert_eq!(raw(&wide), exact.clone(), "after {:?}", op);
+            }
+        }
+
+        #[test]
+        fn f32_round_trip((size, f) in (2..=12usize).prop_flat_map(|size| (Just(size), exact_f32(size)))) {
+            let wide = WideFloat::from_f32(f, size).unwrap();
+            prop_assert_eq!(wide.as_f32_round(), f);
+            prop_assert_eq!(wide < 0, f < 0.0);
+        }
+    }
+
+    #[test]
+    fn negative_products() {
+        for size in 2..=16 {
+            let f = |value| WideFloat::from_f64(value, size).unwrap();
+            assert_eq!(&f(-1.5) * &f(-2.25), f(3.375));
+            assert_eq!(&f(-1.0) * &f(-1.0), f(1.0));
+            assert_eq!(f(-32768.0).square(), f(1073741824.0));
+            // Products of the smallest negative number are below the least significant word
+            let ulp = -WideFloat::min_positive(size, 0);
+            assert_eq!(&ulp * &ulp, WideFloat::zero(size));
+            assert_eq!(&ulp * &f(-1.0), WideFloat::min_positive(size, 0));
+            // The negated whole part minimum wraps around
+            let min = WideFloat::from_i32(i32::MIN, size);
+            assert_eq!(&min * &f(-1.0), min);
+        }
+    }
+
+    #[test]
+    fn subtraction_borrows_through_every_word() {
+        for size in 2..=16 {
+            let ulp = WideFloat::min_positive(size, 0);
+            let below_zero = WideFloat::zero(size) - &ulp;
+            assert_eq!(below_zero.words(), vec![u32::MAX; size]);
+            assert_eq!(below_zero.clone() + &ulp, WideFloat::zero(size));
+
+            let below_one = WideFloat::from_i32(1, size) - &ulp;
+            assert_eq!(below_one.floor(), 0);
+            assert!(below_one.words()[..size - 1].iter().all(|w| *w == u32::MAX));
+
+            let mut min = WideFloat::from_i32(i32::MIN, size);
+            min -= &ulp;
+            assert_eq!(min.floor(), i32::MAX);
+        }
+    }
+
+    #[test]
+    fn shifts_by_whole_words() {
+        let words: Vec<u32> = (1..=6).collect();
+        let num = WideFloat::from_words(words.clone());
+        for rotate in 0..=6 {
+            let mut shifted = num.clone();
+            shifted >>= rotate * WORD_WIDTH;
+            let mut expected = words[rotate..].to_vec();
+            expected.resize(6, 0);
+            assert_eq!(shifted.words(), expected, "{} words right", rotate);
+
+            let mut shifted = num.clone();
+            shifted <<= rotate * WORD_WIDTH;
+            let mut expected = vec![0; rotate];
+            expected.extend_from_slice(&words[..6 - rotate]);
+            assert_eq!(shifted.words(), expected, "{} words left", rotate);
+        }
+
+        let mut shifted = num.clone();
+        shifted >>= 7 * WORD_WIDTH + 5;
+        assert_eq!(shifted, WideFloat::zero(6));
+        let mut shifted = num;
+        shifted <<= 7 * WORD_WIDTH + 5;
+        assert_eq!(shifted, WideFloat::zero(6));
+    }
+
+    #[test]
+    fn raw_formatting() {
+        let value = WideFloat::from_f32(-1.5, 3).unwrap();
+        assert_eq!(format!("{:x}", value), "fffffffe.80000000_00000000");
+        assert_eq!(format!("{:#x}", value), "0xfffffffe.80000000_00000000");
+        assert_eq!(
+            format!("{:x}", WideFloat::from_words(vec![0xdead, 0xbeef, 0x7f])),
+            "0000007f.0000beef_0000dead"
+        );
+        assert_eq!(
+            format!("{:x}", WideFloat::from_i32(-1, 2)),
+            "ffffffff.00000000"
+        );
+
+        let value = WideFloat::from_f32(2.25, 2).unwrap();
+        assert_eq!(
+            format!("{:#b}", value),
+            "0b00000000000000000000000000000010.01000000000000000000000000000000"
+        );
+        assert_eq!(
+            format!("{:b}", -WideFloat::min_positive(2, 0)),
+            "11111111111111111111111111111111.11111111111111111111111111111111"
+        );
+    }
+
+    #[test]
+    fn signed_shifts() {
+        let as_i128 = |w: &WideFloat| {
+            (w.0.iter().rev()).fold(0u128, |acc, word| acc << WORD_WIDTH | *word as u128) as i128
+        };
+        let values = [
+            WideFloat::from_f64(-1.5, 4).unwrap(),
+            WideFloat::from_f64(-3e-20, 4).unwrap(),
+            -WideFloat::min_positive(4, 0),
+            WideFloat::from_i32(i32::MIN, 4),
+            WideFloat::from_f64(12345.678, 4).unwrap(),
+        ];
+        for value in values {
+            let raw = as_i128(&value);
+            for shift in [1, 31, 32, 33] {
+                let shifted = value.clone() >> shift;
+                assert_eq!(as_i128(&shifted), raw >> shift, "{:x} >> {}", value, shift);
+                let mut assigned = value.clone();
+                assigned >>= shift;
+                assert_eq!(assigned, shifted);
+
+                let mut logical = value.clone();
+                logical.logical_shr(shift);
+                assert_eq!(as_i128(&logical), (raw as u128 >> shift) as i128);
+
+                let shifted = value.clone() << shift;
+                assert_eq!(as_i128(&shifted), raw << shift, "{:x} << {}", value, shift);
+                let fits = (raw << shift) >> shift == raw;
+                assert_eq!(value.checked_shl(shift), fits.then_some(shifted));
+            }
+        }
+        assert_eq!(
+            WideFloat::from_i32(-1, 4) >> 1,
+            WideFloat::from_f64(-0.5, 4).unwrap()
+        );
+        assert_eq!(
+            WideFloat::from_i32(-1, 4) >> 1000,
+            -WideFloat::min_positive(4, 0)
+        );
+        assert_eq!(
+            WideFloat::from_i32(-2, 2).checked_shl(1),
+            Some(WideFloat::from_i32(-4, 2))
+        );
+        assert_eq!(WideFloat::from_i32(1 << 30, 2).checked_shl(1), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let value = WideFloat::from_f64(-1.5, 3).unwrap();
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"version":1,"words":[0,2147483648,4294967294]}"#);
+        assert_eq!(serde_json::from_str::<WideFloat>(&json).unwrap(), value);
+
+        assert!(serde_json::from_str::<WideFloat>(r#"{"version":2,"words":[0,1]}"#).is_err());
+        assert!(serde_json::from_str::<WideFloat>(r#"{"version":1,"words":[]}"#).is_err());
+
+        // Compact binary formats take the same path
+        let binary = bincode::serialize(&value).unwrap();
+        assert_eq!(binary.len(), 4 + 8 + 3 * 4);
+        assert_eq!(bincode::deserialize::<WideFloat>(&binary).unwrap(), value);
+        let mut newer = binary.clone();
+        newer[0] = 2;
+        assert!(bincode::deserialize::<WideFloat>(&newer).is_err());
+        assert!(bincode::deserialize::<WideFloat>(&binary[..binary.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        let f = |value| WideFloat::from_f64(value, 3).unwrap();
+        let max = WideFloat::from_i32(i32::MAX, 3) + &WideFloat::from_f64(0.5, 3).unwrap();
+        let min = WideFloat::from_i32(i32::MIN, 3);
+
+        assert_eq!(f(1.5).checked_add(&f(-2.25)), Some(f(-0.75)));
+        assert_eq!(max.checked_add(&f(0.25)), Some(max.clone() + &f(0.25)));
+        assert_eq!(max.checked_add(&f(0.5)), None);
+        assert_eq!(min.checked_add(&f(-0.25)), None);
+        assert_eq!(min.checked_add(&max), Some(f(-0.5)));
+
+        assert_eq!(f(1.5).checked_sub(&f(2.25)), Some(f(-0.75)));
+        assert_eq!(min.checked_sub(&WideFloat::min_positive(3, 0)), None);
+        assert_eq!(max.checked_sub(&f(-0.5)), None);
+        assert_eq!(f(-1.0).checked_sub(&max), None);
+        assert_eq!(f(-0.5).checked_sub(&max), Some(min.clone()));
+        assert_eq!(f(0.0).checked_sub(&min), None);
+
+        assert_eq!(f(-1.5).checked_mul(&f(-2.0)), Some(f(3.0)));
+        assert_eq!(f(65536.0).checked_mul(&f(32767.5)), Some(f(2147450880.0)));
+        assert_eq!(f(65536.0).checked_mul(&f(32768.0)), None);
+        assert_eq!(f(-65536.0).checked_mul(&f(32768.0)), Some(min.clone()));
+        assert_eq!(min.checked_mul(&f(-1.0)), None);
+        assert_eq!(f(-65536.0).checked_mul(&f(32768.5)), None);
+        assert_eq!(f(1e9).checked_mul(&f(1e9)), None);
+    }
+
+    #[test]
+    fn logarithms() {
+        let size = 8;
+        let min_exponent = -((size - 1) as i32 * WORD_WIDTH as i32);
+        for exponent in min_exponent..=1 {
+            let mut power = WideFloat::from_i32(1, size);
+            if exponent > 0 {
+                power <<= exponent as usize;
+            } else {
+                power.logical_shr(-exponent as usize);
+            }
+            let log10 = exponent as f64 * std::f64::consts::LOG10_2;
+            assert_eq!(power.ilog2(), Some(exponent));
+            assert_eq!((-power.clone()).ilog2(), Some(exponent));
+            assert!((power.magnitude_log10().unwrap() - log10).abs() < 1e-9);
+
+            let three = &power * &WideFloat::from_i32(3, size);
+            assert_eq!(three.ilog2(), Some(exponent + 1));
+            let log10 = log10 + 3f64.log10();
+            assert!((three.magnitude_log10().unwrap() - log10).abs() < 1e-9);
+        }
+        assert_eq!(WideFloat::min_positive(size, 0).ilog2(), Some(min_exponent));
+        assert_eq!(WideFloat::from_f32(1.5, 2).unwrap().ilog2(), Some(0));
+        assert_eq!(WideFloat::from_i32(i32::MIN, 2).ilog2(), Some(31));
+        assert_eq!(WideFloat::zero(size).ilog2(), None);
+        assert_eq!(WideFloat::zero(size).magnitude_log10(), None);
+    }
+
+    #[test]
+    fn division() {
+        let size = 6;
+        let f = |value| WideFloat::from_f32(value, size).unwrap();
+        assert_eq!(&f(1.0) / &f(1.0), f(1.0));
+        assert_eq!(&f(-1.0) / &f(1.0), f(-1.0));
+        assert_eq!(&f(-1.0) / &f(-1.0), f(1.0));
+        assert_eq!(&f(3.0) / &f(-0.5), f(-6.0));
+        assert_eq!(&f(0.75) / &f(1.5), f(0.5));
+
+        // Just below 1, 1 - 2^-160
+        let below_one = WideFloat::zero(size) - &WideFloat::min_positive(size, 0) + &f(1.0);
+        assert_eq!(&below_one / &below_one, f(1.0));
+        assert_eq!(&below_one / &f(1.0), below_one);
+        let below_minus_one = -f(1.0) - &WideFloat::min_positive(size, 0);
+        assert_eq!(&below_minus_one / &f(-1.0), -below_minus_one.clone());
+
+        // Tiny steps divide without losing bits
+        let step = WideFloat::min_positive(size, 128);
+        assert_eq!(&step / &f(2.0), WideFloat::min_positive(size, 127));
+        assert_eq!(&step / &step, f(1.0));
+        assert_eq!(&(&step * &f(0.75)) / &step, f(0.75));
+        assert_eq!(&f(1.0) / &f(2.0f32.powi(-30)), f(2.0f32.powi(30)));
+
+        assert!(f(1.0).checked_div(&WideFloat::zero(size)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Division of WideFloat by zero")]
+    fn division_by_zero_panics() {
+        let _ = &WideFloat::from_i32(1, 3) / &WideFloat::zero(3);
+    }
+
+    #[test]
+    fn decimals_are_parsed() {
+        let parse = WideFloat::from_decimal_str;
+        assert_eq!(parse("-1.25", 2).ok(), WideFloat::from_f32(-1.25, 2).ok());
+        assert_eq!(parse("+.5", 3).ok(), WideFloat::from_f32(0.5, 3).ok());
+        assert_eq!(parse("7.", 2).ok(), WideFloat::from_f32(7.0, 2).ok());
+        assert_eq!(parse("3e-2", 4), parse("0.03", 4));
+        assert_eq!(parse("0.0125E2", 2).ok(), WideFloat::from_f32(1.25, 2).ok());
+        assert_eq!(parse("25e3", 2), Ok(WideFloat::from_i32(25000, 2)));
+        assert_eq!(parse("1e-400", 4), Ok(WideFloat::zero(4)));
+        assert_eq!(
+            parse("-2147483647", 2),
+            Ok(WideFloat::from_i32(-i32::MAX, 2))
+        );
+        // Rounded to nearest
+        assert_eq!(
+            parse("0.99999999999999999999999", 2),
+            Ok(WideFloat::from_i32(1, 2))
+        );
+        // Digits beyond f64 precision are kept
+        let value = parse("-0.743643887037158704752191506114774", 8).unwrap();
+        assert_ne!(
+            value,
+            WideFloat::from_f64(-0.743_643_887_037_158_7, 8).unwrap()
+        );
+        let text = value.to_string();
+        assert!(
+            text.starts_with("-0.74364388703715870475219150611477"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn invalid_decimals_are_located() {
+        use ParseDecimalError::*;
+        for (text, error) in [
+            ("", NoDigits),
+            ("-", NoDigits),
+            (".", NoDigits),
+            ("e5", NoDigits),
+            ("1.2.3", InvalidCharacter(3)),
+            ("  1,5", InvalidCharacter(3)),
+            ("0x10", InvalidCharacter(1)),
+            ("- 1", InvalidCharacter(1)),
+            ("1e", InvalidExponent(1)),
+            ("0e99999", InvalidExponent(1)),
+            ("3000000000", OutOfRange(9)),
+            ("-12.5e9", OutOfRange(5)),
+            ("2147483648", OutOfRange(9)),
+            ("3e9", OutOfRange(1)),
+            ("2147483647.99999999999999", OutOfRange(0)),
+        ] {
+            assert_eq!(
+                WideFloat::from_decimal_str(text, 2),
+                Err(error),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn decimals_round_trip() {
+        for size in [2, 3, 8, 16] {
+            let mut value = WideFloat::from_f32(-0.7436438, size).unwrap();
+            let step = WideFloat::min_positive(size, 0);
+            for _ in 0..50 {
+                value += &step;
+                value = &value * &WideFloat::from_f32(1.37, size).unwrap();
+                let text = value.to_string();
+                assert_eq!(
+                    WideFloat::from_decimal_str(&text, size),
+                    Ok(value.clone()),
+                    "{}",
+                    text
+                );
+            }
+        }
+        assert_eq!(WideFloat::from_i32(-3, 2).to_string(), "-3");
+        assert_eq!(WideFloat::from_f32(0.5, 2).unwrap().to_string(), "0.5");
+        // Only as many digits as needed to parse the same number back
+        let tiny = WideFloat::min_positive(3, 0);
+        assert_eq!(tiny.to_string(), "0.00000000000000000005");
+        assert_eq!(
+            WideFloat::from_decimal_str("0.00000000000000000005", 3),
+            Ok(tiny)
+        );
+    }
+
+    #[test]
+    fn f32_conversion_limits() {
+        let convert = |value| WideFloat::from_f32(value, 3);
+        assert!(matches!(convert(f32::NAN), Err(FromFloatError::IsNan)));
+        for value in [
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            2.0f32.powi(31),
+            -2.0f32.powi(32),
+        ] {
+            assert!(
+                matches!(convert(value), Err(FromFloatError::OutOfRange)),
+                "{}",
+                value
+            );
+        }
+        assert_eq!(convert(-2.0f32.powi(31)).unwrap().floor(), i32::MIN);
+        // Largest float below 2^31
+        let max = 2.0f32.powi(31) - 128.0;
+        assert_eq!(convert(max).unwrap(), WideFloat::from_i32(max as i32, 3));
+        assert_eq!(convert(-0.0).unwrap(), WideFloat::zero(3));
+        // Subnormal numbers and bits below the least significant word are rounded to zero
+        assert_eq!(
+            convert(f32::MIN_POSITIVE / 2.0).unwrap(),
+            WideFloat::zero(3)
+        );
+        assert_eq!(convert(2.0f32.powi(-65)).unwrap(), WideFloat::zero(3));
+        assert_eq!(
+            convert(-2.0f32.powi(-64)).unwrap(),
+            -WideFloat::min_positive(3, 0)
+        );
+    }
+
+    #[test]
+    fn f64_conversion() {
+        let value = -0.6827560061104002;
+        assert_eq!(WideFloat::from_f64(value, 3).unwrap().as_f64_round(), value);
+        assert_eq!(
+            WideFloat::from_f64(-1999.125, 2).unwrap().as_f64_round(),
+            -1999.125
+        );
+        assert_eq!(
+            WideFloat::from_f64(-2.0f64.powi(31), 2)
+                .unwrap()
+                .as_f64_round(),
+            -2.0f64.powi(31)
+        );
+        assert_eq!(WideFloat::from_f64(0.0, 4).unwrap(), WideFloat::zero(4));
+        // Too small for the number size
+        assert_eq!(
+            WideFloat::from_f64(2.0f64.powi(-40), 2).unwrap(),
+            WideFloat::zero(2)
+        );
+        assert_eq!(
+            WideFloat::from_f64(f64::MIN_POSITIVE / 2.0, 16).unwrap(),
+            WideFloat::zero(16)
+        );
+        assert!(WideFloat::from_f64(2.0f64.powi(31), 2).is_err());
+        assert!(WideFloat::from_f64(f64::INFINITY, 2).is_err());
+        assert!(WideFloat::from_f64(f64::NAN, 2).is_err());
+    }
+
+    #[test]
+    fn approximate_conversion() {
+        for value in [0.0, 1.5, -1.5, -0.25, 3.0e-5, -1999.125] {
+            let float = WideFloat::from_f32(value, 3).unwrap();
+            assert!((float.as_f32_approx() - value).abs() < 1.0e-9, "{value}");
+        }
+        let tiny = WideFloat::min_positive(4, 0);
+        assert_eq!(tiny.as_f32_approx(), 0.0);
+        assert_eq!((-tiny).as_f32_approx(), 0.0);
+    }
 
     #[test]
     fn precision_diff() {
-        let float = WideFloat(vec![
+        let float = WideFloat::from_words(vec![
             0b00001000_00000001_01000000_00001000,
             0b00001000_00100100_00001000_00010110,
             0b00000000_00000000_10100110_01100010,
@@ -390,5 +1754,177 @@ mod tests {
         assert_eq!(float.precision_diff(96), 1);
         assert_eq!(float.precision_diff(112), 1);
         assert_eq!(float.precision_diff(113), 2);
+
+        // Negative numbers need as many words as their magnitude
+        for bits in [10, 16, 17, 32, 64, 80, 81, 96, 112, 113] {
+            let negative = -float.clone();
+            assert_eq!(negative.precision_diff(bits), float.precision_diff(bits));
+        }
+        let tiny = -WideFloat::min_positive(4, 0);
+        assert_eq!(tiny.precision_diff(10), 1);
+        assert_eq!(WideFloat::from_i32(-1, 4).precision_diff(10), -2);
+    }
+
+    #[test]
+    fn negative_precision_change() {
+        let mut value = WideFloat::from_f64(-1.0 - 2f64.powi(-40), 3).unwrap();
+        value.change_precision(1);
+        assert_eq!(
+            value,
+            WideFloat::from_f64(-1.0 - 2f64.powi(-40), 4).unwrap()
+        );
+        // Truncated towards zero like the magnitude
+        value.change_precision(-2);
+        assert_eq!(value, WideFloat::from_i32(-1, 2));
+        let mut value = WideFloat::from_f64(-2f64.powi(-40), 3).unwrap();
+        value.change_precision(-1);
+        assert_eq!(value, WideFloat::zero(2));
+    }
+
+    /// Times squares against products of a number with itself. Run with
+    /// `cargo test --release --lib square_speedup -- --ignored --nocapture`
+    #[test]
+    #[ignore = "benchmark"]
+    fn square_speedup() {
+        use std::time::Instant;
+
+        for size in [4, 8, 16] {
+            // Fractions filling all words, as deep in a zoom
+            let values: Vec<_> = (1..=1000)
+                .map(|i| {
+                    let mut value = WideFloat::from_f64(1.0 / i as f64 - 0.5, size).unwrap();
+                    value.0[0] = 0x9e3779b9u32.wrapping_mul(i);
+                    value
+                })
+                .collect();
+            let rounds = 200;
+
+            let start = Instant::now();
+            let squares: Vec<_> = (0..rounds)
+                .flat_map(|_| values.iter().map(WideFloat::square))
+                .collect();
+            let square_time = start.elapsed();
+
+            let start = Instant::now();
+            let products: Vec<_> = (0..rounds)
+                .flat_map(|_| values.iter().map(|value| value * value))
+                .collect();
+            let mul_time = start.elapsed();
+
+            assert_eq!(squares, products);
+            println!(
+                "{size} words, {} squares: square {square_time:?}, mul {mul_time:?}, {:.2}x",
+                squares.len(),
+                mul_time.as_secs_f64() / square_time.as_secs_f64(),
+            );
+        }
+    }
+
+    #[test]
+    fn minimum_whole_part_precision() {
+        // -2^31 is its own negation, its magnitude still has the top bit set
+        let min = WideFloat::from_f64(-2147483648.0, 4).unwrap();
+        let half = WideFloat::from_f64(-1073741824.0, 4).unwrap();
+        for bits in [10, 32, 40, 100] {
+            assert_eq!(min.precision_diff(bits), half.precision_diff(bits));
+        }
+
+        let mut value = min.clone();
+        value.change_precision(-2);
+        assert_eq!(value, WideFloat::from_i32(i32::MIN, 2));
+        value.change_precision(2);
+        assert_eq!(value, min);
+    }
+
+    /// Points of a zoom into a spiral, 4x4 pixels around the center for every halving of the
+    /// scale that `size` words can still tell apart
+    fn zoom_points(size: usize) -> Vec<(WideFloat, WideFloat)> {
+        let wide = |value: f64| WideFloat::from_f64(value, size).unwrap();
+        let center = (wide(-0.77568377), wide(0.13646737));
+        let frames = (size - 1) * WORD_WIDTH - 8;
+        (0..frames as i32)
+            .flat_map(|frame| (0..16).map(move |pixel| (frame, pixel)))
+            .map(|(frame, pixel)| {
+                let offset = |i: i32| (i as f64 - 1.5) * 2f64.powi(-frame);
+                (
+                    center.0.clone() + &wide(offset(pixel % 4)),
+                    center.1.clone() + &wide(offset(pixel / 4)),
+                )
+            })
+            .collect()
+    }
+
+    /// Iterates the orbits of `points` the way the reference orbit does, with products instead
+    /// of squares, and returns their last values. Takes the operations, so that the native and
+    /// generic word paths run the same sequence
+    fn iterate_orbits(
+        points: &[(WideFloat, WideFloat)],
+        mul: impl Fn(&WideFloat, &WideFloat) -> WideFloat,
+        add: impl Fn(&WideFloat, &WideFloat) -> WideFloat,
+        sub: impl Fn(&WideFloat, &WideFloat) -> WideFloat,
+    ) -> Vec<(WideFloat, WideFloat)> {
+        points
+            .iter()
+            .map(|(cx, cy)| {
+                let (mut x, mut y) = (cx.clone(), cy.clone());
+                for _ in 0..1000 {
+                    let (re, im) = (x.as_f64_round(), y.as_f64_round());
+                    if re * re + im * im >= 4.0 {
+                        break;
+                    }
+                    let (x2, y2, xy) = (mul(&x, &x), mul(&y, &y), mul(&x, &y));
+                    (x, y) = (add(&sub(&x2, &y2), cx), add(&add(&xy, &xy), cy));
+                }
+                (x, y)
+            })
+            .collect()
+    }
+
+    /// Times the native and generic word paths on the orbits of a zoom sequence. Run with
+    /// `cargo test --release --lib native_words_speedup -- --ignored --nocapture`
+    #[test]
+    #[ignore = "benchmark"]
+    fn native_words_speedup() {
+        use std::time::Instant;
+
+        for size in NATIVE_WORDS {
+            let points = zoom_points(size);
+
+            let start = Instant::now();
+            let native = iterate_orbits(
+                &points,
+                |a, b| a * b,
+                |a, b| a.clone() + b,
+                |a, b| a.clone() - b,
+            );
+            let native_time = start.elapsed();
+
+            let start = Instant::now();
+            let generic = iterate_orbits(
+                &points,
+                |a, b| {
+                    let neg = (a < &0) ^ (b < &0);
+                    WideFloat::from_product(&product(&a.magnitude().0, &b.magnitude().0), size, neg)
+                },
+                |a, b| {
+                    let mut sum = a.clone();
+                    add_words_generic(&mut sum.0, &b.0);
+                    sum
+                },
+                |a, b| {
+                    let mut difference = a.clone();
+                    sub_words_generic(&mut difference.0, &b.0);
+                    difference
+                },
+            );
+            let generic_time = start.elapsed();
+
+            assert_eq!(native, generic);
+            println!(
+                "{size} words, {} orbits: native {native_time:?}, generic {generic_time:?}, {:.1}x",
+                points.len(),
+                generic_time.as_secs_f64() / native_time.as_secs_f64(),
+            );
+        }
     }
 }
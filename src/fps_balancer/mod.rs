@@ -1,9 +1,865 @@
-#[cfg(not(target_arch = "wasm32"))]
-mod native;
-#[cfg(not(target_arch = "wasm32"))]
-pub use native::FpsBalancer;
-
-#[cfg(target_arch = "wasm32")]
-mod wasm;
-#[cfg(target_arch = "wasm32")]
-pub use wasm::FpsBalancer;
+use crate::timer::{Clock, SystemClock};
+use std::cmp::max;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::ops::RangeInclusive;
+
+/// Kind of calculation that frames are balanced for. Each one is calibrated separately, since
+/// their iterations take different amounts of time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Workload {
+    /// Size of the numbers in words
+    pub word_count: usize,
+    /// The derivative of z is tracked along with every iteration
+    pub distance_estimation: bool,
+}
+
+pub struct FpsBalancer<C: Clock = SystemClock> {
+    /// Iteration limit for full redraws
+    present_iterations: BTreeMap<Workload, u32>,
+
+    /// Iteration limit for next iterations
+    pub iteration_iterations: u32,
+
+    /// FPS target that balancer tries to reach
+    target_ms_per_iter: f64,
+
+    calibration_state: Option<(Workload, u32)>,
+    present_iteration_limit: BTreeMap<Workload, u32>,
+
+    /// Fixed iteration count that replaces balancing in deterministic mode
+    fixed_iterations: Option<u32>,
+
+    /// Frame timer
+    timer: Option<FrameTimer>,
+
+    /// Iterations per second of the last timed iteration frame
+    iteration_rate: Option<f64>,
+
+    /// Recent times of each kind of frame
+    present_times: BTreeMap<Workload, FrameTimes>,
+    calibration_times: BTreeMap<Workload, FrameTimes>,
+    iteration_times: FrameTimes,
+
+    /// Pixels of the frames the iterations are balanced for
+    pixels: Option<f64>,
+    /// Workloads whose limits were restored from an earlier run and haven't been presented since
+    restored: BTreeSet<Workload>,
+
+    /// Source of frame times
+    clock: C,
+}
+
+/// Frame rates the balancer can aim for
+pub const TARGET_FPS_RANGE: RangeInclusive<f64> = 5.0..=120.0;
+
+const UNCALIBRATED_LIMIT: u32 = 15;
+const PRESENTATION_DEFAULT: u32 = 10;
+
+/// Frame times that the moving average is taken over
+const HISTORY: usize = 8;
+/// Weight of the latest frame in the moving average
+const AVERAGE_WEIGHT: f64 = 0.3;
+/// Frames slower than this many times the median of the recent ones are stalls that are left out
+/// of the average
+const OUTLIER_RATIO: f64 = 3.0;
+/// Frames expected within this fraction of the target keep their iterations
+const TOLERANCE: f64 = 0.1;
+/// Presented frames calibrate the balancer if at least this fraction of their workgroups were
+/// still iterating. Frames of views that mostly escape at once tell little about the speed of the
+/// iterations
+const MIN_LIVE_ACTIVITY: f64 = 0.5;
+/// Presented frames of restored limits that take this many times longer than the target are
+/// calibrated again
+const STALE_RATIO: f64 = 2.0;
+
+impl FpsBalancer {
+    pub const UNCALIBRATED_LIMIT: u32 = UNCALIBRATED_LIMIT;
+
+    pub fn new(target_fps: f64) -> Self {
+        Self::with_clock(target_fps, SystemClock::default())
+    }
+
+    /// Creates a balancer that always uses `iterations` per frame and never calibrates, making the
+    /// sequence of calculated depths independent of timing
+    pub fn fixed(iterations: u32) -> Self {
+        Self {
+            iteration_iterations: iterations,
+            fixed_iterations: Some(iterations),
+            ..Self::new(1.0)
+        }
+    }
+}
+
+impl<C: Clock> FpsBalancer<C> {
+    pub fn with_clock(target_fps: f64, clock: C) -> Self {
+        let target_ms_per_iter = 1000.0 / target_fps;
+        Self {
+            target_ms_per_iter,
+            present_iterations: Default::default(),
+            iteration_iterations: PRESENTATION_DEFAULT,
+            calibration_state: None,
+            present_iteration_limit: Default::default(),
+            fixed_iterations: None,
+            timer: None,
+            iteration_rate: None,
+            present_times: Default::default(),
+            calibration_times: Default::default(),
+            iteration_times: Default::default(),
+            pixels: None,
+            restored: Default::default(),
+            clock,
+        }
+    }
+
+    /// Returns the frame time the balancer aims for
+    pub fn target_frame_ms(&self) -> f64 {
+        self.target_ms_per_iter
+    }
+
+    /// Aims for a new frame rate. Iterations take as long as before, so the calibrated and
+    /// balanced iterations are rescaled to the new frame time instead of calibrating again
+    pub fn set_target_fps(&mut self, target_fps: f64) {
+        let target_ms_per_iter = 1000.0 / target_fps;
+        let ratio = target_ms_per_iter / self.target_ms_per_iter;
+        self.target_ms_per_iter = target_ms_per_iter;
+        self.rescale(ratio);
+    }
+
+    /// Balances the frames for views of `pixels`. Iterations take as much longer as there are
+    /// more pixels, so the calibration is rescaled
+    pub fn set_pixels(&mut self, pixels: f64) {
+        if let Some(previous) = self.pixels.replace(pixels) {
+            self.rescale(previous / pixels);
+        }
+    }
+
+    fn rescale(&mut self, ratio: f64) {
+        if self.fixed_iterations.is_some() || ratio == 1.0 {
+            return;
+        }
+        // At least 1 iteration per frame
+        let rescale = |iterations: &mut u32| {
+            *iterations = max((*iterations as f64 * ratio).round() as u32, 1);
+        };
+        self.present_iterations.values_mut().for_each(rescale);
+        self.present_iteration_limit.values_mut().for_each(rescale);
+        if let Some((_, limit)) = &mut self.calibration_state {
+            rescale(limit);
+        }
+        rescale(&mut self.iteration_iterations);
+    }
+
+    /// Returns the calibrated speed of every workload in milliseconds per iteration of a pixel,
+    /// which doesn't depend on the frame rate or the size of the views
+    pub fn calibration(&self) -> Vec<(Workload, f64)> {
+        let Some(pixels) = self.pixels.filter(|_| self.fixed_iterations.is_none()) else {
+            return Vec::new();
+        };
+        self.present_iteration_limit
+            .iter()
+            .map(|(&workload, &limit)| {
+                (workload, self.target_ms_per_iter / (limit as f64 * pixels))
+            })
+            .collect()
+    }
+
+    /// Takes over the speeds of an earlier `calibration` for the workloads that aren't
+    /// calibrated yet. They are calibrated again if their first presented frame is too slow
+    pub fn restore_calibration(&mut self, calibration: impl IntoIterator<Item = (Workload, f64)>) {
+        let Some(pixels) = self.pixels.filter(|_| self.fixed_iterations.is_none()) else {
+            return;
+        };
+        for (workload, ms) in calibration {
+            if self.is_calibrated(workload) || !ms.is_finite() || ms <= 0.0 {
+                continue;
+            }
+            // At least 1 iteration per frame
+            let limit = max((self.target_ms_per_iter / (ms * pixels)).round() as u32, 1);
+            self.present_iteration_limit.insert(workload, limit);
+            self.restored.insert(workload);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.present_iterations = Default::default();
+        self.iteration_iterations = self.fixed_iterations.unwrap_or(PRESENTATION_DEFAULT);
+        self.calibration_state = None;
+        self.present_iteration_limit = Default::default();
+        self.timer = None;
+        self.iteration_rate = None;
+        self.present_times = Default::default();
+        self.calibration_times = Default::default();
+        self.iteration_times = Default::default();
+        self.restored = Default::default();
+    }
+
+    pub fn start_presentation_frame(&mut self, workload: Workload) {
+        self.timer = Some(FrameTimer::Presentation(TimerInfo {
+            start: self.clock.now(),
+            workload,
+        }));
+    }
+
+    /// Starts timing a calibration frame and returns its iterations. Calibration frames cover
+    /// fewer pixels than the presented ones, `scale` is the ratio of their pixels that the
+    /// measured time is multiplied by
+    pub fn start_calibration_frame(&mut self, workload: Workload, scale: f64) -> u32 {
+        let (size, lim) = self.calibration_state.get_or_insert((workload, 5));
+        if *size != workload {
+            *size = workload;
+            *lim = 5;
+        }
+        let lim = *lim;
+        self.timer = Some(FrameTimer::Calibration(
+            TimerInfo {
+                start: self.clock.now(),
+                workload,
+            },
+            scale,
+        ));
+        lim
+    }
+
+    pub fn start_iteration_frame(&mut self) {
+        self.timer = Some(FrameTimer::Iteration(self.clock.now()));
+    }
+
+    /// Returns how many iterations per second the last timed iteration frame has calculated
+    pub fn iteration_rate(&self) -> Option<f64> {
+        self.iteration_rate
+    }
+
+    /// Returns how long a frame of `iterations` is expected to take at the last iteration rate
+    pub fn expected_frame_ms(&self, iterations: u32) -> Option<f64> {
+        self.iteration_rate
+            .map(|rate| iterations as f64 * 1000.0 / rate)
+    }
+
+    /// Calibrates `workload` from the time of its presented frames, of which `activity` is the
+    /// fraction of workgroups that were still iterating. Returns false if there are no such
+    /// frames or they escape too early to tell, which leaves it to the calibration frames
+    pub fn calibrate_from_frames(&mut self, workload: Workload, activity: f64) -> bool {
+        if self.is_calibrated(workload) || activity.is_nan() || activity < MIN_LIVE_ACTIVITY {
+            return false;
+        }
+        let Some(ms) = self.present_times.get(&workload).and_then(|t| t.average) else {
+            return false;
+        };
+        // The idle workgroups would take as long as the iterating ones
+        let full_ms = ms / activity.min(1.0);
+        let limit = if full_ms > 0.0 {
+            max((self.target_ms_per_iter / full_ms).round() as u32, 1)
+        } else {
+            UNCALIBRATED_LIMIT
+        };
+        log::info!(
+            "present limit: max {} at {:?} from frames",
+            limit * 3,
+            workload
+        );
+        self.present_iteration_limit.insert(workload, limit);
+        self.calibration_state = None;
+        true
+    }
+
+    pub fn is_calibrated(&self, workload: Workload) -> bool {
+        self.fixed_iterations.is_some() || self.present_iteration_limit.contains_key(&workload)
+    }
+
+    /// Ends the timed frame. Its time is measured with the clock, unless the GPU time of its
+    /// work `gpu_ms` is known, which leaves out the delays of scheduling and polling
+    pub fn end_frame(&mut self, gpu_ms: Option<f64>) {
+        let timer = self.timer.take();
+        if self.fixed_iterations.is_some() {
+            return;
+        }
+        let now = self.clock.now();
+        let elapsed = |start| gpu_ms.unwrap_or(now - start);
+        match timer {
+            Some(FrameTimer::Presentation(TimerInfo { start, workload })) => {
+                let frame_time = elapsed(start);
+
+                let present_iterations = &self
+                    .present_iterations
+                    .get(&workload)
+                    .copied()
+                    .unwrap_or(PRESENTATION_DEFAULT);
+
+                let dispatched = self.present_iterations(workload);
+                let expected = self
+                    .present_times
+                    .entry(workload)
+                    .or_default()
+                    .add(frame_time / dispatched as f64)
+                    * dispatched as f64;
+                if self.restored.remove(&workload)
+                    && expected > self.target_ms_per_iter * STALE_RATIO
+                {
+                    log::info!("Restored limit of {:?} is stale, calibrating", workload);
+                    self.present_iteration_limit.remove(&workload);
+                }
+                let correction = tolerant_correction(self.target_ms_per_iter, expected);
+
+                let iterations = ((*present_iterations as f64 * correction).round() as u32)
+                    .min(self.present_iteration_limit(workload));
+
+                self.present_iterations.insert(workload, iterations);
+                self.iteration_iterations = self.present_iterations(workload);
+                // Iteration frames start over from the presented ones, which may be of another
+                // workload
+                self.iteration_times = Default::default();
+                log::info!("present: {}", self.iteration_iterations);
+            }
+            Some(FrameTimer::Calibration(TimerInfo { start, workload }, scale)) => {
+                if let Some((calibration_workload, limit)) = self.calibration_state.take() {
+                    if workload != calibration_workload {
+                        return;
+                    }
+                    let frame_time = elapsed(start) * scale;
+                    let expected = self
+                        .calibration_times
+                        .entry(workload)
+                        .or_default()
+                        .add(frame_time / limit as f64)
+                        * limit as f64;
+
+                    let correction = iteration_correction(self.target_ms_per_iter, expected);
+                    // At least 1 iteration per frame
+                    let new_limit = max((limit as f64 * correction).round() as u32, 1);
+
+                    // Rounding may leave the limit stuck outside of the window, it's still the
+                    // closest one to the target
+                    if (0.98 < correction && correction < 1.02) || new_limit == limit {
+                        log::info!("present limit: max {} at {:?}", new_limit * 3, workload);
+                        self.present_iteration_limit.insert(workload, new_limit);
+                    } else {
+                        self.calibration_state = Some((workload, new_limit));
+                    }
+                }
+            }
+            Some(FrameTimer::Iteration(start)) => {
+                let frame_time = elapsed(start);
+                if frame_time > 0.0 {
+                    self.iteration_rate =
+                        Some(self.iteration_iterations as f64 * 1000.0 / frame_time);
+                }
+                let iterations = self.iteration_iterations.max(1) as f64;
+                let expected = self.iteration_times.add(frame_time / iterations) * iterations;
+                let correction = tolerant_correction(self.target_ms_per_iter, expected);
+                let new_iteration_count =
+                    (self.iteration_iterations as f64 * correction).round() as u32;
+                // At least 1 iteration per frame
+                self.iteration_iterations = max(new_iteration_count, 1);
+                log::debug!("iteration: {}", self.iteration_iterations);
+            }
+            None => {}
+        }
+    }
+
+    pub fn present_iterations(&self, workload: Workload) -> u32 {
+        if let Some(iterations) = self.fixed_iterations {
+            return iterations;
+        }
+        self.present_iterations
+            .get(&workload)
+            .copied()
+            .unwrap_or(PRESENTATION_DEFAULT)
+            .max(1)
+            .min(self.present_iteration_limit(workload))
+    }
+
+    fn present_iteration_limit(&self, workload: Workload) -> u32 {
+        self.present_iteration_limit
+            .get(&workload)
+            .copied()
+            .map(|l| l * 3)
+            .unwrap_or(UNCALIBRATED_LIMIT)
+            .max(1)
+    }
+}
+
+enum FrameTimer {
+    Presentation(TimerInfo),
+    /// Calibration frame with the ratio of the presented pixels to its own
+    Calibration(TimerInfo, f64),
+    Iteration(f64),
+}
+
+struct TimerInfo {
+    /// Frame start time in milliseconds
+    start: f64,
+    workload: Workload,
+}
+
+/// Recent times per iteration of one kind of frame. Their moving average stands in for the time of
+/// the last frame, so that a single stall doesn't halve the iterations for the next frame to
+/// double them again
+#[derive(Debug, Clone, Default)]
+struct FrameTimes {
+    /// Last samples including the stalls, so that the median follows lasting slowdowns
+    recent: VecDeque<f64>,
+    average: Option<f64>,
+}
+
+impl FrameTimes {
+    /// Adds the time per iteration of a frame and returns the average
+    fn add(&mut self, ms: f64) -> f64 {
+        let stall = median(&self.recent).is_some_and(|m| m > 0.0 && ms > m * OUTLIER_RATIO);
+        if self.recent.len() == HISTORY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(ms);
+        let average = match self.average {
+            Some(average) if stall => average,
+            Some(average) => average + (ms - average) * AVERAGE_WEIGHT,
+            None => ms,
+        };
+        self.average = Some(average);
+        average
+    }
+}
+
+fn median(samples: &VecDeque<f64>) -> Option<f64> {
+    let mut sorted: Vec<f64> = samples.iter().copied().collect();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((sorted[middle - 1] + sorted[middle]) / 2.0),
+        _ => Some(sorted[middle]),
+    }
+}
+
+/// Keeps the iterations of frames expected within the tolerance of the target, where adjusting
+/// them would only follow the noise
+fn tolerant_correction(target_ms: f64, expected_ms: f64) -> f64 {
+    if (expected_ms / target_ms - 1.0).abs() <= TOLERANCE {
+        1.0
+    } else {
+        iteration_correction(target_ms, expected_ms)
+    }
+}
+
+fn iteration_correction(target_ms: f64, actual_ms: f64) -> f64 {
+    if actual_ms > 0.0 {
+        // Smooth multiplier by reducing it to 50%
+        (target_ms / actual_ms - 1.0) * 0.5 + 1.0
+    } else {
+        // Avoid infinity, double the iteration count if frame time is faster than timer detects
+        2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Clock that only moves when told to
+    #[derive(Clone, Default)]
+    struct FakeClock(Rc<Cell<f64>>);
+
+    impl FakeClock {
+        fn advance(&self, ms: f64) {
+            self.0.set(self.0.get() + ms);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> f64 {
+            self.0.get()
+        }
+    }
+
+    const TARGET_MS: f64 = 1000.0 / 30.0;
+
+    fn words(word_count: usize) -> Workload {
+        Workload {
+            word_count,
+            distance_estimation: false,
+        }
+    }
+
+    fn balancer() -> (FpsBalancer<FakeClock>, FakeClock) {
+        let clock = FakeClock::default();
+        (FpsBalancer::with_clock(30.0, clock.clone()), clock)
+    }
+
+    /// Runs calibration frames that take `ms_per_iteration` per iteration. Returns the amount of
+    /// frames it took
+    fn calibrate(
+        balancer: &mut FpsBalancer<FakeClock>,
+        clock: &FakeClock,
+        workload: Workload,
+        ms_per_iteration: f64,
+    ) -> usize {
+        let mut frames = 0;
+        while !balancer.is_calibrated(workload) {
+            assert!(frames < 100, "calibration didn't converge");
+            let iterations = balancer.start_calibration_frame(workload, 1.0);
+            clock.advance(iterations as f64 * ms_per_iteration);
+            balancer.end_frame(None);
+            frames += 1;
+        }
+        frames
+    }
+
+    #[test]
+    fn calibration_converges() {
+        for ms_per_iteration in [0.001, 0.1, 1.0, 6.0, 10.0, 20.0, 100.0] {
+            let (mut balancer, clock) = balancer();
+            calibrate(&mut balancer, &clock, words(2), ms_per_iteration);
+
+            let limit = balancer.present_iteration_limit(words(2)) / 3;
+            let ideal = TARGET_MS / ms_per_iteration;
+            if ideal >= 1.0 {
+                // Within the acceptance window, widened by rounding at small limits
+                let frame_ms = limit as f64 * ms_per_iteration;
+                assert!(
+                    (frame_ms - TARGET_MS).abs() <= TARGET_MS * 0.04 + ms_per_iteration,
+                    "{ms_per_iteration} ms per iteration: limit {limit}, ideal {ideal}"
+                );
+            } else {
+                assert_eq!(limit, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn small_calibration_frames_are_scaled() {
+        let (mut full, clock) = balancer();
+        calibrate(&mut full, &clock, words(2), 1.0);
+
+        // A frame of a quarter of the pixels takes a quarter of the time
+        let (mut small, clock) = balancer();
+        while !small.is_calibrated(words(2)) {
+            let iterations = small.start_calibration_frame(words(2), 4.0);
+            clock.advance(iterations as f64 * 0.25);
+            small.end_frame(None);
+        }
+        assert_eq!(
+            small.present_iteration_limit(words(2)),
+            full.present_iteration_limit(words(2))
+        );
+    }
+
+    #[test]
+    fn gpu_time_replaces_the_clock() {
+        let (mut balancer, clock) = balancer();
+        // Delayed polling doesn't slow the iterations down when the GPU time is known
+        balancer.start_iteration_frame();
+        clock.advance(TARGET_MS * 4.0);
+        balancer.end_frame(Some(TARGET_MS));
+        assert_eq!(balancer.iteration_iterations, PRESENTATION_DEFAULT);
+        assert_eq!(
+            balancer.iteration_rate(),
+            Some(PRESENTATION_DEFAULT as f64 * 1000.0 / TARGET_MS)
+        );
+
+        balancer.start_iteration_frame();
+        clock.advance(TARGET_MS * 2.0);
+        balancer.end_frame(None);
+        assert!(balancer.iteration_iterations < PRESENTATION_DEFAULT);
+    }
+
+    #[test]
+    fn zero_frame_time_doubles() {
+        assert_eq!(iteration_correction(TARGET_MS, 0.0), 2.0);
+        assert!(iteration_correction(TARGET_MS, 1e9) > 0.5);
+
+        let (mut balancer, _clock) = balancer();
+        balancer.start_iteration_frame();
+        balancer.end_frame(None);
+        assert_eq!(balancer.iteration_iterations, PRESENTATION_DEFAULT * 2);
+    }
+
+    #[test]
+    fn iterations_are_clamped() {
+        let (mut balancer, clock) = balancer();
+
+        // Slow frames never go below 1 iteration
+        for _ in 0..20 {
+            balancer.start_iteration_frame();
+            clock.advance(10_000.0);
+            balancer.end_frame(None);
+        }
+        assert_eq!(balancer.iteration_iterations, 1);
+
+        // Fast presentation frames never go above the uncalibrated limit
+        for _ in 0..20 {
+            balancer.start_presentation_frame(words(2));
+            balancer.end_frame(None);
+        }
+        assert_eq!(balancer.present_iterations(words(2)), UNCALIBRATED_LIMIT);
+
+        // Or above 3 times the calibrated limit
+        calibrate(&mut balancer, &clock, words(2), 1.0);
+        let limit = balancer.present_iteration_limit(words(2));
+        for _ in 0..20 {
+            balancer.start_presentation_frame(words(2));
+            balancer.end_frame(None);
+        }
+        assert_eq!(balancer.present_iterations(words(2)), limit);
+    }
+
+    #[test]
+    fn word_counts_are_independent() {
+        let (mut balancer, clock) = balancer();
+        calibrate(&mut balancer, &clock, words(2), 0.5);
+        assert!(balancer.is_calibrated(words(2)));
+        assert!(!balancer.is_calibrated(words(3)));
+
+        calibrate(&mut balancer, &clock, words(3), 2.0);
+        assert!(
+            balancer.present_iteration_limit(words(2)) > balancer.present_iteration_limit(words(3))
+        );
+
+        // Interrupted calibration restarts for the new word count
+        balancer.start_calibration_frame(words(4), 1.0);
+        clock.advance(1.0);
+        assert_eq!(balancer.start_calibration_frame(words(5), 1.0), 5);
+
+        balancer.start_presentation_frame(words(2));
+        clock.advance(TARGET_MS / 4.0);
+        balancer.end_frame(None);
+        assert_eq!(balancer.present_iterations(words(3)), PRESENTATION_DEFAULT);
+    }
+
+    #[test]
+    fn distance_estimation_is_calibrated_separately() {
+        let (mut balancer, clock) = balancer();
+        calibrate(&mut balancer, &clock, words(2), 0.5);
+        let estimated = Workload {
+            distance_estimation: true,
+            ..words(2)
+        };
+        assert!(!balancer.is_calibrated(estimated));
+
+        calibrate(&mut balancer, &clock, estimated, 1.0);
+        assert!(
+            balancer.present_iteration_limit(words(2))
+                > balancer.present_iteration_limit(estimated)
+        );
+    }
+
+    #[test]
+    fn iteration_rate_follows_the_frame_time() {
+        let (mut balancer, clock) = balancer();
+        assert_eq!(balancer.iteration_rate(), None);
+
+        balancer.start_iteration_frame();
+        clock.advance(20.0);
+        balancer.end_frame(None);
+        assert_eq!(
+            balancer.iteration_rate(),
+            Some(PRESENTATION_DEFAULT as f64 * 50.0)
+        );
+
+        // Frames too fast for the timer keep the last rate
+        balancer.start_iteration_frame();
+        balancer.end_frame(None);
+        assert_eq!(
+            balancer.iteration_rate(),
+            Some(PRESENTATION_DEFAULT as f64 * 50.0)
+        );
+    }
+
+    #[test]
+    fn expected_frame_time_follows_the_rate() {
+        let (mut balancer, clock) = balancer();
+        assert_eq!(balancer.expected_frame_ms(100), None);
+
+        balancer.start_iteration_frame();
+        clock.advance(20.0);
+        balancer.end_frame(None);
+        let expected = balancer
+            .expected_frame_ms(PRESENTATION_DEFAULT * 3)
+            .unwrap();
+        assert!((expected - 60.0).abs() < 1e-9);
+    }
+
+    /// Deterministic factors within `1 ± amplitude`
+    fn noise(amplitude: f64) -> impl FnMut() -> f64 {
+        let mut state = 0x2545_f491_u64;
+        move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let unit = (state >> 11) as f64 / (1u64 << 53) as f64;
+            1.0 + (unit * 2.0 - 1.0) * amplitude
+        }
+    }
+
+    #[test]
+    fn stalls_are_left_out() {
+        let mut times = FrameTimes::default();
+        for _ in 0..HISTORY {
+            assert_eq!(times.add(1.0), 1.0);
+        }
+        assert_eq!(times.add(10.0), 1.0);
+        assert_eq!(times.add(0.5), 0.85);
+
+        // Lasting slowdowns become the median and are followed
+        let mut times = FrameTimes::default();
+        times.add(1.0);
+        let averages: Vec<f64> = (0..HISTORY).map(|_| times.add(5.0)).collect();
+        assert_eq!(averages[0], 1.0);
+        assert!(averages[HISTORY - 1] > 4.0);
+    }
+
+    #[test]
+    fn noisy_iterations_stay_in_band() {
+        let (mut balancer, clock) = balancer();
+        let mut noise = noise(0.2);
+        let mut iterations = Vec::new();
+        for frame in 0..200 {
+            balancer.start_iteration_frame();
+            // 1 ms per iteration, with a 10 times longer stall every 25 frames
+            let stall = if frame % 25 == 24 { 10.0 } else { 1.0 };
+            clock.advance(balancer.iteration_iterations as f64 * noise() * stall);
+            balancer.end_frame(None);
+            iterations.push(balancer.iteration_iterations);
+        }
+        let ideal = TARGET_MS as u32;
+        for &count in &iterations[20..] {
+            assert!(
+                (ideal * 3 / 4..=ideal * 5 / 4).contains(&count),
+                "{count} iterations, ideal {ideal}"
+            );
+        }
+        // The budget settles instead of following every frame
+        let changes = iterations[20..].windows(2).filter(|w| w[0] != w[1]).count();
+        assert!(changes < 40, "{changes} changes");
+    }
+
+    #[test]
+    fn noisy_calibration_converges() {
+        for ms_per_iteration in [0.1, 1.0, 6.0] {
+            let (mut balancer, clock) = balancer();
+            let mut noise = noise(0.1);
+            let mut frames = 0;
+            while !balancer.is_calibrated(words(2)) {
+                assert!(frames < 100, "calibration didn't converge");
+                let iterations = balancer.start_calibration_frame(words(2), 1.0);
+                clock.advance(iterations as f64 * ms_per_iteration * noise());
+                balancer.end_frame(None);
+                frames += 1;
+            }
+            let limit = balancer.present_iteration_limit(words(2)) / 3;
+            let frame_ms = limit as f64 * ms_per_iteration;
+            assert!(
+                (frame_ms - TARGET_MS).abs() <= TARGET_MS * 0.15 + ms_per_iteration,
+                "{ms_per_iteration} ms per iteration: limit {limit}"
+            );
+        }
+    }
+
+    #[test]
+    fn target_changes_keep_the_calibration() {
+        let (mut balancer, clock) = balancer();
+        calibrate(&mut balancer, &clock, words(2), 1.0);
+        for _ in 0..20 {
+            balancer.start_presentation_frame(words(2));
+            clock.advance(balancer.present_iterations(words(2)) as f64);
+            balancer.end_frame(None);
+        }
+        let presented = balancer.present_iterations(words(2));
+
+        balancer.set_target_fps(60.0);
+        assert!(balancer.is_calibrated(words(2)));
+        assert_eq!(balancer.target_frame_ms(), 1000.0 / 60.0);
+        assert_eq!(
+            balancer.present_iterations(words(2)),
+            (presented as f64 / 2.0).round() as u32
+        );
+
+        let mut fixed = FpsBalancer::fixed(7);
+        fixed.set_target_fps(60.0);
+        assert_eq!(fixed.iteration_iterations, 7);
+    }
+
+    #[test]
+    fn resizes_rescale_the_calibration() {
+        let (mut balancer, clock) = balancer();
+        balancer.set_pixels(1000.0);
+        calibrate(&mut balancer, &clock, words(2), 1.0);
+        let limit = balancer.present_iteration_limit(words(2)) / 3;
+
+        balancer.set_pixels(2000.0);
+        assert!(balancer.is_calibrated(words(2)));
+        assert_eq!(
+            balancer.present_iteration_limit(words(2)) / 3,
+            (limit as f64 / 2.0).round() as u32
+        );
+    }
+
+    #[test]
+    fn calibration_is_restored() {
+        let (mut calibrated, clock) = balancer();
+        calibrated.set_pixels(1000.0);
+        calibrate(&mut calibrated, &clock, words(2), 1.0);
+        let limit = calibrated.present_iteration_limit(words(2)) / 3;
+        let calibration = calibrated.calibration();
+        assert_eq!(calibration.len(), 1);
+
+        // Twice the frame rate on four times the pixels
+        let (mut restored, clock) = balancer();
+        restored.set_target_fps(60.0);
+        restored.set_pixels(4000.0);
+        restored.restore_calibration(calibration.clone());
+        assert!(restored.is_calibrated(words(2)));
+        assert!(!restored.is_calibrated(words(3)));
+        assert_eq!(
+            restored.present_iteration_limit(words(2)) / 3,
+            (limit as f64 / 8.0).round() as u32
+        );
+
+        // Restored limits that are far off are calibrated again
+        restored.start_presentation_frame(words(2));
+        clock.advance(TARGET_MS * 3.0);
+        restored.end_frame(None);
+        assert!(!restored.is_calibrated(words(2)));
+
+        // Limits that hold are kept
+        let (mut restored, clock) = balancer();
+        restored.set_pixels(1000.0);
+        restored.restore_calibration(calibration);
+        restored.start_presentation_frame(words(2));
+        clock.advance(TARGET_MS);
+        restored.end_frame(None);
+        assert!(restored.is_calibrated(words(2)));
+    }
+
+    #[test]
+    fn presented_frames_calibrate() {
+        let (mut balancer, clock) = balancer();
+        assert!(!balancer.calibrate_from_frames(words(2), 1.0));
+
+        // Half the workgroups iterating at 0.5 ms per iteration are as slow as all of them at 1
+        balancer.start_presentation_frame(words(2));
+        clock.advance(PRESENTATION_DEFAULT as f64 * 0.5);
+        balancer.end_frame(None);
+        assert!(!balancer.calibrate_from_frames(words(2), 0.2));
+        assert!(!balancer.is_calibrated(words(2)));
+        assert!(balancer.calibrate_from_frames(words(2), 0.5));
+        assert!(balancer.is_calibrated(words(2)));
+        assert_eq!(
+            balancer.present_iteration_limit(words(2)) / 3,
+            TARGET_MS.round() as u32
+        );
+        assert!(!balancer.calibrate_from_frames(words(2), 1.0));
+
+        assert!(!balancer.calibrate_from_frames(words(3), 1.0));
+    }
+
+    #[test]
+    fn fixed_ignores_timing() {
+        let mut balancer = FpsBalancer::fixed(7);
+        assert!(balancer.is_calibrated(words(2)));
+        balancer.start_iteration_frame();
+        balancer.end_frame(None);
+        assert_eq!(balancer.iteration_iterations, 7);
+        assert_eq!(balancer.present_iterations(words(5)), 7);
+    }
+}
@@ -0,0 +1,297 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::float::WideFloat;
+
+/// Iteration formula of the fractal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FractalKind {
+    /// z = z^2 + c
+    #[default]
+    Mandelbrot,
+    /// z = (|Re(z^2)| + i Im(z^2)) + c
+    Celtic,
+    /// z = (Re(z) + i |Im(z)|)^2 + c, with the imaginary axis pointing down
+    PerpendicularBurningShip,
+}
+
+impl FractalKind {
+    pub const ALL: [FractalKind; 3] = [
+        FractalKind::Mandelbrot,
+        FractalKind::Celtic,
+        FractalKind::PerpendicularBurningShip,
+    ];
+
+    /// Returns the stable identifier of the kind used in saved files
+    pub fn name(&self) -> &'static str {
+        match self {
+            FractalKind::Mandelbrot => "mandelbrot",
+            FractalKind::Celtic => "celtic",
+            FractalKind::PerpendicularBurningShip => "perpendicular_burning_ship",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// Returns the identifier of the kind used by the compute shader
+    pub fn shader_id(&self) -> u32 {
+        match self {
+            FractalKind::Mandelbrot => 0,
+            FractalKind::Celtic => 1,
+            FractalKind::PerpendicularBurningShip => 2,
+        }
+    }
+
+    fn default_framing(&self) -> Framing {
+        match self {
+            FractalKind::Mandelbrot => Framing {
+                x: 0.0,
+                y: 0.0,
+                span: 4.0,
+            },
+            FractalKind::Celtic | FractalKind::PerpendicularBurningShip => Framing {
+                x: -0.5,
+                y: 0.0,
+                span: 4.0,
+            },
+        }
+    }
+}
+
+impl fmt::Display for FractalKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FractalKind::Mandelbrot => "Mandelbrot",
+            FractalKind::Celtic => "Celtic",
+            FractalKind::PerpendicularBurningShip => "Perpendicular Burning Ship",
+        })
+    }
+}
+
+/// Exponents of z selectable for the iteration formula
+pub const POWER_RANGE: RangeInclusive<u32> = 2..=8;
+
+/// Parameters that select which fractal is calculated, as opposed to which part of it is viewed
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FractalParams {
+    /// Iteration formula
+    pub kind: FractalKind,
+    /// View the plane under the inversion `w = 1/c`
+    pub inverted: bool,
+    /// Exponent of z in the iteration formula, within [`POWER_RANGE`]. Squares are the usual sets,
+    /// higher powers are multibrots
+    pub power: u32,
+    /// Calculate the Julia set of this point instead, the plane is then the starting `z`
+    pub julia: Option<JuliaSeed>,
+}
+
+impl Default for FractalParams {
+    fn default() -> Self {
+        Self {
+            kind: FractalKind::default(),
+            inverted: false,
+            power: 2,
+            julia: None,
+        }
+    }
+}
+
+/// Constant `c` of a Julia set
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JuliaSeed {
+    pub re: WideFloat,
+    pub im: WideFloat,
+}
+
+impl JuliaSeed {
+    /// Returns the seed of the point, none if it isn't finite
+    pub fn from_f64(re: f64, im: f64) -> Option<Self> {
+        Some(Self {
+            re: WideFloat::from_f64(re, 2).ok()?,
+            im: WideFloat::from_f64(im, 2).ok()?,
+        })
+    }
+
+    /// Returns the seed with `size` words, the precision of the coordinates it's calculated with
+    pub fn resized(&self, size: usize) -> Self {
+        let resize = |value: &WideFloat| {
+            let mut value = value.clone();
+            value.change_precision(size as isize - value.word_count() as isize);
+            value
+        };
+        Self {
+            re: resize(&self.re),
+            im: resize(&self.im),
+        }
+    }
+}
+
+/// Plane region shown after a position reset
+#[derive(Debug, Clone, Copy)]
+pub struct Framing {
+    /// Center X coordinate
+    pub x: f32,
+    /// Center Y coordinate
+    pub y: f32,
+    /// Plane extent across the shortest window side
+    pub span: f32,
+}
+
+impl FractalParams {
+    /// Returns the region that fits the whole fractal in the view
+    pub fn default_framing(&self) -> Framing {
+        if self.inverted {
+            // The exterior of the set maps to a bounded region that spans (-0.5, 4) on the real axis
+            Framing {
+                x: 1.75,
+                y: 0.0,
+                span: 5.0,
+            }
+        } else if self.julia.is_some() {
+            // Julia sets of the connected seeds fit in a disk of radius 2 around the origin
+            Framing {
+                x: 0.0,
+                y: 0.0,
+                span: 4.0,
+            }
+        } else {
+            self.kind.default_framing()
+        }
+    }
+
+    /// Returns the max amount of words supported by the fractal
+    pub fn max_word_count(&self) -> Option<usize> {
+        // The inversion is computed in f32, deeper zoom would only show pixelation
+        self.inverted.then_some(2)
+    }
+}
+
+/// Escape radii selectable in the control panel
+pub const ESCAPE_RADIUS_RANGE: RangeInclusive<f32> = 2.0..=1000.0;
+
+/// Parameters that decide when a point has escaped and what's calculated along the way, they
+/// change the results of the pixels but not the set itself
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationParams {
+    /// Distance from the origin past which z has escaped, within [`ESCAPE_RADIUS_RANGE`]
+    pub escape_radius: f32,
+    /// Also track the derivative of z to estimate the distance of escaped points to the set, so
+    /// that its boundary is drawn as thin lines. Slows down every iteration
+    pub distance_estimation: bool,
+}
+
+impl Default for IterationParams {
+    fn default() -> Self {
+        Self {
+            escape_radius: 2.0,
+            distance_estimation: false,
+        }
+    }
+}
+
+impl IterationParams {
+    /// Returns the escape radius used with `power`. Higher powers are limited to smaller radii, so
+    /// that the last step before the escape stays within the whole part of the numbers
+    pub fn escape_radius_for(&self, power: u32) -> f32 {
+        // The step multiplies radius^power by up to a few more bits, which have to fit into 31
+        let max = 2.0f32.powf(29.0 / power as f32);
+        self.escape_radius.min(max)
+    }
+}
+
+/// Orbit of a single point of the Mandelbrot set of z^2, iterated with the full precision of wide
+/// numbers. Perturbed pixels only iterate their difference to it, so that they can use floats
+#[derive(Debug, Clone)]
+pub struct ReferenceOrbit {
+    c: (WideFloat, WideFloat),
+    /// z after the last point, to continue the orbit from
+    z: (WideFloat, WideFloat),
+    /// z after every iteration as floats, starting with the point itself like the shaders
+    points: Vec<[f32; 2]>,
+    /// The last point is past the escape radius, the orbit doesn't continue
+    escaped: bool,
+}
+
+impl ReferenceOrbit {
+    /// Starts the orbit of the point `x`, `y`
+    pub fn new(x: WideFloat, y: WideFloat) -> Self {
+        Self {
+            z: (x.clone(), y.clone()),
+            c: (x, y),
+            points: Vec::new(),
+            escaped: false,
+        }
+    }
+
+    /// Iterates the orbit until it has `length` points, or until it escapes the squared escape
+    /// radius `bailout`. The first point past the radius is the last one
+    pub fn extend_to(&mut self, length: usize, bailout: f64) {
+        while self.points.len() < length && !self.escaped {
+            let (x, y) = &self.z;
+            let (re, im) = (x.as_f64_round(), y.as_f64_round());
+            self.points.push([re as f32, im as f32]);
+            if re * re + im * im >= bailout {
+                self.escaped = true;
+                break;
+            }
+            // Same steps as the shader: 2 * x * y = (x + y)^2 - x^2 - y^2
+            let (x2, y2) = (x.square(), y.square());
+            let cross = (x.clone() + y).square() - &x2 - &y2;
+            self.z = (x2 - &y2 + &self.c.0, cross + &self.c.1);
+        }
+    }
+
+    pub fn points(&self) -> &[[f32; 2]] {
+        &self.points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orbit(x: f64, y: f64, length: usize) -> ReferenceOrbit {
+        let wide = |value: f64| WideFloat::from_f64(value, 4).unwrap();
+        let mut orbit = ReferenceOrbit::new(wide(x), wide(y));
+        orbit.extend_to(length, 4.0);
+        orbit
+    }
+
+    #[test]
+    fn orbits_stop_at_the_escape() {
+        let mut escaping = orbit(1.0, 0.0, 100);
+        assert_eq!(escaping.points(), [[1.0, 0.0], [2.0, 0.0]]);
+        escaping.extend_to(200, 4.0);
+        assert_eq!(escaping.points().len(), 2);
+
+        let cycle = orbit(-1.0, 0.0, 5);
+        assert_eq!(
+            cycle.points(),
+            [
+                [-1.0, 0.0],
+                [0.0, 0.0],
+                [-1.0, 0.0],
+                [0.0, 0.0],
+                [-1.0, 0.0]
+            ]
+        );
+    }
+
+    #[test]
+    fn orbits_continue_where_they_stopped() {
+        let mut orbit = orbit(-0.5, 0.25, 10);
+        orbit.extend_to(300, 4.0);
+
+        let (mut x, mut y) = (-0.5f64, 0.25f64);
+        assert_eq!(orbit.points().len(), 300);
+        for point in orbit.points() {
+            assert!((point[0] as f64 - x).abs() < 1e-4 && (point[1] as f64 - y).abs() < 1e-4);
+            (x, y) = (x * x - y * y - 0.5, 2.0 * x * y + 0.25);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Splitting of the compute dispatch of a frame into bands of rows. Each band is submitted on its
+//! own, so that no single submission runs long enough to trip the GPU watchdog of the OS, which
+//! loses the device
+
+use std::ops::Range;
+
+/// Bands have at least this many rows, unless the views are shorter. Smaller bands would only add
+/// submissions whose overhead outweighs their work
+pub const MIN_BAND_ROWS: u32 = 64;
+
+/// Returns the rows of every band of a frame over `height` rows that is expected to take
+/// `expected_ms`, so that each band stays within `budget_ms`. Frames without an estimate are
+/// dispatched at once
+pub fn band_rows(height: u32, expected_ms: Option<f64>, budget_ms: f64) -> u32 {
+    let Some(expected_ms) = expected_ms else {
+        return height;
+    };
+    // Saturates for estimates that are not a number, which dispatch the frame at once
+    let bands = ((expected_ms / budget_ms).ceil() as u32).max(1);
+    height
+        .div_ceil(bands)
+        .clamp(MIN_BAND_ROWS.min(height), height)
+}
+
+/// Progress of the bands of a frame. It's started along with the frame and presented once the
+/// last band is submitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bands {
+    /// Rows of every band, the last one may have less
+    pub rows: u32,
+    /// Rows of the tallest dispatched view
+    pub height: u32,
+    /// Rows submitted so far
+    pub submitted: u32,
+    /// Rows whose submissions have completed
+    pub done: u32,
+}
+
+impl Bands {
+    pub fn new(rows: u32, height: u32) -> Self {
+        Self {
+            rows,
+            height,
+            submitted: 0,
+            done: 0,
+        }
+    }
+
+    /// Returns the rows of the band to submit next
+    pub fn next(&self) -> Range<u32> {
+        let end = if self.is_last() {
+            self.height
+        } else {
+            self.done + self.rows
+        };
+        self.done..end
+    }
+
+    /// Returns true if the next band is the last one, which is submitted with the frame
+    pub fn is_last(&self) -> bool {
+        self.height - self.done <= self.rows
+    }
+
+    /// Returns true if the next band is the first one
+    pub fn is_first(&self) -> bool {
+        self.done == 0
+    }
+
+    /// Returns true while a band is submitted and hasn't completed yet
+    pub fn in_flight(&self) -> bool {
+        self.submitted > self.done
+    }
+
+    pub fn submit(&mut self) {
+        self.submitted = self.next().end;
+    }
+
+    pub fn complete(&mut self) {
+        self.done = self.submitted;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bands_fit_the_budget() {
+        assert_eq!(band_rows(2160, None, 500.0), 2160);
+        assert_eq!(band_rows(2160, Some(100.0), 500.0), 2160);
+        assert_eq!(band_rows(2160, Some(2000.0), 500.0), 540);
+        assert_eq!(band_rows(2160, Some(2001.0), 500.0), 432);
+        // Bands don't get too small, and short views are dispatched at once
+        assert_eq!(band_rows(2160, Some(1e9), 500.0), MIN_BAND_ROWS);
+        assert_eq!(band_rows(50, Some(1e9), 500.0), 50);
+        assert_eq!(band_rows(0, Some(1e9), 500.0), 0);
+        assert_eq!(band_rows(2160, Some(f64::NAN), 500.0), 2160);
+    }
+
+    #[test]
+    fn bands_cover_the_rows() {
+        let mut bands = Bands::new(100, 250);
+        let mut covered = Vec::new();
+        while !bands.is_last() {
+            assert!(!bands.in_flight());
+            covered.push(bands.next());
+            bands.submit();
+            assert!(bands.in_flight());
+            bands.complete();
+        }
+        covered.push(bands.next());
+        assert_eq!(covered, [0..100, 100..200, 200..250]);
+
+        assert!(Bands::new(250, 250).is_last());
+        assert_eq!(Bands::new(250, 250).next(), 0..250);
+        assert!(Bands::new(0, 0).is_last());
+    }
+}
@@ -0,0 +1,214 @@
+//! Frames of a view captured into memory, without the control panel and the preview drawn over
+//! it
+
+use crate::primitives::ScaledDimensions;
+
+use super::render::RenderBindings;
+
+/// Format views are drawn into for a capture, which encodes their colors into sRGB
+pub(super) const CAPTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Pixels of a captured frame in sRGB, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Capture of a view, taken once the calculation in flight has completed
+pub(super) struct Capture {
+    /// Index of the captured view
+    pub view: usize,
+    sender: flume::Sender<RgbaImage>,
+    stage: Stage,
+}
+
+enum Stage {
+    /// Waits for the work in flight to complete
+    Requested,
+    /// The frame is copied to `buffer` by the submitted work
+    Copied {
+        buffer: wgpu::Buffer,
+        size: ScaledDimensions,
+    },
+    Mapping {
+        buffer: wgpu::Buffer,
+        size: ScaledDimensions,
+        result: flume::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    },
+}
+
+impl Capture {
+    pub fn new(view: usize, sender: flume::Sender<RgbaImage>) -> Self {
+        Self {
+            view,
+            sender,
+            stage: Stage::Requested,
+        }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        matches!(self.stage, Stage::Requested)
+    }
+
+    /// Draws the view of `bindings` and `size` with `pipeline`, which targets
+    /// [`CAPTURE_FORMAT`], and copies the frame to a buffer to map once submitted
+    pub fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        bindings: &RenderBindings,
+        size: ScaledDimensions,
+    ) {
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: CAPTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Capture Target View"),
+            ..Default::default()
+        });
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Readback"),
+            size: (padded_row(size.width) * size.height) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Capture"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, &bindings.bind_group, &[]);
+            rpass.draw(0..4, 0..1);
+        }
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row(size.width)),
+                    rows_per_image: None,
+                },
+            },
+            target.size(),
+        );
+        self.stage = Stage::Copied { buffer, size };
+    }
+
+    /// Starts mapping the frame copied by the work that has just been submitted
+    pub fn request_map(&mut self) {
+        let stage = std::mem::replace(&mut self.stage, Stage::Requested);
+        self.stage = match stage {
+            Stage::Copied { buffer, size } => {
+                let (sender, result) = flume::bounded(1);
+                buffer.slice(..).map_async(wgpu::MapMode::Read, move |r| {
+                    let _ = sender.send(r);
+                });
+                Stage::Mapping {
+                    buffer,
+                    size,
+                    result,
+                }
+            }
+            stage => stage,
+        };
+    }
+
+    /// Sends the pixels to the requester once they have been mapped. Returns true when the
+    /// capture is done, successfully or not. Devices have to be polled for mapping to complete
+    pub fn receive(&mut self) -> bool {
+        let Stage::Mapping {
+            buffer,
+            size,
+            result,
+        } = &self.stage
+        else {
+            return false;
+        };
+        match result.try_recv() {
+            Ok(Ok(())) => {
+                let mapped = buffer.slice(..).get_mapped_range();
+                let pixels = unpad_rows(&mapped, *size);
+                drop(mapped);
+                buffer.unmap();
+                // The requester may have stopped waiting
+                let _ = self.sender.send(RgbaImage {
+                    width: size.width,
+                    height: size.height,
+                    pixels,
+                });
+                true
+            }
+            Ok(Err(e)) => {
+                log::error!("Unable to read the captured frame: {}", e);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// Bytes a row of `width` pixels takes in a readback buffer, whose rows are aligned
+fn padded_row(width: u32) -> u32 {
+    (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// Returns the pixels of the padded rows of a frame of `size`
+fn unpad_rows(padded: &[u8], size: ScaledDimensions) -> Vec<u8> {
+    let row = (size.width * 4) as usize;
+    padded
+        .chunks_exact(padded_row(size.width) as usize)
+        .take(size.height as usize)
+        .flat_map(|padded_row| &padded_row[..row])
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padding_is_dropped() {
+        let size = ScaledDimensions {
+            width: 3,
+            height: 2,
+        };
+        assert_eq!(padded_row(3), 256);
+        assert_eq!(padded_row(64), 256);
+        assert_eq!(padded_row(65), 512);
+        let mut padded = vec![0; 512];
+        padded[..12].fill(1);
+        padded[256..268].fill(2);
+        let pixels = unpad_rows(&padded, size);
+        assert_eq!(pixels.len(), 24);
+        assert_eq!(pixels[..12], [1; 12]);
+        assert_eq!(pixels[12..], [2; 12]);
+    }
+}
@@ -1,18 +1,100 @@
+use crate::float::WideFloat;
+use crate::fractal::{FractalKind, FractalParams, IterationParams};
 use crate::primitives::{Coordinates, ScaledDimensions};
 
+use super::{grow_capacity, ROW_ALIGNMENT};
+
 #[derive(Debug, Clone)]
 pub struct ComputeParams<'c> {
     depth_limit: u32,
     reset: bool,
     size: ScaledDimensions,
+    fractal: &'c FractalParams,
+    iteration: IterationParams,
     coords: &'c Coordinates,
+    /// Pixels found inside the set stop iterating, the main cardioid and the period 2 bulb of the
+    /// Mandelbrot set aren't iterated at all
+    interior_checks: bool,
+    /// Iterations that the pixels handed over by the perturbation shader advance by in a dispatch,
+    /// zero without perturbation
+    repair: u32,
 }
 
+/// Count of the pixels found inside the set by periodicity checking, higher than any depth
+pub const SETTLED: u32 = u32::MAX;
+
+/// Count of the pixels handed over by the perturbation shader until the exact shader has caught up
+/// with the depth, drawn like the pixels that haven't escaped
+pub const GLITCHED: u32 = u32::MAX - 1;
+
 pub struct ComputeBindings {
     pub(super) bind_group: wgpu::BindGroup,
     pub(super) params_buffer: wgpu::Buffer,
-    pub(super) _intermediate_buffer: wgpu::Buffer,
+    pub(super) intermediate_buffer: wgpu::Buffer,
     pub(super) result_buffer: wgpu::Buffer,
+    /// Fraction of an iteration every escaped pixel has left until the next count
+    pub(super) escape_buffer: wgpu::Buffer,
+    /// Estimated distance of every escaped pixel to the set in pixels, only written with
+    /// distance estimation
+    pub(super) distance_buffer: wgpu::Buffer,
+    /// Count of active workgroups, settled pixels and the ones of them that were skipped, followed
+    /// by the finished flags of all workgroups
+    pub(super) tiles_buffer: wgpu::Buffer,
+    /// Sizes of the buffers used by the current view, the buffers may have room for more
+    pub(super) sizes: BufferSizes,
+}
+
+/// Sizes of the compute buffers in bytes. The escape and distance buffers have the size of the
+/// result buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferSizes {
+    pub params: u64,
+    pub intermediate: u64,
+    pub result: u64,
+    pub tiles: u64,
+}
+
+impl BufferSizes {
+    /// Returns the sizes used by a view of `dimensions` with numbers of `word_count` words
+    pub fn new(dimensions: ScaledDimensions, word_count: usize, workgroup_width: u32) -> Self {
+        let pixels = (dimensions.aligned_width(ROW_ALIGNMENT) * dimensions.height) as u64;
+        Self {
+            params: size_hint(word_count) as u64,
+            intermediate: intermediate_stride(word_count) as u64 * 4 * pixels,
+            result: 4 * pixels,
+            tiles: (12 + 4 * tile_count(dimensions, workgroup_width)) as u64,
+        }
+    }
+
+    /// Returns the capacity to allocate for `required` sizes. Buffers that have room keep their
+    /// capacity, the others grow to the required size, at least doubling up to `limit`, so that
+    /// a view growing in small steps is only reallocated a few times
+    pub fn grow(self, required: BufferSizes, limit: u64) -> Self {
+        // Bindings of storage buffers have to be aligned like copies
+        let grow = |capacity, required| {
+            grow_capacity(capacity, required, limit).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+        };
+        Self {
+            params: grow(self.params, required.params),
+            intermediate: grow(self.intermediate, required.intermediate),
+            result: grow(self.result, required.result),
+            tiles: grow(self.tiles, required.tiles),
+        }
+    }
+
+    /// Returns true if buffers of these sizes can be bound on a device of `limits`
+    pub fn fit(&self, limits: &wgpu::Limits) -> bool {
+        let limit = buffer_size_limit(limits);
+        [self.params, self.intermediate, self.result, self.tiles]
+            .iter()
+            .all(|&size| size <= limit)
+    }
+}
+
+/// Largest buffer that can be bound to the compute shader, aligned for copies
+fn buffer_size_limit(limits: &wgpu::Limits) -> u64 {
+    let limit = (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+    limit - limit % wgpu::COPY_BUFFER_ALIGNMENT
 }
 
 impl ComputeBindings {
@@ -50,43 +132,121 @@ impl ComputeBindings {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         }
     }
 
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(
         device: &wgpu::Device,
         layout: &wgpu::BindGroupLayout,
         dimensions: ScaledDimensions,
         word_count: usize,
+        workgroup_width: u32,
     ) -> UninitializedComputeBindings {
+        let sizes = BufferSizes::new(dimensions, word_count, workgroup_width);
+        UninitializedComputeBindings(Self::allocate(device, layout, sizes, sizes))
+    }
+
+    /// Creates the buffers with room for `capacity`, of which `sizes` are used
+    fn allocate(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sizes: BufferSizes,
+        capacity: BufferSizes,
+    ) -> Self {
         // Buffer to pass input parameters to the GPU
         let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Compute Params"),
-            size: size_hint(word_count) as u64,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            size: capacity.params,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         // Buffer with the cache for iterative computation
         let intermediate_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Compute Intermediate"),
-            size: (2 * word_count as u32 * 4 * dimensions.aligned_width(64) * dimensions.height)
-                as u64,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            size: capacity.intermediate,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         // Buffer with result produced by the GPU
         let result_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Compute Result"),
-            size: (4 * dimensions.aligned_width(64) * dimensions.height) as u64,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            size: capacity.result,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Buffer with the escape fractions, laid out like the result buffer
+        let escape_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Escape"),
+            size: capacity.result,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Buffer with the distance estimates, laid out like the result buffer
+        let distance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Distance"),
+            size: capacity.result,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Buffer with the state of workgroups, zeroed so that all of them start out unfinished
+        let tiles_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Tiles"),
+            size: capacity.tiles,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
+            label: Some("Compute BindGroup"),
             layout,
             entries: &[
                 wgpu::BindGroupEntry {
@@ -101,19 +261,107 @@ impl ComputeBindings {
                     binding: 2,
                     resource: intermediate_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: tiles_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: escape_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: distance_buffer.as_entire_binding(),
+                },
             ],
         });
 
-        UninitializedComputeBindings(Self {
+        Self {
             params_buffer,
-            _intermediate_buffer: intermediate_buffer,
+            intermediate_buffer,
             result_buffer,
+            escape_buffer,
+            distance_buffer,
+            tiles_buffer,
             bind_group,
-        })
+            sizes,
+        }
+    }
+
+    /// Sizes the buffers have room for, which may be larger than the used ones
+    pub fn capacity(&self) -> BufferSizes {
+        BufferSizes {
+            params: self.params_buffer.size(),
+            intermediate: self.intermediate_buffer.size(),
+            result: self.result_buffer.size(),
+            tiles: self.tiles_buffer.size(),
+        }
+    }
+
+    /// Resizes the bindings for `dimensions` and `word_count`. Buffers are only replaced if
+    /// they don't have room for the new size, by larger ones with headroom for further growth,
+    /// and kept on shrinking. Returns true if the buffers were replaced. Either way the contents
+    /// are left undefined and the parameters have to be written again
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        dimensions: ScaledDimensions,
+        word_count: usize,
+        workgroup_width: u32,
+    ) -> bool {
+        let sizes = BufferSizes::new(dimensions, word_count, workgroup_width);
+        let capacity = self.capacity();
+        let grown = capacity.grow(sizes, buffer_size_limit(&device.limits()));
+        self.sizes = sizes;
+        if grown == capacity {
+            return false;
+        }
+        *self = Self::allocate(device, layout, sizes, grown);
+        true
+    }
+
+    /// Replaces buffers larger than the used size by ones that fit it exactly, to give back the
+    /// memory kept since the view was larger. The contents are copied over by `encoder`, so the
+    /// calculation goes on. Returns true if the buffers were replaced
+    pub fn trim(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> bool {
+        let sizes = self.sizes;
+        if self.capacity() == sizes {
+            return false;
+        }
+        let trimmed = Self::allocate(device, layout, sizes, sizes);
+        let copies = [
+            (&self.params_buffer, &trimmed.params_buffer, sizes.params),
+            (
+                &self.intermediate_buffer,
+                &trimmed.intermediate_buffer,
+                sizes.intermediate,
+            ),
+            (&self.result_buffer, &trimmed.result_buffer, sizes.result),
+            (&self.escape_buffer, &trimmed.escape_buffer, sizes.result),
+            (
+                &self.distance_buffer,
+                &trimmed.distance_buffer,
+                sizes.result,
+            ),
+            (&self.tiles_buffer, &trimmed.tiles_buffer, sizes.tiles),
+        ];
+        for (from, to, size) in copies {
+            encoder.copy_buffer_to_buffer(from, 0, to, 0, size);
+        }
+        *self = trimmed;
+        true
     }
 
     pub fn write(&self, queue: &wgpu::Queue, params: &ComputeParams) {
         queue.write_buffer(&self.params_buffer, 0, &params.encode());
+        // Reset frames find the settled pixels again
+        self.write_settled(queue, 0);
     }
 
     pub fn write_iterate(&self, queue: &wgpu::Queue, depth_limit: u32) {
@@ -130,6 +378,277 @@ impl ComputeBindings {
         buffer[0..4].copy_from_slice(&bytemuck::cast::<_, [u8; 4]>(depth_limit));
         buffer[4..8].copy_from_slice(&[0, 0, 0, 1]);
         queue.write_buffer(&self.params_buffer, 0, &buffer);
+        self.write_settled(queue, 0);
+    }
+
+    /// Writes the iterations that the pixels handed over by the perturbation shader advance by in
+    /// the next dispatch
+    pub fn write_repair(&self, queue: &wgpu::Queue, iterations: u32) {
+        queue.write_buffer(
+            &self.params_buffer,
+            REPAIR_OFFSET,
+            &bytemuck::cast::<_, [u8; 4]>(iterations),
+        );
+    }
+
+    /// Writes the first row of the band that the next dispatch covers
+    pub fn write_row_offset(&self, queue: &wgpu::Queue, row: u32) {
+        queue.write_buffer(
+            &self.params_buffer,
+            ROW_OFFSET,
+            &bytemuck::cast::<_, [u8; 4]>(row),
+        );
+    }
+
+    /// Writes the amount of settled pixels of results that are restored instead of calculated.
+    /// Results don't tell which of them were skipped, so that count starts over
+    pub fn write_settled(&self, queue: &wgpu::Queue, settled: u32) {
+        queue.write_buffer(&self.tiles_buffer, 4, bytemuck::cast_slice(&[settled, 0]));
+    }
+}
+
+/// Amount of words the intermediate buffer has for every pixel: x and y of z followed by the
+/// derivative, that is a pair of f32 and its exponent, x and y of the periodicity checkpoint, and
+/// the flag and the count of the pixels handed over by the perturbation shader
+pub fn intermediate_stride(word_count: usize) -> u32 {
+    4 * word_count as u32 + 5
+}
+
+/// Returns the amount of settled pixels in the result buffer contents `iterations`
+pub fn settled_count(iterations: &[u8]) -> u32 {
+    iterations
+        .chunks_exact(4)
+        .filter(|count| *count == SETTLED.to_le_bytes())
+        .count() as u32
+}
+
+/// Returns the counts of the result buffer contents `iterations` of `dimensions`, row by row
+/// without the padding
+pub fn unpadded_counts(iterations: &[u8], dimensions: ScaledDimensions) -> Vec<u32> {
+    iterations
+        .chunks_exact(4 * dimensions.aligned_width(ROW_ALIGNMENT) as usize)
+        .take(dimensions.height as usize)
+        .flat_map(|row| row[..4 * dimensions.width as usize].chunks_exact(4))
+        .map(|count| u32::from_le_bytes(count.try_into().expect("Counts are 4 bytes")))
+        .collect()
+}
+
+/// Amount of workgroups dispatched over `dimensions`, each of them covers a part of a row
+pub fn tile_count(dimensions: ScaledDimensions, workgroup_width: u32) -> u32 {
+    dimensions.aligned_width(ROW_ALIGNMENT) / workgroup_width * dimensions.height
+}
+
+/// Reads back the amount of workgroups that still had unfinished pixels in the last dispatch,
+/// along with the amounts of settled and skipped pixels. Counts are skipped while the previous
+/// ones are in flight
+pub struct TileCounter {
+    staging: wgpu::Buffer,
+    stage: CounterStage,
+    /// Last counts read back, of active workgroups, settled and skipped pixels
+    counts: Option<[u32; 3]>,
+}
+
+enum CounterStage {
+    Idle,
+    /// Count is copied to the staging buffer by the submitted work
+    Copied,
+    Mapping(flume::Receiver<Result<(), wgpu::BufferAsyncError>>),
+}
+
+impl TileCounter {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            staging: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Tile Count Staging"),
+                size: 12,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            stage: CounterStage::Idle,
+            counts: None,
+        }
+    }
+
+    /// Resets the count before the dispatch
+    pub fn encode_reset(&self, encoder: &mut wgpu::CommandEncoder, bindings: &ComputeBindings) {
+        encoder.clear_buffer(&bindings.tiles_buffer, 0, Some(4));
+    }
+
+    /// Copies the counts after the dispatch
+    pub fn encode_copy(&mut self, encoder: &mut wgpu::CommandEncoder, bindings: &ComputeBindings) {
+        if let CounterStage::Idle = self.stage {
+            encoder.copy_buffer_to_buffer(&bindings.tiles_buffer, 0, &self.staging, 0, 12);
+            self.stage = CounterStage::Copied;
+        }
+    }
+
+    /// Starts mapping the count copied by the work that has just been submitted
+    pub fn request_map(&mut self) {
+        if let CounterStage::Copied = self.stage {
+            let (sender, result) = flume::bounded(1);
+            self.staging
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |r| {
+                    let _ = sender.send(r);
+                });
+            self.stage = CounterStage::Mapping(result);
+        }
+    }
+
+    /// Collects the mapped count. Devices have to be polled for mapping to complete
+    pub fn receive(&mut self) {
+        let CounterStage::Mapping(result) = &self.stage else {
+            return;
+        };
+        match result.try_recv() {
+            Ok(Ok(())) => {
+                let counts =
+                    bytemuck::pod_read_unaligned(&self.staging.slice(..).get_mapped_range());
+                self.staging.unmap();
+                self.counts = Some(counts);
+                self.stage = CounterStage::Idle;
+            }
+            Ok(Err(e)) => {
+                log::error!("Unable to read the tile count: {}", e);
+                self.stage = CounterStage::Idle;
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Forgets the counts of a calculation that has been replaced
+    pub fn clear(&mut self) {
+        self.counts = None;
+    }
+
+    pub fn active(&self) -> Option<u32> {
+        self.counts.map(|[active, ..]| active)
+    }
+
+    pub fn settled(&self) -> Option<u32> {
+        self.counts.map(|[_, settled, _]| settled)
+    }
+
+    /// Returns the amount of settled pixels found by the cardioid and bulb tests
+    pub fn skipped(&self) -> Option<u32> {
+        self.counts.map(|[.., skipped]| skipped)
+    }
+}
+
+/// Returns the offset of the pixel `x`, `y` in a result buffer of `dimensions`, none if the pixel is
+/// outside of it
+pub fn pixel_offset(dimensions: ScaledDimensions, x: u32, y: u32) -> Option<u64> {
+    (x < dimensions.width && y < dimensions.height).then(|| {
+        let row = dimensions.aligned_width(ROW_ALIGNMENT) as u64;
+        4 * (y as u64 * row + x as u64)
+    })
+}
+
+/// Reads back the iteration count of an inspected pixel. The count is read again after every
+/// dispatch, so that it keeps up with pixels that haven't escaped yet
+pub struct PixelReadback {
+    staging: wgpu::Buffer,
+    stage: CounterStage,
+    /// Offset of the inspected pixel in the result buffer
+    offset: Option<u64>,
+    /// The pixel has just been chosen and has to be read even without a dispatch
+    changed: bool,
+    /// Last count read back
+    iterations: Option<u32>,
+}
+
+impl PixelReadback {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            staging: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Pixel Staging"),
+                size: 4,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            stage: CounterStage::Idle,
+            offset: None,
+            changed: false,
+            iterations: None,
+        }
+    }
+
+    /// Chooses the pixel at `offset` of the result buffer
+    pub fn inspect(&mut self, offset: u64) {
+        self.offset = Some(offset);
+        self.changed = true;
+        self.iterations = None;
+    }
+
+    /// Copies the count of the pixel, if it's new or `dispatched` may have changed it
+    pub fn encode_copy(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        bindings: &ComputeBindings,
+        dispatched: bool,
+    ) {
+        let Some(offset) = self.offset else {
+            return;
+        };
+        if let CounterStage::Idle = self.stage {
+            if self.changed || dispatched {
+                encoder.copy_buffer_to_buffer(&bindings.result_buffer, offset, &self.staging, 0, 4);
+                self.stage = CounterStage::Copied;
+                self.changed = false;
+            }
+        }
+    }
+
+    /// Starts mapping the count copied by the work that has just been submitted
+    pub fn request_map(&mut self) {
+        if let CounterStage::Copied = self.stage {
+            let (sender, result) = flume::bounded(1);
+            self.staging
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |r| {
+                    let _ = sender.send(r);
+                });
+            self.stage = CounterStage::Mapping(result);
+        }
+    }
+
+    /// Collects the mapped count. Devices have to be polled for mapping to complete
+    pub fn receive(&mut self) {
+        let CounterStage::Mapping(result) = &self.stage else {
+            return;
+        };
+        match result.try_recv() {
+            Ok(Ok(())) => {
+                let count =
+                    bytemuck::pod_read_unaligned(&self.staging.slice(..).get_mapped_range());
+                self.staging.unmap();
+                // A count copied before the pixel changed belongs to the previous one
+                if !self.changed && self.offset.is_some() {
+                    self.iterations = Some(count);
+                }
+                self.stage = CounterStage::Idle;
+            }
+            Ok(Err(e)) => {
+                log::error!("Unable to read the inspected pixel: {}", e);
+                self.stage = CounterStage::Idle;
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Forgets the pixel of a calculation that has been replaced
+    pub fn clear(&mut self) {
+        self.offset = None;
+        self.iterations = None;
+    }
+
+    pub fn iterations(&self) -> Option<u32> {
+        self.iterations
+    }
+
+    /// Returns true while the count of a newly chosen pixel hasn't been read yet
+    pub fn is_pending(&self) -> bool {
+        self.offset.is_some() && (self.changed || !matches!(self.stage, CounterStage::Idle))
     }
 }
 
@@ -143,28 +662,364 @@ impl UninitializedComputeBindings {
 }
 
 impl<'c> ComputeParams<'c> {
-    pub fn new(size: ScaledDimensions, coords: &'c Coordinates, depth_limit: u32) -> Self {
+    pub fn new(
+        size: ScaledDimensions,
+        coords: &'c Coordinates,
+        fractal: &'c FractalParams,
+        iteration: IterationParams,
+        depth_limit: u32,
+    ) -> Self {
         Self {
             size,
             coords,
+            fractal,
+            iteration,
             depth_limit,
             reset: true,
+            interior_checks: true,
+            repair: 0,
+        }
+    }
+
+    /// Only calculates the pixels that the perturbation shader hands over, by up to `iterations`
+    /// in a dispatch
+    pub fn with_repair(self, iterations: u32) -> Self {
+        Self {
+            repair: iterations,
+            ..self
+        }
+    }
+
+    /// Iterates every pixel to the depth limit. Calibration frames have to, as they time the
+    /// iterations of their pixels
+    pub fn without_interior_checks(self) -> Self {
+        Self {
+            interior_checks: false,
+            ..self
         }
     }
 
+    /// The cardioid and bulb tests only hold for the Mandelbrot set of z^2
+    fn bulb_checks(&self) -> bool {
+        self.interior_checks
+            && self.fractal.kind == FractalKind::Mandelbrot
+            && self.fractal.power == 2
+            && self.fractal.julia.is_none()
+    }
+
     fn encode(&self) -> Vec<u8> {
         let mut buffer = Vec::with_capacity(size_hint(self.coords.size()) as usize);
         buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.depth_limit));
         buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.reset as u32));
-        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.size.aligned_width(64)));
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(
+            self.size.aligned_width(ROW_ALIGNMENT),
+        ));
         buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.size.height));
-        buffer.extend_from_slice(&self.coords.x.as_bytes());
-        buffer.extend_from_slice(&self.coords.y.as_bytes());
-        buffer.extend_from_slice(&self.coords.step.as_bytes());
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.fractal.inverted as u32));
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.fractal.kind.shader_id()));
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(
+            self.fractal.julia.is_some() as u32
+        ));
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.fractal.power));
+        let (cos, sin) = self.coords.rotation_cos_sin();
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(cos));
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(sin));
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(
+            self.iteration.distance_estimation as u32,
+        ));
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.interior_checks as u32));
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.bulb_checks() as u32));
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(self.repair));
+        // Bands other than the first write their row before they are dispatched
+        buffer.extend_from_slice(&bytemuck::cast::<_, [u8; 4]>(0u32));
+        buffer.extend_from_slice(self.coords.x.as_bytes());
+        buffer.extend_from_slice(self.coords.y.as_bytes());
+        buffer.extend_from_slice(self.coords.step.as_bytes());
+        // Squared, so that it's compared to x^2 + y^2 without a square root
+        let radius = self.iteration.escape_radius_for(self.fractal.power);
+        let bailout = WideFloat::from_f64((radius * radius).into(), self.coords.size())
+            .expect("Escape radius out of range");
+        buffer.extend_from_slice(bailout.as_bytes());
+        if let Some(seed) = &self.fractal.julia {
+            let seed = seed.resized(self.coords.size());
+            buffer.extend_from_slice(seed.re.as_bytes());
+            buffer.extend_from_slice(seed.im.as_bytes());
+        }
         buffer
     }
 }
 
 fn size_hint(word_count: usize) -> u32 {
-    word_count as u32 * 12 + 16
+    // Origin, step, squared escape radius and the Julia seed after the fixed fields
+    word_count as u32 * 24 + HEADER_SIZE as u32
+}
+
+/// Size of the fixed fields before the numbers
+const HEADER_SIZE: usize = 60;
+
+/// Offset of the repair iterations
+const REPAIR_OFFSET: wgpu::BufferAddress = ROW_OFFSET - 4;
+
+/// Offset of the first row of the band, the last of the fixed fields
+const ROW_OFFSET: wgpu::BufferAddress = HEADER_SIZE as wgpu::BufferAddress - 4;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_offsets_follow_the_aligned_rows() {
+        let dimensions = ScaledDimensions {
+            width: 100,
+            height: 50,
+        };
+        assert_eq!(ROW_ALIGNMENT, 64);
+        assert_eq!(pixel_offset(dimensions, 0, 0), Some(0));
+        assert_eq!(pixel_offset(dimensions, 99, 0), Some(4 * 99));
+        assert_eq!(pixel_offset(dimensions, 3, 2), Some(4 * (2 * 128 + 3)));
+        assert_eq!(pixel_offset(dimensions, 100, 0), None);
+        assert_eq!(pixel_offset(dimensions, 0, 50), None);
+    }
+
+    #[test]
+    fn growing_views_are_reallocated_rarely() {
+        let limit = 1 << 30;
+        let sizes = |width, word_count| {
+            let dimensions = ScaledDimensions { width, height: 600 };
+            BufferSizes::new(dimensions, word_count, 64)
+        };
+        let mut capacity = sizes(800, 2);
+        let mut allocations = 0;
+        for step in 1..=100 {
+            let required = sizes(800 + 10 * step, 2);
+            let grown = capacity.grow(required, limit);
+            assert!(grown.params >= required.params && grown.result >= required.result);
+            assert!(grown.intermediate >= required.intermediate && grown.tiles >= required.tiles);
+            allocations += (grown != capacity) as u32;
+            capacity = grown;
+        }
+        // The width more than doubles, which takes only a couple of reallocations by half
+        assert!(allocations <= 3, "{allocations} allocations");
+
+        // Shrinking and switching back keeps the buffers, larger word counts grow them
+        assert_eq!(capacity.grow(sizes(800, 2), limit), capacity);
+        assert_eq!(capacity.grow(sizes(1800, 2), limit), capacity);
+        let deeper = capacity.grow(sizes(1800, 4), limit);
+        assert!(deeper.intermediate > capacity.intermediate);
+        assert_eq!(deeper.result, capacity.result);
+
+        // Growth stops at the limit, but never below the required size
+        let required = sizes(1800, 8);
+        let limited = capacity.grow(required, required.intermediate);
+        assert_eq!(limited.intermediate, required.intermediate);
+        for size in [
+            limited.params,
+            limited.intermediate,
+            limited.result,
+            limited.tiles,
+        ] {
+            assert_eq!(size % wgpu::COPY_BUFFER_ALIGNMENT, 0);
+        }
+    }
+
+    #[test]
+    fn settled_pixels_are_counted() {
+        let results: Vec<u8> = [3, SETTLED, 0, SETTLED, SETTLED - 1]
+            .iter()
+            .flat_map(|count| count.to_le_bytes())
+            .collect();
+        assert_eq!(settled_count(&results), 2);
+        assert_eq!(settled_count(&[]), 0);
+    }
+
+    #[test]
+    fn bulb_checks_only_apply_to_the_mandelbrot_set() {
+        let size = ScaledDimensions {
+            width: 100,
+            height: 50,
+        };
+        let coords = Coordinates::new(-2.0, -1.0, 0.01, 96).unwrap();
+        let checked = |fractal: FractalParams| {
+            ComputeParams::new(size, &coords, &fractal, IterationParams::default(), 100)
+                .bulb_checks()
+        };
+        assert!(checked(FractalParams::default()));
+        assert!(checked(FractalParams {
+            inverted: true,
+            ..Default::default()
+        }));
+        assert!(!checked(FractalParams {
+            kind: FractalKind::PerpendicularBurningShip,
+            ..Default::default()
+        }));
+        assert!(!checked(FractalParams {
+            power: 3,
+            ..Default::default()
+        }));
+        assert!(!checked(FractalParams {
+            julia: crate::fractal::JuliaSeed::from_f64(-0.75, 0.25),
+            ..Default::default()
+        }));
+
+        let fractal = FractalParams::default();
+        let calibration =
+            ComputeParams::new(size, &coords, &fractal, IterationParams::default(), 100)
+                .without_interior_checks();
+        assert!(!calibration.bulb_checks());
+    }
+
+    #[test]
+    fn julia_seeds_follow_the_step() {
+        let size = ScaledDimensions {
+            width: 100,
+            height: 50,
+        };
+        let coords = Coordinates::new(-2.0, -1.0, 0.01, 96).unwrap();
+        let mandelbrot = FractalParams::default();
+        let encoded =
+            ComputeParams::new(size, &coords, &mandelbrot, IterationParams::default(), 100)
+                .encode();
+        assert_eq!(encoded.len(), HEADER_SIZE + 16 * coords.size());
+
+        let julia = FractalParams {
+            julia: crate::fractal::JuliaSeed::from_f64(-0.75, 0.25),
+            ..Default::default()
+        };
+        let encoded =
+            ComputeParams::new(size, &coords, &julia, IterationParams::default(), 100).encode();
+        assert_eq!(encoded.len() as u32, size_hint(coords.size()));
+        assert_eq!(encoded[24..28], [1, 0, 0, 0]);
+        assert_eq!(encoded[28..32], [2, 0, 0, 0]);
+        let seed = julia.julia.unwrap().resized(coords.size());
+        let words = 4 * coords.size();
+        assert_eq!(
+            &encoded[HEADER_SIZE + 4 * words..HEADER_SIZE + 5 * words],
+            seed.re.as_bytes()
+        );
+        assert_eq!(&encoded[HEADER_SIZE + 5 * words..], seed.im.as_bytes());
+    }
+
+    #[test]
+    fn header_matches_the_shader_struct() {
+        let source = super::super::COMPUTE_SHADER_TEMPLATE;
+        let start = source.find("struct Parameters {").unwrap();
+        let end = start + source[start..].find('}').unwrap();
+        // Offsets of the fields in the order they are declared, words start after the last one
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        for line in source[start..end].lines().skip(1) {
+            if line.trim().starts_with("//") {
+                continue;
+            }
+            let Some((name, ty)) = line.trim().trim_end_matches(',').split_once(": ") else {
+                continue;
+            };
+            offsets.push((name, offset));
+            offset += match ty {
+                "u32" => 4,
+                "vec2<u32>" | "vec2<f32>" => 8,
+                _ => 0,
+            };
+        }
+        let offset_of = |field: &str| {
+            offsets
+                .iter()
+                .find(|(name, _)| *name == field)
+                .map(|(_, offset)| *offset)
+                .unwrap()
+        };
+        let word = |encoded: &[u8], offset: usize| {
+            u32::from_le_bytes(encoded[offset..offset + 4].try_into().unwrap())
+        };
+
+        let size = ScaledDimensions {
+            width: 100,
+            height: 50,
+        };
+        let coords = Coordinates::new(-2.0, -1.0, 0.01, 96).unwrap();
+        let fractal = FractalParams {
+            kind: crate::fractal::FractalKind::Celtic,
+            inverted: true,
+            power: 3,
+            julia: crate::fractal::JuliaSeed::from_f64(-0.75, 0.25),
+        };
+        let iteration = IterationParams {
+            distance_estimation: true,
+            ..Default::default()
+        };
+        let encoded = ComputeParams::new(size, &coords, &fractal, iteration, 321).encode();
+        assert_eq!(word(&encoded, offset_of("depth_limit")), 321);
+        assert_eq!(word(&encoded, offset_of("reset")), 1);
+        assert_eq!(word(&encoded, offset_of("size")), 128);
+        assert_eq!(word(&encoded, offset_of("size") + 4), 50);
+        assert_eq!(word(&encoded, offset_of("inverted")), 1);
+        assert_eq!(word(&encoded, offset_of("kind")), fractal.kind.shader_id());
+        assert_eq!(word(&encoded, offset_of("julia")), 1);
+        assert_eq!(word(&encoded, offset_of("power")), 3);
+        assert_eq!(word(&encoded, offset_of("rotation")), 1.0f32.to_bits());
+        assert_eq!(word(&encoded, offset_of("rotation") + 4), 0);
+        assert_eq!(word(&encoded, offset_of("distance")), 1);
+        assert_eq!(word(&encoded, offset_of("periodicity")), 1);
+        let calibration = ComputeParams::new(size, &coords, &fractal, iteration, 321)
+            .without_interior_checks()
+            .encode();
+        assert_eq!(word(&calibration, offset_of("periodicity")), 0);
+        // Celtic fractals don't have the bulbs
+        assert_eq!(word(&encoded, offset_of("bulbs")), 0);
+        assert_eq!(word(&encoded, offset_of("repair")), 0);
+        assert_eq!(offset_of("repair") as u64, REPAIR_OFFSET);
+        let repair = ComputeParams::new(size, &coords, &fractal, iteration, 321)
+            .with_repair(1284)
+            .encode();
+        assert_eq!(word(&repair, offset_of("repair")), 1284);
+        assert_eq!(word(&encoded, offset_of("row_offset")), 0);
+        assert_eq!(offset_of("row_offset") as u64, ROW_OFFSET);
+        assert_eq!(offset_of("words"), HEADER_SIZE);
+        assert_eq!(
+            &encoded[HEADER_SIZE..HEADER_SIZE + 4 * coords.size()],
+            coords.x.as_bytes()
+        );
+    }
+
+    #[test]
+    fn escape_radius_is_squared_after_the_step() {
+        let size = ScaledDimensions {
+            width: 100,
+            height: 50,
+        };
+        let coords = Coordinates::new(-2.0, -1.0, 0.01, 96).unwrap();
+        let words = 4 * coords.size();
+        let bailout = |power: u32, escape_radius: f32| {
+            let fractal = FractalParams {
+                power,
+                ..Default::default()
+            };
+            let iteration = IterationParams {
+                escape_radius,
+                ..Default::default()
+            };
+            let encoded = ComputeParams::new(size, &coords, &fractal, iteration, 100).encode();
+            assert_eq!(
+                &encoded[HEADER_SIZE + 2 * words..HEADER_SIZE + 3 * words],
+                coords.step.as_bytes()
+            );
+            encoded[HEADER_SIZE + 3 * words..HEADER_SIZE + 4 * words].to_vec()
+        };
+        let squared = |value: f64| WideFloat::from_f64(value, coords.size()).unwrap();
+
+        assert_eq!(bailout(2, 2.0), squared(4.0).as_bytes());
+        assert_eq!(bailout(2, 1000.0), squared(1e6).as_bytes());
+        // Radii of higher powers are limited, so that the step past them doesn't overflow
+        let limited = IterationParams {
+            escape_radius: 1000.0,
+            ..Default::default()
+        }
+        .escape_radius_for(8);
+        assert!(limited < 16.0);
+        assert_eq!(
+            bailout(8, 1000.0),
+            squared((limited * limited).into()).as_bytes()
+        );
+        assert_eq!(bailout(8, 2.0), squared(4.0).as_bytes());
+    }
 }
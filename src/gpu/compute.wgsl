@@ -2,6 +2,29 @@ struct Parameters {
     depth_limit: u32,
     reset: u32,
     size: vec2<u32>,
+    inverted: u32,
+    kind: u32,
+    // Non-zero to calculate the Julia set of the seed that follows the origin, step and squared
+    // escape radius in `words`
+    julia: u32,
+    // Exponent of z in the iteration formula, from 2 to 8
+    power: u32,
+    // Cosine and sine of the view rotation
+    rotation: vec2<f32>,
+    // Non-zero to track the derivative of z and estimate the distances of escaped pixels
+    distance: u32,
+    // Non-zero to stop iterating pixels whose z has come back to an earlier value
+    periodicity: u32,
+    // Non-zero to find the pixels in the main cardioid and the period 2 bulb without iterating
+    // them, only set for the Mandelbrot set of z^2
+    bulbs: u32,
+    // Non-zero after the perturbation shader has iterated the pixels. Only the pixels it has
+    // handed over are calculated, by up to this many iterations, so that they catch up with the
+    // depth
+    repair: u32,
+    // First row of the band of rows the dispatch covers, workgroup and invocation ids are counted
+    // from it
+    row_offset: u32,
     words: array<u32>,
 }
 
@@ -17,18 +40,71 @@ var<storage, read_write> iterations: array<u32>;
 @binding(2)
 var<storage, read_write> intermediate: array<u32>;
 
-// Calculate mandelbrot iterations
+struct Tiles {
+    // Amount of workgroups with unfinished pixels in the current dispatch
+    active_count: atomic<u32>,
+    // Amount of pixels found inside the set since the calculation has been reset
+    settled_count: atomic<u32>,
+    // Amount of the settled pixels found by the cardioid and bulb tests
+    skipped_count: atomic<u32>,
+    // Non-zero for every workgroup whose pixels have all escaped or settled
+    done: array<u32>,
+}
+
+@group(0)
+@binding(3)
+var<storage, read_write> tiles: Tiles;
+
+// Fraction of an iteration every escaped pixel has left until the next count, 1 for pixels that
+// have only just passed the escape radius
+@group(0)
+@binding(4)
+var<storage, read_write> escapes: array<f32>;
+
+// Estimated distance of every escaped pixel to the set in pixels, only written with distance
+// estimation
+@group(0)
+@binding(5)
+var<storage, read_write> distances: array<f32>;
+
+// Derivative of z by the pixel position, kept as a pair of f32 scaled by a power of two so that
+// it has the range to grow past f32 at deep zoom and to start at a step below it
+struct Derivative {
+    value: vec2<f32>,
+    exponent: i32,
+}
+
+var<private> derivative: Derivative;
+
+// Pixel step scaled like the derivative, added to it by every step of the Mandelbrot set
+var<private> pixel_step: Derivative;
+
+// Count of the pixels found inside the set by periodicity checking, higher than any depth so that
+// they're drawn and counted like the pixels that haven't escaped
+const settled: u32 = 0xffffffffu;
+
+// Count of the pixels handed over by the perturbation shader until they catch up with the depth,
+// drawn like the pixels that haven't escaped
+const glitched: u32 = 0xfffffffeu;
+
+var<workgroup> tile_done: u32;
+var<workgroup> unfinished_pixels: atomic<u32>;
+
+// Calculate fractal iterations
 //
-// Requires arena to have enough space for 7 wide numbers.
-// Requires first 4 numbers in the arena to be pre-initialized the following params before the call:
+// Requires arena to have enough space for 13 wide numbers.
+// Requires first 4 numbers and the last three in the arena to be pre-initialized the following
+// params before the call:
 // 1: origin X
 // 2: origin Y
 // 3: iteration X
 // 4: iteration Y
+// 11: squared escape radius
+// 12: checkpoint X
+// 13: checkpoint Y
+//
+// Returns `settled` if z comes back to the checkpoint with periodicity checking
 fn wide_mandelbrot(start_iter: u32, depth_limit: u32) -> u32 {
-    let origin_x = NumView(0u * word_count);
-    let origin_y = NumView(1u * word_count);
-
     let x = NumView(2u * word_count);
     let y = NumView(3u * word_count);
 
@@ -36,6 +112,13 @@ fn wide_mandelbrot(start_iter: u32, depth_limit: u32) -> u32 {
     let y2 = NumView(5u * word_count);
 
     let tmp = NumView(6u * word_count);
+    // Free between the steps, `iterate_power` only uses it within one
+    let tmp2 = NumView(7u * word_count);
+
+    let bailout = NumView(10u * word_count);
+
+    let checkpoint_x = NumView(11u * word_count);
+    let checkpoint_y = NumView(12u * word_count);
 
     // x2 = x * x
     wide_square(x, x2);
@@ -44,48 +127,312 @@ fn wide_mandelbrot(start_iter: u32, depth_limit: u32) -> u32 {
 
     var i: u32 = start_iter;
     wide_clone(x2, tmp);
-    while i < depth_limit && wide_cmp(wide_add(tmp, y2), 4) == -1 {
-        // y = square(x2 + y2) - x2 - y2 + origin_y
-        // x = x2 - y2 + origin_x
+    // x2 + y2 < bailout, with the squares only valid while x and y are small enough for them. The
+    // escape radius is limited for every power so that the step past it can't overflow
+    while i < depth_limit
+        && fits_square(x)
+        && fits_square(y)
+        && wide_floor(wide_sub(wide_add(tmp, y2), bailout)) < 0 {
+        if params.distance != 0u {
+            step_derivative(params.kind, params.power);
+        }
+        if params.power == 2u {
+            iterate(params.kind);
+        } else {
+            iterate_power(params.kind, params.power);
+        }
+
+        i++;
+        wide_clone(x2, tmp);
+
+        if params.periodicity != 0u {
+            // A z that repeats within the precision cycles through the same values forever and
+            // never escapes
+            if wide_near(x, checkpoint_x, tmp2) && wide_near(y, checkpoint_y, tmp2) {
+                return settled;
+            }
+            // Checkpoints at powers of two catch cycles of any length once the gap between
+            // them outgrows the period
+            if (i & (i - 1u)) == 0u {
+                wide_clone(x, checkpoint_x);
+                wide_clone(y, checkpoint_y);
+            }
+        }
+    }
 
-        // tmpy = y
-        wide_clone(y, tmp);
+    return i;
+}
 
-        // tmpy += x
-        wide_add(tmp, x);
+// Returns the fraction of the escaped iteration in the arena of `wide_mandelbrot`. The fractional
+// count n + 1 - log_power(log|z| / log(radius)) is continuous across the bands of equal counts
+fn escape_fraction() -> f32 {
+    let x = wide_to_f32(NumView(2u * word_count));
+    let y = wide_to_f32(NumView(3u * word_count));
+    let bailout = wide_to_f32(NumView(10u * word_count));
+    let ratio = log(x * x + y * y) / log(bailout);
+    return clamp(1.0 - log2(ratio) / log2(f32(params.power)), 0.0, 1.0);
+}
 
-        // y = tmpy * tmpy
-        wide_square(tmp, y);
+// Returns the distance to the set in pixels estimated from the escaped z in the arena of
+// `wide_mandelbrot` and its derivative, as |z| log|z| / |dz|
+fn distance_estimate() -> f32 {
+    let x = wide_to_f32(NumView(2u * word_count));
+    let y = wide_to_f32(NumView(3u * word_count));
+    let norm = sqrt(x * x + y * y);
+    let scaled = norm * log(norm) / length(derivative.value);
+    // Derivatives that have overflowed belong to pixels right at the boundary
+    if !(scaled >= 0.0) {
+        return 0.0;
+    }
+    return ldexp(scaled, -derivative.exponent);
+}
 
-        // y -= y2
-        wide_sub(y, y2);
+// Advances the derivative by a step of the fractal `kind` from z in the arena of `wide_mandelbrot`,
+// before z itself is advanced: dz = power * z^(power - 1) * dz, plus the step of the pixel for the
+// Mandelbrot set. Abs mirrors the parts of the derivative along with the parts of z
+fn step_derivative(kind: u32, power: u32) {
+    var z = vec2<f32>(
+        wide_to_f32(NumView(2u * word_count)),
+        wide_to_f32(NumView(3u * word_count)),
+    );
+    var value = derivative.value;
+    if kind == kind_perpendicular_burning_ship && z.y < 0.0 {
+        z.y = -z.y;
+        value.y = -value.y;
+    }
 
-        // y -= x2
-        wide_sub(y, x2);
+    var z_power = vec2<f32>(1.0, 0.0);
+    for (var i = 1u; i < power; i++) {
+        z_power = complex_mul(z_power, z);
+    }
+    value = f32(power) * complex_mul(z_power, value);
+    if kind == kind_celtic && complex_mul(z_power, z).x < 0.0 {
+        value.x = -value.x;
+    }
 
-        // y += origin_y
-        wide_add(y, origin_y);
+    if params.julia == 0u {
+        value.x += ldexp(pixel_step.value.x, pixel_step.exponent - derivative.exponent);
+    }
+    derivative = normalized(Derivative(value, derivative.exponent));
+}
 
-        // x = x2
-        wide_clone(x2, x);
+// Moves the exponent of the larger part of `num` to the scale of the derivative
+fn normalized(num: Derivative) -> Derivative {
+    let larger = max(abs(num.value.x), abs(num.value.y));
+    // Zero and values that have overflowed are left as they are
+    if larger == 0.0 || larger > 3.0e38 {
+        return num;
+    }
+    let exponent = i32((bitcast<u32>(larger) >> 23u) & 0xffu) - 127;
+    return Derivative(ldexp(num.value, vec2<i32>(-exponent)), num.exponent + exponent);
+}
 
-        // x -= y2
-        wide_sub(x, y2);
+fn complex_mul(left: vec2<f32>, right: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(left.x * right.x - left.y * right.y, left.x * right.y + left.y * right.x);
+}
 
-        // x += origin_x
-        wide_add(x, origin_x);
+// Returns the positive wide number as a derivative of a real value, so that numbers below the
+// range of f32 keep their precision
+fn wide_to_derivative(num: NumView) -> Derivative {
+    var top = word_count - 1u;
+    while top > 0u && arena[num.idx + top] == 0u {
+        top--;
+    }
+    var value = f32(arena[num.idx + top]);
+    if top > 0u {
+        value += f32(arena[num.idx + top - 1u]) / 4294967296.0;
+    }
+    let exponent = 32 * (i32(top) - i32(word_count - 1u));
+    return normalized(Derivative(vec2<f32>(value, 0.0), exponent));
+}
 
-        // x2 = x * x
-        wide_square(x, x2);
+// Returns true if c = x + iy is in the main cardioid or the period 2 bulb of the Mandelbrot set,
+// where z is attracted to a cycle and never escapes
+//
+// Uses the numbers 5 to 10 of the arena for the intermediate results
+fn in_main_bulbs(x: NumView, y: NumView) -> bool {
+    // Both shapes are within these bounds, which also keep the products from overflowing
+    let whole_x = wide_floor(x);
+    let whole_y = wide_floor(y);
+    if whole_x < -2 || whole_x > 0 || whole_y < -1 || whole_y > 0 {
+        return false;
+    }
 
-        // y2 = y * y
-        wide_square(y, y2);
+    let y2 = NumView(4u * word_count);
+    let shifted = NumView(5u * word_count);
+    let constant = NumView(6u * word_count);
+    let q = NumView(7u * word_count);
+    let sum = NumView(8u * word_count);
+    let prod = NumView(9u * word_count);
 
-        i++;
-        wide_clone(x2, tmp);
+    wide_square(y, y2);
+
+    // Period 2 bulb: (x + 1)^2 + y^2 <= 1/16
+    wide_clone(x, shifted);
+    wide_from_u32(1u, constant);
+    wide_add(shifted, constant);
+    wide_square(shifted, sum);
+    wide_add(sum, y2);
+    wide_from_f32(0.0625, constant);
+    if wide_floor(wide_sub(constant, sum)) >= 0 {
+        return true;
     }
 
-    return i;
+    // Main cardioid: q * (q + x - 1/4) <= y^2 / 4, with q = (x - 1/4)^2 + y^2
+    wide_clone(x, shifted);
+    wide_from_f32(0.25, constant);
+    wide_sub(shifted, constant);
+    wide_square(shifted, q);
+    wide_add(q, y2);
+    wide_clone(q, sum);
+    wide_add(sum, shifted);
+    wide_mul(q, sum, prod);
+    wide_shr_bits(y2, 2u);
+    return wide_floor(wide_sub(y2, prod)) >= 0;
+}
+
+// Returns true if the square of `num` fits into half of the whole part, so that two of them can
+// be added
+fn fits_square(num: NumView) -> bool {
+    let whole = wide_floor(num);
+    return -32768 < whole && whole < 32768;
+}
+
+const kind_mandelbrot: u32 = 0u;
+const kind_celtic: u32 = 1u;
+const kind_perpendicular_burning_ship: u32 = 2u;
+
+// Performs a single iteration step of the fractal `kind`
+//
+// Uses the same arena layout as `wide_mandelbrot`. Expects x2 and y2 to contain squares of the
+// current x and y and updates them for the next step
+fn iterate(kind: u32) {
+    let origin_x = NumView(0u * word_count);
+    let origin_y = NumView(1u * word_count);
+
+    let x = NumView(2u * word_count);
+    let y = NumView(3u * word_count);
+
+    let x2 = NumView(4u * word_count);
+    let y2 = NumView(5u * word_count);
+
+    let tmp = NumView(6u * word_count);
+
+    // Variants only differ in where abs is applied, so a single step covers all of them:
+    // mandelbrot:                y = 2 * x * y + origin_y,   x = x2 - y2 + origin_x
+    // celtic:                    y = 2 * x * y + origin_y,   x = |x2 - y2| + origin_x
+    // perpendicular burning ship: y = 2 * x * |y| + origin_y, x = x2 - y2 + origin_x
+    let abs_cross_y = kind == kind_perpendicular_burning_ship;
+    let abs_real = kind == kind_celtic;
+
+    // 2 * x * y is calculated as square(x + y) - x2 - y2
+
+    // tmpy = y
+    wide_clone(y, tmp);
+
+    if abs_cross_y {
+        wide_abs(tmp);
+    }
+
+    // tmpy += x
+    wide_add(tmp, x);
+
+    // y = tmpy * tmpy
+    wide_square(tmp, y);
+
+    // y -= y2
+    wide_sub(y, y2);
+
+    // y -= x2
+    wide_sub(y, x2);
+
+    // y += origin_y
+    wide_add(y, origin_y);
+
+    // x = x2
+    wide_clone(x2, x);
+
+    // x -= y2
+    wide_sub(x, y2);
+
+    if abs_real {
+        wide_abs(x);
+    }
+
+    // x += origin_x
+    wide_add(x, origin_x);
+
+    // x2 = x * x
+    wide_square(x, x2);
+
+    // y2 = y * y
+    wide_square(y, y2);
+}
+
+// Performs a single iteration step of the fractal `kind` with z raised to `power` instead of
+// squared, abs is applied to the same parts of z and the power as in `iterate`
+//
+// Uses the same arena layout as `wide_mandelbrot` with 3 more numbers after it. Expects x2 and y2
+// to contain squares of the current x and y and updates them for the next step
+fn iterate_power(kind: u32, power: u32) {
+    let origin_x = NumView(0u * word_count);
+    let origin_y = NumView(1u * word_count);
+
+    let x = NumView(2u * word_count);
+    let y = NumView(3u * word_count);
+
+    let x2 = NumView(4u * word_count);
+    let y2 = NumView(5u * word_count);
+
+    let tmp = NumView(6u * word_count);
+
+    let re = NumView(7u * word_count);
+    let im = NumView(8u * word_count);
+    let prod = NumView(9u * word_count);
+
+    // y is replaced at the end of the step, and its square doesn't depend on the sign
+    if kind == kind_perpendicular_burning_ship {
+        wide_abs(y);
+    }
+
+    // re + i * im = z^2, with 2 * x * y calculated as square(x + y) - x2 - y2
+    wide_clone(x2, re);
+    wide_sub(re, y2);
+    wide_clone(y, tmp);
+    wide_add(tmp, x);
+    wide_square(tmp, im);
+    wide_sub(im, y2);
+    wide_sub(im, x2);
+
+    for (var i = 2u; i < power; i++) {
+        // (re + i * im) * (x + i * y) = (re * x - im * y) + i * (re * y + im * x)
+        wide_mul(re, x, tmp);
+        wide_mul(im, y, prod);
+        wide_sub(tmp, prod);
+        wide_mul(re, y, prod);
+        wide_mul(im, x, re);
+        wide_add(re, prod);
+        wide_clone(re, im);
+        wide_clone(tmp, re);
+    }
+
+    if kind == kind_celtic {
+        wide_abs(re);
+    }
+
+    // x = re + origin_x
+    wide_clone(re, x);
+    wide_add(x, origin_x);
+
+    // y = im + origin_y
+    wide_clone(im, y);
+    wide_add(y, origin_y);
+
+    // x2 = x * x
+    wide_square(x, x2);
+
+    // y2 = y * y
+    wide_square(y, y2);
 }
 
 @compute
@@ -93,9 +440,21 @@ fn wide_mandelbrot(start_iter: u32, depth_limit: u32) -> u32 {
 fn main(
     @builtin(global_invocation_id) global_id: vec3<u32>,
     @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
 ) {
+    // Escaped pixels keep their result, so finished tiles are skipped as a whole
+    let row = workgroup_id.y + params.row_offset;
+    let tile = row * num_workgroups.x + workgroup_id.x;
+    if local_id.x == 0u {
+        tile_done = tiles.done[tile];
+    }
+    if workgroupUniformLoad(&tile_done) != 0u && params.reset == 0u {
+        return;
+    }
+
     let pixel_x = global_id.x;
-    let pixel_y = global_id.y;
+    let pixel_y = global_id.y + params.row_offset;
     let index = (pixel_y * params.size.x) + pixel_x;
 
     // Declare origin_x, origin_y and step
@@ -107,57 +466,195 @@ fn main(
     for (var i = 0u; i < 3 * word_count; i++) {
         arena[i] = params.words[i];
     }
+    // Squared escape radius is read by `wide_mandelbrot`
+    for (var i = 0u; i < word_count; i++) {
+        arena[10 * word_count + i] = params.words[3 * word_count + i];
+    }
+
+    if params.distance != 0u {
+        pixel_step = wide_to_derivative(step);
+    }
 
     let offset_x = NumView(3u * word_count);
     let offset_y = NumView(4u * word_count);
 
-    // offset_x = step * pixel_x
-    wide_clone(step, offset_x);
-    wide_mul_u32(offset_x, pixel_x);
+    if all(params.rotation == vec2<f32>(1.0, 0.0)) {
+        // offset_x = step * pixel_x
+        wide_clone(step, offset_x);
+        wide_mul_u32(offset_x, pixel_x);
 
-    // offset_y = step * pixel_y
-    wide_clone(step, offset_y);
-    wide_mul_u32(offset_y, pixel_y);
+        // offset_y = step * pixel_y
+        wide_clone(step, offset_y);
+        wide_mul_u32(offset_y, pixel_y);
+    } else {
+        // Pixels are offset along the rotated basis vectors
+        let cos_sin = params.rotation;
+        let x = f32(pixel_x);
+        let y = f32(pixel_y);
+        wide_mul_fixed(step, x * cos_sin.x - y * cos_sin.y, offset_x);
+        wide_mul_fixed(step, x * cos_sin.y + y * cos_sin.x, offset_y);
+    }
 
     // origin_x += offset_x
     wide_add(origin_x, offset_x);
     // origin_y += offset_y
     wide_add(origin_y, offset_y);
 
+    if params.inverted != 0u {
+        // c = 1 / w = (x - iy) / (x * x + y * y)
+        // Only precise enough for shallow zoom, the view is limited to 2 words in this mode
+        let x = wide_to_f32(origin_x);
+        let y = wide_to_f32(origin_y);
+        let norm = x * x + y * y;
+        wide_from_f32(x / norm, origin_x);
+        wide_from_f32(-y / norm, origin_y);
+        // The inversion scales distances around the pixel by |c|^2. It also rotates them, which
+        // doesn't change the distance estimate
+        pixel_step = normalized(Derivative(pixel_step.value / norm, pixel_step.exponent));
+    }
+
+    let state = index * intermediate_stride;
+    let checkpoint_state = state + 2u * word_count + 3u;
+    // Flag of the pixels handed over by the perturbation shader, followed by their count
+    let handover_state = checkpoint_state + 2u * word_count;
+
+    // After the perturbation shader the other pixels are only checked for being finished, and the
+    // pixels that have just glitched start over
+    var exact = true;
+    var fresh = params.reset != 0u;
+    if params.repair != 0u {
+        let handed_over = params.reset == 0u && intermediate[handover_state] != 0u;
+        fresh = !handed_over && iterations[index] == glitched;
+        exact = handed_over || fresh;
+        intermediate[handover_state] = u32(exact);
+    }
+
     var iterstart: u32;
-    if params.reset != 0 {
+    if !exact {
+        iterstart = iterations[index];
+    } else if fresh {
         iterstart = 0u;
         // Set intermediate X and Y results to origin
         let x = NumView(2u * word_count);
         let y = NumView(3u * word_count);
         wide_clone(origin_x, x);
         wide_clone(origin_y, y);
+        // z starts at the pixel, so its derivative by the pixel position is the step
+        derivative = pixel_step;
+        // The starting z is the first checkpoint
+        wide_clone(origin_x, NumView(11u * word_count));
+        wide_clone(origin_y, NumView(12u * word_count));
+        if params.bulbs != 0u && in_main_bulbs(origin_x, origin_y) {
+            iterstart = settled;
+            iterations[index] = settled;
+            atomicAdd(&tiles.settled_count, 1u);
+            atomicAdd(&tiles.skipped_count, 1u);
+        }
     } else {
         iterstart = iterations[index];
+        if params.repair != 0u {
+            iterstart = intermediate[handover_state + 1u];
+        }
         // Read intermediate X and Y results
         for (var i = 0u; i < 2 * word_count; i++) {
-            arena[2 * word_count + i] = intermediate[2 * index * word_count + i];
+            arena[2 * word_count + i] = intermediate[state + i];
+        }
+        if params.distance != 0u {
+            derivative = Derivative(
+                bitcast<vec2<f32>>(vec2<u32>(
+                    intermediate[state + 2u * word_count],
+                    intermediate[state + 2u * word_count + 1u],
+                )),
+                bitcast<i32>(intermediate[state + 2u * word_count + 2u]),
+            );
+        }
+        if params.periodicity != 0u {
+            for (var i = 0u; i < 2 * word_count; i++) {
+                arena[11 * word_count + i] = intermediate[checkpoint_state + i];
+            }
+        }
+    }
+
+    if params.julia != 0u {
+        // The pixel is only the starting point of a Julia set, every pixel adds the same seed
+        for (var i = 0u; i < 2 * word_count; i++) {
+            arena[i] = params.words[4 * word_count + i];
         }
     }
 
     let depth_limit = params.depth_limit;
-    let iter_count = wide_mandelbrot(iterstart, depth_limit);
+    // Settled pixels keep their result like the escaped ones
+    var iter_count = iterstart;
+    var unfinished = !exact && iterstart >= depth_limit && iterstart != settled;
+    if exact && iterstart != settled {
+        var limit = depth_limit;
+        if params.repair != 0u {
+            limit = iterstart + min(params.repair, depth_limit - iterstart);
+        }
+        iter_count = wide_mandelbrot(iterstart, limit);
 
-    // Write intermediate X and Y results to continue on the next iteration
-    for (var i = 0u; i < 2 * word_count; i++) {
-        intermediate[2 * index * word_count + i] = arena[2 * word_count + i];
+        // Write intermediate X and Y results to continue on the next iteration
+        for (var i = 0u; i < 2 * word_count; i++) {
+            intermediate[state + i] = arena[2 * word_count + i];
+        }
+        if params.distance != 0u {
+            let value = bitcast<vec2<u32>>(derivative.value);
+            intermediate[state + 2u * word_count] = value.x;
+            intermediate[state + 2u * word_count + 1u] = value.y;
+            intermediate[state + 2u * word_count + 2u] = bitcast<u32>(derivative.exponent);
+        }
+        if params.periodicity != 0u {
+            for (var i = 0u; i < 2 * word_count; i++) {
+                intermediate[checkpoint_state + i] = arena[11 * word_count + i];
+            }
+        }
+
+        if params.repair != 0u {
+            intermediate[handover_state + 1u] = iter_count;
+        }
+
+        if iter_count == settled {
+            iterations[index] = settled;
+            atomicAdd(&tiles.settled_count, 1u);
+        } else if iter_count < limit {
+            iterations[index] = iter_count;
+            escapes[index] = escape_fraction();
+            if params.distance != 0u {
+                distances[index] = distance_estimate();
+            }
+        } else {
+            // Pixels that are still catching up keep their glitched result
+            iterations[index] = select(glitched, iter_count, iter_count >= depth_limit);
+            unfinished = true;
+        }
     }
 
-    iterations[index] = iter_count;
+    if unfinished {
+        atomicAdd(&unfinished_pixels, 1u);
+    }
+    workgroupBarrier();
+    if local_id.x == 0u {
+        let unfinished = atomicLoad(&unfinished_pixels) != 0u;
+        tiles.done[tile] = u32(!unfinished);
+        if unfinished {
+            atomicAdd(&tiles.active_count, 1u);
+        }
+    }
 }
 
 // ===== Bignum =====
+//
+// Modeled step by step in `shader_ref.rs`, changes to the arithmetic have to be mirrored there
 
 // Override variables aren't available yet, temporary bandaid
 // Tracking issue: https://github.com/gfx-rs/wgpu/issues/4484
 const word_count: u32 = 8;
 
-const arena_size: u32 = word_count * 7;
+const arena_size: u32 = word_count * 13;
+
+// Words of the intermediate buffer for every pixel, x and y of z followed by the derivative, x and
+// y of the checkpoint and the handover from the perturbation shader
+const intermediate_stride: u32 = 4u * word_count + 5u;
 var<private> arena: array<u32, arena_size>;
 
 struct NumView {
@@ -197,6 +694,14 @@ fn wide_neg(num: NumView) -> NumView {
     return num;
 }
 
+// Mutates `num` by making it non-negative. Returns the handle to the mutated number
+fn wide_abs(num: NumView) -> NumView {
+    if wide_floor(num) < 0 {
+        wide_neg(num);
+    }
+    return num;
+}
+
 // Mutates `left` by writing the result of multiplication of `left` and `right` to it. Returns
 // the handle to `left`
 //
@@ -212,6 +717,21 @@ fn wide_mul_u32(left: NumView, right: u32) -> NumView {
     return left;
 }
 
+// Mutates `dst` by writing the result of multiplication of `num` and `factor` to it, with
+// `factor` rounded to 16 fraction bits. Returns the handle to `dst`
+//
+// NOTE: Only intended to multiply positive `num` by factors within the i32 range after rounding
+fn wide_mul_fixed(num: NumView, factor: f32, dst: NumView) -> NumView {
+    let fixed = i32(round(factor * 65536.0));
+    wide_clone(num, dst);
+    wide_mul_u32(dst, u32(abs(fixed)));
+    wide_shr_bits(dst, 16u);
+    if fixed < 0 {
+        wide_neg(dst);
+    }
+    return dst;
+}
+
 // Mutates `out` by writing the result of squaring of `num` to it. Returns the handle to `out`
 //
 // NOTE: Overflow is UB
@@ -225,27 +745,41 @@ fn wide_square(num: NumView, out: NumView) -> NumView {
         wide_neg(num);
     }
 
-    for (var i = i32(word_count) / 2 - 1; i < i32(word_count); i++) {
+    // Leading zero words don't contribute to any partial product
+    var len = i32(word_count);
+    while len > 0 && arena[num.idx + u32(len) - 1u] == 0u {
+        len--;
+    }
+
+    for (var i = i32(word_count) / 2 - 1; i < len; i++) {
         let target_idx = 2 * i + 1 - i32(word_count);
         let ni = arena[num.idx + u32(i)];
+        if ni == 0u {
+            continue;
+        }
 
         var prod = carrying_mul(ni, ni, 0u);
         if target_idx >= 0 {
             let res = carrying_add(arena[out.idx + u32(target_idx)], prod.x, 0u);
             arena[out.idx + u32(target_idx)] = res.x;
+            // Squares of a word are at most 0xfffffffe_00000001, so the high word can't overflow
             prod.y += res.y;
         }
         wide_add_u32_at(out, u32(target_idx + 1), prod.y);
     }
 
-    for (var i = 0; i < i32(word_count); i++) {
+    for (var i = 0; i < len; i++) {
+        let ni = arena[num.idx + u32(i)];
+        if ni == 0u {
+            continue;
+        }
+        // Products below the word under the least significant one can't carry into the result
         // TODO: replace with max once it's added to naga
         let min_useful_index = i32(word_count) - i - 3;
         let start = select(i + 1, min_useful_index, i + 1 < min_useful_index);
-        for (var j = start; j < i32(word_count); j++) {
+        for (var j = start; j < len; j++) {
             let target_idx = i + j + 1 - i32(word_count);
 
-            let ni = arena[num.idx + u32(i)];
             let nj = arena[num.idx + u32(j)];
             let prod = carrying_mul(ni, nj, 0u);
             let double_lo = carrying_add(prod.x, prod.x, 0u);
@@ -254,7 +788,10 @@ fn wide_square(num: NumView, out: NumView) -> NumView {
             if target_idx >= 0 {
                 let res = carrying_add(arena[out.idx + u32(target_idx)], double_lo.x, 0u);
                 arena[out.idx + u32(target_idx)] = res.x;
-                double_hi.x += res.y;
+                // The high word can be all ones, so this may carry as well
+                let bumped = carrying_add(double_hi.x, res.y, 0u);
+                double_hi.x = bumped.x;
+                double_hi.y += bumped.y;
             }
             if target_idx >= -1 {
                 let res = carrying_add(arena[out.idx + u32(target_idx) + 1], double_hi.x, 0u);
@@ -272,6 +809,61 @@ fn wide_square(num: NumView, out: NumView) -> NumView {
     return out;
 }
 
+// Mutates `out` by writing the result of multiplication of `left` and `right` to it. Returns the
+// handle to `out`
+//
+// NOTE: `left` and `right` MUST be different numbers, `wide_square` squares a number
+// NOTE: Overflow is UB
+fn wide_mul(left: NumView, right: NumView, out: NumView) -> NumView {
+    for (var idx = out.idx; idx < out.idx + word_count; idx++) {
+        arena[idx] = 0u;
+    }
+
+    let leftneg = sign(wide_floor(left)) == -1;
+    if leftneg {
+        wide_neg(left);
+    }
+    let rightneg = sign(wide_floor(right)) == -1;
+    if rightneg {
+        wide_neg(right);
+    }
+
+    for (var i = 0; i < i32(word_count); i++) {
+        let li = arena[left.idx + u32(i)];
+        if li == 0u {
+            continue;
+        }
+        // Products below the word under the least significant one can't carry into the result
+        // TODO: replace with max once it's added to naga
+        let min_useful_index = i32(word_count) - i - 2;
+        let start = select(0, min_useful_index, 0 < min_useful_index);
+        for (var j = start; j < i32(word_count); j++) {
+            let target_idx = i + j + 1 - i32(word_count);
+
+            var prod = carrying_mul(li, arena[right.idx + u32(j)], 0u);
+            if target_idx >= 0 {
+                let res = carrying_add(arena[out.idx + u32(target_idx)], prod.x, 0u);
+                arena[out.idx + u32(target_idx)] = res.x;
+                // Products of words are at most 0xfffffffe_00000001, so the high word can't overflow
+                prod.y += res.y;
+            }
+            wide_add_u32_at(out, u32(target_idx + 1), prod.y);
+        }
+    }
+
+    if leftneg {
+        wide_neg(left);
+    }
+    if rightneg {
+        wide_neg(right);
+    }
+    if leftneg != rightneg {
+        wide_neg(out);
+    }
+
+    return out;
+}
+
 fn wide_add_u32_at(num: NumView, offset: u32, increment: u32) {
     var inc = increment;
     for (var i = offset; i < word_count; i++) {
@@ -293,23 +885,33 @@ fn wide_shr_words(num: NumView, shift: u32) -> NumView {
     return num;
 }
 
-// Compares wide fraction `left` to i32 `right`. Returns -1 if `left` is less than `right`, 0 if
-// numbers are equal and 1 if `left` is greater than `right`
-fn wide_cmp(left: NumView, right: i32) -> i32 {
-    let whole = wide_floor(left);
-    if whole < right {
-        return -1;
-    } else if whole > right {
-        return 1;
-    } else {
-        // If whole numbers are equal, check if fraction part is non-zero
-        for (var idx = left.idx + 1; idx < left.idx + word_count; idx++) {
-            if arena[idx] != 0 {
-                return 1;
-            }
+// Mutates `num` by logically right shifting it by `shift` bits. Returns the handle to the mutated
+// number
+//
+// NOTE: `shift` MUST be within 1..32
+fn wide_shr_bits(num: NumView, shift: u32) -> NumView {
+    let last = num.idx + word_count - 1;
+    for (var idx = num.idx; idx < last; idx++) {
+        arena[idx] = (arena[idx] >> shift) | (arena[idx + 1] << (32u - shift));
+    }
+    arena[last] = arena[last] >> shift;
+    return num;
+}
+
+// Returns true if `left` and `right` are at most one unit of the least significant word apart.
+// `tmp` is overwritten with the difference
+fn wide_near(left: NumView, right: NumView, tmp: NumView) -> bool {
+    wide_clone(left, tmp);
+    wide_sub(tmp, right);
+    // Differences of -1, 0 and 1 have nothing but the sign above the least significant word
+    let sign_word = select(0u, 0xffffffffu, wide_floor(tmp) < 0);
+    for (var i = 1u; i < word_count; i++) {
+        if arena[tmp.idx + i] != sign_word {
+            return false;
         }
-        return 0;
     }
+    let low = arena[tmp.idx];
+    return select(low <= 1u, low == 0xffffffffu, sign_word != 0u);
 }
 
 // Returns the whole part of the wide number, dropping the fraction
@@ -332,6 +934,30 @@ fn wide_from_u32(src: u32, dst: NumView) {
     }
 }
 
+// Returns the approximate f32 value of the wide number
+fn wide_to_f32(num: NumView) -> f32 {
+    var value = f32(wide_floor(num));
+    var scale = 1.0;
+    for (var i = 2u; i <= min(word_count, 3u); i++) {
+        scale /= 4294967296.0;
+        value += f32(arena[num.idx + word_count - i]) * scale;
+    }
+    return value;
+}
+
+// Initializes `dst` with the f32 value `src`, saturating values that don't fit into the whole part
+fn wide_from_f32(src: f32, dst: NumView) {
+    // Also replaces NaN, produced by inverting zero, with a value that escapes immediately
+    let value = select(1048576.0, clamp(src, -1048576.0, 1048576.0), src == src);
+    let whole = floor(value);
+    let fraction = min((value - whole) * 4294967296.0, 4294967040.0);
+    for (var idx = dst.idx; idx < dst.idx + word_count - 2; idx++) {
+        arena[idx] = 0u;
+    }
+    arena[dst.idx + word_count - 2] = u32(fraction);
+    arena[dst.idx + word_count - 1] = bitcast<u32>(i32(whole));
+}
+
 // ===== Bignum helper functions =====
 
 // Add with carry on overflow. `carry` MUST be 0 or 1
@@ -0,0 +1,104 @@
+//! How frames are presented and which adapter calculates them, chosen in the settings
+
+/// Presentation and adapter options of a context
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Frames wait for the display instead of tearing
+    pub vsync: bool,
+    pub power: PowerPreference,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            power: PowerPreference::default(),
+        }
+    }
+}
+
+/// Adapter to prefer when there's more than one, like the integrated and the discrete GPU of a
+/// laptop
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PowerPreference {
+    LowPower,
+    #[default]
+    HighPerformance,
+}
+
+impl PowerPreference {
+    pub const ALL: [PowerPreference; 2] =
+        [PowerPreference::LowPower, PowerPreference::HighPerformance];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerPreference::LowPower => "low_power",
+            PowerPreference::HighPerformance => "high_performance",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|power| power.name() == name)
+    }
+
+    /// Returns the translation key of the preference label
+    pub fn label_key(self) -> &'static str {
+        match self {
+            PowerPreference::LowPower => "power-low",
+            PowerPreference::HighPerformance => "power-high",
+        }
+    }
+
+    pub(super) fn to_wgpu(self) -> wgpu::PowerPreference {
+        match self {
+            PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+            PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+        }
+    }
+}
+
+/// Returns the present mode out of the `supported` ones. Frames wait for the display with
+/// `vsync`, otherwise they're shown at once where the surface allows it. Fifo is always supported
+pub(super) fn present_mode(supported: &[wgpu::PresentMode], vsync: bool) -> wgpu::PresentMode {
+    let preferred: &[wgpu::PresentMode] = if vsync {
+        &[wgpu::PresentMode::Fifo]
+    } else {
+        &[wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox]
+    };
+    preferred
+        .iter()
+        .copied()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(wgpu::PresentMode::Fifo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::PresentMode;
+
+    #[test]
+    fn present_modes_fall_back_to_fifo() {
+        let all = [
+            PresentMode::Fifo,
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+        ];
+        assert_eq!(present_mode(&all, true), PresentMode::Fifo);
+        assert_eq!(present_mode(&all, false), PresentMode::Immediate);
+        let mailbox = [PresentMode::Fifo, PresentMode::Mailbox];
+        assert_eq!(present_mode(&mailbox, false), PresentMode::Mailbox);
+        assert_eq!(present_mode(&[PresentMode::Fifo], false), PresentMode::Fifo);
+        // Surfaces that report nothing still get the mode every one of them supports
+        assert_eq!(present_mode(&[], false), PresentMode::Fifo);
+        assert_eq!(present_mode(&[], true), PresentMode::Fifo);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for power in PowerPreference::ALL {
+            assert_eq!(PowerPreference::from_name(power.name()), Some(power));
+        }
+        assert_eq!(PowerPreference::from_name("fast"), None);
+    }
+}
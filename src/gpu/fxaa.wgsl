@@ -0,0 +1,75 @@
+@group(0)
+@binding(0)
+var source: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// Fewer samples are taken on edges with smaller contrast
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const EDGE_THRESHOLD_MAX: f32 = 0.125;
+const REDUCE_MIN: f32 = 0.0078125;
+const REDUCE_MUL: f32 = 0.125;
+// Longest blur along an edge in pixels
+const SPAN_MAX: f32 = 8.0;
+
+// Single triangle covering the whole viewport
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn color_at(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(source, source_sampler, uv, 0.0).rgb;
+}
+
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+
+    let center = color_at(in.uv);
+    let luma_m = luma(center);
+    let luma_nw = luma(color_at(in.uv + vec2<f32>(-1.0, -1.0) * texel));
+    let luma_ne = luma(color_at(in.uv + vec2<f32>(1.0, -1.0) * texel));
+    let luma_sw = luma(color_at(in.uv + vec2<f32>(-1.0, 1.0) * texel));
+    let luma_se = luma(color_at(in.uv + vec2<f32>(1.0, 1.0) * texel));
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+    if luma_max - luma_min < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD_MAX) {
+        return vec4<f32>(center, 1.0);
+    }
+
+    // Blur along the edge, perpendicular to the luma gradient
+    var dir = vec2<f32>(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let dir_scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * dir_scale, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+    let inner = 0.5 * (color_at(in.uv + dir * (1.0 / 3.0 - 0.5)) + color_at(in.uv + dir * (2.0 / 3.0 - 0.5)));
+    let outer = 0.5 * inner + 0.25 * (color_at(in.uv - dir * 0.5) + color_at(in.uv + dir * 0.5));
+
+    // The wide blur crossed another edge
+    let luma_outer = luma(outer);
+    if luma_outer < luma_min || luma_outer > luma_max {
+        return vec4<f32>(inner, 1.0);
+    }
+    return vec4<f32>(outer, 1.0);
+}
@@ -0,0 +1,431 @@
+use crate::fractal::{FractalParams, IterationParams};
+use crate::palette::Palette;
+use crate::primitives::{Coordinates, Dimensions, ScaledDimensions, UnsupportedWordCount};
+
+use super::compute::{unpadded_counts, ComputeBindings, ComputeParams};
+use super::histogram::{Histogram, HistogramPass};
+use super::perturbation::{is_perturbed, repair_iterations, Perturbation, PerturbationPass};
+use super::render::{encodes_srgb, ColorParams, FragmentParams, RenderBindings};
+use super::{
+    create_compute_pipeline, create_render_pipeline, workgroup_width, ContextCreationError,
+    ROW_ALIGNMENT,
+};
+
+/// Format of the pixels returned by [`HeadlessContext::render_to_vec`]
+const SNAPSHOT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Max amount of iterations submitted at once, keeps deep snapshots from triggering GPU timeouts
+const ITERATIONS_PER_SUBMISSION: u32 = 256;
+
+/// GPU context that renders the fractal into memory instead of a window surface
+pub struct HeadlessContext {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+
+    compute_bind_group_layout: wgpu::BindGroupLayout,
+    render_bind_group_layout: wgpu::BindGroupLayout,
+    render_pipeline: wgpu::RenderPipeline,
+    /// Format of the render target, the pixels are encoded into sRGB by the shader or by it
+    target_format: wgpu::TextureFormat,
+    histogram_pass: HistogramPass,
+    perturbation_pass: PerturbationPass,
+    /// Deep views are calculated by perturbation instead of exactly
+    perturbation: bool,
+    /// Rows of the bands that every submission is split into, all of them at once if missing
+    band_rows: Option<u32>,
+
+    /// Width of compute workgroups supported by the adapter
+    workgroup_width: u32,
+}
+
+impl HeadlessContext {
+    /// Creates a context on the first available adapter, falling back to a software one
+    pub async fn new() -> Result<Self, ContextCreationError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+
+        let mut adapter = None;
+        for force_fallback_adapter in [false, true] {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    compatible_surface: None,
+                    force_fallback_adapter,
+                })
+                .await;
+            if adapter.is_some() {
+                break;
+            }
+        }
+        let adapter = adapter.ok_or(ContextCreationError::AdapterRequest)?;
+
+        let workgroup_width = workgroup_width(&adapter.limits());
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features: wgpu::Features::empty(),
+                    required_limits: adapter.limits(),
+                    label: Some("Headless Device"),
+                },
+                None,
+            )
+            .await?;
+
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&ComputeBindings::bind_group_layout_desc());
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&RenderBindings::bind_group_layout_desc());
+        let render_pipeline =
+            create_render_pipeline(&device, &render_bind_group_layout, SNAPSHOT_FORMAT);
+        let histogram_pass = HistogramPass::new(&device, workgroup_width);
+        let perturbation_pass = PerturbationPass::new(&device, workgroup_width);
+
+        Ok(Self {
+            device,
+            queue,
+            compute_bind_group_layout,
+            render_bind_group_layout,
+            render_pipeline,
+            target_format: SNAPSHOT_FORMAT,
+            histogram_pass,
+            perturbation_pass,
+            perturbation: false,
+            band_rows: None,
+            workgroup_width,
+        })
+    }
+
+    #[cfg(test)]
+    pub(super) fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    #[cfg(test)]
+    pub(super) fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Chooses between perturbation and the exact calculation of deep views, which is the default
+    /// for reproducible results
+    pub fn set_perturbation(&mut self, enabled: bool) {
+        self.perturbation = enabled;
+    }
+
+    /// Renders into an sRGB target, which encodes the colors itself instead of the shader. The
+    /// pixels are the same either way, up to the rounding of the encoder
+    pub fn set_srgb_target(&mut self, enabled: bool) {
+        self.target_format = if enabled {
+            SNAPSHOT_FORMAT.add_srgb_suffix()
+        } else {
+            SNAPSHOT_FORMAT
+        };
+        self.render_pipeline = create_render_pipeline(
+            &self.device,
+            &self.render_bind_group_layout,
+            self.target_format,
+        );
+    }
+
+    /// Splits every submission into bands of `rows` rows like the frames of the window, instead
+    /// of dispatching all rows at once
+    pub fn set_band_rows(&mut self, rows: Option<u32>) {
+        self.band_rows = rows.map(|rows| rows.max(1));
+    }
+
+    /// Calculates the fractal to `depth` and returns the colorized RGBA pixels, row by row
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_to_vec(
+        &mut self,
+        coords: &Coordinates,
+        fractal: FractalParams,
+        iteration: IterationParams,
+        colors: ColorParams,
+        palette: &Palette,
+        dims: Dimensions,
+        depth: u32,
+    ) -> Result<Vec<u8>, UnsupportedWordCount> {
+        let size = dims.scale_to(1.0);
+        let compute_bindings = self.calculate(coords, &fractal, iteration, size, depth)?;
+
+        let render_bindings =
+            RenderBindings::new(&self.device, &self.render_bind_group_layout, size).write(
+                &self.queue,
+                FragmentParams {
+                    size,
+                    depth,
+                    output_is_srgb: encodes_srgb(self.target_format).into(),
+                },
+            );
+        render_bindings.write_colors(&self.queue, colors);
+        render_bindings.write_palette(&self.queue, palette);
+        render_bindings.write_distance_shading(&self.queue, iteration.distance_estimation);
+
+        if colors.equalize {
+            let mut histogram = Histogram::new(&self.device);
+            let mut command_encoder =
+                self.device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("Snapshot Histogram Encoder"),
+                    });
+            histogram.encode(
+                &self.device,
+                &self.queue,
+                &mut command_encoder,
+                &self.histogram_pass,
+                &compute_bindings,
+                size,
+                depth,
+            );
+            self.queue.submit(Some(command_encoder.finish()));
+            histogram.request_map();
+            self.device.poll(wgpu::Maintain::Wait);
+            render_bindings.write_lut(&self.queue, histogram.receive().as_ref());
+        }
+
+        let target = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Snapshot Target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.target_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Snapshot Target View"),
+            ..Default::default()
+        });
+
+        let padded_row = (size.width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Snapshot Readback"),
+            size: (padded_row * size.height) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut command_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Snapshot Render Encoder"),
+                });
+        render_bindings.encode_copy(
+            &mut command_encoder,
+            &compute_bindings.result_buffer,
+            Some(&compute_bindings.escape_buffer),
+            iteration
+                .distance_estimation
+                .then_some(&compute_bindings.distance_buffer),
+        );
+        {
+            let mut rpass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Snapshot Render"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.render_pipeline);
+            rpass.set_bind_group(0, &render_bindings.bind_group, &[]);
+            rpass.draw(0..4, 0..1);
+        }
+        command_encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: None,
+                },
+            },
+            target.size(),
+        );
+        self.queue.submit(Some(command_encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Unable to map snapshot buffer")
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let mapped = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((size.width * size.height * 4) as usize);
+        for row in mapped.chunks_exact(padded_row as usize) {
+            pixels.extend_from_slice(&row[..(size.width * 4) as usize]);
+        }
+        drop(mapped);
+        readback_buffer.unmap();
+
+        Ok(pixels)
+    }
+
+    /// Calculates the fractal to `depth` and returns the iteration counts of the pixels, row by
+    /// row. Pixels found inside the set have a count of [`crate::SETTLED`]
+    pub fn iterations_to_vec(
+        &mut self,
+        coords: &Coordinates,
+        fractal: FractalParams,
+        iteration: IterationParams,
+        dims: Dimensions,
+        depth: u32,
+    ) -> Result<Vec<u32>, UnsupportedWordCount> {
+        let size = dims.scale_to(1.0);
+        let compute_bindings = self.calculate(coords, &fractal, iteration, size, depth)?;
+
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Iterations Readback"),
+            size: compute_bindings.sizes.result,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut command_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Iterations Readback Encoder"),
+                });
+        command_encoder.copy_buffer_to_buffer(
+            &compute_bindings.result_buffer,
+            0,
+            &readback_buffer,
+            0,
+            readback_buffer.size(),
+        );
+        self.queue.submit(Some(command_encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Unable to map iterations buffer")
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let mapped = slice.get_mapped_range();
+        let iterations = unpadded_counts(&mapped, size);
+        drop(mapped);
+        readback_buffer.unmap();
+
+        Ok(iterations)
+    }
+
+    /// Calculates the fractal to `depth` in submissions of a limited amount of iterations and
+    /// returns the bindings with the results
+    fn calculate(
+        &self,
+        coords: &Coordinates,
+        fractal: &FractalParams,
+        iteration: IterationParams,
+        size: ScaledDimensions,
+        depth: u32,
+    ) -> Result<ComputeBindings, UnsupportedWordCount> {
+        let compute_pipeline = create_compute_pipeline(
+            &self.device,
+            &self.compute_bind_group_layout,
+            coords.size(),
+            self.workgroup_width,
+        )?;
+        let perturbed = self.perturbation && is_perturbed(coords.size(), fractal, iteration);
+        let first_depth = depth.min(ITERATIONS_PER_SUBMISSION);
+        let compute_bindings = ComputeBindings::new(
+            &self.device,
+            &self.compute_bind_group_layout,
+            size,
+            coords.size(),
+            self.workgroup_width,
+        )
+        .write(
+            &self.queue,
+            &ComputeParams::new(size, coords, fractal, iteration, first_depth).with_repair(
+                repair_iterations(perturbed, first_depth, first_depth, depth),
+            ),
+        );
+        let mut perturbation = perturbed.then(|| {
+            Perturbation::new(
+                &self.device,
+                &self.perturbation_pass,
+                &compute_bindings,
+                size,
+                coords,
+                iteration,
+                (size.width / 2, size.height / 2),
+            )
+        });
+        if let Some(perturbation) = &mut perturbation {
+            perturbation.write(
+                &self.device,
+                &self.queue,
+                &self.perturbation_pass,
+                &compute_bindings,
+                first_depth,
+                true,
+            );
+        }
+        let band_rows = self.band_rows.unwrap_or(size.height).max(1);
+
+        let mut reached_depth = 0;
+        loop {
+            for start in (0..size.height).step_by(band_rows as usize) {
+                let workgroups = (
+                    size.aligned_width(ROW_ALIGNMENT) / self.workgroup_width,
+                    band_rows.min(size.height - start),
+                );
+                compute_bindings.write_row_offset(&self.queue, start);
+                if let Some(perturbation) = &mut perturbation {
+                    perturbation.write_row_offset(&self.queue, start);
+                }
+                let mut command_encoder =
+                    self.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Snapshot Compute Encoder"),
+                        });
+                {
+                    let mut cpass =
+                        command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                            label: Some("Snapshot Compute"),
+                            timestamp_writes: None,
+                        });
+                    if let Some(perturbation) = &perturbation {
+                        perturbation.encode(&mut cpass, &self.perturbation_pass, workgroups);
+                    }
+                    cpass.set_pipeline(&compute_pipeline);
+                    cpass.set_bind_group(0, &compute_bindings.bind_group, &[]);
+                    cpass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+                }
+                self.queue.submit(Some(command_encoder.finish()));
+            }
+
+            reached_depth = depth.min(reached_depth + ITERATIONS_PER_SUBMISSION);
+            if reached_depth == depth {
+                break;
+            }
+            let next_depth = depth.min(reached_depth + ITERATIONS_PER_SUBMISSION);
+            compute_bindings.write_iterate(&self.queue, next_depth);
+            if let Some(perturbation) = &mut perturbation {
+                perturbation.write(
+                    &self.device,
+                    &self.queue,
+                    &self.perturbation_pass,
+                    &compute_bindings,
+                    next_depth,
+                    false,
+                );
+                compute_bindings.write_repair(
+                    &self.queue,
+                    repair_iterations(true, next_depth - reached_depth, next_depth, depth),
+                );
+            }
+        }
+
+        Ok(compute_bindings)
+    }
+}
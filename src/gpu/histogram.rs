@@ -0,0 +1,341 @@
+//! Histogram equalization of the colors. Deep zooms put most pixels into a narrow band of counts,
+//! so colors are spread by the share of pixels with lower counts instead of the counts themselves
+
+use bytemuck::{Pod, Zeroable};
+use std::borrow::Cow;
+
+use crate::primitives::ScaledDimensions;
+
+use super::compute::ComputeBindings;
+use super::{with_workgroup_width, ROW_ALIGNMENT};
+
+/// Amount of buckets between the lowest escaped count and the depth
+pub const BUCKETS: usize = 1024;
+
+/// Size of the histogram buffer, the lowest count followed by the buckets
+const HISTOGRAM_SIZE: wgpu::BufferAddress = 4 * (1 + BUCKETS as wgpu::BufferAddress);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct HistogramParams {
+    size: ScaledDimensions,
+    width: u32,
+    depth: u32,
+}
+
+/// Equalized color positions of a range of counts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut {
+    /// Count the first bucket starts at
+    pub start: u32,
+    /// Amount of counts in each bucket
+    pub bucket_width: u32,
+    /// Share of escaped pixels below each bucket edge, [`BUCKETS`] + 1 of them
+    pub positions: Vec<f32>,
+}
+
+/// Returns the width of the buckets from the lowest escaped count to the depth
+pub fn bucket_width(lowest: u32, depth: u32) -> u32 {
+    depth.saturating_sub(lowest).div_ceil(BUCKETS as u32).max(1)
+}
+
+/// Returns the cumulative distribution of `buckets` at their edges, none if there's nothing in
+/// them
+pub fn cumulative_distribution(buckets: &[u32]) -> Option<Vec<f32>> {
+    let total: u64 = buckets.iter().map(|&count| count as u64).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut below = 0;
+    let mut positions = Vec::with_capacity(buckets.len() + 1);
+    positions.push(0.0);
+    for &count in buckets {
+        below += count as u64;
+        positions.push((below as f64 / total as f64) as f32);
+    }
+    Some(positions)
+}
+
+/// Pipelines of the histogram passes, shared by the views
+pub struct HistogramPass {
+    bind_group_layout: wgpu::BindGroupLayout,
+    lowest: wgpu::ComputePipeline,
+    buckets: wgpu::ComputePipeline,
+    workgroup_width: u32,
+}
+
+impl HistogramPass {
+    pub fn new(device: &wgpu::Device, workgroup_width: u32) -> Self {
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Histogram BindGroupLayout"),
+            entries: &[storage(0, true), storage(1, true), storage(2, false)],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Histogram Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(with_workgroup_width(
+                include_str!("histogram.wgsl"),
+                workgroup_width,
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Histogram PipelineLayout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        Self {
+            lowest: pipeline("Histogram Lowest Pipeline", "lowest"),
+            buckets: pipeline("Histogram Buckets Pipeline", "buckets"),
+            bind_group_layout,
+            workgroup_width,
+        }
+    }
+}
+
+/// Histogram of the results of a view, read back to build the color positions. Histograms are
+/// skipped while the previous one is in flight
+pub struct Histogram {
+    params_buffer: wgpu::Buffer,
+    histogram_buffer: wgpu::Buffer,
+    staging: wgpu::Buffer,
+    stage: HistogramStage,
+    /// Depth of the last histogram, none if the next frame should take one
+    last_depth: Option<u32>,
+}
+
+enum HistogramStage {
+    Idle,
+    /// Histogram of the depth is copied to the staging buffer by the submitted work
+    Copied(u32),
+    Mapping(u32, flume::Receiver<Result<(), wgpu::BufferAsyncError>>),
+}
+
+impl Histogram {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            params_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Histogram Params"),
+                size: std::mem::size_of::<HistogramParams>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            histogram_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Histogram"),
+                size: HISTOGRAM_SIZE,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            staging: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Histogram Staging"),
+                size: HISTOGRAM_SIZE,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            stage: HistogramStage::Idle,
+            last_depth: None,
+        }
+    }
+
+    /// Returns true if a frame calculated to `depth` should take a histogram. Every calculation
+    /// takes one with its first frame, further frames only once the depth has doubled since
+    pub fn is_due(&self, depth: u32) -> bool {
+        matches!(self.stage, HistogramStage::Idle)
+            && self
+                .last_depth
+                .is_none_or(|last| depth >= last.saturating_mul(2))
+    }
+
+    /// Records the histogram of the results calculated to `depth`, after the dispatch
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        pass: &HistogramPass,
+        bindings: &ComputeBindings,
+        dimensions: ScaledDimensions,
+        depth: u32,
+    ) {
+        if !matches!(self.stage, HistogramStage::Idle) {
+            return;
+        }
+        let aligned_width = dimensions.aligned_width(ROW_ALIGNMENT);
+        let params = HistogramParams {
+            size: ScaledDimensions {
+                width: aligned_width,
+                height: dimensions.height,
+            },
+            width: dimensions.width,
+            depth,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+        let mut initial = [0u32; 1 + BUCKETS];
+        initial[0] = u32::MAX;
+        queue.write_buffer(&self.histogram_buffer, 0, bytemuck::cast_slice(&initial));
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Histogram BindGroup"),
+            layout: &pass.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: bindings.result_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.histogram_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        encoder.push_debug_group("Histogram");
+        // The buckets start at the lowest count, so it has to be known before the second pass
+        for pipeline in [&pass.lowest, &pass.buckets] {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Histogram"),
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch_workgroups(aligned_width / pass.workgroup_width, dimensions.height, 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.histogram_buffer, 0, &self.staging, 0, HISTOGRAM_SIZE);
+        encoder.pop_debug_group();
+
+        self.stage = HistogramStage::Copied(depth);
+        self.last_depth = Some(depth);
+    }
+
+    /// Starts mapping the histogram copied by the work that has just been submitted
+    pub fn request_map(&mut self) {
+        if let HistogramStage::Copied(depth) = self.stage {
+            let (sender, result) = flume::bounded(1);
+            self.staging
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |r| {
+                    let _ = sender.send(r);
+                });
+            self.stage = HistogramStage::Mapping(depth, result);
+        }
+    }
+
+    /// Collects the mapped histogram, returns its color positions unless nothing has escaped.
+    /// Devices have to be polled for mapping to complete
+    pub fn receive(&mut self) -> Option<Lut> {
+        let HistogramStage::Mapping(depth, result) = &self.stage else {
+            return None;
+        };
+        let depth = *depth;
+        match result.try_recv() {
+            Ok(Ok(())) => {
+                let histogram: Vec<u32> =
+                    bytemuck::pod_collect_to_vec(&self.staging.slice(..).get_mapped_range());
+                self.staging.unmap();
+                self.stage = HistogramStage::Idle;
+                let (lowest, buckets) = (histogram[0], &histogram[1..]);
+                Some(Lut {
+                    start: lowest,
+                    bucket_width: bucket_width(lowest, depth),
+                    positions: cumulative_distribution(buckets)?,
+                })
+            }
+            Ok(Err(e)) => {
+                log::error!("Unable to read the histogram: {}", e);
+                self.stage = HistogramStage::Idle;
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Requests a histogram with the next frame, for a calculation that has been replaced
+    pub fn clear(&mut self) {
+        self.last_depth = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_histograms_are_linear() {
+        let positions = cumulative_distribution(&[5; BUCKETS]).unwrap();
+        assert_eq!(positions.len(), BUCKETS + 1);
+        for (edge, position) in positions.iter().enumerate() {
+            assert!((position - edge as f32 / BUCKETS as f32).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn narrow_bands_are_spread_over_the_colors() {
+        let mut buckets = [0; BUCKETS];
+        buckets[100] = 900;
+        buckets[101] = 100;
+        let positions = cumulative_distribution(&buckets).unwrap();
+        assert_eq!(positions[..=100], [0.0; 101]);
+        assert_eq!(positions[101], 0.9);
+        assert!(positions[102..].iter().all(|&position| position == 1.0));
+    }
+
+    #[test]
+    fn positions_grow_up_to_one() {
+        let buckets: Vec<u32> = (0..BUCKETS as u32).map(|i| i * 7919 % 13).collect();
+        let positions = cumulative_distribution(&buckets).unwrap();
+        assert!(positions.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(positions[BUCKETS], 1.0);
+
+        // Counts of huge views don't overflow
+        let positions = cumulative_distribution(&[u32::MAX, u32::MAX]).unwrap();
+        assert_eq!(positions, [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn empty_histograms_have_no_positions() {
+        assert_eq!(cumulative_distribution(&[0; BUCKETS]), None);
+        assert_eq!(cumulative_distribution(&[]), None);
+    }
+
+    #[test]
+    fn buckets_cover_the_escaped_counts() {
+        assert_eq!(bucket_width(0, 100), 1);
+        assert_eq!(bucket_width(0, 1024), 1);
+        assert_eq!(bucket_width(0, 1025), 2);
+        assert_eq!(bucket_width(50_000, 60_000), 10);
+        // No pixel has escaped if the lowest count is past the depth
+        assert_eq!(bucket_width(u32::MAX, 100), 1);
+    }
+
+    #[test]
+    fn shader_has_as_many_buckets() {
+        let source = include_str!("histogram.wgsl");
+        assert!(source.contains(&format!("array<atomic<u32>, {}>", BUCKETS)));
+        assert!(source.contains(&format!("{}u) / {}u", BUCKETS - 1, BUCKETS)));
+    }
+}
@@ -0,0 +1,58 @@
+struct Parameters {
+    // Aligned width and height of the result buffer
+    size: vec2<u32>,
+    // Width of the view within the aligned rows
+    width: u32,
+    // Pixels with this count haven't escaped yet
+    depth: u32,
+}
+
+struct Histogram {
+    // Lowest escaped count, where the first bucket starts
+    lowest: atomic<u32>,
+    buckets: array<atomic<u32>, 1024>,
+}
+
+@group(0)
+@binding(0)
+var<storage, read> params: Parameters;
+
+@group(0)
+@binding(1)
+var<storage, read> iterations: array<u32>;
+
+@group(0)
+@binding(2)
+var<storage, read_write> histogram: Histogram;
+
+// Returns the count of the pixel, or the depth for the row padding so that it isn't counted
+fn pixel_count(global_id: vec3<u32>) -> u32 {
+    if global_id.x >= params.width || global_id.y >= params.size.y {
+        return params.depth;
+    }
+    return iterations[global_id.y * params.size.x + global_id.x];
+}
+
+// First pass, finds the lowest escaped count
+@compute
+@workgroup_size(64)
+fn lowest(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let count = pixel_count(global_id);
+    if count < params.depth {
+        atomicMin(&histogram.lowest, count);
+    }
+}
+
+// Second pass, counts the pixels in buckets of equal width from the lowest count to the depth
+@compute
+@workgroup_size(64)
+fn buckets(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let count = pixel_count(global_id);
+    if count >= params.depth {
+        return;
+    }
+    let lowest = atomicLoad(&histogram.lowest);
+    let width = max(1u, (params.depth - lowest + 1023u) / 1024u);
+    let bucket = min((count - lowest) / width, 1023u);
+    atomicAdd(&histogram.buckets[bucket], 1u);
+}
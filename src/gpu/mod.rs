@@ -2,51 +2,385 @@ use iced_wgpu::core as iced_core;
 use iced_winit::runtime as iced_runtime;
 use std::borrow::Cow;
 use std::cmp::min;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use thiserror::Error;
 use winit::window::Window;
 
-use crate::fps_balancer::FpsBalancer;
-use crate::primitives::{Coordinates, Dimensions, ScaledDimensions};
-
+use crate::calibrations::{self, Calibrations};
+use crate::fps_balancer::{FpsBalancer, Workload};
+use crate::fractal::{FractalParams, IterationParams};
+use crate::palette::Palette;
+use crate::primitives::{
+    check_word_count, Coordinates, Dimensions, Point, Rect, ScaledDimensions, UnsupportedWordCount,
+};
+use crate::timer::{Clock, SystemClock};
+
+mod bands;
+mod capture;
 mod compute;
+mod display;
+mod fxaa;
+mod headless;
+mod histogram;
+mod perturbation;
+mod pipeline_cache;
+mod poster;
+mod preview;
 mod render;
-
-use self::compute::{ComputeBindings, ComputeParams};
-use self::render::{FragmentParams, RenderBindings};
+#[cfg(test)]
+mod shader_ref;
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+mod snapshot;
+mod timestamps;
+mod watchdog;
+mod work_done;
+
+pub use self::capture::RgbaImage;
+pub use self::compute::{GLITCHED, SETTLED};
+pub use self::display::{DisplayOptions, PowerPreference};
+pub use self::headless::HeadlessContext;
+pub use self::poster::{PosterError, MAX_POSTER_SIDE};
+pub use self::render::{ColorParams, DEFAULT_GAMMA, GAMMA_RANGE};
+pub use self::snapshot::{Iterations, Snapshot, SnapshotKey};
+pub use self::watchdog::Watchdog;
+
+use self::bands::{band_rows, Bands};
+use self::capture::{Capture, CAPTURE_FORMAT};
+use self::compute::{
+    pixel_offset, settled_count, tile_count, unpadded_counts, ComputeBindings, ComputeParams,
+    PixelReadback, TileCounter,
+};
+use self::fxaa::Fxaa;
+use self::histogram::{Histogram, HistogramPass, Lut};
+use self::perturbation::{is_perturbed, repair_iterations, Perturbation, PerturbationPass};
+use self::pipeline_cache::PipelineCache;
+use self::poster::{tile_size, Poster};
+use self::preview::JuliaPreview;
+use self::render::{encodes_srgb, FragmentParams, RenderBindings, Reprojection, Selection};
+use self::snapshot::{Progress, Readback};
+use self::timestamps::PassTimer;
+use self::watchdog::Verdict;
+use self::work_done::WorkDone;
 
 const COMPUTE_SHADER_TEMPLATE: &str = include_str!("compute.wgsl");
 
+/// Workgroup size declaration of the compute shaders, replaced with the adapter specific one
+const WORKGROUP_SIZE_TEMPLATE: &str = "@workgroup_size(64)";
+
+/// Word count declaration of the compute shader, replaced with the one of the view
+const WORD_COUNT_TEMPLATE: &str = "const word_count: u32 = 8;";
+
+/// Alignment of the rows of compute buffers and view textures in pixels. Rows of texture copies
+/// have to be aligned to 256 bytes
+pub(crate) const ROW_ALIGNMENT: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT / 4;
+
+/// Returns the capacity of a buffer or texture that has room for `required`. Ones without room
+/// grow by at least half, up to `limit`, so that growing in small steps only reallocates a few
+/// times
+fn grow_capacity(capacity: u64, required: u64, limit: u64) -> u64 {
+    if required <= capacity {
+        return capacity;
+    }
+    (capacity + capacity / 2).min(limit).max(required)
+}
+
+/// Depth at which reprojected frames have faded out. Only frames that reached it are kept to be
+/// reprojected, the ones before it keep showing the last frame that did
+const REPROJECTION_DEPTH: u32 = 1000;
+
+/// Surface format of the deep color mode. Like sRGB formats it takes linear colors, so shaders
+/// and the UI draw to it unchanged, while 10-bit unorm formats would need manual encoding
+const DEEP_COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Diagnostics requested for bug reports
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    /// Directory to record a wgpu API trace into
+    pub trace_dir: Option<PathBuf>,
+    /// Enable backend validation and debug information
+    pub debug: bool,
+}
+
+/// Device shared with the threads compiling pipelines in the background. The web has no threads,
+/// and its devices can't be sent to them
+#[cfg(not(target_arch = "wasm32"))]
+type SharedDevice = Arc<wgpu::Device>;
+#[cfg(target_arch = "wasm32")]
+type SharedDevice = wgpu::Device;
+
 pub struct GpuContext<'w> {
-    device: wgpu::Device,
+    instance: wgpu::Instance,
+    device: SharedDevice,
     queue: wgpu::Queue,
+    /// Diagnostics the device was requested with, requested again by a rebuild
+    diagnostics: Diagnostics,
 
     config: wgpu::SurfaceConfiguration,
     surface: wgpu::Surface<'w>,
+    /// Formats supported by the surface, preferred first
+    surface_formats: Vec<wgpu::TextureFormat>,
+    /// Present modes supported by the surface
+    present_modes: Vec<wgpu::PresentMode>,
+    /// Presentation and adapter chosen in the settings, the adapter is requested again by a
+    /// rebuild
+    display: DisplayOptions,
 
     pub ui_renderer: iced_wgpu::Renderer,
     pub ui_debug: iced_runtime::Debug,
     viewport: iced_wgpu::graphics::Viewport,
 
     compute_bind_group_layout: wgpu::BindGroupLayout,
-    compute_pipeline: wgpu::ComputePipeline,
-    compute_bindings: ComputeBindings,
-    calibration_bindings: ComputeBindings,
+    /// Compute pipelines of the word counts used so far
+    compute_pipelines: PipelineCache,
+    /// Compute pipeline being compiled in the background, until it's received
+    compilation: Option<Compilation>,
+    /// Called from the compiling thread once its pipeline can be received
+    pipeline_notifier: Option<Arc<dyn Fn() + Send + Sync>>,
 
     render_bind_group_layout: wgpu::BindGroupLayout,
     render_pipeline: wgpu::RenderPipeline,
-    render_bindings: RenderBindings,
+
+    /// Views drawn to regions of the window. Each one is calculated independently
+    views: Vec<View>,
+
+    /// Inset drawn on top of the views
+    julia_preview: JuliaPreview,
+
+    /// Histograms of the views for equalized colors
+    histogram_pass: HistogramPass,
+
+    /// Deltas of the perturbed views
+    perturbation_pass: PerturbationPass,
+    /// Deep views are calculated by perturbation instead of exactly
+    perturbation: bool,
+    /// Views show their previous frames while new ones converge
+    reprojection: bool,
+
+    /// Bands of rows of the frame in progress, until it's presented
+    bands: Option<Bands>,
+    /// Times the compute passes on the GPU if it supports timestamps, otherwise the balancer
+    /// times frames with the wall clock
+    pass_timer: Option<PassTimer>,
+    /// Tells when the watched submissions are done on the web, where polling the device doesn't
+    work_done: Option<WorkDone>,
+    /// Calibrated speeds of the adapters used so far, restored on startup
+    calibrations: Calibrations,
+    /// Adapter the speeds of the current calibration are saved for
+    adapter: String,
+    /// Longest time a band of rows is expected to take
+    band_budget_ms: f64,
+
+    /// Post-process antialiasing of the views, disabled if missing
+    fxaa: Option<Fxaa>,
 
     state: State,
-    params: ParamsState,
+
+    /// Calculation iterations limit
+    max_depth: u32,
+    /// Escape condition shared by all views
+    iteration: IterationParams,
+    /// Coloring shared by all views
+    colors: ColorParams,
+    /// Colors of all views
+    palette: Palette,
+    /// Views keep their depth instead of calculating further, unless their parameters change
+    paused: bool,
+
+    /// Surface size to be applied before the next frame is acquired
+    surface_update: Option<Dimensions>,
+
+    /// Device errors that weren't captured by an error scope
+    device_errors: flume::Receiver<String>,
+    /// Raised once the device or the surface is lost, until the context is rebuilt
+    device_lost: Arc<AtomicBool>,
+
+    /// Completed readbacks with the index of their view, until taken by the app
+    snapshots: Vec<(usize, Snapshot)>,
+    /// Captures of views asked for and not yet read back
+    captures: Vec<Capture>,
+    /// Poster in progress, calculated between the frames
+    poster: Option<Poster>,
+
+    /// Width of compute workgroups supported by the adapter
+    workgroup_width: u32,
 }
 
 struct State {
-    /// Current calculated depth
-    depth: u32,
     /// Amount of iterations for this invocation
     fps_balancer: FpsBalancer,
+    /// Pipeline health
+    status: Status,
+    /// Description of the failure that degraded the context, until taken by the app
+    failure: Option<String>,
+    /// Watches the submitted work for completion
+    watchdog: Watchdog,
+    /// Time reference for the watchdog
+    clock: SystemClock,
+    /// Last watchdog warning, until taken by the app
+    warning: Option<Warning>,
+    /// Amount of times the surface had to be reconfigured to acquire a frame
+    surface_retries: u32,
+}
+
+impl State {
+    /// Checks on the submitted work at `now_ms`. Work that never completes is taken for a lost
+    /// device, `device_lost` is raised for the app to rebuild the context
+    fn watch(&mut self, now_ms: f64, device_lost: &AtomicBool) -> Verdict {
+        let verdict = self.watchdog.check(now_ms);
+        match verdict {
+            Verdict::Waiting => {}
+            Verdict::Stalled => {
+                log::error!("Submitted GPU work hasn't completed in time, waiting once more");
+                self.warning = Some(Warning::Stalled);
+            }
+            Verdict::Lost => {
+                log::error!("Submitted GPU work has never completed, rebuilding the device");
+                self.fps_balancer.reset();
+                self.warning = Some(Warning::Lost);
+                device_lost.store(true, Ordering::Relaxed);
+            }
+        }
+        verdict
+    }
+
+    /// Degrades to `working_word_count` for every error the device has reported outside of an
+    /// error scope
+    fn check_errors(
+        &mut self,
+        errors: &flume::Receiver<String>,
+        working_word_count: impl Fn() -> usize,
+    ) {
+        while let Ok(error) = errors.try_recv() {
+            self.degrade(working_word_count(), error);
+        }
+    }
+
+    fn degrade(&mut self, word_count: usize, error: String) {
+        log::error!(
+            "Compute pipeline failure, falling back to {} words: {}",
+            word_count,
+            error
+        );
+        self.status = Status::Degraded { word_count };
+        self.failure = Some(error);
+    }
+
+    fn retry(&mut self) {
+        self.status = Status::Running;
+    }
+}
+
+/// Compute pipeline of a word count compiled by a background thread
+struct Compilation {
+    word_count: usize,
+    pipeline: flume::Receiver<wgpu::ComputePipeline>,
+}
+
+/// GPU resources and calculation progress of a single view
+struct View {
+    /// Region of the window the view is drawn to
+    rect: Rect,
+
+    compute_pipeline: Arc<wgpu::ComputePipeline>,
+    compute_bindings: ComputeBindings,
+    calibration_bindings: ComputeBindings,
+    render_bindings: RenderBindings,
+
+    /// Current calculated depth
+    depth: u32,
     /// Current task in progress
     task: Option<Task>,
+
+    /// Readback of the calculation progress, the view isn't calculated meanwhile
+    readback: Option<Readback>,
+    /// Result buffer was restored from a snapshot and has to be copied to the texture
+    restored: bool,
+    tile_counter: TileCounter,
+    pixel_readback: PixelReadback,
+    histogram: Histogram,
+    /// Equalized colors of the last histogram read back
+    lut: Option<Lut>,
+    /// Reference orbit and deltas of a perturbed view, which the exact shader only repairs
+    perturbation: Option<Perturbation>,
+    /// Coordinates of the results in the textures, known once the first update is applied
+    shown: Option<Coordinates>,
+    /// Frame kept in the previous textures to be reprojected
+    previous_frame: Option<PreviousFrame>,
+
+    params: ParamsState,
+}
+
+/// Frame a view showed before its coordinates changed
+struct PreviousFrame {
+    coords: Coordinates,
+    size: ScaledDimensions,
+    depth: u32,
+}
+
+/// Problem with the submitted GPU work detected by the watchdog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum Warning {
+    #[error("GPU work is taking unusually long to complete")]
+    Stalled,
+    #[error("GPU work has never completed, restarting the calculation")]
+    Lost,
+    #[error("The GPU device was lost and has been recreated, restarting the calculation")]
+    DeviceLost,
+}
+
+/// Way to recover from a frame that couldn't be acquired
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceRecovery {
+    /// Try again with the next frame
+    Redraw,
+    /// Resize the surface and the views to the window, the surface was configured before the
+    /// window was resized
+    Resize(Dimensions),
+    /// Rebuild the context with the next frame, reconfiguring the surface hasn't helped
+    Rebuild,
+    /// Nothing can be drawn without memory
+    Exit,
+}
+
+/// Returns how to recover from `error` of a surface configured with `configured` dimensions in a
+/// window of `window` ones. Frames are only acquired again after reconfiguring the surface once
+fn surface_recovery(
+    error: &wgpu::SurfaceError,
+    configured: Dimensions,
+    window: Dimensions,
+) -> SurfaceRecovery {
+    match error {
+        wgpu::SurfaceError::Timeout => SurfaceRecovery::Redraw,
+        wgpu::SurfaceError::OutOfMemory => SurfaceRecovery::Exit,
+        wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated if configured != window => {
+            SurfaceRecovery::Resize(window)
+        }
+        wgpu::SurfaceError::Outdated => SurfaceRecovery::Redraw,
+        wgpu::SurfaceError::Lost => SurfaceRecovery::Rebuild,
+    }
+}
+
+/// Health of the compute pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    /// Coordinates are calculated with the word count they come with
+    Running,
+    /// Pipeline creation has failed, coordinates are calculated with the last working word count
+    /// until a retry
+    Degraded { word_count: usize },
+}
+
+impl Status {
+    /// Returns the word count used to calculate coordinates of `requested` words
+    fn word_count(&self, requested: usize) -> usize {
+        match self {
+            Status::Running => requested,
+            Status::Degraded { word_count } => *word_count,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -57,20 +391,33 @@ enum Task {
 
 /// Fractal calculation parameters that CPU is responsible to keep track of
 struct ParamsState {
-    /// Calculation iterations limit
-    max_depth: u32,
-
     /// View scale factor
     scale: f64,
 
     /// The amount of words in each number in comupte shader
     word_count: usize,
 
+    /// The last word count known to produce a valid pipeline
+    working_word_count: usize,
+
+    /// The compute buffers track the derivative and estimate distances
+    distance_estimation: bool,
+
     /// View dimensions, scaled by view_scale
     scaled_dimensions: ScaledDimensions,
 
+    /// Calculated fractal
+    fractal: FractalParams,
+
     /// Parameter update to be applied on the next iteration start
     update: Option<ParamsUpdate>,
+
+    /// Coordinates waiting for the pipeline of their word count, which are shown with the current
+    /// word count meanwhile
+    awaiting: Option<Coordinates>,
+
+    /// Calculation the compute buffers belong to, known once the first update is applied
+    key: Option<SnapshotKey>,
 }
 
 enum ParamsUpdate {
@@ -78,18 +425,57 @@ enum ParamsUpdate {
         coords: Coordinates,
     },
     Resize {
-        dimensions: Dimensions,
+        rect: Rect,
         scale: f64,
         coords: Coordinates,
     },
 }
 
+/// Returns the pixels of the views of `dimensions` that the iterations are balanced for
+fn balanced_pixels(dimensions: ScaledDimensions) -> f64 {
+    (dimensions.aligned_width(ROW_ALIGNMENT) * dimensions.height) as f64
+}
+
+/// Returns the name the calibration of `info` is saved under. Adapters on other backends or
+/// drivers run at different speeds. It's a single trimmed line, as it's saved
+fn adapter_key(info: &wgpu::AdapterInfo) -> String {
+    format!(
+        "{:04x}:{:04x} {:?} {} {}",
+        info.vendor, info.device, info.backend, info.name, info.driver_info
+    )
+    .replace(['\n', '\r'], " ")
+    .trim()
+    .to_owned()
+}
+
+/// Size of the calibration frames, independent of the view. Their time is scaled by the pixels
+/// of the view to calibrate it
+const CALIBRATION_GRID: ScaledDimensions = ScaledDimensions {
+    width: 512,
+    height: 320,
+};
+
+#[allow(clippy::excessive_precision)]
 fn calibration_coords(size: usize, precision: usize) -> Coordinates {
     // Coordinates of the top left corner of the biggest 16:10 rectangle that can be inscribed in the main cardioid
     // Thanks to Koitz for calculating them for me
     Coordinates::new_magnified(-0.6827560061104002, -0.2914862451646308, size, precision)
+        .expect("Calibration coordinates must be representable")
+}
+
+/// Returns the parameters calibration frames iterate with. They only keep the ones that change
+/// how long an iteration takes
+fn calibration_iteration(iteration: IterationParams) -> IterationParams {
+    IterationParams {
+        distance_estimation: iteration.distance_estimation,
+        ..Default::default()
+    }
 }
 
+#[derive(Debug, Error)]
+#[error("Deep color is not supported by this display")]
+pub struct DeepColorUnsupported;
+
 #[derive(Debug, Error)]
 pub enum ContextCreationError {
     #[error("Create surface error: {0}")]
@@ -100,309 +486,1021 @@ pub enum ContextCreationError {
     AdapterRequest,
     #[error("Request device error: {0}")]
     DeviceRequest(#[from] wgpu::RequestDeviceError),
+    #[error(transparent)]
+    UnsupportedWordCount(#[from] UnsupportedWordCount),
 }
 
-impl<'w> GpuContext<'w> {
-    pub async fn new(
-        window: &'w Window,
-        dimensions: Dimensions,
-        scale: f64,
-        coords: &Coordinates,
-        fps: f64,
-        max_depth: u32,
-    ) -> Result<Self, ContextCreationError> {
-        let scaled_dimensions = dimensions.scale_to(scale);
+/// Returns true if a frame has to dispatch the calculation of a view, rather than only draw its
+/// results again with the current colors. Converged views have no pixels left to iterate
+fn needs_compute(
+    update_pending: bool,
+    restored: bool,
+    depth: u32,
+    max_depth: u32,
+    converged: bool,
+) -> bool {
+    update_pending || restored || (depth < max_depth && !converged)
+}
 
-        let viewport = iced_wgpu::graphics::Viewport::with_physical_size(
-            iced_core::Size::new(dimensions.width, dimensions.height),
-            scale,
-        );
+impl View {
+    /// Returns the calculation the frame times of the view are balanced for
+    fn workload(&self) -> Workload {
+        Workload {
+            word_count: self.params.word_count,
+            distance_estimation: self.params.distance_estimation,
+        }
+    }
 
-        let state = State {
-            depth: 0,
-            fps_balancer: FpsBalancer::new(fps),
-            task: None,
-        };
+    /// Returns true if the last dispatch left no workgroup iterating. Views that haven't reached
+    /// the reprojection depth go on, so that the previous frame fades out
+    fn is_converged(&self) -> bool {
+        self.tile_counter.active() == Some(0) && self.depth >= REPROJECTION_DEPTH
+    }
 
-        let params = ParamsState {
-            max_depth,
-            scale,
-            word_count: coords.size(),
-            scaled_dimensions,
-            update: None,
-        };
+    /// Returns the depth of the results the next frame draws
+    fn drawn_depth(&self) -> u32 {
+        match self.task {
+            Some(Task::Render(depth)) => depth,
+            _ => self.depth,
+        }
+    }
 
-        // GPU handle
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            // Should opt-out of WebGL here as it doesn't support compute shaders, but
-            // wgpu::Instance::request_adapter panics on unsupported platforms otherwise
-            backends: wgpu::Backends::all(),
-            flags: wgpu::InstanceFlags::default(),
-            dx12_shader_compiler: wgpu::Dx12Compiler::default(),
-            gles_minor_version: wgpu::Gles3MinorVersion::default(),
-        });
+    /// Returns the region, scale and coordinates the view is calculated with once its pending
+    /// update is applied
+    fn target(&self) -> (Rect, f64, &Coordinates) {
+        match &self.params.update {
+            Some(ParamsUpdate::Resize {
+                rect,
+                scale,
+                coords,
+            }) => (*rect, *scale, coords),
+            Some(ParamsUpdate::Move { coords }) => (self.rect, self.params.scale, coords),
+            None => (
+                self.rect,
+                self.params.scale,
+                self.shown
+                    .as_ref()
+                    .expect("Views are created with an update"),
+            ),
+        }
+    }
 
-        let surface = instance.create_surface(window)?;
+    /// Returns true if the frame in progress dispatches the calculation of the view. Snapshots
+    /// don't have the escape fractions, a pass to the restored depth calculates them again
+    /// without iterating further
+    fn dispatches(&self, max_depth: u32) -> bool {
+        self.readback.is_none()
+            && ((self.depth < max_depth && self.task.is_some()) || self.restored)
+    }
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .ok_or(ContextCreationError::AdapterRequest)?;
+    /// Returns the pixel of the compute buffers that the reference orbit starts at, the center
+    /// of the view keeps the glitches away from where zooms aim
+    fn reference_pixel(&self) -> (u32, u32) {
+        let dimensions = self.params.scaled_dimensions;
+        (dimensions.width / 2, dimensions.height / 2)
+    }
 
-        let mut device_limits = wgpu::Limits::default().using_resolution(adapter.limits());
+    /// Keeps the frame of `source`, the render bindings it was drawn with and calculated to
+    /// `depth`, before the textures start over for `coords`. Frames that haven't reached
+    /// `fade_depth` leave the one kept before them in place
+    #[allow(clippy::too_many_arguments)]
+    fn keep_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: Option<&RenderBindings>,
+        coords: &Coordinates,
+        depth: u32,
+        fade_depth: u32,
+        enabled: bool,
+    ) {
+        let shown = self.shown.replace(coords.clone());
+        if !enabled {
+            self.previous_frame = None;
+            self.render_bindings.write_reprojection(queue, None);
+            return;
+        }
 
-        // TODO: Save the limit and use it for buffer sizing
-        device_limits.max_storage_buffer_binding_size =
-            adapter.limits().max_storage_buffer_binding_size;
+        let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Keep Frame Encoder"),
+        });
+        let replaced = source.is_some();
+        let source = source.unwrap_or(&self.render_bindings);
+        match shown {
+            Some(shown) if depth >= fade_depth || self.previous_frame.is_none() => {
+                let size = self
+                    .render_bindings
+                    .encode_keep(&mut command_encoder, source, false);
+                self.previous_frame = Some(PreviousFrame {
+                    coords: shown,
+                    size,
+                    depth,
+                });
+            }
+            // Resized bindings don't have the kept frame yet
+            _ if replaced => {
+                if let Some(frame) = &mut self.previous_frame {
+                    let size = self
+                        .render_bindings
+                        .encode_keep(&mut command_encoder, source, true);
+                    frame.size.width = frame.size.width.min(size.width);
+                    frame.size.height = frame.size.height.min(size.height);
+                }
+            }
+            _ => {}
+        }
+        queue.submit(Some(command_encoder.finish()));
+
+        let reprojection = self.previous_frame.as_ref().and_then(|frame| {
+            Some(Reprojection {
+                transform: coords.pixel_transform_to(&frame.coords)?,
+                previous_size: frame.size,
+                previous_depth: frame.depth,
+                fade_depth,
+            })
+        });
+        self.render_bindings.write_reprojection(queue, reprojection);
+    }
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
-                    required_limits: device_limits,
-                    label: None,
-                },
-                None, // Trace path
+    /// Starts the deltas of the view over for `coords` if it's `perturbed`, after its compute
+    /// bindings have been written
+    #[allow(clippy::too_many_arguments)]
+    fn start_perturbation(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pass: &PerturbationPass,
+        coords: &Coordinates,
+        iteration: IterationParams,
+        depth: u32,
+        perturbed: bool,
+    ) {
+        self.perturbation = perturbed.then(|| {
+            Perturbation::new(
+                device,
+                pass,
+                &self.compute_bindings,
+                self.params.scaled_dimensions,
+                coords,
+                iteration,
+                self.reference_pixel(),
             )
-            .await?;
-
-        let compute_shader_src = COMPUTE_SHADER_TEMPLATE.replace(
-            "const word_count: u32 = 8;",
-            &format!("const word_count: u32 = {};", params.word_count),
-        );
-
-        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Owned(compute_shader_src)),
-        });
-        let render_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("render.wgsl"))),
         });
+        if let Some(perturbation) = &mut self.perturbation {
+            perturbation.write(device, queue, pass, &self.compute_bindings, depth, true);
+        }
+    }
 
-        let compute_bind_group_layout =
-            device.create_bind_group_layout(&ComputeBindings::bind_group_layout_desc());
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        compute_bind_group_layout: &wgpu::BindGroupLayout,
+        render_bind_group_layout: &wgpu::BindGroupLayout,
+        compute_pipeline: Arc<wgpu::ComputePipeline>,
+        workgroup_width: u32,
+        rect: Rect,
+        scale: f64,
+        coords: &Coordinates,
+        fractal: FractalParams,
+        iteration: IterationParams,
+        colors: ColorParams,
+        palette: &Palette,
+        present_iterations: u32,
+        output_is_srgb: bool,
+    ) -> Result<Self, UnsupportedWordCount> {
+        check_word_count(coords.size())?;
+        let scaled_dimensions = rect.dimensions().scale_to(scale);
 
-        let present_iterations = state.fps_balancer.present_iterations(params.word_count);
         let compute_bindings = ComputeBindings::new(
-            &device,
-            &compute_bind_group_layout,
+            device,
+            compute_bind_group_layout,
             scaled_dimensions,
             coords.size(),
+            workgroup_width,
         )
         .write(
-            &queue,
-            &ComputeParams::new(scaled_dimensions, coords, present_iterations),
+            queue,
+            &ComputeParams::new(
+                scaled_dimensions,
+                coords,
+                &fractal,
+                iteration,
+                present_iterations,
+            ),
         );
         let calibration_bindings = ComputeBindings::new(
-            &device,
-            &compute_bind_group_layout,
-            scaled_dimensions,
+            device,
+            compute_bind_group_layout,
+            CALIBRATION_GRID,
             coords.size(),
+            workgroup_width,
         )
         .write(
-            &queue,
+            queue,
             &ComputeParams::new(
-                scaled_dimensions,
+                CALIBRATION_GRID,
                 &calibration_coords(coords.size(), coords.precision()),
+                &FractalParams::default(),
+                calibration_iteration(iteration),
                 present_iterations,
-            ),
+            )
+            .without_interior_checks(),
         );
 
-        let compute_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Compute PipelineLayout"),
-                bind_group_layouts: &[&compute_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Compute Pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            module: &compute_shader,
-            entry_point: "main",
-        });
-
-        let render_bind_group_layout =
-            device.create_bind_group_layout(&RenderBindings::bind_group_layout_desc());
-
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline"),
-                bind_group_layouts: &[&render_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
         let render_bindings =
-            RenderBindings::new(&device, &render_bind_group_layout, scaled_dimensions).write(
-                &queue,
+            RenderBindings::new(device, render_bind_group_layout, scaled_dimensions).write(
+                queue,
                 FragmentParams {
                     size: scaled_dimensions,
                     depth: 0,
+                    output_is_srgb: output_is_srgb.into(),
                 },
             );
-
-        let swapchain_capabilities = surface.get_capabilities(&adapter);
-        let swapchain_format = swapchain_capabilities.formats[0];
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &render_shader,
-                entry_point: "vs_main",
-                buffers: &[],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &render_shader,
-                entry_point: "fs_main",
-                targets: &[Some(swapchain_format.into())],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleStrip,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Front),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
-
-        let mut config = surface
-            .get_default_config(&adapter, dimensions.width, dimensions.height)
-            .ok_or(ContextCreationError::SurfaceUnsupported)?;
-        config.present_mode = wgpu::PresentMode::AutoNoVsync;
-        surface.configure(&device, &config);
-
-        let ui_renderer = iced_wgpu::Renderer::new(
-            iced_wgpu::Backend::new(
-                &device,
-                &queue,
-                iced_wgpu::Settings::default(),
-                swapchain_format,
-            ),
-            iced::Font::default(),
-            iced::Pixels(16.0),
-        );
-        let ui_debug = iced_runtime::Debug::new();
+        render_bindings.write_colors(queue, colors);
+        render_bindings.write_palette(queue, palette);
+        render_bindings.write_distance_shading(queue, iteration.distance_estimation);
 
         Ok(Self {
-            device,
-            queue,
-            config,
-            surface,
-            ui_renderer,
-            ui_debug,
-            viewport,
-            compute_bind_group_layout,
+            rect,
             compute_pipeline,
             compute_bindings,
             calibration_bindings,
-            render_bind_group_layout,
-            render_pipeline,
             render_bindings,
-            state,
-            params,
+            depth: 0,
+            task: None,
+            readback: None,
+            restored: false,
+            tile_counter: TileCounter::new(device),
+            pixel_readback: PixelReadback::new(device),
+            histogram: Histogram::new(device),
+            lut: None,
+            perturbation: None,
+            shown: None,
+            previous_frame: None,
+            params: ParamsState {
+                scale,
+                word_count: coords.size(),
+                working_word_count: coords.size(),
+                distance_estimation: iteration.distance_estimation,
+                scaled_dimensions,
+                fractal,
+                update: None,
+                awaiting: None,
+                key: None,
+            },
         })
     }
+}
 
-    pub fn rescale_ui(&mut self, window_scale: f64) {
-        self.viewport = iced_wgpu::graphics::Viewport::with_physical_size(
-            self.viewport.physical_size(),
-            window_scale,
-        );
-    }
-
-    pub fn resize_and_update_params(
-        &mut self,
+impl<'w> GpuContext<'w> {
+    /// Creates a context with a single view covering the whole window
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(
+        window: &'w Window,
         dimensions: Dimensions,
         scale: f64,
-        coords: Coordinates,
-    ) {
-        self.viewport = iced_wgpu::graphics::Viewport::with_physical_size(
+        coords: &Coordinates,
+        fps_balancer: FpsBalancer,
+        watchdog: Watchdog,
+        max_depth: u32,
+        diagnostics: &Diagnostics,
+        display: DisplayOptions,
+    ) -> Result<Self, ContextCreationError> {
+        check_word_count(coords.size())?;
+
+        let viewport = iced_wgpu::graphics::Viewport::with_physical_size(
             iced_core::Size::new(dimensions.width, dimensions.height),
-            self.viewport.scale_factor(),
-        );
-        self.params.update = Some(ParamsUpdate::Resize {
-            dimensions,
             scale,
-            coords,
-        });
-    }
+        );
 
-    pub fn update_params(&mut self, new_coords: Coordinates) {
-        match &mut self.params.update {
-            Some(ParamsUpdate::Resize { coords, .. }) => {
-                *coords = new_coords;
-            }
+        let mut state = State {
+            fps_balancer,
+            status: Status::Running,
+            failure: None,
+            watchdog,
+            clock: SystemClock::default(),
+            warning: None,
+            surface_retries: 0,
+        };
+
+        // GPU handle
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            // Should opt-out of WebGL here as it doesn't support compute shaders, but
+            // wgpu::Instance::request_adapter panics on unsupported platforms otherwise
+            backends: wgpu::Backends::all(),
+            flags: if diagnostics.debug {
+                wgpu::InstanceFlags::DEBUG | wgpu::InstanceFlags::VALIDATION
+            } else {
+                wgpu::InstanceFlags::default()
+            },
+            dx12_shader_compiler: wgpu::Dx12Compiler::default(),
+            gles_minor_version: wgpu::Gles3MinorVersion::default(),
+        });
+
+        let surface = instance.create_surface(window)?;
+        let (adapter, device, queue) =
+            request_device(&instance, &surface, diagnostics, display.power).await?;
+        let workgroup_width = workgroup_width(&device.limits());
+        let (device_errors, device_lost) = watch_device(&device);
+
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&ComputeBindings::bind_group_layout_desc());
+
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&RenderBindings::bind_group_layout_desc());
+
+        let mut compute_pipelines = PipelineCache::default();
+        let compute_pipeline = compute_pipelines.get_or_try_insert(coords.size(), || {
+            create_compute_pipeline(
+                &device,
+                &compute_bind_group_layout,
+                coords.size(),
+                workgroup_width,
+            )
+        })?;
+        let capabilities = surface.get_capabilities(&adapter);
+        let surface_formats = capabilities.formats;
+        let present_modes = capabilities.present_modes;
+        let swapchain_format = surface_format(&surface_formats, false)
+            .ok_or(ContextCreationError::SurfaceUnsupported)?;
+
+        let view = View::new(
+            &device,
+            &queue,
+            &compute_bind_group_layout,
+            &render_bind_group_layout,
+            compute_pipeline,
+            workgroup_width,
+            Rect::covering(dimensions),
+            scale,
+            coords,
+            FractalParams::default(),
+            IterationParams::default(),
+            ColorParams::default(),
+            &Palette::default(),
+            state.fps_balancer.present_iterations(Workload {
+                word_count: coords.size(),
+                distance_estimation: false,
+            }),
+            encodes_srgb(swapchain_format),
+        )?;
+
+        let julia_preview = JuliaPreview::new(
+            &device,
+            &queue,
+            &render_bind_group_layout,
+            workgroup_width,
+            crate::defaults::JULIA_PREVIEW_DEPTH,
+            encodes_srgb(swapchain_format),
+        );
+
+        let histogram_pass = HistogramPass::new(&device, workgroup_width);
+        let pass_timer = PassTimer::new(&device, &queue);
+        let perturbation_pass = PerturbationPass::new(&device, workgroup_width);
+
+        let render_pipeline =
+            create_render_pipeline(&device, &render_bind_group_layout, swapchain_format);
+
+        let mut config = surface
+            .get_default_config(&adapter, dimensions.width, dimensions.height)
+            .ok_or(ContextCreationError::SurfaceUnsupported)?;
+        config.present_mode = display::present_mode(&present_modes, display.vsync);
+        surface.configure(&device, &config);
+
+        let ui_renderer = create_ui_renderer(&device, &queue, swapchain_format);
+        let ui_debug = iced_runtime::Debug::new();
+
+        let calibrations = calibrations::load();
+        let adapter = adapter_key(&adapter.get_info());
+        state
+            .fps_balancer
+            .set_pixels(balanced_pixels(view.params.scaled_dimensions));
+        state
+            .fps_balancer
+            .restore_calibration(calibrations.of(&adapter));
+
+        Ok(Self {
+            instance,
+            device: SharedDevice::from(device),
+            queue,
+            diagnostics: diagnostics.clone(),
+            config,
+            surface,
+            surface_formats,
+            present_modes,
+            display,
+            ui_renderer,
+            ui_debug,
+            viewport,
+            compute_bind_group_layout,
+            compute_pipelines,
+            compilation: None,
+            pipeline_notifier: None,
+            render_bind_group_layout,
+            render_pipeline,
+            views: vec![view],
+            julia_preview,
+            histogram_pass,
+            perturbation_pass,
+            perturbation: true,
+            reprojection: true,
+            bands: None,
+            pass_timer,
+            work_done: cfg!(target_arch = "wasm32").then(WorkDone::default),
+            calibrations,
+            adapter,
+            band_budget_ms: crate::defaults::BAND_BUDGET_MS,
+            fxaa: None,
+            state,
+            max_depth,
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            paused: false,
+            surface_update: None,
+            device_errors,
+            device_lost,
+            snapshots: Vec::new(),
+            captures: Vec::new(),
+            poster: None,
+            workgroup_width,
+        })
+    }
+
+    pub fn rescale_ui(&mut self, window_scale: f64) {
+        self.viewport = iced_wgpu::graphics::Viewport::with_physical_size(
+            self.viewport.physical_size(),
+            window_scale,
+        );
+    }
+
+    /// Adds a view drawn to `rect`. Coordinates of unsupported size are rejected
+    pub fn add_view(
+        &mut self,
+        rect: Rect,
+        scale: f64,
+        coords: Coordinates,
+        fractal: FractalParams,
+    ) -> Result<(), UnsupportedWordCount> {
+        let word_count = self.state.status.word_count(coords.size());
+        let compute_pipeline = self.compute_pipeline(coords.size())?;
+        let mut view = View::new(
+            &self.device,
+            &self.queue,
+            &self.compute_bind_group_layout,
+            &self.render_bind_group_layout,
+            compute_pipeline,
+            self.workgroup_width,
+            rect,
+            scale,
+            &coords,
+            fractal,
+            self.iteration,
+            self.colors,
+            &self.palette,
+            self.state.fps_balancer.present_iterations(Workload {
+                word_count,
+                distance_estimation: self.iteration.distance_estimation,
+            }),
+            encodes_srgb(self.config.format),
+        )?;
+        view.params.update = Some(ParamsUpdate::Move { coords });
+        self.views.push(view);
+        Ok(())
+    }
+
+    /// Returns the shared compute pipeline of `word_count` words, compiled unless it's cached
+    fn compute_pipeline(
+        &mut self,
+        word_count: usize,
+    ) -> Result<Arc<wgpu::ComputePipeline>, UnsupportedWordCount> {
+        self.compute_pipelines.get_or_try_insert(word_count, || {
+            create_compute_pipeline(
+                &self.device,
+                &self.compute_bind_group_layout,
+                word_count,
+                self.workgroup_width,
+            )
+        })
+    }
+
+    /// Gives back the memory that views hold past their size since they were larger. Their
+    /// calculation goes on, but their previous frames aren't reprojected anymore
+    pub fn trim_buffers(&mut self) {
+        let mut command_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Trim Encoder"),
+                });
+        for view in &mut self.views {
+            let layout = &self.compute_bind_group_layout;
+            view.calibration_bindings
+                .trim(&self.device, layout, &mut command_encoder);
+            if view
+                .compute_bindings
+                .trim(&self.device, layout, &mut command_encoder)
+            {
+                if let Some(perturbation) = &mut view.perturbation {
+                    perturbation.rebind(
+                        &self.device,
+                        &self.perturbation_pass,
+                        &view.compute_bindings,
+                    );
+                }
+            }
+            if view.render_bindings.trim(
+                &self.device,
+                &self.render_bind_group_layout,
+                &mut command_encoder,
+            ) {
+                view.previous_frame = None;
+            }
+        }
+        self.queue.submit(Some(command_encoder.finish()));
+    }
+
+    /// Removes views past the first `count`. Their work in progress is discarded
+    pub fn truncate_views(&mut self, count: usize) {
+        self.views.truncate(count.max(1));
+    }
+
+    /// Queues a resize of the window surface. Views have to be resized separately
+    pub fn resize_surface(&mut self, dimensions: Dimensions) {
+        self.viewport = iced_wgpu::graphics::Viewport::with_physical_size(
+            iced_core::Size::new(dimensions.width, dimensions.height),
+            self.viewport.scale_factor(),
+        );
+        self.surface_update = Some(dimensions);
+    }
+
+    /// Queues a resize of a view with new coordinates. Coordinates of unsupported size are
+    /// rejected
+    pub fn resize_and_update_params(
+        &mut self,
+        view: usize,
+        rect: Rect,
+        scale: f64,
+        coords: Coordinates,
+    ) -> Result<(), UnsupportedWordCount> {
+        check_word_count(coords.size())?;
+        self.views[view].params.update = Some(ParamsUpdate::Resize {
+            rect,
+            scale,
+            coords,
+        });
+        Ok(())
+    }
+
+    /// Queues new coordinates of a view. Coordinates of unsupported size are rejected
+    pub fn update_params(
+        &mut self,
+        view: usize,
+        new_coords: Coordinates,
+    ) -> Result<(), UnsupportedWordCount> {
+        check_word_count(new_coords.size())?;
+        match &mut self.views[view].params.update {
+            Some(ParamsUpdate::Resize { coords, .. }) => {
+                *coords = new_coords;
+            }
             update => *update = Some(ParamsUpdate::Move { coords: new_coords }),
         }
+        Ok(())
+    }
+
+    /// Sets the fractal calculated by a view. Takes effect with the next parameter update
+    pub fn set_fractal(&mut self, view: usize, fractal: FractalParams) {
+        self.views[view].params.fractal = fractal;
+    }
+
+    /// Shows the previous frames of the views where new ones haven't converged yet, instead of
+    /// the pixels calculated so far alone
+    pub fn set_reprojection(&mut self, enabled: bool) {
+        self.reprojection = enabled;
+        if !enabled {
+            for view in &mut self.views {
+                view.previous_frame = None;
+                view.render_bindings.write_reprojection(&self.queue, None);
+            }
+        }
+    }
+
+    /// Chooses between perturbation and the exact calculation of deep views. Takes effect with the
+    /// next parameter update
+    pub fn set_perturbation(&mut self, enabled: bool) {
+        self.perturbation = enabled;
     }
 
     pub fn set_max_depth(&mut self, max_depth: u32) {
-        self.params.max_depth = max_depth;
+        self.max_depth = max_depth;
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        if self.state.task.is_some() {
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// Sets the escape condition of all views. Takes effect with the next parameter update
+    pub fn set_iteration(&mut self, iteration: IterationParams) {
+        self.iteration = iteration;
+    }
+
+    pub fn iteration(&self) -> IterationParams {
+        self.iteration
+    }
+
+    pub fn colors(&self) -> ColorParams {
+        self.colors
+    }
+
+    /// Sets the coloring of all views, escaped pixels keep their counts. Equalized colors take
+    /// effect once the histograms of the views are read back
+    pub fn set_colors(&mut self, colors: ColorParams) {
+        let equalize = colors.equalize && !self.colors.equalize;
+        self.colors = colors;
+        for view in &mut self.views {
+            if equalize {
+                view.histogram.clear();
+                view.lut = None;
+            } else if !colors.equalize {
+                view.render_bindings.write_lut(&self.queue, None);
+            }
+        }
+        self.refresh_colors();
+    }
+
+    /// Sets the colors of all views and the preview
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.refresh_colors();
+    }
+
+    /// Draws the views again from the results they have with the current colors. Only the render
+    /// bindings are updated, so frames of views that have reached the max depth don't dispatch
+    /// the calculation
+    pub fn refresh_colors(&mut self) {
+        for view in &self.views {
+            view.render_bindings.write(
+                &self.queue,
+                FragmentParams {
+                    size: view.params.scaled_dimensions,
+                    depth: view.drawn_depth(),
+                    output_is_srgb: encodes_srgb(self.config.format).into(),
+                },
+            );
+            view.render_bindings.write_colors(&self.queue, self.colors);
+            view.render_bindings
+                .write_palette(&self.queue, &self.palette);
+        }
+        self.julia_preview
+            .write_colors(&self.queue, &self.palette, self.colors.gamma);
+    }
+
+    /// Returns the description of the failure if the context has degraded since the last call.
+    /// Coordinates should be updated afterwards to recalculate them with the working word count
+    pub fn take_failure(&mut self) -> Option<String> {
+        self.state.failure.take()
+    }
+
+    /// Returns the last warning about the submitted work. Coordinates should be updated after
+    /// [`Warning::Lost`] to restart the calculation
+    pub fn take_warning(&mut self) -> Option<Warning> {
+        self.state.warning.take()
+    }
+
+    /// Switches the surface to a 16-bit float format, or back to the preferred one
+    pub fn set_deep_color(&mut self, enabled: bool) -> Result<(), DeepColorUnsupported> {
+        let format = surface_format(&self.surface_formats, enabled).ok_or(DeepColorUnsupported)?;
+        if format == self.config.format {
             return Ok(());
         }
 
-        self.start_render_frame();
+        self.config.format = format;
+        self.surface.configure(&self.device, &self.config);
+        // Pipelines drawing to the surface are specific to its format
+        self.render_pipeline =
+            create_render_pipeline(&self.device, &self.render_bind_group_layout, format);
+        if self.fxaa.is_some() {
+            self.fxaa = Some(Fxaa::new(&self.device, format));
+        }
+        self.ui_renderer = create_ui_renderer(&self.device, &self.queue, format);
+        // Only one of the formats may encode the colors for the display
+        self.julia_preview
+            .set_output(&self.queue, encodes_srgb(format));
+        self.refresh_colors();
+        Ok(())
+    }
 
-        let frame = self.surface.get_current_texture()?;
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    pub fn display(&self) -> DisplayOptions {
+        self.display
+    }
 
-        let mut command_encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    /// Makes frames wait for the display, or be shown at once where the surface allows it
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.display.vsync = vsync;
+        let mode = display::present_mode(&self.present_modes, vsync);
+        if mode != self.config.present_mode {
+            self.config.present_mode = mode;
+            self.surface.configure(&self.device, &self.config);
+        }
+    }
 
-        if self.state.depth < self.params.max_depth {
-            command_encoder.push_debug_group("Compute");
-            {
-                let mut cpass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                    label: None,
-                    timestamp_writes: None,
+    /// Sets the adapter to request with the next rebuild. Tells if the preference changed
+    pub fn set_power_preference(&mut self, power: PowerPreference) -> bool {
+        std::mem::replace(&mut self.display.power, power) != power
+    }
+
+    /// Requests the preferred adapter again and moves the views to it. Views keep their
+    /// coordinates, their calculation starts over
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub async fn switch_adapter(&mut self, window: &'w Window) -> Result<(), ContextCreationError> {
+        self.rebuild(window).await?;
+        // The device was replaced on purpose rather than lost
+        self.state.warning = None;
+        Ok(())
+    }
+
+    pub fn is_deep_color(&self) -> bool {
+        self.config.format == DEEP_COLOR_FORMAT
+    }
+
+    pub fn set_antialiasing(&mut self, enabled: bool) {
+        match (enabled, &self.fxaa) {
+            (true, None) => self.fxaa = Some(Fxaa::new(&self.device, self.config.format)),
+            (false, Some(_)) => self.fxaa = None,
+            _ => {}
+        }
+    }
+
+    /// Shows the Julia set preview for the plane point `c`, or hides it
+    pub fn set_julia_preview(&mut self, c: Option<[f32; 2]>) {
+        self.julia_preview.set_point(c);
+    }
+
+    /// Draws the selection from `min` to `max` in view pixels over the view at `index`, or hides it
+    pub fn set_selection(&mut self, index: usize, selection: Option<(Point, Point)>) {
+        let Some(view) = self.views.get(index) else {
+            return;
+        };
+        let (width, height) = (view.rect.width as f32, view.rect.height as f32);
+        let selection = selection.map_or(Selection::default(), |(min, max)| Selection {
+            min: [min.x / width, min.y / height],
+            max: [max.x / width, max.y / height],
+        });
+        view.render_bindings.write_selection(&self.queue, selection);
+    }
+
+    /// Stops calculating deeper until unpaused, frames still show the views. Views with new
+    /// parameters are calculated to their first frame
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Leaves the degraded state. Takes effect with the next parameter update
+    pub fn retry(&mut self) {
+        self.state.retry();
+    }
+
+    /// Returns how to recover from a frame that [`GpuContext::render`] couldn't acquire in a window
+    /// of `window` dimensions. Surfaces that stay lost mark the context for a rebuild
+    pub fn recover(&mut self, error: &wgpu::SurfaceError, window: Dimensions) -> SurfaceRecovery {
+        let configured = self.surface_update.unwrap_or(Dimensions {
+            width: self.config.width,
+            height: self.config.height,
+        });
+        let recovery = surface_recovery(error, configured, window);
+        if recovery == SurfaceRecovery::Rebuild {
+            self.device_lost.store(true, Ordering::Relaxed);
+        }
+        recovery
+    }
+
+    /// Returns true once the device or the surface is lost, until the context is rebuilt
+    pub fn is_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    /// Recreates the surface, the device and everything on it after either is lost. Views keep
+    /// their coordinates, fractals and colors, their calculation starts over
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub async fn rebuild(&mut self, window: &'w Window) -> Result<(), ContextCreationError> {
+        // The old surface is dropped before the new one is configured, a window only has one
+        // swapchain at a time
+        self.surface = self.instance.create_surface(window)?;
+        let (adapter, device, queue) = request_device(
+            &self.instance,
+            &self.surface,
+            &self.diagnostics,
+            self.display.power,
+        )
+        .await?;
+        let workgroup_width = workgroup_width(&device.limits());
+        (self.device_errors, self.device_lost) = watch_device(&device);
+
+        // Another adapter may have been picked, which runs at another speed
+        let key = adapter_key(&adapter.get_info());
+        if key != self.adapter {
+            self.calibrations
+                .update(&self.adapter, self.state.fps_balancer.calibration());
+            self.adapter = key;
+            self.state.fps_balancer.reset();
+            self.state
+                .fps_balancer
+                .restore_calibration(self.calibrations.of(&self.adapter));
+        }
+
+        let deep_color = self.is_deep_color();
+        let capabilities = self.surface.get_capabilities(&adapter);
+        self.surface_formats = capabilities.formats;
+        self.present_modes = capabilities.present_modes;
+        self.config.present_mode = display::present_mode(&self.present_modes, self.display.vsync);
+        self.config.format = surface_format(&self.surface_formats, deep_color)
+            .or_else(|| surface_format(&self.surface_formats, false))
+            .ok_or(ContextCreationError::SurfaceUnsupported)?;
+        if let Some(dimensions) = self.surface_update.take() {
+            self.config.width = dimensions.width;
+            self.config.height = dimensions.height;
+        }
+        self.surface.configure(&device, &self.config);
+
+        self.compute_bind_group_layout =
+            device.create_bind_group_layout(&ComputeBindings::bind_group_layout_desc());
+        self.render_bind_group_layout =
+            device.create_bind_group_layout(&RenderBindings::bind_group_layout_desc());
+        self.render_pipeline =
+            create_render_pipeline(&device, &self.render_bind_group_layout, self.config.format);
+        let preview_point = self.julia_preview.point();
+        self.julia_preview = JuliaPreview::new(
+            &device,
+            &queue,
+            &self.render_bind_group_layout,
+            workgroup_width,
+            crate::defaults::JULIA_PREVIEW_DEPTH,
+            encodes_srgb(self.config.format),
+        );
+        self.julia_preview.set_point(preview_point);
+        self.histogram_pass = HistogramPass::new(&device, workgroup_width);
+        self.pass_timer = PassTimer::new(&device, &queue);
+        if let Some(work_done) = &mut self.work_done {
+            work_done.reset();
+        }
+        self.perturbation_pass = PerturbationPass::new(&device, workgroup_width);
+        if self.fxaa.is_some() {
+            self.fxaa = Some(Fxaa::new(&device, self.config.format));
+        }
+        self.ui_renderer = create_ui_renderer(&device, &queue, self.config.format);
+
+        self.compute_pipelines.clear();
+        let mut views = Vec::with_capacity(self.views.len());
+        for view in &self.views {
+            let (rect, scale, coords) = view.target();
+            let compute_pipeline =
+                self.compute_pipelines
+                    .get_or_try_insert(coords.size(), || {
+                        create_compute_pipeline(
+                            &device,
+                            &self.compute_bind_group_layout,
+                            coords.size(),
+                            workgroup_width,
+                        )
+                    })?;
+            let mut rebuilt = View::new(
+                &device,
+                &queue,
+                &self.compute_bind_group_layout,
+                &self.render_bind_group_layout,
+                compute_pipeline,
+                workgroup_width,
+                rect,
+                scale,
+                coords,
+                view.params.fractal.clone(),
+                self.iteration,
+                self.colors,
+                &self.palette,
+                self.state.fps_balancer.present_iterations(Workload {
+                    word_count: self.state.status.word_count(coords.size()),
+                    distance_estimation: self.iteration.distance_estimation,
+                }),
+                encodes_srgb(self.config.format),
+            )?;
+            rebuilt.params.update = Some(ParamsUpdate::Move {
+                coords: coords.clone(),
+            });
+            views.push(rebuilt);
+        }
+        self.views = views;
+        self.device = SharedDevice::from(device);
+        self.compilation = None;
+        self.queue = queue;
+        self.workgroup_width = workgroup_width;
+        self.bands = None;
+        // Captures of the old device are dropped, their requesters get nothing
+        self.captures.clear();
+        self.poster = None;
+        self.refresh_colors();
+        self.state.watchdog.completed();
+        self.state.warning = Some(Warning::DeviceLost);
+        Ok(())
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if self.is_busy() {
+            return Ok(());
+        }
+
+        if let Some(dimensions) = self.surface_update.take() {
+            self.config.width = dimensions.width;
+            self.config.height = dimensions.height;
+            self.surface.configure(&self.device, &self.config);
+        }
+        if self.bands.is_none() {
+            self.start_frames();
+        }
+        if self.bands.is_some_and(|bands| !bands.is_last()) {
+            self.submit_band();
+            return Ok(());
+        }
+        // A frame that can't be acquired leaves the last band to the next attempt
+        let frame = self.acquire_frame()?;
+        let mut bands = self.bands.take().expect("Frames are started above");
+
+        let frame_view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Frame View"),
+            ..Default::default()
+        });
+
+        let mut command_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Frame Encoder"),
                 });
-                cpass.set_pipeline(&self.compute_pipeline);
-                cpass.set_bind_group(0, &self.compute_bindings.bind_group, &[]);
-                cpass.dispatch_workgroups(
-                    self.params.scaled_dimensions.aligned_width(64) / 64,
-                    self.params.scaled_dimensions.height,
-                    1,
-                );
+
+        self.encode_band(&mut command_encoder, &mut bands);
+        if let Some(timer) = &mut self.pass_timer {
+            timer.encode_resolve(&mut command_encoder);
+        }
+        for view in &mut self.views {
+            if let Some(readback) = &mut view.readback {
+                readback.encode_next(&mut command_encoder, &view.compute_bindings);
+                continue;
             }
-            command_encoder.pop_debug_group();
+            let dispatched = view.dispatches(self.max_depth);
+            view.restored = false;
+            // Only paused and finished views aren't dispatched
+            if !dispatched {
+                continue;
+            }
+            view.render_bindings.encode_copy(
+                &mut command_encoder,
+                &view.compute_bindings.result_buffer,
+                Some(&view.compute_bindings.escape_buffer),
+                view.params
+                    .distance_estimation
+                    .then_some(&view.compute_bindings.distance_buffer),
+            );
+        }
 
-            command_encoder.copy_buffer_to_texture(
-                wgpu::ImageCopyBuffer {
-                    buffer: &self.compute_bindings.result_buffer,
-                    layout: wgpu::ImageDataLayout {
-                        offset: 0,
-                        bytes_per_row: Some(self.render_bindings.texture.size().width * 4),
-                        rows_per_image: None,
-                    },
-                },
-                self.render_bindings.texture.as_image_copy(),
-                self.render_bindings.texture.size(),
+        for view in &mut self.views {
+            let dispatched = view.task.is_some() && view.readback.is_none();
+            view.pixel_readback.encode_copy(
+                &mut command_encoder,
+                &view.compute_bindings,
+                dispatched,
             );
         }
 
+        if self.colors.equalize {
+            for view in &mut self.views {
+                // Paused and finished views keep the results of their depth
+                let depth = view.drawn_depth();
+                if view.readback.is_none() && view.histogram.is_due(depth) {
+                    view.histogram.encode(
+                        &self.device,
+                        &self.queue,
+                        &mut command_encoder,
+                        &self.histogram_pass,
+                        &view.compute_bindings,
+                        view.params.scaled_dimensions,
+                        depth,
+                    );
+                }
+            }
+        }
+
+        self.julia_preview.encode(&self.queue, &mut command_encoder);
+
+        // With antialiasing the views are filtered into the frame afterwards
+        let views_target = match &mut self.fxaa {
+            Some(fxaa) => fxaa.target(&self.device, frame.texture.size()),
+            None => &frame_view,
+        };
+
         command_encoder.push_debug_group("Render");
         {
             let mut rpass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
+                label: Some("Render"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: views_target,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -414,11 +1512,46 @@ impl<'w> GpuContext<'w> {
                 occlusion_query_set: None,
             });
             rpass.set_pipeline(&self.render_pipeline);
-            rpass.set_bind_group(0, &self.render_bindings.bind_group, &[]);
-            rpass.draw(0..4, 0..1);
+            for view in &self.views {
+                // Views may lag behind the surface size until their resize is applied
+                let Some(rect) = view.rect.clamp_to(self.config.width, self.config.height) else {
+                    continue;
+                };
+                rpass.set_viewport(
+                    rect.x as f32,
+                    rect.y as f32,
+                    rect.width as f32,
+                    rect.height as f32,
+                    0.0,
+                    1.0,
+                );
+                rpass.set_scissor_rect(rect.x, rect.y, rect.width, rect.height);
+                rpass.set_bind_group(0, &view.render_bindings.bind_group, &[]);
+                rpass.draw(0..4, 0..1);
+            }
+            if let Some((bindings, rect)) = self
+                .julia_preview
+                .draw_target(self.config.width, self.config.height)
+            {
+                rpass.set_viewport(
+                    rect.x as f32,
+                    rect.y as f32,
+                    rect.width as f32,
+                    rect.height as f32,
+                    0.0,
+                    1.0,
+                );
+                rpass.set_scissor_rect(rect.x, rect.y, rect.width, rect.height);
+                rpass.set_bind_group(0, &bindings.bind_group, &[]);
+                rpass.draw(0..4, 0..1);
+            }
         }
         command_encoder.pop_debug_group();
 
+        if let Some(fxaa) = &self.fxaa {
+            fxaa.encode(&mut command_encoder, &frame_view);
+        }
+
         // Render iced UI on top
         self.ui_renderer.with_primitives(|backend, primitive| {
             backend.present(
@@ -427,7 +1560,7 @@ impl<'w> GpuContext<'w> {
                 &mut command_encoder,
                 None,
                 frame.texture.format(),
-                &view,
+                &frame_view,
                 primitive,
                 &self.viewport,
                 &self.ui_debug.overlay(),
@@ -436,318 +1569,1852 @@ impl<'w> GpuContext<'w> {
 
         // submit will accept anything that implements IntoIter
         self.queue.submit(Some(command_encoder.finish()));
+        self.watch_submission();
+        if let Some(timer) = &mut self.pass_timer {
+            timer.request_map();
+        }
+        for view in &mut self.views {
+            view.tile_counter.request_map();
+            view.pixel_readback.request_map();
+            view.histogram.request_map();
+            if let Some(readback) = &mut view.readback {
+                readback.request_map();
+            }
+        }
         frame.present();
 
         Ok(())
     }
 
     pub fn poll(&mut self) -> wgpu::MaintainResult {
-        match self.device.poll(wgpu::Maintain::Poll) {
+        // The failing view is unknown, fall back to what works for all of them
+        let views = &self.views;
+        self.state.check_errors(&self.device_errors, || {
+            views
+                .iter()
+                .map(|view| view.params.working_word_count)
+                .min()
+                .expect("There's always a view")
+        });
+
+        let result = match self.device.poll(wgpu::Maintain::Poll) {
+            // The browser polls the device itself, the work is only known to be done once the
+            // mappings after it resolve
+            wgpu::MaintainResult::SubmissionQueueEmpty
+                if self
+                    .work_done
+                    .as_mut()
+                    .is_some_and(|work_done| !work_done.is_done()) =>
+            {
+                wgpu::MaintainResult::Ok
+            }
+            result => result,
+        };
+        match result {
             wgpu::MaintainResult::SubmissionQueueEmpty => {
-                self.state.fps_balancer.end_frame();
-
-                match self.state.task.take() {
-                    Some(Task::Render(new_depth)) => {
-                        self.state.depth = new_depth;
-                        if !self
-                            .state
-                            .fps_balancer
-                            .is_calibrated(self.params.word_count)
-                        {
-                            self.start_calibration_frame();
-                            wgpu::MaintainResult::Ok
-                        } else {
-                            wgpu::MaintainResult::SubmissionQueueEmpty
+                self.state.watchdog.completed();
+                if self.poster.as_ref().is_some_and(Poster::in_flight) {
+                    return self.complete_poster();
+                }
+                // Bands before the last one advance to the next, the frame presents the last one
+                if let Some(bands) = &mut self.bands {
+                    bands.complete();
+                    if bands.is_last() {
+                        return wgpu::MaintainResult::SubmissionQueueEmpty;
+                    }
+                    self.submit_band();
+                    return wgpu::MaintainResult::Ok;
+                }
+                let gpu_ms = self.pass_timer.as_mut().and_then(PassTimer::receive);
+                self.state.fps_balancer.end_frame(gpu_ms);
+
+                let mut rendered = false;
+                for (index, view) in self.views.iter_mut().enumerate() {
+                    if let Some(Task::Render(new_depth)) = view.task.take() {
+                        view.depth = new_depth;
+                        rendered = true;
+                    }
+                    view.tile_counter.receive();
+                    view.pixel_readback.receive();
+                    if let Some(lut) = view.histogram.receive() {
+                        if self.colors.equalize {
+                            view.render_bindings.write_lut(&self.queue, Some(&lut));
                         }
+                        view.lut = Some(lut);
+                    }
+                    if let Some(readback) = &mut view.readback {
+                        match readback.receive() {
+                            Progress::Pending => {}
+                            Progress::Done(snapshot) => {
+                                self.snapshots.push((index, snapshot));
+                                view.readback = None;
+                            }
+                            Progress::Failed(e) => {
+                                log::error!("Readback of view {} has failed: {}", index, e);
+                                view.readback = None;
+                            }
+                        }
+                    }
+                }
+
+                self.advance_captures();
+
+                let uncalibrated = self
+                    .views
+                    .iter()
+                    .position(|view| !self.state.fps_balancer.is_calibrated(view.workload()));
+                match uncalibrated {
+                    Some(view) if rendered => {
+                        // The frames just presented are timed already, calibration frames only
+                        // run for views that escape too early to tell
+                        let activity = self
+                            .active_tiles(view)
+                            .map(|(active, total)| active as f64 / total.max(1) as f64);
+                        let workload = self.views[view].workload();
+                        if activity.is_some_and(|activity| {
+                            self.state
+                                .fps_balancer
+                                .calibrate_from_frames(workload, activity)
+                        }) {
+                            return wgpu::MaintainResult::SubmissionQueueEmpty;
+                        }
+                        self.start_calibration_frame(view);
+                        wgpu::MaintainResult::Ok
+                    }
+                    // Posters take their steps between the frames
+                    _ if self.poster.is_some() => {
+                        self.step_poster();
+                        wgpu::MaintainResult::Ok
+                    }
+                    _ => wgpu::MaintainResult::SubmissionQueueEmpty,
+                }
+            }
+            wgpu::MaintainResult::Ok => {
+                match self.state.watch(self.state.clock.now(), &self.device_lost) {
+                    Verdict::Waiting | Verdict::Stalled => wgpu::MaintainResult::Ok,
+                    Verdict::Lost => {
+                        // Treat the tasks as finished, the calculation restarts on the rebuilt device
+                        for view in &mut self.views {
+                            view.task = None;
+                            view.readback = None;
+                            view.depth = 0;
+                        }
+                        self.bands = None;
+                        // The requester of the poster learns that it's lost
+                        self.poster = None;
+                        wgpu::MaintainResult::SubmissionQueueEmpty
                     }
-                    None | Some(Task::Calibration) => wgpu::MaintainResult::SubmissionQueueEmpty,
                 }
             }
-            wgpu::MaintainResult::Ok => wgpu::MaintainResult::Ok,
         }
     }
 
+    /// Returns true if the next frame calculates or reads back any view, otherwise the views are
+    /// only drawn again and nothing changes until they do
+    pub fn needs_frames(&self) -> bool {
+        self.bands.is_some()
+            || !self.captures.is_empty()
+            || self.poster.is_some()
+            || self.views.iter().any(|view| {
+                view.readback.is_some()
+                    || view.pixel_readback.is_pending()
+                    || needs_compute(
+                        view.params.update.is_some(),
+                        view.restored,
+                        view.depth,
+                        self.max_depth,
+                        view.is_converged(),
+                    )
+            })
+    }
+
+    /// Captures the frame of `view` at the depth its work in flight reaches, without the control
+    /// panel. Resolves to nothing if the device is lost before
+    pub fn capture_frame(&mut self, view: usize) -> impl Future<Output = Option<RgbaImage>> {
+        let (sender, receiver) = flume::bounded(1);
+        self.captures.push(Capture::new(view, sender));
+        async move { receiver.recv_async().await.ok() }
+    }
+
+    /// Draws the views asked for now that their work has completed, and sends the frames that
+    /// have been mapped
+    fn advance_captures(&mut self) {
+        self.captures.retain_mut(|capture| !capture.receive());
+        // Captures of views closed since they were asked for are dropped
+        let views = self.views.len();
+        self.captures
+            .retain(|capture| !capture.is_requested() || capture.view < views);
+        if !self.captures.iter().any(Capture::is_requested) {
+            return;
+        }
+
+        let pipeline =
+            create_render_pipeline(&self.device, &self.render_bind_group_layout, CAPTURE_FORMAT);
+        let mut command_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Capture Encoder"),
+                });
+        let requested = self.captures.iter_mut().filter(|c| c.is_requested());
+        for capture in requested {
+            let view = &self.views[capture.view];
+            // The capture target encodes the colors, whichever the surface does
+            view.render_bindings.write(
+                &self.queue,
+                FragmentParams {
+                    size: view.params.scaled_dimensions,
+                    depth: view.drawn_depth(),
+                    output_is_srgb: encodes_srgb(CAPTURE_FORMAT).into(),
+                },
+            );
+            capture.encode(
+                &self.device,
+                &mut command_encoder,
+                &pipeline,
+                &view.render_bindings,
+                view.params.scaled_dimensions,
+            );
+        }
+        self.queue.submit(Some(command_encoder.finish()));
+        for capture in &mut self.captures {
+            capture.request_map();
+        }
+        // Writes after the submission apply to the next frames, drawn for the surface again
+        self.refresh_colors();
+    }
+
+    /// Starts a poster of `view` at `dimensions`, with the center and the extent of the view.
+    /// Its tiles are calculated to the depth limit, or until they converge, and drawn one step
+    /// between the frames. A poster already in progress is cancelled. Resolves once the poster
+    /// is stitched, cancelled or lost with the device
+    pub fn render_poster(
+        &mut self,
+        view: usize,
+        dimensions: Dimensions,
+    ) -> Result<impl Future<Output = Result<RgbaImage, PosterError>>, UnsupportedWordCount> {
+        let size = dimensions.scale_to(1.0);
+        let (rect, scale, coords) = self.views[view].target();
+        let coords = coords.resized(rect.dimensions().scale_to(scale), size);
+        let fractal = self.views[view].params.fractal.clone();
+        let lut = self.views[view].lut.clone();
+        self.start_poster(view, coords, fractal, size, self.max_depth, lut)
+    }
+
+    /// Starts a frame of the size of `view` at `coords` and `fractal` instead of its own, like the
+    /// frames of a recorded zoom. It's calculated to `depth`, or until it converges, the same way
+    /// as a poster, which it cancels. Colors aren't equalized, the histogram of the view doesn't
+    /// apply to other locations
+    pub fn render_frame(
+        &mut self,
+        view: usize,
+        coords: Coordinates,
+        fractal: FractalParams,
+        depth: u32,
+    ) -> Result<impl Future<Output = Result<RgbaImage, PosterError>>, UnsupportedWordCount> {
+        let (rect, scale, _) = self.views[view].target();
+        let size = rect.dimensions().scale_to(scale);
+        self.start_poster(view, coords, fractal, size, depth, None)
+    }
+
+    fn start_poster(
+        &mut self,
+        view: usize,
+        mut coords: Coordinates,
+        fractal: FractalParams,
+        size: ScaledDimensions,
+        depth: u32,
+        lut: Option<Lut>,
+    ) -> Result<impl Future<Output = Result<RgbaImage, PosterError>>, UnsupportedWordCount> {
+        coords.set_word_count(self.state.status.word_count(coords.size()));
+        let compute_pipeline = self.compute_pipeline(coords.size())?;
+
+        let perturbed = self.perturbation && is_perturbed(coords.size(), &fractal, self.iteration);
+        let tile = tile_size(&self.device.limits(), coords.size(), self.workgroup_width);
+        let (sender, receiver) = flume::bounded(1);
+        let poster = Poster::new(
+            view,
+            coords,
+            fractal,
+            self.iteration,
+            self.colors,
+            self.palette.clone(),
+            lut,
+            compute_pipeline,
+            create_render_pipeline(&self.device, &self.render_bind_group_layout, CAPTURE_FORMAT),
+            self.workgroup_width,
+            perturbed,
+            size,
+            depth,
+            tile,
+            sender,
+        );
+        self.cancel_poster();
+        self.poster = Some(poster);
+        Ok(async move {
+            receiver
+                .recv_async()
+                .await
+                .unwrap_or(Err(PosterError::Lost))
+        })
+    }
+
+    /// Cancels the poster in progress. Returns false without one
+    pub fn cancel_poster(&mut self) -> bool {
+        // A step in flight is left to complete, its results are dropped with the poster
+        self.poster.take().map(Poster::cancel).is_some()
+    }
+
+    /// Returns the tile of the poster in progress, counted from 1, and the amount of its tiles
+    pub fn poster_progress(&self) -> Option<(usize, usize)> {
+        self.poster.as_ref().map(Poster::progress)
+    }
+
+    /// Submits the next step of the poster, as long as a frame of its calculation takes at the
+    /// calibrated speed
+    fn step_poster(&mut self) {
+        let Some(poster) = &mut self.poster else {
+            return;
+        };
+        let workload = poster.workload();
+        let ms = self
+            .state
+            .fps_balancer
+            .calibration()
+            .into_iter()
+            .find_map(|(calibrated, ms)| (calibrated == workload).then_some(ms));
+        poster.step(
+            &self.device,
+            &self.queue,
+            &self.compute_bind_group_layout,
+            &self.render_bind_group_layout,
+            &self.perturbation_pass,
+            ms,
+            self.state.fps_balancer.target_frame_ms(),
+        );
+        self.watch_submission();
+    }
+
+    /// Collects the step of the poster that has completed and sends the poster once all of its
+    /// tiles are stitched. The next frame is drawn before the next step
+    fn complete_poster(&mut self) -> wgpu::MaintainResult {
+        let poster = self.poster.as_mut().expect("Only posters are in flight");
+        match poster.complete() {
+            Ok(false) => return wgpu::MaintainResult::Ok,
+            Ok(true) if poster.is_finished() => {
+                self.poster.take().expect("Poster is finished").finish();
+            }
+            Ok(true) => {}
+            Err(e) => {
+                log::error!("Poster has failed: {}", e);
+                self.poster = None;
+            }
+        }
+        wgpu::MaintainResult::SubmissionQueueEmpty
+    }
+
+    /// Returns true while submitted work hasn't completed. Frames waiting for their last band
+    /// aren't, it's submitted by the next render
+    fn is_busy(&self) -> bool {
+        (self.views.iter().any(|view| view.task.is_some())
+            && self.bands.is_none_or(|bands| bands.in_flight()))
+            || self.poster.as_ref().is_some_and(Poster::in_flight)
+    }
+
+    /// Sets the longest time a band of rows is expected to take, frames that take longer are
+    /// split into more of them
+    pub fn set_band_budget(&mut self, budget_ms: f64) {
+        self.band_budget_ms = budget_ms;
+    }
+
+    /// Returns the calibrated speeds of the adapters used so far, including the current one
+    pub fn calibrations(&mut self) -> &Calibrations {
+        self.calibrations
+            .update(&self.adapter, self.state.fps_balancer.calibration());
+        &self.calibrations
+    }
+
+    /// Sets the frame rate the iterations are balanced for
+    pub fn set_target_fps(&mut self, target_fps: f64) {
+        self.state.fps_balancer.set_target_fps(target_fps);
+    }
+
+    fn watch_submission(&mut self) {
+        let expected_ms = self.state.fps_balancer.target_frame_ms();
+        self.state
+            .watchdog
+            .submitted(self.state.clock.now(), expected_ms);
+        if let Some(work_done) = &mut self.work_done {
+            work_done.submitted(&self.device);
+        }
+    }
+
+    /// Returns true if the submitted work calls the work done notifier once it completes, so
+    /// that it doesn't need to be polled until then
+    pub fn awaits_work_done(&self) -> bool {
+        self.work_done.as_ref().is_some_and(WorkDone::is_pending)
+    }
+
     pub fn viewport(&self) -> &iced_wgpu::graphics::Viewport {
         &self.viewport
     }
 
-    pub fn current_depth(&self) -> u32 {
-        self.state.depth
+    pub fn current_depth(&self, view: usize) -> u32 {
+        self.views[view].depth
     }
 
-    fn start_calibration_frame(&mut self) {
-        debug_assert!(self.state.task.is_none());
+    /// Returns the amount of workgroups with unfinished pixels and the total amount of them, once
+    /// it's known
+    pub fn active_tiles(&self, view: usize) -> Option<(u32, u32)> {
+        let view = &self.views[view];
+        let total = tile_count(view.params.scaled_dimensions, self.workgroup_width);
+        view.tile_counter.active().map(|active| (active, total))
+    }
 
-        self.state.task = Some(Task::Calibration);
+    /// Returns the fractions of the calculated pixels that have been found inside the set, and of
+    /// the ones of them that the cardioid and bulb tests have skipped, once they're known.
+    /// Includes the padding of the rows
+    pub fn settled_fraction(&self, view: usize) -> Option<(f64, f64)> {
+        let view = &self.views[view];
+        let size = view.params.scaled_dimensions;
+        let total = size.aligned_width(ROW_ALIGNMENT) as f64 * size.height as f64;
+        let settled = view.tile_counter.settled()?;
+        let skipped = view.tile_counter.skipped()?;
+        Some((settled as f64 / total, skipped as f64 / total))
+    }
+
+    /// Starts reading back the iteration count of the view pixel `x`, `y` in physical pixels from
+    /// the top left corner of the view. The count is collected by the polls after the next frame
+    /// and kept up to date until the view changes. Returns false if the pixel is outside the view
+    pub fn read_result_pixel(&mut self, view: usize, x: u32, y: u32) -> bool {
+        let view = &mut self.views[view];
+        let scaled = view.params.scaled_dimensions;
+        // Views may be calculated at a lower resolution than they're shown at
+        let scale = |position: u32, shown: u32, calculated: u32| {
+            (position as u64 * calculated as u64 / shown.max(1) as u64) as u32
+        };
+        let offset = pixel_offset(
+            scaled,
+            scale(x, view.rect.width, scaled.width),
+            scale(y, view.rect.height, scaled.height),
+        );
+        match offset {
+            Some(offset) => {
+                view.pixel_readback.inspect(offset);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the last iteration count read back for the pixel inspected in a view
+    pub fn result_pixel(&self, view: usize) -> Option<u32> {
+        self.views[view].pixel_readback.iterations()
+    }
+
+    /// Returns how many iterations per second the last timed frame has calculated, slower with
+    /// distance estimation
+    pub fn iteration_rate(&self) -> Option<f64> {
+        self.state.fps_balancer.iteration_rate()
+    }
+
+    pub fn surface_retries(&self) -> u32 {
+        self.state.surface_retries
+    }
 
+    /// Returns the next surface texture, reconfiguring the surface once if it's lost or outdated
+    fn acquire_frame(&mut self) -> Result<wgpu::SurfaceTexture, wgpu::SurfaceError> {
+        match self.surface.get_current_texture() {
+            Err(e @ (wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)) => {
+                log::debug!("Reconfiguring the surface: {}", e);
+                self.state.surface_retries += 1;
+                self.surface.configure(&self.device, &self.config);
+                self.surface.get_current_texture()
+            }
+            result => result,
+        }
+    }
+
+    /// Starts the frames of the views that need them and splits their dispatch into bands
+    fn start_frames(&mut self) {
+        if let Some(timer) = &mut self.pass_timer {
+            timer.start_frame();
+        }
+        for view in 0..self.views.len() {
+            let view_ref = &mut self.views[view];
+            if view_ref.readback.is_some() {
+                if view_ref.params.update.is_none() {
+                    continue;
+                }
+                // The progress being read back is outdated
+                view_ref.readback = None;
+            }
+            if self.paused && view_ref.params.update.is_none() {
+                continue;
+            }
+            // Finished views are only drawn again, their results stay in the textures
+            if !needs_compute(
+                view_ref.params.update.is_some(),
+                view_ref.restored,
+                view_ref.depth,
+                self.max_depth,
+                view_ref.is_converged(),
+            ) {
+                continue;
+            }
+            self.start_render_frame(view);
+        }
+
+        let dispatched = self
+            .views
+            .iter()
+            .filter(|view| view.dispatches(self.max_depth));
+        let height = dispatched
+            .clone()
+            .map(|view| view.params.scaled_dimensions.height)
+            .max()
+            .unwrap_or(0);
+        let iterations = dispatched
+            .filter_map(|view| match view.task {
+                Some(Task::Render(depth)) => Some(depth.saturating_sub(view.depth)),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let expected_ms = self.state.fps_balancer.expected_frame_ms(iterations);
+        let rows = band_rows(height, expected_ms, self.band_budget_ms);
+        if rows < height {
+            log::debug!("Frame split into bands of {} rows", rows);
+        }
+        self.bands = Some(Bands::new(rows, height));
+    }
+
+    /// Submits the next band of the frame in progress on its own
+    fn submit_band(&mut self) {
+        let Some(mut bands) = self.bands else {
+            return;
+        };
+        let mut command_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Band Encoder"),
+                });
+        self.encode_band(&mut command_encoder, &mut bands);
+        self.queue.submit(Some(command_encoder.finish()));
+        self.watch_submission();
+        self.bands = Some(bands);
+    }
+
+    /// Records the compute dispatch of the next band of the views in the frame in progress
+    fn encode_band(&mut self, command_encoder: &mut wgpu::CommandEncoder, bands: &mut Bands) {
+        let rows = bands.next();
+        for view in &mut self.views {
+            if !view.dispatches(self.max_depth) {
+                continue;
+            }
+            let height = view.params.scaled_dimensions.height;
+            let (start, end) = (rows.start.min(height), rows.end.min(height));
+
+            command_encoder.push_debug_group("Compute");
+            if bands.is_first() {
+                view.tile_counter
+                    .encode_reset(command_encoder, &view.compute_bindings);
+            }
+            if start < end {
+                view.compute_bindings.write_row_offset(&self.queue, start);
+                if let Some(perturbation) = &mut view.perturbation {
+                    perturbation.write_row_offset(&self.queue, start);
+                }
+                let mut cpass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Compute"),
+                    timestamp_writes: self.pass_timer.as_mut().and_then(PassTimer::pass_writes),
+                });
+                let workgroups = (
+                    view.params.scaled_dimensions.aligned_width(ROW_ALIGNMENT)
+                        / self.workgroup_width,
+                    end - start,
+                );
+                // The exact shader repairs the glitches of the deltas right after them
+                if let Some(perturbation) = &view.perturbation {
+                    perturbation.encode(&mut cpass, &self.perturbation_pass, workgroups);
+                }
+                cpass.set_pipeline(&view.compute_pipeline);
+                cpass.set_bind_group(0, &view.compute_bindings.bind_group, &[]);
+                cpass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+            }
+            if bands.is_last() {
+                view.tile_counter
+                    .encode_copy(command_encoder, &view.compute_bindings);
+            }
+            command_encoder.pop_debug_group();
+        }
+        bands.submit();
+    }
+
+    fn start_calibration_frame(&mut self, view: usize) {
+        debug_assert!(!self.is_busy());
+
+        let view = &mut self.views[view];
+        view.task = Some(Task::Calibration);
+
+        let scale =
+            balanced_pixels(view.params.scaled_dimensions) / balanced_pixels(CALIBRATION_GRID);
         let iter_count = self
             .state
             .fps_balancer
-            .start_calibration_frame(self.params.word_count);
+            .start_calibration_frame(view.workload(), scale);
 
-        let mut command_encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let mut command_encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Calibration Encoder"),
+                });
 
-        self.calibration_bindings
-            .write_iterate_reset(&mut self.queue, iter_count);
+        view.calibration_bindings
+            .write_iterate_reset(&self.queue, iter_count);
+        if let Some(timer) = &mut self.pass_timer {
+            timer.start_frame();
+        }
 
         command_encoder.push_debug_group("Calibrate");
         {
             let mut cpass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                label: None,
-                timestamp_writes: None,
+                label: Some("Calibrate"),
+                timestamp_writes: self.pass_timer.as_mut().and_then(PassTimer::pass_writes),
             });
-            cpass.set_pipeline(&self.compute_pipeline);
-            cpass.set_bind_group(0, &self.calibration_bindings.bind_group, &[]);
+            cpass.set_pipeline(&view.compute_pipeline);
+            cpass.set_bind_group(0, &view.calibration_bindings.bind_group, &[]);
             cpass.dispatch_workgroups(
-                self.params.scaled_dimensions.aligned_width(64) / 64,
-                self.params.scaled_dimensions.height,
+                CALIBRATION_GRID.aligned_width(ROW_ALIGNMENT) / self.workgroup_width,
+                CALIBRATION_GRID.height,
                 1,
             );
         }
         command_encoder.pop_debug_group();
+        if let Some(timer) = &mut self.pass_timer {
+            timer.encode_resolve(&mut command_encoder);
+        }
 
         // submit will accept anything that implements IntoIter
         self.queue.submit(Some(command_encoder.finish()));
+        self.watch_submission();
+        if let Some(timer) = &mut self.pass_timer {
+            timer.request_map();
+        }
     }
 
-    fn start_render_frame(&mut self) {
-        debug_assert!(self.state.task.is_none());
+    /// Prepares the next frame of a view. With several views the balancer times their combined
+    /// work, so that all of them fit into the frame together
+    fn start_render_frame(&mut self, index: usize) {
+        debug_assert!(self.views[index].task.is_none());
 
-        match self.params.update.take() {
+        match self.views[index].params.update.take() {
             Some(ParamsUpdate::Move { coords }) => {
+                let previous_word_count = self.views[index].params.word_count;
+                let coords = self.prepare_word_count(index, coords);
+
+                let view = &mut self.views[index];
+                view.keep_frame(
+                    &self.device,
+                    &self.queue,
+                    None,
+                    &coords,
+                    view.depth,
+                    min(REPROJECTION_DEPTH, self.max_depth),
+                    self.reprojection,
+                );
+
                 // Reset calculated depth
-                self.state.depth = 0;
+                view.depth = 0;
+                view.tile_counter.clear();
+                view.pixel_readback.clear();
+                view.histogram.clear();
+                let perturbed = self.perturbation
+                    && is_perturbed(coords.size(), &view.params.fractal, self.iteration);
+                // Snapshots don't have the deltas, so perturbed views aren't cached
+                view.params.key = (!perturbed).then(|| {
+                    SnapshotKey::new(
+                        &coords,
+                        view.params.scaled_dimensions,
+                        &view.params.fractal,
+                        self.iteration,
+                    )
+                });
+                view.params.distance_estimation = self.iteration.distance_estimation;
 
-                let iterations = self
-                    .state
-                    .fps_balancer
-                    .present_iterations(self.params.word_count);
-                let new_depth = min(iterations, self.params.max_depth);
-
-                // NOTE: Not extracted into a dedicated function since it's a temporary solution while override
-                // variables are not supported in wgpu
-                if coords.size() != self.params.word_count {
-                    log::info!("Changing number word count to {}", coords.size());
-                    self.params.word_count = coords.size();
-                    let compute_shader_src = COMPUTE_SHADER_TEMPLATE.replace(
-                        "const word_count: u32 = 8;",
-                        &format!("const word_count: u32 = {};", self.params.word_count),
-                    );
-                    let compute_shader =
-                        self.device
-                            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                                label: None,
-                                source: wgpu::ShaderSource::Wgsl(Cow::Owned(compute_shader_src)),
-                            });
-                    self.compute_pipeline =
-                        self.device
-                            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                                label: Some("Compute Pipeline"),
-                                layout: Some(&self.device.create_pipeline_layout(
-                                    &wgpu::PipelineLayoutDescriptor {
-                                        label: Some("Compute PipelineLayout"),
-                                        bind_group_layouts: &[&self.compute_bind_group_layout],
-                                        push_constant_ranges: &[],
-                                    },
-                                )),
-                                module: &compute_shader,
-                                entry_point: "main",
-                            });
+                let iterations = self.state.fps_balancer.present_iterations(view.workload());
+                let new_depth = min(iterations, self.max_depth);
+                let repair = repair_iterations(perturbed, new_depth, new_depth, self.max_depth);
 
+                if view.params.word_count != previous_word_count {
                     // Resize compute shader bindings
-                    self.compute_bindings = ComputeBindings::new(
+                    view.compute_bindings.resize(
                         &self.device,
                         &self.compute_bind_group_layout,
-                        self.params.scaled_dimensions,
+                        view.params.scaled_dimensions,
                         coords.size(),
-                    )
-                    .write(
-                        &self.queue,
-                        &ComputeParams::new(self.params.scaled_dimensions, &coords, new_depth),
+                        self.workgroup_width,
                     );
-                    if !self
-                        .state
-                        .fps_balancer
-                        .is_calibrated(self.params.word_count)
-                    {
-                        self.calibration_bindings = ComputeBindings::new(
-                            &self.device,
-                            &self.compute_bind_group_layout,
-                            self.params.scaled_dimensions,
-                            coords.size(),
-                        )
-                        .write(
-                            &self.queue,
-                            &ComputeParams::new(
-                                self.params.scaled_dimensions,
-                                &calibration_coords(coords.size(), coords.precision()),
-                                FpsBalancer::UNCALIBRATED_LIMIT,
-                            ),
-                        );
-                    }
-                } else {
-                    self.compute_bindings.write(
-                        &self.queue,
-                        &ComputeParams::new(self.params.scaled_dimensions, &coords, new_depth),
+                }
+                view.compute_bindings.write(
+                    &self.queue,
+                    &ComputeParams::new(
+                        view.params.scaled_dimensions,
+                        &coords,
+                        &view.params.fractal,
+                        self.iteration,
+                        new_depth,
+                    )
+                    .with_repair(repair),
+                );
+                view.start_perturbation(
+                    &self.device,
+                    &self.queue,
+                    &self.perturbation_pass,
+                    &coords,
+                    self.iteration,
+                    new_depth,
+                    perturbed,
+                );
+
+                // Calibration frames of a new workload iterate like it
+                if !self.state.fps_balancer.is_calibrated(view.workload()) {
+                    let calibration_coords = calibration_coords(coords.size(), coords.precision());
+                    let calibration_fractal = FractalParams::default();
+                    let calibration_params = ComputeParams::new(
+                        CALIBRATION_GRID,
+                        &calibration_coords,
+                        &calibration_fractal,
+                        calibration_iteration(self.iteration),
+                        FpsBalancer::UNCALIBRATED_LIMIT,
+                    )
+                    .without_interior_checks();
+                    // Only the word count changes the size of the calibration buffers
+                    view.calibration_bindings.resize(
+                        &self.device,
+                        &self.compute_bind_group_layout,
+                        CALIBRATION_GRID,
+                        coords.size(),
+                        self.workgroup_width,
                     );
+                    view.calibration_bindings
+                        .write(&self.queue, &calibration_params);
                 }
 
-                self.render_bindings.write(
+                view.render_bindings.write(
                     &self.queue,
                     FragmentParams {
-                        size: self.params.scaled_dimensions,
+                        size: view.params.scaled_dimensions,
                         depth: new_depth,
+                        output_is_srgb: encodes_srgb(self.config.format).into(),
                     },
                 );
+                view.render_bindings
+                    .write_distance_shading(&self.queue, view.params.distance_estimation);
 
-                self.state.task = Some(Task::Render(new_depth));
+                view.task = Some(Task::Render(new_depth));
 
                 if new_depth == iterations {
                     self.state
                         .fps_balancer
-                        .start_presentation_frame(self.params.word_count)
+                        .start_presentation_frame(view.workload())
                 }
             }
             Some(ParamsUpdate::Resize {
-                dimensions,
+                rect,
                 scale,
                 coords,
             }) => {
-                // Reset calculated depth
-                self.state.depth = 0;
+                // The calibration follows the pixels of the resized view
+                let scaled_dimensions = rect.dimensions().scale_to(scale);
+                self.state
+                    .fps_balancer
+                    .set_pixels(balanced_pixels(scaled_dimensions));
 
-                // Reset fps balancer
-                self.state.fps_balancer.reset();
+                let coords = self.prepare_word_count(index, coords);
 
-                let iterations = self
-                    .state
-                    .fps_balancer
-                    .present_iterations(self.params.word_count);
-                let new_depth = min(iterations, self.params.max_depth);
+                let view = &mut self.views[index];
+                let shown_depth = view.depth;
 
-                // Update window scale
-                self.params.scale = scale;
+                // Reset calculated depth
+                view.depth = 0;
+                view.tile_counter.clear();
+                view.pixel_readback.clear();
+                view.histogram.clear();
+                view.params.distance_estimation = self.iteration.distance_estimation;
 
-                // Reconfigure the surface
-                self.config.width = dimensions.width;
-                self.config.height = dimensions.height;
-                self.surface.configure(&self.device, &self.config);
+                let iterations = self.state.fps_balancer.present_iterations(view.workload());
+                let new_depth = min(iterations, self.max_depth);
 
-                let scaled_dimensions = dimensions.scale_to(scale);
-                self.params.scaled_dimensions = scaled_dimensions;
-
-                // NOTE: Not extracted into a dedicated function since it's a temporary solution while override
-                // variables are not supported in wgpu
-                if coords.size() != self.params.word_count {
-                    log::info!("Changing number word count to {}", coords.size());
-                    self.params.word_count = coords.size();
-                    let compute_shader_src = COMPUTE_SHADER_TEMPLATE.replace(
-                        "const word_count: u32 = 8;",
-                        &format!("const word_count: u32 = {};", self.params.word_count),
-                    );
-                    let compute_shader =
-                        self.device
-                            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                                label: None,
-                                source: wgpu::ShaderSource::Wgsl(Cow::Owned(compute_shader_src)),
-                            });
-                    self.compute_pipeline =
-                        self.device
-                            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                                label: Some("Compute Pipeline"),
-                                layout: Some(&self.device.create_pipeline_layout(
-                                    &wgpu::PipelineLayoutDescriptor {
-                                        label: Some("Compute PipelineLayout"),
-                                        bind_group_layouts: &[&self.compute_bind_group_layout],
-                                        push_constant_ranges: &[],
-                                    },
-                                )),
-                                module: &compute_shader,
-                                entry_point: "main",
-                            });
-                }
+                // Update window scale
+                view.params.scale = scale;
+
+                view.rect = rect;
+                view.params.scaled_dimensions = scaled_dimensions;
+                let perturbed = self.perturbation
+                    && is_perturbed(coords.size(), &view.params.fractal, self.iteration);
+                view.params.key = (!perturbed).then(|| {
+                    SnapshotKey::new(
+                        &coords,
+                        scaled_dimensions,
+                        &view.params.fractal,
+                        self.iteration,
+                    )
+                });
 
                 // Resize compute shader bindings
-                self.compute_bindings = ComputeBindings::new(
+                view.compute_bindings.resize(
                     &self.device,
                     &self.compute_bind_group_layout,
                     scaled_dimensions,
                     coords.size(),
-                )
-                .write(
+                    self.workgroup_width,
+                );
+                view.compute_bindings.write(
+                    &self.queue,
+                    &ComputeParams::new(
+                        scaled_dimensions,
+                        &coords,
+                        &view.params.fractal,
+                        self.iteration,
+                        new_depth,
+                    )
+                    .with_repair(repair_iterations(
+                        perturbed,
+                        new_depth,
+                        new_depth,
+                        self.max_depth,
+                    )),
+                );
+                view.start_perturbation(
+                    &self.device,
                     &self.queue,
-                    &ComputeParams::new(scaled_dimensions, &coords, new_depth),
+                    &self.perturbation_pass,
+                    &coords,
+                    self.iteration,
+                    new_depth,
+                    perturbed,
                 );
 
-                // Update calibration bindings
-                self.calibration_bindings = ComputeBindings::new(
+                // Update calibration bindings, whose size only changes with the word count
+                view.calibration_bindings.resize(
                     &self.device,
                     &self.compute_bind_group_layout,
-                    scaled_dimensions,
+                    CALIBRATION_GRID,
                     coords.size(),
-                )
-                .write(
+                    self.workgroup_width,
+                );
+                view.calibration_bindings.write(
                     &self.queue,
                     &ComputeParams::new(
-                        self.params.scaled_dimensions,
+                        CALIBRATION_GRID,
                         &calibration_coords(coords.size(), coords.precision()),
+                        &FractalParams::default(),
+                        calibration_iteration(self.iteration),
                         FpsBalancer::UNCALIBRATED_LIMIT,
-                    ),
+                    )
+                    .without_interior_checks(),
                 );
 
-                // Resize render shader bindings
-                self.render_bindings = RenderBindings::new(
+                // Resize render shader bindings. Textures that have room for the new size are
+                // reused, so the shown frame is kept before the new one overwrites it
+                let fade_depth = min(REPROJECTION_DEPTH, self.max_depth);
+                if view.render_bindings.fits(scaled_dimensions) {
+                    view.keep_frame(
+                        &self.device,
+                        &self.queue,
+                        None,
+                        &coords,
+                        shown_depth,
+                        fade_depth,
+                        self.reprojection,
+                    );
+                }
+                let shown_bindings = view.render_bindings.resize(
                     &self.device,
                     &self.render_bind_group_layout,
                     scaled_dimensions,
-                )
-                .write(
+                );
+                view.render_bindings.write(
                     &self.queue,
                     FragmentParams {
                         size: scaled_dimensions,
                         depth: new_depth,
+                        output_is_srgb: encodes_srgb(self.config.format).into(),
                     },
                 );
+                if let Some(shown_bindings) = &shown_bindings {
+                    view.keep_frame(
+                        &self.device,
+                        &self.queue,
+                        Some(shown_bindings),
+                        &coords,
+                        shown_depth,
+                        fade_depth,
+                        self.reprojection,
+                    );
+                }
+                view.render_bindings.write_colors(&self.queue, self.colors);
+                view.render_bindings
+                    .write_palette(&self.queue, &self.palette);
+                view.render_bindings
+                    .write_distance_shading(&self.queue, view.params.distance_estimation);
 
-                self.state.task = Some(Task::Render(new_depth));
+                view.task = Some(Task::Render(new_depth));
 
                 if iterations == new_depth {
                     self.state
                         .fps_balancer
-                        .start_presentation_frame(self.params.word_count);
+                        .start_presentation_frame(view.workload());
                 }
             }
             None => {
+                let view = &mut self.views[index];
                 let iterations = self.state.fps_balancer.iteration_iterations;
-                let new_depth = self
-                    .state
-                    .depth
-                    .saturating_add(iterations)
-                    .min(self.params.max_depth);
+                let new_depth = view.depth.saturating_add(iterations).min(self.max_depth);
 
-                if self.state.depth < new_depth {
-                    self.compute_bindings.write_iterate(&self.queue, new_depth);
+                if view.depth < new_depth {
+                    view.compute_bindings.write_iterate(&self.queue, new_depth);
+                    if let Some(perturbation) = &mut view.perturbation {
+                        perturbation.write(
+                            &self.device,
+                            &self.queue,
+                            &self.perturbation_pass,
+                            &view.compute_bindings,
+                            new_depth,
+                            false,
+                        );
+                        view.compute_bindings.write_repair(
+                            &self.queue,
+                            repair_iterations(
+                                true,
+                                new_depth - view.depth,
+                                new_depth,
+                                self.max_depth,
+                            ),
+                        );
+                    }
 
-                    self.state.task = Some(Task::Render(new_depth));
+                    view.task = Some(Task::Render(new_depth));
 
                     // Start frame timer if iteration count wasn't clamped
-                    if new_depth - self.state.depth == iterations {
+                    if new_depth - view.depth == iterations {
                         self.state.fps_balancer.start_iteration_frame()
                     }
                 } else {
-                    self.state.task = Some(Task::Render(self.state.depth));
+                    view.task = Some(Task::Render(view.depth));
                 }
 
-                self.render_bindings.write(
+                view.render_bindings.write(
                     &self.queue,
                     FragmentParams {
-                        size: self.params.scaled_dimensions,
+                        size: view.params.scaled_dimensions,
                         depth: new_depth,
+                        output_is_srgb: encodes_srgb(self.config.format).into(),
                     },
                 );
             }
         }
     }
 }
+
+/// Snapshots of the calculation progress. Only the native disk cache uses them
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+impl GpuContext<'_> {
+    /// Returns the calculation a view is busy with, unless new parameters are yet to be applied
+    pub fn snapshot_key(&self, view: usize) -> Option<&SnapshotKey> {
+        let params = &self.views[view].params;
+        params
+            .update
+            .is_none()
+            .then_some(params.key.as_ref())
+            .flatten()
+    }
+
+    /// Starts reading back the calculation progress of a view. The view is paused until the
+    /// snapshot is taken with [`GpuContext::take_snapshot`], or until its parameters change
+    pub fn start_readback(&mut self, index: usize) -> bool {
+        let Some(key) = self.snapshot_key(index).cloned() else {
+            return false;
+        };
+        let view = &mut self.views[index];
+        if view.readback.is_some() || view.depth == 0 {
+            return false;
+        }
+        // Work in flight would change the buffers between chunks
+        if view.task.is_some() || self.bands.is_some() {
+            return false;
+        }
+        view.readback = Some(Readback::new(
+            &self.device,
+            &view.compute_bindings,
+            key,
+            view.depth,
+        ));
+        true
+    }
+
+    /// Returns a completed readback with the index of its view
+    pub fn take_snapshot(&mut self) -> Option<(usize, Snapshot)> {
+        self.snapshots.pop()
+    }
+
+    /// Reads back the calculation progress of a view, blocking until it's complete. Meant for
+    /// the app exit, when there are no more frames to spread the readback over
+    pub fn read_back_now(&mut self, index: usize) -> Option<Snapshot> {
+        while self.is_busy() {
+            self.device.poll(wgpu::Maintain::Wait);
+            self.poll();
+        }
+        if self.views[index].readback.is_none() && !self.start_readback(index) {
+            return None;
+        }
+        let view = &mut self.views[index];
+        let readback = view.readback.as_mut().expect("Readback is started above");
+        loop {
+            let mut command_encoder =
+                self.device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("Readback Encoder"),
+                    });
+            readback.encode_next(&mut command_encoder, &view.compute_bindings);
+            self.queue.submit(Some(command_encoder.finish()));
+            readback.request_map();
+            self.device.poll(wgpu::Maintain::Wait);
+            match readback.receive() {
+                Progress::Pending => {}
+                Progress::Done(snapshot) => {
+                    view.readback = None;
+                    return Some(snapshot);
+                }
+                Progress::Failed(e) => {
+                    log::error!("Readback of view {} has failed: {}", index, e);
+                    view.readback = None;
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Returns true while a frame is split into bands, snapshots are only restored once it's
+    /// presented
+    pub fn is_splitting_frame(&self) -> bool {
+        self.bands.is_some()
+    }
+
+    /// Reads back the iteration counts of a view along with the state to resume its calculation
+    /// from, blocking until they're complete. Returns nothing before the first frame of the view
+    /// and while a frame is split into bands, whose rows are at different depths
+    pub fn export_iterations(&mut self, index: usize) -> Option<Iterations> {
+        while self.is_busy() {
+            self.device.poll(wgpu::Maintain::Wait);
+            self.poll();
+        }
+        if self.bands.is_some() || self.views[index].depth == 0 {
+            return None;
+        }
+        // Perturbed views have no key, their deltas aren't read back
+        let resume = self
+            .snapshot_key(index)
+            .is_some()
+            .then(|| self.read_back_now(index))
+            .flatten();
+        let view = &self.views[index];
+        let coords = view.shown.clone()?;
+        let size = view.params.scaled_dimensions;
+        let counts = match &resume {
+            Some(snapshot) => unpadded_counts(&snapshot.iterations, size),
+            None => {
+                let bindings = &view.compute_bindings;
+                let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Iterations Export"),
+                    size: bindings.sizes.result,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let mut command_encoder =
+                    self.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Iterations Export Encoder"),
+                        });
+                command_encoder.copy_buffer_to_buffer(
+                    &bindings.result_buffer,
+                    0,
+                    &staging,
+                    0,
+                    bindings.sizes.result,
+                );
+                self.queue.submit(Some(command_encoder.finish()));
+                let (sender, result) = flume::bounded(1);
+                staging.slice(..).map_async(wgpu::MapMode::Read, move |r| {
+                    let _ = sender.send(r);
+                });
+                self.device.poll(wgpu::Maintain::Wait);
+                if let Err(e) = result.recv().expect("Mapping completes with the poll") {
+                    log::error!("Unable to read the iterations of view {}: {}", index, e);
+                    return None;
+                }
+                let counts = unpadded_counts(&staging.slice(..).get_mapped_range(), size);
+                staging.unmap();
+                counts
+            }
+        };
+        Some(Iterations {
+            size,
+            coords,
+            depth: view.depth,
+            counts,
+            resume,
+        })
+    }
+
+    /// Resumes the calculation of a view from a snapshot. Snapshots of other calculations and
+    /// ones behind the current depth are ignored
+    pub fn restore(&mut self, index: usize, snapshot: Snapshot) -> bool {
+        // Bands left of the frame in progress would continue from the state it started with
+        if self.snapshot_key(index) != Some(&snapshot.key) || self.bands.is_some() {
+            return false;
+        }
+        let view = &mut self.views[index];
+        let bindings = &view.compute_bindings;
+        if snapshot.depth <= view.depth
+            || snapshot.iterations.len() as u64 != bindings.sizes.result
+            || snapshot.intermediate.len() as u64 != bindings.sizes.intermediate
+        {
+            return false;
+        }
+
+        view.readback = None;
+        self.queue
+            .write_buffer(&bindings.result_buffer, 0, &snapshot.iterations);
+        self.queue
+            .write_buffer(&bindings.intermediate_buffer, 0, &snapshot.intermediate);
+        // Finished flags were set by the reset frame at the same location, but don't rely on it
+        self.queue.write_buffer(
+            &bindings.tiles_buffer,
+            0,
+            &vec![0; bindings.sizes.tiles as usize],
+        );
+        bindings.write_settled(&self.queue, settled_count(&snapshot.iterations));
+        // Continue from the restored state instead of resetting it
+        bindings.write_iterate(&self.queue, snapshot.depth);
+        view.render_bindings.write(
+            &self.queue,
+            FragmentParams {
+                size: view.params.scaled_dimensions,
+                depth: snapshot.depth,
+                output_is_srgb: encodes_srgb(self.config.format).into(),
+            },
+        );
+        view.depth = snapshot.depth;
+        view.restored = true;
+        // Work in flight finishes before the writes, its depth is outdated
+        if let Some(Task::Render(_)) = view.task {
+            view.task = Some(Task::Render(snapshot.depth));
+        }
+        true
+    }
+}
+
+impl GpuContext<'_> {
+    /// Switches the pipeline of a view to the word count of `coords`. Returns the coordinates
+    /// resized to the word count that is actually used
+    fn prepare_word_count(&mut self, view: usize, mut coords: Coordinates) -> Coordinates {
+        coords.set_word_count(self.state.status.word_count(coords.size()));
+        let word_count = self.views[view].params.word_count;
+        self.views[view].params.awaiting = None;
+        if coords.size() != word_count && !self.change_word_count(view, coords.size()) {
+            if self.compiling_word_count() == Some(coords.size()) {
+                self.views[view].params.awaiting = Some(coords.clone());
+            }
+            coords.set_word_count(word_count);
+        }
+        coords
+    }
+
+    /// Switches the compute pipeline of a view to `word_count` words. Returns false if the
+    /// pipeline is still being compiled or has failed, in which case the current one is kept and
+    /// the context is degraded
+    fn change_word_count(&mut self, view: usize, word_count: usize) -> bool {
+        let pipeline = match self.compute_pipelines.get(word_count) {
+            Some(pipeline) => pipeline,
+            None => match self.compile_pipeline(word_count) {
+                Some(Ok(pipeline)) => pipeline,
+                Some(Err(e)) => {
+                    let word_count = self.views[view].params.word_count;
+                    self.state.degrade(word_count, e.to_string());
+                    return false;
+                }
+                None => return false,
+            },
+        };
+        log::info!("Changing number word count to {}", word_count);
+        let params = &mut self.views[view].params;
+        // Errors of the new pipeline aren't captured while it's compiled, only the previous one is
+        // known to work until they arrive
+        params.working_word_count = params.word_count;
+        params.word_count = word_count;
+        self.views[view].compute_pipeline = pipeline;
+        true
+    }
+
+    /// Starts compiling the compute pipeline of `word_count` words on another thread, replacing
+    /// a compilation of another word count, whose pipeline is dropped. Returns None while it's
+    /// compiled
+    #[cfg(not(target_arch = "wasm32"))]
+    fn compile_pipeline(
+        &mut self,
+        word_count: usize,
+    ) -> Option<Result<Arc<wgpu::ComputePipeline>, wgpu::Error>> {
+        if self.compiling_word_count() == Some(word_count) {
+            return None;
+        }
+        log::info!("Compiling the compute pipeline of {} words", word_count);
+        let source = compute_shader_source(word_count, self.workgroup_width)
+            .expect("Word count is checked when the update is queued");
+        let layout = create_compute_pipeline_layout(&self.device, &self.compute_bind_group_layout);
+        let device = self.device.clone();
+        let notifier = self.pipeline_notifier.clone();
+        let (sender, pipeline) = flume::bounded(1);
+        std::thread::spawn(move || {
+            let compiled = compile_compute_pipeline(&device, &layout, source);
+            if sender.send(compiled).is_ok() {
+                if let Some(notify) = notifier {
+                    notify();
+                }
+            }
+        });
+        self.compilation = Some(Compilation {
+            word_count,
+            pipeline,
+        });
+        None
+    }
+
+    /// Compiles the compute pipeline of `word_count` words right away, the web has no threads to
+    /// compile it on
+    #[cfg(target_arch = "wasm32")]
+    fn compile_pipeline(
+        &mut self,
+        word_count: usize,
+    ) -> Option<Result<Arc<wgpu::ComputePipeline>, wgpu::Error>> {
+        let compiled = capture_errors(&self.device, || {
+            create_compute_pipeline(
+                &self.device,
+                &self.compute_bind_group_layout,
+                word_count,
+                self.workgroup_width,
+            )
+            .expect("Word count is checked when the update is queued")
+        });
+        Some(compiled.map(|pipeline| self.compute_pipelines.insert(word_count, pipeline)))
+    }
+
+    /// Sets the function called from another thread once a pipeline compiled in the background
+    /// can be received
+    pub fn set_pipeline_notifier(&mut self, notifier: impl Fn() + Send + Sync + 'static) {
+        self.pipeline_notifier = Some(Arc::new(notifier));
+    }
+
+    /// Calls `notifier` once the watched submissions are done, where polling the device doesn't
+    /// tell. Only used on the web
+    pub fn set_work_done_notifier(&mut self, notifier: impl Fn() + Send + Sync + 'static) {
+        if let Some(work_done) = &mut self.work_done {
+            work_done.set_notifier(Arc::new(notifier));
+        }
+    }
+
+    /// Word count of the compute pipeline being compiled in the background
+    pub fn compiling_word_count(&self) -> Option<usize> {
+        self.compilation
+            .as_ref()
+            .map(|compilation| compilation.word_count)
+    }
+
+    /// Receives the pipeline compiled in the background. The views waiting for it switch to it
+    /// and restart their calculation. Returns true if it was received
+    pub fn receive_pipeline(&mut self) -> bool {
+        let Some(compilation) = &self.compilation else {
+            return false;
+        };
+        let Ok(pipeline) = compilation.pipeline.try_recv() else {
+            return false;
+        };
+        let word_count = compilation.word_count;
+        self.compilation = None;
+        self.compute_pipelines.insert(word_count, pipeline);
+        for view in &mut self.views {
+            if let Some(coords) = view.params.awaiting.take() {
+                view.params
+                    .update
+                    .get_or_insert(ParamsUpdate::Move { coords });
+            }
+        }
+        true
+    }
+}
+
+/// Runs `f`. Error scopes can only be resolved asynchronously on the web, errors reach the
+/// uncaptured error handler instead
+#[cfg(target_arch = "wasm32")]
+fn capture_errors<T>(_device: &wgpu::Device, f: impl FnOnce() -> T) -> Result<T, wgpu::Error> {
+    Ok(f())
+}
+
+/// Requests a device on the adapter that fits the surface best
+async fn request_device(
+    instance: &wgpu::Instance,
+    surface: &wgpu::Surface<'_>,
+    diagnostics: &Diagnostics,
+    power: PowerPreference,
+) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue), ContextCreationError> {
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: power.to_wgpu(),
+            compatible_surface: Some(surface),
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok_or(ContextCreationError::AdapterRequest)?;
+
+    let mut device_limits = wgpu::Limits::default().using_resolution(adapter.limits());
+
+    // TODO: Save the limit and use it for buffer sizing
+    device_limits.max_storage_buffer_binding_size =
+        adapter.limits().max_storage_buffer_binding_size;
+
+    // Some mobile adapters support less than the default workgroups, shaders adapt to them
+    device_limits.max_compute_workgroup_size_x = device_limits
+        .max_compute_workgroup_size_x
+        .min(adapter.limits().max_compute_workgroup_size_x);
+    device_limits.max_compute_invocations_per_workgroup = device_limits
+        .max_compute_invocations_per_workgroup
+        .min(adapter.limits().max_compute_invocations_per_workgroup);
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                // Frames are timed on the GPU where possible
+                required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+                required_limits: device_limits,
+                label: Some("Device"),
+            },
+            diagnostics.trace_dir.as_deref(),
+        )
+        .await?;
+    Ok((adapter, device, queue))
+}
+
+/// Reports the errors of `device` that aren't captured by an error scope, and raises the
+/// returned flag once the device is lost
+fn watch_device(device: &wgpu::Device) -> (flume::Receiver<String>, Arc<AtomicBool>) {
+    // The default handler panics, report errors to the app instead
+    let (device_error_sender, device_errors) = flume::unbounded();
+    device.on_uncaptured_error(Box::new(move |e| {
+        let _ = device_error_sender.send(e.to_string());
+    }));
+    let device_lost = Arc::new(AtomicBool::new(false));
+    let flag = device_lost.clone();
+    device.set_device_lost_callback(move |reason, message| {
+        // Devices replaced by a rebuild are dropped on purpose
+        if matches!(reason, wgpu::DeviceLostReason::Unknown) {
+            log::error!("GPU device lost: {}", message);
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+    (device_errors, device_lost)
+}
+
+/// Returns the surface format to use out of the supported `formats`
+fn surface_format(
+    formats: &[wgpu::TextureFormat],
+    deep_color: bool,
+) -> Option<wgpu::TextureFormat> {
+    if deep_color {
+        formats.iter().copied().find(|f| *f == DEEP_COLOR_FORMAT)
+    } else {
+        // Formats that don't encode the colors into sRGB themselves have it done by the shader
+        let srgb = formats.iter().copied().find(|f| f.is_srgb());
+        if srgb.is_none() {
+            log::info!("No sRGB surface format, colors are encoded by the shader");
+        }
+        srgb.or_else(|| formats.first().copied())
+    }
+}
+
+fn create_ui_renderer(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: wgpu::TextureFormat,
+) -> iced_wgpu::Renderer {
+    iced_wgpu::Renderer::new(
+        iced_wgpu::Backend::new(device, queue, iced_wgpu::Settings::default(), format),
+        iced::Font::default(),
+        iced::Pixels(16.0),
+    )
+}
+
+/// Returns the widest workgroup within `limits` that rows are divided into evenly
+fn workgroup_width(limits: &wgpu::Limits) -> u32 {
+    let width = ROW_ALIGNMENT
+        .min(limits.max_compute_workgroup_size_x)
+        .min(limits.max_compute_invocations_per_workgroup)
+        .max(1);
+    1 << width.ilog2()
+}
+
+/// Replaces the workgroup size of a compute shader, as override variables aren't supported by
+/// wgpu yet
+fn with_workgroup_width(source: &str, width: u32) -> String {
+    replace_template(
+        source,
+        WORKGROUP_SIZE_TEMPLATE,
+        &format!("@workgroup_size({})", width),
+    )
+}
+
+/// Replaces the `template` declaration of a shader, which has to be there
+fn replace_template(source: &str, template: &str, replacement: &str) -> String {
+    assert!(
+        source.contains(template),
+        "Shader doesn't declare {}",
+        template
+    );
+    source.replace(template, replacement)
+}
+
+fn compute_shader_source(
+    word_count: usize,
+    workgroup_width: u32,
+) -> Result<String, UnsupportedWordCount> {
+    let word_count = check_word_count(word_count)?;
+    // Override variables aren't supported by wgpu 0.19, and they couldn't size the private
+    // arrays of the shader either. The constant is replaced in the source instead, every word
+    // count is compiled once and cached
+    let source = replace_template(
+        COMPUTE_SHADER_TEMPLATE,
+        WORD_COUNT_TEMPLATE,
+        &format!("const word_count: u32 = {};", word_count),
+    );
+    Ok(with_workgroup_width(&source, workgroup_width))
+}
+
+fn create_compute_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    word_count: usize,
+    workgroup_width: u32,
+) -> Result<wgpu::ComputePipeline, UnsupportedWordCount> {
+    let source = compute_shader_source(word_count, workgroup_width)?;
+    let layout = create_compute_pipeline_layout(device, bind_group_layout);
+    Ok(compile_compute_pipeline(device, &layout, source))
+}
+
+fn create_compute_pipeline_layout(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::PipelineLayout {
+    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Compute PipelineLayout"),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    })
+}
+
+/// Compiles the compute shader `source`, which takes a while for large word counts
+fn compile_compute_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    source: String,
+) -> wgpu::ComputePipeline {
+    let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Compute Shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Compute Pipeline"),
+        layout: Some(layout),
+        module: &compute_shader,
+        entry_point: "main",
+    })
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let render_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Render Shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("render.wgsl"))),
+    });
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Render PipelineLayout"),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(&render_pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &render_shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &render_shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Front),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{MAX_WORD_COUNT, MIN_WORD_COUNT};
+
+    #[test]
+    fn compute_shader_source_word_count() {
+        for word_count in MIN_WORD_COUNT..=MAX_WORD_COUNT {
+            let source = compute_shader_source(word_count, 64).unwrap();
+            assert!(source.contains(&format!("const word_count: u32 = {};", word_count)));
+        }
+        assert!(compute_shader_source(0, 64).is_err());
+        assert!(compute_shader_source(MIN_WORD_COUNT - 1, 64).is_err());
+        assert!(compute_shader_source(MAX_WORD_COUNT + 1, 64).is_err());
+        assert!(compute_shader_source(10_000, 64).is_err());
+    }
+
+    #[test]
+    fn shader_workgroup_size_matches_dispatch() {
+        // The templates declare the widest supported workgroup
+        let preview = include_str!("preview.wgsl");
+        for template in [COMPUTE_SHADER_TEMPLATE, preview] {
+            assert_eq!(template.matches("@workgroup_size(").count(), 1);
+            assert!(template.contains(WORKGROUP_SIZE_TEMPLATE));
+        }
+        assert_eq!(
+            WORKGROUP_SIZE_TEMPLATE,
+            format!("@workgroup_size({})", ROW_ALIGNMENT)
+        );
+
+        for width in [1, 16, 32, 64] {
+            let expected = format!("@workgroup_size({})", width);
+            assert!(compute_shader_source(MIN_WORD_COUNT, width)
+                .unwrap()
+                .contains(&expected));
+            assert!(with_workgroup_width(preview, width).contains(&expected));
+            assert_eq!(ROW_ALIGNMENT % width, 0);
+        }
+    }
+
+    #[test]
+    fn every_workgroup_has_a_tile() {
+        let size = ScaledDimensions {
+            width: 100,
+            height: 3,
+        };
+        // Rows are padded to 128 pixels
+        assert_eq!(tile_count(size, 64), 2 * 3);
+        assert_eq!(tile_count(size, 16), 8 * 3);
+        assert_eq!(tile_count(size, 1), 128 * 3);
+    }
+
+    #[test]
+    fn workgroup_width_fits_adapter() {
+        let limits = |x, invocations| wgpu::Limits {
+            max_compute_workgroup_size_x: x,
+            max_compute_invocations_per_workgroup: invocations,
+            ..wgpu::Limits::default()
+        };
+        assert_eq!(workgroup_width(&wgpu::Limits::default()), 64);
+        assert_eq!(workgroup_width(&wgpu::Limits::downlevel_defaults()), 64);
+        assert_eq!(workgroup_width(&limits(32, 256)), 32);
+        assert_eq!(workgroup_width(&limits(256, 48)), 32);
+        assert_eq!(workgroup_width(&limits(16, 16)), 16);
+        assert_eq!(workgroup_width(&limits(0, 0)), 1);
+    }
+
+    #[test]
+    fn deep_color_needs_float_format() {
+        use wgpu::TextureFormat::*;
+        let formats = [Bgra8UnormSrgb, Rgb10a2Unorm, Rgba16Float];
+        assert_eq!(surface_format(&formats, false), Some(Bgra8UnormSrgb));
+        assert_eq!(surface_format(&formats, true), Some(Rgba16Float));
+        assert_eq!(surface_format(&formats[..2], true), None);
+    }
+
+    #[test]
+    fn srgb_formats_are_preferred() {
+        use wgpu::TextureFormat::*;
+        let formats = [Bgra8Unorm, Rgba16Float, Rgba8UnormSrgb];
+        assert_eq!(surface_format(&formats, false), Some(Rgba8UnormSrgb));
+        assert_eq!(surface_format(&formats[..2], false), Some(Bgra8Unorm));
+        assert_eq!(surface_format(&[], false), None);
+    }
+
+    fn state() -> State {
+        State {
+            fps_balancer: FpsBalancer::new(30.0),
+            status: Status::Running,
+            failure: None,
+            watchdog: Watchdog::new(10.0, 100.0),
+            clock: SystemClock::default(),
+            warning: None,
+            surface_retries: 0,
+        }
+    }
+
+    #[test]
+    fn hung_work_loses_the_device() {
+        let mut state = state();
+        let device_lost = AtomicBool::new(false);
+        state.watchdog.submitted(0.0, 30.0);
+        assert_eq!(state.watch(299.0, &device_lost), Verdict::Waiting);
+        assert_eq!(state.watch(300.0, &device_lost), Verdict::Stalled);
+        assert_eq!(state.warning.take(), Some(Warning::Stalled));
+        // Waiting once more doesn't give up on the device yet
+        assert!(!device_lost.load(Ordering::Relaxed));
+
+        assert_eq!(state.watch(600.0, &device_lost), Verdict::Lost);
+        assert_eq!(state.warning, Some(Warning::Lost));
+        // The flag is what `GpuContext::is_lost` reports, the app rebuilds the context on it
+        assert!(device_lost.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn failed_pipelines_degrade_until_a_retry() {
+        let context = match pollster::block_on(HeadlessContext::new()) {
+            Ok(context) => context,
+            Err(e) => {
+                eprintln!("Skipping the pipeline failure: {e}");
+                return;
+            }
+        };
+        let device = context.device();
+        let (errors, _) = watch_device(device);
+        let bind_group_layout =
+            device.create_bind_group_layout(&ComputeBindings::bind_group_layout_desc());
+        let layout = create_compute_pipeline_layout(device, &bind_group_layout);
+        // A shader that doesn't compile fails the pipeline like a driver bug would
+        let _ = compile_compute_pipeline(device, &layout, "fn main() {".to_owned());
+
+        let mut state = state();
+        state.check_errors(&errors, || 3);
+        assert_eq!(state.status, Status::Degraded { word_count: 3 });
+        assert_eq!(state.status.word_count(7), 3);
+        assert!(state.failure.take().is_some());
+
+        // The same pipeline compiles fine once its source is valid again
+        let source = compute_shader_source(4, workgroup_width(&device.limits())).unwrap();
+        let _ = compile_compute_pipeline(device, &layout, source);
+        state.retry();
+        state.check_errors(&errors, || 3);
+        assert_eq!(state.status, Status::Running);
+        assert_eq!(state.status.word_count(7), 7);
+        assert_eq!(state.failure, None);
+    }
+
+    /// Calibrates the balancer for views of `view` pixels with calibration frames of `frame`
+    /// pixels and returns the limit of presented iterations
+    fn calibrated_limit(
+        context: &HeadlessContext,
+        word_count: usize,
+        view: ScaledDimensions,
+        frame: ScaledDimensions,
+    ) -> u32 {
+        let (device, queue) = (context.device(), context.queue());
+        let workgroup_width = workgroup_width(&device.limits());
+        let bind_group_layout =
+            device.create_bind_group_layout(&ComputeBindings::bind_group_layout_desc());
+        let pipeline =
+            create_compute_pipeline(device, &bind_group_layout, word_count, workgroup_width)
+                .unwrap();
+        let bindings = ComputeBindings::new(
+            device,
+            &bind_group_layout,
+            frame,
+            word_count,
+            workgroup_width,
+        )
+        .write(
+            queue,
+            &ComputeParams::new(
+                frame,
+                &calibration_coords(word_count, (word_count - 1) * 32),
+                &FractalParams::default(),
+                calibration_iteration(IterationParams::default()),
+                FpsBalancer::UNCALIBRATED_LIMIT,
+            )
+            .without_interior_checks(),
+        );
+
+        let workload = Workload {
+            word_count,
+            distance_estimation: false,
+        };
+        let mut balancer = FpsBalancer::new(crate::defaults::TARGET_FPS);
+        balancer.set_pixels(balanced_pixels(view));
+        while !balancer.is_calibrated(workload) {
+            let iterations = balancer
+                .start_calibration_frame(workload, balanced_pixels(view) / balanced_pixels(frame));
+            bindings.write_iterate_reset(queue, iterations);
+            let mut command_encoder = device.create_command_encoder(&Default::default());
+            {
+                let mut cpass = command_encoder.begin_compute_pass(&Default::default());
+                cpass.set_pipeline(&pipeline);
+                cpass.set_bind_group(0, &bindings.bind_group, &[]);
+                cpass.dispatch_workgroups(
+                    frame.aligned_width(ROW_ALIGNMENT) / workgroup_width,
+                    frame.height,
+                    1,
+                );
+            }
+            queue.submit(Some(command_encoder.finish()));
+            device.poll(wgpu::Maintain::Wait);
+            balancer.end_frame(None);
+        }
+        // Same as the present iteration limit of the balancer
+        let (_, ms) = balancer.calibration()[0];
+        3 * (balancer.target_frame_ms() / (ms * balanced_pixels(view))).round() as u32
+    }
+
+    /// Compares the limits of calibrations on the grid with the ones of calibrations on the whole
+    /// view. Run with `cargo test --release --lib grid_calibration -- --ignored --nocapture`
+    #[test]
+    #[ignore = "benchmark"]
+    fn grid_calibration_matches_full_frames() {
+        let context = match pollster::block_on(HeadlessContext::new()) {
+            Ok(context) => context,
+            Err(e) => {
+                eprintln!("Skipping the calibration comparison: {e}");
+                return;
+            }
+        };
+        let limits = context.device().limits();
+        for (width, height) in [(1280, 800), (1920, 1200), (3840, 2400)] {
+            let view = ScaledDimensions { width, height };
+            for word_count in [2, 4] {
+                let sizes = compute::BufferSizes::new(view, word_count, workgroup_width(&limits));
+                // Full frames of big views don't fit into the buffers of every adapter
+                if sizes.intermediate > limits.max_storage_buffer_binding_size as u64 {
+                    println!("{width}x{height}, {word_count} words: too big for the adapter");
+                    continue;
+                }
+                let full = calibrated_limit(&context, word_count, view, view);
+                let grid = calibrated_limit(&context, word_count, view, CALIBRATION_GRID);
+                let difference = (grid as f64 / full as f64 - 1.0) * 100.0;
+                println!("{width}x{height}, {word_count} words: full {full}, grid {grid}, {difference:+.1}%");
+            }
+        }
+    }
+
+    #[test]
+    fn work_done_notifies_once_all_mappings_resolve() {
+        let context = match pollster::block_on(HeadlessContext::new()) {
+            Ok(context) => context,
+            Err(e) => {
+                eprintln!("Skipping the work done notification: {e}");
+                return;
+            }
+        };
+        let device = context.device();
+        let notified = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut work_done = WorkDone::default();
+        let counter = notified.clone();
+        work_done.set_notifier(Arc::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+        assert!(work_done.is_done());
+
+        for _ in 0..2 {
+            work_done.submitted(device);
+            work_done.submitted(device);
+            assert!(work_done.is_pending());
+            assert!(!work_done.is_done());
+
+            // Natively the mappings resolve when the device is polled
+            device.poll(wgpu::Maintain::Wait);
+            assert!(!work_done.is_pending());
+            assert!(work_done.is_done());
+        }
+        // Once per batch of submissions, the buffers are mapped again after being unmapped
+        assert_eq!(notified.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn degraded_status_pins_word_count() {
+        let mut status = Status::Running;
+        assert_eq!(status.word_count(3), 3);
+        assert_eq!(status.word_count(7), 7);
+
+        status = Status::Degraded { word_count: 4 };
+        assert_eq!(status.word_count(3), 4);
+        assert_eq!(status.word_count(7), 4);
+
+        status = Status::Running;
+        assert_eq!(status.word_count(7), 7);
+    }
+
+    #[test]
+    fn finished_views_are_only_drawn() {
+        // Color changes don't queue parameter updates, so views at the max depth stay finished
+        assert!(!needs_compute(false, false, 500, 500, false));
+        assert!(!needs_compute(false, false, 800, 500, false));
+        assert!(needs_compute(false, false, 499, 500, false));
+        assert!(needs_compute(true, false, 500, 500, false));
+        // Restored results are dispatched once to calculate their escape fractions again
+        assert!(needs_compute(false, true, 500, 500, false));
+        // Nothing is left to iterate in converged views, until they change
+        assert!(!needs_compute(false, false, 499, 500, true));
+        assert!(needs_compute(true, false, 499, 500, true));
+    }
+
+    #[test]
+    fn lost_surfaces_are_resized_then_rebuilt() {
+        let stale = Dimensions {
+            width: 800,
+            height: 600,
+        };
+        let window = Dimensions {
+            width: 1024,
+            height: 768,
+        };
+        // Reconfiguring with the stale dimensions keeps failing until the surface follows the
+        // window
+        for error in [wgpu::SurfaceError::Lost, wgpu::SurfaceError::Outdated] {
+            assert_eq!(
+                surface_recovery(&error, stale, window),
+                SurfaceRecovery::Resize(window)
+            );
+        }
+        assert_eq!(
+            surface_recovery(&wgpu::SurfaceError::Outdated, window, window),
+            SurfaceRecovery::Redraw
+        );
+        assert_eq!(
+            surface_recovery(&wgpu::SurfaceError::Lost, window, window),
+            SurfaceRecovery::Rebuild
+        );
+        assert_eq!(
+            surface_recovery(&wgpu::SurfaceError::Timeout, stale, window),
+            SurfaceRecovery::Redraw
+        );
+        assert_eq!(
+            surface_recovery(&wgpu::SurfaceError::OutOfMemory, window, window),
+            SurfaceRecovery::Exit
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! Perturbation of deep views. A single reference orbit is iterated with the full precision on the
+//! CPU, and the pixels only iterate their small difference to it in f32. Pixels whose difference
+//! loses its precision are handed over to the exact shader, which calculates them again
+
+use bytemuck::{Pod, Zeroable};
+use std::borrow::Cow;
+
+use crate::float::WideFloat;
+use crate::fractal::{FractalKind, FractalParams, IterationParams, ReferenceOrbit};
+use crate::primitives::{Coordinates, ScaledDimensions};
+
+use super::compute::ComputeBindings;
+use super::{with_workgroup_width, ROW_ALIGNMENT};
+
+/// Views of at least this many words are perturbed, shallower ones iterate fast enough exactly
+pub const PERTURBATION_WORD_COUNT: usize = 6;
+
+/// Pixels handed over to the exact shader advance this many times faster than the depth, so that
+/// they catch up with it
+pub const REPAIR_SPEEDUP: u32 = 4;
+
+/// Returns the iterations that the pixels handed over to the exact shader advance by in a frame
+/// that deepens the view by `iterations` to `depth`, zero for views that aren't `perturbed`. The
+/// frame that reaches the max depth lets them catch up all the way, no frames follow it
+pub fn repair_iterations(perturbed: bool, iterations: u32, depth: u32, max_depth: u32) -> u32 {
+    if !perturbed {
+        0
+    } else if depth >= max_depth {
+        u32::MAX
+    } else {
+        iterations.saturating_mul(REPAIR_SPEEDUP).max(1)
+    }
+}
+
+/// Points the orbit buffer has room for at first, it grows along with the depth
+const INITIAL_ORBIT_CAPACITY: usize = 1024;
+
+/// Size of the delta of every pixel in the shader
+const DELTA_SIZE: u32 = 16;
+
+/// Returns true if a view of `word_count` words is calculated by perturbation. Only the Mandelbrot
+/// set of z^2 has its delta formula here, and the deltas don't track the derivative for distance
+/// estimation
+pub fn is_perturbed(
+    word_count: usize,
+    fractal: &FractalParams,
+    iteration: IterationParams,
+) -> bool {
+    word_count >= PERTURBATION_WORD_COUNT
+        && fractal.kind == FractalKind::Mandelbrot
+        && fractal.power == 2
+        && fractal.julia.is_none()
+        && !fractal.inverted
+        && !iteration.distance_estimation
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct PerturbationParams {
+    depth_limit: u32,
+    reset: u32,
+    size: ScaledDimensions,
+    rotation: [f32; 2],
+    reference: [f32; 2],
+    step: f32,
+    step_exponent: i32,
+    orbit_length: u32,
+    bailout: f32,
+    row_offset: u32,
+    _padding: u32,
+}
+
+/// Returns the step as a value in 1..2 and its power of two, so that steps below the range of f32
+/// keep their precision
+fn scaled_step(step: &WideFloat) -> (f32, i32) {
+    let Some(exponent) = step.ilog2() else {
+        return (0.0, 0);
+    };
+    let mut scaled = step.clone();
+    if exponent > 0 {
+        scaled.logical_shr(exponent as usize);
+    } else {
+        scaled <<= -exponent as usize;
+    }
+    (scaled.as_f64_round() as f32, exponent)
+}
+
+/// Pipeline of the perturbation shader, shared by the views
+pub struct PerturbationPass {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+    /// Longest orbit that fits into a binding of the device
+    max_orbit_length: usize,
+}
+
+impl PerturbationPass {
+    pub fn new(device: &wgpu::Device, workgroup_width: u32) -> Self {
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Perturbation BindGroupLayout"),
+            entries: &[
+                storage(0, true),
+                storage(1, true),
+                storage(2, false),
+                storage(3, false),
+                storage(4, true),
+                storage(5, false),
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Perturbation Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(with_workgroup_width(
+                include_str!("perturbation.wgsl"),
+                workgroup_width,
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Perturbation PipelineLayout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Perturbation Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let limits = device.limits();
+        let max_binding = limits
+            .max_storage_buffer_binding_size
+            .min(limits.max_buffer_size.min(u32::MAX as u64) as u32);
+        Self {
+            bind_group_layout,
+            pipeline,
+            max_orbit_length: max_binding as usize / 8,
+        }
+    }
+}
+
+/// Reference orbit of a view and the deltas of its pixels to it
+pub struct Perturbation {
+    params: PerturbationParams,
+    orbit: ReferenceOrbit,
+    /// Squared escape radius the orbit is iterated to
+    bailout: f64,
+    /// Points of the orbit that are already written to the buffer
+    uploaded: usize,
+
+    params_buffer: wgpu::Buffer,
+    orbit_buffer: wgpu::Buffer,
+    delta_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Perturbation {
+    /// Creates the deltas of the pixels of `bindings`, calculated around the pixel `reference`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        pass: &PerturbationPass,
+        bindings: &ComputeBindings,
+        dimensions: ScaledDimensions,
+        coords: &Coordinates,
+        iteration: IterationParams,
+        reference: (u32, u32),
+    ) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Perturbation Params"),
+            size: std::mem::size_of::<PerturbationParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let delta_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Perturbation Deltas"),
+            size: (DELTA_SIZE * dimensions.aligned_width(ROW_ALIGNMENT) * dimensions.height)
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let orbit_buffer = create_orbit_buffer(device, INITIAL_ORBIT_CAPACITY);
+        let bind_group = create_bind_group(
+            device,
+            pass,
+            bindings,
+            &params_buffer,
+            &orbit_buffer,
+            &delta_buffer,
+        );
+
+        let (x, y) = coords.complex_at_pixel(reference.0, reference.1);
+        let (cos, sin) = coords.rotation_cos_sin();
+        let (step, step_exponent) = scaled_step(&coords.step);
+        // The reference is perturbed with z^2, whose radius isn't limited
+        let radius = iteration.escape_radius_for(2);
+        let bailout = radius * radius;
+        Self {
+            params: PerturbationParams {
+                depth_limit: 0,
+                reset: 1,
+                size: ScaledDimensions {
+                    width: dimensions.aligned_width(ROW_ALIGNMENT),
+                    height: dimensions.height,
+                },
+                rotation: [cos, sin],
+                reference: [reference.0 as f32, reference.1 as f32],
+                step,
+                step_exponent,
+                orbit_length: 0,
+                bailout,
+                row_offset: 0,
+                _padding: 0,
+            },
+            orbit: ReferenceOrbit::new(x, y),
+            bailout: bailout.into(),
+            uploaded: 0,
+            params_buffer,
+            orbit_buffer,
+            delta_buffer,
+            bind_group,
+        }
+    }
+
+    /// Extends the orbit to `depth_limit` and writes the parameters of the next dispatch, which
+    /// starts the deltas over with `reset`
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pass: &PerturbationPass,
+        bindings: &ComputeBindings,
+        depth_limit: u32,
+        reset: bool,
+    ) {
+        let length = (depth_limit as usize).min(pass.max_orbit_length);
+        self.orbit.extend_to(length, self.bailout);
+        let points = self.orbit.points();
+
+        let capacity = self.orbit_buffer.size() as usize / 8;
+        if points.len() > capacity {
+            let capacity = points.len().next_power_of_two().min(pass.max_orbit_length);
+            self.orbit_buffer = create_orbit_buffer(device, capacity);
+            self.bind_group = create_bind_group(
+                device,
+                pass,
+                bindings,
+                &self.params_buffer,
+                &self.orbit_buffer,
+                &self.delta_buffer,
+            );
+            self.uploaded = 0;
+        }
+        if points.len() > self.uploaded {
+            queue.write_buffer(
+                &self.orbit_buffer,
+                8 * self.uploaded as wgpu::BufferAddress,
+                bytemuck::cast_slice(&points[self.uploaded..]),
+            );
+            self.uploaded = points.len();
+        }
+
+        self.params.depth_limit = depth_limit;
+        self.params.reset = reset as u32;
+        self.params.orbit_length = points.len() as u32;
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&self.params));
+    }
+
+    /// Binds the compute buffers of `bindings` again after they have been replaced
+    pub fn rebind(
+        &mut self,
+        device: &wgpu::Device,
+        pass: &PerturbationPass,
+        bindings: &ComputeBindings,
+    ) {
+        self.bind_group = create_bind_group(
+            device,
+            pass,
+            bindings,
+            &self.params_buffer,
+            &self.orbit_buffer,
+            &self.delta_buffer,
+        );
+    }
+
+    /// Writes the first row of the band that the next dispatch covers
+    pub fn write_row_offset(&mut self, queue: &wgpu::Queue, row: u32) {
+        self.params.row_offset = row;
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&self.params));
+    }
+
+    /// Records the dispatch of the deltas, before the exact shader that repairs the glitches
+    pub fn encode<'p>(
+        &'p self,
+        cpass: &mut wgpu::ComputePass<'p>,
+        pass: &'p PerturbationPass,
+        workgroups: (u32, u32),
+    ) {
+        cpass.set_pipeline(&pass.pipeline);
+        cpass.set_bind_group(0, &self.bind_group, &[]);
+        cpass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+    }
+}
+
+fn create_orbit_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Perturbation Orbit"),
+        size: 8 * capacity as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    pass: &PerturbationPass,
+    bindings: &ComputeBindings,
+    params_buffer: &wgpu::Buffer,
+    orbit_buffer: &wgpu::Buffer,
+    delta_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Perturbation BindGroup"),
+        layout: &pass.bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: orbit_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: bindings.result_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: delta_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: bindings.tiles_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: bindings.escape_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_keep_their_precision_below_f32() {
+        assert_eq!(
+            scaled_step(&WideFloat::from_f64(0.75, 3).unwrap()),
+            (1.5, -1)
+        );
+        assert_eq!(scaled_step(&WideFloat::from_f64(4.0, 3).unwrap()), (1.0, 2));
+        assert_eq!(scaled_step(&WideFloat::zero(3)), (0.0, 0));
+
+        // 1.25 * 2^-200 is far below the smallest f32
+        let tiny = WideFloat::from_f64(1.25, 8).unwrap() >> 200;
+        assert_eq!(scaled_step(&tiny), (1.25, -200));
+    }
+
+    #[test]
+    fn handed_over_pixels_catch_up() {
+        assert_eq!(repair_iterations(false, 100, 1000, 5000), 0);
+        assert_eq!(
+            repair_iterations(true, 100, 1000, 5000),
+            100 * REPAIR_SPEEDUP
+        );
+        assert_eq!(repair_iterations(true, 100, 5000, 5000), u32::MAX);
+        assert_eq!(
+            repair_iterations(true, u32::MAX / 2, 1000, u32::MAX),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn only_the_mandelbrot_set_of_squares_is_perturbed() {
+        let iteration = IterationParams::default();
+        let fractal = FractalParams::default();
+        assert!(is_perturbed(PERTURBATION_WORD_COUNT, &fractal, iteration));
+        assert!(!is_perturbed(
+            PERTURBATION_WORD_COUNT - 1,
+            &fractal,
+            iteration
+        ));
+        assert!(!is_perturbed(
+            PERTURBATION_WORD_COUNT,
+            &FractalParams {
+                power: 3,
+                ..Default::default()
+            },
+            iteration
+        ));
+        assert!(!is_perturbed(
+            PERTURBATION_WORD_COUNT,
+            &FractalParams {
+                kind: FractalKind::Celtic,
+                ..Default::default()
+            },
+            iteration
+        ));
+        assert!(!is_perturbed(
+            PERTURBATION_WORD_COUNT,
+            &fractal,
+            IterationParams {
+                distance_estimation: true,
+                ..Default::default()
+            }
+        ));
+    }
+
+    #[test]
+    fn params_match_the_shader_struct() {
+        let source = include_str!("perturbation.wgsl");
+        let start = source.find("struct Parameters {").unwrap();
+        let end = start + source[start..].find('}').unwrap();
+        let fields: Vec<_> = source[start..end]
+            .lines()
+            .skip(1)
+            .filter(|line| !line.trim().starts_with("//"))
+            .filter_map(|line| line.trim().split_once(": "))
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "depth_limit",
+                "reset",
+                "size",
+                "rotation",
+                "reference",
+                "step",
+                "step_exponent",
+                "orbit_length",
+                "bailout",
+                "row_offset"
+            ]
+        );
+        assert_eq!(std::mem::size_of::<PerturbationParams>(), 56);
+        assert!(source.contains(&format!("{:#x}u", super::super::compute::GLITCHED)));
+    }
+}
@@ -0,0 +1,153 @@
+struct Parameters {
+    depth_limit: u32,
+    reset: u32,
+    // Aligned width and height of the result buffer
+    size: vec2<u32>,
+    // Cosine and sine of the view rotation
+    rotation: vec2<f32>,
+    // Pixel the reference orbit starts at, the deltas of the others are measured from it
+    reference: vec2<f32>,
+    // Pixel step as a value in 1..2 and its power of two
+    step: f32,
+    step_exponent: i32,
+    // Amount of points in the orbit, pixels that need more of them are handed over
+    orbit_length: u32,
+    // Squared escape radius
+    bailout: f32,
+    // First row of the band of rows the dispatch covers
+    row_offset: u32,
+}
+
+@group(0)
+@binding(0)
+var<storage, read> params: Parameters;
+
+// z of the reference after every iteration, starting with the reference point itself
+@group(0)
+@binding(1)
+var<storage, read> orbit: array<vec2<f32>>;
+
+@group(0)
+@binding(2)
+var<storage, read_write> iterations: array<u32>;
+
+// Difference of z to the reference orbit, scaled by a power of two like the derivative of the
+// exact shader, so that it has the range to start at a step far below f32
+struct Delta {
+    value: vec2<f32>,
+    exponent: i32,
+    // Non-zero once the pixel is calculated by the exact shader
+    handed_over: u32,
+}
+
+@group(0)
+@binding(3)
+var<storage, read_write> deltas: array<Delta>;
+
+// Tiles of the exact shader, which runs after this one and finishes them for both
+struct Tiles {
+    active_count: u32,
+    settled_count: u32,
+    skipped_count: u32,
+    done: array<u32>,
+}
+
+@group(0)
+@binding(4)
+var<storage, read> tiles: Tiles;
+
+@group(0)
+@binding(5)
+var<storage, read_write> escapes: array<f32>;
+
+// Count of the pixels handed over to the exact shader, which starts them over
+const glitched: u32 = 0xfffffffeu;
+
+// z far below the reference has lost the precision of the delta that cancels the reference out
+const glitch_tolerance: f32 = 1.0e-6;
+
+// Moves the exponent of the larger part of `num` to the scale of the delta
+fn normalized(num: Delta) -> Delta {
+    let larger = max(abs(num.value.x), abs(num.value.y));
+    if larger == 0.0 || larger > 3.0e38 {
+        return num;
+    }
+    let exponent = i32((bitcast<u32>(larger) >> 23u) & 0xffu) - 127;
+    return Delta(ldexp(num.value, vec2<i32>(-exponent)), num.exponent + exponent, num.handed_over);
+}
+
+fn complex_mul(left: vec2<f32>, right: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(left.x * right.x - left.y * right.y, left.x * right.y + left.y * right.x);
+}
+
+// Returns the fraction of the escaped iteration like the exact shader does for z^2
+fn escape_fraction(norm: f32) -> f32 {
+    return clamp(1.0 - log2(log(norm) / log(params.bailout)), 0.0, 1.0);
+}
+
+@compute
+@workgroup_size(64)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let pixel = vec2<u32>(global_id.x, global_id.y + params.row_offset);
+    let tile = pixel.y * num_workgroups.x + workgroup_id.x;
+    if tiles.done[tile] != 0u && params.reset == 0u {
+        return;
+    }
+
+    let index = pixel.y * params.size.x + pixel.x;
+
+    // Pixels are offset along the rotated basis vectors, like in the exact shader
+    let offset = vec2<f32>(pixel) - params.reference;
+    let cos_sin = params.rotation;
+    let rotated = vec2<f32>(
+        offset.x * cos_sin.x - offset.y * cos_sin.y,
+        offset.x * cos_sin.y + offset.y * cos_sin.x,
+    );
+    let pixel_delta = normalized(Delta(rotated * params.step, params.step_exponent, 0u));
+
+    var delta: Delta;
+    var i: u32;
+    if params.reset != 0u {
+        // z starts at the pixel, so the delta starts at its offset from the reference
+        delta = pixel_delta;
+        i = 0u;
+    } else {
+        delta = deltas[index];
+        if delta.handed_over != 0u {
+            return;
+        }
+        i = iterations[index];
+    }
+
+    while i < params.depth_limit {
+        if i >= params.orbit_length {
+            delta.handed_over = 1u;
+            break;
+        }
+        let reference = orbit[i];
+        let z = reference + ldexp(delta.value, vec2<i32>(delta.exponent));
+        let norm = dot(z, z);
+        if norm >= params.bailout {
+            escapes[index] = escape_fraction(norm);
+            break;
+        }
+        if norm < glitch_tolerance * dot(reference, reference) {
+            delta.handed_over = 1u;
+            break;
+        }
+
+        // d = 2 * Z * d + d^2 + dc, in the scale of d
+        let value = 2.0 * complex_mul(reference, delta.value)
+            + ldexp(complex_mul(delta.value, delta.value), vec2<i32>(delta.exponent))
+            + ldexp(pixel_delta.value, vec2<i32>(pixel_delta.exponent - delta.exponent));
+        delta = normalized(Delta(value, delta.exponent, 0u));
+        i++;
+    }
+
+    deltas[index] = delta;
+    iterations[index] = select(i, glitched, delta.handed_over != 0u);
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Compute pipelines of the word counts used so far, shared by the views. Switching back to a
+/// word count doesn't compile its shader again
+pub struct PipelineCache<P = wgpu::ComputePipeline> {
+    pipelines: HashMap<usize, Arc<P>>,
+}
+
+impl<P> Default for PipelineCache<P> {
+    fn default() -> Self {
+        Self {
+            pipelines: HashMap::new(),
+        }
+    }
+}
+
+impl<P> PipelineCache<P> {
+    /// Returns the pipeline of `word_count` words if it's cached
+    pub fn get(&self, word_count: usize) -> Option<Arc<P>> {
+        self.pipelines.get(&word_count).cloned()
+    }
+
+    /// Caches the pipeline of `word_count` words, compiled elsewhere
+    pub fn insert(&mut self, word_count: usize, pipeline: P) -> Arc<P> {
+        let pipeline = Arc::new(pipeline);
+        self.pipelines.insert(word_count, pipeline.clone());
+        pipeline
+    }
+
+    /// Returns the pipeline of `word_count` words, created by `create` unless it's cached. Failed
+    /// pipelines aren't cached
+    pub fn get_or_try_insert<E>(
+        &mut self,
+        word_count: usize,
+        create: impl FnOnce() -> Result<P, E>,
+    ) -> Result<Arc<P>, E> {
+        if let Some(pipeline) = self.pipelines.get(&word_count) {
+            return Ok(pipeline.clone());
+        }
+        let pipeline = Arc::new(create()?);
+        self.pipelines.insert(word_count, pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Drops the pipelines of a device that is no longer used
+    pub fn clear(&mut self) {
+        self.pipelines.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_counts_reuse_their_pipelines() {
+        let mut cache = PipelineCache::default();
+        let mut compiled = Vec::new();
+        let mut pipeline = |word_count: usize| {
+            cache
+                .get_or_try_insert(word_count, || {
+                    compiled.push(word_count);
+                    Ok::<_, ()>(format!("{word_count} words"))
+                })
+                .unwrap()
+        };
+        let two = pipeline(2);
+        let eight = pipeline(8);
+        assert!(Arc::ptr_eq(&pipeline(2), &two));
+        assert!(Arc::ptr_eq(&pipeline(8), &eight));
+        assert_eq!(*two, "2 words");
+        assert_eq!(compiled, [2, 8]);
+
+        assert_eq!(
+            cache.get_or_try_insert(3, || Err("invalid")),
+            Err("invalid")
+        );
+        assert_eq!(
+            *cache
+                .get_or_try_insert(3, || Ok::<_, ()>("3".into()))
+                .unwrap(),
+            "3"
+        );
+        assert!(cache.get(4).is_none());
+        let four = cache.insert(4, "4".into());
+        assert!(Arc::ptr_eq(&cache.get(4).unwrap(), &four));
+        cache.clear();
+        assert!(cache.get(4).is_none());
+        assert_eq!(
+            *cache
+                .get_or_try_insert(2, || Ok::<_, ()>("new".into()))
+                .unwrap(),
+            "new"
+        );
+    }
+}
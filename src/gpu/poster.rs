@@ -0,0 +1,522 @@
+//! Posters of a view at a larger size than the window. They're calculated in tiles that fit the
+//! limits of the device, one step of a tile between the frames of the window, and stitched into a
+//! single image
+
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::fps_balancer::Workload;
+use crate::fractal::{FractalParams, IterationParams};
+use crate::palette::Palette;
+use crate::primitives::{Coordinates, Rect, ScaledDimensions};
+
+use super::capture::{Capture, RgbaImage};
+use super::compute::{BufferSizes, ComputeBindings, ComputeParams, TileCounter};
+use super::histogram::Lut;
+use super::perturbation::{repair_iterations, Perturbation, PerturbationPass};
+use super::render::{ColorParams, FragmentParams, RenderBindings};
+use super::ROW_ALIGNMENT;
+
+/// Longest side of a poster
+pub const MAX_POSTER_SIDE: u32 = 16384;
+
+/// Longest side of a tile. Larger tiles would hold on to more memory and report their progress
+/// rarely
+const MAX_TILE_SIDE: u32 = 2048;
+
+/// Iterations of every step of a tile whose speed isn't calibrated
+const UNCALIBRATED_STEP_ITERATIONS: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum PosterError {
+    #[error("The poster was cancelled")]
+    Cancelled,
+    #[error("The poster was lost with the GPU device")]
+    Lost,
+}
+
+/// Returns the largest tile with numbers of `word_count` words whose buffers and textures fit
+/// `limits`. Its width is aligned to the rows of the compute buffers
+pub(super) fn tile_size(
+    limits: &wgpu::Limits,
+    word_count: usize,
+    workgroup_width: u32,
+) -> ScaledDimensions {
+    let side = limits.max_texture_dimension_2d.min(MAX_TILE_SIDE);
+    let mut size = ScaledDimensions {
+        width: (side / ROW_ALIGNMENT).max(1) * ROW_ALIGNMENT,
+        height: side,
+    };
+    // The longer side is halved until the buffers fit
+    while !BufferSizes::new(size, word_count, workgroup_width).fit(limits) && size.height > 1 {
+        if size.width > size.height && size.width > ROW_ALIGNMENT {
+            size.width = (size.width / 2 / ROW_ALIGNMENT).max(1) * ROW_ALIGNMENT;
+        } else {
+            size.height /= 2;
+        }
+    }
+    size
+}
+
+/// Returns the tiles of at most `tile` dimensions that cover a poster of `size`, row by row
+pub(super) fn tiles(size: ScaledDimensions, tile: ScaledDimensions) -> Vec<Rect> {
+    (0..size.height)
+        .step_by(tile.height as usize)
+        .flat_map(|y| {
+            (0..size.width)
+                .step_by(tile.width as usize)
+                .map(move |x| Rect {
+                    x,
+                    y,
+                    width: tile.width.min(size.width - x),
+                    height: tile.height.min(size.height - y),
+                })
+        })
+        .collect()
+}
+
+/// Returns the iterations of a step of a tile of `pixels` that takes about `budget_ms` at the
+/// calibrated speed of `ms` per iteration of a pixel
+pub(super) fn step_iterations(ms: Option<f64>, pixels: f64, budget_ms: f64) -> u32 {
+    match ms {
+        // Saturates for tiny speeds, at least 1 iteration per step
+        Some(ms) if ms > 0.0 => ((budget_ms / (ms * pixels)) as u32).max(1),
+        _ => UNCALIBRATED_STEP_ITERATIONS,
+    }
+}
+
+/// Copies the rows of the pixels of `tile` into the ones of a poster `width` pixels wide
+fn stitch(pixels: &mut [u8], width: u32, tile: Rect, tile_pixels: &[u8]) {
+    let row = (tile.width * 4) as usize;
+    for (y, tile_row) in tile_pixels.chunks_exact(row).enumerate() {
+        let start = (((tile.y as usize + y) * width as usize) + tile.x as usize) * 4;
+        pixels[start..start + row].copy_from_slice(tile_row);
+    }
+}
+
+/// Poster in progress
+pub(super) struct Poster {
+    /// Index of the view the poster is taken of
+    view: usize,
+    /// Coordinates of the whole poster
+    coords: Coordinates,
+    fractal: FractalParams,
+    iteration: IterationParams,
+    colors: ColorParams,
+    palette: Palette,
+    /// Equalized colors of the view, the tiles are colored alike
+    lut: Option<Lut>,
+    compute_pipeline: Arc<wgpu::ComputePipeline>,
+    /// Draws the tiles into [`super::capture::CAPTURE_FORMAT`]
+    render_pipeline: wgpu::RenderPipeline,
+    workgroup_width: u32,
+    /// Tiles are calculated by perturbation
+    perturbed: bool,
+
+    size: ScaledDimensions,
+    /// Depth the tiles are calculated to, unless they converge before it
+    depth: u32,
+    tiles: Vec<Rect>,
+    /// Index of the tile in progress
+    next: usize,
+    tile: Option<Tile>,
+    /// Pixels of the completed tiles
+    pixels: Vec<u8>,
+    /// Submitted step that hasn't been collected yet
+    in_flight: bool,
+    sender: flume::Sender<Result<RgbaImage, PosterError>>,
+}
+
+/// Calculation of a single tile
+struct Tile {
+    rect: Rect,
+    bindings: ComputeBindings,
+    perturbation: Option<Perturbation>,
+    counter: TileCounter,
+    /// Depth of the submitted step
+    submitted: u32,
+    /// Depth of the completed steps
+    depth: u32,
+    /// Colors of the calculated tile being read back
+    capture: Option<(RenderBindings, Capture, flume::Receiver<RgbaImage>)>,
+}
+
+impl Tile {
+    fn encode_dispatch(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        pass: &PerturbationPass,
+        workgroup_width: u32,
+    ) {
+        let size = self.size();
+        let workgroups = (
+            size.aligned_width(ROW_ALIGNMENT) / workgroup_width,
+            size.height,
+        );
+        self.counter.encode_reset(encoder, &self.bindings);
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Poster Compute"),
+                timestamp_writes: None,
+            });
+            if let Some(perturbation) = &self.perturbation {
+                perturbation.encode(&mut cpass, pass, workgroups);
+            }
+            cpass.set_pipeline(pipeline);
+            cpass.set_bind_group(0, &self.bindings.bind_group, &[]);
+            cpass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+        }
+        self.counter.encode_copy(encoder, &self.bindings);
+    }
+
+    fn size(&self) -> ScaledDimensions {
+        ScaledDimensions {
+            width: self.rect.width,
+            height: self.rect.height,
+        }
+    }
+
+    /// Returns true once the tile has reached `depth` or nothing is left to iterate
+    fn is_calculated(&self, depth: u32) -> bool {
+        self.depth >= depth || self.counter.active() == Some(0)
+    }
+}
+
+impl Poster {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        view: usize,
+        coords: Coordinates,
+        fractal: FractalParams,
+        iteration: IterationParams,
+        colors: ColorParams,
+        palette: Palette,
+        lut: Option<Lut>,
+        compute_pipeline: Arc<wgpu::ComputePipeline>,
+        render_pipeline: wgpu::RenderPipeline,
+        workgroup_width: u32,
+        perturbed: bool,
+        size: ScaledDimensions,
+        depth: u32,
+        tile: ScaledDimensions,
+        sender: flume::Sender<Result<RgbaImage, PosterError>>,
+    ) -> Self {
+        Self {
+            view,
+            coords,
+            fractal,
+            iteration,
+            colors,
+            palette,
+            lut,
+            compute_pipeline,
+            render_pipeline,
+            workgroup_width,
+            perturbed,
+            size,
+            depth,
+            tiles: tiles(size, tile),
+            next: 0,
+            tile: None,
+            pixels: vec![0; (size.width as usize) * (size.height as usize) * 4],
+            in_flight: false,
+            sender,
+        }
+    }
+
+    /// Returns the calculation the steps of the poster are timed for
+    pub fn workload(&self) -> Workload {
+        Workload {
+            word_count: self.coords.size(),
+            distance_estimation: self.iteration.distance_estimation,
+        }
+    }
+
+    /// Returns the tile in progress, counted from 1, and the amount of tiles
+    pub fn progress(&self) -> (usize, usize) {
+        ((self.next + 1).min(self.tiles.len()), self.tiles.len())
+    }
+
+    pub fn in_flight(&self) -> bool {
+        self.in_flight
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.tiles.len()
+    }
+
+    /// Submits the next step of the tile in progress: its iterations, taking about `budget_ms` at
+    /// the calibrated speed of `ms` per iteration of a pixel, or its colors once it's calculated
+    #[allow(clippy::too_many_arguments)]
+    pub fn step(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        compute_layout: &wgpu::BindGroupLayout,
+        render_layout: &wgpu::BindGroupLayout,
+        pass: &PerturbationPass,
+        ms: Option<f64>,
+        budget_ms: f64,
+    ) {
+        debug_assert!(!self.in_flight && !self.is_finished());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Poster Encoder"),
+        });
+        let rect = self.tiles[self.next];
+        let pixels = (rect.width.next_multiple_of(ROW_ALIGNMENT) * rect.height) as f64;
+        let iterations = step_iterations(ms, pixels, budget_ms);
+
+        match &mut self.tile {
+            Some(tile) if tile.is_calculated(self.depth) => {
+                let size = tile.size();
+                let render_bindings = RenderBindings::new(device, render_layout, size).write(
+                    queue,
+                    FragmentParams {
+                        size,
+                        depth: tile.depth,
+                        output_is_srgb: 1,
+                    },
+                );
+                render_bindings.write_colors(queue, self.colors);
+                render_bindings.write_palette(queue, &self.palette);
+                if self.colors.equalize {
+                    render_bindings.write_lut(queue, self.lut.as_ref());
+                }
+                render_bindings.write_distance_shading(queue, self.iteration.distance_estimation);
+                render_bindings.encode_copy(
+                    &mut encoder,
+                    &tile.bindings.result_buffer,
+                    Some(&tile.bindings.escape_buffer),
+                    self.iteration
+                        .distance_estimation
+                        .then_some(&tile.bindings.distance_buffer),
+                );
+                let (sender, receiver) = flume::bounded(1);
+                let mut capture = Capture::new(self.view, sender);
+                capture.encode(
+                    device,
+                    &mut encoder,
+                    &self.render_pipeline,
+                    &render_bindings,
+                    size,
+                );
+                tile.capture = Some((render_bindings, capture, receiver));
+            }
+            Some(tile) => {
+                let depth = self.depth.min(tile.depth.saturating_add(iterations));
+                tile.bindings.write_iterate(queue, depth);
+                if let Some(perturbation) = &mut tile.perturbation {
+                    perturbation.write(device, queue, pass, &tile.bindings, depth, false);
+                    tile.bindings.write_repair(
+                        queue,
+                        repair_iterations(true, depth - tile.depth, depth, self.depth),
+                    );
+                }
+                tile.submitted = depth;
+                tile.encode_dispatch(
+                    &mut encoder,
+                    &self.compute_pipeline,
+                    pass,
+                    self.workgroup_width,
+                );
+            }
+            None => {
+                let (x, y) = self.coords.complex_at_pixel(rect.x, rect.y);
+                let mut coords = Coordinates::from_parts(
+                    x,
+                    y,
+                    self.coords.step.clone(),
+                    self.coords.precision(),
+                )
+                .expect("Tiles have the numbers of the poster");
+                coords
+                    .set_rotation(self.coords.rotation())
+                    .expect("Rotation is valid");
+
+                let size = ScaledDimensions {
+                    width: rect.width,
+                    height: rect.height,
+                };
+                let depth = self.depth.min(iterations);
+                let bindings = ComputeBindings::new(
+                    device,
+                    compute_layout,
+                    size,
+                    coords.size(),
+                    self.workgroup_width,
+                )
+                .write(
+                    queue,
+                    &ComputeParams::new(size, &coords, &self.fractal, self.iteration, depth)
+                        .with_repair(repair_iterations(self.perturbed, depth, depth, self.depth)),
+                );
+                let mut perturbation = self.perturbed.then(|| {
+                    Perturbation::new(
+                        device,
+                        pass,
+                        &bindings,
+                        size,
+                        &coords,
+                        self.iteration,
+                        (size.width / 2, size.height / 2),
+                    )
+                });
+                if let Some(perturbation) = &mut perturbation {
+                    perturbation.write(device, queue, pass, &bindings, depth, true);
+                }
+                let tile = self.tile.insert(Tile {
+                    rect,
+                    bindings,
+                    perturbation,
+                    counter: TileCounter::new(device),
+                    submitted: depth,
+                    depth: 0,
+                    capture: None,
+                });
+                tile.encode_dispatch(
+                    &mut encoder,
+                    &self.compute_pipeline,
+                    pass,
+                    self.workgroup_width,
+                );
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+        if let Some(tile) = &mut self.tile {
+            tile.counter.request_map();
+            if let Some((_, capture, _)) = &mut tile.capture {
+                capture.request_map();
+            }
+        }
+        self.in_flight = true;
+    }
+
+    /// Collects the step that has completed. Returns false while its colors are being mapped.
+    /// Devices have to be polled for mapping to complete
+    pub fn complete(&mut self) -> Result<bool, PosterError> {
+        let tile = self.tile.as_mut().expect("Steps are submitted for a tile");
+        match &mut tile.capture {
+            Some((_, capture, receiver)) => {
+                if !capture.receive() {
+                    return Ok(false);
+                }
+                let image = receiver.try_recv().map_err(|_| PosterError::Lost)?;
+                stitch(&mut self.pixels, self.size.width, tile.rect, &image.pixels);
+                self.tile = None;
+                self.next += 1;
+            }
+            None => {
+                tile.counter.receive();
+                tile.depth = tile.submitted;
+            }
+        }
+        self.in_flight = false;
+        Ok(true)
+    }
+
+    /// Sends the stitched poster to the requester
+    pub fn finish(self) {
+        // The requester may have stopped waiting
+        let _ = self.sender.send(Ok(RgbaImage {
+            width: self.size.width,
+            height: self.size.height,
+            pixels: self.pixels,
+        }));
+    }
+
+    pub fn cancel(self) {
+        let _ = self.sender.send(Err(PosterError::Cancelled));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_cover_the_poster() {
+        let size = ScaledDimensions {
+            width: 5000,
+            height: 3000,
+        };
+        let tile = ScaledDimensions {
+            width: 2048,
+            height: 2048,
+        };
+        let covering = tiles(size, tile);
+        assert_eq!(covering.len(), 6);
+        assert_eq!(
+            covering[2],
+            Rect {
+                x: 4096,
+                y: 0,
+                width: 904,
+                height: 2048
+            }
+        );
+        assert_eq!(
+            covering[5],
+            Rect {
+                x: 4096,
+                y: 2048,
+                width: 904,
+                height: 952
+            }
+        );
+        let covered: u32 = covering.iter().map(|tile| tile.width * tile.height).sum();
+        assert_eq!(covered, size.width * size.height);
+
+        // Every pixel of the poster comes from its tile
+        let size = ScaledDimensions {
+            width: 5,
+            height: 3,
+        };
+        let mut pixels = vec![0; 5 * 3 * 4];
+        for (index, tile) in tiles(
+            size,
+            ScaledDimensions {
+                width: 2,
+                height: 2,
+            },
+        )
+        .into_iter()
+        .enumerate()
+        {
+            let tile_pixels = vec![index as u8 + 1; (tile.width * tile.height * 4) as usize];
+            stitch(&mut pixels, size.width, tile, &tile_pixels);
+        }
+        let firsts: Vec<_> = pixels.chunks_exact(4).map(|pixel| pixel[0]).collect();
+        assert_eq!(firsts, [1, 1, 2, 2, 3, 1, 1, 2, 2, 3, 4, 4, 5, 5, 6]);
+    }
+
+    #[test]
+    fn tiles_fit_the_limits() {
+        let limits = wgpu::Limits::default();
+        let tile = tile_size(&limits, 2, 64);
+        assert_eq!(tile.width % ROW_ALIGNMENT, 0);
+        assert!(tile.width <= MAX_TILE_SIDE && tile.height <= MAX_TILE_SIDE);
+        assert!(BufferSizes::new(tile, 2, 64).fit(&limits));
+        // Wider numbers take smaller tiles
+        let wide = tile_size(&limits, 16, 64);
+        assert!(wide.width * wide.height < tile.width * tile.height);
+        assert!(BufferSizes::new(wide, 16, 64).fit(&limits));
+        let small = wgpu::Limits {
+            max_texture_dimension_2d: 1000,
+            ..limits
+        };
+        assert_eq!(tile_size(&small, 2, 64).width, 960);
+    }
+
+    #[test]
+    fn steps_fit_the_budget() {
+        assert_eq!(step_iterations(Some(1e-6), 1e4, 50.0), 5000);
+        // Steps always iterate
+        assert_eq!(step_iterations(Some(1.0), 1e6, 50.0), 1);
+        assert_eq!(
+            step_iterations(None, 1e6, 50.0),
+            UNCALIBRATED_STEP_ITERATIONS
+        );
+    }
+}
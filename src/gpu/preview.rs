@@ -0,0 +1,283 @@
+use bytemuck::{Pod, Zeroable};
+use std::borrow::Cow;
+
+use crate::palette::Palette;
+use crate::primitives::{Rect, ScaledDimensions};
+
+use super::render::{ColorParams, FragmentParams, RenderBindings};
+use super::{with_workgroup_width, ROW_ALIGNMENT};
+
+/// Size of the preview inset in physical pixels
+const SIZE: ScaledDimensions = ScaledDimensions {
+    width: 200,
+    height: 150,
+};
+
+/// Distance between the inset and the window corner in physical pixels
+const MARGIN: u32 = 10;
+
+/// Plane extent across the inset height, fits the whole Julia set for points of the Mandelbrot set
+const SPAN: f32 = 3.0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct PreviewParams {
+    size: ScaledDimensions,
+    depth_limit: u32,
+    _padding: u32,
+    c: [f32; 2],
+    origin: [f32; 2],
+    step: f32,
+    _end_padding: u32,
+}
+
+/// Julia set preview for the point under the cursor. Calculated in f32 at a low depth,
+/// independently of the precision of the main views, so that it keeps up with the cursor
+pub struct JuliaPreview {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    result_buffer: wgpu::Buffer,
+    render_bindings: RenderBindings,
+    depth: u32,
+    workgroup_width: u32,
+    /// Point the preview is shown for, hidden if missing
+    point: Option<[f32; 2]>,
+    /// The point has changed since the last calculation
+    outdated: bool,
+}
+
+impl JuliaPreview {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        render_bind_group_layout: &wgpu::BindGroupLayout,
+        workgroup_width: u32,
+        depth: u32,
+        output_is_srgb: bool,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Preview BindGroupLayout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Preview Params"),
+            size: std::mem::size_of::<PreviewParams>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let result_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Preview Result"),
+            size: (4 * SIZE.aligned_width(ROW_ALIGNMENT) * SIZE.height) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Preview BindGroup"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: result_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Preview Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(with_workgroup_width(
+                include_str!("preview.wgsl"),
+                workgroup_width,
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Preview PipelineLayout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Preview Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let render_bindings = RenderBindings::new(device, render_bind_group_layout, SIZE).write(
+            queue,
+            FragmentParams {
+                size: SIZE,
+                depth,
+                output_is_srgb: output_is_srgb.into(),
+            },
+        );
+        render_bindings.write_colors(queue, ColorParams::default());
+        render_bindings.write_palette(queue, &Palette::default());
+
+        Self {
+            pipeline,
+            bind_group,
+            params_buffer,
+            result_buffer,
+            render_bindings,
+            workgroup_width,
+            depth,
+            point: None,
+            outdated: false,
+        }
+    }
+
+    /// Shows the preview for the plane point `c`, or hides it
+    pub fn set_point(&mut self, c: Option<[f32; 2]>) {
+        if c != self.point {
+            self.point = c;
+            self.outdated = c.is_some();
+        }
+    }
+
+    /// Draws for a target that encodes the colors for the display itself or not
+    pub fn set_output(&self, queue: &wgpu::Queue, output_is_srgb: bool) {
+        self.render_bindings.write(
+            queue,
+            FragmentParams {
+                size: SIZE,
+                depth: self.depth,
+                output_is_srgb: output_is_srgb.into(),
+            },
+        );
+    }
+
+    /// Returns the plane point the preview is shown for
+    pub fn point(&self) -> Option<[f32; 2]> {
+        self.point
+    }
+
+    /// Colors the preview like the views, in bands as it has no escape fractions
+    pub fn write_colors(&self, queue: &wgpu::Queue, palette: &Palette, gamma: f32) {
+        self.render_bindings.write_colors(
+            queue,
+            ColorParams {
+                gamma,
+                ..Default::default()
+            },
+        );
+        self.render_bindings.write_palette(queue, palette);
+    }
+
+    /// Records the calculation if the point has changed since the last one
+    pub fn encode(&mut self, queue: &wgpu::Queue, command_encoder: &mut wgpu::CommandEncoder) {
+        let Some(c) = self.point.filter(|_| self.outdated) else {
+            return;
+        };
+        self.outdated = false;
+
+        let step = SPAN / SIZE.height as f32;
+        let params = PreviewParams {
+            size: ScaledDimensions {
+                width: SIZE.aligned_width(ROW_ALIGNMENT),
+                height: SIZE.height,
+            },
+            depth_limit: self.depth,
+            _padding: 0,
+            c,
+            origin: [
+                -(SIZE.width as f32) / 2.0 * step,
+                -(SIZE.height as f32) / 2.0 * step,
+            ],
+            step,
+            _end_padding: 0,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        command_encoder.push_debug_group("Preview");
+        {
+            let mut cpass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Preview"),
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &self.bind_group, &[]);
+            cpass.dispatch_workgroups(
+                SIZE.aligned_width(ROW_ALIGNMENT) / self.workgroup_width,
+                SIZE.height,
+                1,
+            );
+        }
+        command_encoder.pop_debug_group();
+
+        // The preview is always drawn in bands
+        self.render_bindings
+            .encode_copy(command_encoder, &self.result_buffer, None, None);
+    }
+
+    /// Returns the bindings to draw the preview with and where to draw it, if it's shown and fits
+    /// into the surface
+    pub fn draw_target(
+        &self,
+        surface_width: u32,
+        surface_height: u32,
+    ) -> Option<(&RenderBindings, Rect)> {
+        self.point?;
+        Some((
+            &self.render_bindings,
+            inset_rect(surface_width, surface_height)?,
+        ))
+    }
+}
+
+/// Returns the region of the inset in the bottom right corner of the surface
+fn inset_rect(surface_width: u32, surface_height: u32) -> Option<Rect> {
+    Some(Rect {
+        x: surface_width.checked_sub(SIZE.width + MARGIN)?,
+        y: surface_height.checked_sub(SIZE.height + MARGIN)?,
+        width: SIZE.width,
+        height: SIZE.height,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inset_is_placed_in_the_corner() {
+        let rect = inset_rect(800, 500).unwrap();
+        assert_eq!(rect.x + rect.width + MARGIN, 800);
+        assert_eq!(rect.y + rect.height + MARGIN, 500);
+        assert!(inset_rect(SIZE.width + MARGIN - 1, 500).is_none());
+        assert!(inset_rect(800, SIZE.height).is_none());
+    }
+
+    #[test]
+    fn params_match_shader_layout() {
+        // vec2<u32>, u32, then vec2<f32> aligned to 8 bytes, vec2<f32>, f32 and struct padding
+        assert_eq!(std::mem::size_of::<PreviewParams>(), 40);
+    }
+}
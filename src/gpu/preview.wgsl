@@ -0,0 +1,35 @@
+struct Parameters {
+    size: vec2<u32>,
+    depth_limit: u32,
+    c: vec2<f32>,
+    origin: vec2<f32>,
+    step: f32,
+}
+
+@group(0)
+@binding(0)
+var<storage, read> params: Parameters;
+
+@group(0)
+@binding(1)
+var<storage, read_write> iterations: array<u32>;
+
+// Julia set of z = z^2 + c in f32, only meant for a low depth preview
+@compute
+@workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= params.size.x || global_id.y >= params.size.y {
+        return;
+    }
+
+    var z = params.origin + vec2<f32>(global_id.xy) * params.step;
+    var i = 0u;
+    for (; i < params.depth_limit; i++) {
+        if dot(z, z) > 4.0 {
+            break;
+        }
+        z = vec2<f32>(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + params.c;
+    }
+
+    iterations[global_id.y * params.size.x + global_id.x] = i;
+}
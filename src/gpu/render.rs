@@ -1,17 +1,113 @@
-use crate::primitives::ScaledDimensions;
+use crate::palette::{Palette, TEXELS};
+use crate::primitives::{PixelTransform, ScaledDimensions};
 use bytemuck::{Pod, Zeroable};
+use std::ops::RangeInclusive;
+
+use super::histogram::{Lut, BUCKETS};
+use super::{grow_capacity, ROW_ALIGNMENT};
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 pub struct FragmentParams {
     pub size: ScaledDimensions,
     pub depth: u32,
+    /// Non-zero if the target encodes the colors for the display itself, see [`encodes_srgb`]
+    pub output_is_srgb: u32,
+}
+
+/// Tells if the colors drawn to `format` are taken as linear, so that the target encodes them
+/// for the display. That's the case for sRGB formats and for float ones, which are extended
+/// linear sRGB. Colors drawn to other formats are encoded by the shader
+pub fn encodes_srgb(format: wgpu::TextureFormat) -> bool {
+    format.is_srgb() || format == wgpu::TextureFormat::Rgba16Float
+}
+
+/// Display gamma of sRGB, which colors are drawn for without correction
+pub const DEFAULT_GAMMA: f32 = 2.2;
+
+pub const GAMMA_RANGE: RangeInclusive<f32> = 1.0..=3.0;
+
+/// How iteration counts are mapped to positions along the palette, chosen in the control panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorParams {
+    /// Blend the colors of neighboring counts by how far past the escape radius z got, instead
+    /// of coloring bands of equal counts
+    pub smooth: bool,
+    /// Spread the colors by the share of pixels with lower counts, recomputed from a histogram
+    pub equalize: bool,
+    /// Gamma of the display the colors are corrected for, within [`GAMMA_RANGE`]
+    pub gamma: f32,
+}
+
+impl Default for ColorParams {
+    fn default() -> Self {
+        Self {
+            smooth: false,
+            equalize: false,
+            gamma: DEFAULT_GAMMA,
+        }
+    }
+}
+
+/// Offset of the smooth colors flag in the parameters buffer, in the padding past the distance
+/// shading flag
+const COLORS_OFFSET: wgpu::BufferAddress = 44;
+
+/// Rectangle drawn over the view, in fractions of the view size. An empty one isn't drawn
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pod, Zeroable)]
+pub struct Selection {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+/// Offset of the selection in the parameters buffer, past the fragment parameters
+const SELECTION_OFFSET: wgpu::BufferAddress = 16;
+
+/// Offset of the count range of the equalized color positions, past the selection. A zero bucket
+/// width draws the counts unequalized
+const LUT_RANGE_OFFSET: wgpu::BufferAddress = 32;
+
+/// Offset of the distance shading flag, past the count range
+const DISTANCE_SHADING_OFFSET: wgpu::BufferAddress = 40;
+
+/// Offset of the reprojection, past the smooth colors flag at the alignment of its matrix
+const REPROJECTION_OFFSET: wgpu::BufferAddress = 48;
+
+/// Offset of the display gamma, past the reprojection
+const GAMMA_OFFSET: wgpu::BufferAddress =
+    REPROJECTION_OFFSET + std::mem::size_of::<Reprojection>() as wgpu::BufferAddress;
+
+/// Previous frame drawn in place of the pixels that haven't escaped yet, while the new one
+/// converges. A zero depth draws no previous frame
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct Reprojection {
+    /// Map from the pixels of the view to the ones of the previous frame
+    pub transform: PixelTransform,
+    pub previous_size: ScaledDimensions,
+    pub previous_depth: u32,
+    /// Depth at which the previous frame has faded out
+    pub fade_depth: u32,
 }
 
 pub struct RenderBindings {
     pub(super) bind_group: wgpu::BindGroup,
     pub(super) params_buffer: wgpu::Buffer,
     pub(super) texture: wgpu::Texture,
+    /// Fractions of an iteration past the escape, for smooth colors
+    pub(super) escape_texture: wgpu::Texture,
+    /// Distances of the escaped pixels to the set, for the boundary lines
+    distance_texture: wgpu::Texture,
+    /// Equalized color positions at the edges of the histogram buckets
+    lut_texture: wgpu::Texture,
+    /// Colors of the palette from its first stop to the last
+    palette_texture: wgpu::Texture,
+    /// Counts and escape fractions of the previous frame, for the reprojection
+    previous_texture: wgpu::Texture,
+    previous_escape_texture: wgpu::Texture,
+    /// Part of the view textures used by the current view, they may have room for more
+    extent: wgpu::Extent3d,
 }
 
 impl RenderBindings {
@@ -39,39 +135,212 @@ impl RenderBindings {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D1,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Uint,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 8,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
             ],
         }
     }
 
-    fn itercount_texture_desc(aligned_extent: wgpu::Extent3d) -> wgpu::TextureDescriptor<'static> {
+    fn texture_desc(
+        label: &'static str,
+        extent: wgpu::Extent3d,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::TextureDescriptor<'static> {
         wgpu::TextureDescriptor {
-            label: Some("ItercountTexture"),
-            size: aligned_extent,
+            label: Some(label),
+            size: extent,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::R32Uint,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format,
+            // Frames are copied to the previous textures to be reprojected
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[],
         }
     }
 
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(
         device: &wgpu::Device,
         layout: &wgpu::BindGroupLayout,
         size: ScaledDimensions,
     ) -> UninitializedRenderBindings {
-        let texture = device.create_texture(&Self::itercount_texture_desc(wgpu::Extent3d {
-            width: size.aligned_width(64),
-            height: size.height,
-            depth_or_array_layers: 1,
-        }));
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let extent = aligned_extent(size);
+        UninitializedRenderBindings(Self::allocate(device, layout, extent, extent))
+    }
+
+    /// Creates the view textures with room for `capacity`, of which `extent` is used
+    fn allocate(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        extent: wgpu::Extent3d,
+        capacity: wgpu::Extent3d,
+    ) -> Self {
+        let texture = device.create_texture(&Self::texture_desc(
+            "ItercountTexture",
+            capacity,
+            wgpu::TextureFormat::R32Uint,
+        ));
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("ItercountTexture View"),
+            ..Default::default()
+        });
+        let escape_texture = device.create_texture(&Self::texture_desc(
+            "EscapeTexture",
+            capacity,
+            wgpu::TextureFormat::R32Float,
+        ));
+        let escape_texture_view = escape_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("EscapeTexture View"),
+            ..Default::default()
+        });
+
+        let distance_texture = device.create_texture(&Self::texture_desc(
+            "DistanceTexture",
+            capacity,
+            wgpu::TextureFormat::R32Float,
+        ));
+        let distance_texture_view = distance_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("DistanceTexture View"),
+            ..Default::default()
+        });
+
+        let previous_texture = device.create_texture(&Self::texture_desc(
+            "PreviousItercountTexture",
+            capacity,
+            wgpu::TextureFormat::R32Uint,
+        ));
+        let previous_texture_view = previous_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("PreviousItercountTexture View"),
+            ..Default::default()
+        });
+        let previous_escape_texture = device.create_texture(&Self::texture_desc(
+            "PreviousEscapeTexture",
+            capacity,
+            wgpu::TextureFormat::R32Float,
+        ));
+        let previous_escape_texture_view =
+            previous_escape_texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("PreviousEscapeTexture View"),
+                ..Default::default()
+            });
+
+        let lut_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("LutTexture"),
+            size: wgpu::Extent3d {
+                width: BUCKETS as u32 + 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D1,
+            format: wgpu::TextureFormat::R32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let lut_texture_view = lut_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("LutTexture View"),
+            ..Default::default()
+        });
+
+        let palette_texture = device.create_texture(&Self::texture_desc(
+            "PaletteTexture",
+            wgpu::Extent3d {
+                width: TEXELS as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        ));
+        let palette_texture_view = palette_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("PaletteTexture View"),
+            ..Default::default()
+        });
+        // Counts past the end of the palette go back through it, so that it never jumps
+        let palette_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Palette Sampler"),
+            address_mode_u: wgpu::AddressMode::MirrorRepeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
 
         let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("FragmentParams"),
-            size: 16,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            // The struct is padded to the alignment of its vectors
+            size: (GAMMA_OFFSET + 4).next_multiple_of(8),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
@@ -86,21 +355,296 @@ impl RenderBindings {
                     binding: 1,
                     resource: wgpu::BindingResource::TextureView(&texture_view),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&escape_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&lut_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&palette_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Sampler(&palette_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&distance_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: wgpu::BindingResource::TextureView(&previous_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: wgpu::BindingResource::TextureView(&previous_escape_texture_view),
+                },
             ],
-            label: None,
+            label: Some("Render BindGroup"),
         });
 
-        UninitializedRenderBindings(Self {
+        Self {
             bind_group,
             params_buffer,
             texture,
-        })
+            escape_texture,
+            distance_texture,
+            lut_texture,
+            palette_texture,
+            previous_texture,
+            previous_escape_texture,
+            extent,
+        }
+    }
+
+    /// Returns true if the view textures have room for `size`
+    pub fn fits(&self, size: ScaledDimensions) -> bool {
+        let extent = aligned_extent(size);
+        let capacity = self.texture.size();
+        extent.width <= capacity.width && extent.height <= capacity.height
+    }
+
+    /// Resizes the bindings for `size`. Textures that have room for it are kept along with their
+    /// contents, otherwise the bindings are replaced by larger ones with headroom for further
+    /// growth. The replaced bindings are returned so that their frame can be kept, the
+    /// parameters, colors and palette of the new ones have to be written again
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        size: ScaledDimensions,
+    ) -> Option<RenderBindings> {
+        let fits = self.fits(size);
+        self.extent = aligned_extent(size);
+        if fits {
+            return None;
+        }
+        let limit = device.limits().max_texture_dimension_2d.into();
+        let capacity = self.texture.size();
+        let grow = |capacity: u32, required: u32| {
+            grow_capacity(capacity.into(), required.into(), limit) as u32
+        };
+        let capacity = wgpu::Extent3d {
+            width: grow(capacity.width, self.extent.width),
+            height: grow(capacity.height, self.extent.height),
+            depth_or_array_layers: 1,
+        };
+        Some(std::mem::replace(
+            self,
+            Self::allocate(device, layout, self.extent, capacity),
+        ))
+    }
+
+    /// Replaces textures larger than the used size by ones that fit it exactly, to give back the
+    /// memory kept since the view was larger. The contents are copied over by `encoder`, except
+    /// for the previous frame, which may be larger and isn't reprojected anymore. Returns true if
+    /// the bindings were replaced
+    pub fn trim(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> bool {
+        if self.texture.size() == self.extent {
+            return false;
+        }
+        let trimmed = Self::allocate(device, layout, self.extent, self.extent);
+        let copies = [
+            (&self.texture, &trimmed.texture, self.extent),
+            (&self.escape_texture, &trimmed.escape_texture, self.extent),
+            (
+                &self.distance_texture,
+                &trimmed.distance_texture,
+                self.extent,
+            ),
+            (
+                &self.lut_texture,
+                &trimmed.lut_texture,
+                self.lut_texture.size(),
+            ),
+            (
+                &self.palette_texture,
+                &trimmed.palette_texture,
+                self.palette_texture.size(),
+            ),
+        ];
+        for (from, to, size) in copies {
+            encoder.copy_texture_to_texture(from.as_image_copy(), to.as_image_copy(), size);
+        }
+        // New buffers are zeroed, which leaves the reprojection out
+        encoder.copy_buffer_to_buffer(
+            &self.params_buffer,
+            0,
+            &trimmed.params_buffer,
+            0,
+            REPROJECTION_OFFSET,
+        );
+        encoder.copy_buffer_to_buffer(
+            &self.params_buffer,
+            GAMMA_OFFSET,
+            &trimmed.params_buffer,
+            GAMMA_OFFSET,
+            4,
+        );
+        *self = trimmed;
+        true
+    }
+
+    /// Copies the results of a calculation to the textures. Results without escape fractions can
+    /// only be drawn without smooth colors, and without distances without the boundary lines
+    pub fn encode_copy(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        iterations: &wgpu::Buffer,
+        escapes: Option<&wgpu::Buffer>,
+        distances: Option<&wgpu::Buffer>,
+    ) {
+        let copies = [(iterations, &self.texture)]
+            .into_iter()
+            .chain(escapes.map(|escapes| (escapes, &self.escape_texture)))
+            .chain(distances.map(|distances| (distances, &self.distance_texture)));
+        for (buffer, texture) in copies {
+            encoder.copy_buffer_to_texture(
+                wgpu::ImageCopyBuffer {
+                    buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        // Both formats have 4 bytes per pixel
+                        bytes_per_row: Some(self.extent.width * 4),
+                        rows_per_image: None,
+                    },
+                },
+                texture.as_image_copy(),
+                self.extent,
+            );
+        }
+    }
+
+    /// Copies the frame drawn by `source`, or the previous frame kept by it, to the previous
+    /// textures. Returns the size of the copy, which is cut to the smaller textures
+    pub fn encode_keep(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &RenderBindings,
+        previous: bool,
+    ) -> ScaledDimensions {
+        let (counts, escapes) = if previous {
+            (&source.previous_texture, &source.previous_escape_texture)
+        } else {
+            (&source.texture, &source.escape_texture)
+        };
+        let size = wgpu::Extent3d {
+            width: source.extent.width.min(self.extent.width),
+            height: source.extent.height.min(self.extent.height),
+            depth_or_array_layers: 1,
+        };
+        for (from, to) in [
+            (counts, &self.previous_texture),
+            (escapes, &self.previous_escape_texture),
+        ] {
+            encoder.copy_texture_to_texture(from.as_image_copy(), to.as_image_copy(), size);
+        }
+        ScaledDimensions {
+            width: size.width,
+            height: size.height,
+        }
     }
 
     pub fn write(&self, queue: &wgpu::Queue, params: FragmentParams) {
         let bytes: [u8; std::mem::size_of::<FragmentParams>()] = bytemuck::cast(params);
         queue.write_buffer(&self.params_buffer, 0, &bytes);
     }
+
+    pub fn write_colors(&self, queue: &wgpu::Queue, colors: ColorParams) {
+        queue.write_buffer(
+            &self.params_buffer,
+            COLORS_OFFSET,
+            bytemuck::bytes_of(&(colors.smooth as u32)),
+        );
+        queue.write_buffer(
+            &self.params_buffer,
+            GAMMA_OFFSET,
+            bytemuck::bytes_of(&colors.gamma),
+        );
+    }
+
+    pub fn write_palette(&self, queue: &wgpu::Queue, palette: &Palette) {
+        queue.write_texture(
+            self.palette_texture.as_image_copy(),
+            bytemuck::cast_slice(&palette.texels()),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: None,
+                rows_per_image: None,
+            },
+            self.palette_texture.size(),
+        );
+    }
+
+    /// Writes the equalized color positions, or draws the counts unequalized without them
+    pub fn write_lut(&self, queue: &wgpu::Queue, lut: Option<&Lut>) {
+        let Some(lut) = lut else {
+            queue.write_buffer(&self.params_buffer, LUT_RANGE_OFFSET, &[0; 8]);
+            return;
+        };
+        queue.write_texture(
+            self.lut_texture.as_image_copy(),
+            bytemuck::cast_slice(&lut.positions),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: None,
+                rows_per_image: None,
+            },
+            self.lut_texture.size(),
+        );
+        queue.write_buffer(
+            &self.params_buffer,
+            LUT_RANGE_OFFSET,
+            bytemuck::cast_slice(&[lut.start, lut.bucket_width]),
+        );
+    }
+
+    /// Darkens the pixels close to the set by their distances, which have to be copied with
+    /// [`RenderBindings::encode_copy`]
+    pub fn write_distance_shading(&self, queue: &wgpu::Queue, enabled: bool) {
+        queue.write_buffer(
+            &self.params_buffer,
+            DISTANCE_SHADING_OFFSET,
+            bytemuck::bytes_of(&(enabled as u32)),
+        );
+    }
+
+    /// Draws the previous frame kept with [`RenderBindings::encode_keep`] where the view hasn't
+    /// converged yet, or only the view without one
+    pub fn write_reprojection(&self, queue: &wgpu::Queue, reprojection: Option<Reprojection>) {
+        queue.write_buffer(
+            &self.params_buffer,
+            REPROJECTION_OFFSET,
+            bytemuck::bytes_of(&reprojection.unwrap_or_else(Reprojection::zeroed)),
+        );
+    }
+
+    pub fn write_selection(&self, queue: &wgpu::Queue, selection: Selection) {
+        queue.write_buffer(
+            &self.params_buffer,
+            SELECTION_OFFSET,
+            bytemuck::bytes_of(&selection),
+        );
+    }
+}
+
+/// Size of the view textures for a view of `size`, with their rows aligned like the compute
+/// buffers they are copied from
+fn aligned_extent(size: ScaledDimensions) -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width: size.aligned_width(ROW_ALIGNMENT),
+        height: size.height,
+        depth_or_array_layers: 1,
+    }
 }
 
 pub struct UninitializedRenderBindings(RenderBindings);
@@ -111,3 +655,64 @@ impl UninitializedRenderBindings {
         self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_match_the_shader_struct() {
+        let source = include_str!("render.wgsl");
+        let start = source.find("struct Parameters {").unwrap();
+        let end = start + source[start..].find('}').unwrap();
+        // Offsets of the fields in the order they are declared, aligned to their sizes
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        for line in source[start..end].lines().skip(1) {
+            let Some((name, ty)) = line.trim().trim_end_matches(',').split_once(": ") else {
+                continue;
+            };
+            let (size, align) = match ty {
+                "u32" | "f32" => (4, 4),
+                "vec2<u32>" | "vec2<f32>" => (8, 8),
+                "mat2x2<f32>" => (16, 8),
+                _ => unreachable!("Unexpected field type {ty}"),
+            };
+            offset = u64::next_multiple_of(offset, align);
+            offsets.push((name, offset));
+            offset += size;
+        }
+        let offset_of = |field: &str| {
+            offsets
+                .iter()
+                .find(|(name, _)| *name == field)
+                .map(|(_, offset)| *offset)
+                .unwrap()
+        };
+
+        assert_eq!(offset_of("smooth_colors"), COLORS_OFFSET);
+        assert_eq!(offset_of("selection_min"), SELECTION_OFFSET);
+        assert_eq!(offset_of("lut_start"), LUT_RANGE_OFFSET);
+        assert_eq!(offset_of("distance_shading"), DISTANCE_SHADING_OFFSET);
+        assert_eq!(offset_of("reprojection"), REPROJECTION_OFFSET);
+        assert_eq!(offset_of("gamma"), GAMMA_OFFSET);
+        assert_eq!(
+            offset_of("output_is_srgb"),
+            std::mem::offset_of!(FragmentParams, output_is_srgb) as u64
+        );
+        assert_eq!(
+            offset.next_multiple_of(8),
+            (GAMMA_OFFSET + 4).next_multiple_of(8)
+        );
+    }
+
+    #[test]
+    fn linear_targets_encode_themselves() {
+        use wgpu::TextureFormat::*;
+        assert!(encodes_srgb(Bgra8UnormSrgb));
+        assert!(encodes_srgb(Rgba8UnormSrgb));
+        assert!(encodes_srgb(Rgba16Float));
+        assert!(!encodes_srgb(Bgra8Unorm));
+        assert!(!encodes_srgb(Rgb10a2Unorm));
+    }
+}
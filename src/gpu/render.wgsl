@@ -16,6 +16,30 @@ fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
 struct Parameters {
     dimensions: vec2<u32>,
     max: u32,
+    // Non-zero if the target encodes the linear colors for the display, otherwise they're
+    // encoded into sRGB here
+    output_is_srgb: u32,
+    // Selection rectangle in fractions of the view size, drawn if it isn't empty
+    selection_min: vec2<f32>,
+    selection_max: vec2<f32>,
+    // Count the equalized color positions start at and the counts in each of their buckets.
+    // Counts are colored unequalized with a zero bucket width
+    lut_start: u32,
+    lut_bucket_width: u32,
+    // Non-zero to darken the escaped pixels close to the set by their distances to it
+    distance_shading: u32,
+    // Non-zero to add the escape fractions to the counts, so that colors blend between them
+    smooth_colors: u32,
+    // Map from the pixels of the view to the ones of the previous frame, which is drawn where the
+    // view hasn't escaped yet until the depth reaches the fade depth. A zero depth of the
+    // previous frame draws the view alone
+    reprojection: mat2x2<f32>,
+    reprojection_offset: vec2<f32>,
+    previous_size: vec2<u32>,
+    previous_depth: u32,
+    fade_depth: u32,
+    // Gamma of the display the colors are corrected for, sRGB is about 2.2
+    gamma: f32,
 }
 
 @group(0)
@@ -26,26 +50,104 @@ var<storage, read> params: Parameters;
 @binding(1)
 var r_color: texture_2d<u32>;
 
-fn colors(i: u32) -> vec3<f32> {
-    let p = 2.0 * radians(180.0) / 3.0;
-    var buffer: u32 = 20;
-    let cutoff = 0.2;
+// Fraction of an iteration each escaped pixel has left until the next count
+@group(0)
+@binding(2)
+var r_escape: texture_2d<f32>;
+
+// Share of escaped pixels with lower counts at the edges of the histogram buckets
+@group(0)
+@binding(3)
+var r_lut: texture_1d<f32>;
+
+// Colors of the palette, sampled by the position along it. The texture is sRGB, so the samples
+// are linear
+@group(0)
+@binding(4)
+var r_palette: texture_2d<f32>;
+
+@group(0)
+@binding(5)
+var s_palette: sampler;
+
+// Estimated distance of each escaped pixel to the set in pixels
+@group(0)
+@binding(6)
+var r_distance: texture_2d<f32>;
+
+// Counts and escape fractions of the previous frame
+@group(0)
+@binding(7)
+var r_previous_color: texture_2d<u32>;
+
+@group(0)
+@binding(8)
+var r_previous_escape: texture_2d<f32>;
+
+// Count of the pixels found inside the set
+const settled: u32 = 0xffffffffu;
+
+// Distance in pixels up to which pixels are darkened, the width of the boundary lines at any zoom
+const line_width: f32 = 1.0;
+
+// Gamma of sRGB, colors are drawn for it without correction
+const srgb_gamma: f32 = 2.2;
+
+// Position along the palette of the fractional count `count`. Positions past the end of the
+// palette go back through it
+fn palette_position(count: f32) -> f32 {
+    if params.lut_bucket_width != 0u {
+        return equalized(count);
+    }
+    return sqrt(count / 5.0) / (2.0 * radians(180.0));
+}
 
-    if i >= params.max {
+fn palette(position: f32) -> vec3<f32> {
+    return textureSampleLevel(r_palette, s_palette, vec2<f32>(position, 0.5), 0.0).rgb;
+}
+
+// Returns the equalized color position of the fractional count `count`
+fn equalized(count: f32) -> f32 {
+    let edges = textureDimensions(r_lut) - 1u;
+    let bucket = clamp(
+        (count - f32(params.lut_start)) / f32(params.lut_bucket_width),
+        0.0,
+        f32(edges),
+    );
+    let edge = min(u32(bucket), edges - 1u);
+    let low = textureLoad(r_lut, edge, 0).x;
+    let high = textureLoad(r_lut, edge + 1u, 0).x;
+    return mix(low, high, bucket - f32(edge));
+}
+
+// Color of the escaped count `count`, which has the fraction `escape` left until the next one
+fn escaped_color(count: u32, escape: f32) -> vec3<f32> {
+    var position = f32(count);
+    if params.smooth_colors != 0u {
+        position += escape;
+    }
+    return palette(palette_position(position));
+}
+
+// Color of the previous frame at the view pixel `point`, fading out as the depth nears the fade
+// depth. Black where the previous frame hasn't escaped either or doesn't reach
+fn reprojected(point: vec2<u32>) -> vec3<f32> {
+    if params.previous_depth == 0u || params.max >= params.fade_depth {
         return vec3<f32>(0.0, 0.0, 0.0);
-    } else if i < buffer {
-        let n = f32(i) / f32(buffer - 1);
-        let r = 1 - (1 + cutoff) / (2 - cutoff);
-        let g = 1 - (cos(p) + cutoff) / (2 - cutoff);
-        let b = 1 - (cos(2.0 * p) + cutoff) / (2 - cutoff);
-        return vec3<f32>(1.0 - n * r, 1.0 - n * g, 1.0 - n * b);
-    } else {
-        let n = sqrt(f32(i - buffer) / 5.0);
-        let r = (cos(n) + cutoff) / (2 - cutoff);
-        let g = (cos(n + p) + cutoff) / (2 - cutoff);
-        let b = (cos(n + 2.0 * p) + cutoff) / (2 - cutoff);
-        return vec3<f32>(r, g, b);
     }
+    let center = vec2<f32>(point) + 0.5;
+    let previous = floor(params.reprojection * center + params.reprojection_offset);
+    if any(previous < vec2<f32>(0.0)) || any(previous >= vec2<f32>(params.previous_size)) {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+    let previous_point = vec2<u32>(previous);
+    let count = textureLoad(r_previous_color, previous_point, 0).x;
+    if count >= params.previous_depth {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+    let fade_depth = f32(params.fade_depth);
+    let fade = 1.0 - smoothstep(fade_depth / 4.0, fade_depth, f32(params.max));
+    return fade * escaped_color(count, textureLoad(r_previous_escape, previous_point, 0).x);
 }
 
 @fragment
@@ -53,6 +155,48 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     let coords = vec2<f32>(vertex.coordinates.x, -vertex.coordinates.y);
     let point = vec2<u32>((coords + 1.0) / 2.0 * vec2<f32>(params.dimensions));
     let tex = textureLoad(r_color, point, 0);
+    // Without equalization colors depend on the iteration count alone, so escaped pixels keep
+    // their color as the depth grows. Equalized colors also depend on the histogram, whose table
+    // is rebuilt every time the depth doubles and recolors the escaped pixels with it. The depth
+    // only tells the pixels that haven't escaped yet, pixels found inside the set have a count past
+    // any depth and are colored like them
+    var color = vec3<f32>(0.0, 0.0, 0.0);
+    if tex.x < params.max {
+        color = escaped_color(tex.x, textureLoad(r_escape, point, 0).x);
+        if params.distance_shading != 0u {
+            color *= smoothstep(0.0, line_width, textureLoad(r_distance, point, 0).x);
+        }
+    } else if tex.x != settled {
+        color = reprojected(point);
+    }
+
+    // Inverted outline of the selection, two pixels wide at any view size
+    let fraction = (coords + 1.0) / 2.0;
+    let width = 2.0 * fwidth(fraction);
+    let outer = all(fraction >= params.selection_min - width)
+        && all(fraction <= params.selection_max + width);
+    let inner = all(fraction > params.selection_min) && all(fraction < params.selection_max);
+    if all(params.selection_max > params.selection_min) && outer && !inner {
+        return output(1.0 - color);
+    }
+    return output(color);
+}
+
+// Converts the linear `color` for the display, correcting it for the gamma and encoding it into
+// sRGB unless the target does. Both ways produce the same values
+fn output(color: vec3<f32>) -> vec4<f32> {
+    var corrected = color;
+    if params.gamma != srgb_gamma {
+        corrected = pow(color, vec3<f32>(params.gamma / srgb_gamma));
+    }
+    if params.output_is_srgb != 0u {
+        return vec4<f32>(corrected, 1.0);
+    }
+    return vec4<f32>(srgb_encoded(corrected), 1.0);
+}
 
-    return vec4<f32>(colors(tex.x), 1.0);
+fn srgb_encoded(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3<f32>(0.0031308));
 }
@@ -0,0 +1,529 @@
+//! Rust model of the bignum arithmetic in `compute.wgsl`. Functions mirror their shader
+//! counterparts step by step, including wrapping and truncation, so that the shader can be changed
+//! with confidence. Any change to the arithmetic in the shader has to be made here as well
+
+/// Add with carry on overflow. `carry` MUST be 0 or 1
+fn carrying_add(left: u32, right: u32, carry: u32) -> (u32, u32) {
+    let sum = left.wrapping_add(right).wrapping_add(carry);
+    (sum, (sum < left || (sum == left && carry != 0)) as u32)
+}
+
+/// Add with borrow on underflow. `borrow` MUST be 0 or 1
+fn borrowing_sub(left: u32, right: u32, borrow: u32) -> (u32, u32) {
+    let diff = left.wrapping_sub(right).wrapping_sub(borrow);
+    (diff, (diff > left || (diff == left && borrow != 0)) as u32)
+}
+
+/// Multiply with carry on overflow, from 16-bit halves as there's no 64-bit type in WGSL
+fn carrying_mul(left: u32, right: u32, carry: u32) -> (u32, u32) {
+    let x0 = 0xffff & left;
+    let x1 = left >> 16;
+    let y0 = 0xffff & right;
+    let y1 = right >> 16;
+
+    let p00 = x0 * y0;
+    let p01 = x0 * y1;
+    let p10 = x1 * y0;
+    let p11 = x1 * y1;
+
+    let middle = p10 + (p00 >> 16) + (0xffff & p01);
+
+    let value = (middle << 16) | (0xffff & p00);
+    let carried_res = carrying_add(value, carry, 0);
+
+    let mul_carry = p11 + (middle >> 16) + (p01 >> 16) + carried_res.1;
+
+    (carried_res.0, mul_carry)
+}
+
+pub fn wide_add(left: &mut [u32], right: &[u32]) {
+    let mut carry = 0;
+    for (l, r) in left.iter_mut().zip(right) {
+        (*l, carry) = carrying_add(*l, *r, carry);
+    }
+}
+
+pub fn wide_sub(left: &mut [u32], right: &[u32]) {
+    let mut borrow = 0;
+    for (l, r) in left.iter_mut().zip(right) {
+        (*l, borrow) = borrowing_sub(*l, *r, borrow);
+    }
+}
+
+pub fn wide_neg(num: &mut [u32]) {
+    let mut carry = 1;
+    for word in num.iter_mut() {
+        (*word, carry) = carrying_add(!*word, 0, carry);
+    }
+}
+
+fn wide_floor(num: &[u32]) -> i32 {
+    num[num.len() - 1] as i32
+}
+
+pub fn wide_abs(num: &mut [u32]) {
+    if wide_floor(num) < 0 {
+        wide_neg(num);
+    }
+}
+
+pub fn wide_mul_u32(left: &mut [u32], right: u32) {
+    let mut carry = 0;
+    for word in left.iter_mut() {
+        (*word, carry) = carrying_mul(*word, right, carry);
+    }
+}
+
+/// Adds `increment` to `num` starting at the word `offset`. Offsets past the end are a no-op,
+/// like the wrapped negative offsets of the shader
+fn wide_add_u32_at(num: &mut [u32], offset: i32, increment: u32) {
+    if offset < 0 {
+        return;
+    }
+    let mut inc = increment;
+    for word in num.iter_mut().skip(offset as usize) {
+        (*word, inc) = carrying_add(*word, inc, 0);
+        if inc == 0 {
+            break;
+        }
+    }
+}
+
+pub fn wide_square(num: &mut [u32], out: &mut [u32]) {
+    let word_count = num.len() as i32;
+    out.fill(0);
+
+    let numneg = wide_floor(num) < 0;
+    if numneg {
+        wide_neg(num);
+    }
+
+    // Leading zero words don't contribute to any partial product
+    let mut len = word_count;
+    while len > 0 && num[len as usize - 1] == 0 {
+        len -= 1;
+    }
+
+    for i in word_count / 2 - 1..len {
+        let target_idx = 2 * i + 1 - word_count;
+        let ni = num[i as usize];
+        if ni == 0 {
+            continue;
+        }
+
+        let mut prod = carrying_mul(ni, ni, 0);
+        if target_idx >= 0 {
+            let res = carrying_add(out[target_idx as usize], prod.0, 0);
+            out[target_idx as usize] = res.0;
+            // Squares of a word are at most 0xfffffffe_00000001, so the high word can't overflow
+            prod.1 += res.1;
+        }
+        wide_add_u32_at(out, target_idx + 1, prod.1);
+    }
+
+    for i in 0..len {
+        let ni = num[i as usize];
+        if ni == 0 {
+            continue;
+        }
+        // Products below the word under the least significant one can't carry into the result
+        let min_useful_index = word_count - i - 3;
+        let start = (i + 1).max(min_useful_index);
+        for j in start..len {
+            let target_idx = i + j + 1 - word_count;
+
+            let nj = num[j as usize];
+            let prod = carrying_mul(ni, nj, 0);
+            let double_lo = carrying_add(prod.0, prod.0, 0);
+            let mut double_hi = carrying_add(prod.1, prod.1, double_lo.1);
+
+            if target_idx >= 0 {
+                let res = carrying_add(out[target_idx as usize], double_lo.0, 0);
+                out[target_idx as usize] = res.0;
+                // The high word can be all ones, so this may carry as well
+                let bumped = carrying_add(double_hi.0, res.1, 0);
+                double_hi.0 = bumped.0;
+                double_hi.1 += bumped.1;
+            }
+            if target_idx >= -1 {
+                let res = carrying_add(out[(target_idx + 1) as usize], double_hi.0, 0);
+                out[(target_idx + 1) as usize] = res.0;
+                double_hi.1 += res.1;
+            }
+            wide_add_u32_at(out, target_idx + 2, double_hi.1);
+        }
+    }
+
+    if numneg {
+        wide_neg(num);
+    }
+}
+
+/// Product of different numbers `left` and `right`, truncated like [`wide_square`]
+pub fn wide_mul(left: &mut [u32], right: &mut [u32], out: &mut [u32]) {
+    let word_count = left.len() as i32;
+    out.fill(0);
+
+    let leftneg = wide_floor(left) < 0;
+    if leftneg {
+        wide_neg(left);
+    }
+    let rightneg = wide_floor(right) < 0;
+    if rightneg {
+        wide_neg(right);
+    }
+
+    for i in 0..word_count {
+        let li = left[i as usize];
+        if li == 0 {
+            continue;
+        }
+        // Products below the word under the least significant one can't carry into the result
+        let min_useful_index = word_count - i - 2;
+        let start = min_useful_index.max(0);
+        for j in start..word_count {
+            let target_idx = i + j + 1 - word_count;
+
+            let mut prod = carrying_mul(li, right[j as usize], 0);
+            if target_idx >= 0 {
+                let res = carrying_add(out[target_idx as usize], prod.0, 0);
+                out[target_idx as usize] = res.0;
+                // Products of words are at most 0xfffffffe_00000001, so the high word can't overflow
+                prod.1 += res.1;
+            }
+            wide_add_u32_at(out, target_idx + 1, prod.1);
+        }
+    }
+
+    if leftneg {
+        wide_neg(left);
+    }
+    if rightneg {
+        wide_neg(right);
+    }
+    if leftneg != rightneg {
+        wide_neg(out);
+    }
+}
+
+pub fn wide_shr_words(num: &mut [u32], shift: u32) {
+    let len = num.len();
+    for idx in 0..len {
+        let src = idx + shift as usize;
+        num[idx] = if src < len { num[src] } else { 0 };
+    }
+}
+
+/// Logical right shift by `shift` bits. `shift` MUST be within 1..32
+pub fn wide_shr_bits(num: &mut [u32], shift: u32) {
+    let last = num.len() - 1;
+    for idx in 0..last {
+        num[idx] = (num[idx] >> shift) | (num[idx + 1] << (32 - shift));
+    }
+    num[last] >>= shift;
+}
+
+/// Product of non-negative `num` and `factor` rounded to 16 fraction bits
+pub fn wide_mul_fixed(num: &[u32], factor: f32, dst: &mut [u32]) {
+    let fixed = (factor * 65536.0).round() as i32;
+    dst.copy_from_slice(num);
+    wide_mul_u32(dst, fixed.unsigned_abs());
+    wide_shr_bits(dst, 16);
+    if fixed < 0 {
+        wide_neg(dst);
+    }
+}
+
+/// `src` as the whole part of `dst`
+pub fn wide_from_u32(src: u32, dst: &mut [u32]) {
+    let last = dst.len() - 1;
+    dst[..last].fill(0);
+    dst[last] = src;
+}
+
+/// `src` in `dst` with the fraction truncated to the word below the whole part, saturating values
+/// that don't fit into the whole part
+pub fn wide_from_f32(src: f32, dst: &mut [u32]) {
+    let value = if src.is_nan() {
+        1048576.0
+    } else {
+        src.clamp(-1048576.0, 1048576.0)
+    };
+    let whole = value.floor();
+    let fraction = ((value - whole) * 4294967296.0).min(4294967040.0);
+    let len = dst.len();
+    dst[..len - 2].fill(0);
+    dst[len - 2] = fraction as u32;
+    dst[len - 1] = whole as i32 as u32;
+}
+
+/// True if c = `x` + i`y` is in the main cardioid or the period 2 bulb of the Mandelbrot set
+pub fn in_main_bulbs(x: &[u32], y: &[u32]) -> bool {
+    let (whole_x, whole_y) = (wide_floor(x), wide_floor(y));
+    if !(-2..=0).contains(&whole_x) || !(-1..=0).contains(&whole_y) {
+        return false;
+    }
+
+    let len = x.len();
+    let mut y2 = vec![0; len];
+    let mut shifted = vec![0; len];
+    let mut constant = vec![0; len];
+    let mut q = vec![0; len];
+    let mut sum = vec![0; len];
+    let mut prod = vec![0; len];
+
+    wide_square(&mut y.to_vec(), &mut y2);
+
+    // Period 2 bulb: (x + 1)^2 + y^2 <= 1/16
+    shifted.copy_from_slice(x);
+    wide_from_u32(1, &mut constant);
+    wide_add(&mut shifted, &constant);
+    wide_square(&mut shifted, &mut sum);
+    wide_add(&mut sum, &y2);
+    wide_from_f32(0.0625, &mut constant);
+    wide_sub(&mut constant, &sum);
+    if wide_floor(&constant) >= 0 {
+        return true;
+    }
+
+    // Main cardioid: q * (q + x - 1/4) <= y^2 / 4, with q = (x - 1/4)^2 + y^2
+    shifted.copy_from_slice(x);
+    wide_from_f32(0.25, &mut constant);
+    wide_sub(&mut shifted, &constant);
+    wide_square(&mut shifted, &mut q);
+    wide_add(&mut q, &y2);
+    sum.copy_from_slice(&q);
+    wide_add(&mut sum, &shifted);
+    wide_mul(&mut q, &mut sum, &mut prod);
+    wide_shr_bits(&mut y2, 2);
+    wide_sub(&mut y2, &prod);
+    wide_floor(&y2) >= 0
+}
+
+/// True if `left` and `right` are at most one unit of the least significant word apart. `tmp` is
+/// overwritten with the difference
+pub fn wide_near(left: &[u32], right: &[u32], tmp: &mut [u32]) -> bool {
+    tmp.copy_from_slice(left);
+    wide_sub(tmp, right);
+    let sign_word = if wide_floor(tmp) < 0 { u32::MAX } else { 0 };
+    if tmp[1..].iter().any(|&word| word != sign_word) {
+        return false;
+    }
+    if sign_word != 0 {
+        tmp[0] == u32::MAX
+    } else {
+        tmp[0] <= 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::WideFloat;
+    use num_bigint::{BigInt, Sign};
+    use proptest::prelude::*;
+
+    /// Raw value of the number as a signed integer, the real value is `raw / 2^fraction_bits`
+    fn raw(words: &[u32]) -> BigInt {
+        let unsigned = BigInt::from_slice(Sign::Plus, words);
+        if wide_floor(words) < 0 {
+            unsigned - (BigInt::from(1) << (words.len() * 32))
+        } else {
+            unsigned
+        }
+    }
+
+    fn sized_pair() -> impl Strategy<Value = (WideFloat, WideFloat)> {
+        (2..=16usize).prop_flat_map(|size| (WideFloat::arbitrary(size), WideFloat::arbitrary(size)))
+    }
+
+    fn sized() -> impl Strategy<Value = WideFloat> {
+        (2..=16usize).prop_flat_map(WideFloat::arbitrary)
+    }
+
+    #[test]
+    fn bulb_tests_match_f64() {
+        let mut inside = 0;
+        for row in -80..=80 {
+            for column in -160..=64 {
+                let (x, y) = (column as f64 / 64.0, row as f64 / 64.0);
+                let bulb = (x + 1.0).powi(2) + y * y - 1.0 / 16.0;
+                let q = (x - 0.25).powi(2) + y * y;
+                let cardioid = q * (q + x - 0.25) - y * y / 4.0;
+                let expected = bulb <= 0.0 || cardioid <= 0.0;
+
+                let x_wide = WideFloat::from_f64(x, 3).unwrap();
+                let y_wide = WideFloat::from_f64(y, 3).unwrap();
+                let actual = in_main_bulbs(x_wide.words(), y_wide.words());
+                // Products are truncated, which may only decide points right at the boundary
+                if bulb.abs().min(cardioid.abs()) > 1e-12 {
+                    assert_eq!(actual, expected, "{x}, {y}");
+                }
+                inside += actual as u32;
+            }
+        }
+        assert!(inside > 1000, "{inside}");
+    }
+
+    #[test]
+    fn square_carries_out_of_an_all_ones_high_word() {
+        // 2 * 0xcb5b965b * 0xa1227e3c has a high word of all ones, adding the carry from the lower
+        // word used to wrap it to zero and lose the carry
+        let mut num = vec![0x467bd9b0, 0xcb5b965b, 0xa1227e3c, 0];
+        let mut out = vec![0; 4];
+        wide_square(&mut num, &mut out);
+        let exact: BigInt = raw(&num).pow(2) >> 96;
+        let error = exact - raw(&out);
+        assert!(
+            error.sign() != Sign::Minus && error <= BigInt::from(8),
+            "{}",
+            error
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(1024))]
+
+        #[test]
+        fn carrying_mul_matches_u64(left: u32, right: u32, carry: u32) {
+            let exact = left as u64 * right as u64 + carry as u64;
+            prop_assert_eq!(carrying_mul(left, right, carry), (exact as u32, (exact >> 32) as u32));
+        }
+
+        #[test]
+        fn add_sub_neg_match_wide_float((a, b) in sized_pair()) {
+            let mut sum = a.words().to_vec();
+            wide_add(&mut sum, b.words());
+            prop_assert_eq!(sum, (a.clone() + &b).words().to_vec());
+
+            let mut difference = a.words().to_vec();
+            wide_sub(&mut difference, b.words());
+            prop_assert_eq!(difference, (a.clone() - &b).words().to_vec());
+
+            let mut negated = a.words().to_vec();
+            wide_neg(&mut negated);
+            prop_assert_eq!(negated, (-a.clone()).words().to_vec());
+
+            let mut abs = a.words().to_vec();
+            wide_abs(&mut abs);
+            let expected = if a < 0 { -a } else { a };
+            prop_assert_eq!(abs, expected.words());
+        }
+
+        #[test]
+        fn mul_u32_is_exact(a in sized(), right: u32) {
+            let a = if a < 0 { -a } else { a };
+            let mut product = a.words().to_vec();
+            wide_mul_u32(&mut product, right);
+            let bits = a.word_count() * 32;
+            let exact = raw(a.words()) * right;
+            let wrapped = exact & ((BigInt::from(1) << bits) - 1);
+            prop_assert_eq!(BigInt::from_slice(Sign::Plus, &product), wrapped);
+        }
+
+        #[test]
+        fn square_is_truncated_exact_square(a in sized()) {
+            let mut num = a.words().to_vec();
+            let mut out = vec![0; num.len()];
+            wide_square(&mut num, &mut out);
+            // The input is restored after being made non-negative
+            prop_assert_eq!(&num, a.words());
+
+            let fraction_bits = (a.word_count() - 1) * 32;
+            let exact = raw(a.words()).pow(2) >> fraction_bits;
+            // Partial products that can't reach the kept words are skipped, which loses at most a
+            // few ulps per word
+            let error = exact - raw(&out);
+            let max_error = BigInt::from(2 * a.word_count());
+            prop_assert!(error.sign() != Sign::Minus, "rounded up by {}", -error);
+            prop_assert!(error <= max_error, "error: {}", error);
+        }
+
+        #[test]
+        fn square_matches_wide_float(a in sized()) {
+            let mut num = a.words().to_vec();
+            let mut out = vec![0; num.len()];
+            wide_square(&mut num, &mut out);
+            let error = raw(a.square().words()) - raw(&out);
+            prop_assert!(error.magnitude() <= BigInt::from(2 * a.word_count()).magnitude());
+        }
+
+        #[test]
+        fn mul_is_truncated_exact_product((a, b) in sized_pair()) {
+            let (mut left, mut right) = (a.words().to_vec(), b.words().to_vec());
+            let mut out = vec![0; left.len()];
+            wide_mul(&mut left, &mut right, &mut out);
+            // The inputs are restored after being made non-negative
+            prop_assert_eq!(&left, a.words());
+            prop_assert_eq!(&right, b.words());
+
+            let fraction_bits = (a.word_count() - 1) * 32;
+            let product = raw(a.words()) * raw(b.words());
+            let result = raw(&out);
+            if result.sign() != Sign::NoSign {
+                prop_assert_eq!(result.sign(), product.sign());
+            }
+            // Partial products of the magnitudes that can't reach the kept words are skipped,
+            // which loses at most a few ulps per word
+            let exact = BigInt::from(product.magnitude() >> fraction_bits);
+            let error = exact - BigInt::from(result.magnitude().clone());
+            prop_assert!(error.sign() != Sign::Minus, "rounded up by {}", -error);
+            prop_assert!(error <= BigInt::from(2 * a.word_count()), "error: {}", error);
+        }
+
+        #[test]
+        fn shr_words_matches_wide_float(a in sized(), shift in 0..2u32) {
+            let a = if a < 0 { -a } else { a };
+            let mut shifted = a.words().to_vec();
+            wide_shr_words(&mut shifted, shift);
+            let mut expected = a.clone();
+            expected.logical_shr(shift as usize * 32);
+            prop_assert_eq!(shifted, expected.words());
+        }
+
+        #[test]
+        fn shr_bits_matches_wide_float(a in sized(), shift in 1..32usize) {
+            let mut shifted = a.words().to_vec();
+            wide_shr_bits(&mut shifted, shift as u32);
+            let mut expected = a.clone();
+            expected.logical_shr(shift);
+            prop_assert_eq!(shifted, expected.words());
+        }
+
+        #[test]
+        fn near_is_within_one_ulp((a, b) in sized_pair(), offset in -3i32..=3) {
+            let mut tmp = vec![0; a.word_count()];
+            let distance = raw(a.words()) - raw(b.words());
+            prop_assert_eq!(
+                wide_near(a.words(), b.words(), &mut tmp),
+                distance.magnitude() <= &1u32.into()
+            );
+
+            // Numbers a few units next to `a` are only near it within one unit, also where the
+            // difference wraps around the range
+            let mut moved = a.words().to_vec();
+            let mut step = vec![0; a.word_count()];
+            step[0] = offset.unsigned_abs();
+            if offset < 0 {
+                wide_sub(&mut moved, &step);
+            } else {
+                wide_add(&mut moved, &step);
+            }
+            prop_assert_eq!(wide_near(&moved, a.words(), &mut tmp), offset.abs() <= 1);
+        }
+
+        #[test]
+        fn mul_fixed_is_truncated_product(a in sized(), pixel in -16384i32..16384) {
+            let a = if a < 0 { -a } else { a };
+            // Keeps the product of the whole part and the fixed point factor within the word
+            let a = a >> 45;
+            let factor = pixel as f32 / 4.0;
+            let mut product = vec![0; a.word_count()];
+            wide_mul_fixed(a.words(), factor, &mut product);
+            // Quarter pixels are exact in 16 fraction bits, so only the shift truncates
+            let magnitude: BigInt = (raw(a.words()) * (pixel.unsigned_abs() << 14)) >> 16;
+            let expected = if pixel < 0 { -magnitude } else { magnitude };
+            prop_assert_eq!(raw(&product), expected);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::fractal::{FractalParams, IterationParams};
+use crate::primitives::{Coordinates, ScaledDimensions};
+
+use super::compute::ComputeBindings;
+use super::COMPUTE_SHADER_TEMPLATE;
+
+/// Size of the parts buffers are read back in, so that a single copy doesn't stall the frame
+const CHUNK_SIZE: u64 = 16 << 20;
+
+/// Identifies the calculation a snapshot belongs to. Snapshots can only be resumed by the view
+/// calculating the exact same pixels with the same shader
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SnapshotKey(Vec<u8>);
+
+/// Calculation progress of a view read back from the GPU
+#[derive(Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub key: SnapshotKey,
+    /// Depth all pixels are calculated to
+    pub depth: u32,
+    /// Contents of the result buffer
+    pub iterations: Vec<u8>,
+    /// Contents of the intermediate buffer
+    pub intermediate: Vec<u8>,
+}
+
+/// Iteration counts of a view, with the state to resume its calculation from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Iterations {
+    pub size: ScaledDimensions,
+    /// Coordinates the counts are calculated at
+    pub coords: Coordinates,
+    /// Depth all pixels are calculated to
+    pub depth: u32,
+    /// Counts of the pixels row by row, without the padding of the result buffer
+    pub counts: Vec<u32>,
+    /// Compute buffers of the view, missing for perturbed views
+    pub resume: Option<Snapshot>,
+}
+
+impl std::fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("key", &format_args!("{:016x}", self.key.fingerprint()))
+            .field("depth", &self.depth)
+            .field("iterations", &self.iterations.len())
+            .field("intermediate", &self.intermediate.len())
+            .finish()
+    }
+}
+
+impl SnapshotKey {
+    pub(super) fn new(
+        coords: &Coordinates,
+        size: ScaledDimensions,
+        fractal: &FractalParams,
+        iteration: IterationParams,
+    ) -> Self {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&fingerprint(COMPUTE_SHADER_TEMPLATE.as_bytes()).to_le_bytes());
+        bytes.extend_from_slice(&size.width.to_le_bytes());
+        bytes.extend_from_slice(&size.height.to_le_bytes());
+        bytes.extend_from_slice(&fractal.kind.shader_id().to_le_bytes());
+        bytes.push(fractal.inverted as u8);
+        bytes.extend_from_slice(&fractal.power.to_le_bytes());
+        bytes.extend_from_slice(&iteration.escape_radius_for(fractal.power).to_le_bytes());
+        bytes.push(iteration.distance_estimation as u8);
+        if let Some(seed) = &fractal.julia {
+            bytes.extend_from_slice(seed.re.as_bytes());
+            bytes.extend_from_slice(seed.im.as_bytes());
+        }
+        bytes.extend_from_slice(&(coords.size() as u32).to_le_bytes());
+        bytes.extend_from_slice(coords.x.as_bytes());
+        bytes.extend_from_slice(coords.y.as_bytes());
+        bytes.extend_from_slice(coords.step.as_bytes());
+        bytes.extend_from_slice(&coords.rotation().to_le_bytes());
+        Self(bytes)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Short hash of the key, stable across runs and platforms
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint(&self.0)
+    }
+}
+
+/// 64-bit FNV-1a hash
+fn fingerprint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Chunked asynchronous copy of the compute buffers of a view. The view isn't calculated until the
+/// readback is complete, so that all chunks belong to the same depth
+pub(super) struct Readback {
+    key: SnapshotKey,
+    depth: u32,
+    staging: wgpu::Buffer,
+    iterations_size: u64,
+    intermediate_size: u64,
+    data: Vec<u8>,
+    stage: Stage,
+}
+
+enum Stage {
+    /// Next chunk can be copied
+    Idle,
+    /// Chunk of `len` bytes is copied to the staging buffer by the submitted work
+    Copied { len: u64 },
+    /// Staging buffer is being mapped
+    Mapping {
+        len: u64,
+        result: flume::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    },
+}
+
+pub(super) enum Progress {
+    Pending,
+    Done(Snapshot),
+    Failed(wgpu::BufferAsyncError),
+}
+
+impl Readback {
+    pub fn new(
+        device: &wgpu::Device,
+        bindings: &ComputeBindings,
+        key: SnapshotKey,
+        depth: u32,
+    ) -> Self {
+        let iterations_size = bindings.sizes.result;
+        let intermediate_size = bindings.sizes.intermediate;
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Staging"),
+            size: CHUNK_SIZE.min(iterations_size.max(intermediate_size)),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            key,
+            depth,
+            staging,
+            iterations_size,
+            intermediate_size,
+            data: Vec::with_capacity((iterations_size + intermediate_size) as usize),
+            stage: Stage::Idle,
+        }
+    }
+
+    /// Copies the next chunk into the staging buffer, unless the previous one is still in flight
+    pub fn encode_next(&mut self, encoder: &mut wgpu::CommandEncoder, bindings: &ComputeBindings) {
+        if !matches!(self.stage, Stage::Idle) {
+            return;
+        }
+        let read = self.data.len() as u64;
+        // Chunks don't cross buffer boundaries
+        let (buffer, offset, remaining) = if read < self.iterations_size {
+            (&bindings.result_buffer, read, self.iterations_size - read)
+        } else {
+            let offset = read - self.iterations_size;
+            (
+                &bindings.intermediate_buffer,
+                offset,
+                self.intermediate_size - offset,
+            )
+        };
+        let len = remaining.min(self.staging.size());
+        encoder.copy_buffer_to_buffer(buffer, offset, &self.staging, 0, len);
+        self.stage = Stage::Copied { len };
+    }
+
+    /// Starts mapping the chunk copied by the work that has just been submitted
+    pub fn request_map(&mut self) {
+        if let Stage::Copied { len } = self.stage {
+            let (sender, result) = flume::bounded(1);
+            self.staging
+                .slice(..len)
+                .map_async(wgpu::MapMode::Read, move |r| {
+                    let _ = sender.send(r);
+                });
+            self.stage = Stage::Mapping { len, result };
+        }
+    }
+
+    /// Collects the mapped chunk. Devices have to be polled for mapping to complete
+    pub fn receive(&mut self) -> Progress {
+        let Stage::Mapping { len, result } = &self.stage else {
+            return Progress::Pending;
+        };
+        match result.try_recv() {
+            Ok(Ok(())) => {
+                let len = *len;
+                self.data
+                    .extend_from_slice(&self.staging.slice(..len).get_mapped_range());
+                self.staging.unmap();
+                self.stage = Stage::Idle;
+            }
+            Ok(Err(e)) => return Progress::Failed(e),
+            Err(_) => return Progress::Pending,
+        }
+
+        if (self.data.len() as u64) < self.iterations_size + self.intermediate_size {
+            return Progress::Pending;
+        }
+        let intermediate = self.data.split_off(self.iterations_size as usize);
+        Progress::Done(Snapshot {
+            key: self.key.clone(),
+            depth: self.depth,
+            iterations: std::mem::take(&mut self.data),
+            intermediate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::{FractalKind, JuliaSeed};
+
+    #[test]
+    fn fingerprint_is_stable() {
+        // Reference values of FNV-1a
+        assert_eq!(fingerprint(b""), 0xcbf29ce484222325);
+        assert_eq!(fingerprint(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fingerprint(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn keys_tell_calculations_apart() {
+        let coords = Coordinates::new(-0.75, 0.1, 1e-3, 10).unwrap();
+        let size = ScaledDimensions {
+            width: 320,
+            height: 200,
+        };
+        let iteration = IterationParams::default();
+        let key = SnapshotKey::new(&coords, size, &FractalParams::default(), iteration);
+        assert_eq!(
+            key,
+            SnapshotKey::new(&coords, size, &FractalParams::default(), iteration)
+        );
+
+        let celtic = FractalParams {
+            kind: FractalKind::Celtic,
+            ..Default::default()
+        };
+        assert_ne!(key, SnapshotKey::new(&coords, size, &celtic, iteration));
+        let julia = FractalParams {
+            julia: JuliaSeed::from_f64(-0.8, 0.156),
+            ..Default::default()
+        };
+        assert_ne!(key, SnapshotKey::new(&coords, size, &julia, iteration));
+        let cubic = FractalParams {
+            power: 3,
+            ..Default::default()
+        };
+        assert_ne!(key, SnapshotKey::new(&coords, size, &cubic, iteration));
+        let wider = IterationParams {
+            escape_radius: 100.0,
+            ..Default::default()
+        };
+        assert_ne!(
+            key,
+            SnapshotKey::new(&coords, size, &FractalParams::default(), wider)
+        );
+        let estimated = IterationParams {
+            distance_estimation: true,
+            ..Default::default()
+        };
+        assert_ne!(
+            key,
+            SnapshotKey::new(&coords, size, &FractalParams::default(), estimated)
+        );
+        let taller = ScaledDimensions {
+            height: 201,
+            ..size
+        };
+        assert_ne!(
+            key,
+            SnapshotKey::new(&coords, taller, &FractalParams::default(), iteration)
+        );
+        let mut moved = coords.clone();
+        moved.move_by_delta(1.0, 0.0).unwrap();
+        assert_ne!(
+            key,
+            SnapshotKey::new(&moved, size, &FractalParams::default(), iteration)
+        );
+        let mut rotated = coords.clone();
+        rotated.set_rotation(90.0).unwrap();
+        assert_ne!(
+            key,
+            SnapshotKey::new(&rotated, size, &FractalParams::default(), iteration)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! GPU timing of the compute passes of a frame with timestamp queries. Unlike the wall clock it
+//! leaves out the time the work waits to be scheduled and polled
+
+/// Passes of a frame that can be timed, the time of frames with more of them isn't measured
+pub const MAX_PASSES: u32 = 128;
+
+/// Times the compute passes of a frame, which may span several submissions, and reads back their
+/// total once the frame has completed
+pub struct PassTimer {
+    query_set: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    staging: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    /// Passes of the frame in progress that were given timestamps
+    passes: u32,
+    /// The frame in progress is timed, its passes fit and the previous readback was done
+    timing: bool,
+    stage: Stage,
+}
+
+enum Stage {
+    Idle,
+    /// Timestamps of `passes` are copied to the staging buffer by the submitted work
+    Copied {
+        passes: u32,
+    },
+    Mapping {
+        passes: u32,
+        result: flume::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    },
+}
+
+impl PassTimer {
+    /// Creates a timer if the device supports timestamp queries
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let size = timestamps_size(MAX_PASSES);
+        Some(Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Pass Timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: 2 * MAX_PASSES,
+            }),
+            resolve: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Pass Timestamps Resolve"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            staging: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Pass Timestamps Staging"),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            period: queue.get_timestamp_period(),
+            passes: 0,
+            timing: false,
+            stage: Stage::Idle,
+        })
+    }
+
+    /// Starts timing a frame. Frames started while the last one is still read back aren't timed
+    pub fn start_frame(&mut self) {
+        self.passes = 0;
+        self.timing = matches!(self.stage, Stage::Idle);
+    }
+
+    /// Returns the timestamps of the next compute pass of the frame
+    pub fn pass_writes(&mut self) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        if !self.timing {
+            return None;
+        }
+        if self.passes == MAX_PASSES {
+            self.timing = false;
+            return None;
+        }
+        let index = 2 * self.passes;
+        self.passes += 1;
+        Some(wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// Copies the timestamps of the frame after its last pass
+    pub fn encode_resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !std::mem::take(&mut self.timing) || self.passes == 0 {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..2 * self.passes, &self.resolve, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve,
+            0,
+            &self.staging,
+            0,
+            timestamps_size(self.passes),
+        );
+        self.stage = Stage::Copied {
+            passes: self.passes,
+        };
+    }
+
+    /// Starts mapping the timestamps copied by the work that has just been submitted
+    pub fn request_map(&mut self) {
+        if let Stage::Copied { passes } = self.stage {
+            let (sender, result) = flume::bounded(1);
+            self.staging.slice(..timestamps_size(passes)).map_async(
+                wgpu::MapMode::Read,
+                move |r| {
+                    let _ = sender.send(r);
+                },
+            );
+            self.stage = Stage::Mapping { passes, result };
+        }
+    }
+
+    /// Returns the GPU time of the completed frame in milliseconds, if it was timed and its
+    /// timestamps have been mapped. Devices have to be polled for mapping to complete
+    pub fn receive(&mut self) -> Option<f64> {
+        let Stage::Mapping { passes, result } = &self.stage else {
+            return None;
+        };
+        let passes = *passes;
+        match result.try_recv() {
+            Ok(Ok(())) => {
+                let slice = self.staging.slice(..timestamps_size(passes));
+                let ms = passes_ms(bytemuck::cast_slice(&slice.get_mapped_range()), self.period);
+                self.staging.unmap();
+                self.stage = Stage::Idle;
+                Some(ms)
+            }
+            Ok(Err(e)) => {
+                log::error!("Unable to read the pass timestamps: {}", e);
+                self.stage = Stage::Idle;
+                None
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// Size of the beginning and end timestamps of `passes`
+fn timestamps_size(passes: u32) -> wgpu::BufferAddress {
+    2 * passes as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress
+}
+
+/// Returns the total time of the passes with the beginning and end `timestamps` in
+/// milliseconds, of `period` nanoseconds per tick
+fn passes_ms(timestamps: &[u64], period: f32) -> f64 {
+    let ticks: u64 = timestamps
+        .chunks_exact(2)
+        // Timestamps of some drivers aren't monotonic across passes, those count as instant
+        .map(|pass| pass[1].saturating_sub(pass[0]))
+        .sum();
+    ticks as f64 * period as f64 / 1e6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pass_times_are_added_up() {
+        assert_eq!(passes_ms(&[], 1.0), 0.0);
+        assert_eq!(passes_ms(&[1_000_000, 3_000_000], 1.0), 2.0);
+        // Gaps between the passes aren't counted
+        assert_eq!(passes_ms(&[0, 1_000_000, 5_000_000, 6_000_000], 2.0), 4.0);
+        assert_eq!(passes_ms(&[10, 5], 1.0), 0.0);
+    }
+}
@@ -0,0 +1,110 @@
+/// Outcome of checking on the submitted work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Nothing is submitted or the work is still within its timeout
+    Waiting,
+    /// The work has exceeded its timeout once and is given one more
+    Stalled,
+    /// The work hasn't completed within two timeouts and should be considered lost
+    Lost,
+}
+
+/// Detects submitted GPU work that never signals completion
+pub struct Watchdog {
+    /// Timeout as a multiple of the expected frame time
+    frame_multiplier: f64,
+    /// Lower bound of the timeout, leaves room for lazy shader compilation on the first dispatch
+    min_timeout_ms: f64,
+    submission: Option<Submission>,
+}
+
+struct Submission {
+    deadline_ms: f64,
+    timeout_ms: f64,
+    stalled: bool,
+}
+
+impl Watchdog {
+    pub fn new(frame_multiplier: f64, min_timeout_ms: f64) -> Self {
+        Self {
+            frame_multiplier,
+            min_timeout_ms,
+            submission: None,
+        }
+    }
+
+    /// Starts watching work submitted at `now_ms` that is expected to take `expected_ms`
+    pub fn submitted(&mut self, now_ms: f64, expected_ms: f64) {
+        let timeout_ms = (expected_ms * self.frame_multiplier).max(self.min_timeout_ms);
+        self.submission = Some(Submission {
+            deadline_ms: now_ms + timeout_ms,
+            timeout_ms,
+            stalled: false,
+        });
+    }
+
+    pub fn completed(&mut self) {
+        self.submission = None;
+    }
+
+    pub fn check(&mut self, now_ms: f64) -> Verdict {
+        let Some(submission) = &mut self.submission else {
+            return Verdict::Waiting;
+        };
+        if now_ms < submission.deadline_ms {
+            Verdict::Waiting
+        } else if !submission.stalled {
+            submission.stalled = true;
+            submission.deadline_ms = now_ms + submission.timeout_ms;
+            Verdict::Stalled
+        } else {
+            self.submission = None;
+            Verdict::Lost
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completed_work() {
+        let mut watchdog = Watchdog::new(10.0, 100.0);
+        assert_eq!(watchdog.check(0.0), Verdict::Waiting);
+
+        watchdog.submitted(1000.0, 30.0);
+        assert_eq!(watchdog.check(1299.0), Verdict::Waiting);
+        watchdog.completed();
+        assert_eq!(watchdog.check(5000.0), Verdict::Waiting);
+    }
+
+    #[test]
+    fn stalled_then_completed() {
+        let mut watchdog = Watchdog::new(10.0, 100.0);
+        watchdog.submitted(0.0, 30.0);
+        assert_eq!(watchdog.check(300.0), Verdict::Stalled);
+        assert_eq!(watchdog.check(301.0), Verdict::Waiting);
+        watchdog.completed();
+        assert_eq!(watchdog.check(1000.0), Verdict::Waiting);
+    }
+
+    #[test]
+    fn lost_work() {
+        let mut watchdog = Watchdog::new(10.0, 100.0);
+        watchdog.submitted(0.0, 30.0);
+        assert_eq!(watchdog.check(350.0), Verdict::Stalled);
+        assert_eq!(watchdog.check(649.0), Verdict::Waiting);
+        assert_eq!(watchdog.check(650.0), Verdict::Lost);
+        // Lost work is reported once
+        assert_eq!(watchdog.check(10_000.0), Verdict::Waiting);
+    }
+
+    #[test]
+    fn min_timeout() {
+        let mut watchdog = Watchdog::new(10.0, 2000.0);
+        watchdog.submitted(0.0, 30.0);
+        assert_eq!(watchdog.check(1999.0), Verdict::Waiting);
+        assert_eq!(watchdog.check(2000.0), Verdict::Stalled);
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Tells when the submitted work is done where polling the device doesn't. The WebGPU backend
+/// leaves `Queue::on_submitted_work_done` unimplemented and returns from every `Device::poll` at
+/// once, but a buffer mapped after a submission only resolves once the work before it is done
+#[derive(Default)]
+pub struct WorkDone {
+    /// Buffers mapped after submissions, unmapped once all of them have resolved
+    mapped: Vec<wgpu::Buffer>,
+    /// Unmapped buffers to map after the next submissions
+    idle: Vec<wgpu::Buffer>,
+    /// Mappings that haven't resolved yet
+    pending: Arc<AtomicUsize>,
+    /// Called when the last pending mapping resolves
+    notifier: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+impl WorkDone {
+    pub fn set_notifier(&mut self, notifier: Arc<dyn Fn() + Send + Sync>) {
+        self.notifier = Some(notifier);
+    }
+
+    /// Forgets the buffers and mappings of a lost device
+    pub fn reset(&mut self) {
+        self.mapped.clear();
+        self.idle.clear();
+        // Mappings of the lost device may still resolve, they don't count for the new one
+        self.pending = Default::default();
+    }
+
+    /// Marks the end of the work submitted so far. The notifier is called once all of it is done
+    pub fn submitted(&mut self, device: &wgpu::Device) {
+        let buffer = self.idle.pop().unwrap_or_else(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Work Done"),
+                size: wgpu::MAP_ALIGNMENT,
+                usage: wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            })
+        });
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        let pending = self.pending.clone();
+        let notifier = self.notifier.clone();
+        // Failed mappings resolve as well, the device is lost then and the work won't complete
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |_| {
+            if pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+                if let Some(notify) = notifier {
+                    notify();
+                }
+            }
+        });
+        self.mapped.push(buffer);
+    }
+
+    /// Returns true if some of the submitted work isn't done yet, the notifier is called once it is
+    pub fn is_pending(&self) -> bool {
+        self.pending.load(Ordering::SeqCst) > 0
+    }
+
+    /// Returns true if all of the submitted work is done, and unmaps the buffers for the next
+    /// submissions then
+    pub fn is_done(&mut self) -> bool {
+        if self.is_pending() {
+            return false;
+        }
+        for buffer in self.mapped.drain(..) {
+            buffer.unmap();
+            self.idle.push(buffer);
+        }
+        true
+    }
+}
@@ -0,0 +1,255 @@
+//! Navigation history of a view for undo and redo. Every gesture is a single step: a drag from
+//! press to release, or a burst of zooms or rotations until it settles
+
+use std::collections::VecDeque;
+
+use crate::timer::{Clock, SystemClock};
+
+/// Steps kept by default
+pub const DEFAULT_DEPTH: usize = 100;
+/// Zooms and rotations further apart than this in milliseconds are separate gestures
+const SETTLE_MS: f64 = 500.0;
+
+/// Kind of change made to the view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    /// Lasts until it's finished explicitly
+    Drag,
+    Zoom,
+    Rotate,
+}
+
+/// Steps of the states `T` of a view
+#[derive(Debug, Clone)]
+pub struct History<T, C: Clock = SystemClock> {
+    /// States before every step, oldest first
+    undo: VecDeque<T>,
+    /// States after every undone step, most recently undone last
+    redo: Vec<T>,
+    depth: usize,
+    /// Gesture in progress, the state before it and the time it last changed the view
+    pending: Option<(Gesture, T, f64)>,
+    clock: C,
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    pub fn new(depth: usize) -> Self {
+        Self::with_clock(depth, SystemClock::default())
+    }
+}
+
+impl<T: Clone + PartialEq, C: Clock> History<T, C> {
+    pub fn with_clock(depth: usize, clock: C) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+            pending: None,
+            clock,
+        }
+    }
+
+    /// Notes that `gesture` is about to change the view at `current`. Continues the gesture in
+    /// progress if it's of the same kind and hasn't settled yet
+    pub fn navigate(&mut self, gesture: Gesture, current: &T) {
+        let now = self.clock.now();
+        match &mut self.pending {
+            Some((pending, _, last_ms))
+                if *pending == gesture
+                    && (gesture == Gesture::Drag || now - *last_ms < SETTLE_MS) =>
+            {
+                *last_ms = now;
+            }
+            _ => {
+                self.finish(current);
+                self.pending = Some((gesture, current.clone(), now));
+            }
+        }
+    }
+
+    /// Makes a step of an instant change, such as a jump to a location, from `current`
+    pub fn jump(&mut self, current: &T) {
+        self.finish(current);
+        self.push(current.clone());
+    }
+
+    /// Ends the gesture in progress, which left the view at `current`
+    pub fn finish(&mut self, current: &T) {
+        if let Some((_, before, _)) = self.pending.take() {
+            if before != *current {
+                self.push(before);
+            }
+        }
+    }
+
+    /// Returns the state before the last step, going back from `current`
+    pub fn undo(&mut self, current: &T) -> Option<T> {
+        self.finish(current);
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current.clone());
+        Some(previous)
+    }
+
+    /// Returns the state after the last undone step, going forward from `current`
+    pub fn redo(&mut self, current: &T) -> Option<T> {
+        self.finish(current);
+        let next = self.redo.pop()?;
+        self.undo.push_back(current.clone());
+        Some(next)
+    }
+
+    /// Drops the oldest steps past `depth`
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo.len() > depth {
+            self.undo.pop_front();
+        }
+    }
+
+    fn push(&mut self, before: T) {
+        self.redo.clear();
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(before);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::Coordinates;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Clock that only moves when told to
+    #[derive(Clone, Default)]
+    struct FakeClock(Rc<Cell<f64>>);
+
+    impl FakeClock {
+        fn advance(&self, ms: f64) {
+            self.0.set(self.0.get() + ms);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> f64 {
+            self.0.get()
+        }
+    }
+
+    /// Applies `gesture` to `coords`, recording it in `history`
+    fn change(
+        history: &mut History<Coordinates, FakeClock>,
+        gesture: Gesture,
+        coords: &mut Coordinates,
+    ) {
+        history.navigate(gesture, coords);
+        match gesture {
+            Gesture::Drag => coords.move_by_delta(3.0, -2.0).unwrap(),
+            Gesture::Zoom => {
+                coords.zoom_with_anchor(0.9, 400.0, 250.0, 1.0).unwrap();
+            }
+            Gesture::Rotate => coords.set_rotation(coords.rotation() + 15.0).unwrap(),
+        }
+    }
+
+    fn start() -> Coordinates {
+        Coordinates::new(-0.75, 0.1, 1.0 / 512.0, 10).unwrap()
+    }
+
+    #[test]
+    fn zoom_bursts_are_one_step() {
+        let clock = FakeClock::default();
+        let mut history = History::with_clock(DEFAULT_DEPTH, clock.clone());
+        let mut coords = start();
+        for _ in 0..20 {
+            change(&mut history, Gesture::Zoom, &mut coords);
+            clock.advance(100.0);
+        }
+        let burst = coords.clone();
+        clock.advance(SETTLE_MS);
+        change(&mut history, Gesture::Zoom, &mut coords);
+
+        assert_eq!(history.undo(&coords), Some(burst.clone()));
+        assert_eq!(history.undo(&burst), Some(start()));
+        assert_eq!(history.undo(&start()), None);
+    }
+
+    #[test]
+    fn drags_last_until_finished() {
+        let clock = FakeClock::default();
+        let mut history = History::with_clock(DEFAULT_DEPTH, clock.clone());
+        let mut coords = start();
+        for _ in 0..5 {
+            change(&mut history, Gesture::Drag, &mut coords);
+            clock.advance(SETTLE_MS * 2.0);
+        }
+        history.finish(&coords);
+        let dragged = coords.clone();
+        change(&mut history, Gesture::Drag, &mut coords);
+        // Changing the kind of gesture starts a new step as well
+        let second = coords.clone();
+        change(&mut history, Gesture::Rotate, &mut coords);
+
+        assert_eq!(history.undo(&coords), Some(second.clone()));
+        assert_eq!(history.undo(&second), Some(dragged.clone()));
+        assert_eq!(history.undo(&dragged), Some(start()));
+    }
+
+    #[test]
+    fn redo_follows_undo() {
+        let mut history = History::with_clock(DEFAULT_DEPTH, FakeClock::default());
+        let mut coords = start();
+        change(&mut history, Gesture::Drag, &mut coords);
+        history.finish(&coords);
+        let moved = coords.clone();
+        history.jump(&coords);
+        coords = Coordinates::new(0.25, 0.0, 1.0 / 1024.0, 10).unwrap();
+        let jumped = coords.clone();
+
+        assert_eq!(history.undo(&jumped), Some(moved.clone()));
+        assert_eq!(history.undo(&moved), Some(start()));
+        assert_eq!(history.redo(&start()), Some(moved.clone()));
+        assert_eq!(history.redo(&moved), Some(jumped.clone()));
+        assert_eq!(history.redo(&jumped), None);
+
+        // A new step drops the undone ones
+        assert_eq!(history.undo(&jumped), Some(moved.clone()));
+        coords = moved;
+        change(&mut history, Gesture::Zoom, &mut coords);
+        assert_eq!(history.redo(&coords), None);
+    }
+
+    #[test]
+    fn unchanged_views_and_old_steps_are_dropped() {
+        let mut history = History::with_clock(3, FakeClock::default());
+        let coords = start();
+        history.navigate(Gesture::Drag, &coords);
+        history.finish(&coords);
+        assert_eq!(history.undo(&coords), None);
+
+        let mut coords = start();
+        let mut steps = vec![];
+        for _ in 0..5 {
+            steps.push(coords.clone());
+            change(&mut history, Gesture::Drag, &mut coords);
+            history.finish(&coords);
+        }
+        for step in steps.iter().rev().take(3) {
+            assert_eq!(history.undo(&coords).as_ref(), Some(step));
+            coords = step.clone();
+        }
+        assert_eq!(history.undo(&coords), None);
+
+        for _ in 0..2 {
+            history.jump(&coords);
+        }
+        history.set_depth(1);
+        assert!(history.undo(&coords).is_some());
+        assert_eq!(history.undo(&coords), None);
+    }
+}
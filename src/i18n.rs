@@ -0,0 +1,485 @@
+//! Translations of the user interface. Strings are looked up by key in tables embedded at compile
+//! time, keys missing from a translation fall back to English
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+const EN: &[(&str, &str)] = &[
+    ("demo-hint", "Demo mode, press any key to exit"),
+    (
+        "rendering-degraded",
+        "Rendering fell back to lower precision: {}",
+    ),
+    ("retry", "Retry"),
+    ("dismiss", "Dismiss"),
+    ("split-view", "Split view"),
+    ("link-views", "Link views"),
+    ("settings-apply-left", "Settings apply to the left view"),
+    ("settings-apply-right", "Settings apply to the right view"),
+    ("depth", "Depth: {}/{}"),
+    ("inspected", "Clicked pixel: {} iterations"),
+    (
+        "inspected-inside",
+        "Clicked pixel: not escaped in {} iterations",
+    ),
+    ("inspected-settled", "Clicked pixel: inside the set"),
+    ("inspected-glitched", "Clicked pixel: being recalculated"),
+    ("zoom", "Zoom: 10^{}×"),
+    ("active-tiles", "Active tiles: {}/{}"),
+    ("settled", "Found inside the set: {}%"),
+    (
+        "settled-skipped",
+        "Found inside the set: {}%, {}% without iterating",
+    ),
+    ("iteration-rate", "Iterations per second: {}"),
+    (
+        "compiling-precision",
+        "Compiling the precision kernel for {} words…",
+    ),
+    ("surface-retries", "Surface retries: {}"),
+    ("cache-usage", "Cache: {} MiB"),
+    ("julia-preview", "Julia preview"),
+    ("escape-radius", "Escape radius: {}"),
+    ("distance-estimation", "Boundary lines (slower)"),
+    ("scale", "Scale: {}"),
+    ("target-fps", "Target frame rate: {} FPS"),
+    ("rotation", "Rotation: {}°"),
+    ("antialiasing", "Antialiasing (FXAA)"),
+    ("reprojection", "Show the last view while zooming"),
+    ("smooth-colors", "Smooth colors"),
+    ("equalize-colors", "Histogram equalization"),
+    ("gamma", "Display gamma: {}"),
+    ("palette", "Palette"),
+    ("custom-palette", "Custom"),
+    ("palette-stops", "Colors of the stops"),
+    ("deep-color", "Deep color (16-bit)"),
+    ("vsync", "Wait for the display (vsync)"),
+    ("power-preference", "GPU"),
+    ("power-low", "Power saving"),
+    ("power-high", "High performance"),
+    ("smooth-zoom", "Smooth zoom"),
+    ("zoom-sensitivity", "Zoom sensitivity: {}×"),
+    ("invert-zoom", "Invert zoom direction"),
+    ("pan-with", "Drag the view with"),
+    ("pan-left", "Left button"),
+    ("pan-middle", "Middle button"),
+    ("pan-space-left", "Space + left button"),
+    ("reset-position", "Reset position"),
+    ("undo", "Back"),
+    ("redo", "Forward"),
+    ("bookmarks", "Bookmarks"),
+    ("save-location", "Save location"),
+    ("bookmark-default-name", "Location {}"),
+    ("bookmark-name", "Name"),
+    ("rename", "Rename"),
+    ("delete", "Delete"),
+    ("mark-start", "From"),
+    ("mark-end", "To"),
+    ("done", "Done"),
+    ("demo-mode", "Demo mode"),
+    ("fractal", "Fractal"),
+    ("inverted-plane", "Inverted plane (1/c)"),
+    ("power", "Power of z: {}"),
+    ("julia-set", "Julia set"),
+    ("pick-julia-seed", "Pick Julia seed"),
+    ("copy-settings", "Copy settings string"),
+    ("export-png", "Export PNG (S)"),
+    ("poster-size", "Poster size (width × height)"),
+    ("export-poster", "Export poster"),
+    ("poster-progress", "Rendering poster: tile {} of {}"),
+    ("cancel-poster", "Cancel poster"),
+    ("record-zoom", "Record a zoom between bookmarks"),
+    ("recording-start", "From: {}"),
+    ("recording-end", "To: {}"),
+    ("recording-unmarked", "not marked"),
+    ("recording-frames", "Frames"),
+    ("recording-depth", "Depth"),
+    ("start-recording", "Record"),
+    ("encode-video", "Encode a video with ffmpeg"),
+    ("video-fps", "Frames per second"),
+    ("recording-progress", "Recording: frame {} of {}"),
+    ("cancel-recording", "Cancel recording"),
+    ("export-iterations", "Export iterations"),
+    ("apply-settings", "Apply settings string"),
+    ("advanced", "Advanced"),
+    ("hide-advanced", "Hide advanced"),
+    ("precision", "Precision: {} bits, {} words"),
+    ("manual-precision", "Manual precision"),
+    ("language", "Language"),
+    ("eta-done", "done"),
+    ("eta-under-second", "<1 s remaining"),
+    ("eta-seconds", "~{} s remaining"),
+    ("eta-minutes", "~{} m {} s remaining"),
+    ("eta-hours", "~{} h {} m remaining"),
+    ("notice-demo-stopped", "Demo stopped"),
+    ("notice-clipboard-empty", "Clipboard is empty"),
+    ("notice-location-copied", "Location copied"),
+    (
+        "notice-pick-julia-seed",
+        "Click the point to use as the Julia seed",
+    ),
+    ("notice-location-pasted", "Pasted {}"),
+    ("notice-settings-copied", "Settings copied"),
+    ("notice-frame-exported", "Saved {}"),
+    ("notice-poster-cancelled", "Poster cancelled"),
+    ("notice-recording-finished", "Recorded {} frames, saved {}"),
+    ("notice-recording-cancelled", "Recording cancelled"),
+    ("notice-iterations-resumed", "Resumed from the file at depth {}"),
+    (
+        "notice-iterations-mismatch",
+        "The file was calculated at another size or with other settings, only its location is shown",
+    ),
+    (
+        "notice-power-on-reload",
+        "The GPU is switched once the page is reloaded",
+    ),
+    ("notice-settings-applied", "Settings applied"),
+    ("notice-cache-saved", "Depth {} saved to the cache"),
+    ("notice-cache-resumed", "Resumed from the cache at depth {}"),
+    (
+        "notice-max-zoom",
+        "Maximum zoom, the numbers can't get any more precise",
+    ),
+    ("format-native", "location string"),
+    ("format-kfr", ".kfr location"),
+    ("format-pair", "\"re, im\" pair"),
+    ("format-url", "location URL"),
+    (
+        "warning-stalled",
+        "GPU work is taking unusually long to complete",
+    ),
+    (
+        "warning-lost",
+        "GPU work has never completed, restarting the calculation",
+    ),
+    (
+        "warning-device-lost",
+        "The GPU device was lost and has been recreated, restarting the calculation",
+    ),
+];
+
+const DE: &[(&str, &str)] = &[
+    (
+        "demo-hint",
+        "Demo-Modus, zum Beenden beliebige Taste drücken",
+    ),
+    (
+        "rendering-degraded",
+        "Darstellung auf geringere Genauigkeit zurückgefallen: {}",
+    ),
+    ("retry", "Erneut versuchen"),
+    ("dismiss", "Schließen"),
+    ("split-view", "Geteilte Ansicht"),
+    ("link-views", "Ansichten koppeln"),
+    (
+        "settings-apply-left",
+        "Einstellungen gelten für die linke Ansicht",
+    ),
+    (
+        "settings-apply-right",
+        "Einstellungen gelten für die rechte Ansicht",
+    ),
+    ("depth", "Tiefe: {}/{}"),
+    ("inspected", "Angeklickter Pixel: {} Iterationen"),
+    (
+        "inspected-inside",
+        "Angeklickter Pixel: nach {} Iterationen nicht entkommen",
+    ),
+    ("inspected-settled", "Angeklickter Pixel: in der Menge"),
+    (
+        "inspected-glitched",
+        "Angeklickter Pixel: wird neu berechnet",
+    ),
+    ("zoom", "Vergrößerung: 10^{}×"),
+    ("active-tiles", "Aktive Kacheln: {}/{}"),
+    ("settled", "In der Menge gefunden: {}%"),
+    (
+        "settled-skipped",
+        "In der Menge gefunden: {}%, davon {}% ohne Iteration",
+    ),
+    ("iteration-rate", "Iterationen pro Sekunde: {}"),
+    (
+        "compiling-precision",
+        "Präzisionskernel für {} Wörter wird kompiliert…",
+    ),
+    ("surface-retries", "Neuversuche der Oberfläche: {}"),
+    ("cache-usage", "Cache: {} MiB"),
+    ("julia-preview", "Julia-Vorschau"),
+    ("escape-radius", "Fluchtradius: {}"),
+    ("distance-estimation", "Randlinien (langsamer)"),
+    ("scale", "Skalierung: {}"),
+    ("target-fps", "Ziel-Bildrate: {} FPS"),
+    ("rotation", "Drehung: {}°"),
+    ("antialiasing", "Kantenglättung (FXAA)"),
+    ("reprojection", "Letzte Ansicht beim Zoomen zeigen"),
+    ("smooth-colors", "Weiche Farbverläufe"),
+    ("equalize-colors", "Histogrammausgleich"),
+    ("gamma", "Bildschirm-Gamma: {}"),
+    ("palette", "Farbpalette"),
+    ("custom-palette", "Eigene"),
+    ("palette-stops", "Farben der Stützpunkte"),
+    ("deep-color", "Hohe Farbtiefe (16 Bit)"),
+    ("vsync", "Auf den Bildschirm warten (VSync)"),
+    ("power-preference", "Grafikprozessor"),
+    ("power-low", "Energiesparend"),
+    ("power-high", "Hohe Leistung"),
+    ("smooth-zoom", "Sanftes Zoomen"),
+    ("zoom-sensitivity", "Zoom-Empfindlichkeit: {}×"),
+    ("invert-zoom", "Zoomrichtung umkehren"),
+    ("pan-with", "Ansicht verschieben mit"),
+    ("pan-left", "Linker Taste"),
+    ("pan-middle", "Mittlerer Taste"),
+    ("pan-space-left", "Leertaste + linker Taste"),
+    ("reset-position", "Position zurücksetzen"),
+    ("undo", "Zurück"),
+    ("redo", "Vorwärts"),
+    ("bookmarks", "Lesezeichen"),
+    ("save-location", "Position speichern"),
+    ("bookmark-default-name", "Position {}"),
+    ("bookmark-name", "Name"),
+    ("rename", "Umbenennen"),
+    ("delete", "Löschen"),
+    ("mark-start", "Von"),
+    ("mark-end", "Bis"),
+    ("done", "Fertig"),
+    ("demo-mode", "Demo-Modus"),
+    ("fractal", "Fraktal"),
+    ("inverted-plane", "Invertierte Ebene (1/c)"),
+    ("power", "Potenz von z: {}"),
+    ("julia-set", "Julia-Menge"),
+    ("pick-julia-seed", "Julia-Parameter wählen"),
+    ("copy-settings", "Einstellungen kopieren"),
+    ("export-png", "Als PNG exportieren (S)"),
+    ("poster-size", "Postergröße (Breite × Höhe)"),
+    ("export-poster", "Poster exportieren"),
+    ("poster-progress", "Poster wird berechnet: Kachel {} von {}"),
+    ("cancel-poster", "Poster abbrechen"),
+    ("record-zoom", "Zoom zwischen Lesezeichen aufnehmen"),
+    ("recording-start", "Von: {}"),
+    ("recording-end", "Bis: {}"),
+    ("recording-unmarked", "nicht markiert"),
+    ("recording-frames", "Bilder"),
+    ("recording-depth", "Tiefe"),
+    ("start-recording", "Aufnehmen"),
+    ("encode-video", "Video mit ffmpeg kodieren"),
+    ("video-fps", "Bilder pro Sekunde"),
+    ("recording-progress", "Aufnahme: Bild {} von {}"),
+    ("cancel-recording", "Aufnahme abbrechen"),
+    ("export-iterations", "Iterationen exportieren"),
+    ("apply-settings", "Einstellungen einfügen"),
+    ("advanced", "Erweitert"),
+    ("hide-advanced", "Erweitert ausblenden"),
+    ("precision", "Genauigkeit: {} Bit, {} Wörter"),
+    ("manual-precision", "Manuelle Genauigkeit"),
+    ("language", "Sprache"),
+    ("eta-done", "fertig"),
+    ("eta-under-second", "<1 s verbleibend"),
+    ("eta-seconds", "~{} s verbleibend"),
+    ("eta-minutes", "~{} min {} s verbleibend"),
+    ("eta-hours", "~{} h {} min verbleibend"),
+    ("notice-demo-stopped", "Demo beendet"),
+    ("notice-clipboard-empty", "Zwischenablage ist leer"),
+    ("notice-location-copied", "Position kopiert"),
+    (
+        "notice-pick-julia-seed",
+        "Punkt für den Julia-Parameter anklicken",
+    ),
+    ("notice-location-pasted", "Eingefügt: {}"),
+    ("notice-settings-copied", "Einstellungen kopiert"),
+    ("notice-frame-exported", "Gespeichert: {}"),
+    ("notice-poster-cancelled", "Poster abgebrochen"),
+    ("notice-recording-finished", "{} Bilder aufgenommen, gespeichert unter {}"),
+    ("notice-recording-cancelled", "Aufnahme abgebrochen"),
+    ("notice-iterations-resumed", "Aus der Datei bei Tiefe {} fortgesetzt"),
+    (
+        "notice-iterations-mismatch",
+        "Die Datei wurde in einer anderen Größe oder mit anderen Einstellungen berechnet, nur ihre Position wird gezeigt",
+    ),
+    (
+        "notice-power-on-reload",
+        "Der Grafikprozessor wird nach dem Neuladen der Seite gewechselt",
+    ),
+    ("notice-settings-applied", "Einstellungen übernommen"),
+    ("notice-cache-saved", "Tiefe {} im Cache gespeichert"),
+    (
+        "notice-cache-resumed",
+        "Aus dem Cache bei Tiefe {} fortgesetzt",
+    ),
+    (
+        "notice-max-zoom",
+        "Maximale Vergrößerung, genauere Zahlen sind nicht möglich",
+    ),
+    ("format-native", "Positionsangabe"),
+    ("format-kfr", ".kfr-Position"),
+    ("format-pair", "\"re, im\"-Paar"),
+    ("format-url", "Positions-URL"),
+    ("warning-stalled", "GPU-Arbeit dauert ungewöhnlich lange"),
+    (
+        "warning-lost",
+        "GPU-Arbeit wurde nie abgeschlossen, die Berechnung beginnt neu",
+    ),
+    (
+        "warning-device-lost",
+        "Das GPU-Gerät ging verloren und wurde neu erstellt, die Berechnung beginnt neu",
+    ),
+];
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+        }
+    }
+
+    /// Parses a language code or a locale name such as `de_DE.UTF-8` or `de-AT`
+    pub fn from_code(code: &str) -> Option<Self> {
+        let primary = code
+            .split(['_', '-', '.', '@'])
+            .next()?
+            .to_ascii_lowercase();
+        Self::ALL.into_iter().find(|lang| lang.code() == primary)
+    }
+
+    /// Language of the system, English if it's unsupported
+    pub fn detect() -> Self {
+        system_locale()
+            .and_then(|locale| Self::from_code(&locale))
+            .unwrap_or_default()
+    }
+
+    fn table(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => EN,
+            Language::German => DE,
+        }
+    }
+
+    /// Returns the string for `key`. Keys without any translation are returned as is
+    pub fn tr(self, key: &'static str) -> &'static str {
+        translate(self.table(), key)
+    }
+
+    /// Returns the string for `key` with its `{}` placeholders replaced by `args` in order
+    pub fn format(self, key: &'static str, args: &[&dyn fmt::Display]) -> String {
+        let mut parts = self.tr(key).split("{}");
+        let mut out = parts.next().unwrap_or_default().to_owned();
+        let mut args = args.iter();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                out.push_str(&arg.to_string());
+            }
+            out.push_str(part);
+        }
+        out
+    }
+
+    /// Formats `value` with `decimals` fraction digits and the decimal separator of the language
+    pub fn number(self, value: f64, decimals: usize) -> String {
+        let text = format!("{:.*}", decimals, value);
+        match self {
+            Language::English => text,
+            Language::German => text.replace('.', ","),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Every language is listed under its own name
+        f.write_str(match self {
+            Language::English => "English",
+            Language::German => "Deutsch",
+        })
+    }
+}
+
+fn translate(table: &[(&str, &'static str)], key: &'static str) -> &'static str {
+    lookup(table, key)
+        .or_else(|| lookup(EN, key))
+        .unwrap_or(key)
+}
+
+fn lookup(table: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    table
+        .iter()
+        .find_map(|&(k, value)| (k == key).then_some(value))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+}
+
+#[cfg(target_arch = "wasm32")]
+fn system_locale() -> Option<String> {
+    web_sys::window()?.navigator().language()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locales_are_parsed() {
+        assert_eq!(Language::from_code("de"), Some(Language::German));
+        assert_eq!(Language::from_code("de_DE.UTF-8"), Some(Language::German));
+        assert_eq!(Language::from_code("DE-at"), Some(Language::German));
+        assert_eq!(Language::from_code("en_US"), Some(Language::English));
+        assert_eq!(Language::from_code("fr_FR"), None);
+        assert_eq!(Language::from_code(""), None);
+    }
+
+    #[test]
+    fn translations_match_english() {
+        for lang in Language::ALL {
+            for (key, value) in lang.table() {
+                let english = lookup(EN, key).unwrap_or_else(|| panic!("{} is unknown", key));
+                assert_eq!(
+                    value.matches("{}").count(),
+                    english.matches("{}").count(),
+                    "{} has different placeholders in {}",
+                    key,
+                    lang
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn missing_keys_fall_back() {
+        assert_eq!(Language::German.tr("retry"), "Erneut versuchen");
+        let partial = &[("dismiss", "Schließen")];
+        assert_eq!(translate(partial, "dismiss"), "Schließen");
+        assert_eq!(translate(partial, "retry"), "Retry");
+        assert_eq!(Language::German.tr("no-such-key"), "no-such-key");
+    }
+
+    #[test]
+    fn placeholders_are_filled() {
+        assert_eq!(
+            Language::English.format("depth", &[&10, &500]),
+            "Depth: 10/500"
+        );
+        assert_eq!(Language::German.format("depth", &[&10]), "Tiefe: 10/");
+        assert_eq!(Language::English.format("retry", &[&1]), "Retry");
+    }
+
+    #[test]
+    fn numbers_use_the_decimal_separator() {
+        assert_eq!(Language::English.number(1.5, 2), "1.50");
+        assert_eq!(Language::German.number(1.5, 2), "1,50");
+        assert_eq!(Language::German.number(1999.125, 1), "1999,1");
+    }
+}
@@ -0,0 +1,226 @@
+//! Raw iteration counts of a view saved to a file for other tools, and loaded back to show the
+//! same location and resume its calculation.
+//!
+//! Files start with the magic and the format version, followed by a header of little-endian
+//! u32: width, height, depth, word count and extra precision bits. Then come the rotation as an
+//! f32 and the words of x, y and the step of the coordinates. The counts of the pixels follow,
+//! row by row as little-endian u32, so that other tools can skip the header and read them. The
+//! state to resume the calculation from is optional and comes last.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::float::WideFloat;
+use crate::gpu::{Iterations, Snapshot, SnapshotKey};
+use crate::primitives::{Coordinates, ScaledDimensions};
+use crate::screenshot;
+
+/// Version of the file layout, bump this when it changes
+const FORMAT_VERSION: u32 = 1;
+const MAGIC: &[u8; 4] = b"MBIT";
+pub const EXTENSION: &str = "mbit";
+
+#[derive(Debug, Error)]
+pub enum IterationsError {
+    #[error("Iterations file I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Iterations file is corrupt or made by another version")]
+    Format,
+    #[error("The view has no complete frame to export yet")]
+    Unavailable,
+}
+
+pub fn encode(iterations: &Iterations) -> Vec<u8> {
+    let coords = &iterations.coords;
+    let mut out = Vec::with_capacity(32 + 4 * iterations.counts.len());
+    out.extend_from_slice(MAGIC);
+    for value in [
+        FORMAT_VERSION,
+        iterations.size.width,
+        iterations.size.height,
+        iterations.depth,
+        coords.size() as u32,
+        coords.precision() as u32,
+    ] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&coords.rotation().to_le_bytes());
+    for number in [&coords.x, &coords.y, &coords.step] {
+        out.extend_from_slice(number.as_bytes());
+    }
+    for count in &iterations.counts {
+        out.extend_from_slice(&count.to_le_bytes());
+    }
+    if let Some(snapshot) = &iterations.resume {
+        let key = snapshot.key.as_bytes();
+        out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        out.extend_from_slice(key);
+        out.extend_from_slice(&(snapshot.iterations.len() as u64).to_le_bytes());
+        out.extend_from_slice(&snapshot.iterations);
+        out.extend_from_slice(&snapshot.intermediate);
+    }
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Iterations, IterationsError> {
+    let mut rest = bytes;
+    if take(&mut rest, 4)? != MAGIC || take_u32(&mut rest)? != FORMAT_VERSION {
+        return Err(IterationsError::Format);
+    }
+    let size = ScaledDimensions {
+        width: take_u32(&mut rest)?,
+        height: take_u32(&mut rest)?,
+    };
+    let depth = take_u32(&mut rest)?;
+    let word_count = take_u32(&mut rest)? as usize;
+    let precision = take_u32(&mut rest)? as usize;
+    let rotation = f32::from_bits(take_u32(&mut rest)?);
+
+    let mut number = || {
+        let words = take(&mut rest, 4 * word_count)?
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        Ok::<_, IterationsError>(WideFloat::from_words(words))
+    };
+    let (x, y, step) = (number()?, number()?, number()?);
+    let mut coords =
+        Coordinates::from_parts(x, y, step, precision).map_err(|_| IterationsError::Format)?;
+    coords
+        .set_rotation(rotation)
+        .map_err(|_| IterationsError::Format)?;
+
+    let pixels = size.width as usize * size.height as usize;
+    let counts = take(&mut rest, 4 * pixels)?
+        .chunks_exact(4)
+        .map(|count| u32::from_le_bytes(count.try_into().unwrap()))
+        .collect();
+
+    let resume = if rest.is_empty() {
+        None
+    } else {
+        let key_len = take_u32(&mut rest)? as usize;
+        let key = SnapshotKey::from_bytes(take(&mut rest, key_len)?.to_vec());
+        let iterations_len = u64::from_le_bytes(take(&mut rest, 8)?.try_into().unwrap());
+        let iterations = take(&mut rest, iterations_len as usize)?.to_vec();
+        Some(Snapshot {
+            key,
+            depth,
+            iterations,
+            intermediate: rest.to_vec(),
+        })
+    };
+    Ok(Iterations {
+        size,
+        coords,
+        depth,
+        counts,
+        resume,
+    })
+}
+
+/// Returns the first `len` bytes of `rest` and moves past them
+fn take<'b>(rest: &mut &'b [u8], len: usize) -> Result<&'b [u8], IterationsError> {
+    let (taken, remaining) = rest.split_at_checked(len).ok_or(IterationsError::Format)?;
+    *rest = remaining;
+    Ok(taken)
+}
+
+fn take_u32(rest: &mut &[u8]) -> Result<u32, IterationsError> {
+    Ok(u32::from_le_bytes(take(rest, 4)?.try_into().unwrap()))
+}
+
+/// Saves `iterations` to the documents directory, or the current one without it, under a name of
+/// the current time. Returns where they were saved
+pub fn export(iterations: &Iterations) -> Result<String, IterationsError> {
+    let unix_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let path = dirs::document_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(screenshot::file_name(unix_secs, EXTENSION));
+    std::fs::write(&path, encode(iterations))?;
+    Ok(path.display().to_string())
+}
+
+/// Returns true if `path` names an iterations file
+pub fn is_iterations_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == EXTENSION)
+}
+
+pub fn import(path: &Path) -> Result<Iterations, IterationsError> {
+    decode(&std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::{FractalParams, IterationParams};
+    use crate::gpu::HeadlessContext;
+    use crate::primitives::Dimensions;
+
+    #[test]
+    fn rendered_counts_round_trip() {
+        let mut context = match pollster::block_on(HeadlessContext::new()) {
+            Ok(context) => context,
+            Err(e) => {
+                eprintln!("Skipping the iterations round trip: {e}");
+                return;
+            }
+        };
+        let dimensions = Dimensions {
+            width: 70,
+            height: 30,
+        };
+        let mut coords = Coordinates::new(-1.5, -0.5, 1.0 / 32.0, 10).unwrap();
+        coords.set_rotation(30.0).unwrap();
+        coords.set_word_count(3);
+        let counts = context
+            .iterations_to_vec(
+                &coords,
+                FractalParams::default(),
+                IterationParams::default(),
+                dimensions,
+                200,
+            )
+            .unwrap();
+        let iterations = Iterations {
+            size: dimensions.scale_to(1.0),
+            coords,
+            depth: 200,
+            counts,
+            resume: None,
+        };
+        let encoded = encode(&iterations);
+        assert_eq!(decode(&encoded).unwrap(), iterations);
+        // Other tools find the counts right after the header
+        let header = encoded.len() - 4 * iterations.counts.len();
+        assert_eq!(header, 32 + 3 * 4 * 3);
+        assert_eq!(
+            encoded[header..header + 4],
+            iterations.counts[0].to_le_bytes()
+        );
+
+        let resumed = Iterations {
+            resume: Some(Snapshot {
+                key: SnapshotKey::from_bytes(b"key".to_vec()),
+                depth: 200,
+                iterations: vec![1; 8],
+                intermediate: vec![2; 24],
+            }),
+            ..iterations
+        };
+        let encoded = encode(&resumed);
+        assert_eq!(decode(&encoded).unwrap(), resumed);
+        assert!(matches!(
+            decode(&encoded[..encoded.len() - 40]),
+            Err(IterationsError::Format)
+        ));
+        assert!(decode(&encoded[..header - 1]).is_err());
+        let mut newer = encoded.clone();
+        newer[4] += 1;
+        assert!(matches!(decode(&newer), Err(IterationsError::Format)));
+    }
+}
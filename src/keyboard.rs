@@ -0,0 +1,198 @@
+//! Keyboard navigation. A key press moves the view by a step at once, holding the key keeps it
+//! moving smoothly every frame instead of jumping with the key repeats of the system
+
+use winit::keyboard::{Key, KeyCode};
+
+/// Pan of an arrow key press, in fractions of the view size
+const PAN_STEP: f32 = 0.1;
+/// Pan of an arrow key press with Shift
+const FAST_PAN_STEP: f32 = 0.5;
+/// Magnification of a zoom key press
+const ZOOM_STEP: f32 = 1.5;
+/// Time a key has to be held before it moves the view continuously, in milliseconds
+const HOLD_DELAY_MS: f64 = 250.0;
+/// Time a held key takes to move the view by a step, in milliseconds
+const STEP_MS: f64 = 100.0;
+
+/// Movement of the view
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Motion {
+    /// Screen delta in fractions of the view size, like a drag
+    pub pan: [f32; 2],
+    /// Natural logarithm of the magnification, positive to zoom in
+    pub zoom: f32,
+}
+
+impl Motion {
+    /// Returns the motion of the key press, if the key navigates
+    pub fn of_key(code: KeyCode, key: &Key, shift: bool) -> Option<Self> {
+        let step = if shift { FAST_PAN_STEP } else { PAN_STEP };
+        let pan = |x: f32, y: f32| Self {
+            pan: [x * step, y * step],
+            zoom: 0.0,
+        };
+        let zoom = |sign: f32| Self {
+            pan: [0.0, 0.0],
+            zoom: sign * ZOOM_STEP.ln(),
+        };
+        // Looking to the right drags the plane to the left
+        match code {
+            KeyCode::ArrowLeft => return Some(pan(1.0, 0.0)),
+            KeyCode::ArrowRight => return Some(pan(-1.0, 0.0)),
+            KeyCode::ArrowUp => return Some(pan(0.0, 1.0)),
+            KeyCode::ArrowDown => return Some(pan(0.0, -1.0)),
+            KeyCode::NumpadAdd => return Some(zoom(1.0)),
+            KeyCode::NumpadSubtract => return Some(zoom(-1.0)),
+            _ => {}
+        }
+        // Zoom keys are wherever the layout puts the characters
+        match key {
+            Key::Character(c) if c == "+" || c == "=" => Some(zoom(1.0)),
+            Key::Character(c) if c == "-" => Some(zoom(-1.0)),
+            _ => None,
+        }
+    }
+
+    /// Returns the delta of [`crate::view_state::ViewState::zoom_with_anchor`] of the zoom
+    pub fn zoom_delta(&self) -> f32 {
+        if self.zoom > 0.0 {
+            self.zoom.exp() - 1.0
+        } else {
+            1.0 - (-self.zoom).exp()
+        }
+    }
+
+    fn scaled(self, factor: f32) -> Self {
+        Self {
+            pan: [self.pan[0] * factor, self.pan[1] * factor],
+            zoom: self.zoom * factor,
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            pan: [self.pan[0] + other.pan[0], self.pan[1] + other.pan[1]],
+            zoom: self.zoom + other.zoom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HeldKey {
+    code: KeyCode,
+    motion: Motion,
+    pressed_ms: f64,
+}
+
+/// Navigation keys held down
+#[derive(Debug, Default)]
+pub struct KeyNavigation {
+    held: Vec<HeldKey>,
+    /// Time the held keys last moved the view
+    last_frame_ms: f64,
+}
+
+impl KeyNavigation {
+    /// Handles a key press at `now_ms`. Returns the step to move the view by, none for repeats
+    /// of a key that's held already
+    pub fn press(&mut self, code: KeyCode, motion: Motion, now_ms: f64) -> Option<Motion> {
+        if self.held.iter().any(|key| key.code == code) {
+            return None;
+        }
+        if self.held.is_empty() {
+            self.last_frame_ms = now_ms;
+        }
+        self.held.push(HeldKey {
+            code,
+            motion,
+            pressed_ms: now_ms,
+        });
+        Some(motion)
+    }
+
+    /// Handles a key release. Returns true if the key was held
+    pub fn release(&mut self, code: KeyCode) -> bool {
+        let held = self.held.len();
+        self.held.retain(|key| key.code != code);
+        self.held.len() != held
+    }
+
+    /// Releases every key, for when the key releases can't be seen
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+
+    pub fn is_held(&self) -> bool {
+        !self.held.is_empty()
+    }
+
+    /// Returns the motion of the held keys since the last frame, none if nothing moves yet
+    pub fn frame(&mut self, now_ms: f64) -> Option<Motion> {
+        let since = self.last_frame_ms;
+        self.last_frame_ms = now_ms;
+        self.held
+            .iter()
+            .filter_map(|key| {
+                let start = since.max(key.pressed_ms + HOLD_DELAY_MS);
+                (now_ms > start).then(|| key.motion.scaled(((now_ms - start) / STEP_MS) as f32))
+            })
+            .reduce(Motion::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::keyboard::NamedKey;
+
+    fn right() -> Motion {
+        Motion::of_key(
+            KeyCode::ArrowRight,
+            &Key::Named(NamedKey::ArrowRight),
+            false,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn keys_are_mapped() {
+        assert_eq!(right().pan, [-PAN_STEP, 0.0]);
+        let fast = Motion::of_key(KeyCode::ArrowUp, &Key::Named(NamedKey::ArrowUp), true).unwrap();
+        assert_eq!(fast.pan, [0.0, FAST_PAN_STEP]);
+
+        let plus = Motion::of_key(KeyCode::BracketRight, &Key::Character("+".into()), false);
+        let equal = Motion::of_key(KeyCode::Equal, &Key::Character("=".into()), false);
+        assert_eq!(plus, equal);
+        assert!(plus.unwrap().zoom > 0.0);
+        let minus =
+            Motion::of_key(KeyCode::NumpadSubtract, &Key::Character("-".into()), false).unwrap();
+        assert!(minus.zoom < 0.0);
+        // Zooming in and out by a step cancel out
+        let (zoom_in, zoom_out) = (plus.unwrap().zoom_delta(), minus.zoom_delta());
+        assert!((1.0 / (1.0 + zoom_in) * (1.0 - zoom_out) - 1.0).abs() < 1e-6);
+
+        assert_eq!(
+            Motion::of_key(KeyCode::KeyA, &Key::Character("a".into()), false),
+            None
+        );
+    }
+
+    #[test]
+    fn held_keys_move_every_frame() {
+        let mut keys = KeyNavigation::default();
+        assert_eq!(keys.press(KeyCode::ArrowRight, right(), 0.0), Some(right()));
+        // Key repeats of the system don't move the view again
+        assert_eq!(keys.press(KeyCode::ArrowRight, right(), 30.0), None);
+        assert_eq!(keys.frame(100.0), None);
+
+        let frame = keys.frame(HOLD_DELAY_MS + STEP_MS / 2.0).unwrap();
+        assert!((frame.pan[0] + PAN_STEP / 2.0).abs() < 1e-6);
+        let frame = keys.frame(HOLD_DELAY_MS + STEP_MS).unwrap();
+        assert!((frame.pan[0] + PAN_STEP / 2.0).abs() < 1e-6);
+
+        assert!(keys.release(KeyCode::ArrowRight));
+        assert!(!keys.release(KeyCode::ArrowRight));
+        assert!(!keys.is_held());
+        assert_eq!(keys.frame(1000.0), None);
+    }
+}
@@ -1,44 +1,221 @@
-#![feature(bigint_helper_methods)]
-
 use iced_winit::core as iced_core;
 use iced_winit::runtime as iced_runtime;
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::sync::Mutex;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 use winit::{
     event::*,
-    event_loop::{ControlFlow, EventLoopBuilder},
-    keyboard::{KeyCode, PhysicalKey},
-    window::WindowBuilder,
+    event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+    window::{CursorGrabMode, WindowBuilder},
 };
 
+mod bookmarks;
+#[cfg(not(target_arch = "wasm32"))]
+mod cache;
+mod calibrations;
+mod cli;
 mod defaults;
+mod demo;
+mod double_click;
+mod eta;
 mod float;
 mod fps_balancer;
+mod fractal;
 mod gpu;
+mod history;
+mod i18n;
+#[cfg(not(target_arch = "wasm32"))]
+mod iterations;
+mod keyboard;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod link;
+mod location;
 mod overlay;
+mod palette;
+mod pan;
 mod primitives;
+mod recording;
+mod screenshot;
+mod scroll;
+mod session;
+mod settings;
 mod timer;
+mod touch;
+#[cfg(not(target_arch = "wasm32"))]
+mod video;
 mod view_state;
+mod views;
+
+use crate::double_click::DoubleClick;
+use crate::fps_balancer::FpsBalancer;
+use crate::gpu::{GpuContext, PowerPreference, Watchdog};
+use crate::i18n::Language;
+use crate::keyboard::{KeyNavigation, Motion};
+use crate::pan::PanBinding;
+use crate::primitives::Point;
+use crate::scroll::{ScrollCalibration, ScrollZoom};
+use crate::timer::{Clock, SystemClock};
+use crate::touch::{TouchMotion, Touches};
+use crate::view_state::{Precision, ViewState};
+use crate::views::Views;
+
+pub use crate::fractal::{FractalKind, FractalParams, IterationParams, JuliaSeed};
+pub use crate::gpu::{ColorParams, ContextCreationError, HeadlessContext, SETTLED};
+pub use crate::palette::{Palette, Preset, Stop};
+pub use crate::primitives::{
+    Coordinates, Dimensions, UnsupportedWordCount, MAX_WORD_COUNT, MIN_WORD_COUNT,
+};
 
-use crate::gpu::GpuContext;
-use crate::primitives::{Dimensions, Point};
-use crate::view_state::ViewState;
+/// Rotation of a Q or E key press in degrees
+const ROTATION_KEY_STEP: f32 = 15.0;
+/// Time between frames of animated zooms
+const ANIMATION_FRAME: std::time::Duration = std::time::Duration::from_millis(16);
 
 #[derive(Debug, Default)]
 struct InputState {
     modifiers: winit::keyboard::ModifiersState,
     pointer: Option<Point>,
-    grab: HashSet<DeviceId>,
+    /// Devices dragging the view and the binding that started each drag
+    grab: HashMap<DeviceId, PanBinding>,
+    /// Space is held, which makes the left button drag with [`PanBinding::SpaceLeft`]
+    space_held: bool,
+    /// The cursor is grabbed for a drag, which then follows raw mouse motion
+    cursor_grabbed: bool,
+    /// Window point a Shift drag started at, the view zooms to the selection on release
+    selection: Option<Point>,
+    clicks: DoubleClick,
+    /// View that takes the point of the next left click as its Julia seed
+    seed_target: Option<usize>,
+    keys: KeyNavigation,
+    touches: Touches,
+}
+
+/// Decides when frames are presented. Nothing is rendered while the window is fully covered, and
+/// the calculation doesn't go deeper while it's unfocused. The work already submitted still
+/// completes, so the depth is kept as it was when the window is shown or focused again
+#[derive(Debug, Default)]
+struct Presenter {
+    occluded: bool,
+    unfocused: bool,
+    /// A frame was requested while occluded
+    deferred: bool,
+    /// The calculation stopped going deeper while paused
+    stalled: bool,
+    /// The views have stopped changing and their last frame was shown
+    idle: bool,
+}
+
+impl Presenter {
+    /// Returns true if a requested frame should be rendered, otherwise it's deferred until the
+    /// window is visible
+    fn render(&mut self) -> bool {
+        self.deferred |= self.occluded;
+        !self.occluded
+    }
+
+    /// Returns true if the calculation should go on with the next frame, otherwise it's stalled
+    /// until the window is visible and focused
+    fn iterate(&mut self) -> bool {
+        let paused = self.is_paused();
+        self.stalled |= paused;
+        !paused
+    }
+
+    /// Returns true if another frame should follow the completed one. Frames go on while the views
+    /// `change`, and once more after they stop, to show the final statistics
+    fn follow(&mut self, change: bool) -> bool {
+        let idle = std::mem::replace(&mut self.idle, !change);
+        change || !idle
+    }
+
+    fn is_paused(&self) -> bool {
+        self.occluded || self.unfocused
+    }
+
+    /// Handles the window getting covered or uncovered. Returns true if the deferred frame has to
+    /// be requested again
+    fn set_occluded(&mut self, occluded: bool) -> bool {
+        self.occluded = occluded;
+        self.resume()
+    }
+
+    /// Handles the window getting focused or unfocused. Returns true if the stalled frame has to
+    /// be requested again
+    fn set_focused(&mut self, focused: bool) -> bool {
+        self.unfocused = !focused;
+        self.resume()
+    }
+
+    fn resume(&mut self) -> bool {
+        let deferred = !self.occluded && std::mem::take(&mut self.deferred);
+        let stalled = !self.is_paused() && std::mem::take(&mut self.stalled);
+        deferred || stalled
+    }
 }
 
 #[derive(Debug)]
 enum UserEvent {
     RenderNeedsPolling,
     ViewScaleFactorChanged(f64),
+    TargetFpsChanged(f64),
     PositionReset,
-    PrecisionChanged(usize),
+    Undo,
+    Redo,
+    PrecisionChanged(Precision),
+    RotationChanged(f32),
     MaxDepthChanged(u32),
+    IterationChanged(fractal::IterationParams),
+    FractalChanged(fractal::FractalParams),
+    RetryRendering,
+    /// A compute pipeline compiled in the background can be received
+    PipelineCompiled,
+    SplitToggled(bool),
+    LinkToggled(bool),
+    JuliaPreviewToggled(bool),
+    /// The active view switches between the Mandelbrot set and the Julia set of its center
+    JuliaToggled(bool),
+    /// The next left click picks the Julia seed of the active view
+    JuliaSeedPicking,
+    AntialiasingToggled(bool),
+    ReprojectionToggled(bool),
+    ColorsChanged(gpu::ColorParams),
+    PaletteChanged(palette::Palette),
+    DeepColorToggled(bool),
+    VsyncToggled(bool),
+    PowerPreferenceChanged(PowerPreference),
+    SmoothZoomToggled(bool),
+    PanBindingChanged(PanBinding),
+    ScrollCalibrated(ScrollCalibration),
+    SettingsCopied(settings::Settings),
+    SettingsPasted,
+    /// The control panel or the S key asks for a PNG of the active view
+    ExportRequested,
+    /// The control panel asks for a poster of the active view at a size of its own
+    PosterRequested(Dimensions),
+    PosterCancelled,
+    /// Where the PNG was saved, or why it wasn't
+    FrameExported(Result<String, screenshot::ExportError>),
+    /// The control panel asks for a zoom between two bookmarks to be recorded
+    RecordingRequested(Box<recording::Request>),
+    RecordingCancelled,
+    /// Where the image of the frame of the recording was saved, or why it wasn't
+    FrameRecorded(Result<String, screenshot::ExportError>),
+    DemoStarted,
+    LanguageChanged(Language),
+    /// The control panel asks for a bookmark of the active view
+    BookmarkRequested,
+    JumpTo(Box<bookmarks::Bookmark>),
+    BookmarksChanged(Vec<bookmarks::Bookmark>),
+    #[cfg(not(target_arch = "wasm32"))]
+    CacheResponded(cache::Response),
+    /// The control panel asks for the iteration counts of the active view
+    #[cfg(not(target_arch = "wasm32"))]
+    IterationsExportRequested,
+    /// Where the iteration counts were saved, or why they weren't
+    #[cfg(not(target_arch = "wasm32"))]
+    IterationsExported(Result<String, String>),
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
@@ -53,6 +230,35 @@ pub async fn run() {
         console_log::init().expect("could not initialize logger");
     }
 
+    #[cfg(target_arch = "wasm32")]
+    let args = cli::page_args();
+    #[cfg(not(target_arch = "wasm32"))]
+    let args = match cli::Args::parse(std::env::args().skip(1)) {
+        Ok(args) if args.help => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let playlist = match &args.demo_playlist {
+        Some(path) => match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| demo::parse(&text).map_err(|e| e.to_string()))
+        {
+            Ok(playlist) => playlist,
+            Err(e) => {
+                eprintln!("Invalid playlist {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        },
+        None => demo::showcase(),
+    };
+
     let event_loop = EventLoopBuilder::<UserEvent>::with_user_event()
         .build()
         .unwrap();
@@ -60,9 +266,53 @@ pub async fn run() {
 
     let event_loop_proxy = event_loop.create_proxy();
 
+    // Resume the last session unless asked not to
+    let session = if args.fresh { None } else { session::load() };
+    // The language chosen in the settings is remembered, the one given on the command line isn't
+    let mut chosen_language = session.as_ref().and_then(|session| session.language);
+    let mut language = args
+        .lang
+        .or(chosen_language)
+        .unwrap_or_else(Language::detect);
+    let mut scroll = ScrollZoom::new(
+        session
+            .as_ref()
+            .map(|session| session.scroll)
+            .unwrap_or_default(),
+    );
+    let mut pan_binding = session
+        .as_ref()
+        .map(|session| session.pan_binding)
+        .unwrap_or_default();
+    let display = session
+        .as_ref()
+        .map(|session| session.display)
+        .unwrap_or_default();
+
     #[allow(unused_mut)]
     let mut builder = WindowBuilder::new();
 
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(session) = &session {
+        builder = builder
+            .with_inner_size(winit::dpi::PhysicalSize::new(
+                session.window.width,
+                session.window.height,
+            ))
+            .with_maximized(session.window.maximized);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some((width, height)) = args.window_size {
+            builder = builder
+                .with_inner_size(winit::dpi::PhysicalSize::new(width, height))
+                .with_maximized(false);
+        }
+        if args.fullscreen {
+            builder = builder.with_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+        }
+    }
+
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen::JsCast;
@@ -79,24 +329,90 @@ pub async fn run() {
     }
     let window = builder.with_title("Mandelbrot").build(&event_loop).unwrap();
 
+    let mut max_depth = session
+        .as_ref()
+        .map_or(defaults::MAX_DEPTH, |session| session.max_depth);
+    #[allow(unused_mut)]
+    let mut colors = session
+        .as_ref()
+        .map(|session| session.colors)
+        .unwrap_or_default();
     let mut view_state = {
         let window_size = window.inner_size();
-        ViewState::default(
-            Dimensions::new_nonzero(window_size.width, window_size.height),
-            window.scale_factor(),
-            defaults::PRECISION_BITS,
-        )
+        let dimensions = Dimensions::new_nonzero(window_size.width, window_size.height);
+        match session {
+            Some(session) => ViewState::restore(
+                dimensions,
+                session.scale_factor,
+                session.coords,
+                session.fractal,
+                session.precision,
+            ),
+            None => ViewState::default(dimensions, window.scale_factor(), Precision::Auto),
+        }
     };
+    // Pages opened with a link show its view instead of the last session
+    #[cfg(target_arch = "wasm32")]
+    if let Some(linked) = link::read() {
+        match view_state.jump_to_bookmark(&linked.bookmark) {
+            Ok(()) => {
+                max_depth = linked.bookmark.max_depth;
+                colors = linked.colors;
+            }
+            Err(e) => log::warn!("Ignoring the link: {}", e),
+        }
+    }
+    // Options override both the last session and the link
+    if let Some(depth) = args.max_depth {
+        max_depth = depth;
+    }
+    if let Some(precision) = args.precision {
+        view_state.set_precision(precision);
+    }
+    if let Some(location) = args.position.location(&view_state) {
+        if let Err(e) = view_state.jump_to(&location) {
+            #[cfg(target_arch = "wasm32")]
+            log::error!("Ignoring the start position: {}", e);
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                eprintln!("Invalid start position: {}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+    let mut autosave = session::Autosave::new();
 
     let mut input_state = InputState::default();
+    let mut presenter = Presenter::default();
+
+    // Deterministic mode calculates a fixed amount of iterations per frame instead of balancing
+    // them by frame time, so the sequence of presented depths is the same on every run
+    let target_fps = args.fps.unwrap_or(defaults::TARGET_FPS);
+    let fps_balancer = match std::env::var(defaults::DETERMINISTIC_ENV).map(|v| v.parse()) {
+        Ok(Ok(iterations)) => {
+            log::info!("Deterministic mode: {} iterations per frame", iterations);
+            FpsBalancer::fixed(iterations)
+        }
+        Ok(Err(e)) => {
+            log::error!("Invalid {}: {}", defaults::DETERMINISTIC_ENV, e);
+            default_fps_balancer(target_fps)
+        }
+        Err(_) => default_fps_balancer(target_fps),
+    };
 
     let mut gpu_context = match GpuContext::new(
         &window,
         view_state.dimensions(),
         view_state.scale_factor(),
         view_state.coords(),
-        30.0,
-        defaults::MAX_DEPTH,
+        fps_balancer,
+        Watchdog::new(
+            defaults::WATCHDOG_FRAME_MULTIPLIER,
+            defaults::WATCHDOG_MIN_TIMEOUT_MS,
+        ),
+        max_depth,
+        &gpu_diagnostics(&args),
+        display,
     )
     .await
     {
@@ -122,12 +438,68 @@ pub async fn run() {
         }
     };
 
+    gpu_context.set_fractal(0, view_state.fractal());
+    gpu_context.set_colors(colors);
+    gpu_context.set_perturbation(!args.exact);
+    // The proxy can only be sent to the compiling threads, not shared between them
+    let pipeline_proxy = Mutex::new(event_loop_proxy.clone());
+    gpu_context.set_pipeline_notifier(move || {
+        // The event loop might have closed while the pipeline was compiled
+        let _ = pipeline_proxy
+            .lock()
+            .unwrap()
+            .send_event(UserEvent::PipelineCompiled);
+    });
+    // On the web the browser tells when the submitted work is done, polling resumes then
+    let work_done_proxy = Mutex::new(event_loop_proxy.clone());
+    gpu_context.set_work_done_notifier(move || {
+        let _ = work_done_proxy
+            .lock()
+            .unwrap()
+            .send_event(UserEvent::RenderNeedsPolling);
+    });
+    if let Some(budget) = args.band_budget_ms {
+        gpu_context.set_band_budget(budget.into());
+    }
+    gpu_context
+        .update_params(0, view_state.coords().clone())
+        .expect("Word count is checked on creation");
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut cache = match args.cache.then(cache::DiskCache::open) {
+        Some(Some(disk)) => Some(cache::Cache::start(disk, event_loop_proxy.clone())),
+        Some(None) => {
+            log::error!("No cache directory to save the calculation progress to");
+            None
+        }
+        None => None,
+    };
+    // Calculation state of a dropped iterations file, restored once the view shows its location
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut pending_resume: Option<gpu::Snapshot> = None;
+    // Zoom being recorded, one frame is calculated at a time
+    let mut zoom_recording: Option<recording::Recording> = None;
+
     let overlay = overlay::Overlay::new(
         event_loop_proxy.clone(),
-        window.scale_factor(),
-        defaults::MAX_DEPTH,
+        view_state.scale_factor(),
+        max_depth,
+        view_state.precision(),
+        view_state.fractal(),
+        language,
+        scroll.calibration,
+        pan_binding,
+        display,
+        bookmarks::load(),
     );
-    let mut clipboard = iced_winit::Clipboard::unconnected();
+    if let Some(interval) = args.double_click_ms {
+        input_state.clicks = DoubleClick::new(interval.into());
+    }
+    if let Some(depth) = args.history_depth {
+        view_state.set_history_depth(depth);
+    }
+    let mut views = Views::new(view_state);
+    let mut clipboard = iced_winit::Clipboard::connect(&window);
     let mut ui_state = iced_runtime::program::State::new(
         overlay,
         gpu_context.viewport().logical_size(),
@@ -135,13 +507,67 @@ pub async fn run() {
         &mut gpu_context.ui_debug,
     );
 
+    ui_state.queue_message(overlay::Message::ColorsApplied(colors));
+    if let Some(fps) = args.fps {
+        ui_state.queue_message(overlay::Message::TargetFpsChanged(fps));
+    }
+    // The browser history goes back and forth between the views of the page URL
+    #[cfg(target_arch = "wasm32")]
+    let mut link_sync = {
+        let proxy = event_loop_proxy.clone();
+        link::LinkSync::new(move |linked| {
+            let _ = proxy.send_event(UserEvent::JumpTo(Box::new(linked.bookmark)));
+        })
+    };
+
+    let mut eta = eta::Tracker::default();
+    let mut demo = None;
+    if args.demo {
+        demo = Some(start_demo(
+            &playlist,
+            &mut gpu_context,
+            &mut views,
+            &mut ui_state,
+        ));
+    }
     let mut theme = iced::Theme::Light;
+    let mut julia_preview = false;
+    let mut smooth_zoom = true;
+    let animation_clock = SystemClock::default();
 
     event_loop
         .run(|event, elwt| {
             match event {
                 Event::WindowEvent { event, .. } => {
                     match &event {
+                        // Any deliberate input hands the app back to the user
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    ..
+                                },
+                            ..
+                        }
+                        | WindowEvent::MouseInput {
+                            state: ElementState::Pressed,
+                            ..
+                        }
+                        | WindowEvent::MouseWheel { .. }
+                        | WindowEvent::TouchpadMagnify { .. }
+                        | WindowEvent::Touch(Touch {
+                            phase: TouchPhase::Started,
+                            ..
+                        }) if demo.is_some() => {
+                            demo = None;
+                            ui_state.queue_message(overlay::Message::DemoRunning(false));
+                            ui_state.queue_message(overlay::Message::Notified(
+                                language.tr("notice-demo-stopped").to_owned(),
+                            ));
+                        }
+                        // Typing into the control panel doesn't trigger shortcuts
+                        WindowEvent::KeyboardInput { .. }
+                            if ui_state.program().is_editing_text() => {}
                         WindowEvent::CloseRequested
                         | WindowEvent::KeyboardInput {
                             event:
@@ -151,19 +577,256 @@ pub async fn run() {
                                     ..
                                 },
                             ..
-                        } => elwt.exit(),
+                        } => {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if let Some(cache) = &mut cache {
+                                cache.save_now(&mut gpu_context, views.len());
+                            }
+                            calibrations::save(gpu_context.calibrations());
+                            session::save(&current_session(
+                                &views,
+                                &gpu_context,
+                                &window,
+                                chosen_language,
+                                scroll.calibration,
+                                pan_binding,
+                            ));
+                            elwt.exit()
+                        }
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key: PhysicalKey::Code(KeyCode::KeyV),
+                                    ..
+                                },
+                            ..
+                        } if is_shortcut(input_state.modifiers) => {
+                            // Pastes into the text fields of the overlay are skipped above
+                            match clipboard.read(iced_core::clipboard::Kind::Standard) {
+                                Some(text) => {
+                                    match paste_location(views.active_view_mut(), &text, language) {
+                                        Ok(notice) => {
+                                            ui_state
+                                                .queue_message(overlay::Message::Notified(notice));
+                                            update_view(
+                                                &mut gpu_context,
+                                                &views,
+                                                views.active(),
+                                                &mut ui_state,
+                                            );
+                                            window.request_redraw();
+                                        }
+                                        Err(e) => ui_state
+                                            .queue_message(overlay::Message::ErrorReported(e)),
+                                    }
+                                }
+                                None => ui_state.queue_message(overlay::Message::Notified(
+                                    language.tr("notice-clipboard-empty").to_owned(),
+                                )),
+                            }
+                        }
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key: PhysicalKey::Code(KeyCode::KeyC),
+                                    ..
+                                },
+                            ..
+                        } if is_shortcut(input_state.modifiers) => {
+                            clipboard.write(
+                                iced_core::clipboard::Kind::Standard,
+                                views.active_view().location().to_string(),
+                            );
+                            ui_state.queue_message(overlay::Message::Notified(
+                                language.tr("notice-location-copied").to_owned(),
+                            ));
+                        }
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key: PhysicalKey::Code(KeyCode::KeyZ),
+                                    ..
+                                },
+                            ..
+                        } if is_shortcut(input_state.modifiers) => {
+                            let event = if input_state.modifiers.shift_key() {
+                                UserEvent::Redo
+                            } else {
+                                UserEvent::Undo
+                            };
+                            event_loop_proxy
+                                .send_event(event)
+                                .expect("Event loop closed");
+                        }
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key:
+                                        PhysicalKey::Code(key @ (KeyCode::KeyQ | KeyCode::KeyE)),
+                                    ..
+                                },
+                            ..
+                        } if !is_shortcut(input_state.modifiers) => {
+                            let step = if *key == KeyCode::KeyQ {
+                                -ROTATION_KEY_STEP
+                            } else {
+                                ROTATION_KEY_STEP
+                            };
+                            for index in views.navigated() {
+                                let view = views.get_mut(index);
+                                view.set_rotation(view.coords().rotation() + step);
+                                update_view(&mut gpu_context, &views, index, &mut ui_state);
+                            }
+                            window.request_redraw();
+                        }
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key: PhysicalKey::Code(KeyCode::KeyJ),
+                                    repeat: false,
+                                    ..
+                                },
+                            ..
+                        } if !is_shortcut(input_state.modifiers) => {
+                            // The Julia set of the point under the cursor is shown in its place
+                            let view = views.active_view();
+                            let seed =
+                                match view.fractal().julia {
+                                    Some(_) => None,
+                                    None => Some(view.julia_seed(
+                                        input_state.pointer.map(|p| views.to_active(p)),
+                                    )),
+                                };
+                            let active = views.active();
+                            set_julia(&mut gpu_context, &mut views, active, &mut ui_state, seed);
+                            if julia_preview {
+                                let point = julia_point(&views, input_state.pointer);
+                                gpu_context.set_julia_preview(point);
+                            }
+                            window.request_redraw();
+                        }
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key: PhysicalKey::Code(KeyCode::KeyS),
+                                    repeat: false,
+                                    ..
+                                },
+                            ..
+                        } if !is_shortcut(input_state.modifiers) => {
+                            event_loop_proxy
+                                .send_event(UserEvent::ExportRequested)
+                                .expect("Event loop closed");
+                        }
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key: PhysicalKey::Code(KeyCode::Space),
+                                    ..
+                                },
+                            ..
+                        } if !is_shortcut(input_state.modifiers) => input_state.space_held = true,
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key: PhysicalKey::Code(KeyCode::Home),
+                                    ..
+                                },
+                            ..
+                        } if !is_shortcut(input_state.modifiers) => {
+                            event_loop_proxy
+                                .send_event(UserEvent::PositionReset)
+                                .expect("Event loop closed");
+                        }
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key: PhysicalKey::Code(code),
+                                    logical_key,
+                                    ..
+                                },
+                            ..
+                        } if !is_shortcut(input_state.modifiers) => {
+                            let step = Motion::of_key(
+                                *code,
+                                logical_key,
+                                input_state.modifiers.shift_key(),
+                            )
+                            .and_then(|motion| {
+                                input_state.keys.press(*code, motion, animation_clock.now())
+                            });
+                            if let Some(step) = step {
+                                navigate_views(
+                                    &mut gpu_context,
+                                    &mut views,
+                                    &mut ui_state,
+                                    step,
+                                    language,
+                                );
+                                window.request_redraw();
+                            }
+                        }
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Released,
+                                    physical_key: PhysicalKey::Code(code),
+                                    ..
+                                },
+                            ..
+                        } => {
+                            if *code == KeyCode::Space {
+                                input_state.space_held = false;
+                                let grabs = input_state.grab.len();
+                                input_state.grab.retain(|_, binding| !binding.needs_space());
+                                if input_state.grab.len() != grabs {
+                                    end_drag(&window, &mut views, &mut input_state);
+                                }
+                            }
+                            // Panning keys drag the view, which lasts until they're all released
+                            let released = input_state.keys.release(*code);
+                            if released && !input_state.keys.is_held() {
+                                for index in views.navigated() {
+                                    views.get_mut(index).end_gesture();
+                                }
+                            }
+                        }
                         WindowEvent::ModifiersChanged(modifiers) => {
                             input_state.modifiers = modifiers.state();
                         }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        WindowEvent::DroppedFile(path) if iterations::is_iterations_file(path) => {
+                            match iterations::import(path) {
+                                Ok(counts) => {
+                                    views.active_view_mut().show_coordinates(counts.coords);
+                                    update_view(
+                                        &mut gpu_context,
+                                        &views,
+                                        views.active(),
+                                        &mut ui_state,
+                                    );
+                                    pending_resume = counts.resume;
+                                }
+                                Err(e) => ui_state
+                                    .queue_message(overlay::Message::ErrorReported(e.to_string())),
+                            }
+                            window.request_redraw();
+                        }
                         WindowEvent::Resized(new_size) => {
                             let dimensions =
                                 Dimensions::new_nonzero(new_size.width, new_size.height);
-                            view_state.set_dimensions(dimensions);
-                            gpu_context.resize_and_update_params(
-                                dimensions,
-                                view_state.scale_factor(),
-                                view_state.coords().clone(),
-                            );
+                            views.set_window(dimensions);
+                            gpu_context.resize_surface(dimensions);
+                            resize_views(&mut gpu_context, &views, &mut ui_state);
 
                             window.request_redraw();
                         }
@@ -172,34 +835,125 @@ pub async fn run() {
                             window.request_redraw();
                         }
                         WindowEvent::TouchpadMagnify { delta, .. } => {
-                            view_state.zoom_with_anchor(*delta as f32, input_state.pointer);
-                            gpu_context.update_params(view_state.coords().clone());
+                            let anchor = input_state.pointer.map(|p| views.to_active(p));
+                            zoom_views(
+                                &mut gpu_context,
+                                &mut views,
+                                &mut ui_state,
+                                *delta as f32,
+                                anchor,
+                                None,
+                                language,
+                            );
                             window.request_redraw();
                         }
                         WindowEvent::MouseWheel {
                             delta: scroll_delta,
                             ..
                         } => {
-                            let delta = match scroll_delta {
-                                MouseScrollDelta::LineDelta(_, delta) => *delta,
-                                MouseScrollDelta::PixelDelta(winit::dpi::PhysicalPosition {
-                                    x: _,
-                                    y: delta,
-                                }) => (*delta / 500.0) as f32,
-                            };
-                            if delta != 0.0 {
-                                view_state.zoom_with_anchor(delta, input_state.pointer);
-                                gpu_context.update_params(view_state.coords().clone());
+                            if let Some(delta) = scroll.zoom(scroll_delta) {
+                                let anchor = input_state.pointer.map(|p| views.to_active(p));
+                                // Touchpads scroll in small steps already, only wheel ticks are
+                                // animated
+                                let animated_at = (smooth_zoom
+                                    && matches!(scroll_delta, MouseScrollDelta::LineDelta(..)))
+                                .then(|| animation_clock.now());
+                                zoom_views(
+                                    &mut gpu_context,
+                                    &mut views,
+                                    &mut ui_state,
+                                    delta,
+                                    anchor,
+                                    animated_at,
+                                    language,
+                                );
                                 window.request_redraw();
                             }
                         }
+                        // Presses on the control panel are left to it, middle clicks included
                         WindowEvent::MouseInput {
                             device_id,
                             state: ElementState::Pressed,
-                            button: MouseButton::Left,
-                        } => {
-                            if !ui_state.program().is_pointer_captured() {
-                                input_state.grab.insert(*device_id);
+                            button: button @ (MouseButton::Left | MouseButton::Middle),
+                        } if !ui_state.program().is_pointer_captured() => {
+                            if let Some(pointer) = input_state.pointer {
+                                if views.activate_at(pointer) {
+                                    ui_state.queue_message(overlay::Message::ViewActivated(
+                                        active_view(&views),
+                                    ));
+                                }
+                            }
+                            // Space turns the left button into a pan of any binding
+                            let clicked = *button == MouseButton::Left && !input_state.space_held;
+                            let picked = input_state
+                                .seed_target
+                                .filter(|_| clicked)
+                                .zip(input_state.pointer);
+                            let double_click = input_state.pointer.filter(|&pointer| {
+                                clicked
+                                    && picked.is_none()
+                                    && input_state.clicks.press(animation_clock.now(), pointer)
+                            });
+                            if let Some((target, pointer)) = picked {
+                                // The seed can be picked from another view, such as the
+                                // Mandelbrot set next to its Julia sets
+                                input_state.seed_target = None;
+                                let seed = views
+                                    .active_view()
+                                    .julia_seed(Some(views.to_active(pointer)));
+                                set_julia(
+                                    &mut gpu_context,
+                                    &mut views,
+                                    target,
+                                    &mut ui_state,
+                                    Some(seed),
+                                );
+                                if julia_preview {
+                                    let point = julia_point(&views, input_state.pointer);
+                                    gpu_context.set_julia_preview(point);
+                                }
+                                window.request_redraw();
+                            } else if let Some(pointer) = double_click {
+                                // Shift makes the first press start a selection, it's too small
+                                // to zoom to by the second one
+                                input_state.selection = None;
+                                let magnification = if input_state.modifiers.shift_key() {
+                                    0.5
+                                } else {
+                                    2.0
+                                };
+                                center_views(
+                                    &mut gpu_context,
+                                    &mut views,
+                                    &mut ui_state,
+                                    pointer,
+                                    magnification,
+                                    language,
+                                );
+                                window.request_redraw();
+                            } else if clicked && input_state.modifiers.shift_key() {
+                                input_state.selection = input_state.pointer;
+                            } else if pan_binding.starts(*button, input_state.space_held) {
+                                if input_state.grab.is_empty() {
+                                    input_state.cursor_grabbed = grab_cursor(&window);
+                                }
+                                input_state.grab.insert(*device_id, pan_binding);
+                            }
+                        }
+                        WindowEvent::MouseInput {
+                            state: ElementState::Pressed,
+                            button: MouseButton::Right,
+                            ..
+                        } if !ui_state.program().is_pointer_captured() => {
+                            if let Some(pointer) = input_state.pointer {
+                                let point = views.to_active(pointer);
+                                if gpu_context.read_result_pixel(
+                                    views.active(),
+                                    point.x as u32,
+                                    point.y as u32,
+                                ) {
+                                    window.request_redraw();
+                                }
                             }
                         }
                         WindowEvent::CursorMoved {
@@ -210,40 +964,199 @@ pub async fn run() {
                                 x: position.x as f32,
                                 y: position.y as f32,
                             };
-                            if !input_state.grab.is_empty() {
+                            if let Some(start) = input_state.selection {
+                                show_selection(
+                                    &mut gpu_context,
+                                    &views,
+                                    Some((start, new_position)),
+                                );
+                                window.request_redraw();
+                            }
+                            if !input_state.grab.is_empty() && !input_state.cursor_grabbed {
                                 if let Some(old_position) = &input_state.pointer {
                                     let delta_x = new_position.x - old_position.x;
                                     let delta_y = new_position.y - old_position.y;
-                                    if delta_x.abs() >= 0.05 || delta_y.abs() >= 0.05 {
-                                        view_state.move_by_screen_delta(delta_x, delta_y);
-                                        gpu_context.update_params(view_state.coords().clone());
-
-                                        window.request_redraw();
-                                    }
+                                    drag_views(
+                                        &window,
+                                        &mut gpu_context,
+                                        &mut views,
+                                        &mut ui_state,
+                                        delta_x,
+                                        delta_y,
+                                    );
                                 }
                             }
+                            // The view under the cursor receives input, except while dragging
+                            if input_state.grab.is_empty()
+                                && input_state.selection.is_none()
+                                && !ui_state.program().is_pointer_captured()
+                                && views.activate_at(new_position)
+                            {
+                                ui_state.queue_message(overlay::Message::ViewActivated(
+                                    active_view(&views),
+                                ));
+                            }
                             input_state.pointer = Some(new_position);
+                            if julia_preview {
+                                let point = julia_point(&views, input_state.pointer);
+                                gpu_context.set_julia_preview(point);
+                                window.request_redraw();
+                            }
                         }
-                        WindowEvent::CursorLeft { device_id } => {
-                            input_state.grab.remove(device_id);
+                        WindowEvent::CursorLeft { .. } => {
+                            // A drag goes on outside of the window until the button is released
                             input_state.pointer = None;
+                            input_state.selection = None;
+                            input_state.clicks.reset();
+                            show_selection(&mut gpu_context, &views, None);
+                            gpu_context.set_julia_preview(None);
+                            window.request_redraw();
                         }
                         WindowEvent::MouseInput {
                             device_id,
                             state: ElementState::Released,
-                            button: MouseButton::Left,
+                            button,
                         } => {
-                            input_state.grab.remove(device_id);
+                            // A drag ends with the button of the binding that started it
+                            let dragged = input_state.grab.get(device_id).copied();
+                            if dragged.is_some_and(|binding| binding.button() == *button) {
+                                input_state.grab.remove(device_id);
+                                end_drag(&window, &mut views, &mut input_state);
+                            }
+                            let selected = (*button == MouseButton::Left)
+                                .then(|| input_state.selection.take())
+                                .flatten();
+                            if let Some(start) = selected {
+                                show_selection(&mut gpu_context, &views, None);
+                                if let Some(end) = input_state.pointer {
+                                    fit_views(
+                                        &mut gpu_context,
+                                        &mut views,
+                                        &mut ui_state,
+                                        (start, end),
+                                        language,
+                                    );
+                                }
+                                window.request_redraw();
+                            }
                         }
-                        WindowEvent::Touch(_touch) => {
-                            todo!("Handle touch")
+                        WindowEvent::Touch(touch) => {
+                            let position = Point {
+                                x: touch.location.x as f32,
+                                y: touch.location.y as f32,
+                            };
+                            // The overlay sees touches through the pointer, so that they're
+                            // taken by its buttons
+                            input_state.pointer = Some(position);
+                            match touch.phase {
+                                TouchPhase::Started => {
+                                    // Fingers don't hover, move the cursor so that the overlay
+                                    // knows if it's touched before the finger moves
+                                    ui_state.queue_event(iced_core::Event::Mouse(
+                                        iced_core::mouse::Event::CursorMoved {
+                                            position: iced_winit::conversion::cursor_position(
+                                                touch.location,
+                                                window.scale_factor(),
+                                            ),
+                                        },
+                                    ));
+                                    if !input_state.touches.is_navigating()
+                                        && !ui_state.program().is_pointer_captured()
+                                        && views.activate_at(position)
+                                    {
+                                        ui_state.queue_message(overlay::Message::ViewActivated(
+                                            active_view(&views),
+                                        ));
+                                    }
+                                    input_state.touches.press(touch.id, position);
+                                }
+                                TouchPhase::Moved => {
+                                    let captured = ui_state.program().is_pointer_captured();
+                                    if let Some(motion) =
+                                        input_state.touches.moved(touch.id, position, captured)
+                                    {
+                                        touch_views(
+                                            &mut gpu_context,
+                                            &mut views,
+                                            &mut ui_state,
+                                            motion,
+                                            language,
+                                        );
+                                        window.request_redraw();
+                                    }
+                                }
+                                TouchPhase::Ended | TouchPhase::Cancelled => {
+                                    if input_state.touches.release(touch.id) {
+                                        for index in views.navigated() {
+                                            views.get_mut(index).end_gesture();
+                                        }
+                                    }
+                                }
+                            }
                         }
                         WindowEvent::ThemeChanged(os_theme) => match os_theme {
                             winit::window::Theme::Light => theme = iced::theme::Theme::Light,
                             winit::window::Theme::Dark => theme = iced::theme::Theme::Dark,
                         },
+                        WindowEvent::Occluded(occluded) => {
+                            let resumed = presenter.set_occluded(*occluded);
+                            gpu_context.set_paused(presenter.is_paused());
+                            // Hidden windows don't need room for the views to grow back into
+                            if *occluded {
+                                gpu_context.trim_buffers();
+                            }
+                            if resumed {
+                                window.request_redraw();
+                            }
+                        }
+                        WindowEvent::Focused(focused) => {
+                            if !focused {
+                                // Releases are sent to the focused window, a drag would never end
+                                input_state.grab.clear();
+                                // The pointer may be anywhere when the window is entered again
+                                input_state.pointer = None;
+                                release_cursor(&window, &mut input_state);
+                                input_state.keys.clear();
+                                input_state.space_held = false;
+                                input_state.clicks.reset();
+                                if input_state.selection.take().is_some() {
+                                    show_selection(&mut gpu_context, &views, None);
+                                    window.request_redraw();
+                                }
+                            }
+                            let resumed = presenter.set_focused(*focused);
+                            gpu_context.set_paused(presenter.is_paused());
+                            if resumed {
+                                window.request_redraw();
+                            }
+                        }
+                        WindowEvent::RedrawRequested if !presenter.render() => {}
+                        WindowEvent::RedrawRequested if gpu_context.is_lost() => {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            match pollster::block_on(gpu_context.rebuild(&window)) {
+                                Ok(()) => {
+                                    log::info!("GPU context rebuilt");
+                                    window.request_redraw();
+                                }
+                                Err(e) => {
+                                    log::error!("Unable to rebuild the GPU context: {}", e);
+                                    elwt.exit();
+                                }
+                            }
+                            // Adapters are only requested asynchronously on the web
+                            #[cfg(target_arch = "wasm32")]
+                            {
+                                log::error!("GPU device lost, reload the page to continue");
+                                elwt.exit();
+                            }
+                        }
                         WindowEvent::RedrawRequested => match gpu_context.render() {
                             Ok(()) => {
+                                if let Some(failure) = gpu_context.take_failure() {
+                                    ui_state.queue_message(overlay::Message::RenderingDegraded(
+                                        failure,
+                                    ));
+                                }
                                 // Update the mouse cursor
                                 window.set_cursor_icon(iced_winit::conversion::mouse_interaction(
                                     ui_state.mouse_interaction(),
@@ -252,8 +1165,25 @@ pub async fn run() {
                                     .send_event(UserEvent::RenderNeedsPolling)
                                     .expect("Event loop closed");
                             }
-                            Err(wgpu::SurfaceError::OutOfMemory) => elwt.exit(),
-                            Err(e) => log::warn!("Render error: {:?}", e),
+                            Err(e) => {
+                                let size = window.inner_size();
+                                let dimensions = Dimensions::new_nonzero(size.width, size.height);
+                                match gpu_context.recover(&e, dimensions) {
+                                    gpu::SurfaceRecovery::Exit => elwt.exit(),
+                                    gpu::SurfaceRecovery::Resize(dimensions) => {
+                                        log::warn!("Resizing the surface after {:?}", e);
+                                        views.set_window(dimensions);
+                                        gpu_context.resize_surface(dimensions);
+                                        resize_views(&mut gpu_context, &views, &mut ui_state);
+                                        window.request_redraw();
+                                    }
+                                    // Routine under load, try again with the next frame
+                                    recovery => {
+                                        log::debug!("Render error {:?}, {:?}", e, recovery);
+                                        window.request_redraw();
+                                    }
+                                }
+                            }
                         },
                         _ => {}
                     };
@@ -269,7 +1199,7 @@ pub async fn run() {
 
                     // Update iced if any events are pending
                     if !ui_state.is_queue_empty() {
-                        let _ = ui_state.update(
+                        let (_, command) = ui_state.update(
                             gpu_context.viewport().logical_size(),
                             input_state
                                 .pointer
@@ -289,53 +1219,1218 @@ pub async fn run() {
                             &mut clipboard,
                             &mut gpu_context.ui_debug,
                         );
+                        // Clicks and tabs move the focus between the text fields
+                        let focus = ui_state.program().focus_operation();
+                        let size = gpu_context.viewport().logical_size();
+                        ui_state.operate(
+                            &mut gpu_context.ui_renderer,
+                            std::iter::once(focus),
+                            size,
+                            &mut gpu_context.ui_debug,
+                        );
 
-                        window.request_redraw();
+                        // The closed panel only shows its toggle button, which pointer moves
+                        // don't change
+                        window.set_cursor_icon(iced_winit::conversion::mouse_interaction(
+                            ui_state.mouse_interaction(),
+                        ));
+                        if command.is_some() || ui_state.program().is_open() {
+                            window.request_redraw();
+                        }
                     }
                 }
                 Event::UserEvent(event) => match event {
+                    UserEvent::TargetFpsChanged(target_fps) => {
+                        gpu_context.set_target_fps(target_fps);
+                    }
+
                     UserEvent::ViewScaleFactorChanged(scale_factor) => {
-                        view_state.set_scale_factor(scale_factor);
-                        gpu_context.resize_and_update_params(
-                            view_state.dimensions(),
-                            view_state.scale_factor(),
-                            view_state.coords().clone(),
+                        views.active_view_mut().set_scale_factor(scale_factor);
+                        let view = views.active_view();
+                        report_error(
+                            &mut ui_state,
+                            gpu_context.resize_and_update_params(
+                                views.active(),
+                                views.rect(views.active()),
+                                view.scale_factor(),
+                                view.coords().clone(),
+                            ),
                         );
                         window.request_redraw();
                     }
 
                     UserEvent::PositionReset => {
-                        view_state.reset();
-                        gpu_context.update_params(view_state.coords().clone());
+                        views.active_view_mut().reset();
+                        update_view(&mut gpu_context, &views, views.active(), &mut ui_state);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::Undo | UserEvent::Redo => {
+                        for index in views.navigated() {
+                            let view = views.get_mut(index);
+                            let changed = match event {
+                                UserEvent::Undo => view.undo(),
+                                _ => view.redo(),
+                            };
+                            if changed {
+                                gpu_context.set_fractal(index, views.get(index).fractal());
+                                update_view(&mut gpu_context, &views, index, &mut ui_state);
+                            }
+                        }
+                        // The precision and the fractal are restored as well
+                        ui_state
+                            .queue_message(overlay::Message::ViewActivated(active_view(&views)));
                         window.request_redraw();
                     }
 
                     UserEvent::PrecisionChanged(precision) => {
-                        view_state.set_precision(precision);
-                        gpu_context.update_params(view_state.coords().clone());
+                        views.active_view_mut().set_precision(precision);
+                        update_view(&mut gpu_context, &views, views.active(), &mut ui_state);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::RotationChanged(degrees) => {
+                        views.active_view_mut().set_rotation(degrees);
+                        update_view(&mut gpu_context, &views, views.active(), &mut ui_state);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::FractalChanged(fractal) => {
+                        views.active_view_mut().set_fractal(fractal.clone());
+                        gpu_context.set_fractal(views.active(), views.active_view().fractal());
+                        update_view(&mut gpu_context, &views, views.active(), &mut ui_state);
+                        if julia_preview {
+                            let point = julia_point(&views, input_state.pointer);
+                            gpu_context.set_julia_preview(point);
+                        }
+                        window.request_redraw();
+                    }
+
+                    UserEvent::SplitToggled(split) => {
+                        if split && !views.is_split() {
+                            views.split();
+                            let view = views.get(1);
+                            report_error(
+                                &mut ui_state,
+                                gpu_context.add_view(
+                                    views.rect(1),
+                                    view.scale_factor(),
+                                    view.coords().clone(),
+                                    view.fractal(),
+                                ),
+                            );
+                        } else if !split && views.is_split() {
+                            // The remaining view is recalculated with the whole window size anyway
+                            views.unsplit();
+                            input_state.seed_target = None;
+                            gpu_context.truncate_views(1);
+                            gpu_context.set_fractal(0, views.get(0).fractal());
+                        }
+                        resize_views(&mut gpu_context, &views, &mut ui_state);
+                        ui_state
+                            .queue_message(overlay::Message::ViewActivated(active_view(&views)));
+                        window.request_redraw();
+                    }
+
+                    UserEvent::JuliaToggled(enabled) => {
+                        let view = views.active_view();
+                        let seed = enabled.then(|| view.julia_seed(None));
+                        let active = views.active();
+                        set_julia(&mut gpu_context, &mut views, active, &mut ui_state, seed);
+                        if julia_preview {
+                            let point = julia_point(&views, input_state.pointer);
+                            gpu_context.set_julia_preview(point);
+                        }
+                        window.request_redraw();
+                    }
+
+                    UserEvent::JuliaSeedPicking => {
+                        input_state.seed_target = Some(views.active());
+                        ui_state.queue_message(overlay::Message::Notified(
+                            language.tr("notice-pick-julia-seed").to_owned(),
+                        ));
+                    }
+
+                    UserEvent::LinkToggled(linked) => {
+                        views.set_linked(linked);
+                    }
+
+                    UserEvent::JuliaPreviewToggled(enabled) => {
+                        julia_preview = enabled;
+                        let point = julia_point(&views, input_state.pointer.filter(|_| enabled));
+                        gpu_context.set_julia_preview(point);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::SmoothZoomToggled(enabled) => smooth_zoom = enabled,
+                    UserEvent::ScrollCalibrated(calibration) => scroll.calibration = calibration,
+                    UserEvent::PanBindingChanged(binding) => pan_binding = binding,
+
+                    UserEvent::AntialiasingToggled(enabled) => {
+                        gpu_context.set_antialiasing(enabled);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::ReprojectionToggled(enabled) => {
+                        gpu_context.set_reprojection(enabled);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::ColorsChanged(colors) => {
+                        gpu_context.set_colors(colors);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::PaletteChanged(palette) => {
+                        gpu_context.set_palette(palette);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::DeepColorToggled(enabled) => {
+                        report_error(&mut ui_state, gpu_context.set_deep_color(enabled));
+                        ui_state.queue_message(overlay::Message::DeepColorApplied(
+                            gpu_context.is_deep_color(),
+                        ));
+                        window.request_redraw();
+                    }
+
+                    UserEvent::VsyncToggled(enabled) => {
+                        gpu_context.set_vsync(enabled);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::PowerPreferenceChanged(power) => {
+                        if gpu_context.set_power_preference(power) {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            match pollster::block_on(gpu_context.switch_adapter(&window)) {
+                                Ok(()) => {
+                                    log::info!("Switched to the {} adapter", power.name());
+                                    window.request_redraw();
+                                }
+                                Err(e) => {
+                                    log::error!("Unable to switch the GPU adapter: {}", e);
+                                    elwt.exit();
+                                }
+                            }
+                            // Adapters are only requested asynchronously on the web, the saved
+                            // preference is used by the next page load
+                            #[cfg(target_arch = "wasm32")]
+                            ui_state.queue_message(overlay::Message::Notified(
+                                language.tr("notice-power-on-reload").to_owned(),
+                            ));
+                        }
+                    }
+
+                    UserEvent::SettingsCopied(settings) => {
+                        clipboard.write(iced_core::clipboard::Kind::Standard, settings.encode());
+                        ui_state.queue_message(overlay::Message::Notified(
+                            language.tr("notice-settings-copied").to_owned(),
+                        ));
+                    }
+
+                    UserEvent::ExportRequested => {
+                        let metadata = screenshot::Metadata {
+                            location: views.active_view().location().to_string(),
+                            settings: ui_state.program().settings().encode(),
+                        };
+                        let capture = gpu_context.capture_frame(views.active());
+                        let proxy = event_loop_proxy.clone();
+                        let export = async move {
+                            let result = match capture.await {
+                                Some(image) => screenshot::export(&image, &metadata),
+                                None => Err(screenshot::ExportError::Lost),
+                            };
+                            // The event loop might have closed while the frame was captured
+                            let _ = proxy.send_event(UserEvent::FrameExported(result));
+                        };
+                        // Encoding takes a while, and the capture completes with the next frames
+                        #[cfg(not(target_arch = "wasm32"))]
+                        std::thread::spawn(move || pollster::block_on(export));
+                        #[cfg(target_arch = "wasm32")]
+                        wasm_bindgen_futures::spawn_local(export);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::PosterRequested(dimensions) => {
+                        let metadata = screenshot::Metadata {
+                            location: views.active_view().location().to_string(),
+                            settings: ui_state.program().settings().encode(),
+                        };
+                        match gpu_context.render_poster(views.active(), dimensions) {
+                            Ok(poster) => {
+                                let proxy = event_loop_proxy.clone();
+                                let export = async move {
+                                    let result = match poster.await {
+                                        Ok(image) => screenshot::export(&image, &metadata),
+                                        Err(e) => Err(e.into()),
+                                    };
+                                    let _ = proxy.send_event(UserEvent::FrameExported(result));
+                                };
+                                #[cfg(not(target_arch = "wasm32"))]
+                                std::thread::spawn(move || pollster::block_on(export));
+                                #[cfg(target_arch = "wasm32")]
+                                wasm_bindgen_futures::spawn_local(export);
+                            }
+                            Err(e) => ui_state
+                                .queue_message(overlay::Message::ErrorReported(e.to_string())),
+                        }
+                        window.request_redraw();
+                    }
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    UserEvent::IterationsExportRequested => {
+                        match gpu_context.export_iterations(views.active()) {
+                            Some(counts) => {
+                                let proxy = event_loop_proxy.clone();
+                                // Files of deep views hold all of their compute buffers
+                                std::thread::spawn(move || {
+                                    let result =
+                                        iterations::export(&counts).map_err(|e| e.to_string());
+                                    let _ = proxy.send_event(UserEvent::IterationsExported(result));
+                                });
+                            }
+                            None => ui_state.queue_message(overlay::Message::ErrorReported(
+                                iterations::IterationsError::Unavailable.to_string(),
+                            )),
+                        }
+                        window.request_redraw();
+                    }
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    UserEvent::IterationsExported(result) => {
+                        ui_state.queue_message(match result {
+                            Ok(path) => overlay::Message::Notified(
+                                language.format("notice-frame-exported", &[&path]),
+                            ),
+                            Err(e) => overlay::Message::ErrorReported(e),
+                        });
                         window.request_redraw();
                     }
 
+                    UserEvent::PosterCancelled => {
+                        gpu_context.cancel_poster();
+                        window.request_redraw();
+                    }
+
+                    UserEvent::RecordingRequested(request) => {
+                        let stem = screenshot::current_stem();
+                        #[cfg(not(target_arch = "wasm32"))]
+                        let output = match request.video {
+                            Some(options) => {
+                                let active = views.active();
+                                let size = views
+                                    .rect(active)
+                                    .dimensions()
+                                    .scale_to(views.get(active).scale_factor());
+                                video::Encoder::spawn(
+                                    video::FFMPEG.as_ref(),
+                                    size,
+                                    options,
+                                    &video::path(&stem, options.format),
+                                )
+                                .map(|encoder| {
+                                    recording::Output::Video(std::sync::Arc::new(Mutex::new(
+                                        encoder,
+                                    )))
+                                })
+                                .map_err(|e| e.to_string())
+                            }
+                            None => Ok(recording::Output::Images(stem)),
+                        };
+                        #[cfg(target_arch = "wasm32")]
+                        let output = Ok(recording::Output::Images(stem));
+                        let started = output
+                            .and_then(|output| {
+                                recording::Recording::new(views.active_view(), &request, output)
+                                    .map_err(|e| e.to_string())
+                            })
+                            .and_then(|started| {
+                                record_frame(
+                                    &mut gpu_context,
+                                    &views,
+                                    &started,
+                                    ui_state.program().settings().encode(),
+                                    &event_loop_proxy,
+                                )?;
+                                Ok(started)
+                            });
+                        match started {
+                            Ok(started) => zoom_recording = Some(started),
+                            Err(e) => ui_state.queue_message(overlay::Message::ErrorReported(e)),
+                        }
+                        window.request_redraw();
+                    }
+
+                    UserEvent::RecordingCancelled => {
+                        if zoom_recording.take().is_some() {
+                            gpu_context.cancel_poster();
+                            ui_state.queue_message(overlay::Message::Notified(
+                                language.tr("notice-recording-cancelled").to_owned(),
+                            ));
+                        }
+                        window.request_redraw();
+                    }
+
+                    // Frames of a cancelled recording have nothing left to do
+                    UserEvent::FrameRecorded(result) => {
+                        if let Some(active) = &mut zoom_recording {
+                            let stopped = match result {
+                                Ok(_) if active.advance() => record_frame(
+                                    &mut gpu_context,
+                                    &views,
+                                    active,
+                                    ui_state.program().settings().encode(),
+                                    &event_loop_proxy,
+                                )
+                                .err()
+                                .map(overlay::Message::ErrorReported),
+                                Ok(path) => Some(overlay::Message::Notified(language.format(
+                                    "notice-recording-finished",
+                                    &[&active.progress().1, &path],
+                                ))),
+                                Err(screenshot::ExportError::Poster(
+                                    gpu::PosterError::Cancelled,
+                                )) => Some(overlay::Message::Notified(
+                                    language.tr("notice-recording-cancelled").to_owned(),
+                                )),
+                                Err(e) => Some(overlay::Message::ErrorReported(e.to_string())),
+                            };
+                            if let Some(message) = stopped {
+                                zoom_recording = None;
+                                ui_state.queue_message(message);
+                            }
+                        }
+                        window.request_redraw();
+                    }
+
+                    UserEvent::FrameExported(result) => match result {
+                        Ok(path) => ui_state.queue_message(overlay::Message::Notified(
+                            language.format("notice-frame-exported", &[&path]),
+                        )),
+                        Err(screenshot::ExportError::Poster(gpu::PosterError::Cancelled)) => {
+                            ui_state.queue_message(overlay::Message::Notified(
+                                language.tr("notice-poster-cancelled").to_owned(),
+                            ))
+                        }
+                        Err(e) => {
+                            ui_state.queue_message(overlay::Message::ErrorReported(e.to_string()))
+                        }
+                    },
+
+                    UserEvent::SettingsPasted => {
+                        let settings = clipboard
+                            .read(iced_core::clipboard::Kind::Standard)
+                            .ok_or_else(|| language.tr("notice-clipboard-empty").to_owned())
+                            .and_then(|text| {
+                                settings::Settings::decode(&text).map_err(|e| e.to_string())
+                            });
+                        match settings {
+                            Ok(settings) => {
+                                // Applied at once, so that the view is recalculated only once
+                                let view = views.active_view_mut();
+                                view.set_scale_factor(settings.scale_factor);
+                                view.set_precision(settings.precision);
+                                view.set_fractal(settings.fractal.clone());
+                                gpu_context.set_max_depth(settings.max_depth);
+                                let iteration_changed =
+                                    gpu_context.iteration() != settings.iteration;
+                                gpu_context.set_iteration(settings.iteration);
+                                gpu_context.set_antialiasing(settings.antialiasing);
+                                gpu_context.set_colors(settings.colors);
+                                gpu_context.set_palette(settings.palette.clone());
+                                gpu_context.set_target_fps(settings.target_fps);
+                                gpu_context.set_fractal(views.active(), settings.fractal.clone());
+                                let view = views.active_view();
+                                report_error(
+                                    &mut ui_state,
+                                    gpu_context.resize_and_update_params(
+                                        views.active(),
+                                        views.rect(views.active()),
+                                        view.scale_factor(),
+                                        view.coords().clone(),
+                                    ),
+                                );
+                                if iteration_changed {
+                                    // The other views keep their settings but share the radius
+                                    update_views(&mut gpu_context, &views, &mut ui_state);
+                                }
+                                ui_state.queue_message(overlay::Message::SettingsApplied(settings));
+                                ui_state.queue_message(overlay::Message::Notified(
+                                    language.tr("notice-settings-applied").to_owned(),
+                                ));
+                                window.request_redraw();
+                            }
+                            Err(e) => {
+                                ui_state.queue_message(overlay::Message::ErrorReported(e));
+                            }
+                        }
+                    }
+
+                    UserEvent::BookmarkRequested => {
+                        let bookmark = views
+                            .active_view()
+                            .bookmark(String::new(), gpu_context.max_depth());
+                        ui_state.queue_message(overlay::Message::BookmarkCreated(bookmark));
+                    }
+
+                    UserEvent::JumpTo(bookmark) => {
+                        match views.active_view_mut().jump_to_bookmark(&bookmark) {
+                            Ok(()) => {
+                                gpu_context.set_max_depth(bookmark.max_depth);
+                                ui_state.queue_message(overlay::Message::MaxDepthApplied(
+                                    bookmark.max_depth,
+                                ));
+                                gpu_context.set_fractal(views.active(), bookmark.fractal);
+                                // A different word count rebuilds the pipeline with the next frame
+                                update_view(
+                                    &mut gpu_context,
+                                    &views,
+                                    views.active(),
+                                    &mut ui_state,
+                                );
+                                if julia_preview {
+                                    let point = julia_point(&views, input_state.pointer);
+                                    gpu_context.set_julia_preview(point);
+                                }
+                                window.request_redraw();
+                            }
+                            Err(e) => ui_state
+                                .queue_message(overlay::Message::ErrorReported(e.to_string())),
+                        }
+                        ui_state
+                            .queue_message(overlay::Message::ViewActivated(active_view(&views)));
+                    }
+
+                    UserEvent::BookmarksChanged(list) => bookmarks::save(&list),
+
+                    UserEvent::PipelineCompiled => {
+                        if gpu_context.receive_pipeline() {
+                            window.request_redraw();
+                        }
+                    }
+
+                    UserEvent::DemoStarted => {
+                        demo = Some(start_demo(
+                            &playlist,
+                            &mut gpu_context,
+                            &mut views,
+                            &mut ui_state,
+                        ));
+                        window.request_redraw();
+                    }
+
+                    UserEvent::LanguageChanged(changed) => {
+                        language = changed;
+                        chosen_language = Some(changed);
+                        window.request_redraw();
+                    }
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    UserEvent::CacheResponded(response) => {
+                        let Some(cache) = &mut cache else {
+                            return;
+                        };
+                        match cache.handle(response, &mut gpu_context, language) {
+                            Ok(Some(notice)) => {
+                                ui_state.queue_message(overlay::Message::Notified(notice));
+                                window.request_redraw();
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                log::error!("{}", e);
+                                ui_state.queue_message(overlay::Message::ErrorReported(e));
+                            }
+                        }
+                    }
+
                     UserEvent::MaxDepthChanged(max_depth) => {
                         gpu_context.set_max_depth(max_depth);
                     }
 
+                    UserEvent::IterationChanged(iteration) => {
+                        // Counts of every pixel change, so all views start over
+                        gpu_context.set_iteration(iteration);
+                        update_views(&mut gpu_context, &views, &mut ui_state);
+                        window.request_redraw();
+                    }
+
+                    UserEvent::RetryRendering => {
+                        gpu_context.retry();
+                        update_views(&mut gpu_context, &views, &mut ui_state);
+                        window.request_redraw();
+                    }
+
                     UserEvent::RenderNeedsPolling => match gpu_context.poll() {
                         wgpu::MaintainResult::SubmissionQueueEmpty => {
-                            ui_state.queue_message(overlay::Message::InfoUpdated(overlay::Info {
-                                depth: gpu_context.current_depth(),
-                            }));
-                            window.request_redraw()
+                            if let Some(warning) = gpu_context.take_warning() {
+                                ui_state.queue_message(overlay::Message::ErrorReported(
+                                    language.tr(warning_key(warning)).to_owned(),
+                                ));
+                                if warning != gpu::Warning::Stalled {
+                                    update_views(&mut gpu_context, &views, &mut ui_state);
+                                }
+                            }
+                            if let Some(failure) = gpu_context.take_failure() {
+                                // Recalculate with the word count the context has fallen back to
+                                ui_state
+                                    .queue_message(overlay::Message::RenderingDegraded(failure));
+                                update_views(&mut gpu_context, &views, &mut ui_state);
+                            }
+                            if let Some(location) = demo.as_mut().and_then(|demo| demo.advance()) {
+                                report_error(
+                                    &mut ui_state,
+                                    views.active_view_mut().jump_to(location),
+                                );
+                                update_view(
+                                    &mut gpu_context,
+                                    &views,
+                                    views.active(),
+                                    &mut ui_state,
+                                );
+                            }
+                            #[cfg(target_arch = "wasm32")]
+                            link_sync.update(link::encode(
+                                &views
+                                    .active_view()
+                                    .bookmark(String::new(), gpu_context.max_depth()),
+                                gpu_context.colors(),
+                            ));
+                            if autosave.is_due() {
+                                calibrations::save(gpu_context.calibrations());
+                                session::save(&current_session(
+                                    &views,
+                                    &gpu_context,
+                                    &window,
+                                    chosen_language,
+                                    scroll.calibration,
+                                    pan_binding,
+                                ));
+                            }
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if let Some(snapshot) = pending_resume.take() {
+                                let active = views.active();
+                                // Frames split into bands are presented before
+                                match gpu_context.snapshot_key(active) {
+                                    None => pending_resume = Some(snapshot),
+                                    Some(_) if gpu_context.is_splitting_frame() => {
+                                        pending_resume = Some(snapshot)
+                                    }
+                                    Some(key) if *key == snapshot.key => {
+                                        let depth = snapshot.depth;
+                                        let key = if gpu_context.restore(active, snapshot) {
+                                            language.format("notice-iterations-resumed", &[&depth])
+                                        } else {
+                                            language.tr("notice-iterations-mismatch").to_owned()
+                                        };
+                                        ui_state.queue_message(overlay::Message::Notified(key));
+                                    }
+                                    Some(_) => ui_state.queue_message(overlay::Message::Notified(
+                                        language.tr("notice-iterations-mismatch").to_owned(),
+                                    )),
+                                }
+                            }
+                            #[cfg(not(target_arch = "wasm32"))]
+                            let cache_usage = cache.as_mut().map(|cache| {
+                                cache.update(&mut gpu_context, views.len());
+                                cache.usage()
+                            });
+                            #[cfg(target_arch = "wasm32")]
+                            let cache_usage = None;
+                            // Idle windows don't draw until something changes, demos go on to
+                            // advance to the next location. The last frame shows the final
+                            // statistics, idle ones would only redraw them
+                            let follow =
+                                presenter.follow(gpu_context.needs_frames() || demo.is_some());
+                            if follow {
+                                let depth = gpu_context.current_depth(views.active());
+                                eta.record(views.active(), depth);
+                                ui_state.queue_message(overlay::Message::InfoUpdated(
+                                    overlay::Info {
+                                        depth,
+                                        remaining: eta.estimate(gpu_context.max_depth()),
+                                        surface_retries: gpu_context.surface_retries(),
+                                        precision_bits: views.active_view().precision_bits(),
+                                        word_count: views.active_view().coords().size(),
+                                        zoom_log10: views.active_view().zoom_log10(),
+                                        rotation: views.active_view().coords().rotation(),
+                                        cache_usage,
+                                        active_tiles: gpu_context.active_tiles(views.active()),
+                                        settled: gpu_context.settled_fraction(views.active()),
+                                        iteration_rate: gpu_context.iteration_rate(),
+                                        compiling: gpu_context.compiling_word_count(),
+                                        inspected: gpu_context.result_pixel(views.active()),
+                                        poster: gpu_context.poster_progress(),
+                                        recording: zoom_recording
+                                            .as_ref()
+                                            .map(recording::Recording::progress),
+                                    },
+                                ));
+                            }
+                            if follow && presenter.iterate() {
+                                window.request_redraw()
+                            }
                         }
                         wgpu::MaintainResult::Ok => {
-                            event_loop_proxy
-                                .send_event(UserEvent::RenderNeedsPolling)
-                                .expect("Event loop closed");
+                            if let Some(warning) = gpu_context.take_warning() {
+                                ui_state.queue_message(overlay::Message::ErrorReported(
+                                    language.tr(warning_key(warning)).to_owned(),
+                                ));
+                                window.request_redraw();
+                            }
+                            // Work that notifies when it's done isn't polled until then
+                            if !gpu_context.awaits_work_done() {
+                                event_loop_proxy
+                                    .send_event(UserEvent::RenderNeedsPolling)
+                                    .expect("Event loop closed");
+                            }
                         }
                     },
                 },
+                Event::DeviceEvent {
+                    event: DeviceEvent::MouseMotion { delta: (dx, dy) },
+                    ..
+                } if input_state.cursor_grabbed && !input_state.grab.is_empty() => {
+                    drag_views(
+                        &window,
+                        &mut gpu_context,
+                        &mut views,
+                        &mut ui_state,
+                        dx as f32,
+                        dy as f32,
+                    );
+                }
+                Event::AboutToWait => {
+                    let now_ms = animation_clock.now();
+                    // Keys released while typing aren't seen
+                    if ui_state.program().is_editing_text() {
+                        input_state.keys.clear();
+                        input_state.space_held = false;
+                    }
+                    if let Some(motion) = input_state.keys.frame(now_ms) {
+                        navigate_views(
+                            &mut gpu_context,
+                            &mut views,
+                            &mut ui_state,
+                            motion,
+                            language,
+                        );
+                        window.request_redraw();
+                    }
+                    let mut animating = false;
+                    for index in 0..views.len() {
+                        if views.get(index).is_animating() {
+                            animating |= views.get_mut(index).tick(now_ms);
+                            update_view(&mut gpu_context, &views, index, &mut ui_state);
+                            window.request_redraw();
+                        }
+                    }
+                    // Wakes up for the next frame of the animation even without any events
+                    elwt.set_control_flow(if animating || input_state.keys.is_held() {
+                        ControlFlow::wait_duration(ANIMATION_FRAME)
+                    } else {
+                        ControlFlow::Wait
+                    });
+                }
                 _ => {}
             };
         })
         .unwrap();
 }
+
+/// Returns the session of the active view
+fn current_session(
+    views: &Views,
+    gpu_context: &GpuContext,
+    window: &winit::window::Window,
+    language: Option<Language>,
+    scroll: ScrollCalibration,
+    pan_binding: PanBinding,
+) -> session::Session {
+    let view_state = views.active_view();
+    let window_size = window.inner_size();
+    session::Session {
+        coords: view_state.coords().clone(),
+        scale_factor: view_state.scale_factor(),
+        fractal: view_state.fractal(),
+        precision: view_state.precision(),
+        depth: gpu_context.current_depth(views.active()),
+        max_depth: gpu_context.max_depth(),
+        colors: gpu_context.colors(),
+        window: session::WindowState {
+            width: window_size.width,
+            height: window_size.height,
+            maximized: window.is_maximized(),
+        },
+        language,
+        scroll,
+        pan_binding,
+        display: gpu_context.display(),
+    }
+}
+
+/// Zooms the navigated views by `delta` around `anchor`, in an animation started at
+/// `animated_at` or at once. Tells when the max zoom is reached, as the view stops changing
+fn zoom_views(
+    gpu_context: &mut GpuContext,
+    views: &mut Views,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+    delta: f32,
+    anchor: Option<Point>,
+    animated_at: Option<f64>,
+    language: Language,
+) {
+    let mut limited = false;
+    for index in views.navigated() {
+        let view = views.get_mut(index);
+        limited |= match animated_at {
+            Some(now_ms) => view.animate_zoom(delta, anchor, now_ms),
+            None => view.zoom_with_anchor(delta, anchor),
+        };
+        update_view(gpu_context, views, index, ui_state);
+    }
+    if limited && delta > 0.0 {
+        ui_state.queue_message(overlay::Message::Notified(
+            language.tr("notice-max-zoom").to_owned(),
+        ));
+    }
+}
+
+/// Confines the cursor to the window for a drag, or locks it if it can't be confined, so that the
+/// drag goes on past the window edges. Returns true if the cursor is grabbed
+fn grab_cursor(window: &winit::window::Window) -> bool {
+    // Pointer lock of browsers is granted later, if at all, while moves of a pressed button keep
+    // arriving from outside the canvas anyway
+    if cfg!(target_arch = "wasm32") {
+        return false;
+    }
+    [CursorGrabMode::Confined, CursorGrabMode::Locked]
+        .into_iter()
+        .any(|mode| window.set_cursor_grab(mode).is_ok())
+}
+
+/// Ends the gestures of the views once the last drag is over
+fn end_drag(window: &winit::window::Window, views: &mut Views, input_state: &mut InputState) {
+    if input_state.grab.is_empty() {
+        for index in views.navigated() {
+            views.get_mut(index).end_gesture();
+        }
+        release_cursor(window, input_state);
+    }
+}
+
+/// Ends the grab of a drag, handing the cursor back
+fn release_cursor(window: &winit::window::Window, input_state: &mut InputState) {
+    if std::mem::take(&mut input_state.cursor_grabbed) {
+        if let Err(e) = window.set_cursor_grab(CursorGrabMode::None) {
+            log::warn!("Unable to release the cursor: {}", e);
+        }
+    }
+}
+
+/// Drags the navigated views by a screen delta, unless it's too small to matter
+fn drag_views(
+    window: &winit::window::Window,
+    gpu_context: &mut GpuContext,
+    views: &mut Views,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+    dx: f32,
+    dy: f32,
+) {
+    if dx.abs() < 0.05 && dy.abs() < 0.05 {
+        return;
+    }
+    for index in views.navigated() {
+        views.get_mut(index).move_by_screen_delta(dx, dy);
+        update_view(gpu_context, views, index, ui_state);
+    }
+    window.request_redraw();
+}
+
+/// Moves the navigated views by a keyboard `motion`, zooming around their centers
+fn navigate_views(
+    gpu_context: &mut GpuContext,
+    views: &mut Views,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+    motion: Motion,
+    language: Language,
+) {
+    let mut limited = false;
+    for index in views.navigated() {
+        let view = views.get_mut(index);
+        let dimensions = view.dimensions();
+        if motion.pan != [0.0, 0.0] {
+            view.move_by_screen_delta(
+                motion.pan[0] * dimensions.width as f32,
+                motion.pan[1] * dimensions.height as f32,
+            );
+        }
+        if motion.zoom != 0.0 {
+            limited |= view.zoom_with_anchor(motion.zoom_delta(), None);
+        }
+        update_view(gpu_context, views, index, ui_state);
+    }
+    if limited && motion.zoom > 0.0 {
+        ui_state.queue_message(overlay::Message::Notified(
+            language.tr("notice-max-zoom").to_owned(),
+        ));
+    }
+}
+
+/// Moves the navigated views by a touch `motion`
+fn touch_views(
+    gpu_context: &mut GpuContext,
+    views: &mut Views,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+    motion: TouchMotion,
+    language: Language,
+) {
+    let zoom = motion
+        .zoom
+        .map(|(magnification, anchor)| (magnification, views.to_active(anchor)));
+    let mut limited = false;
+    for index in views.navigated() {
+        let view = views.get_mut(index);
+        match zoom {
+            Some((magnification, anchor)) => {
+                limited |= view.pinch(motion.dx, motion.dy, magnification, anchor)
+            }
+            None => view.move_by_screen_delta(motion.dx, motion.dy),
+        }
+        update_view(gpu_context, views, index, ui_state);
+    }
+    if limited && zoom.is_some_and(|(magnification, _)| magnification > 1.0) {
+        ui_state.queue_message(overlay::Message::Notified(
+            language.tr("notice-max-zoom").to_owned(),
+        ));
+    }
+}
+
+/// Draws the selection between the window points `start` and `end` over the navigated views, or
+/// hides it
+fn show_selection(gpu_context: &mut GpuContext, views: &Views, selection: Option<(Point, Point)>) {
+    for index in 0..views.len() {
+        let rect = selection
+            .filter(|_| views.navigated().contains(&index))
+            .and_then(|(start, end)| {
+                views
+                    .get(index)
+                    .selection(views.to_active(start), views.to_active(end))
+            });
+        gpu_context.set_selection(index, rect);
+    }
+}
+
+/// Zooms the navigated views to the selection between the window points `start` and `end`.
+/// Selections too small to be a click leave the views as they are
+fn fit_views(
+    gpu_context: &mut GpuContext,
+    views: &mut Views,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+    (start, end): (Point, Point),
+    language: Language,
+) {
+    let (start, end) = (views.to_active(start), views.to_active(end));
+    let mut limited = false;
+    for index in views.navigated() {
+        match views.get_mut(index).fit_rect(start, end) {
+            Ok(true) => update_view(gpu_context, views, index, ui_state),
+            Ok(false) => {}
+            Err(_) => limited = true,
+        }
+    }
+    if limited {
+        ui_state.queue_message(overlay::Message::Notified(
+            language.tr("notice-max-zoom").to_owned(),
+        ));
+    }
+}
+
+/// Centers the navigated views on the window `point` and zooms them in by `magnification`
+fn center_views(
+    gpu_context: &mut GpuContext,
+    views: &mut Views,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+    point: Point,
+    magnification: f32,
+    language: Language,
+) {
+    let point = views.to_active(point);
+    let mut limited = false;
+    for index in views.navigated() {
+        limited |= views.get_mut(index).center_and_zoom(point, magnification);
+        update_view(gpu_context, views, index, ui_state);
+    }
+    if limited {
+        ui_state.queue_message(overlay::Message::Notified(
+            language.tr("notice-max-zoom").to_owned(),
+        ));
+    }
+}
+
+/// Switches a view between the Mandelbrot set and the Julia set of `seed`, keeping it in place
+fn set_julia(
+    gpu_context: &mut GpuContext,
+    views: &mut Views,
+    index: usize,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+    seed: Option<JuliaSeed>,
+) {
+    let view = views.get_mut(index);
+    view.set_julia(seed);
+    gpu_context.set_fractal(index, view.fractal());
+    update_view(gpu_context, views, index, ui_state);
+    if index == views.active() {
+        ui_state.queue_message(overlay::Message::ViewActivated(active_view(views)));
+    }
+}
+
+/// Queues the coordinates of a view for calculation
+fn update_view(
+    gpu_context: &mut GpuContext,
+    views: &Views,
+    index: usize,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+) {
+    report_error(
+        ui_state,
+        gpu_context.update_params(index, views.get(index).coords().clone()),
+    );
+}
+
+/// Starts the calculation of the current frame of `recording` in the active view, its image is
+/// saved once it's drawn
+fn record_frame(
+    gpu_context: &mut GpuContext,
+    views: &Views,
+    recording: &recording::Recording,
+    settings: String,
+    proxy: &EventLoopProxy<UserEvent>,
+) -> Result<(), String> {
+    let view = recording.frame_view().map_err(|e| e.to_string())?;
+    let metadata = screenshot::Metadata {
+        location: view.location().to_string(),
+        settings,
+    };
+    let frame = gpu_context
+        .render_frame(
+            views.active(),
+            view.coords().clone(),
+            view.fractal(),
+            recording.depth(),
+        )
+        .map_err(|e| e.to_string())?;
+    let sink = recording.frame_sink();
+    let proxy = proxy.clone();
+    let export = async move {
+        let result = match frame.await {
+            Ok(image) => sink.save(&image, &metadata),
+            Err(e) => Err(e.into()),
+        };
+        let _ = proxy.send_event(UserEvent::FrameRecorded(result));
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(move || pollster::block_on(export));
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(export);
+    Ok(())
+}
+
+/// Queues the coordinates of all views for calculation
+fn update_views(
+    gpu_context: &mut GpuContext,
+    views: &Views,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+) {
+    for index in 0..views.len() {
+        update_view(gpu_context, views, index, ui_state);
+    }
+}
+
+/// Queues the layout and coordinates of all views after the window or the layout has changed
+fn resize_views(
+    gpu_context: &mut GpuContext,
+    views: &Views,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+) {
+    for index in 0..views.len() {
+        let view = views.get(index);
+        report_error(
+            ui_state,
+            gpu_context.resize_and_update_params(
+                index,
+                views.rect(index),
+                view.scale_factor(),
+                view.coords().clone(),
+            ),
+        );
+    }
+}
+
+fn active_view(views: &Views) -> overlay::ActiveView {
+    let view = views.active_view();
+    overlay::ActiveView {
+        index: views.active(),
+        scale_factor: view.scale_factor(),
+        precision: view.precision(),
+        fractal: view.fractal(),
+    }
+}
+
+/// Returns the Julia set parameter under the window `pointer`. Only the plain Mandelbrot set maps
+/// its points to Julia sets of the same formula as the preview
+fn julia_point(views: &Views, pointer: Option<Point>) -> Option<[f32; 2]> {
+    let pointer = pointer?;
+    let view = views.active_view();
+    let fractal = view.fractal();
+    if fractal.kind != FractalKind::Mandelbrot
+        || fractal.inverted
+        || fractal.power != 2
+        || fractal.julia.is_some()
+    {
+        return None;
+    }
+    Some(view.plane_point(views.to_active(pointer)))
+}
+
+/// Returns true if `modifiers` turn a key press into a shortcut
+fn is_shortcut(modifiers: ModifiersState) -> bool {
+    if cfg!(target_os = "macos") {
+        modifiers.super_key()
+    } else {
+        modifiers.control_key()
+    }
+}
+
+/// Navigates to the location in `text`. Returns the notice to display
+fn paste_location(
+    view_state: &mut ViewState,
+    text: &str,
+    language: Language,
+) -> Result<String, String> {
+    let (format, location) = location::sniff(text).map_err(|e| e.to_string())?;
+    view_state.jump_to(&location).map_err(|e| e.to_string())?;
+    let format = language.tr(match format {
+        location::Format::Native => "format-native",
+        location::Format::Kfr => "format-kfr",
+        location::Format::Pair => "format-pair",
+        location::Format::Url => "format-url",
+    });
+    Ok(language.format("notice-location-pasted", &[&format]))
+}
+
+/// Translation key of the message of a GPU warning
+fn warning_key(warning: gpu::Warning) -> &'static str {
+    match warning {
+        gpu::Warning::Stalled => "warning-stalled",
+        gpu::Warning::Lost => "warning-lost",
+        gpu::Warning::DeviceLost => "warning-device-lost",
+    }
+}
+
+/// Enters demo mode in the active view. Playlist locations are meant for the plain Mandelbrot set
+fn start_demo(
+    playlist: &[demo::Entry],
+    gpu_context: &mut GpuContext,
+    views: &mut Views,
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+) -> demo::Demo {
+    let fractal = FractalParams::default();
+    views.active_view_mut().set_fractal(fractal.clone());
+    gpu_context.set_fractal(views.active(), fractal);
+    ui_state.queue_message(overlay::Message::ViewActivated(active_view(views)));
+    ui_state.queue_message(overlay::Message::DemoRunning(true));
+    demo::Demo::new(playlist.to_vec())
+}
+
+/// Returns the diagnostics requested by `args`. The trace directory is created if missing
+fn gpu_diagnostics(args: &cli::Args) -> gpu::Diagnostics {
+    let trace_dir = args
+        .gpu_trace
+        .clone()
+        .filter(|dir| match std::fs::create_dir_all(dir) {
+            Ok(()) => true,
+            Err(e) => {
+                log::error!("Not recording a GPU trace into {}: {}", dir.display(), e);
+                false
+            }
+        });
+    gpu::Diagnostics {
+        trace_dir,
+        debug: args.gpu_debug,
+    }
+}
+
+/// Balancer of the frames outside deterministic mode. On the web the frames end once the browser
+/// resolves the mappings after their work, so they're timed by `performance.now()` like natively
+fn default_fps_balancer(target_fps: f64) -> FpsBalancer {
+    FpsBalancer::new(target_fps)
+}
+
+/// Logs the error and displays it in the overlay
+fn report_error<E: std::fmt::Display>(
+    ui_state: &mut iced_runtime::program::State<overlay::Overlay>,
+    result: Result<(), E>,
+) {
+    if let Err(e) = result {
+        log::error!("{}", e);
+        ui_state.queue_message(overlay::Message::ErrorReported(e.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering_pauses_while_occluded() {
+        let mut presenter = Presenter::default();
+        assert!(presenter.render());
+        assert!(!presenter.set_occluded(true));
+        // The frame requested by the last submission is deferred, breaking the render loop
+        assert!(!presenter.render());
+        assert!(!presenter.render());
+        assert!(presenter.set_occluded(false));
+        assert!(presenter.render());
+    }
+
+    #[test]
+    fn calculation_stops_while_unfocused() {
+        let mut presenter = Presenter::default();
+        assert!(presenter.iterate());
+        assert!(!presenter.set_focused(false));
+        // Frames are still shown, but the calculation doesn't ask for the next one
+        assert!(presenter.render());
+        assert!(!presenter.iterate());
+        // Focus alone doesn't resume a covered window
+        assert!(!presenter.set_occluded(true));
+        assert!(!presenter.set_focused(true));
+        assert!(presenter.set_occluded(false));
+        assert!(presenter.iterate());
+        assert!(!presenter.set_focused(true));
+    }
+
+    #[test]
+    fn idle_views_stop_drawing() {
+        let mut presenter = Presenter::default();
+        assert!(presenter.follow(true));
+        assert!(presenter.follow(true));
+        // One more frame shows the final state, then the loop stops until the views change
+        assert!(presenter.follow(false));
+        assert!(!presenter.follow(false));
+        assert!(!presenter.follow(false));
+        assert!(presenter.follow(true));
+        assert!(presenter.follow(false));
+    }
+
+    #[test]
+    fn nothing_to_resume_without_requests() {
+        let mut presenter = Presenter::default();
+        assert!(!presenter.set_occluded(true));
+        assert!(!presenter.set_occluded(false));
+        // Repeated events don't resume twice
+        presenter.set_occluded(true);
+        presenter.render();
+        assert!(presenter.set_occluded(false));
+        assert!(!presenter.set_occluded(false));
+    }
+}
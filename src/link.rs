@@ -0,0 +1,299 @@
+//! Views shared as links. On the web the fragment of the page URL follows the active view, so that
+//! it can be copied, and the browser history navigates between the views it held.
+//!
+//! Fragments are the `key value` fields of a bookmark and the colors as `key=value` pairs joined
+//! by `&`, with spaces of the values as `+`, like `#version=1&x=ffffffff:80000000&...`
+
+use std::fmt::Write;
+use thiserror::Error;
+
+use crate::bookmarks::{Bookmark, BookmarkError};
+use crate::gpu::{ColorParams, GAMMA_RANGE};
+
+const VERSION: u32 = 1;
+
+/// View a link shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub bookmark: Bookmark,
+    pub colors: ColorParams,
+}
+
+#[derive(Debug, Error)]
+pub enum LinkError {
+    #[error("Unsupported link version {0}")]
+    Version(String),
+    #[error("Link is missing {0}")]
+    MissingField(&'static str),
+    #[error("Link has invalid {0}")]
+    InvalidField(&'static str),
+    #[error("Invalid link location: {0}")]
+    Bookmark(#[from] BookmarkError),
+}
+
+/// Returns the fragment of a link to `bookmark` with `colors`, without the `#`. The name of the
+/// bookmark is left out
+pub fn encode(bookmark: &Bookmark, colors: ColorParams) -> String {
+    let mut lines = String::new();
+    writeln!(lines, "version {}", VERSION).unwrap();
+    bookmark.encode(&mut lines);
+    writeln!(lines, "smooth_colors {}", colors.smooth).unwrap();
+    writeln!(lines, "equalize_colors {}", colors.equalize).unwrap();
+    writeln!(lines, "gamma {}", colors.gamma).unwrap();
+    let fields: Vec<_> = lines
+        .lines()
+        .filter(|line| !line.starts_with("name "))
+        .map(|line| line.replacen(' ', "=", 1).replace(' ', "+"))
+        .collect();
+    fields.join("&")
+}
+
+/// Parses a fragment written by [`encode`], with or without the `#`
+pub fn decode(fragment: &str) -> Result<Link, LinkError> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+    let mut lines = vec!["name Link".to_owned()];
+    lines.extend(
+        fragment
+            .split('&')
+            .map(|field| field.replacen('=', " ", 1).replace('+', " ")),
+    );
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+
+    let field = |name: &'static str| {
+        lines
+            .iter()
+            .find_map(|line| {
+                let (key, value) = line.split_once(' ')?;
+                (key == name).then_some(value.trim())
+            })
+            .ok_or(LinkError::MissingField(name))
+    };
+    fn parse<T: std::str::FromStr>(name: &'static str, value: &str) -> Result<T, LinkError> {
+        value.parse().map_err(|_| LinkError::InvalidField(name))
+    }
+
+    let version = field("version")?;
+    if parse::<u32>("version", version).ok() != Some(VERSION) {
+        return Err(LinkError::Version(version.to_owned()));
+    }
+    let bookmark = Bookmark::decode(&lines)?;
+    if bookmark.max_depth == 0 {
+        return Err(LinkError::InvalidField("max_depth"));
+    }
+    let colors = ColorParams {
+        smooth: parse("smooth_colors", field("smooth_colors")?)?,
+        equalize: parse("equalize_colors", field("equalize_colors")?)?,
+        gamma: Some(parse("gamma", field("gamma")?)?)
+            .filter(|gamma| GAMMA_RANGE.contains(gamma))
+            .ok_or(LinkError::InvalidField("gamma"))?,
+    };
+    Ok(Link { bookmark, colors })
+}
+
+#[cfg(target_arch = "wasm32")]
+pub use self::browser::{read, LinkSync};
+
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use wasm_bindgen::prelude::*;
+
+    use super::{decode, Link};
+
+    /// Time without navigation before the fragment follows the view, in milliseconds
+    const DEBOUNCE_MS: i32 = 500;
+
+    fn hash() -> Option<String> {
+        let hash = web_sys::window()?.location().hash().ok()?;
+        // Fragments copied from elsewhere might have escaped characters
+        js_sys::decode_uri_component(&hash).ok().map(String::from)
+    }
+
+    /// Returns the view the page was opened with. Malformed fragments are ignored
+    pub fn read() -> Option<Link> {
+        parse(&hash()?)
+    }
+
+    fn parse(hash: &str) -> Option<Link> {
+        if hash.len() <= 1 {
+            return None;
+        }
+        match decode(hash) {
+            Ok(link) => Some(link),
+            Err(e) => {
+                log::warn!("Ignoring malformed link: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Keeps the fragment in sync with the view, both ways
+    pub struct LinkSync {
+        /// Fragment written last, with the `#`
+        written: Rc<RefCell<String>>,
+        /// Write waiting for the navigation to pause
+        pending: Option<i32>,
+        /// Fragment the pending write is of
+        fragment: String,
+        _listener: Closure<dyn FnMut()>,
+    }
+
+    impl LinkSync {
+        /// Starts listening to fragments changed by the user or the browser history, which are
+        /// passed to `navigate`
+        pub fn new(mut navigate: impl FnMut(Link) + 'static) -> Self {
+            let written = Rc::new(RefCell::new(hash().unwrap_or_default()));
+            let listener = {
+                let written = written.clone();
+                Closure::<dyn FnMut()>::new(move || {
+                    let Some(hash) = hash() else {
+                        return;
+                    };
+                    // Fragments written here change too
+                    if *written.borrow() != hash {
+                        *written.borrow_mut() = hash.clone();
+                        if let Some(link) = parse(&hash) {
+                            navigate(link);
+                        }
+                    }
+                })
+            };
+            if let Some(window) = web_sys::window() {
+                let _ = window.add_event_listener_with_callback(
+                    "hashchange",
+                    listener.as_ref().unchecked_ref(),
+                );
+            }
+            Self {
+                written,
+                pending: None,
+                fragment: String::new(),
+                _listener: listener,
+            }
+        }
+
+        /// Writes `fragment` once it stays the same for a while, a new one restarts the wait
+        pub fn update(&mut self, fragment: String) {
+            let unchanged = match self.pending {
+                Some(_) => fragment == self.fragment,
+                None => format!("#{}", fragment) == *self.written.borrow(),
+            };
+            let Some(window) = web_sys::window() else {
+                return;
+            };
+            if unchanged {
+                return;
+            }
+            if let Some(handle) = self.pending.take() {
+                window.clear_timeout_with_handle(handle);
+            }
+            let written = self.written.clone();
+            let hash = format!("#{}", fragment);
+            let write = Closure::once_into_js(move || {
+                *written.borrow_mut() = hash.clone();
+                if let Some(window) = web_sys::window() {
+                    // Every write is an entry of the browser history
+                    let _ = window.location().set_hash(&hash);
+                }
+            });
+            self.pending = window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    write.unchecked_ref(),
+                    DEBOUNCE_MS,
+                )
+                .ok();
+            self.fragment = fragment;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::{FractalParams, JuliaSeed};
+    use crate::primitives::{Coordinates, Dimensions};
+    use crate::session::encode_wide;
+    use crate::view_state::Precision;
+
+    fn link() -> Link {
+        let mut coords = Coordinates::new(-0.7436, 0.1318, 1e-3, 10).unwrap();
+        for _ in 0..40 {
+            coords.zoom_with_anchor(0.5, 400.0, 250.0, 1.0).unwrap();
+        }
+        coords.set_rotation(12.5).unwrap();
+        Link {
+            bookmark: Bookmark {
+                name: "Link".to_owned(),
+                coords,
+                dimensions: Dimensions::new_nonzero(1280, 720),
+                scale_factor: 1.25,
+                fractal: FractalParams {
+                    julia: JuliaSeed::from_f64(-0.8, 0.156),
+                    ..Default::default()
+                },
+                precision: Precision::Manual(96),
+                max_depth: 5000,
+            },
+            colors: ColorParams {
+                smooth: true,
+                equalize: false,
+                gamma: 2.4,
+            },
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let link = link();
+        let fragment = encode(&link.bookmark, link.colors);
+        assert!(!fragment.contains([' ', '\n', '#']));
+        assert!(!fragment.contains("name"));
+        assert_eq!(decode(&fragment).unwrap(), link);
+        assert_eq!(decode(&format!("#{}", fragment)).unwrap(), link);
+    }
+
+    #[test]
+    fn malformed_fragments_are_rejected() {
+        let link = link();
+        let fragment = encode(&link.bookmark, link.colors);
+        // Truncated fragments never panic, the ones missing fields are rejected
+        for end in 0..fragment.len() {
+            let _ = decode(&fragment[..end]);
+        }
+        let without_colors = &fragment[..fragment.find("&smooth_colors").unwrap()];
+        assert!(matches!(
+            decode(without_colors),
+            Err(LinkError::MissingField("smooth_colors"))
+        ));
+        assert!(decode("").is_err());
+        assert!(decode("#").is_err());
+        assert!(decode("#re=-0.5&im=0").is_err());
+        assert!(matches!(
+            decode(&fragment.replace("version=1", "version=2")),
+            Err(LinkError::Version(_))
+        ));
+
+        let x = encode_wide(&link.bookmark.coords.x);
+        // A word short of the other numbers
+        let truncated = fragment.replace(&x, &x[9..]);
+        assert!(matches!(
+            decode(&truncated),
+            Err(LinkError::Bookmark(BookmarkError::Coordinates(_)))
+        ));
+        // More words than any pipeline has
+        let words = vec!["00000000"; 1000].join(":");
+        let step = encode_wide(&link.bookmark.coords.step);
+        let long = fragment
+            .replace(&x, &words)
+            .replace(&encode_wide(&link.bookmark.coords.y), &words)
+            .replace(&step, &words);
+        assert!(matches!(
+            decode(&long),
+            Err(LinkError::Bookmark(BookmarkError::Coordinates(_)))
+        ));
+        assert!(decode(&fragment.replace(&x, "xyz")).is_err());
+        assert!(decode(&fragment.replace("gamma=2.4", "gamma=9")).is_err());
+        assert!(decode(&fragment.replace("max_depth=5000", "max_depth=0")).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! Plane locations exchanged as text, and recognition of the formats other people paste them in
+
+use std::fmt;
+use thiserror::Error;
+
+use crate::float::WideFloat;
+use crate::primitives::MAX_WORD_COUNT;
+
+/// Word count locations are parsed with. Trimmed to the precision of the view afterwards
+const PARSE_WORD_COUNT: usize = MAX_WORD_COUNT;
+
+/// Prefix of our own location string
+const NATIVE_PREFIX: &str = "mandelbrot";
+
+/// Point in the plane to show and, optionally, how much of the plane around it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    /// Real coordinate of the view center
+    pub re: WideFloat,
+    /// Imaginary coordinate of the view center, in the orientation of the view
+    pub im: WideFloat,
+    /// Plane extent across the shortest window side. Current zoom is kept if missing
+    pub span: Option<WideFloat>,
+}
+
+/// Text formats locations are recognized in, in the order they're tried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `mandelbrot re=<re> im=<im> span=<span>`, as copied from this program
+    Native,
+    /// `Re: <re>`, `Im: <im>` and `Zoom: <zoom>` lines of a Kalles Fraktaler .kfr file
+    Kfr,
+    /// Bare `<re>, <im>` pair
+    Pair,
+    /// URL with a `#re=<re>&im=<im>&span=<span>` fragment
+    Url,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Native, Format::Kfr, Format::Pair, Format::Url];
+
+    fn parse(&self, text: &str) -> Option<Location> {
+        match self {
+            Format::Native => parse_native(text),
+            Format::Kfr => parse_kfr(text),
+            Format::Pair => parse_pair(text),
+            Format::Url => parse_url(text),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Native => "location string",
+            Format::Kfr => ".kfr location",
+            Format::Pair => "\"re, im\" pair",
+            Format::Url => "location URL",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+#[error("Unrecognized location, tried {}", Format::ALL.map(|f| f.to_string()).join(", "))]
+pub struct UnrecognizedLocation;
+
+/// Parses a location in the first format that recognizes `text`
+pub fn sniff(text: &str) -> Result<(Format, Location), UnrecognizedLocation> {
+    Format::ALL
+        .into_iter()
+        .find_map(|format| Some((format, format.parse(text)?)))
+        .ok_or(UnrecognizedLocation)
+}
+
+impl Location {
+    fn fields(&self) -> Vec<String> {
+        let mut fields = vec![format!("re={}", self.re), format!("im={}", self.im)];
+        if let Some(span) = &self.span {
+            fields.push(format!("span={}", span));
+        }
+        fields
+    }
+
+    /// Builds a location from `key=value` fields. `re` and `im` are required
+    fn from_fields<'a>(fields: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let (mut re, mut im, mut span) = (None, None, None);
+        for field in fields {
+            let (key, value) = field.split_once('=')?;
+            let slot = match key {
+                "re" => &mut re,
+                "im" => &mut im,
+                "span" => &mut span,
+                _ => return None,
+            };
+            *slot = Some(parse_decimal(value, PARSE_WORD_COUNT)?);
+        }
+        Some(Location {
+            re: re?,
+            im: im?,
+            span,
+        })
+    }
+}
+
+/// Formats the location as our own location string
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", NATIVE_PREFIX, self.fields().join(" "))
+    }
+}
+
+fn parse_native(text: &str) -> Option<Location> {
+    let mut tokens = text.split_whitespace();
+    if tokens.next()? != NATIVE_PREFIX {
+        return None;
+    }
+    Location::from_fields(tokens)
+}
+
+fn parse_kfr(text: &str) -> Option<Location> {
+    let field = |name: &str| {
+        text.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then_some(value.trim())
+        })
+    };
+    Some(Location {
+        re: parse_decimal(field("Re")?, PARSE_WORD_COUNT)?,
+        im: parse_decimal(field("Im")?, PARSE_WORD_COUNT)?,
+        span: match field("Zoom") {
+            Some(zoom) => Some(span_from_kfr_zoom(zoom)?),
+            None => None,
+        },
+    })
+}
+
+/// Zoom 1 shows the plane from -2 to 2 across the height of the view
+fn span_from_kfr_zoom(zoom: &str) -> Option<WideFloat> {
+    // Zoom is usually far out of f64 range, so only the mantissa is divided
+    let (mantissa, exponent) = match zoom.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+        None => (zoom, 0),
+    };
+    let mantissa: f64 = mantissa.parse().ok()?;
+    if !(mantissa.is_finite() && mantissa > 0.0) {
+        return None;
+    }
+    let span = format!("{:e}", 4.0 / mantissa);
+    let (span_mantissa, span_exponent) = span.split_once('e')?;
+    let span_exponent = span_exponent.parse::<i32>().ok()?.checked_sub(exponent)?;
+    parse_decimal(
+        &format!("{}e{}", span_mantissa, span_exponent),
+        PARSE_WORD_COUNT,
+    )
+}
+
+fn parse_pair(text: &str) -> Option<Location> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .unwrap_or(text);
+    let (re, im) = text.split_once(',')?;
+    Some(Location {
+        re: parse_decimal(re, PARSE_WORD_COUNT)?,
+        im: parse_decimal(im, PARSE_WORD_COUNT)?,
+        span: None,
+    })
+}
+
+fn parse_url(text: &str) -> Option<Location> {
+    let text = text.trim();
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+    let (_, fragment) = text.split_once('#')?;
+    Location::from_fields(fragment.split('&'))
+}
+
+/// Parses a decimal into a number of `size` words
+fn parse_decimal(text: &str, size: usize) -> Option<WideFloat> {
+    WideFloat::from_decimal_str(text, size).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(text: &str) -> WideFloat {
+        parse_decimal(text, PARSE_WORD_COUNT).unwrap()
+    }
+
+    fn location(re: &str, im: &str, span: Option<&str>) -> Location {
+        Location {
+            re: decimal(re),
+            im: decimal(im),
+            span: span.map(decimal),
+        }
+    }
+
+    #[test]
+    fn native_format() {
+        let expected = location("-0.75", "0.1", Some("1e-30"));
+        let text = expected.to_string();
+        assert!(text.starts_with("mandelbrot re=-0.75 im=0.1"));
+        assert!(text.contains(" span=0.000"));
+        assert_eq!(sniff(&text).unwrap(), (Format::Native, expected));
+
+        // Fields may come in any order, span is optional
+        assert_eq!(
+            sniff("  mandelbrot im=0.1 re=-0.75 \n").unwrap(),
+            (Format::Native, location("-0.75", "0.1", None))
+        );
+        assert!(parse_native("mandelbrot re=-0.75").is_none());
+        assert!(parse_native("mandelbrot re=-0.75 im=0.1 depth=5").is_none());
+    }
+
+    #[test]
+    fn kfr_format() {
+        let text = "Re: -1.76938317919551501821384728608547378290574726365475143746552821652788819126\r\n\
+                    Im: 0.00423684791873677221492650717136799707668267091740375727945943565011234400\r\n\
+                    Zoom: 2.5E50\r\n\
+                    Iterations: 200000\r\n";
+        let (format, parsed) = sniff(text).unwrap();
+        assert_eq!(format, Format::Kfr);
+        assert_eq!(
+            parsed,
+            location(
+                "-1.76938317919551501821384728608547378290574726365475143746552821652788819126",
+                "0.00423684791873677221492650717136799707668267091740375727945943565011234400",
+                Some("1.6e-50"),
+            )
+        );
+
+        assert_eq!(
+            parse_kfr("Re: 1\nIm: 2\nZoom: 1"),
+            Some(location("1", "2", Some("4")))
+        );
+        assert_eq!(parse_kfr("Re: 1\nIm: 2"), Some(location("1", "2", None)));
+        assert!(parse_kfr("Re: 1\nIm: 2\nZoom: 0").is_none());
+        assert!(parse_kfr("Re: 1\nZoom: 1").is_none());
+    }
+
+    #[test]
+    fn pair_format() {
+        let expected = (Format::Pair, location("-0.75", "0.1", None));
+        assert_eq!(sniff("-0.75, 0.1").unwrap(), expected);
+        assert_eq!(sniff("(-0.75,0.1)\n").unwrap(), expected);
+        assert!(parse_pair("-0.75, 0.1, 5").is_none());
+        assert!(parse_pair("-0.75 0.1").is_none());
+    }
+
+    #[test]
+    fn url_format() {
+        let expected = location("-0.75", "0.1", Some("0.5"));
+        let url = format!(
+            "https://example.com/mandelbrot/#{}",
+            expected.fields().join("&")
+        );
+        assert_eq!(sniff(&url).unwrap(), (Format::Url, expected));
+        assert!(parse_url("https://example.com/#section").is_none());
+        assert!(parse_url("https://example.com/#re=1&im=").is_none());
+        assert!(parse_url("see https://example.com/#re=1&im=2").is_none());
+    }
+
+    #[test]
+    fn ambiguous_inputs() {
+        // Earlier formats win, a URL in a .kfr comment doesn't override the block
+        let text = "Re: 1\nIm: 2\nComment: https://example.com/#re=3&im=4";
+        assert_eq!(
+            sniff(text).unwrap(),
+            (Format::Kfr, location("1", "2", None))
+        );
+        // A decimal comma can't be told apart from a pair
+        assert_eq!(
+            sniff("1,5").unwrap(),
+            (Format::Pair, location("1", "5", None))
+        );
+        // Commas before the fragment don't make a pair
+        let url = "https://example.com/a,b#re=1&im=2";
+        assert_eq!(sniff(url).unwrap().0, Format::Url);
+        // A .kfr field that looks like a pair is still a .kfr field
+        assert_eq!(sniff("Re: 1, 2\nIm: 3").ok(), None);
+        // Prose around coordinates isn't guessed at
+        assert!(sniff("try -0.75, 0.1 at depth 500").is_err());
+    }
+
+    #[test]
+    fn unrecognized_location_lists_formats() {
+        let error = sniff("hello").unwrap_err().to_string();
+        for format in Format::ALL {
+            assert!(error.contains(&format.to_string()), "{}", error);
+        }
+    }
+}
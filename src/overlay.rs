@@ -1,11 +1,39 @@
 use iced::{Color, Theme};
 use iced_wgpu::Renderer;
-use iced_widget::{button, column, container, mouse_area, scrollable, slider, text};
+use iced_widget::{
+    button, checkbox, column, container, mouse_area, pick_list, progress_bar, radio, row,
+    scrollable, slider, text, text_input,
+};
 use iced_winit::core::alignment;
+use iced_winit::core::widget::{
+    self,
+    operation::{Focusable, Operation, Outcome},
+};
+use iced_winit::core::Rectangle;
 use iced_winit::core::{Element, Length};
 use iced_winit::runtime::{Command, Program};
 use winit::event_loop::EventLoopProxy;
 
+use crate::bookmarks::Bookmark;
+use crate::defaults;
+use crate::eta::Estimate;
+use crate::fps_balancer::TARGET_FPS_RANGE;
+use crate::fractal::{
+    FractalKind, FractalParams, IterationParams, ESCAPE_RADIUS_RANGE, POWER_RANGE,
+};
+use crate::gpu::{
+    ColorParams, DisplayOptions, PowerPreference, GAMMA_RANGE, GLITCHED, MAX_POSTER_SIDE, SETTLED,
+};
+use crate::i18n::Language;
+use crate::palette::{Palette, Preset};
+use crate::pan::PanBinding;
+use crate::primitives::Dimensions;
+use crate::recording::{self, MAX_FRAMES};
+use crate::scroll::{ScrollCalibration, SENSITIVITY_RANGE};
+use crate::settings::{Settings, MAX_PRECISION_BITS};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::video::{VideoFormat, VideoOptions, MAX_FPS};
+use crate::view_state::Precision;
 use crate::UserEvent;
 
 /// Iced Program responsible for control panel UI
@@ -15,34 +43,147 @@ pub struct Overlay {
     event_loop_proxy: EventLoopProxy<UserEvent>,
     /// Indicates if pointer is interacting with control panel UI
     pointer_captured: bool,
+    /// A text field of the control panel has keyboard focus
+    text_focused: bool,
     /// Determines if control panel is displayed or hidden
     settings_open: bool,
     /// Max calculation depth
     max_depth: u32,
+    /// Escape condition of all views
+    iteration: IterationParams,
     /// Square root of fractal view scale factor. Square to get an actual scale factor value.
     /// Stored as sqrt to allow exponential scaling in the linear slider
     scale_factor_sqrt: f64,
-    /// Amount of extra 32 bit words of precision
-    precision_words: u32,
+    /// Precision of the active view
+    precision: Precision,
+    /// Frame rate the iterations are balanced for
+    target_fps: f64,
+    /// Display expert settings
+    advanced_open: bool,
+    /// Calculated fractal
+    fractal: FractalParams,
     /// Statistics and information
     info: Info,
+    /// Last reported error, displayed until dismissed
+    error: Option<String>,
+    /// Failure that made rendering fall back to lower precision, displayed until retried
+    degraded: Option<String>,
+    /// Outcome of the last user action, displayed until dismissed or replaced
+    notice: Option<String>,
+    /// The window is split into two views
+    split: bool,
+    /// Navigation is mirrored to both views
+    linked: bool,
+    /// The Julia set of the point under the cursor is shown in an inset
+    julia_preview: bool,
+    /// Views are smoothed by a post-process pass
+    antialiasing: bool,
+    /// Views show their previous frames while new ones converge
+    reprojection: bool,
+    /// How iteration counts are colored
+    colors: ColorParams,
+    /// Colors of the views
+    palette: Palette,
+    /// Index of the palette stop the color sliders edit
+    edited_stop: usize,
+    /// The surface uses a 16-bit float format
+    deep_color: bool,
+    /// Wheel ticks zoom in a short animation instead of at once
+    smooth_zoom: bool,
+    /// How much scrolling zooms
+    scroll: ScrollCalibration,
+    /// Button that drags the view
+    pan_binding: PanBinding,
+    /// Presentation and adapter of the context
+    display: DisplayOptions,
+    /// Index of the view the settings apply to
+    active_view: usize,
+    /// Demo mode is cycling through locations, the control panel is hidden
+    demo: bool,
+    /// Language of the interface
+    language: Language,
+    /// Saved locations, in the order they were saved
+    bookmarks: Vec<Bookmark>,
+    /// Bookmark being renamed and its edited name
+    renaming: Option<(usize, String)>,
+    /// Edited width and height of the poster to export
+    poster_size: (String, String),
+    /// Bookmarks marked as the start and the end of the zoom to record
+    recording_marks: (Option<Bookmark>, Option<Bookmark>),
+    /// Edited frame count and depth of the zoom to record
+    recording_frames: String,
+    recording_depth: String,
+    /// Recordings are encoded into a video by ffmpeg, at the edited frame rate
+    #[cfg(not(target_arch = "wasm32"))]
+    recording_video: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    recording_fps: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    video_format: VideoFormat,
 }
 
 impl Overlay {
     /// Creates a new cotrol panel instance
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         event_loop_proxy: EventLoopProxy<UserEvent>,
         scale_factor: f64,
         max_depth: u32,
+        precision: Precision,
+        fractal: FractalParams,
+        language: Language,
+        scroll: ScrollCalibration,
+        pan_binding: PanBinding,
+        display: DisplayOptions,
+        bookmarks: Vec<Bookmark>,
     ) -> Overlay {
         Overlay {
             event_loop_proxy,
             pointer_captured: false,
+            text_focused: false,
             settings_open: false,
             max_depth,
+            iteration: IterationParams::default(),
             scale_factor_sqrt: scale_factor.sqrt(),
-            precision_words: 0,
+            precision,
+            target_fps: defaults::TARGET_FPS,
+            advanced_open: false,
+            fractal,
             info: Default::default(),
+            error: None,
+            degraded: None,
+            notice: None,
+            split: false,
+            linked: false,
+            julia_preview: false,
+            antialiasing: false,
+            reprojection: true,
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            edited_stop: 0,
+            deep_color: false,
+            smooth_zoom: true,
+            scroll,
+            pan_binding,
+            display,
+            active_view: 0,
+            demo: false,
+            language,
+            bookmarks,
+            renaming: None,
+            poster_size: (
+                DEFAULT_POSTER_SIDE.to_string(),
+                DEFAULT_POSTER_SIDE.to_string(),
+            ),
+            recording_marks: (None, None),
+            recording_frames: DEFAULT_RECORDING_FRAMES.to_string(),
+            recording_depth: max_depth.to_string(),
+            #[cfg(not(target_arch = "wasm32"))]
+            recording_video: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            recording_fps: DEFAULT_RECORDING_FPS.to_string(),
+            #[cfg(not(target_arch = "wasm32"))]
+            video_format: VideoFormat::default(),
         }
     }
 
@@ -50,22 +191,226 @@ impl Overlay {
     pub fn is_pointer_captured(&self) -> bool {
         self.pointer_captured
     }
+
+    /// Returns true if the control panel is shown, not only its toggle button
+    pub fn is_open(&self) -> bool {
+        self.settings_open
+    }
+
+    /// Returns true if keyboard input goes to a text field of the control panel
+    pub fn is_editing_text(&self) -> bool {
+        self.renaming.is_some() || self.text_focused
+    }
+
+    /// Returns the operation that keeps [`Overlay::is_editing_text`] up to date with the focus of
+    /// the widgets, to be applied after every update
+    pub fn focus_operation(&self) -> Box<dyn Operation<Message>> {
+        text_focus(self.text_focused)
+    }
+
+    /// Returns the edited poster size, unless a side isn't a number of pixels up to
+    /// [`MAX_POSTER_SIDE`]
+    fn poster_dimensions(&self) -> Option<Dimensions> {
+        let side = |edited: &str| {
+            edited
+                .trim()
+                .parse()
+                .ok()
+                .filter(|side| (1..=MAX_POSTER_SIDE).contains(side))
+        };
+        Some(Dimensions {
+            width: side(&self.poster_size.0)?,
+            height: side(&self.poster_size.1)?,
+        })
+    }
+
+    /// Returns the zoom to record, unless a bookmark isn't marked or the frame count, the depth or
+    /// the frame rate of the video isn't valid
+    fn recording_request(&self) -> Option<recording::Request> {
+        let (Some(start), Some(end)) = &self.recording_marks else {
+            return None;
+        };
+        Some(recording::Request {
+            start: start.clone(),
+            end: end.clone(),
+            frames: self
+                .recording_frames
+                .trim()
+                .parse()
+                .ok()
+                .filter(|frames| (2..=MAX_FRAMES).contains(frames))?,
+            depth: self
+                .recording_depth
+                .trim()
+                .parse()
+                .ok()
+                .filter(|depth| *depth > 0)?,
+            #[cfg(not(target_arch = "wasm32"))]
+            video: match self.recording_video {
+                true => Some(VideoOptions {
+                    fps: self
+                        .recording_fps
+                        .trim()
+                        .parse()
+                        .ok()
+                        .filter(|fps| (1..=MAX_FPS).contains(fps))?,
+                    format: self.video_format,
+                }),
+                false => None,
+            },
+        })
+    }
+
+    fn scroll_calibrated(&self) {
+        self.event_loop_proxy
+            .send_event(UserEvent::ScrollCalibrated(self.scroll))
+            .expect("Event loop closed")
+    }
+
+    fn palette_changed(&self) {
+        self.event_loop_proxy
+            .send_event(UserEvent::PaletteChanged(self.palette.clone()))
+            .expect("Event loop closed")
+    }
+
+    fn bookmarks_changed(&self) {
+        self.event_loop_proxy
+            .send_event(UserEvent::BookmarksChanged(self.bookmarks.clone()))
+            .expect("Event loop closed")
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     ToggleSettings,
     CapturePointer(bool),
+    TextFocusChanged(bool),
     MaxDepthChanged(u32),
+    EscapeRadiusChanged(f32),
+    DistanceEstimationToggled(bool),
     ScaleChanged(f64),
+    TargetFpsChanged(f64),
     PositionReset,
-    PrecisionChanged(u32),
+    Undo,
+    Redo,
+    ToggleAdvanced,
+    PrecisionChanged(Precision),
+    RotationChanged(f32),
+    FractalKindChanged(FractalKind),
+    InvertedPlaneToggled(bool),
+    PowerChanged(u32),
+    JuliaSetToggled(bool),
+    PickJuliaSeed,
     InfoUpdated(Info),
+    ErrorReported(String),
+    ErrorDismissed,
+    RenderingDegraded(String),
+    RetryRendering,
+    Notified(String),
+    NoticeDismissed,
+    SplitToggled(bool),
+    LinkToggled(bool),
+    JuliaPreviewToggled(bool),
+    AntialiasingToggled(bool),
+    ReprojectionToggled(bool),
+    SmoothColorsToggled(bool),
+    EqualizationToggled(bool),
+    GammaChanged(f32),
+    PresetSelected(Preset),
+    PaletteStopSelected(usize),
+    /// New color of the edited palette stop
+    PaletteStopEdited([u8; 3]),
+    DeepColorToggled(bool),
+    VsyncToggled(bool),
+    PowerPreferenceChanged(PowerPreference),
+    SmoothZoomToggled(bool),
+    ZoomSensitivityChanged(f32),
+    ZoomInversionToggled(bool),
+    PanBindingChanged(PanBinding),
+    /// Deep color mode in effect after it was toggled
+    DeepColorApplied(bool),
+    /// Max depth in effect after a bookmark was shown
+    MaxDepthApplied(u32),
+    /// Colors in effect after a session was restored or a link was opened
+    ColorsApplied(ColorParams),
+    ViewActivated(ActiveView),
+    CopySettings,
+    ExportFrame,
+    #[cfg(not(target_arch = "wasm32"))]
+    ExportIterations,
+    PosterWidthEdited(String),
+    PosterHeightEdited(String),
+    ExportPoster,
+    CancelPoster,
+    MarkRecordingStart(usize),
+    MarkRecordingEnd(usize),
+    RecordingFramesEdited(String),
+    RecordingDepthEdited(String),
+    #[cfg(not(target_arch = "wasm32"))]
+    RecordingVideoToggled(bool),
+    #[cfg(not(target_arch = "wasm32"))]
+    RecordingFpsEdited(String),
+    #[cfg(not(target_arch = "wasm32"))]
+    VideoFormatChanged(VideoFormat),
+    StartRecording,
+    CancelRecording,
+    PasteSettings,
+    SettingsApplied(Settings),
+    StartDemo,
+    /// Demo mode started or stopped
+    DemoRunning(bool),
+    LanguageChanged(Language),
+    SaveBookmark,
+    /// Snapshot of the active view to add to the bookmarks
+    BookmarkCreated(Bookmark),
+    JumpToBookmark(usize),
+    RenameBookmark(usize),
+    BookmarkNameEdited(String),
+    BookmarkRenamed,
+    DeleteBookmark(usize),
+}
+
+/// Settings of the view the control panel applies to
+#[derive(Debug, Clone)]
+pub struct ActiveView {
+    pub index: usize,
+    pub scale_factor: f64,
+    pub precision: Precision,
+    pub fractal: FractalParams,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Info {
     pub depth: u32,
+    /// Time left until the depth limit is reached
+    pub remaining: Estimate,
+    /// Amount of times the surface had to be reconfigured to present a frame
+    pub surface_retries: u32,
+    /// Extra precision bits in effect for the active view
+    pub precision_bits: usize,
+    /// Size of the numbers of the active view
+    pub word_count: usize,
+    /// Decimal logarithm of the magnification of the active view
+    pub zoom_log10: Option<f64>,
+    /// Rotation of the active view in degrees
+    pub rotation: f32,
+    /// Size of the disk cache in bytes, if it's enabled
+    pub cache_usage: Option<u64>,
+    /// Workgroups of the active view that still have unfinished pixels, and all of them
+    pub active_tiles: Option<(u32, u32)>,
+    /// Fractions of the pixels of the active view found inside the set, and of the ones of them
+    /// found without iterating
+    pub settled: Option<(f64, f64)>,
+    /// Iterations calculated per second by the last timed frame
+    pub iteration_rate: Option<f64>,
+    /// Word count of the compute pipeline being compiled, the current one is used meanwhile
+    pub compiling: Option<usize>,
+    /// Iteration count of the pixel inspected with a right click in the active view
+    pub inspected: Option<u32>,
+    /// Tile of the poster in progress, counted from 1, and the amount of its tiles
+    pub poster: Option<(usize, usize)>,
+    /// Frame of the recording in progress, counted from 0, and the amount of its frames
+    pub recording: Option<(usize, usize)>,
 }
 
 impl Program for Overlay {
@@ -81,43 +426,393 @@ impl Program for Overlay {
             Message::CapturePointer(status) => {
                 self.pointer_captured = status;
             }
+            Message::TextFocusChanged(focused) => {
+                self.text_focused = focused;
+            }
             Message::MaxDepthChanged(depth) => {
                 self.max_depth = depth;
                 self.event_loop_proxy
                     .send_event(UserEvent::MaxDepthChanged(depth))
                     .expect("Event loop closed");
             }
+            Message::EscapeRadiusChanged(escape_radius) => {
+                // Every new radius restarts the calculation, drags only report the changed ones
+                if escape_radius != self.iteration.escape_radius {
+                    self.iteration.escape_radius = escape_radius;
+                    self.event_loop_proxy
+                        .send_event(UserEvent::IterationChanged(self.iteration))
+                        .expect("Event loop closed")
+                }
+            }
+            Message::DistanceEstimationToggled(enabled) => {
+                self.iteration.distance_estimation = enabled;
+                self.event_loop_proxy
+                    .send_event(UserEvent::IterationChanged(self.iteration))
+                    .expect("Event loop closed")
+            }
             Message::ScaleChanged(scale) => {
                 self.scale_factor_sqrt = scale;
                 self.event_loop_proxy
                     .send_event(UserEvent::ViewScaleFactorChanged(scale * scale))
                     .expect("Event loop closed")
             }
+            Message::TargetFpsChanged(fps) => {
+                self.target_fps = fps;
+                self.event_loop_proxy
+                    .send_event(UserEvent::TargetFpsChanged(fps))
+                    .expect("Event loop closed")
+            }
             Message::PositionReset => self
                 .event_loop_proxy
                 .send_event(UserEvent::PositionReset)
                 .expect("Event loop closed"),
+            Message::Undo => self
+                .event_loop_proxy
+                .send_event(UserEvent::Undo)
+                .expect("Event loop closed"),
+            Message::Redo => self
+                .event_loop_proxy
+                .send_event(UserEvent::Redo)
+                .expect("Event loop closed"),
+            Message::ToggleAdvanced => self.advanced_open = !self.advanced_open,
             Message::PrecisionChanged(precision) => {
-                self.precision_words = precision;
+                self.precision = precision;
+                self.event_loop_proxy
+                    .send_event(UserEvent::PrecisionChanged(precision))
+                    .expect("Event loop closed")
+            }
+            Message::RotationChanged(degrees) => {
+                self.info.rotation = degrees;
                 self.event_loop_proxy
-                    .send_event(UserEvent::PrecisionChanged(self.precision_bits()))
+                    .send_event(UserEvent::RotationChanged(degrees))
                     .expect("Event loop closed")
             }
+            Message::FractalKindChanged(kind) => {
+                self.fractal.kind = kind;
+                self.event_loop_proxy
+                    .send_event(UserEvent::FractalChanged(self.fractal.clone()))
+                    .expect("Event loop closed")
+            }
+            Message::InvertedPlaneToggled(inverted) => {
+                self.fractal.inverted = inverted;
+                self.event_loop_proxy
+                    .send_event(UserEvent::FractalChanged(self.fractal.clone()))
+                    .expect("Event loop closed")
+            }
+            Message::PowerChanged(power) => {
+                // Sliders report every move of a drag, only new powers reframe the view
+                if power != self.fractal.power {
+                    self.fractal.power = power;
+                    self.event_loop_proxy
+                        .send_event(UserEvent::FractalChanged(self.fractal.clone()))
+                        .expect("Event loop closed")
+                }
+            }
+            Message::JuliaSetToggled(enabled) => self
+                .event_loop_proxy
+                .send_event(UserEvent::JuliaToggled(enabled))
+                .expect("Event loop closed"),
+            Message::PickJuliaSeed => self
+                .event_loop_proxy
+                .send_event(UserEvent::JuliaSeedPicking)
+                .expect("Event loop closed"),
             Message::InfoUpdated(info) => self.info = info,
+            Message::ErrorReported(error) => self.error = Some(error),
+            Message::ErrorDismissed => self.error = None,
+            Message::RenderingDegraded(failure) => self.degraded = Some(failure),
+            Message::RetryRendering => {
+                self.degraded = None;
+                self.event_loop_proxy
+                    .send_event(UserEvent::RetryRendering)
+                    .expect("Event loop closed")
+            }
+            Message::Notified(notice) => self.notice = Some(notice),
+            Message::NoticeDismissed => self.notice = None,
+            Message::SplitToggled(split) => {
+                self.split = split;
+                self.event_loop_proxy
+                    .send_event(UserEvent::SplitToggled(split))
+                    .expect("Event loop closed")
+            }
+            Message::LinkToggled(linked) => {
+                self.linked = linked;
+                self.event_loop_proxy
+                    .send_event(UserEvent::LinkToggled(linked))
+                    .expect("Event loop closed")
+            }
+            Message::JuliaPreviewToggled(enabled) => {
+                self.julia_preview = enabled;
+                self.event_loop_proxy
+                    .send_event(UserEvent::JuliaPreviewToggled(enabled))
+                    .expect("Event loop closed")
+            }
+            Message::AntialiasingToggled(enabled) => {
+                self.antialiasing = enabled;
+                self.event_loop_proxy
+                    .send_event(UserEvent::AntialiasingToggled(enabled))
+                    .expect("Event loop closed")
+            }
+            Message::ReprojectionToggled(enabled) => {
+                self.reprojection = enabled;
+                self.event_loop_proxy
+                    .send_event(UserEvent::ReprojectionToggled(enabled))
+                    .expect("Event loop closed")
+            }
+            Message::SmoothColorsToggled(enabled) => {
+                self.colors.smooth = enabled;
+                self.event_loop_proxy
+                    .send_event(UserEvent::ColorsChanged(self.colors))
+                    .expect("Event loop closed")
+            }
+            Message::EqualizationToggled(enabled) => {
+                self.colors.equalize = enabled;
+                self.event_loop_proxy
+                    .send_event(UserEvent::ColorsChanged(self.colors))
+                    .expect("Event loop closed")
+            }
+            Message::GammaChanged(gamma) => {
+                self.colors.gamma = gamma;
+                self.event_loop_proxy
+                    .send_event(UserEvent::ColorsChanged(self.colors))
+                    .expect("Event loop closed")
+            }
+            Message::PresetSelected(preset) => {
+                self.palette = preset.palette();
+                self.edited_stop = 0;
+                self.palette_changed();
+            }
+            Message::PaletteStopSelected(index) => self.edited_stop = index,
+            Message::PaletteStopEdited(color) => {
+                // Sliders report every move of a drag, only new colors are uploaded
+                if color != self.palette.stops()[self.edited_stop].color {
+                    self.palette.set_color(self.edited_stop, color);
+                    self.palette_changed();
+                }
+            }
+            Message::DeepColorToggled(enabled) => {
+                self.deep_color = enabled;
+                self.event_loop_proxy
+                    .send_event(UserEvent::DeepColorToggled(enabled))
+                    .expect("Event loop closed")
+            }
+            Message::VsyncToggled(enabled) => {
+                self.display.vsync = enabled;
+                self.event_loop_proxy
+                    .send_event(UserEvent::VsyncToggled(enabled))
+                    .expect("Event loop closed")
+            }
+            Message::PowerPreferenceChanged(power) => {
+                self.display.power = power;
+                self.event_loop_proxy
+                    .send_event(UserEvent::PowerPreferenceChanged(power))
+                    .expect("Event loop closed")
+            }
+            Message::SmoothZoomToggled(enabled) => {
+                self.smooth_zoom = enabled;
+                self.event_loop_proxy
+                    .send_event(UserEvent::SmoothZoomToggled(enabled))
+                    .expect("Event loop closed")
+            }
+            Message::ZoomSensitivityChanged(sensitivity) => {
+                self.scroll.sensitivity = sensitivity;
+                self.scroll_calibrated();
+            }
+            Message::ZoomInversionToggled(inverted) => {
+                self.scroll.inverted = inverted;
+                self.scroll_calibrated();
+            }
+            Message::PanBindingChanged(binding) => {
+                self.pan_binding = binding;
+                self.event_loop_proxy
+                    .send_event(UserEvent::PanBindingChanged(binding))
+                    .expect("Event loop closed")
+            }
+            Message::DeepColorApplied(enabled) => self.deep_color = enabled,
+            Message::MaxDepthApplied(max_depth) => self.max_depth = max_depth,
+            Message::ColorsApplied(colors) => self.colors = colors,
+            Message::ViewActivated(view) => {
+                self.active_view = view.index;
+                self.scale_factor_sqrt = view.scale_factor.sqrt();
+                self.precision = view.precision;
+                self.fractal = view.fractal;
+            }
+            Message::CopySettings => self
+                .event_loop_proxy
+                .send_event(UserEvent::SettingsCopied(self.settings()))
+                .expect("Event loop closed"),
+            Message::ExportFrame => self
+                .event_loop_proxy
+                .send_event(UserEvent::ExportRequested)
+                .expect("Event loop closed"),
+            #[cfg(not(target_arch = "wasm32"))]
+            Message::ExportIterations => self
+                .event_loop_proxy
+                .send_event(UserEvent::IterationsExportRequested)
+                .expect("Event loop closed"),
+            Message::PosterWidthEdited(width) => self.poster_size.0 = width,
+            Message::PosterHeightEdited(height) => self.poster_size.1 = height,
+            Message::ExportPoster => {
+                if let Some(dimensions) = self.poster_dimensions() {
+                    self.event_loop_proxy
+                        .send_event(UserEvent::PosterRequested(dimensions))
+                        .expect("Event loop closed")
+                }
+            }
+            Message::CancelPoster => self
+                .event_loop_proxy
+                .send_event(UserEvent::PosterCancelled)
+                .expect("Event loop closed"),
+            Message::MarkRecordingStart(index) => {
+                self.recording_marks.0 = Some(self.bookmarks[index].clone())
+            }
+            Message::MarkRecordingEnd(index) => {
+                self.recording_marks.1 = Some(self.bookmarks[index].clone())
+            }
+            Message::RecordingFramesEdited(frames) => self.recording_frames = frames,
+            Message::RecordingDepthEdited(depth) => self.recording_depth = depth,
+            #[cfg(not(target_arch = "wasm32"))]
+            Message::RecordingVideoToggled(video) => self.recording_video = video,
+            #[cfg(not(target_arch = "wasm32"))]
+            Message::RecordingFpsEdited(fps) => self.recording_fps = fps,
+            #[cfg(not(target_arch = "wasm32"))]
+            Message::VideoFormatChanged(format) => self.video_format = format,
+            Message::StartRecording => {
+                if let Some(request) = self.recording_request() {
+                    self.event_loop_proxy
+                        .send_event(UserEvent::RecordingRequested(Box::new(request)))
+                        .expect("Event loop closed")
+                }
+            }
+            Message::CancelRecording => self
+                .event_loop_proxy
+                .send_event(UserEvent::RecordingCancelled)
+                .expect("Event loop closed"),
+            Message::PasteSettings => self
+                .event_loop_proxy
+                .send_event(UserEvent::SettingsPasted)
+                .expect("Event loop closed"),
+            Message::SettingsApplied(settings) => {
+                self.max_depth = settings.max_depth;
+                self.iteration = settings.iteration;
+                self.scale_factor_sqrt = settings.scale_factor.sqrt();
+                self.precision = settings.precision;
+                self.fractal = settings.fractal;
+                self.antialiasing = settings.antialiasing;
+                self.colors = settings.colors;
+                self.palette = settings.palette;
+                self.target_fps = settings.target_fps;
+                self.edited_stop = 0;
+            }
+            Message::StartDemo => self
+                .event_loop_proxy
+                .send_event(UserEvent::DemoStarted)
+                .expect("Event loop closed"),
+            Message::LanguageChanged(language) => {
+                self.language = language;
+                self.event_loop_proxy
+                    .send_event(UserEvent::LanguageChanged(language))
+                    .expect("Event loop closed")
+            }
+            Message::SaveBookmark => self
+                .event_loop_proxy
+                .send_event(UserEvent::BookmarkRequested)
+                .expect("Event loop closed"),
+            Message::BookmarkCreated(mut bookmark) => {
+                let number = self.bookmarks.len() + 1;
+                bookmark.name = self.language.format("bookmark-default-name", &[&number]);
+                self.renaming = Some((self.bookmarks.len(), bookmark.name.clone()));
+                self.bookmarks.push(bookmark);
+                self.bookmarks_changed();
+                return text_input::focus(bookmark_name_id());
+            }
+            Message::JumpToBookmark(index) => {
+                let bookmark = Box::new(self.bookmarks[index].clone());
+                self.event_loop_proxy
+                    .send_event(UserEvent::JumpTo(bookmark))
+                    .expect("Event loop closed")
+            }
+            Message::RenameBookmark(index) => {
+                self.renaming = Some((index, self.bookmarks[index].name.clone()));
+                return text_input::focus(bookmark_name_id());
+            }
+            Message::BookmarkNameEdited(name) => {
+                if let Some((_, edited)) = &mut self.renaming {
+                    *edited = name;
+                }
+            }
+            Message::BookmarkRenamed => {
+                if let Some((index, name)) = self.renaming.take() {
+                    // Empty names keep the previous one, there would be nothing to click on
+                    let name = name.trim();
+                    if !name.is_empty() && name != self.bookmarks[index].name {
+                        self.bookmarks[index].name = name.to_owned();
+                        self.bookmarks_changed();
+                    }
+                }
+            }
+            Message::DeleteBookmark(index) => {
+                self.bookmarks.remove(index);
+                self.renaming = None;
+                self.bookmarks_changed();
+            }
+            Message::DemoRunning(running) => {
+                self.demo = running;
+                if running {
+                    // The panel is gone, so it can't get the exit event of the pointer
+                    self.settings_open = false;
+                    self.pointer_captured = false;
+                }
+            }
         }
 
         Command::none()
     }
 
-    fn view(&self) -> Element<Message, Theme, Renderer> {
+    fn view(&self) -> Element<'_, Message, Theme, Renderer> {
+        if self.demo {
+            return container(text(self.language.tr("demo-hint")))
+                .padding(10)
+                .into();
+        }
+
         let toggle_button_label = if self.settings_open { "X" } else { "=" };
         let toggle_button = button(toggle_button_label).on_press(Message::ToggleSettings);
 
-        let interface = if self.settings_open {
-            column![toggle_button, self.settings_view()].max_width(300)
-        } else {
-            column![toggle_button]
-        };
+        let mut interface = column![toggle_button].max_width(300);
+        if let Some(failure) = &self.degraded {
+            interface = interface.push(
+                column![
+                    text(self.language.format("rendering-degraded", &[failure]))
+                        .style(Color::from_rgb(0.8, 0.0, 0.0)),
+                    button(self.language.tr("retry")).on_press(Message::RetryRendering),
+                ]
+                .spacing(5)
+                .padding(10),
+            );
+        }
+        if let Some(error) = &self.error {
+            interface = interface.push(
+                column![
+                    text(error).style(Color::from_rgb(0.8, 0.0, 0.0)),
+                    button(self.language.tr("dismiss")).on_press(Message::ErrorDismissed),
+                ]
+                .spacing(5)
+                .padding(10),
+            );
+        }
+        if let Some(notice) = &self.notice {
+            interface = interface.push(
+                column![
+                    text(notice),
+                    button(self.language.tr("dismiss")).on_press(Message::NoticeDismissed),
+                ]
+                .spacing(5)
+                .padding(10),
+            );
+        }
+        if self.settings_open {
+            interface = interface.push(self.settings_view());
+        }
 
         mouse_area(
             container(interface)
@@ -147,30 +842,210 @@ impl Program for Overlay {
 }
 
 impl Overlay {
-    fn settings_view(&self) -> Element<Message, Theme, Renderer> {
+    fn settings_view(&self) -> Element<'_, Message, Theme, Renderer> {
+        let lang = self.language;
+        let mut split =
+            column![checkbox(lang.tr("split-view"), self.split).on_toggle(Message::SplitToggled)]
+                .spacing(10);
+        if self.split {
+            let side = if self.active_view == 0 {
+                "settings-apply-left"
+            } else {
+                "settings-apply-right"
+            };
+            split = split
+                .push(checkbox(lang.tr("link-views"), self.linked).on_toggle(Message::LinkToggled));
+            split = split.push(text(lang.tr(side)));
+        }
+
+        let stats = column![text(
+            lang.format("depth", &[&self.info.depth, &self.max_depth])
+        )]
+        .push_maybe(self.info.inspected.map(|count| {
+            // Pixels that haven't escaped stop at the depth calculated so far
+            let key = if count == SETTLED {
+                "inspected-settled"
+            } else if count == GLITCHED {
+                "inspected-glitched"
+            } else if count < self.info.depth {
+                "inspected"
+            } else {
+                "inspected-inside"
+            };
+            text(lang.format(key, &[&count]))
+        }))
+        .push_maybe(
+            self.info
+                .zoom_log10
+                .map(|zoom| text(lang.format("zoom", &[&lang.number(zoom, 1)]))),
+        )
+        .push_maybe(
+            (self.info.remaining != Estimate::Unknown)
+                .then(|| text(self.info.remaining.localized(lang))),
+        )
+        .push_maybe(
+            self.info
+                .active_tiles
+                .map(|(active, total)| text(lang.format("active-tiles", &[&active, &total]))),
+        )
+        .push_maybe(self.info.settled.map(|(settled, skipped)| {
+            let settled = lang.number(settled * 100.0, 1);
+            // Restored results don't tell the skipped pixels apart
+            if skipped > 0.0 {
+                let skipped = lang.number(skipped * 100.0, 1);
+                text(lang.format("settled-skipped", &[&settled, &skipped]))
+            } else {
+                text(lang.format("settled", &[&settled]))
+            }
+        }))
+        // Shows what distance estimation costs
+        .push_maybe(
+            self.info
+                .iteration_rate
+                .map(|rate| text(lang.format("iteration-rate", &[&lang.number(rate, 0)]))),
+        )
+        .push_maybe(
+            self.info
+                .compiling
+                .map(|words| text(lang.format("compiling-precision", &[&words]))),
+        )
+        .push(text(
+            lang.format("surface-retries", &[&self.info.surface_retries]),
+        ))
+        .push_maybe(self.info.cache_usage.map(|usage| {
+            let mib = lang.number(usage as f64 / (1 << 20) as f64, 1);
+            text(lang.format("cache-usage", &[&mib]))
+        }))
+        .spacing(10);
+
         let content = container(
             column![
-                text(format!("Depth: {}/{}", self.info.depth, self.max_depth)),
+                split,
+                checkbox(lang.tr("julia-preview"), self.julia_preview)
+                    .on_toggle(Message::JuliaPreviewToggled),
+                stats,
                 slider(
                     1..=(u32::MAX.ilog2() + 1) * 16,
                     max_depth_to_slider(self.max_depth),
                     |depth| { Message::MaxDepthChanged(slider_to_max_depth(depth)) },
                 ),
-                text(format!(
-                    "Scale: {:.2}",
-                    self.scale_factor_sqrt * self.scale_factor_sqrt
+                // Higher powers limit the radius, the one in effect is shown
+                text(lang.format(
+                    "escape-radius",
+                    &[&lang.number(
+                        self.iteration.escape_radius_for(self.fractal.power).into(),
+                        0
+                    )]
+                )),
+                slider(
+                    ESCAPE_RADIUS_RANGE,
+                    self.iteration.escape_radius,
+                    Message::EscapeRadiusChanged
+                )
+                .step(1.0),
+                checkbox(
+                    lang.tr("distance-estimation"),
+                    self.iteration.distance_estimation
+                )
+                .on_toggle(Message::DistanceEstimationToggled),
+                text(lang.format(
+                    "scale",
+                    &[&lang.number(self.scale_factor_sqrt * self.scale_factor_sqrt, 2)]
                 )),
                 slider(1.0..=30.0_f64.sqrt(), self.scale_factor_sqrt, |scale| {
                     Message::ScaleChanged(scale)
                 })
                 .step(0.01),
-                text(format!("Precision: {}", self.precision_bits())),
-                slider(0..=4, self.precision_words, |p| {
-                    Message::PrecisionChanged(p)
-                })
-                .step(1u32),
-                button("Reset position").on_press(Message::PositionReset),
+                text(lang.format("target-fps", &[&lang.number(self.target_fps, 0)])),
+                slider(TARGET_FPS_RANGE, self.target_fps, Message::TargetFpsChanged).step(1.0),
+                text(lang.format("rotation", &[&lang.number(self.info.rotation.into(), 0)])),
+                slider(0.0..=359.0, self.info.rotation, Message::RotationChanged).step(1.0),
+                checkbox(lang.tr("antialiasing"), self.antialiasing)
+                    .on_toggle(Message::AntialiasingToggled),
+                checkbox(lang.tr("reprojection"), self.reprojection)
+                    .on_toggle(Message::ReprojectionToggled),
+                checkbox(lang.tr("smooth-colors"), self.colors.smooth)
+                    .on_toggle(Message::SmoothColorsToggled),
+                checkbox(lang.tr("equalize-colors"), self.colors.equalize)
+                    .on_toggle(Message::EqualizationToggled),
+                text(lang.format("gamma", &[&lang.number(self.colors.gamma.into(), 1)])),
+                slider(GAMMA_RANGE, self.colors.gamma, Message::GammaChanged).step(0.1),
+                self.palette_view(),
+                checkbox(lang.tr("deep-color"), self.deep_color)
+                    .on_toggle(Message::DeepColorToggled),
+                checkbox(lang.tr("vsync"), self.display.vsync).on_toggle(Message::VsyncToggled),
+                text(lang.tr("power-preference")),
+                column(PowerPreference::ALL.map(|power| {
+                    radio(
+                        lang.tr(power.label_key()),
+                        power,
+                        Some(self.display.power),
+                        Message::PowerPreferenceChanged,
+                    )
+                    .into()
+                }))
+                .spacing(5),
+                checkbox(lang.tr("smooth-zoom"), self.smooth_zoom)
+                    .on_toggle(Message::SmoothZoomToggled),
+                text(lang.format(
+                    "zoom-sensitivity",
+                    &[&lang.number(self.scroll.sensitivity.into(), 1)]
+                )),
+                slider(
+                    SENSITIVITY_RANGE,
+                    self.scroll.sensitivity,
+                    Message::ZoomSensitivityChanged
+                )
+                .step(0.1),
+                checkbox(lang.tr("invert-zoom"), self.scroll.inverted)
+                    .on_toggle(Message::ZoomInversionToggled),
+                text(lang.tr("pan-with")),
+                column(PanBinding::ALL.map(|binding| {
+                    radio(
+                        lang.tr(binding.label_key()),
+                        binding,
+                        Some(self.pan_binding),
+                        Message::PanBindingChanged,
+                    )
+                    .into()
+                }))
+                .spacing(5),
+                button(lang.tr("reset-position")).on_press(Message::PositionReset),
+                row![
+                    button(lang.tr("undo")).on_press(Message::Undo),
+                    button(lang.tr("redo")).on_press(Message::Redo),
+                ]
+                .spacing(10),
+                self.bookmarks_view(),
+                button(lang.tr("demo-mode")).on_press(Message::StartDemo),
+                text(lang.tr("fractal")),
+                pick_list(
+                    &FractalKind::ALL[..],
+                    Some(self.fractal.kind),
+                    Message::FractalKindChanged
+                ),
+                checkbox(lang.tr("inverted-plane"), self.fractal.inverted)
+                    .on_toggle(Message::InvertedPlaneToggled),
+                text(lang.format("power", &[&self.fractal.power])),
+                slider(POWER_RANGE, self.fractal.power, Message::PowerChanged),
+                checkbox(lang.tr("julia-set"), self.fractal.julia.is_some())
+                    .on_toggle(Message::JuliaSetToggled),
+                button(lang.tr("pick-julia-seed")).on_press(Message::PickJuliaSeed),
+                button(lang.tr("copy-settings")).on_press(Message::CopySettings),
+                button(lang.tr("apply-settings")).on_press(Message::PasteSettings),
+                button(lang.tr("export-png")).on_press(Message::ExportFrame),
+                self.poster_view(),
+                self.recording_view(),
+                button(lang.tr(if self.advanced_open {
+                    "hide-advanced"
+                } else {
+                    "advanced"
+                }))
+                .on_press(Message::ToggleAdvanced),
+                text(lang.tr("language")),
+                pick_list(&Language::ALL[..], Some(lang), Message::LanguageChanged),
             ]
+            .push_maybe(self.advanced_open.then(|| self.advanced_view()))
             .spacing(10),
         )
         .padding(10)
@@ -180,13 +1055,337 @@ impl Overlay {
         scrollable(content).height(Length::Fill).into()
     }
 
-    fn precision_bits(&self) -> usize {
-        if self.precision_words == 0 {
-            10
-        } else {
-            self.precision_words as usize * 32
+    /// Returns the settings of the control panel, as shared in the settings string
+    pub fn settings(&self) -> Settings {
+        Settings {
+            max_depth: self.max_depth,
+            iteration: self.iteration,
+            scale_factor: self.scale_factor_sqrt * self.scale_factor_sqrt,
+            precision: self.precision,
+            fractal: self.fractal.clone(),
+            antialiasing: self.antialiasing,
+            colors: self.colors,
+            palette: self.palette.clone(),
+            target_fps: self.target_fps,
+        }
+    }
+
+    fn palette_view(&self) -> Element<'_, Message, Theme, Renderer> {
+        let lang = self.language;
+        let stops = row(self.palette.stops().iter().enumerate().map(|(index, _)| {
+            let style = if index == self.edited_stop {
+                iced::theme::Button::Primary
+            } else {
+                iced::theme::Button::Secondary
+            };
+            button(text(index + 1))
+                .style(style)
+                .on_press(Message::PaletteStopSelected(index))
+                .into()
+        }))
+        .spacing(5);
+
+        let color = self.palette.stops()[self.edited_stop].color;
+        let channel = |label, index: usize| {
+            row![
+                text(label).width(20),
+                slider(0..=255, color[index], move |value| {
+                    let mut color = color;
+                    color[index] = value;
+                    Message::PaletteStopEdited(color)
+                }),
+            ]
+        };
+
+        column![
+            text(lang.tr("palette")),
+            pick_list(
+                &Preset::ALL[..],
+                self.palette.preset(),
+                Message::PresetSelected
+            )
+            .placeholder(lang.tr("custom-palette")),
+            text(lang.tr("palette-stops")),
+            stops,
+            channel("R", 0),
+            channel("G", 1),
+            channel("B", 2),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn bookmarks_view(&self) -> Element<'_, Message, Theme, Renderer> {
+        let lang = self.language;
+        let mut list = column![].spacing(5);
+        for (index, bookmark) in self.bookmarks.iter().enumerate() {
+            let entry = match &self.renaming {
+                Some((renamed, name)) if *renamed == index => row![
+                    text_input(lang.tr("bookmark-name"), name)
+                        .id(bookmark_name_id())
+                        .on_input(Message::BookmarkNameEdited)
+                        .on_submit(Message::BookmarkRenamed),
+                    button(lang.tr("done")).on_press(Message::BookmarkRenamed),
+                ],
+                _ => row![
+                    button(text(&bookmark.name))
+                        .on_press(Message::JumpToBookmark(index))
+                        .width(Length::Fill),
+                    button(lang.tr("rename")).on_press(Message::RenameBookmark(index)),
+                    button(lang.tr("mark-start")).on_press(Message::MarkRecordingStart(index)),
+                    button(lang.tr("mark-end")).on_press(Message::MarkRecordingEnd(index)),
+                ],
+            };
+            list = list.push(
+                entry
+                    .push(button(lang.tr("delete")).on_press(Message::DeleteBookmark(index)))
+                    .spacing(5),
+            );
+        }
+
+        column![
+            text(lang.tr("bookmarks")),
+            button(lang.tr("save-location")).on_press(Message::SaveBookmark),
+            container(scrollable(list)).max_height(200.0),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn poster_view(&self) -> Element<'_, Message, Theme, Renderer> {
+        let lang = self.language;
+        // Frames of recordings are drawn like posters
+        if let (Some((tile, tiles)), None) = (self.info.poster, self.info.recording) {
+            return column![
+                text(lang.format("poster-progress", &[&tile, &tiles])),
+                button(lang.tr("cancel-poster")).on_press(Message::CancelPoster),
+            ]
+            .spacing(5)
+            .into();
+        }
+        column![
+            text(lang.tr("poster-size")),
+            row![
+                text_input("", &self.poster_size.0)
+                    .id(poster_width_id())
+                    .on_input(Message::PosterWidthEdited),
+                text("×"),
+                text_input("", &self.poster_size.1)
+                    .id(poster_height_id())
+                    .on_input(Message::PosterHeightEdited),
+            ]
+            .spacing(5)
+            .align_items(alignment::Alignment::Center),
+            button(lang.tr("export-poster"))
+                .on_press_maybe(self.poster_dimensions().map(|_| Message::ExportPoster)),
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    fn recording_view(&self) -> Element<'_, Message, Theme, Renderer> {
+        let lang = self.language;
+        if let Some((frame, frames)) = self.info.recording {
+            return column![
+                text(lang.format("recording-progress", &[&(frame + 1), &frames])),
+                progress_bar(0.0..=frames as f32, frame as f32).height(10.0),
+                button(lang.tr("cancel-recording")).on_press(Message::CancelRecording),
+            ]
+            .spacing(5)
+            .into();
+        }
+        let mark = |bookmark: &Option<Bookmark>| match bookmark {
+            Some(bookmark) => bookmark.name.clone(),
+            None => lang.tr("recording-unmarked").to_owned(),
+        };
+        #[allow(unused_mut)]
+        let mut recording = column![
+            text(lang.tr("record-zoom")),
+            text(lang.format("recording-start", &[&mark(&self.recording_marks.0)])),
+            text(lang.format("recording-end", &[&mark(&self.recording_marks.1)])),
+            row![
+                text(lang.tr("recording-frames")),
+                text_input("", &self.recording_frames)
+                    .id(recording_frames_id())
+                    .on_input(Message::RecordingFramesEdited),
+                text(lang.tr("recording-depth")),
+                text_input("", &self.recording_depth)
+                    .id(recording_depth_id())
+                    .on_input(Message::RecordingDepthEdited),
+            ]
+            .spacing(5)
+            .align_items(alignment::Alignment::Center),
+        ]
+        .spacing(5);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            recording = recording.push(
+                checkbox(lang.tr("encode-video"), self.recording_video)
+                    .on_toggle(Message::RecordingVideoToggled),
+            );
+            if self.recording_video {
+                recording = recording.push(
+                    row![
+                        text(lang.tr("video-fps")),
+                        text_input("", &self.recording_fps)
+                            .id(recording_fps_id())
+                            .on_input(Message::RecordingFpsEdited),
+                        pick_list(
+                            &VideoFormat::ALL[..],
+                            Some(self.video_format),
+                            Message::VideoFormatChanged
+                        ),
+                    ]
+                    .spacing(5)
+                    .align_items(alignment::Alignment::Center),
+                );
+            }
+        }
+        recording
+            .push(
+                button(lang.tr("start-recording"))
+                    .on_press_maybe(self.recording_request().map(|_| Message::StartRecording)),
+            )
+            .into()
+    }
+
+    fn advanced_view(&self) -> Element<'_, Message, Theme, Renderer> {
+        let mut advanced = column![
+            text(self.language.format(
+                "precision",
+                &[&self.info.precision_bits, &self.info.word_count]
+            )),
+            checkbox(
+                self.language.tr("manual-precision"),
+                self.precision != Precision::Auto
+            )
+            .on_toggle(|manual| {
+                Message::PrecisionChanged(if manual {
+                    Precision::Manual(manual_precision_step(self.info.precision_bits))
+                } else {
+                    Precision::Auto
+                })
+            }),
+        ]
+        .spacing(10);
+        if let Precision::Manual(bits) = self.precision {
+            advanced = advanced.push(
+                slider(
+                    MANUAL_PRECISION_STEP as u32..=MAX_PRECISION_BITS as u32,
+                    bits as u32,
+                    |bits| Message::PrecisionChanged(Precision::Manual(bits as usize)),
+                )
+                .step(MANUAL_PRECISION_STEP as u32),
+            );
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            advanced = advanced.push(
+                button(self.language.tr("export-iterations")).on_press(Message::ExportIterations),
+            );
+        }
+        advanced.into()
+    }
+}
+
+/// Text field of the bookmark being renamed
+fn bookmark_name_id() -> text_input::Id {
+    text_input::Id::new("bookmark-name")
+}
+
+fn poster_width_id() -> text_input::Id {
+    text_input::Id::new("poster-width")
+}
+
+fn poster_height_id() -> text_input::Id {
+    text_input::Id::new("poster-height")
+}
+
+fn recording_frames_id() -> text_input::Id {
+    text_input::Id::new("recording-frames")
+}
+
+fn recording_depth_id() -> text_input::Id {
+    text_input::Id::new("recording-depth")
+}
+
+fn recording_fps_id() -> text_input::Id {
+    text_input::Id::new("recording-fps")
+}
+
+/// Text fields that keep the keys from the view while they have focus
+fn text_field_ids() -> Vec<widget::Id> {
+    [
+        bookmark_name_id(),
+        poster_width_id(),
+        poster_height_id(),
+        recording_frames_id(),
+        recording_depth_id(),
+        recording_fps_id(),
+    ]
+    .into_iter()
+    .map(Into::into)
+    .collect()
+}
+
+/// Returns the operation that tells the control panel when one of its text fields gains or loses
+/// focus. Only widgets know their focus, `focused` is what the panel knows of it so far
+fn text_focus(focused: bool) -> Box<dyn Operation<Message>> {
+    struct TextFocus {
+        known: bool,
+        focused: bool,
+        fields: Vec<widget::Id>,
+    }
+
+    impl Operation<Message> for TextFocus {
+        fn focusable(&mut self, state: &mut dyn Focusable, id: Option<&widget::Id>) {
+            if state.is_focused() && id.is_some_and(|id| self.fields.contains(id)) {
+                self.focused = true;
+            }
+        }
+
+        fn container(
+            &mut self,
+            _id: Option<&widget::Id>,
+            _bounds: Rectangle,
+            operate_on_children: &mut dyn FnMut(&mut dyn Operation<Message>),
+        ) {
+            operate_on_children(self);
+        }
+
+        fn finish(&self) -> Outcome<Message> {
+            if self.focused == self.known {
+                Outcome::None
+            } else {
+                Outcome::Some(Message::TextFocusChanged(self.focused))
+            }
         }
     }
+
+    Box::new(TextFocus {
+        known: focused,
+        focused: false,
+        fields: text_field_ids(),
+    })
+}
+
+/// Side of the poster the control panel starts out with
+const DEFAULT_POSTER_SIDE: u32 = 8000;
+
+/// Frame count of the zoom the control panel starts out with, 10 seconds at 30 frames per second
+const DEFAULT_RECORDING_FRAMES: usize = 300;
+
+/// Frame rate of the videos of recordings the control panel starts out with
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_RECORDING_FPS: u32 = 30;
+
+/// Granularity of the manual precision slider
+const MANUAL_PRECISION_STEP: usize = 8;
+
+/// Rounds `bits` up to a value of the manual precision slider
+fn manual_precision_step(bits: usize) -> usize {
+    (bits.div_ceil(MANUAL_PRECISION_STEP) * MANUAL_PRECISION_STEP)
+        .clamp(MANUAL_PRECISION_STEP, MAX_PRECISION_BITS)
 }
 
 fn slider_to_max_depth(v: u32) -> u32 {
@@ -201,5 +1400,5 @@ fn max_depth_to_slider(v: u32) -> u32 {
     let base = v.ilog2();
     let part_size = 1 << base;
     let part = v ^ part_size;
-    (base * 16).saturating_add((part as f32 / (part_size as f32 / 16 as f32)).ceil() as u32)
+    (base * 16).saturating_add((part as f32 / (part_size as f32 / 16.0)).ceil() as u32)
 }
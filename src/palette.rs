@@ -0,0 +1,250 @@
+//! Gradients the iteration counts are colored with. A palette is sampled into a texture, the
+//! shader picks colors from it by the position of the count along the gradient
+
+use std::fmt;
+
+/// Amount of colors a palette is sampled into for the shader
+pub const TEXELS: usize = 256;
+
+/// Most stops a palette can have
+pub const MAX_STOPS: usize = 8;
+
+/// Color of a gradient at a position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stop {
+    /// Position along the gradient, from 0 to 1
+    pub position: f32,
+    /// sRGB color
+    pub color: [u8; 3],
+}
+
+const fn stop(position: f32, color: u32) -> Stop {
+    Stop {
+        position,
+        color: [(color >> 16) as u8, (color >> 8) as u8, color as u8],
+    }
+}
+
+/// Gradient of colors between stops, ordered by their positions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    stops: Vec<Stop>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Preset::Classic.palette()
+    }
+}
+
+impl Palette {
+    /// Creates a palette of 1 to [`MAX_STOPS`] stops in ascending order between 0 and 1
+    pub fn new(stops: Vec<Stop>) -> Option<Self> {
+        let in_range = stops
+            .iter()
+            .all(|stop| (0.0..=1.0).contains(&stop.position));
+        let ascending = stops
+            .windows(2)
+            .all(|pair| pair[0].position <= pair[1].position);
+        (!stops.is_empty() && stops.len() <= MAX_STOPS && in_range && ascending)
+            .then_some(Self { stops })
+    }
+
+    pub fn stops(&self) -> &[Stop] {
+        &self.stops
+    }
+
+    /// Changes the color of the stop at `index`, if there's one
+    pub fn set_color(&mut self, index: usize, color: [u8; 3]) {
+        if let Some(stop) = self.stops.get_mut(index) {
+            stop.color = color;
+        }
+    }
+
+    /// Returns the preset the palette is unchanged from, if any
+    pub fn preset(&self) -> Option<Preset> {
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.palette() == *self)
+    }
+
+    /// Returns the color at `position`, blended between the stops around it. Positions past the
+    /// first or the last stop have its color
+    pub fn sample(&self, position: f32) -> [u8; 3] {
+        let after = self.stops.partition_point(|stop| stop.position <= position);
+        let (low, high) = match after {
+            0 => return self.stops[0].color,
+            _ if after == self.stops.len() => return self.stops[after - 1].color,
+            _ => (self.stops[after - 1], self.stops[after]),
+        };
+        let t = (position - low.position) / (high.position - low.position);
+        std::array::from_fn(|channel| {
+            let (low, high) = (low.color[channel] as f32, high.color[channel] as f32);
+            (low + (high - low) * t).round() as u8
+        })
+    }
+
+    /// Returns the palette sampled into [`TEXELS`] RGBA colors, from the first stop to the last
+    pub fn texels(&self) -> Vec<[u8; 4]> {
+        (0..TEXELS)
+            .map(|texel| {
+                let [r, g, b] = self.sample(texel as f32 / (TEXELS - 1) as f32);
+                [r, g, b, 255]
+            })
+            .collect()
+    }
+
+    /// Returns the stops as `position:rrggbb` words separated by spaces
+    pub fn encode(&self) -> String {
+        self.stops
+            .iter()
+            .map(|stop| {
+                let [r, g, b] = stop.color;
+                format!("{}:{:02x}{:02x}{:02x}", stop.position, r, g, b)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Parses the stops written by [`Palette::encode`]
+    pub fn parse(text: &str) -> Option<Self> {
+        let stops = text
+            .split_whitespace()
+            .map(|word| {
+                let (position, color) = word.split_once(':')?;
+                if color.len() != 6 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return None;
+                }
+                let color = u32::from_str_radix(color, 16).ok()?;
+                Some(stop(position.parse().ok()?, color))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Self::new(stops)
+    }
+}
+
+/// Built-in palettes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Preset {
+    /// Blue and gold
+    #[default]
+    Classic,
+    Grayscale,
+    /// Black through red and yellow to white
+    Fire,
+    /// Perceptually uniform purple through green to yellow
+    Viridis,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::Classic,
+        Preset::Grayscale,
+        Preset::Fire,
+        Preset::Viridis,
+    ];
+
+    pub fn palette(self) -> Palette {
+        let stops: &[Stop] = match self {
+            Preset::Classic => &[
+                stop(0.0, 0x000764),
+                stop(0.16, 0x206bcb),
+                stop(0.42, 0xedffff),
+                stop(0.6425, 0xffaa00),
+                stop(0.8575, 0x000200),
+            ],
+            Preset::Grayscale => &[stop(0.0, 0x000000), stop(1.0, 0xffffff)],
+            Preset::Fire => &[
+                stop(0.0, 0x000000),
+                stop(0.3, 0x9b0000),
+                stop(0.6, 0xff8c00),
+                stop(0.85, 0xffe45a),
+                stop(1.0, 0xffffff),
+            ],
+            Preset::Viridis => &[
+                stop(0.0, 0x440154),
+                stop(0.25, 0x3b528b),
+                stop(0.5, 0x21918c),
+                stop(0.75, 0x5ec962),
+                stop(1.0, 0xfde725),
+            ],
+        };
+        Palette {
+            stops: stops.to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Preset::Classic => "Classic",
+            Preset::Grayscale => "Grayscale",
+            Preset::Fire => "Fire",
+            Preset::Viridis => "Viridis",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid() {
+        for preset in Preset::ALL {
+            let palette = preset.palette();
+            assert_eq!(Palette::new(palette.stops.clone()), Some(palette.clone()));
+            assert_eq!(palette.preset(), Some(preset));
+        }
+
+        let mut edited = Palette::default();
+        edited.set_color(1, [1, 2, 3]);
+        assert_eq!(edited.stops()[1].color, [1, 2, 3]);
+        assert_eq!(edited.preset(), None);
+    }
+
+    #[test]
+    fn colors_are_blended_between_stops() {
+        let palette = Palette::new(vec![stop(0.25, 0x000000), stop(0.75, 0xff8000)]).unwrap();
+        assert_eq!(palette.sample(0.5), [128, 64, 0]);
+        assert_eq!(palette.sample(0.0), [0, 0, 0]);
+        assert_eq!(palette.sample(1.0), [255, 128, 0]);
+
+        let texels = Preset::Grayscale.palette().texels();
+        assert_eq!(texels.len(), TEXELS);
+        assert_eq!(texels[0], [0, 0, 0, 255]);
+        assert_eq!(texels[TEXELS - 1], [255, 255, 255, 255]);
+        assert!(texels.windows(2).all(|pair| pair[0][0] <= pair[1][0]));
+
+        // A single stop colors the whole gradient
+        let single = Palette::new(vec![stop(0.5, 0x123456)]).unwrap();
+        assert!(single
+            .texels()
+            .iter()
+            .all(|&texel| texel == [0x12, 0x34, 0x56, 255]));
+    }
+
+    #[test]
+    fn stops_round_trip() {
+        for preset in Preset::ALL {
+            let palette = preset.palette();
+            assert_eq!(Palette::parse(&palette.encode()), Some(palette));
+        }
+        assert_eq!(
+            Palette::default().encode(),
+            "0:000764 0.16:206bcb 0.42:edffff 0.6425:ffaa00 0.8575:000200"
+        );
+    }
+
+    #[test]
+    fn invalid_stops_are_rejected() {
+        assert_eq!(Palette::parse(""), None);
+        assert_eq!(Palette::parse("0.5:12345"), None);
+        assert_eq!(Palette::parse("0.5:gggggg"), None);
+        assert_eq!(Palette::parse("0.5:+12345"), None);
+        assert_eq!(Palette::parse("1.5:000000"), None);
+        assert_eq!(Palette::parse("0.6:000000 0.4:ffffff"), None);
+        assert_eq!(Palette::parse(&["0:000000"; MAX_STOPS + 1].join(" ")), None);
+    }
+}
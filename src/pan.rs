@@ -0,0 +1,83 @@
+//! Bindings of the pan gesture, so that the left button can be left to clicks
+
+use winit::event::MouseButton;
+
+/// Button, or Space and the button, that drags the view
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PanBinding {
+    #[default]
+    Left,
+    Middle,
+    /// Left button while Space is held
+    SpaceLeft,
+}
+
+impl PanBinding {
+    pub const ALL: [PanBinding; 3] = [PanBinding::Left, PanBinding::Middle, PanBinding::SpaceLeft];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PanBinding::Left => "left",
+            PanBinding::Middle => "middle",
+            PanBinding::SpaceLeft => "space_left",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|binding| binding.name() == name)
+    }
+
+    /// Returns the translation key of the binding label
+    pub fn label_key(self) -> &'static str {
+        match self {
+            PanBinding::Left => "pan-left",
+            PanBinding::Middle => "pan-middle",
+            PanBinding::SpaceLeft => "pan-space-left",
+        }
+    }
+
+    /// Returns the button that drags the view, a release of it ends the drag
+    pub fn button(self) -> MouseButton {
+        match self {
+            PanBinding::Left | PanBinding::SpaceLeft => MouseButton::Left,
+            PanBinding::Middle => MouseButton::Middle,
+        }
+    }
+
+    /// Returns true if a press of `button` starts a drag, with Space held if `space` is true
+    pub fn starts(self, button: MouseButton, space: bool) -> bool {
+        button == self.button() && (space || self != PanBinding::SpaceLeft)
+    }
+
+    /// Returns true if a release of Space ends a drag started by the binding
+    pub fn needs_space(self) -> bool {
+        self == PanBinding::SpaceLeft
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_start_drags_of_their_binding() {
+        assert!(PanBinding::Left.starts(MouseButton::Left, false));
+        assert!(PanBinding::Left.starts(MouseButton::Left, true));
+        assert!(!PanBinding::Left.starts(MouseButton::Middle, false));
+
+        assert!(PanBinding::Middle.starts(MouseButton::Middle, false));
+        assert!(!PanBinding::Middle.starts(MouseButton::Left, false));
+
+        assert!(PanBinding::SpaceLeft.starts(MouseButton::Left, true));
+        assert!(!PanBinding::SpaceLeft.starts(MouseButton::Left, false));
+        assert!(!PanBinding::SpaceLeft.starts(MouseButton::Middle, true));
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for binding in PanBinding::ALL {
+            assert_eq!(PanBinding::from_name(binding.name()), Some(binding));
+        }
+        assert_eq!(PanBinding::from_name("right"), None);
+    }
+}
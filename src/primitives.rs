@@ -2,8 +2,10 @@ use std::cmp::min;
 
 use crate::float::WideFloat;
 use bytemuck::{Pod, Zeroable};
+use thiserror::Error;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dimensions {
     pub width: u32,
     pub height: u32,
@@ -29,8 +31,51 @@ impl Dimensions {
     }
 }
 
+/// Region of the window in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// Returns the rect covering the whole area of `dimensions`
+    pub fn covering(dimensions: Dimensions) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: dimensions.width,
+            height: dimensions.height,
+        }
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        Dimensions::new_nonzero(self.width, self.height)
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.x as f32
+            && point.x < (self.x + self.width) as f32
+            && point.y >= self.y as f32
+            && point.y < (self.y + self.height) as f32
+    }
+
+    /// Returns the part of the rect within `width` and `height`, if it isn't empty
+    pub fn clamp_to(&self, width: u32, height: u32) -> Option<Rect> {
+        let rect = Rect {
+            x: self.x,
+            y: self.y,
+            width: self.width.min(width.saturating_sub(self.x)),
+            height: self.height.min(height.saturating_sub(self.y)),
+        };
+        (rect.width > 0 && rect.height > 0).then_some(rect)
+    }
+}
+
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
 pub struct ScaledDimensions {
     pub width: u32,
     pub height: u32,
@@ -42,7 +87,23 @@ impl ScaledDimensions {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Affine map from the pixels of one view to the pixels of another, with a column-major `matrix`
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct PixelTransform {
+    pub matrix: [f32; 4],
+    pub offset: [f32; 2],
+}
+
+/// Views further apart than this many pixels have nothing in common to map
+const MAX_TRANSFORM_OFFSET: f32 = (1 << 24) as f32;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SerializedCoordinates", into = "SerializedCoordinates")
+)]
 pub struct Coordinates {
     /// X coordinate
     pub x: WideFloat,
@@ -52,62 +113,376 @@ pub struct Coordinates {
     pub step: WideFloat,
     /// extra precision bit count
     precision: usize,
+    /// rotation of the view in degrees, `0..360`
+    rotation: f32,
+}
+
+/// Coordinates that are validated when deserialized
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedCoordinates {
+    x: WideFloat,
+    y: WideFloat,
+    step: WideFloat,
+    precision: usize,
+    #[serde(default)]
+    rotation: f32,
+}
+
+#[cfg(feature = "serde")]
+impl From<Coordinates> for SerializedCoordinates {
+    fn from(coords: Coordinates) -> Self {
+        Self {
+            x: coords.x,
+            y: coords.y,
+            step: coords.step,
+            precision: coords.precision,
+            rotation: coords.rotation,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedCoordinates> for Coordinates {
+    type Error = CoordError;
+
+    fn try_from(coords: SerializedCoordinates) -> Result<Self, Self::Error> {
+        let mut parsed =
+            Coordinates::from_parts(coords.x, coords.y, coords.step, coords.precision)?;
+        parsed.set_rotation(coords.rotation)?;
+        Ok(parsed)
+    }
+}
+
+/// Min number size in words supported by the compute shader
+pub const MIN_WORD_COUNT: usize = 2;
+/// Max number size in words supported by the compute shader. Bounds the size of the intermediate
+/// compute buffer and the shader compilation time
+pub const MAX_WORD_COUNT: usize = 16;
+
+#[derive(Debug, Clone, Copy, Error)]
+#[error(
+    "Unsupported number size of {0} words, expected {}..={}",
+    MIN_WORD_COUNT,
+    MAX_WORD_COUNT
+)]
+pub struct UnsupportedWordCount(pub usize);
+
+/// Returns `word_count` if it's supported by the compute shader
+pub fn check_word_count(word_count: usize) -> Result<usize, UnsupportedWordCount> {
+    if (MIN_WORD_COUNT..=MAX_WORD_COUNT).contains(&word_count) {
+        Ok(word_count)
+    } else {
+        Err(UnsupportedWordCount(word_count))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum CoordError {
+    #[error("Invalid {name}: {value}")]
+    InvalidValue { name: &'static str, value: f64 },
+    #[error(transparent)]
+    UnsupportedWordCount(#[from] UnsupportedWordCount),
+    #[error("Coordinate numbers differ in size")]
+    SizeMismatch,
+}
+
+fn wide_from_f32(name: &'static str, value: f32, size: usize) -> Result<WideFloat, CoordError> {
+    WideFloat::from_f32(value, size).map_err(|_| CoordError::InvalidValue {
+        name,
+        value: value.into(),
+    })
+}
+
+fn wide_from_f64(name: &'static str, value: f64, size: usize) -> Result<WideFloat, CoordError> {
+    WideFloat::from_f64(value, size).map_err(|_| CoordError::InvalidValue { name, value })
+}
+
+/// Farthest a move takes coordinates from the origin, the picture is uniform this far out
+const MAX_COORDINATE: i32 = 4;
+
+/// Returns `coord` moved by `delta` steps, clamped instead of wrapping around
+fn moved(coord: &WideFloat, delta: &WideFloat, step: &WideFloat) -> WideFloat {
+    let size = coord.word_count();
+    let max = WideFloat::from_i32(MAX_COORDINATE, size).max(coord.clone());
+    let min = WideFloat::from_i32(-MAX_COORDINATE, size).min(coord.clone());
+    match delta
+        .checked_mul(step)
+        .and_then(|offset| coord.checked_sub(&offset))
+    {
+        Some(moved) => moved.clamp(min, max),
+        // Overflows are always past the limit, opposite to the offset
+        None if (*delta < 0) ^ (*step < 0) => max,
+        None => min,
+    }
 }
 
 impl Coordinates {
-    pub fn new(x: f32, y: f32, step: f32, precision: usize) -> Self {
-        let x = WideFloat::from_f32(x, 2).expect("Invalid x");
-        let y = WideFloat::from_f32(y, 2).expect("Invalid y");
-        let step = WideFloat::from_f32(step, 2).expect("Invalid step");
-        Coordinates {
-            x,
-            y,
-            step,
+    pub fn new(x: f64, y: f64, step: f64, precision: usize) -> Result<Self, CoordError> {
+        Ok(Coordinates {
+            x: wide_from_f64("x", x, 2)?,
+            y: wide_from_f64("y", y, 2)?,
+            step: wide_from_f64("step", step, 2)?,
             precision,
-        }
+            rotation: 0.0,
+        })
     }
 
-    pub fn new_magnified(x: f32, y: f32, size: usize, precision: usize) -> Self {
-        let step = WideFloat::min_positive(size, precision);
-        let x = WideFloat::from_f32(x, size).expect("Invalid x");
-        let y = WideFloat::from_f32(y, size).expect("Invalid y");
-        Coordinates {
+    pub fn new_magnified(
+        x: f64,
+        y: f64,
+        size: usize,
+        precision: usize,
+    ) -> Result<Self, CoordError> {
+        let size = check_word_count(size)?;
+        Ok(Coordinates {
+            x: wide_from_f64("x", x, size)?,
+            y: wide_from_f64("y", y, size)?,
+            step: WideFloat::min_positive(size, precision),
+            precision,
+            rotation: 0.0,
+        })
+    }
+
+    /// Creates coordinates from exact numbers of equal supported size
+    pub fn from_parts(
+        x: WideFloat,
+        y: WideFloat,
+        step: WideFloat,
+        precision: usize,
+    ) -> Result<Self, CoordError> {
+        let size = check_word_count(step.word_count())?;
+        if x.word_count() != size || y.word_count() != size {
+            return Err(CoordError::SizeMismatch);
+        }
+        Ok(Coordinates {
             x,
             y,
             step,
             precision,
-        }
+            rotation: 0.0,
+        })
     }
 
-    pub fn move_by_delta(&mut self, dx: f32, dy: f32) {
-        self.x -=
-            &(&WideFloat::from_f32(dx, self.size()).expect("Invalid move delta") * &self.step);
-        self.y -=
-            &(&WideFloat::from_f32(dy, self.size()).expect("Invalid move delta") * &self.step);
+    /// Moves coordinates by `dx` and `dy` steps. Moving can't take coordinates further than
+    /// `MAX_COORDINATE` from the origin, or further out if they're already past it. Coordinates are
+    /// left unchanged on error
+    pub fn move_by_delta(&mut self, dx: f32, dy: f32) -> Result<(), CoordError> {
+        let (cos, sin) = self.rotation_cos_sin();
+        let (dx, dy) = (dx * cos - dy * sin, dx * sin + dy * cos);
+        let dx = wide_from_f32("move delta", dx, self.size())?;
+        let dy = wide_from_f32("move delta", dy, self.size())?;
+        self.x = moved(&self.x, &dx, &self.step);
+        self.y = moved(&self.y, &dy, &self.step);
+        Ok(())
     }
 
-    pub fn zoom_with_anchor(&mut self, mul: f32, x: i32, y: i32, max_limit: f32) {
-        self.change_precision(self.step.precision_diff(self.precision));
+    /// Multiplies the step by `mul`, keeping the point `x`, `y` pixels away from the corner in place.
+    /// Returns true if the step is clamped to the smallest one the numbers can hold. Coordinates are
+    /// left unchanged on error
+    pub fn zoom_with_anchor(
+        &mut self,
+        mul: f32,
+        x: f32,
+        y: f32,
+        max_limit: f32,
+    ) -> Result<bool, CoordError> {
+        let word_diff = self.clamp_word_diff(self.precision_word_diff());
+        let size = (self.size() as isize + word_diff) as usize;
 
-        let wide_x = WideFloat::from_i32(x, self.size());
-        let wide_y = WideFloat::from_i32(y, self.size());
-        let wide_mul = WideFloat::from_f32(mul, self.size()).unwrap();
+        let wide_mul = wide_from_f32("zoom multiplier", mul, size)?;
+        let wide_max_limit = wide_from_f32("max limit", max_limit, size)?;
+        let wide_x = wide_from_f32("zoom anchor", x, size)?;
+        let wide_y = wide_from_f32("zoom anchor", y, size)?;
 
-        let mut new_step = &self.step * &wide_mul;
+        self.change_precision(word_diff);
 
-        // Limit zoom out
-        if self.size() == 2 {
-            let wide_max_limit =
-                WideFloat::from_f32(max_limit, self.size()).expect("Invalid max limit");
-            new_step = min(wide_max_limit, new_step);
+        // Limit zoom out, products past the whole part are beyond the limit as well
+        let mut new_step = match self.step.checked_mul(&wide_mul) {
+            Some(step) => min(wide_max_limit.clone(), step),
+            None => wide_max_limit.clone(),
+        };
+        let zoomed_out = new_step == wide_max_limit;
+
+        // Limit zoom in, a zero step would collapse the view into a single point. Words are added
+        // as the step gets smaller, so this only happens at the max word count
+        let min_step = WideFloat::min_positive(self.size(), 0);
+        let clamped = new_step < min_step;
+        if clamped {
+            new_step = min_step;
         }
 
-        let dx = &(self.step.clone() - &new_step) * &wide_x;
-        let dy = &(self.step.clone() - &new_step) * &wide_y;
+        let step_diff = self.step.clone() - &new_step;
+        let (dx, dy) = self.rotate(&step_diff * &wide_x, &step_diff * &wide_y);
 
         self.step = new_step;
         self.x += &dx;
         self.y += &dy;
+
+        if zoomed_out {
+            // Nothing is left to zoom out to, so a spare word would never be needed
+            self.change_precision(self.step.precision_diff(self.precision));
+        } else {
+            // The smaller step may need more words right away, before the next zoom
+            self.change_precision(self.precision_word_diff().max(0));
+        }
+        Ok(clamped)
+    }
+
+    /// Returns the plane point at the center of a view of `dimensions`
+    pub fn center(&self, dimensions: ScaledDimensions) -> (WideFloat, WideFloat) {
+        let half = |side: u32| WideFloat::from_i32(side as i32, self.size()) >> 1;
+        let (dx, dy) = self.plane_offset(&half(dimensions.width), &half(dimensions.height));
+        (self.x.clone() + &dx, self.y.clone() + &dy)
+    }
+
+    /// Returns the coordinates of a view of `to` dimensions with the center and the extent across
+    /// the shortest side of a view of these coordinates and `from` dimensions. The numbers grow by
+    /// the words the smaller step needs
+    pub fn resized(&self, from: ScaledDimensions, to: ScaledDimensions) -> Coordinates {
+        let shortest = |dimensions: ScaledDimensions| dimensions.width.min(dimensions.height);
+        let (x, y) = self.center(from);
+        let mut coords = Coordinates {
+            x,
+            y,
+            ..self.clone()
+        };
+        // A spare word keeps the bits of the smaller step, the ones it doesn't need are trimmed
+        coords.change_precision(1);
+        let ratio = WideFloat::from_f64(shortest(from) as f64 / shortest(to) as f64, coords.size())
+            .expect("Side ratios are representable");
+        coords.step = (&coords.step * &ratio).max(WideFloat::min_positive(coords.size(), 0));
+        coords.change_precision(coords.step.precision_diff(coords.precision));
+
+        let half = |side: u32| WideFloat::from_i32(side as i32, coords.size()) >> 1;
+        let (dx, dy) = coords.plane_offset(&half(to.width), &half(to.height));
+        coords.x -= &dx;
+        coords.y -= &dy;
+        coords
+    }
+
+    /// Returns the plane point at the corner of the pixel `x`, `y`
+    pub fn complex_at_pixel(&self, x: u32, y: u32) -> (WideFloat, WideFloat) {
+        let pixel = |pixel: u32| WideFloat::from_i32(pixel as i32, self.size());
+        let (dx, dy) = self.plane_offset(&pixel(x), &pixel(y));
+        (self.x.clone() + &dx, self.y.clone() + &dy)
+    }
+
+    /// Returns the pixel of a view of `dimensions` that contains the plane point `re`, `im`, if any
+    pub fn pixel_at_complex(
+        &self,
+        re: &WideFloat,
+        im: &WideFloat,
+        dimensions: ScaledDimensions,
+    ) -> Option<(u32, u32)> {
+        let offset = |value: &WideFloat, origin: &WideFloat| {
+            let mut value = value.clone();
+            value.change_precision(self.size() as isize - value.word_count() as isize);
+            value.checked_sub(origin)
+        };
+        let (dx, dy) = (offset(re, &self.x)?, offset(im, &self.y)?);
+
+        // Rotates the offset back into the view
+        let (cos, sin) = self.rotation_cos_sin();
+        let (cos, sin) = (self.wide_trig(cos), self.wide_trig(sin));
+        let view_x = dx.checked_mul(&cos)?.checked_add(&dy.checked_mul(&sin)?)?;
+        let view_y = dy.checked_mul(&cos)?.checked_sub(&dx.checked_mul(&sin)?)?;
+
+        let pixel = |offset: WideFloat, side: u32| {
+            let extent = self
+                .step
+                .checked_mul(&WideFloat::from_i32(side as i32, self.size()))?;
+            if offset < 0 || offset >= extent {
+                return None;
+            }
+            Some(offset.checked_div(&self.step)?.floor() as u32)
+        };
+        Some((
+            pixel(view_x, dimensions.width)?,
+            pixel(view_y, dimensions.height)?,
+        ))
+    }
+
+    /// Returns the map from the pixels of these coordinates to the pixels of `other`, if they're
+    /// close enough to each other for floats
+    pub fn pixel_transform_to(&self, other: &Self) -> Option<PixelTransform> {
+        let size = self.size().max(other.size());
+        let resized = |value: &WideFloat| {
+            let mut value = value.clone();
+            value.change_precision(size as isize - value.word_count() as isize);
+            value
+        };
+        let other_step = resized(&other.step);
+        let dx = resized(&self.x).checked_sub(&resized(&other.x))?;
+        let dy = resized(&self.y).checked_sub(&resized(&other.y))?;
+
+        // The corner of these coordinates in the rotated pixels of the other ones
+        let (cos, sin) = other.rotation_cos_sin();
+        let trig = |value: f32| WideFloat::from_f32(value, size).expect("Trig is representable");
+        let (cos, sin) = (trig(cos), trig(sin));
+        let view_x = dx.checked_mul(&cos)?.checked_add(&dy.checked_mul(&sin)?)?;
+        let view_y = dy.checked_mul(&cos)?.checked_sub(&dx.checked_mul(&sin)?)?;
+        let offset = [
+            view_x.checked_div(&other_step)?.as_f64_round() as f32,
+            view_y.checked_div(&other_step)?.as_f64_round() as f32,
+        ];
+        if offset
+            .iter()
+            .any(|offset| offset.abs() > MAX_TRANSFORM_OFFSET)
+        {
+            return None;
+        }
+
+        let ratio = resized(&self.step).checked_div(&other_step)?.as_f64_round() as f32;
+        let (sin, cos) = (self.rotation - other.rotation).to_radians().sin_cos();
+        Some(PixelTransform {
+            matrix: [ratio * cos, ratio * sin, -ratio * sin, ratio * cos],
+            offset,
+        })
+    }
+
+    /// Returns the plane vector from the corner of the view to the point `x`, `y` pixels away
+    pub fn plane_offset(&self, x: &WideFloat, y: &WideFloat) -> (WideFloat, WideFloat) {
+        self.rotate(&self.step * x, &self.step * y)
+    }
+
+    /// Rotation of the view in degrees, `0..360`
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Sets the rotation of the view around its corner. Coordinates are left unchanged on error
+    pub fn set_rotation(&mut self, degrees: f32) -> Result<(), CoordError> {
+        if !degrees.is_finite() {
+            return Err(CoordError::InvalidValue {
+                name: "rotation",
+                value: degrees.into(),
+            });
+        }
+        // Tiny negative angles round up to a whole turn
+        let degrees = degrees.rem_euclid(360.0);
+        self.rotation = if degrees < 360.0 { degrees } else { 0.0 };
+        Ok(())
+    }
+
+    /// Returns the cosine and sine of the rotation, exactly 1 and 0 for an axis-aligned view
+    pub fn rotation_cos_sin(&self) -> (f32, f32) {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        (cos, sin)
+    }
+
+    /// Rotates the view vector `x`, `y` into the plane
+    fn rotate(&self, x: WideFloat, y: WideFloat) -> (WideFloat, WideFloat) {
+        if self.rotation == 0.0 {
+            return (x, y);
+        }
+        let (cos, sin) = self.rotation_cos_sin();
+        let (cos, sin) = (self.wide_trig(cos), self.wide_trig(sin));
+        (&x * &cos - &(&y * &sin), &x * &sin + &(&y * &cos))
+    }
+
+    fn wide_trig(&self, value: f32) -> WideFloat {
+        WideFloat::from_f32(value, self.size()).expect("Sine and cosine are representable")
     }
 
     pub fn size(&self) -> usize {
@@ -115,6 +490,7 @@ impl Coordinates {
     }
 
     pub fn set_precision(&mut self, precision: usize) {
+        self.precision = precision;
         self.change_precision(self.step.precision_diff(precision))
     }
 
@@ -122,7 +498,30 @@ impl Coordinates {
         self.precision
     }
 
+    /// Returns the word count change that keeps the precision. Words are added as soon as they're
+    /// needed, but removed only while a spare word remains, so that zooming back and forth around a
+    /// word boundary doesn't switch sizes every time
+    fn precision_word_diff(&self) -> isize {
+        match self.step.precision_diff(self.precision) {
+            diff if diff < 0 => diff + 1,
+            diff => diff,
+        }
+    }
+
+    /// Resizes the numbers to `word_count` words, clamped to the supported range. Trims the least
+    /// significant words if the count decreases
+    pub fn set_word_count(&mut self, word_count: usize) {
+        self.change_precision(word_count as isize - self.size() as isize)
+    }
+
+    /// Limits `word_diff` so that the changed size stays within supported word counts
+    fn clamp_word_diff(&self, word_diff: isize) -> isize {
+        let size = self.size() as isize;
+        (size + word_diff).clamp(MIN_WORD_COUNT as isize, MAX_WORD_COUNT as isize) - size
+    }
+
     fn change_precision(&mut self, word_diff: isize) {
+        let word_diff = self.clamp_word_diff(word_diff);
         self.x.change_precision(word_diff);
         self.y.change_precision(word_diff);
         self.step.change_precision(word_diff);
@@ -130,8 +529,417 @@ impl Coordinates {
 }
 
 #[repr(C)]
-#[derive(Debug, Default, Clone, Copy, Pod, Zeroable)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counts allocations per thread, as tests run in parallel
+    struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn word_count_is_clamped() {
+        let mut coords = Coordinates::new(-0.75, 0.1, 4.0 / 500.0, 10).unwrap();
+        for _ in 0..2000 {
+            coords.zoom_with_anchor(0.5, 400.0, 250.0, 1.0).unwrap();
+            assert!(check_word_count(coords.size()).is_ok());
+        }
+        assert_eq!(coords.size(), MAX_WORD_COUNT);
+
+        coords.set_precision(0);
+        assert!(check_word_count(coords.size()).is_ok());
+        coords.set_precision(MAX_WORD_COUNT * 64);
+        assert_eq!(coords.size(), MAX_WORD_COUNT);
+    }
+
+    #[test]
+    fn minimum_whole_part_changes_word_count() {
+        let mut coords = Coordinates::new_magnified(-2147483648.0, 0.0, 4, 10).unwrap();
+        coords.set_word_count(2);
+        assert_eq!(coords.size(), 2);
+        assert_eq!(coords.x, WideFloat::from_i32(i32::MIN, 2));
+    }
+
+    #[test]
+    fn step_is_clamped_at_max_zoom() {
+        let mut coords = Coordinates::new(-0.75, 0.1, 4.0 / 500.0, 10).unwrap();
+        let mut zooms = 0;
+        while !coords.zoom_with_anchor(0.3, 400.0, 250.0, 1.0).unwrap() {
+            assert!(coords.step > 0, "step degenerated after {} zooms", zooms);
+            zooms += 1;
+            assert!(zooms < 10_000, "step has never been clamped");
+        }
+        assert_eq!(coords.size(), MAX_WORD_COUNT);
+
+        let min_step = WideFloat::min_positive(MAX_WORD_COUNT, 0);
+        assert_eq!(coords.step, min_step);
+        let (x, y) = (coords.x.clone(), coords.y.clone());
+        for _ in 0..10 {
+            assert!(coords.zoom_with_anchor(0.5, 400.0, 250.0, 1.0).unwrap());
+            assert_eq!(coords.step, min_step);
+        }
+        // The anchor stays in place, so nothing moves at the min step
+        assert_eq!((coords.x.clone(), coords.y.clone()), (x, y));
+
+        // Zooming out is still possible
+        assert!(!coords.zoom_with_anchor(2.0, 400.0, 250.0, 1.0).unwrap());
+        assert!(coords.step > min_step);
+    }
+
+    #[test]
+    fn zoom_out_is_limited_at_any_word_count() {
+        let start = Coordinates::new(-0.75, 0.1, 1.0 / 512.0, 10).unwrap();
+        let anchor = start.complex_at_pixel(400, 250);
+        let mut coords = start.clone();
+        while coords.size() < 6 {
+            coords.zoom_with_anchor(0.5, 400.0, 250.0, 1.0).unwrap();
+        }
+
+        // Spare words are trimmed as the step grows, until it's clamped at the limit
+        let mut word_count = coords.size();
+        for _ in 0..10 {
+            coords.zoom_with_anchor(1e9, 400.0, 250.0, 1.0).unwrap();
+            assert!(coords.size() <= word_count);
+            word_count = coords.size();
+        }
+        assert_eq!(coords.size(), 2);
+        assert_eq!(coords.step, WideFloat::from_i32(1, 2));
+
+        // The anchor stays within a step of where it started
+        let (re, im) = coords.complex_at_pixel(400, 250);
+        let distance = |a: &WideFloat, b: &WideFloat| (a.clone() - b).max(b.clone() - a);
+        assert!(distance(&re, &anchor.0) <= start.step);
+        assert!(distance(&im, &anchor.1) <= start.step);
+    }
+
+    #[test]
+    fn word_count_has_hysteresis() {
+        let mut coords = Coordinates::new(-0.75, 0.1, 4.0 / 500.0, 10).unwrap();
+        while coords.size() == 2 {
+            coords.zoom_with_anchor(0.5, 400.0, 250.0, 1.0).unwrap();
+        }
+        for _ in 0..5 {
+            coords.zoom_with_anchor(2.0, 400.0, 250.0, 1.0).unwrap();
+            assert_eq!(coords.size(), 3);
+            coords.zoom_with_anchor(0.5, 400.0, 250.0, 1.0).unwrap();
+            assert_eq!(coords.size(), 3);
+        }
+        // Zooming out far enough still trims the spare words
+        for _ in 0..100 {
+            coords.zoom_with_anchor(2.0, 400.0, 250.0, 1.0).unwrap();
+        }
+        assert_eq!(coords.size(), 2);
+    }
+
+    #[test]
+    fn zooming_across_the_origin_keeps_the_anchor() {
+        // The view starts left of the origin, zooming into its right edge moves it across
+        let mut coords = Coordinates::new(-0.001, -0.001, 1e-5, 10).unwrap();
+        let anchor = |coords: &Coordinates, x: i32| {
+            coords.x.clone() + &(&coords.step * &WideFloat::from_i32(x, coords.size()))
+        };
+        for i in 0..200 {
+            let x = if i % 3 == 0 { 0 } else { 800 };
+            let mut before = anchor(&coords, x);
+            coords.zoom_with_anchor(0.7, x as f32, 250.0, 1.0).unwrap();
+            before.change_precision(coords.size() as isize - before.word_count() as isize);
+
+            let error = anchor(&coords, x) - &before;
+            let error = if error < 0 { -error } else { error };
+            let ulps = WideFloat::from_i32(4, coords.size());
+            assert!(error <= &WideFloat::min_positive(coords.size(), 0) * &ulps);
+        }
+        assert!(coords.x > 0);
+        assert!(coords.size() > 4);
+    }
+
+    #[test]
+    fn zooming_while_rotated_keeps_the_anchor() {
+        let mut coords = Coordinates::new(-0.75, 0.1, 1.0 / 512.0, 10).unwrap();
+        coords.set_rotation(-30.0).unwrap();
+        assert_eq!(coords.rotation(), 330.0);
+        for i in 0..200 {
+            let (x, y) = if i % 3 == 0 { (0, 600) } else { (800, 250) };
+            let (mut re, mut im) = coords.complex_at_pixel(x, y);
+            // Products with the sine and cosine are truncated to the size before the zoom
+            let ulps =
+                &WideFloat::min_positive(coords.size(), 0) * &WideFloat::from_i32(8, coords.size());
+            coords
+                .zoom_with_anchor(0.7, x as f32, y as f32, 1.0)
+                .unwrap();
+            let resize = |value: &mut WideFloat| {
+                value.change_precision(coords.size() as isize - value.word_count() as isize)
+            };
+            let mut ulps = ulps;
+            for value in [&mut re, &mut im, &mut ulps] {
+                resize(value);
+            }
+
+            let (after_re, after_im) = coords.complex_at_pixel(x, y);
+            for error in [after_re - &re, after_im - &im] {
+                let error = if error < 0 { -error } else { error };
+                assert!(error <= ulps, "{:x} after {} zooms", error, i);
+            }
+        }
+        assert!(coords.size() > 4);
+
+        // Moves follow the rotated view
+        let mut coords = Coordinates::new(0.0, 0.0, 1.0 / 512.0, 10).unwrap();
+        coords.set_rotation(90.0).unwrap();
+        coords.move_by_delta(-512.0, 0.0).unwrap();
+        assert!((coords.x.as_f32_approx()).abs() < 1e-6);
+        assert!((coords.y.as_f32_approx() - 1.0).abs() < 1e-6);
+        assert!(coords.set_rotation(f32::NAN).is_err());
+        assert_eq!(coords.rotation(), 90.0);
+    }
+
+    #[test]
+    fn pan_and_zoom_dont_allocate() {
+        let mut coords = Coordinates::new(-0.75, 0.1, 4.0 / 500.0, 10).unwrap();
+        while coords.size() < 8 {
+            coords.zoom_with_anchor(0.5, 400.0, 250.0, 1.0).unwrap();
+        }
+
+        let before = ALLOCATIONS.with(Cell::get);
+        for i in 0..100 {
+            coords.move_by_delta(3.0, -2.0).unwrap();
+            let mul = if i % 2 == 0 { 0.9 } else { 1.1 };
+            coords.zoom_with_anchor(mul, 400.0, 250.0, 1.0).unwrap();
+        }
+        assert_eq!(coords.size(), 8);
+        assert_eq!(ALLOCATIONS.with(Cell::get) - before, 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut coords = Coordinates::new(-0.75, 0.1, 4.0 / 500.0, 10).unwrap();
+        coords.set_word_count(5);
+        let json = serde_json::to_string(&coords).unwrap();
+        assert_eq!(serde_json::from_str::<Coordinates>(&json).unwrap(), coords);
+
+        let mismatched = json.replacen(r#""words":[0,"#, r#""words":["#, 1);
+        assert!(serde_json::from_str::<Coordinates>(&mismatched).is_err());
+
+        let binary = bincode::serialize(&coords).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Coordinates>(&binary).unwrap(),
+            coords
+        );
+        let mut serialized = SerializedCoordinates::from(coords.clone());
+        serialized.y.change_precision(-1);
+        let mismatched = bincode::serialize(&serialized).unwrap();
+        assert!(bincode::deserialize::<Coordinates>(&mismatched).is_err());
+        assert!(bincode::deserialize::<Coordinates>(&binary[..binary.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn moves_are_clamped() {
+        let mut coords = Coordinates::new(-0.75, 0.1, 1.0 / 512.0, 10).unwrap();
+        let max = WideFloat::from_i32(MAX_COORDINATE, 2);
+        for _ in 0..5000 {
+            coords.move_by_delta(-1e9, 3e8).unwrap();
+            assert_eq!(coords.x, max);
+            assert_eq!(coords.y, -max.clone());
+        }
+        coords.move_by_delta(512.0, 0.0).unwrap();
+        assert_eq!(coords.x, WideFloat::from_i32(3, 2));
+
+        // Coordinates past the limit can only move back
+        let mut coords = Coordinates::new(-9.0, 0.0, 1.0, 10).unwrap();
+        coords.move_by_delta(1.0, 0.0).unwrap();
+        assert_eq!(coords.x, WideFloat::from_i32(-9, 2));
+        coords.move_by_delta(-2.0, 0.0).unwrap();
+        assert_eq!(coords.x, WideFloat::from_i32(-7, 2));
+    }
+
+    #[test]
+    fn pixels_map_to_the_plane() {
+        let dimensions = ScaledDimensions {
+            width: 800,
+            height: 600,
+        };
+        let f = |value, size| WideFloat::from_f64(value, size).unwrap();
+        let mut coords =
+            Coordinates::from_parts(f(-2.0, 2), f(-1.5, 2), f(1.0 / 256.0, 2), 10).unwrap();
+        for size in [2, 3, 5, 3] {
+            // The precision may change between calls
+            coords.set_word_count(size);
+
+            assert_eq!(
+                coords.center(dimensions),
+                (f(-0.4375, size), f(-0.328125, size))
+            );
+            assert_eq!(
+                coords.complex_at_pixel(0, 0),
+                (f(-2.0, size), f(-1.5, size))
+            );
+            assert_eq!(
+                coords.complex_at_pixel(256, 512),
+                (f(-1.0, size), f(0.5, size))
+            );
+
+            let pixel = |re, im| coords.pixel_at_complex(&f(re, size), &f(im, size), dimensions);
+            assert_eq!(pixel(-1.0, 0.5), Some((256, 512)));
+            assert_eq!(pixel(-1.0 + 1.0 / 512.0, 0.5), Some((256, 512)));
+            assert_eq!(pixel(-1.0 - 2f64.powi(-20), 0.5), Some((255, 512)));
+            assert_eq!(pixel(-2.0, -1.5), Some((0, 0)));
+            assert_eq!(pixel(-2.0 + 799.5 / 256.0, 0.0), Some((799, 384)));
+            assert_eq!(pixel(-2.0 - 2f64.powi(-20), 0.0), None);
+            assert_eq!(pixel(-2.0 + 800.0 / 256.0, 0.0), None);
+            assert_eq!(pixel(0.0, 1.0e6), None);
+        }
+
+        // Rotated views map pixel centers back to the same pixels
+        let half = WideFloat::from_i32(1, 3) >> 1;
+        let mut rotated = coords.clone();
+        for degrees in [45.0, 90.0, 200.0] {
+            rotated.set_rotation(degrees).unwrap();
+            for (x, y) in [(0, 0), (17, 42), (799, 599)] {
+                let (dx, dy) = rotated.plane_offset(
+                    &(WideFloat::from_i32(x, 3) + &half),
+                    &(WideFloat::from_i32(y, 3) + &half),
+                );
+                let (re, im) = (rotated.x.clone() + &dx, rotated.y.clone() + &dy);
+                assert_eq!(
+                    rotated.pixel_at_complex(&re, &im, dimensions),
+                    Some((x as u32, y as u32))
+                );
+            }
+            let far = rotated.x.clone() + &WideFloat::from_i32(10, 3);
+            assert_eq!(rotated.pixel_at_complex(&far, &rotated.y, dimensions), None);
+        }
+
+        // Points of a different size are resized to the coordinates
+        let (re, im) = coords.complex_at_pixel(17, 42);
+        let mut re_wide = re.clone();
+        re_wide.change_precision(2);
+        assert_eq!(
+            coords.pixel_at_complex(&re_wide, &im, dimensions),
+            Some((17, 42))
+        );
+    }
+
+    #[test]
+    fn resized_views_keep_the_center_and_extent() {
+        let f = |value, size| WideFloat::from_f64(value, size).unwrap();
+        let from = ScaledDimensions {
+            width: 800,
+            height: 600,
+        };
+        let mut coords = Coordinates::new(-2.0, -1.5, 1.0 / 256.0, 10).unwrap();
+        coords.set_rotation(30.0).unwrap();
+        let center = coords.center(from);
+        let close = |a: WideFloat, b: &WideFloat| {
+            let diff = a - b;
+            diff < f(1e-9, 2) && diff > f(-1e-9, 2)
+        };
+        for to in [
+            ScaledDimensions {
+                width: 3200,
+                height: 2400,
+            },
+            // The extent is kept across the shortest side
+            ScaledDimensions {
+                width: 6400,
+                height: 2400,
+            },
+        ] {
+            let resized = coords.resized(from, to);
+            assert_eq!(resized.size(), 2);
+            assert_eq!(resized.step, f(1.0 / 1024.0, 2));
+            assert_eq!(resized.rotation(), 30.0);
+            let (x, y) = resized.center(to);
+            assert!(close(x, &center.0) && close(y, &center.1));
+        }
+
+        // Steps past the precision of the numbers get another word
+        let deep = Coordinates::new_magnified(-0.75, 0.1, 2, 10).unwrap();
+        let to = ScaledDimensions {
+            width: 3200,
+            height: 2400,
+        };
+        let resized = deep.resized(from, to);
+        assert_eq!(resized.size(), 3);
+        let mut step = deep.step.clone();
+        step.change_precision(1);
+        assert_eq!(&resized.step * &f(4.0, 3), step);
+    }
+
+    #[test]
+    fn pixels_map_between_views() {
+        let mut coords = Coordinates::new(-2.0, -1.5, 1.0 / 256.0, 10).unwrap();
+        let map = |transform: PixelTransform, (x, y): (f32, f32)| {
+            let [a, b, c, d] = transform.matrix;
+            (
+                a * x + c * y + transform.offset[0],
+                b * x + d * y + transform.offset[1],
+            )
+        };
+        assert_eq!(
+            coords.pixel_transform_to(&coords),
+            Some(PixelTransform {
+                matrix: [1.0, 0.0, 0.0, 1.0],
+                offset: [0.0, 0.0]
+            })
+        );
+
+        // A 2x zoom into the pixel 100, 60 maps the view into the half around it
+        let mut zoomed = coords.clone();
+        zoomed.zoom_with_anchor(0.5, 100.0, 60.0, 4.0).unwrap();
+        let transform = zoomed.pixel_transform_to(&coords).unwrap();
+        assert_eq!(map(transform, (100.0, 60.0)), (100.0, 60.0));
+        assert_eq!(map(transform, (0.0, 0.0)), (50.0, 30.0));
+
+        // Rotations turn the pixels around the corner
+        zoomed.set_rotation(90.0).unwrap();
+        let transform = zoomed.pixel_transform_to(&coords).unwrap();
+        let (x, y) = map(transform, (10.0, 0.0));
+        assert!((x - 50.0).abs() < 1e-4 && (y - 35.0).abs() < 1e-4);
+
+        // Deeper views map into the shallower pixels they came from
+        let shallow = coords.clone();
+        for _ in 0..40 {
+            coords.zoom_with_anchor(0.5, 0.0, 0.0, 4.0).unwrap();
+        }
+        assert!(coords.size() > shallow.size());
+        let transform = coords.pixel_transform_to(&shallow).unwrap();
+        assert_eq!(transform.offset, [0.0, 0.0]);
+        assert_eq!(transform.matrix[0], 0.5f32.powi(40));
+
+        let far = Coordinates::new(100_000.0, 0.0, 1.0e-9, 10).unwrap();
+        assert_eq!(far.pixel_transform_to(&shallow), None);
+    }
+
+    #[test]
+    fn unsupported_word_count_is_rejected() {
+        assert!(check_word_count(MIN_WORD_COUNT - 1).is_err());
+        assert!(check_word_count(MAX_WORD_COUNT + 1).is_err());
+        assert!(Coordinates::new_magnified(0.0, 0.0, MAX_WORD_COUNT + 1, 10).is_err());
+    }
+}
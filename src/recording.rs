@@ -0,0 +1,308 @@
+//! Zoom animations from one bookmark to another, calculated offscreen frame by frame and saved as
+//! numbered PNG images, or encoded into a video by ffmpeg on native targets
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{Arc, Mutex};
+
+use crate::bookmarks::Bookmark;
+use crate::float::WideFloat;
+use crate::gpu::RgbaImage;
+use crate::location::Location;
+use crate::screenshot::{self, ExportError, Metadata};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::video::{Encoder, VideoOptions};
+use crate::view_state::{LocationTooDeep, ViewState};
+
+/// Most frames a recording can have
+pub const MAX_FRAMES: usize = 100_000;
+
+/// Zoom the control panel asks to record
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub start: Bookmark,
+    pub end: Bookmark,
+    /// Amount of frames, the first and the last ones show the bookmarks
+    pub frames: usize,
+    /// Depth every frame is calculated to, unless it converges earlier
+    pub depth: u32,
+    /// Video to encode the frames into instead of saving them as images
+    #[cfg(not(target_arch = "wasm32"))]
+    pub video: Option<VideoOptions>,
+}
+
+/// Where the frames of a recording go
+#[derive(Debug)]
+pub enum Output {
+    /// Numbered images in the directory
+    Images(String),
+    /// Video encoded by ffmpeg as the frames come, one at a time
+    #[cfg(not(target_arch = "wasm32"))]
+    Video(Arc<Mutex<Encoder>>),
+}
+
+/// Recording in progress, one frame is calculated at a time
+#[derive(Debug)]
+pub struct Recording {
+    /// View the frames are shown in, with the fractal and the precision of the start bookmark
+    view: ViewState,
+    start: Location,
+    end: Location,
+    frames: usize,
+    depth: u32,
+    /// Frame being calculated, counted from 0
+    frame: usize,
+    output: Output,
+}
+
+impl Recording {
+    /// Starts recording `request` at the size of `view` into `output`
+    pub fn new(
+        view: &ViewState,
+        request: &Request,
+        output: Output,
+    ) -> Result<Self, LocationTooDeep> {
+        let mut end = view.clone();
+        end.jump_to_bookmark(&request.end)?;
+        let mut view = view.clone();
+        view.jump_to_bookmark(&request.start)?;
+        Ok(Self {
+            start: view.location(),
+            end: end.location(),
+            view,
+            frames: request.frames.max(2),
+            depth: request.depth,
+            frame: 0,
+            output,
+        })
+    }
+
+    /// Returns the view of the frame being calculated. Its precision grows along the zoom the same
+    /// way as while navigating
+    pub fn frame_view(&self) -> Result<ViewState, LocationTooDeep> {
+        let t = self.frame as f64 / (self.frames - 1) as f64;
+        let mut view = self.view.clone();
+        view.jump_to(&interpolate(&self.start, &self.end, t))?;
+        Ok(view)
+    }
+
+    /// Returns where the frame being calculated goes, to be saved away from the event loop
+    pub fn frame_sink(&self) -> FrameSink {
+        match &self.output {
+            Output::Images(directory) => {
+                FrameSink::Image(format!("{}/{:05}.png", directory, self.frame))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Output::Video(encoder) => FrameSink::Video {
+                encoder: encoder.clone(),
+                last: self.frame + 1 == self.frames,
+            },
+        }
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns the frame being calculated, counted from 0, and the amount of frames
+    pub fn progress(&self) -> (usize, usize) {
+        (self.frame, self.frames)
+    }
+
+    /// Moves on to the next frame. Returns false once all of them are recorded
+    pub fn advance(&mut self) -> bool {
+        self.frame += 1;
+        self.frame < self.frames
+    }
+}
+
+/// Destination of a frame of a recording
+pub enum FrameSink {
+    /// Name of the image
+    Image(String),
+    #[cfg(not(target_arch = "wasm32"))]
+    Video {
+        encoder: Arc<Mutex<Encoder>>,
+        /// The video ends with the frame
+        last: bool,
+    },
+}
+
+impl FrameSink {
+    /// Saves the frame `image` shows. Returns where it was saved, or where the video is once it
+    /// has ended
+    pub fn save(self, image: &RgbaImage, metadata: &Metadata) -> Result<String, ExportError> {
+        match self {
+            FrameSink::Image(name) => screenshot::export_as(image, metadata, &name),
+            #[cfg(not(target_arch = "wasm32"))]
+            FrameSink::Video { encoder, last } => {
+                let mut encoder = encoder.lock().expect("Encoder is only used to write");
+                encoder.write(image)?;
+                Ok(if last {
+                    encoder.finish()?
+                } else {
+                    String::new()
+                })
+            }
+        }
+    }
+}
+
+/// Returns the location `t` of the way from `start` to `end`, which both must have a span. The
+/// span changes by the same ratio every frame, and the center moves towards the point that stays
+/// in place on the screen while zooming from one span to the other. Numbers are sized for the
+/// larger of the two locations
+pub fn interpolate(start: &Location, end: &Location, t: f64) -> Location {
+    let span = |location: &Location| {
+        location
+            .span
+            .clone()
+            .expect("Recorded locations have a span")
+    };
+    let (start_span, end_span) = (span(start), span(end));
+    // Binary logarithm of the ratio of the spans
+    let ratio_log2 = match (start_span.magnitude_log10(), end_span.magnitude_log10()) {
+        (Some(start), Some(end)) => (end - start) / std::f64::consts::LOG10_2,
+        _ => 0.0,
+    };
+    // Zooming out is zooming in played backwards, so that the scale factors stay below 1
+    if ratio_log2 > 0.0 {
+        return interpolate(end, start, 1.0 - t);
+    }
+
+    let size = start.re.word_count().max(end.re.word_count());
+    let resized = |number: &WideFloat| {
+        let mut number = number.clone();
+        number.change_precision(size as isize - number.word_count() as isize);
+        number
+    };
+    // With the ratio r, centers are c1 + (c0 - c1) * (r^t - r) / (1 - r)
+    let remaining = if ratio_log2 == 0.0 {
+        1.0 - t
+    } else {
+        let ln_ratio = ratio_log2 * std::f64::consts::LN_2;
+        ((1.0 - t) * ln_ratio).exp_m1() / ln_ratio.exp_m1()
+    };
+    let offset_log2 = t * ratio_log2 + remaining.log2();
+    let center = |start: &WideFloat, end: &WideFloat| {
+        let end = resized(end);
+        let offset = scaled(&(resized(start) - &end), offset_log2);
+        end + &offset
+    };
+    Location {
+        re: center(&start.re, &end.re),
+        im: center(&start.im, &end.im),
+        span: Some(scaled(&resized(&start_span), t * ratio_log2)),
+    }
+}
+
+/// Returns `value` multiplied by 2 to the power of `log2`, which is at most 0
+fn scaled(value: &WideFloat, log2: f64) -> WideFloat {
+    let size = value.word_count();
+    if log2 == f64::NEG_INFINITY {
+        return WideFloat::zero(size);
+    }
+    // The fraction of the power is applied as a float in 1..2, the whole part as a shift
+    let whole = log2.floor();
+    let fraction =
+        WideFloat::from_f64((log2 - whole).exp2(), size).expect("Fraction of the power fits");
+    (value * &fraction) >> (-whole) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::Dimensions;
+    use crate::view_state::Precision;
+
+    fn location(re: &str, im: &str, span: &str, size: usize) -> Location {
+        let number = |text| WideFloat::from_decimal_str(text, size).unwrap();
+        Location {
+            re: number(re),
+            im: number(im),
+            span: Some(number(span)),
+        }
+    }
+
+    #[test]
+    fn zooms_are_exponential_and_keep_their_fixed_point() {
+        let start = location("-0.5", "0", "4", 3);
+        let end = location("-0.75", "0.1", "1e-6", 3);
+        assert_eq!(interpolate(&start, &end, 0.0), start);
+        let last = interpolate(&start, &end, 1.0);
+        assert_eq!((&last.re, &last.im), (&end.re, &end.im));
+
+        let ratio: f64 = 1e-6 / 4.0;
+        let fixed = (-0.75 - -0.5 * ratio) / (1.0 - ratio);
+        for t in [0.25, 0.5, 0.9] {
+            let frame = interpolate(&start, &end, t);
+            let span = frame.span.unwrap().as_f64_round();
+            assert!((span / (4.0 * ratio.powf(t)) - 1.0).abs() < 1e-9);
+            // The point the zoom converges to stays at the same place on the screen
+            let screen = (fixed - frame.re.as_f64_round()) / span;
+            assert!((screen - (fixed - -0.5) / 4.0).abs() < 1e-6);
+        }
+
+        // Zooming out goes through the same frames backwards
+        let back = interpolate(&end, &start, 0.75);
+        let forth = interpolate(&start, &end, 0.25);
+        assert!((back.re.as_f64_round() - forth.re.as_f64_round()).abs() < 1e-12);
+        assert_eq!(interpolate(&start, &start, 0.5), start);
+    }
+
+    #[test]
+    fn deep_frames_keep_the_precision_of_the_end() {
+        let start = location("-0.5", "0", "3", 2);
+        let end = location(
+            "-1.7400623825793399052208441670658256",
+            "0.0281753397792110489924115211453",
+            "1e-30",
+            5,
+        );
+        let frame = interpolate(&start, &end, 0.95);
+        assert_eq!(frame.re.word_count(), 5);
+        let span = frame.span.clone().unwrap();
+        // Frames near the end are centered within their span of it
+        let distance = (frame.re.clone() - &end.re).magnitude_log10().unwrap();
+        assert!(distance < span.magnitude_log10().unwrap());
+        assert!(
+            (span.magnitude_log10().unwrap() - (0.95 * -30.0 + 0.05 * 3f64.log10())).abs() < 1e-9
+        );
+    }
+
+    #[test]
+    fn precision_grows_along_the_recording() {
+        let mut view = ViewState::default(Dimensions::new_nonzero(320, 200), 1.0, Precision::Auto);
+        let start = view.bookmark("start".to_owned(), 500);
+        view.jump_to(&location(
+            "-1.7400623825793399052208",
+            "0.0281753397792110489",
+            "1e-18",
+            4,
+        ))
+        .unwrap();
+        let end = view.bookmark("end".to_owned(), 500);
+        let request = Request {
+            start,
+            end,
+            frames: 11,
+            depth: 500,
+            #[cfg(not(target_arch = "wasm32"))]
+            video: None,
+        };
+        let output = Output::Images("zoom".to_owned());
+        let mut recording = Recording::new(&view, &request, output).unwrap();
+        let first = recording.frame_view().unwrap();
+        assert!(
+            matches!(recording.frame_sink(), FrameSink::Image(name) if name == "zoom/00000.png")
+        );
+
+        let mut sizes = vec![first.coords().size()];
+        while recording.advance() {
+            sizes.push(recording.frame_view().unwrap().coords().size());
+        }
+        assert_eq!(recording.progress(), (11, 11));
+        assert!(sizes.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(sizes[0] < sizes[10]);
+        assert_eq!(sizes[10], view.coords().size());
+    }
+}
@@ -0,0 +1,225 @@
+//! Captured frames saved as PNG images, with the location and settings they were taken with
+//! embedded as text chunks to show them again
+
+use thiserror::Error;
+
+use crate::gpu::{PosterError, RgbaImage};
+
+/// Keyword of the text chunk with the location string of the view
+pub const LOCATION_KEYWORD: &str = "mandelbrot-location";
+
+/// Keyword of the text chunk with the settings string of the view
+pub const SETTINGS_KEYWORD: &str = "mandelbrot-settings";
+
+/// Text embedded into the image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub location: String,
+    pub settings: String,
+}
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("Unable to encode the image: {0}")]
+    Encoding(#[from] png::EncodingError),
+    #[error("Unable to save the image: {0}")]
+    Save(String),
+    #[error("The frame was lost with the GPU device")]
+    Lost,
+    #[error(transparent)]
+    Poster(#[from] PosterError),
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error(transparent)]
+    Video(#[from] crate::video::VideoError),
+}
+
+/// Encodes `image` as an sRGB PNG with `metadata` in its text chunks
+pub fn encode(image: &RgbaImage, metadata: &Metadata) -> Result<Vec<u8>, png::EncodingError> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+    encoder.add_text_chunk(LOCATION_KEYWORD.to_owned(), metadata.location.clone())?;
+    encoder.add_text_chunk(SETTINGS_KEYWORD.to_owned(), metadata.settings.clone())?;
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.pixels)?;
+    writer.finish()?;
+    Ok(out)
+}
+
+/// Returns the name of a file with `extension` saved `unix_secs` seconds after the epoch, like
+/// `mandelbrot-20240229-235959.png` in UTC
+pub fn file_name(unix_secs: u64, extension: &str) -> String {
+    format!("{}.{}", file_stem(unix_secs), extension)
+}
+
+/// Returns the name of a file saved `unix_secs` seconds after the epoch without an extension
+fn file_stem(unix_secs: u64) -> String {
+    let (days, secs) = (unix_secs / 86_400, unix_secs % 86_400);
+    let (year, month, day) = civil_date(days as i64);
+    format!(
+        "mandelbrot-{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+/// Returns a name of the current time without an extension, for the directories of recordings
+/// and their videos
+pub fn current_stem() -> String {
+    file_stem(storage::unix_secs())
+}
+
+/// Returns the year, month and day of the Gregorian calendar `days` days after 1970-01-01
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    // Days since 0000-03-01, counted in eras of 400 years that start in March, so that leap
+    // days end the year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// Encodes `image` and saves it under a name of the current time. Returns where it was saved
+pub fn export(image: &RgbaImage, metadata: &Metadata) -> Result<String, ExportError> {
+    let png = encode(image, metadata)?;
+    storage::save(&file_name(storage::unix_secs(), "png"), &png).map_err(ExportError::Save)
+}
+
+/// Encodes `image` and saves it as `name`, which may be in a directory. Returns where it was saved
+pub fn export_as(
+    image: &RgbaImage,
+    metadata: &Metadata,
+    name: &str,
+) -> Result<String, ExportError> {
+    let png = encode(image, metadata)?;
+    storage::save(name, &png).map_err(ExportError::Save)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    pub fn unix_secs() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    }
+
+    /// Writes the image to the pictures directory, or the current one without it
+    pub fn save(name: &str, png: &[u8]) -> Result<String, String> {
+        let path = dirs::picture_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(name);
+        let error = |e: std::io::Error| format!("{}: {}", path.display(), e);
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(error)?;
+        }
+        std::fs::write(&path, png).map_err(error)?;
+        Ok(path.display().to_string())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use wasm_bindgen::JsCast;
+
+    pub fn unix_secs() -> u64 {
+        (js_sys::Date::now() / 1000.0) as u64
+    }
+
+    /// Downloads the image through a link to a blob of it
+    pub fn save(name: &str, png: &[u8]) -> Result<String, String> {
+        let download = || {
+            let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(png));
+            let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
+                &parts,
+                web_sys::BlobPropertyBag::new().type_("image/png"),
+            )?;
+            let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+            let anchor: web_sys::HtmlAnchorElement = web_sys::window()
+                .and_then(|window| window.document())
+                .ok_or("No document to download from")?
+                .create_element("a")?
+                .dyn_into()?;
+            anchor.set_href(&url);
+            // Downloads have no directories, images of one keep its name as a prefix
+            anchor.set_download(&name.replace('/', "-"));
+            anchor.click();
+            web_sys::Url::revoke_object_url(&url)
+        };
+        download().map_err(|e: wasm_bindgen::JsValue| format!("{:?}", e))?;
+        Ok(name.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_is_embedded() {
+        let image = RgbaImage {
+            width: 2,
+            height: 1,
+            pixels: vec![255, 0, 0, 255, 0, 0, 255, 255],
+        };
+        let metadata = Metadata {
+            location: "mandelbrot re=-0.75 im=0.1 span=0.01".to_owned(),
+            settings: "dmVyc2lvbiAx".to_owned(),
+        };
+        let png = encode(&image, &metadata).unwrap();
+
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let text: Vec<_> = reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.as_str(), chunk.text.as_str()))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                (LOCATION_KEYWORD, metadata.location.as_str()),
+                (SETTINGS_KEYWORD, metadata.settings.as_str())
+            ]
+        );
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels, image.pixels);
+    }
+
+    #[test]
+    fn names_are_utc_times() {
+        assert_eq!(file_name(0, "png"), "mandelbrot-19700101-000000.png");
+        assert_eq!(
+            file_name(951_868_799, "png"),
+            "mandelbrot-20000229-235959.png"
+        );
+        assert_eq!(
+            file_name(1_709_251_200, "png"),
+            "mandelbrot-20240301-000000.png"
+        );
+        assert_eq!(
+            file_name(1_792_000_000, "mbit"),
+            "mandelbrot-20261014-174640.mbit"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Zoom by scrolling. Wheels report ticks and touchpads report pixels, both are calibrated to the
+//! same scale so that a wheel tick zooms by 2× at the default sensitivity
+
+use winit::event::MouseScrollDelta;
+
+/// Pixels of a touchpad scroll that zoom as much as a wheel tick
+const PIXELS_PER_LINE: f64 = 500.0;
+/// Smallest pixel scroll worth zooming by, smaller ones are added up until they get there
+const MIN_PIXELS: f64 = 1.0;
+/// Magnification of a wheel tick at the default sensitivity
+const LINE_MAGNIFICATION: f64 = 2.0;
+
+/// Lowest and highest zoom sensitivity selectable in the control panel
+pub const SENSITIVITY_RANGE: std::ops::RangeInclusive<f32> = 0.1..=4.0;
+
+/// How much a scroll zooms, chosen in the control panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollCalibration {
+    /// Multiplier of the scroll amount
+    pub sensitivity: f32,
+    /// Scrolling up zooms out instead of in
+    pub inverted: bool,
+}
+
+impl Default for ScrollCalibration {
+    fn default() -> Self {
+        Self {
+            sensitivity: 1.0,
+            inverted: false,
+        }
+    }
+}
+
+/// Scrolls turned into zooms
+#[derive(Debug, Default)]
+pub struct ScrollZoom {
+    pub calibration: ScrollCalibration,
+    /// Pixels scrolled that are too few to zoom by yet
+    pending_pixels: f64,
+}
+
+impl ScrollZoom {
+    pub fn new(calibration: ScrollCalibration) -> Self {
+        Self {
+            calibration,
+            pending_pixels: 0.0,
+        }
+    }
+
+    /// Returns the delta of [`crate::view_state::ViewState::zoom_with_anchor`] of the scroll, none
+    /// if it doesn't zoom yet
+    pub fn zoom(&mut self, delta: &MouseScrollDelta) -> Option<f32> {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, lines) => *lines as f64,
+            MouseScrollDelta::PixelDelta(position) => {
+                self.pending_pixels += position.y;
+                if self.pending_pixels.abs() < MIN_PIXELS {
+                    return None;
+                }
+                std::mem::take(&mut self.pending_pixels) / PIXELS_PER_LINE
+            }
+        };
+        let sign = if self.calibration.inverted { -1.0 } else { 1.0 };
+        let exponent = sign * lines * self.calibration.sensitivity as f64;
+        if !exponent.is_finite() || exponent == 0.0 {
+            return None;
+        }
+        // Zooming is a product of magnifications, so that a scroll in parts adds up like a single
+        // one of the same length
+        let magnification = LINE_MAGNIFICATION.powf(exponent);
+        Some(if magnification > 1.0 {
+            (magnification - 1.0) as f32
+        } else {
+            (1.0 - 1.0 / magnification) as f32
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::dpi::PhysicalPosition;
+
+    fn pixels(y: f64) -> MouseScrollDelta {
+        MouseScrollDelta::PixelDelta(PhysicalPosition { x: 0.0, y })
+    }
+
+    /// Returns the magnification of a zoom by `delta`
+    fn magnification(delta: f32) -> f64 {
+        if delta > 0.0 {
+            1.0 + delta as f64
+        } else {
+            1.0 / (1.0 - delta as f64)
+        }
+    }
+
+    #[test]
+    fn wheel_ticks_zoom_twofold() {
+        let mut scroll = ScrollZoom::default();
+        assert_eq!(
+            scroll.zoom(&MouseScrollDelta::LineDelta(0.0, 1.0)),
+            Some(1.0)
+        );
+        assert_eq!(
+            scroll.zoom(&MouseScrollDelta::LineDelta(0.0, -1.0)),
+            Some(-1.0)
+        );
+        assert_eq!(scroll.zoom(&MouseScrollDelta::LineDelta(1.0, 0.0)), None);
+
+        scroll.calibration = ScrollCalibration {
+            sensitivity: 2.0,
+            inverted: true,
+        };
+        assert_eq!(
+            scroll.zoom(&MouseScrollDelta::LineDelta(0.0, 1.0)),
+            Some(-3.0)
+        );
+    }
+
+    #[test]
+    fn tiny_pixel_deltas_add_up() {
+        let mut whole = ScrollZoom::default();
+        let expected = magnification(whole.zoom(&pixels(120.0)).unwrap());
+
+        let mut parts = ScrollZoom::default();
+        let total: f64 = (0..400)
+            .filter_map(|_| parts.zoom(&pixels(0.3)))
+            .map(magnification)
+            .product();
+        assert!(
+            (total / expected - 1.0).abs() < 1e-4,
+            "{} {}",
+            total,
+            expected
+        );
+
+        // Deltas back and forth cancel out before they zoom
+        assert_eq!(parts.zoom(&pixels(0.6)), None);
+        assert_eq!(parts.zoom(&pixels(-0.6)), None);
+        assert_eq!(parts.zoom(&pixels(0.9)), None);
+    }
+}
@@ -0,0 +1,570 @@
+//! Exact state of the last session, restored on startup. Kept apart from user settings, so that
+//! resetting them doesn't lose the position
+
+use std::fmt::Write;
+use thiserror::Error;
+
+use crate::defaults;
+use crate::float::WideFloat;
+use crate::fractal::{FractalKind, FractalParams, JuliaSeed, POWER_RANGE};
+use crate::gpu::{ColorParams, DisplayOptions, PowerPreference, GAMMA_RANGE};
+use crate::i18n::Language;
+use crate::pan::PanBinding;
+use crate::primitives::{CoordError, Coordinates};
+use crate::scroll::{ScrollCalibration, SENSITIVITY_RANGE};
+use crate::timer::{Clock, SystemClock};
+use crate::view_state::Precision;
+
+const VERSION: u32 = 1;
+
+/// Time between automatic saves in milliseconds
+const AUTOSAVE_INTERVAL_MS: f64 = 30_000.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub coords: Coordinates,
+    /// View scale factor the coordinate step applies to
+    pub scale_factor: f64,
+    pub fractal: FractalParams,
+    pub precision: Precision,
+    /// Depth calculated so far
+    pub depth: u32,
+    /// Depth limit chosen in the control panel
+    pub max_depth: u32,
+    pub colors: ColorParams,
+    pub window: WindowState,
+    /// Language chosen in the settings, none to follow the system
+    pub language: Option<Language>,
+    /// Zoom by scrolling chosen in the settings
+    pub scroll: ScrollCalibration,
+    /// Binding of the pan gesture chosen in the settings
+    pub pan_binding: PanBinding,
+    /// Presentation and adapter chosen in the settings
+    pub display: DisplayOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowState {
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("Unsupported session version {0}")]
+    Version(String),
+    #[error("Missing field {0}")]
+    MissingField(&'static str),
+    #[error("Invalid field {0}")]
+    InvalidField(&'static str),
+    #[error("Invalid coordinates: {0}")]
+    Coordinates(#[from] CoordError),
+}
+
+impl Session {
+    /// Serializes the session into a line based `key value` text
+    pub fn encode(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "version {}", VERSION).unwrap();
+        writeln!(out, "x {}", encode_wide(&self.coords.x)).unwrap();
+        writeln!(out, "y {}", encode_wide(&self.coords.y)).unwrap();
+        writeln!(out, "step {}", encode_wide(&self.coords.step)).unwrap();
+        writeln!(out, "precision {}", self.coords.precision()).unwrap();
+        writeln!(out, "rotation {}", self.coords.rotation()).unwrap();
+        writeln!(out, "scale_factor {}", self.scale_factor).unwrap();
+        writeln!(out, "fractal {}", self.fractal.kind.name()).unwrap();
+        writeln!(out, "inverted {}", self.fractal.inverted).unwrap();
+        writeln!(out, "power {}", self.fractal.power).unwrap();
+        if let Some(seed) = &self.fractal.julia {
+            writeln!(out, "julia {}", encode_julia(seed)).unwrap();
+        }
+        writeln!(out, "precision_mode {}", self.precision).unwrap();
+        writeln!(out, "depth {}", self.depth).unwrap();
+        writeln!(out, "max_depth {}", self.max_depth).unwrap();
+        writeln!(out, "smooth_colors {}", self.colors.smooth).unwrap();
+        writeln!(out, "equalize_colors {}", self.colors.equalize).unwrap();
+        writeln!(out, "gamma {}", self.colors.gamma).unwrap();
+        writeln!(
+            out,
+            "window {} {} {}",
+            self.window.width, self.window.height, self.window.maximized
+        )
+        .unwrap();
+        if let Some(language) = self.language {
+            writeln!(out, "language {}", language.code()).unwrap();
+        }
+        writeln!(out, "zoom_sensitivity {}", self.scroll.sensitivity).unwrap();
+        writeln!(out, "invert_zoom {}", self.scroll.inverted).unwrap();
+        writeln!(out, "pan_binding {}", self.pan_binding.name()).unwrap();
+        writeln!(out, "vsync {}", self.display.vsync).unwrap();
+        writeln!(out, "power_preference {}", self.display.power.name()).unwrap();
+        out
+    }
+
+    pub fn decode(text: &str) -> Result<Self, SessionError> {
+        let field = |name: &'static str| {
+            text.lines()
+                .find_map(|line| {
+                    let (key, value) = line.split_once(' ')?;
+                    (key == name).then_some(value.trim())
+                })
+                .ok_or(SessionError::MissingField(name))
+        };
+        fn parse<T: std::str::FromStr>(name: &'static str, value: &str) -> Result<T, SessionError> {
+            value.parse().map_err(|_| SessionError::InvalidField(name))
+        }
+
+        let version = field("version")?;
+        if parse::<u32>("version", version).ok() != Some(VERSION) {
+            return Err(SessionError::Version(version.to_owned()));
+        }
+
+        let mut coords = Coordinates::from_parts(
+            decode_wide("x", field("x")?)?,
+            decode_wide("y", field("y")?)?,
+            decode_wide("step", field("step")?)?,
+            parse("precision", field("precision")?)?,
+        )?;
+        // Sessions saved before rotation support are axis-aligned
+        if let Ok(value) = field("rotation") {
+            coords.set_rotation(parse("rotation", value)?)?;
+        }
+
+        let scale_factor: f64 = parse("scale_factor", field("scale_factor")?)?;
+        if !(scale_factor.is_finite() && scale_factor > 0.0) {
+            return Err(SessionError::InvalidField("scale_factor"));
+        }
+
+        let fractal = FractalParams {
+            kind: FractalKind::from_name(field("fractal")?)
+                .ok_or(SessionError::InvalidField("fractal"))?,
+            inverted: parse("inverted", field("inverted")?)?,
+            // Sessions saved before multibrots square z
+            power: match field("power") {
+                Ok(value) => Some(parse::<u32>("power", value)?)
+                    .filter(|power| POWER_RANGE.contains(power))
+                    .ok_or(SessionError::InvalidField("power"))?,
+                Err(_) => 2,
+            },
+            julia: match field("julia") {
+                Ok(value) => Some(parse_julia(value).ok_or(SessionError::InvalidField("julia"))?),
+                Err(_) => None,
+            },
+        };
+
+        let mut window = field("window")?.split(' ');
+        let mut window_field = || window.next().ok_or(SessionError::InvalidField("window"));
+        let window = WindowState {
+            width: parse("window", window_field()?)?,
+            height: parse("window", window_field()?)?,
+            maximized: parse("window", window_field()?)?,
+        };
+
+        // Sessions saved before the automatic precision used the manual one
+        let precision = match field("precision_mode") {
+            Ok(value) => parse("precision_mode", value)?,
+            Err(_) => Precision::Manual(coords.precision()),
+        };
+
+        let language = match field("language") {
+            Ok(value) => {
+                Some(Language::from_code(value).ok_or(SessionError::InvalidField("language"))?)
+            }
+            Err(_) => None,
+        };
+
+        // Sessions saved before the scroll calibration zoom by default
+        let mut scroll = ScrollCalibration::default();
+        if let Ok(value) = field("zoom_sensitivity") {
+            scroll.sensitivity = parse("zoom_sensitivity", value)?;
+            if !SENSITIVITY_RANGE.contains(&scroll.sensitivity) {
+                return Err(SessionError::InvalidField("zoom_sensitivity"));
+            }
+        }
+        if let Ok(value) = field("invert_zoom") {
+            scroll.inverted = parse("invert_zoom", value)?;
+        }
+
+        let pan_binding = match field("pan_binding") {
+            Ok(value) => {
+                PanBinding::from_name(value).ok_or(SessionError::InvalidField("pan_binding"))?
+            }
+            Err(_) => PanBinding::default(),
+        };
+
+        // Sessions saved before the depth limit and the colors were kept start with the defaults
+        let max_depth = match field("max_depth") {
+            Ok(value) => Some(parse::<u32>("max_depth", value)?)
+                .filter(|max_depth| *max_depth > 0)
+                .ok_or(SessionError::InvalidField("max_depth"))?,
+            Err(_) => defaults::MAX_DEPTH,
+        };
+        let mut colors = ColorParams::default();
+        if let Ok(value) = field("smooth_colors") {
+            colors.smooth = parse("smooth_colors", value)?;
+        }
+        if let Ok(value) = field("equalize_colors") {
+            colors.equalize = parse("equalize_colors", value)?;
+        }
+        if let Ok(value) = field("gamma") {
+            colors.gamma = parse("gamma", value)?;
+            if !GAMMA_RANGE.contains(&colors.gamma) {
+                return Err(SessionError::InvalidField("gamma"));
+            }
+        }
+
+        // Sessions saved before the display options present with vsync on the fast adapter
+        let mut display = DisplayOptions::default();
+        if let Ok(value) = field("vsync") {
+            display.vsync = parse("vsync", value)?;
+        }
+        if let Ok(value) = field("power_preference") {
+            display.power = PowerPreference::from_name(value)
+                .ok_or(SessionError::InvalidField("power_preference"))?;
+        }
+
+        Ok(Session {
+            coords,
+            scale_factor,
+            fractal,
+            precision,
+            depth: parse("depth", field("depth")?)?,
+            max_depth,
+            colors,
+            window,
+            language,
+            scroll,
+            pan_binding,
+            display,
+        })
+    }
+}
+
+/// Words as hex, most significant first
+pub(crate) fn encode_wide(value: &WideFloat) -> String {
+    let words: Vec<_> = value
+        .words()
+        .iter()
+        .rev()
+        .map(|w| format!("{:08x}", w))
+        .collect();
+    words.join(":")
+}
+
+fn decode_wide(name: &'static str, text: &str) -> Result<WideFloat, SessionError> {
+    parse_wide(text).ok_or(SessionError::InvalidField(name))
+}
+
+/// Parses the words written by [`encode_wide`]
+pub(crate) fn parse_wide(text: &str) -> Option<WideFloat> {
+    let mut words = text
+        .split(':')
+        .map(|w| u32::from_str_radix(w, 16))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    words.reverse();
+    Some(WideFloat::from_words(words))
+}
+
+/// Real and imaginary part of the seed, as written by [`encode_wide`]
+pub(crate) fn encode_julia(seed: &JuliaSeed) -> String {
+    format!("{} {}", encode_wide(&seed.re), encode_wide(&seed.im))
+}
+
+/// Parses the seed written by [`encode_julia`]
+pub(crate) fn parse_julia(text: &str) -> Option<JuliaSeed> {
+    let (re, im) = text.split_once(' ')?;
+    Some(JuliaSeed {
+        re: parse_wide(re)?,
+        im: parse_wide(im.trim())?,
+    })
+}
+
+/// Loads the last session. Missing and corrupt sessions are ignored
+pub fn load() -> Option<Session> {
+    let text = storage::read()?;
+    match Session::decode(&text) {
+        Ok(session) => Some(session),
+        Err(e) => {
+            log::warn!("Ignoring corrupt session: {}", e);
+            None
+        }
+    }
+}
+
+pub fn save(session: &Session) {
+    storage::write(&session.encode());
+}
+
+/// Tells when the session is due for an automatic save
+pub struct Autosave {
+    clock: SystemClock,
+    last_save_ms: f64,
+}
+
+impl Autosave {
+    pub fn new() -> Self {
+        Self {
+            clock: SystemClock::default(),
+            last_save_ms: 0.0,
+        }
+    }
+
+    /// Returns true once per autosave interval
+    pub fn is_due(&mut self) -> bool {
+        let now = self.clock.now();
+        if now - self.last_save_ms >= AUTOSAVE_INTERVAL_MS {
+            self.last_save_ms = now;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::path::PathBuf;
+
+    fn path() -> Option<PathBuf> {
+        Some(
+            dirs::data_local_dir()?
+                .join("mandelbrot")
+                .join("session.txt"),
+        )
+    }
+
+    pub fn read() -> Option<String> {
+        let path = path()?;
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Some(text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("Unable to read session {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn write(text: &str) {
+        let Some(path) = path() else {
+            log::warn!("No data directory to save the session to");
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, text));
+        if let Err(e) = result {
+            log::warn!("Unable to save session {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    const KEY: &str = "mandelbrot-session";
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok().flatten()
+    }
+
+    pub fn read() -> Option<String> {
+        local_storage()?.get_item(KEY).ok().flatten()
+    }
+
+    pub fn write(text: &str) {
+        if let Some(Err(e)) = local_storage().map(|s| s.set_item(KEY, text)) {
+            log::warn!("Unable to save session: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        let mut coords = Coordinates::new(-0.7436, 0.1318, 1e-3, 10).unwrap();
+        for _ in 0..40 {
+            coords.zoom_with_anchor(0.5, 400.0, 250.0, 1.0).unwrap();
+        }
+        coords.move_by_delta(-13.0, 7.0).unwrap();
+        coords.set_rotation(30.5).unwrap();
+        Session {
+            coords,
+            scale_factor: 1.5,
+            fractal: FractalParams {
+                kind: FractalKind::Celtic,
+                inverted: true,
+                power: 5,
+                julia: JuliaSeed::from_f64(-0.8, 0.156),
+            },
+            precision: Precision::Auto,
+            depth: 12345,
+            max_depth: 40_000,
+            colors: ColorParams {
+                smooth: true,
+                equalize: true,
+                gamma: 1.8,
+            },
+            window: WindowState {
+                width: 1280,
+                height: 720,
+                maximized: false,
+            },
+            language: Some(Language::German),
+            scroll: ScrollCalibration {
+                sensitivity: 0.5,
+                inverted: true,
+            },
+            pan_binding: PanBinding::Middle,
+            display: DisplayOptions {
+                vsync: false,
+                power: PowerPreference::LowPower,
+            },
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let session = session();
+        assert!(session.coords.size() > 2);
+        let decoded = Session::decode(&session.encode()).unwrap();
+        assert_eq!(decoded, session);
+        assert_eq!(decoded.encode(), session.encode());
+    }
+
+    #[test]
+    fn negative_coordinates_round_trip() {
+        let mut session = session();
+        session.coords = Coordinates::new(-1.25, -0.5, 0.01, 64).unwrap();
+        assert_eq!(Session::decode(&session.encode()).unwrap(), session);
+    }
+
+    #[test]
+    fn language_is_optional() {
+        let mut session = session();
+        session.language = None;
+        let encoded = session.encode();
+        assert!(!encoded.contains("language"));
+        assert_eq!(Session::decode(&encoded).unwrap(), session);
+    }
+
+    #[test]
+    fn scroll_calibration_is_optional() {
+        let encoded = session()
+            .encode()
+            .replace("zoom_sensitivity 0.5\n", "")
+            .replace("invert_zoom true\n", "");
+        let decoded = Session::decode(&encoded).unwrap();
+        assert_eq!(decoded.scroll, ScrollCalibration::default());
+        let encoded = session().encode().replace("pan_binding middle\n", "");
+        assert_eq!(
+            Session::decode(&encoded).unwrap().pan_binding,
+            PanBinding::Left
+        );
+    }
+
+    #[test]
+    fn display_options_are_optional() {
+        let encoded = session()
+            .encode()
+            .replace("vsync false\n", "")
+            .replace("power_preference low_power\n", "");
+        let decoded = Session::decode(&encoded).unwrap();
+        assert_eq!(decoded.display, DisplayOptions::default());
+        assert!(decoded.display.vsync);
+    }
+
+    #[test]
+    fn depth_limit_and_colors_are_optional() {
+        let encoded = session()
+            .encode()
+            .replace("max_depth 40000\n", "")
+            .replace("smooth_colors true\n", "")
+            .replace("equalize_colors true\n", "")
+            .replace("gamma 1.8\n", "");
+        let decoded = Session::decode(&encoded).unwrap();
+        assert_eq!(decoded.max_depth, defaults::MAX_DEPTH);
+        assert_eq!(decoded.colors, ColorParams::default());
+    }
+
+    #[test]
+    fn sessions_of_other_versions_are_rejected() {
+        // Other versions might lay out the same fields differently, their sessions start afresh
+        for version in ["0", "2", "1.5", ""] {
+            let encoded = session()
+                .encode()
+                .replace("version 1\n", &format!("version {}\n", version));
+            assert!(matches!(
+                Session::decode(&encoded),
+                Err(SessionError::Version(v)) if v == version
+            ));
+        }
+        let encoded = session().encode().replace("version 1\n", "");
+        assert!(matches!(
+            Session::decode(&encoded),
+            Err(SessionError::MissingField("version"))
+        ));
+    }
+
+    #[test]
+    fn julia_seed_is_optional() {
+        let mut session = session();
+        session.fractal.julia = None;
+        let encoded = session.encode();
+        assert!(!encoded.contains("julia"));
+        assert_eq!(Session::decode(&encoded).unwrap(), session);
+    }
+
+    #[test]
+    fn power_is_optional() {
+        let encoded = session().encode().replace("power 5\n", "");
+        assert_eq!(Session::decode(&encoded).unwrap().fractal.power, 2);
+    }
+
+    #[test]
+    fn rotation_is_optional() {
+        let encoded = session().encode().replace("rotation 30.5\n", "");
+        let decoded = Session::decode(&encoded).unwrap();
+        assert_eq!(decoded.coords.rotation(), 0.0);
+        assert_eq!(decoded.coords.x, session().coords.x);
+    }
+
+    #[test]
+    fn corrupt_sessions_are_rejected() {
+        let encoded = session().encode();
+        let replace = |from: &str, to: &str| Session::decode(&encoded.replace(from, to));
+
+        assert!(Session::decode("").is_err());
+        assert!(Session::decode("garbage\n\0\u{fffd}").is_err());
+        assert!(replace("version 1", "version 2").is_err());
+        assert!(replace("fractal celtic", "fractal julia").is_err());
+        assert!(replace("scale_factor 1.5", "scale_factor NaN").is_err());
+        assert!(replace("window 1280 720 false", "window 1280").is_err());
+        assert!(replace("inverted true", "inverted yes").is_err());
+        assert!(replace("language de", "language xx").is_err());
+        assert!(replace("rotation 30.5", "rotation inf").is_err());
+        assert!(replace("zoom_sensitivity 0.5", "zoom_sensitivity 0").is_err());
+        assert!(replace("invert_zoom true", "invert_zoom 1").is_err());
+        assert!(replace("pan_binding middle", "pan_binding right").is_err());
+        assert!(replace("vsync false", "vsync off").is_err());
+        assert!(replace("power_preference low_power", "power_preference fast").is_err());
+        assert!(replace("power 5", "power 9").is_err());
+        assert!(replace("power 5", "power 1.5").is_err());
+        assert!(replace("max_depth 40000", "max_depth 0").is_err());
+        assert!(replace("gamma 1.8", "gamma 0.5").is_err());
+        assert!(replace("smooth_colors true", "smooth_colors 1").is_err());
+        let julia = encoded.lines().find(|l| l.starts_with("julia ")).unwrap();
+        assert!(replace(julia, "julia 00000000").is_err());
+
+        // Word counts must match and be supported
+        let x = encoded.lines().find(|l| l.starts_with("x ")).unwrap();
+        assert!(replace(x, "x 00000000").is_err());
+        assert!(replace(x, "x zz").is_err());
+        let truncated: String = encoded.lines().take(3).collect::<Vec<_>>().join("\n");
+        assert!(Session::decode(&truncated).is_err());
+    }
+}
@@ -0,0 +1,432 @@
+//! Visual settings shared as a single line of text. Locations are shared separately, so that the
+//! same setup can be applied anywhere
+
+use base64::engine::{general_purpose::URL_SAFE_NO_PAD, Engine};
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::defaults;
+use crate::fps_balancer::TARGET_FPS_RANGE;
+use crate::fractal::{
+    FractalKind, FractalParams, IterationParams, ESCAPE_RADIUS_RANGE, POWER_RANGE,
+};
+use crate::gpu::{ColorParams, DEFAULT_GAMMA, GAMMA_RANGE};
+use crate::palette::Palette;
+use crate::session::{encode_julia, parse_julia};
+use crate::view_state::Precision;
+
+const VERSION: u32 = 1;
+
+/// Longest JSON a settings string may inflate to
+const MAX_JSON_LEN: usize = 64 * 1024;
+
+/// Highest manual precision selectable in the control panel
+pub const MAX_PRECISION_BITS: usize = 4 * 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub max_depth: u32,
+    pub iteration: IterationParams,
+    pub scale_factor: f64,
+    pub precision: Precision,
+    pub fractal: FractalParams,
+    pub antialiasing: bool,
+    pub colors: ColorParams,
+    pub palette: Palette,
+    /// Frame rate the iterations are balanced for
+    pub target_fps: f64,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SettingsError {
+    #[error("Not a settings string")]
+    Encoding,
+    #[error("Settings string is made by a newer version ({0})")]
+    Version(String),
+    #[error("Settings string is missing {0}")]
+    MissingField(&'static str),
+    #[error("Settings string has invalid {0}")]
+    InvalidField(&'static str),
+}
+
+impl Settings {
+    /// Serializes the settings into versioned JSON, deflated and base64 encoded
+    pub fn encode(&self) -> String {
+        let mut fields = json!({
+            "version": VERSION,
+            "max_depth": self.max_depth,
+            "escape_radius": self.iteration.escape_radius,
+            "distance_estimation": self.iteration.distance_estimation,
+            "scale_factor": self.scale_factor,
+            "precision": self.precision.to_string(),
+            "fractal": self.fractal.kind.name(),
+            "inverted": self.fractal.inverted,
+            "power": self.fractal.power,
+            "antialiasing": self.antialiasing,
+            "smooth_colors": self.colors.smooth,
+            "equalize_colors": self.colors.equalize,
+            "gamma": self.colors.gamma,
+            "gradient": self.palette.encode(),
+            "target_fps": self.target_fps,
+        });
+        if let Some(seed) = &self.fractal.julia {
+            fields["julia"] = Value::from(encode_julia(seed));
+        }
+        URL_SAFE_NO_PAD.encode(compress_to_vec(fields.to_string().as_bytes(), 9))
+    }
+
+    /// Parses a settings string. Unknown fields are ignored, so that strings of newer versions
+    /// that only add fields keep working
+    pub fn decode(text: &str) -> Result<Self, SettingsError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(text.trim())
+            .map_err(|_| SettingsError::Encoding)?;
+        let json = decompress_to_vec_with_limit(&bytes, MAX_JSON_LEN)
+            .map_err(|_| SettingsError::Encoding)?;
+        let fields = match serde_json::from_slice(&json) {
+            Ok(Value::Object(fields)) => fields,
+            _ => return Err(SettingsError::Encoding),
+        };
+
+        let field = |name: &'static str| fields.get(name).ok_or(SettingsError::MissingField(name));
+        fn number(name: &'static str, value: &Value) -> Result<f64, SettingsError> {
+            value.as_f64().ok_or(SettingsError::InvalidField(name))
+        }
+        fn integer(name: &'static str, value: &Value) -> Result<u32, SettingsError> {
+            value
+                .as_u64()
+                .and_then(|value| u32::try_from(value).ok())
+                .ok_or(SettingsError::InvalidField(name))
+        }
+        fn boolean(name: &'static str, value: &Value) -> Result<bool, SettingsError> {
+            value.as_bool().ok_or(SettingsError::InvalidField(name))
+        }
+        fn string<'a>(name: &'static str, value: &'a Value) -> Result<&'a str, SettingsError> {
+            value.as_str().ok_or(SettingsError::InvalidField(name))
+        }
+
+        let version = field("version").map_err(|_| SettingsError::Encoding)?;
+        match integer("version", version)? {
+            0 => return Err(SettingsError::InvalidField("version")),
+            1..=VERSION => {}
+            _ => return Err(SettingsError::Version(version.to_string())),
+        }
+
+        let max_depth = integer("max_depth", field("max_depth")?)?;
+        if max_depth == 0 {
+            return Err(SettingsError::InvalidField("max_depth"));
+        }
+
+        // Added after the first version, the usual radius and no distance estimation in strings
+        // that don't have them
+        let iteration = IterationParams {
+            escape_radius: match field("escape_radius") {
+                Ok(value) => Some(number("escape_radius", value)? as f32)
+                    .filter(|radius| ESCAPE_RADIUS_RANGE.contains(radius))
+                    .ok_or(SettingsError::InvalidField("escape_radius"))?,
+                Err(_) => IterationParams::default().escape_radius,
+            },
+            distance_estimation: match field("distance_estimation") {
+                Ok(value) => boolean("distance_estimation", value)?,
+                Err(_) => false,
+            },
+        };
+
+        let scale_factor = number("scale_factor", field("scale_factor")?)?;
+        if !(1.0..=30.0).contains(&scale_factor) {
+            return Err(SettingsError::InvalidField("scale_factor"));
+        }
+
+        let precision: Precision = string("precision", field("precision")?)?
+            .parse()
+            .map_err(|_| SettingsError::InvalidField("precision"))?;
+        if matches!(precision, Precision::Manual(bits) if bits > MAX_PRECISION_BITS) {
+            return Err(SettingsError::InvalidField("precision"));
+        }
+
+        let fractal = FractalParams {
+            kind: FractalKind::from_name(string("fractal", field("fractal")?)?)
+                .ok_or(SettingsError::InvalidField("fractal"))?,
+            inverted: boolean("inverted", field("inverted")?)?,
+            // Added after the first version, squares in strings that don't have it
+            power: match field("power") {
+                Ok(value) => Some(integer("power", value)?)
+                    .filter(|power| POWER_RANGE.contains(power))
+                    .ok_or(SettingsError::InvalidField("power"))?,
+                Err(_) => 2,
+            },
+            // Only Julia sets have a seed
+            julia: match field("julia") {
+                Ok(value) => Some(
+                    parse_julia(string("julia", value)?)
+                        .ok_or(SettingsError::InvalidField("julia"))?,
+                ),
+                Err(_) => None,
+            },
+        };
+
+        // Added after the first version, off in strings that don't have it
+        let antialiasing = match field("antialiasing") {
+            Ok(value) => boolean("antialiasing", value)?,
+            Err(_) => false,
+        };
+        // Added after the first version, bands of unequalized colors in strings that don't have it
+        let colors = ColorParams {
+            smooth: match field("smooth_colors") {
+                Ok(value) => boolean("smooth_colors", value)?,
+                Err(_) => false,
+            },
+            equalize: match field("equalize_colors") {
+                Ok(value) => boolean("equalize_colors", value)?,
+                Err(_) => false,
+            },
+            // Added after the first version, uncorrected sRGB in strings that don't have it
+            gamma: match field("gamma") {
+                Ok(value) => Some(number("gamma", value)? as f32)
+                    .filter(|gamma| GAMMA_RANGE.contains(gamma))
+                    .ok_or(SettingsError::InvalidField("gamma"))?,
+                Err(_) => DEFAULT_GAMMA,
+            },
+        };
+        // Added after the first version, the classic palette in strings that don't have it
+        let palette = match field("gradient") {
+            Ok(value) => Palette::parse(string("gradient", value)?)
+                .ok_or(SettingsError::InvalidField("gradient"))?,
+            Err(_) => Palette::default(),
+        };
+        // Added after the first version, the default frame rate in strings that don't have it
+        let target_fps = match field("target_fps") {
+            Ok(value) => Some(number("target_fps", value)?)
+                .filter(|fps| TARGET_FPS_RANGE.contains(fps))
+                .ok_or(SettingsError::InvalidField("target_fps"))?,
+            Err(_) => defaults::TARGET_FPS,
+        };
+
+        Ok(Settings {
+            max_depth,
+            iteration,
+            scale_factor,
+            precision,
+            fractal,
+            antialiasing,
+            colors,
+            palette,
+            target_fps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::JuliaSeed;
+    use serde_json::Map;
+
+    fn settings() -> Settings {
+        Settings {
+            max_depth: 12345,
+            iteration: IterationParams {
+                escape_radius: 256.0,
+                distance_estimation: true,
+            },
+            scale_factor: 2.25,
+            precision: Precision::Manual(64),
+            fractal: FractalParams {
+                kind: FractalKind::Celtic,
+                inverted: true,
+                power: 3,
+                julia: JuliaSeed::from_f64(0.285, 0.01),
+            },
+            antialiasing: true,
+            colors: ColorParams {
+                smooth: true,
+                equalize: true,
+                gamma: 1.8,
+            },
+            palette: Palette::parse("0:102030 0.5:ff8000 1:ffffff").unwrap(),
+            target_fps: 60.0,
+        }
+    }
+
+    /// Returns the JSON fields of the settings string
+    fn fields() -> Map<String, Value> {
+        let bytes = URL_SAFE_NO_PAD.decode(settings().encode()).unwrap();
+        let json = decompress_to_vec_with_limit(&bytes, MAX_JSON_LEN).unwrap();
+        serde_json::from_slice(&json).unwrap()
+    }
+
+    /// Encodes JSON fields the way settings strings are encoded
+    fn encode(fields: &Map<String, Value>) -> String {
+        let json = Value::Object(fields.clone()).to_string();
+        URL_SAFE_NO_PAD.encode(compress_to_vec(json.as_bytes(), 9))
+    }
+
+    /// Decodes the fields of the test settings with one field removed
+    fn without(name: &str) -> Settings {
+        let mut fields = fields();
+        assert!(fields.remove(name).is_some());
+        Settings::decode(&encode(&fields)).unwrap()
+    }
+
+    /// Decodes the fields of the test settings with one field replaced
+    fn with(name: &str, value: Value) -> Result<Settings, SettingsError> {
+        let mut fields = fields();
+        fields.insert(name.to_owned(), value);
+        Settings::decode(&encode(&fields))
+    }
+
+    #[test]
+    fn round_trip() {
+        let encoded = settings().encode();
+        assert!(!encoded.contains(char::is_whitespace));
+        assert_eq!(Settings::decode(&encoded), Ok(settings()));
+        assert_eq!(Settings::decode(&format!(" {}\n", encoded)), Ok(settings()));
+    }
+
+    #[test]
+    fn unknown_fields_are_ignored() {
+        let mut extended = fields();
+        extended.insert("palette".to_owned(), json!(["000000", "ffffff"]));
+        extended.insert("smoothing".to_owned(), json!(true));
+        assert_eq!(Settings::decode(&encode(&extended)), Ok(settings()));
+    }
+
+    #[test]
+    fn automatic_precision_round_trip() {
+        let auto = Settings {
+            precision: Precision::Auto,
+            ..settings()
+        };
+        assert_eq!(Settings::decode(&auto.encode()), Ok(auto));
+    }
+
+    #[test]
+    fn missing_julia_seed_is_mandelbrot() {
+        assert_eq!(without("julia").fractal.julia, None);
+    }
+
+    #[test]
+    fn missing_power_is_square() {
+        assert_eq!(without("power").fractal.power, 2);
+    }
+
+    #[test]
+    fn missing_escape_radius_is_two() {
+        assert_eq!(
+            without("escape_radius").iteration,
+            IterationParams {
+                distance_estimation: true,
+                ..Default::default()
+            }
+        );
+
+        for radius in [json!(1), json!(1001), json!(null), json!("far")] {
+            assert_eq!(
+                with("escape_radius", radius),
+                Err(SettingsError::InvalidField("escape_radius"))
+            );
+        }
+    }
+
+    #[test]
+    fn missing_distance_estimation_is_off() {
+        let decoded = without("distance_estimation");
+        assert!(!decoded.iteration.distance_estimation);
+        assert_eq!(decoded.iteration.escape_radius, 256.0);
+    }
+
+    #[test]
+    fn missing_antialiasing_is_off() {
+        assert!(!without("antialiasing").antialiasing);
+    }
+
+    #[test]
+    fn missing_smooth_colors_are_bands() {
+        assert!(!without("smooth_colors").colors.smooth);
+    }
+
+    #[test]
+    fn missing_equalization_is_off() {
+        assert_eq!(
+            without("equalize_colors").colors,
+            ColorParams {
+                smooth: true,
+                equalize: false,
+                gamma: 1.8,
+            }
+        );
+    }
+
+    #[test]
+    fn missing_gamma_is_srgb() {
+        assert_eq!(without("gamma").colors.gamma, DEFAULT_GAMMA);
+        assert_eq!(
+            with("gamma", json!(0)),
+            Err(SettingsError::InvalidField("gamma"))
+        );
+    }
+
+    #[test]
+    fn missing_gradient_is_classic() {
+        assert_eq!(without("gradient").palette, Palette::default());
+        assert_eq!(
+            with("gradient", json!("0:102030 0.5:orange 1:ffffff")),
+            Err(SettingsError::InvalidField("gradient"))
+        );
+    }
+
+    #[test]
+    fn missing_target_fps_is_the_default() {
+        assert_eq!(without("target_fps").target_fps, defaults::TARGET_FPS);
+
+        for fps in [json!(0), json!(121), json!(null), json!("fast")] {
+            assert_eq!(
+                with("target_fps", fps),
+                Err(SettingsError::InvalidField("target_fps"))
+            );
+        }
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        assert_eq!(
+            with("version", json!(2)),
+            Err(SettingsError::Version("2".to_owned()))
+        );
+        assert_eq!(
+            with("version", json!(0)),
+            Err(SettingsError::InvalidField("version"))
+        );
+    }
+
+    #[test]
+    fn invalid_strings_are_rejected() {
+        assert_eq!(Settings::decode("hello"), Err(SettingsError::Encoding));
+        let uncompressed = URL_SAFE_NO_PAD.encode(Value::Object(fields()).to_string());
+        assert_eq!(
+            Settings::decode(&uncompressed),
+            Err(SettingsError::Encoding)
+        );
+        assert_eq!(
+            Settings::decode(&encode(&Map::new())),
+            Err(SettingsError::Encoding)
+        );
+        let only_version = json!({ "version": 1 }).as_object().unwrap().clone();
+        assert_eq!(
+            Settings::decode(&encode(&only_version)),
+            Err(SettingsError::MissingField("max_depth"))
+        );
+        assert_eq!(
+            with("inverted", json!("yes")),
+            Err(SettingsError::InvalidField("inverted"))
+        );
+        let mut invalid = settings();
+        invalid.scale_factor = 0.0;
+        assert_eq!(
+            Settings::decode(&invalid.encode()),
+            Err(SettingsError::InvalidField("scale_factor"))
+        );
+    }
+}
@@ -1,6 +1,25 @@
-#![cfg_attr(target_arch = "wasm32", allow(unused))]
-// See fps_balancer module
+/// Source of monotonic time in milliseconds
+pub trait Clock {
+    fn now(&self) -> f64;
+}
+
+/// Clock that measures time since its creation
+#[derive(Debug, Clone)]
+pub struct SystemClock(Timer);
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self(Timer::start())
+    }
+}
 
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        self.0.elapsed()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Timer {
     internal: TimerImpl,
 }
@@ -12,12 +31,15 @@ impl Timer {
         }
     }
 
-    pub fn stop(self) -> f64 {
-        self.internal.stop()
+    /// Returns milliseconds passed since the start. Never negative, even if the time source isn't
+    /// monotonic
+    pub fn elapsed(&self) -> f64 {
+        self.internal.elapsed().max(0.0)
     }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
 struct TimerImpl(std::time::Instant);
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -26,21 +48,39 @@ impl TimerImpl {
         TimerImpl(std::time::Instant::now())
     }
 
-    fn stop(self) -> f64 {
+    fn elapsed(&self) -> f64 {
         (std::time::Instant::now() - self.0).as_secs_f64() * 1000.0
     }
 }
 
 #[cfg(target_arch = "wasm32")]
-struct TimerImpl(f64);
+#[derive(Debug, Clone)]
+enum TimerImpl {
+    /// Monotonic high resolution time
+    Performance(web_sys::Performance, f64),
+    /// Wall-clock time in whole milliseconds, which can jump backwards
+    Date(f64),
+}
 
 #[cfg(target_arch = "wasm32")]
 impl TimerImpl {
     fn start() -> Self {
-        TimerImpl(js_sys::Date::now())
+        match web_sys::window().and_then(|window| window.performance()) {
+            Some(performance) => {
+                let start = performance.now();
+                TimerImpl::Performance(performance, start)
+            }
+            None => {
+                log::warn!("Performance API is unavailable, timing with Date");
+                TimerImpl::Date(js_sys::Date::now())
+            }
+        }
     }
 
-    fn stop(self) -> f64 {
-        js_sys::Date::now() - self.0
+    fn elapsed(&self) -> f64 {
+        match self {
+            TimerImpl::Performance(performance, start) => performance.now() - start,
+            TimerImpl::Date(start) => js_sys::Date::now() - start,
+        }
     }
 }
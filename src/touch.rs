@@ -0,0 +1,160 @@
+//! Touch gestures. One finger drags the view, two fingers drag it by their midpoint and zoom it by
+//! the change of their distance. Fingers can be added and lifted at any time without the view
+//! jumping, as every motion is measured between the same fingers
+
+use crate::primitives::Point;
+
+/// Change of the view by a finger move
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchMotion {
+    /// Screen delta of the fingers
+    pub dx: f32,
+    pub dy: f32,
+    /// Magnification and the window point it's anchored at, if two fingers touch
+    pub zoom: Option<(f32, Point)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Finger {
+    id: u64,
+    position: Point,
+    /// Decided at the first move, fingers that start on the control panel are left to it
+    navigating: Option<bool>,
+}
+
+/// Fingers touching the window, in the order they touched it
+#[derive(Debug, Default)]
+pub struct Touches {
+    fingers: Vec<Finger>,
+}
+
+impl Touches {
+    pub fn press(&mut self, id: u64, position: Point) {
+        self.release(id);
+        self.fingers.push(Finger {
+            id,
+            position,
+            navigating: None,
+        });
+    }
+
+    /// Handles a finger move to `position`. `captured` tells if the control panel has the pointer,
+    /// which decides if the finger navigates at its first move. Returns the motion of the view
+    pub fn moved(&mut self, id: u64, position: Point, captured: bool) -> Option<TouchMotion> {
+        let index = self.fingers.iter().position(|finger| finger.id == id)?;
+        let navigating = *self.fingers[index].navigating.get_or_insert(!captured);
+        if !navigating {
+            self.fingers[index].position = position;
+            return None;
+        }
+
+        let before = self.gesture();
+        self.fingers[index].position = position;
+        let after = self.gesture();
+        let ((before_center, before_spread), (after_center, after_spread)) = (before?, after?);
+
+        let zoom = match (before_spread, after_spread) {
+            (Some(before), Some(after)) if before > 0.0 && after > 0.0 => {
+                Some((after / before, after_center))
+            }
+            _ => None,
+        };
+        Some(TouchMotion {
+            dx: after_center.x - before_center.x,
+            dy: after_center.y - before_center.y,
+            zoom,
+        })
+    }
+
+    /// Handles a finger lifted off or cancelled. Returns true if no navigating fingers are left
+    /// and the gesture is over
+    pub fn release(&mut self, id: u64) -> bool {
+        let len = self.fingers.len();
+        self.fingers.retain(|finger| finger.id != id);
+        self.fingers.len() != len && !self.is_navigating()
+    }
+
+    pub fn is_navigating(&self) -> bool {
+        self.fingers
+            .iter()
+            .any(|finger| finger.navigating == Some(true))
+    }
+
+    /// Returns the center of the first two navigating fingers and their distance, if there are
+    /// two of them
+    fn gesture(&self) -> Option<(Point, Option<f32>)> {
+        let mut fingers = self
+            .fingers
+            .iter()
+            .filter(|finger| finger.navigating == Some(true))
+            .map(|finger| finger.position);
+        let first = fingers.next()?;
+        Some(match fingers.next() {
+            Some(second) => (
+                Point {
+                    x: (first.x + second.x) / 2.0,
+                    y: (first.y + second.y) / 2.0,
+                },
+                Some((second.x - first.x).hypot(second.y - first.y)),
+            ),
+            None => (first, None),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn one_finger_drags() {
+        let mut touches = Touches::default();
+        touches.press(1, point(10.0, 10.0));
+        let motion = touches.moved(1, point(15.0, 7.0), false).unwrap();
+        assert_eq!((motion.dx, motion.dy, motion.zoom), (5.0, -3.0, None));
+        assert!(touches.release(1));
+        assert!(!touches.is_navigating());
+    }
+
+    #[test]
+    fn two_fingers_pinch_around_their_midpoint() {
+        let mut touches = Touches::default();
+        touches.press(1, point(100.0, 100.0));
+        touches.press(2, point(200.0, 100.0));
+        let still = touches.moved(1, point(100.0, 100.0), false).unwrap();
+        assert_eq!((still.dx, still.dy, still.zoom), (0.0, 0.0, None));
+        // Only the first finger navigates so far, the second one joins without a jump
+        let motion = touches.moved(2, point(300.0, 100.0), false).unwrap();
+        assert_eq!((motion.dx, motion.dy), (50.0, 0.0));
+        assert_eq!(motion.zoom, Some((2.0, point(200.0, 100.0))));
+
+        // A third finger doesn't count until one of the two is lifted
+        touches.press(3, point(0.0, 0.0));
+        let motion = touches.moved(3, point(50.0, 50.0), false).unwrap();
+        assert_eq!(motion.zoom, Some((1.0, point(200.0, 100.0))));
+        assert!(!touches.release(1));
+        let motion = touches.moved(2, point(310.0, 100.0), false).unwrap();
+        assert_eq!((motion.dx, motion.dy), (5.0, 0.0));
+        assert_eq!(motion.zoom.unwrap().1, point(180.0, 75.0));
+    }
+
+    #[test]
+    fn fingers_on_the_control_panel_are_ignored() {
+        let mut touches = Touches::default();
+        touches.press(1, point(10.0, 10.0));
+        assert_eq!(touches.moved(1, point(20.0, 10.0), true), None);
+        // The decision of the first move sticks
+        assert_eq!(touches.moved(1, point(30.0, 10.0), false), None);
+        assert!(!touches.is_navigating());
+
+        // Cancelled touches are released like lifted ones
+        touches.press(2, point(10.0, 10.0));
+        touches.moved(2, point(20.0, 10.0), false).unwrap();
+        assert!(touches.release(2));
+        assert!(!touches.release(2));
+    }
+}
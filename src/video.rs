@@ -0,0 +1,215 @@
+//! Frames of recordings streamed to an ffmpeg child process, which encodes them into a video as
+//! they come instead of saving every one of them as an image
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use thiserror::Error;
+
+use crate::gpu::RgbaImage;
+use crate::primitives::ScaledDimensions;
+
+/// Program videos are encoded with, looked up in the path
+pub const FFMPEG: &str = "ffmpeg";
+
+/// Most frames per second a video can have
+pub const MAX_FPS: u32 = 240;
+
+#[derive(Debug, Error)]
+pub enum VideoError {
+    #[error("ffmpeg wasn't found, install it or record PNG images instead")]
+    NotFound,
+    #[error("ffmpeg I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ffmpeg has failed with {0}")]
+    Failed(ExitStatus),
+    #[error("Frames of a video can't change their size, the window was resized while recording")]
+    Resized,
+}
+
+/// Container of the video, ffmpeg picks its codecs by the extension
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VideoFormat {
+    #[default]
+    Mp4,
+    WebM,
+}
+
+impl VideoFormat {
+    pub const ALL: [VideoFormat; 2] = [VideoFormat::Mp4, VideoFormat::WebM];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            VideoFormat::Mp4 => "mp4",
+            VideoFormat::WebM => "webm",
+        }
+    }
+}
+
+impl fmt::Display for VideoFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            VideoFormat::Mp4 => "MP4",
+            VideoFormat::WebM => "WebM",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoOptions {
+    pub fps: u32,
+    pub format: VideoFormat,
+}
+
+/// ffmpeg encoding frames of `size` into a video
+#[derive(Debug)]
+pub struct Encoder {
+    child: Option<Child>,
+    /// Closed to tell ffmpeg that the video is over
+    stdin: Option<ChildStdin>,
+    size: ScaledDimensions,
+    path: PathBuf,
+}
+
+impl Encoder {
+    /// Starts `program` to encode frames of `size` into a video at `path`
+    pub fn spawn(
+        program: &OsStr,
+        size: ScaledDimensions,
+        options: VideoOptions,
+        path: &Path,
+    ) -> Result<Self, VideoError> {
+        let mut child = Command::new(program)
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgba",
+            ])
+            .args(["-s", &format!("{}x{}", size.width, size.height)])
+            .args(["-r", &options.fps.to_string(), "-i", "-"])
+            // Players expect 4:2:0 chroma, which needs an even size
+            .args([
+                "-vf",
+                "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+                "-pix_fmt",
+                "yuv420p",
+            ])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => VideoError::NotFound,
+                _ => VideoError::Io(e),
+            })?;
+        Ok(Self {
+            stdin: child.stdin.take(),
+            child: Some(child),
+            size,
+            path: path.to_owned(),
+        })
+    }
+
+    /// Sends the next frame, blocking while ffmpeg is busy with the previous ones
+    pub fn write(&mut self, image: &RgbaImage) -> Result<(), VideoError> {
+        if (image.width, image.height) != (self.size.width, self.size.height) {
+            return Err(VideoError::Resized);
+        }
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        stdin.write_all(&image.pixels)?;
+        Ok(())
+    }
+
+    /// Ends the video and waits for ffmpeg to save it. Returns where it was saved
+    pub fn finish(&mut self) -> Result<String, VideoError> {
+        drop(self.stdin.take());
+        if let Some(mut child) = self.child.take() {
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(VideoError::Failed(status));
+            }
+        }
+        Ok(self.path.display().to_string())
+    }
+}
+
+/// Videos of cancelled recordings end with the frames sent so far, ffmpeg is waited for in the
+/// background
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        drop(self.stdin.take());
+        if let Some(mut child) = self.child.take() {
+            std::thread::spawn(move || child.wait());
+        }
+    }
+}
+
+/// Returns where a video of `format` named `stem` is saved, in the videos directory or the current
+/// one without it
+pub fn path(stem: &str, format: VideoFormat) -> PathBuf {
+    dirs::video_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(format!("{}.{}", stem, format.extension()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn frames_are_streamed_to_ffmpeg() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("mandelbrot-ffmpeg-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Counts the bytes it's sent into the video file
+        let shim = dir.join("ffmpeg");
+        std::fs::write(
+            &shim,
+            "#!/bin/sh\nfor last; do :; done\nwc -c > \"$last\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&shim, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let size = ScaledDimensions {
+            width: 6,
+            height: 4,
+        };
+        let options = VideoOptions {
+            fps: 30,
+            format: VideoFormat::Mp4,
+        };
+        let video = dir.join("zoom.mp4");
+        let mut encoder = Encoder::spawn(shim.as_os_str(), size, options, &video).unwrap();
+        let image = RgbaImage {
+            width: 6,
+            height: 4,
+            pixels: vec![7; 6 * 4 * 4],
+        };
+        for _ in 0..3 {
+            encoder.write(&image).unwrap();
+        }
+        let resized = RgbaImage { width: 4, ..image };
+        assert!(matches!(encoder.write(&resized), Err(VideoError::Resized)));
+        assert_eq!(encoder.finish().unwrap(), video.display().to_string());
+        let written = std::fs::read_to_string(&video).unwrap();
+        assert_eq!(written.trim(), (3 * 6 * 4 * 4).to_string());
+
+        let missing = dir.join("missing");
+        assert!(matches!(
+            Encoder::spawn(missing.as_os_str(), size, options, &video),
+            Err(VideoError::NotFound)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
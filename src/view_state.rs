@@ -1,46 +1,296 @@
-use crate::primitives::{Coordinates, Dimensions, Point};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::bookmarks::Bookmark;
+use crate::float::WideFloat;
+use crate::fractal::{FractalParams, Framing, JuliaSeed};
+use crate::history::{self, Gesture, History};
+use crate::location::Location;
+use crate::primitives::{Coordinates, Dimensions, Point, MAX_WORD_COUNT};
 
 #[derive(Debug, Clone)]
 pub struct ViewState {
     dimensions: Dimensions,
     scale_factor: f64,
     coords: Coordinates,
+    fractal: FractalParams,
+    precision: Precision,
     reset: bool,
+    /// Views before the steps of navigation
+    history: History<Snapshot>,
+    /// Zoom in progress, if zooming is animated
+    animation: Option<ZoomAnimation>,
+    /// An ignored input has been logged since the last valid one
+    warned: bool,
+}
+
+/// Duration of an animated zoom in milliseconds
+const ZOOM_ANIMATION_MS: f64 = 200.0;
+
+/// Zoom from one step to the other, eased out and exponential in the step, so that its speed
+/// doesn't depend on the depth
+#[derive(Debug, Clone)]
+struct ZoomAnimation {
+    start: Coordinates,
+    target: Coordinates,
+    /// Ratio of the target step to the start one
+    mul: f64,
+    anchor: Point,
+    start_ms: f64,
+}
+
+impl ZoomAnimation {
+    /// Returns the eased progress at `now_ms`, 1 once the animation is over
+    fn progress(&self, now_ms: f64) -> f64 {
+        let t = ((now_ms - self.start_ms) / ZOOM_ANIMATION_MS).clamp(0.0, 1.0);
+        1.0 - (1.0 - t).powi(3)
+    }
+}
+
+/// Part of the view state restored by undo and redo
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    coords: Coordinates,
+    precision: Precision,
+    fractal: FractalParams,
+}
+
+/// Significant bits kept in the step, which decide how far numbers grow while zooming
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Precision {
+    /// Enough bits to tell apart every pixel of the view
+    #[default]
+    Auto,
+    Manual(usize),
+}
+
+impl Precision {
+    /// Returns the bits in effect for a view of `dimensions` at `scale_factor`
+    pub fn bits(&self, dimensions: Dimensions, scale_factor: f64) -> usize {
+        match self {
+            Precision::Auto => {
+                let longest_side = dimensions.width.max(dimensions.height) as f64 / scale_factor;
+                (longest_side.max(1.0).log2().ceil() as usize) + AUTO_PRECISION_MARGIN
+            }
+            Precision::Manual(bits) => *bits,
+        }
+    }
 }
 
-fn default_coordinates(dimensions: Dimensions, scale_factor: f64, precision: usize) -> Coordinates {
-    let step = 4.0 * scale_factor as f32 / dimensions.shortest_side() as f32;
-    let x = -(dimensions.width as f32 / scale_factor as f32 / 2.0) * step;
-    let y = -(dimensions.height as f32 / scale_factor as f32 / 2.0) * step;
-    Coordinates::new(x, y, step, precision)
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Precision::Auto => write!(f, "auto"),
+            Precision::Manual(bits) => write!(f, "{}", bits),
+        }
+    }
+}
+
+impl FromStr for Precision {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Precision::Auto),
+            bits => bits.parse().map(Precision::Manual),
+        }
+    }
+}
+
+/// Returns the step multiplier of a zoom by `delta`, in for positive and out for negative
+fn zoom_multiplier(delta: f32) -> f32 {
+    if delta > 0.0 {
+        1.0 / (1.0 + delta)
+    } else {
+        1.0 - delta
+    }
+}
+
+/// Longest side of a selection in physical pixels that still counts as a click
+const MIN_SELECTION: f32 = 4.0;
+
+/// Bits of the automatic precision beyond the ones needed to address a pixel, so that rounding
+/// errors of pixel coordinates stay well below a pixel
+const AUTO_PRECISION_MARGIN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Location is too deep to be shown with {0} words of precision")]
+pub struct LocationTooDeep(pub usize);
+
+fn default_coordinates(
+    dimensions: Dimensions,
+    scale_factor: f64,
+    precision: usize,
+    framing: Framing,
+) -> Coordinates {
+    let step = framing.span * scale_factor as f32 / dimensions.shortest_side() as f32;
+    let x = framing.x - (dimensions.width as f32 / scale_factor as f32 / 2.0) * step;
+    let y = framing.y - (dimensions.height as f32 / scale_factor as f32 / 2.0) * step;
+    Coordinates::new(x.into(), y.into(), step.into(), precision)
+        .expect("Default framing must be representable")
 }
 
 impl ViewState {
-    pub fn default(dimensions: Dimensions, scale_factor: f64, precision: usize) -> Self {
+    pub fn default(dimensions: Dimensions, scale_factor: f64, precision: Precision) -> Self {
+        let fractal = FractalParams::default();
         Self {
             dimensions,
             scale_factor,
-            coords: default_coordinates(dimensions, scale_factor, precision),
+            coords: default_coordinates(
+                dimensions,
+                scale_factor,
+                precision.bits(dimensions, scale_factor),
+                fractal.default_framing(),
+            ),
+            fractal,
+            precision,
             reset: true,
+            history: History::new(history::DEFAULT_DEPTH),
+            animation: None,
+            warned: false,
         }
     }
 
+    /// Creates a view at exact coordinates of a previous session
+    pub fn restore(
+        dimensions: Dimensions,
+        scale_factor: f64,
+        coords: Coordinates,
+        fractal: FractalParams,
+        precision: Precision,
+    ) -> Self {
+        let mut view = Self {
+            dimensions,
+            scale_factor,
+            coords,
+            fractal,
+            precision,
+            reset: false,
+            history: History::new(history::DEFAULT_DEPTH),
+            animation: None,
+            warned: false,
+        };
+        view.update_precision();
+        view
+    }
+
     pub fn reset(&mut self) {
+        self.history.jump(&self.snapshot());
+        self.animation = None;
         self.reset = true;
-        self.coords = default_coordinates(self.dimensions, self.scale_factor, self.precision());
+        self.coords = self.default_coordinates();
+    }
+
+    /// Goes back to the view before the last navigation step. Returns false if there's none
+    pub fn undo(&mut self) -> bool {
+        let previous = self.history.undo(&self.snapshot());
+        self.restore_snapshot(previous)
+    }
+
+    /// Goes forward to the view the last undo left. Returns false if there's none
+    pub fn redo(&mut self) -> bool {
+        let next = self.history.redo(&self.snapshot());
+        self.restore_snapshot(next)
+    }
+
+    /// Sets the maximum number of steps that can be undone
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.set_depth(depth);
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            coords: self.coords.clone(),
+            precision: self.precision,
+            fractal: self.fractal.clone(),
+        }
+    }
+
+    fn restore_snapshot(&mut self, snapshot: Option<Snapshot>) -> bool {
+        let Some(snapshot) = snapshot else {
+            return false;
+        };
+        self.reset = false;
+        self.animation = None;
+        self.coords = snapshot.coords;
+        self.precision = snapshot.precision;
+        self.fractal = snapshot.fractal;
+        self.update_precision();
+        true
+    }
+
+    /// Ends the drag in progress, making it a single step of the history
+    pub fn end_gesture(&mut self) {
+        self.history.finish(&self.snapshot());
+    }
+
+    fn default_coordinates(&self) -> Coordinates {
+        default_coordinates(
+            self.dimensions,
+            self.scale_factor,
+            self.precision_bits(),
+            self.fractal.default_framing(),
+        )
+    }
+
+    pub fn fractal(&self) -> FractalParams {
+        self.fractal.clone()
+    }
+
+    /// Switches to a different fractal and frames it
+    pub fn set_fractal(&mut self, fractal: FractalParams) {
+        self.history.jump(&self.snapshot());
+        self.animation = None;
+        self.fractal = fractal;
+        self.reset = true;
+        self.coords = self.default_coordinates();
+    }
+
+    /// Switches between the Mandelbrot set and the Julia set of `seed`, keeping the view in place
+    pub fn set_julia(&mut self, seed: Option<JuliaSeed>) {
+        self.history.jump(&self.snapshot());
+        self.animation = None;
+        self.reset = false;
+        self.fractal.julia = seed;
+    }
+
+    /// Returns the Julia seed of the plane point under the view `point`, or at the view center
+    pub fn julia_seed(&self, point: Option<Point>) -> JuliaSeed {
+        let (re, im) = self.exact_plane_point(self.anchor_or_center(point));
+        JuliaSeed { re, im }
     }
 
     pub fn dimensions(&self) -> Dimensions {
         self.dimensions
     }
 
+    /// Resizes the view, keeping the plane point at its center in place
     pub fn set_dimensions(&mut self, dimensions: Dimensions) {
+        self.animation = None;
         if self.reset {
             self.dimensions = dimensions;
-            self.coords = default_coordinates(dimensions, self.scale_factor, self.precision())
-        } else {
-            self.dimensions = dimensions;
+            self.coords = self.default_coordinates();
+            return;
         }
+
+        let (re, im) = self.center();
+        self.dimensions = dimensions;
+        self.update_precision();
+        self.center_on(re, im);
+    }
+
+    /// Moves the view so that the plane point `re`, `im` is at its center
+    fn center_on(&mut self, mut re: WideFloat, mut im: WideFloat) {
+        let word_diff = self.coords.size() as isize - re.word_count() as isize;
+        re.change_precision(word_diff);
+        im.change_precision(word_diff);
+
+        let (half_width, half_height) = self.half_size(self.coords.size());
+        let (dx, dy) = self.coords.plane_offset(&half_width, &half_height);
+        self.coords.x = re - &dx;
+        self.coords.y = im - &dy;
     }
 
     pub fn scale_factor(&self) -> f64 {
@@ -48,14 +298,23 @@ impl ViewState {
     }
 
     pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.animation = None;
         if self.reset {
             self.scale_factor = scale_factor;
-            self.coords = default_coordinates(self.dimensions, scale_factor, self.precision());
+            self.coords = self.default_coordinates();
         } else {
             let mul = scale_factor / self.scale_factor;
-            self.coords.step = &self.coords.step
-                * &crate::float::WideFloat::from_f32(mul as f32, self.coords.size()).unwrap();
-            self.scale_factor = scale_factor;
+            match crate::float::WideFloat::from_f32(mul as f32, self.coords.size()) {
+                Ok(mul) => {
+                    self.warned = false;
+                    self.coords.step = &self.coords.step * &mul;
+                    self.scale_factor = scale_factor;
+                    self.update_precision();
+                }
+                Err(e) => {
+                    self.ignore_input(format_args!("Ignoring scale factor {scale_factor}: {e:?}"))
+                }
+            }
         }
     }
 
@@ -63,51 +322,964 @@ impl ViewState {
         &self.coords
     }
 
-    pub fn precision(&self) -> usize {
-        self.coords.precision()
+    pub fn precision(&self) -> Precision {
+        self.precision
     }
 
-    pub fn set_precision(&mut self, precision: usize) {
-        self.coords.set_precision(precision)
+    /// Returns the extra bits of the step currently in effect
+    pub fn precision_bits(&self) -> usize {
+        self.precision.bits(self.dimensions, self.scale_factor)
     }
 
-    pub fn zoom_with_anchor(&mut self, delta: f32, anchor: Option<Point>) {
-        self.reset = false;
-        let anchor = anchor.unwrap_or(Point {
-            x: (self.dimensions.width / 2) as f32,
-            y: (self.dimensions.height / 2) as f32,
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.animation = None;
+        self.precision = precision;
+        self.update_precision();
+    }
+
+    /// Applies the precision after the view size might have changed
+    fn update_precision(&mut self) {
+        let bits = self.precision_bits();
+        if bits != self.coords.precision() {
+            self.coords.set_precision(bits);
+        }
+    }
+
+    /// Zooms in for positive `delta` and out for negative, keeping `anchor` in place. Returns true
+    /// if the view can't zoom in any further
+    pub fn zoom_with_anchor(&mut self, delta: f32, anchor: Option<Point>) -> bool {
+        if !delta.is_finite() {
+            self.ignore_input(format_args!("Ignoring zoom by {delta}"));
+            return false;
+        }
+        self.animation = None;
+        let anchor = self.anchor_or_center(anchor);
+        self.history.navigate(Gesture::Zoom, &self.snapshot());
+        self.zoom_by(zoom_multiplier(delta), anchor)
+    }
+
+    /// Zooms like [`Self::zoom_with_anchor`], but gets to the target step in a short animation
+    /// started at `now_ms`. Zooming again while it plays continues toward the combined target
+    pub fn animate_zoom(&mut self, delta: f32, anchor: Option<Point>, now_ms: f64) -> bool {
+        if !delta.is_finite() {
+            self.ignore_input(format_args!("Ignoring zoom by {delta}"));
+            return false;
+        }
+        let remaining = match &self.animation {
+            Some(animation) => animation.mul.powf(1.0 - animation.progress(now_ms)),
+            None => 1.0,
+        };
+        let anchor = self.anchor_or_center(anchor);
+        self.history.navigate(Gesture::Zoom, &self.snapshot());
+        let start = self.coords.clone();
+        let limited = self.zoom_by((remaining * zoom_multiplier(delta) as f64) as f32, anchor);
+        let target = std::mem::replace(&mut self.coords, start.clone());
+
+        let mul = match (target.step.magnitude_log10(), start.step.magnitude_log10()) {
+            (Some(to), Some(from)) => 10f64.powf(to - from),
+            _ => 1.0,
+        };
+        self.animation = Some(ZoomAnimation {
+            start,
+            target,
+            mul,
+            anchor,
+            start_ms: now_ms,
         });
+        limited
+    }
 
-        let mul = if delta > 0.0 {
-            1.0 / (1.0 + delta)
-        } else {
-            1.0 - delta
+    /// Moves an animated zoom forward to `now_ms`. Returns true while the animation is playing and
+    /// the view needs to be drawn again
+    pub fn tick(&mut self, now_ms: f64) -> bool {
+        let Some(animation) = &self.animation else {
+            return false;
         };
+        let progress = animation.progress(now_ms);
+        if progress < 1.0 {
+            let mut coords = animation.start.clone();
+            let zoomed = coords.zoom_with_anchor(
+                animation.mul.powf(progress) as f32,
+                animation.anchor.x / self.scale_factor as f32,
+                animation.anchor.y / self.scale_factor as f32,
+                self.max_step(),
+            );
+            if zoomed.is_ok() {
+                self.coords = coords;
+                return true;
+            }
+        }
+        // The target was reached by the instant zoom already, which clamped and validated it
+        self.coords = animation.target.clone();
+        self.animation = None;
+        false
+    }
+
+    /// Returns true if an animated zoom is playing
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    fn anchor_or_center(&self, anchor: Option<Point>) -> Point {
+        anchor.unwrap_or(Point {
+            x: (self.dimensions.width / 2) as f32,
+            y: (self.dimensions.height / 2) as f32,
+        })
+    }
+
+    /// Step of the furthest zoom out, in plane units per logical pixel
+    fn max_step(&self) -> f32 {
+        2.0 * self.fractal.default_framing().span / self.dimensions.shortest_side() as f32
+            * self.scale_factor as f32
+    }
 
-        self.coords.zoom_with_anchor(
+    /// Multiplies the step by `mul`, keeping `anchor` in place
+    fn zoom_by(&mut self, mul: f32, anchor: Point) -> bool {
+        self.reset = false;
+
+        let previous = self
+            .fractal
+            .max_word_count()
+            .map(|max| (max, self.coords.clone()));
+
+        let mut limited = match self.coords.zoom_with_anchor(
             mul,
-            (anchor.x / self.scale_factor as f32).round() as i32,
-            (anchor.y / self.scale_factor as f32).round() as i32,
-            2.0 * 4.0 / self.dimensions.shortest_side() as f32 * self.scale_factor as f32,
-        );
+            anchor.x / self.scale_factor as f32,
+            anchor.y / self.scale_factor as f32,
+            self.max_step(),
+        ) {
+            Ok(limited) => limited,
+            Err(e) => {
+                self.ignore_input(format_args!("Ignoring zoom: {e}"));
+                return false;
+            }
+        };
+        self.warned = false;
+
+        if let Some((max_word_count, previous)) = previous {
+            if self.coords.size() > max_word_count {
+                self.coords = previous;
+                limited = true;
+            }
+        }
 
         log::info!(
             "x: {}, y: {}, scale: {}",
-            self.coords.x.as_f32_round(),
-            self.coords.y.as_f32_round(),
-            self.coords.step.as_f32_round(),
+            self.coords.x.as_f64_round(),
+            self.coords.y.as_f64_round(),
+            self.coords.step.as_f64_round(),
+        );
+        log::debug!(
+            "x: {:x}, y: {:x}, step: {:x}",
+            self.coords.x,
+            self.coords.y,
+            self.coords.step,
+        );
+        limited
+    }
+
+    /// Returns the decimal logarithm of the magnification relative to the default framing, none if
+    /// the step is zero
+    pub fn zoom_log10(&self) -> Option<f64> {
+        let default_step = self.fractal.default_framing().span as f64 * self.scale_factor
+            / self.dimensions.shortest_side() as f64;
+        let step = self.coords.step.magnitude_log10()?;
+        Some(default_step.log10() - step)
+    }
+
+    /// Returns the location at the center of the view
+    pub fn location(&self) -> Location {
+        let shortest_side = WideFloat::from_f32(
+            self.dimensions.shortest_side() as f32 / self.scale_factor as f32,
+            self.coords.size(),
+        )
+        .expect("Window size must be representable");
+        let (re, im) = self.center();
+        Location {
+            re,
+            im,
+            span: Some(&self.coords.step * &shortest_side),
+        }
+    }
+
+    /// Returns the plane point at the center of the view
+    fn center(&self) -> (WideFloat, WideFloat) {
+        let (half_width, half_height) = self.half_size(self.coords.size());
+        let (dx, dy) = self.coords.plane_offset(&half_width, &half_height);
+        (self.coords.x.clone() + &dx, self.coords.y.clone() + &dy)
+    }
+
+    /// Centers the view on `location`, keeping the current zoom unless the location has a span.
+    /// Numbers of the location must be of equal size. The view is left unchanged on error
+    pub fn jump_to(&mut self, location: &Location) -> Result<(), LocationTooDeep> {
+        let size = location.re.word_count();
+        let step = match &location.span {
+            Some(span) => {
+                let pixel = WideFloat::from_f32(
+                    self.scale_factor as f32 / self.dimensions.shortest_side() as f32,
+                    size,
+                )
+                .expect("Window size must be representable");
+                let max_step = WideFloat::from_f32(
+                    2.0 * self.fractal.default_framing().span * self.scale_factor as f32
+                        / self.dimensions.shortest_side() as f32,
+                    size,
+                )
+                .expect("Default framing must be representable");
+                (span * &pixel).min(max_step)
+            }
+            None => {
+                let mut step = self.coords.step.clone();
+                step.change_precision(size as isize - step.word_count() as isize);
+                step
+            }
+        };
+
+        let mut coords = Coordinates::from_parts(
+            location.re.clone(),
+            location.im.clone(),
+            step,
+            self.precision_bits(),
+        )
+        .expect("Locations are parsed with a supported word count");
+        coords
+            .set_rotation(self.coords.rotation())
+            .expect("Rotation is valid");
+        let (half_width, half_height) = self.half_size(size);
+        let (dx, dy) = coords.plane_offset(&half_width, &half_height);
+        coords.x -= &dx;
+        coords.y -= &dy;
+        coords.set_precision(self.precision_bits());
+
+        let max_word_count = self.fractal.max_word_count().unwrap_or(MAX_WORD_COUNT);
+        if coords.size() > max_word_count || coords.step == 0 {
+            return Err(LocationTooDeep(max_word_count));
+        }
+
+        self.history.jump(&self.snapshot());
+        self.animation = None;
+        self.reset = false;
+        self.coords = coords;
+        Ok(())
+    }
+
+    /// Returns the selection from `p1` to `p2` in view pixels, grown around its center to the
+    /// aspect ratio of the view. Returns none for selections small enough to be a click
+    pub fn selection(&self, p1: Point, p2: Point) -> Option<(Point, Point)> {
+        let (width, height) = ((p2.x - p1.x).abs(), (p2.y - p1.y).abs());
+        if !(width.is_finite() && height.is_finite()) || width.max(height) <= MIN_SELECTION {
+            return None;
+        }
+        let (view_width, view_height) =
+            (self.dimensions.width as f32, self.dimensions.height as f32);
+        let scale = (width / view_width).max(height / view_height);
+        let (half_width, half_height) = (scale * view_width / 2.0, scale * view_height / 2.0);
+        let (center_x, center_y) = ((p1.x + p2.x) / 2.0, (p1.y + p2.y) / 2.0);
+        Some((
+            Point {
+                x: center_x - half_width,
+                y: center_y - half_height,
+            },
+            Point {
+                x: center_x + half_width,
+                y: center_y + half_height,
+            },
+        ))
+    }
+
+    /// Zooms so that the [selection](Self::selection) from `p1` to `p2` fills the view. Returns
+    /// false if the selection is a click and the view is left unchanged, as it is on error
+    pub fn fit_rect(&mut self, p1: Point, p2: Point) -> Result<bool, LocationTooDeep> {
+        let Some((min, max)) = self.selection(p1, p2) else {
+            return Ok(false);
+        };
+        // A spare word keeps the bits of the smaller step, the jump trims it to the precision
+        let size = (self.coords.size() + 1).min(MAX_WORD_COUNT);
+        let mut coords = self.coords.clone();
+        coords.set_word_count(size);
+
+        let wide = |value: f32| {
+            WideFloat::from_f32(value / self.scale_factor as f32, size)
+                .expect("Window size must be representable")
+        };
+        let (dx, dy) =
+            coords.plane_offset(&wide((min.x + max.x) / 2.0), &wide((min.y + max.y) / 2.0));
+        let scale = (max.x - min.x) / self.dimensions.width as f32;
+        let span = &coords.step * &wide(scale * self.dimensions.shortest_side() as f32);
+
+        self.jump_to(&Location {
+            re: coords.x + &dx,
+            im: coords.y + &dy,
+            span: Some(span),
+        })?;
+        Ok(true)
+    }
+
+    /// Saves the view as a bookmark
+    pub fn bookmark(&self, name: String, max_depth: u32) -> Bookmark {
+        Bookmark {
+            name,
+            coords: self.coords.clone(),
+            dimensions: self.dimensions,
+            scale_factor: self.scale_factor,
+            fractal: self.fractal.clone(),
+            precision: self.precision,
+            max_depth,
+        }
+    }
+
+    /// Shows the view saved in `bookmark` with the same center and plane extent across the
+    /// shortest side, at the size and scale factor of this view. The view is left unchanged on
+    /// error
+    pub fn jump_to_bookmark(&mut self, bookmark: &Bookmark) -> Result<(), LocationTooDeep> {
+        let saved = Self::restore(
+            bookmark.dimensions,
+            bookmark.scale_factor,
+            bookmark.coords.clone(),
+            bookmark.fractal.clone(),
+            bookmark.precision,
         );
+        let mut shown = Self::restore(
+            self.dimensions,
+            self.scale_factor,
+            saved.coords.clone(),
+            bookmark.fractal.clone(),
+            bookmark.precision,
+        );
+        shown.jump_to(&saved.location())?;
+
+        self.history.jump(&self.snapshot());
+        self.animation = None;
+        self.reset = false;
+        self.coords = shown.coords;
+        self.fractal = shown.fractal;
+        self.precision = shown.precision;
+        Ok(())
+    }
+
+    /// Shows the exact `coords` of a view of the same size, like the ones an iterations file was
+    /// calculated at
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub fn show_coordinates(&mut self, coords: Coordinates) {
+        self.history.jump(&self.snapshot());
+        self.animation = None;
+        self.reset = false;
+        self.coords = coords;
+        self.update_precision();
+    }
+
+    /// Returns the approximate plane point under the view `point`
+    pub fn plane_point(&self, point: Point) -> [f32; 2] {
+        let (re, im) = self.exact_plane_point(point);
+        [re.as_f32_approx(), im.as_f32_approx()]
+    }
+
+    fn exact_plane_point(&self, point: Point) -> (WideFloat, WideFloat) {
+        let size = self.coords.size();
+        let offset = |position: f32| {
+            WideFloat::from_f32(position / self.scale_factor as f32, size)
+                .expect("Window size must be representable")
+        };
+        let (dx, dy) = self.coords.plane_offset(&offset(point.x), &offset(point.y));
+        (self.coords.x.clone() + &dx, self.coords.y.clone() + &dy)
+    }
+
+    /// Returns half of the view size in steps
+    fn half_size(&self, size: usize) -> (WideFloat, WideFloat) {
+        let half = |side: u32| {
+            WideFloat::from_f32(side as f32 / self.scale_factor as f32 / 2.0, size)
+                .expect("Window size must be representable")
+        };
+        (half(self.dimensions.width), half(self.dimensions.height))
+    }
+
+    /// Rotates the view to `degrees` around its center
+    pub fn set_rotation(&mut self, degrees: f32) {
+        let (half_width, half_height) = self.half_size(self.coords.size());
+        let (before_x, before_y) = self.coords.plane_offset(&half_width, &half_height);
+        self.history.navigate(Gesture::Rotate, &self.snapshot());
+        self.animation = None;
+        if let Err(e) = self.coords.set_rotation(degrees) {
+            self.ignore_input(format_args!("Ignoring rotation: {e}"));
+            return;
+        }
+        self.warned = false;
+        self.reset = false;
+        let (after_x, after_y) = self.coords.plane_offset(&half_width, &half_height);
+        self.coords.x += &(before_x - &after_x);
+        self.coords.y += &(before_y - &after_y);
     }
 
     pub fn move_by_screen_delta(&mut self, dx: f32, dy: f32) {
+        self.history.navigate(Gesture::Drag, &self.snapshot());
+        self.animation = None;
+        self.move_by(dx, dy);
+    }
+
+    /// Moves the view by the screen delta `dx`, `dy` and zooms it in by `magnification` around
+    /// `anchor`, as two fingers do. Like a drag, it lasts until [`Self::end_gesture`]. Returns
+    /// true if the view can't zoom in any further
+    pub fn pinch(&mut self, dx: f32, dy: f32, magnification: f32, anchor: Point) -> bool {
+        if !(magnification.is_finite() && magnification > 0.0) {
+            self.ignore_input(format_args!("Ignoring pinch by {magnification}"));
+            return false;
+        }
+        self.history.navigate(Gesture::Drag, &self.snapshot());
+        self.animation = None;
+        self.move_by(dx, dy);
+        self.zoom_by(1.0 / magnification, anchor)
+    }
+
+    /// Centers the view on the view `point` and zooms in by `magnification` there, in a single
+    /// step of the history. Returns true if the view can't zoom in any further
+    pub fn center_and_zoom(&mut self, point: Point, magnification: f32) -> bool {
+        if !(magnification.is_finite() && magnification > 0.0) {
+            self.ignore_input(format_args!("Ignoring zoom by {magnification}"));
+            return false;
+        }
+        self.history.jump(&self.snapshot());
+        self.animation = None;
+        let limited = self.zoom_by(1.0 / magnification, point);
+        let center = self.anchor_or_center(None);
+        self.move_by(center.x - point.x, center.y - point.y);
+        limited
+    }
+
+    /// Logs the ignored input, unless another one has already been logged since the last valid
+    /// input. Streams of bogus events would flood the log otherwise
+    fn ignore_input(&mut self, message: fmt::Arguments) {
+        if !self.warned {
+            log::warn!("{}", message);
+            self.warned = true;
+        }
+    }
+
+    fn move_by(&mut self, dx: f32, dy: f32) {
         self.reset = false;
-        self.coords
-            .move_by_delta(dx / self.scale_factor as f32, dy / self.scale_factor as f32);
+        if let Err(e) = self
+            .coords
+            .move_by_delta(dx / self.scale_factor as f32, dy / self.scale_factor as f32)
+        {
+            self.ignore_input(format_args!("Ignoring move: {e}"));
+            return;
+        }
+        self.warned = false;
 
         log::info!(
             "x: {}, y: {}",
-            self.coords.x.as_f32_round(),
-            self.coords.y.as_f32_round(),
+            self.coords.x.as_f64_round(),
+            self.coords.y.as_f64_round(),
         );
+        log::debug!("x: {:x}, y: {:x}", self.coords.x, self.coords.y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view() -> ViewState {
+        let mut view = ViewState::default(Dimensions::new_nonzero(800, 500), 1.0, Precision::Auto);
+        view.move_by_screen_delta(13.0, -7.0);
+        view
+    }
+
+    #[test]
+    fn invalid_move_is_ignored() {
+        let mut view = view();
+        let before = view.coords().clone();
+        for delta in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, 1e30, -1e30] {
+            view.move_by_screen_delta(delta, 0.0);
+            view.move_by_screen_delta(0.0, delta);
+            assert_eq!(view.coords(), &before);
+        }
+    }
+
+    #[test]
+    fn ignored_input_is_logged_once() {
+        let mut view = view();
+        view.move_by_screen_delta(f32::NAN, 0.0);
+        assert!(view.warned);
+        view.zoom_with_anchor(f32::NAN, None);
+        view.pinch(0.0, 0.0, 0.0, Point { x: 0.0, y: 0.0 });
+        assert!(view.warned);
+
+        // The next valid input logs the following ignored one again
+        view.move_by_screen_delta(1.0, 0.0);
+        assert!(!view.warned);
+        view.zoom_with_anchor(f32::INFINITY, None);
+        assert!(view.warned);
+        view.zoom_with_anchor(1.0, None);
+        assert!(!view.warned);
+    }
+
+    #[test]
+    fn jump_to_centers_location() {
+        let mut view = view();
+        for _ in 0..100 {
+            view.zoom_with_anchor(1.0, None);
+        }
+        let deep = view.location();
+        assert!(view.coords().size() > 2);
+
+        let mut other = ViewState::default(Dimensions::new_nonzero(640, 480), 2.0, Precision::Auto);
+        other.jump_to(&deep).unwrap();
+        assert_eq!(other.coords().size(), view.coords().size());
+        let location = other.location();
+        // The values are too small for f32
+        let distance = |a: &WideFloat, b: &WideFloat| (a.clone() - b).max(b.clone() - a);
+        assert!(distance(&location.re, &deep.re) <= other.coords().step);
+        assert!(distance(&location.im, &deep.im) <= other.coords().step);
+        let (span, deep_span) = (location.span.unwrap(), deep.span.unwrap());
+        let tolerance = WideFloat::from_i32(1000, span.word_count());
+        assert!(&distance(&span, &deep_span) * &tolerance <= deep_span);
+
+        // Without a span the zoom is kept
+        let step = other.coords().step.clone();
+        let mut shallow = view.location();
+        shallow.span = None;
+        shallow.re = WideFloat::from_f32(0.25, deep.re.word_count()).unwrap();
+        shallow.im = WideFloat::zero(deep.re.word_count());
+        other.jump_to(&shallow).unwrap();
+        assert_eq!(other.coords().step, step);
+    }
+
+    #[test]
+    fn plane_point_under_center_matches_location() {
+        let view = view();
+        let location = view.location();
+        let [re, im] = view.plane_point(Point { x: 400.0, y: 250.0 });
+        assert!((re - location.re.as_f32_approx()).abs() < 1.0e-6);
+        assert!((im - location.im.as_f32_approx()).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn rotation_keeps_the_center() {
+        let mut view = view();
+        let location = view.location();
+        for degrees in [30.0, 90.0, 347.0] {
+            view.set_rotation(degrees);
+            let rotated = view.location();
+            assert!((rotated.re.as_f32_approx() - location.re.as_f32_approx()).abs() < 1.0e-5);
+            assert!((rotated.im.as_f32_approx() - location.im.as_f32_approx()).abs() < 1.0e-5);
+            let [re, im] = view.plane_point(Point { x: 400.0, y: 250.0 });
+            assert!((re - location.re.as_f32_approx()).abs() < 1.0e-5);
+            assert!((im - location.im.as_f32_approx()).abs() < 1.0e-5);
+        }
+
+        // Jumps keep the rotation, resets restore an axis-aligned view
+        view.jump_to(&location).unwrap();
+        assert_eq!(view.coords().rotation(), 347.0);
+        view.reset();
+        assert_eq!(view.coords().rotation(), 0.0);
+    }
+
+    #[test]
+    fn undo_restores_the_view_before_a_gesture() {
+        let mut view = ViewState::default(Dimensions::new_nonzero(800, 500), 1.0, Precision::Auto);
+        let start = view.coords().clone();
+        assert!(!view.undo());
+        for _ in 0..10 {
+            view.move_by_screen_delta(5.0, 3.0);
+        }
+        view.end_gesture();
+        let dragged = view.coords().clone();
+        view.reset();
+
+        assert!(view.undo());
+        assert_eq!(view.coords(), &dragged);
+        assert!(view.undo());
+        assert_eq!(view.coords(), &start);
+        assert!(!view.undo());
+        assert!(view.redo());
+        assert_eq!(view.coords(), &dragged);
+    }
+
+    #[test]
+    fn pinches_keep_the_plane_under_the_fingers() {
+        let mut view = ViewState::default(Dimensions::new_nonzero(800, 500), 1.0, Precision::Auto);
+        let start = view.coords().clone();
+        let mut fingers = Point { x: 200.0, y: 150.0 };
+        let [re, im] = view.plane_point(fingers);
+        for _ in 0..10 {
+            fingers.x += 10.0;
+            fingers.y -= 5.0;
+            view.pinch(10.0, -5.0, 1.2, fingers);
+            // A finger lifted off goes on as a drag
+            view.move_by_screen_delta(0.0, 0.0);
+        }
+        let [after_re, after_im] = view.plane_point(fingers);
+        let step = view.coords().step.as_f32_round();
+        assert!((after_re - re).abs() < step && (after_im - im).abs() < step);
+        assert!(view.coords().step < start.step);
+
+        assert!(!view.pinch(1.0, 1.0, f32::NAN, fingers));
+        view.end_gesture();
+        assert!(view.undo());
+        assert_eq!(view.coords(), &start);
+    }
+
+    #[test]
+    fn double_clicks_center_the_point() {
+        let mut view = ViewState::default(Dimensions::new_nonzero(800, 500), 1.0, Precision::Auto);
+        let start = view.coords().clone();
+        let point = Point { x: 600.0, y: 100.0 };
+        let [re, im] = view.plane_point(point);
+        view.center_and_zoom(point, 2.0);
+
+        let [center_re, center_im] = view.plane_point(Point { x: 400.0, y: 250.0 });
+        let step = view.coords().step.as_f32_round();
+        assert!((center_re - re).abs() < step && (center_im - im).abs() < step);
+        assert!((step * 2.0 - start.step.as_f32_round()).abs() < 1e-9);
+
+        view.center_and_zoom(Point { x: 400.0, y: 250.0 }, 0.5);
+        assert_eq!(view.coords().step, start.step);
+        assert!(view.undo() && view.undo());
+        assert_eq!(view.coords(), &start);
+    }
+
+    #[test]
+    fn julia_sets_keep_the_view() {
+        let mut view = view();
+        let start = view.coords().clone();
+        let seed = view.julia_seed(None);
+        let location = view.location();
+        assert_eq!((&seed.re, &seed.im), (&location.re, &location.im));
+
+        view.set_julia(Some(seed.clone()));
+        assert_eq!(view.fractal().julia, Some(seed));
+        assert_eq!(view.coords(), &start);
+        assert!(view.undo());
+        assert_eq!(view.fractal().julia, None);
+    }
+
+    /// Returns the number of bits from the leading bit of a positive number to its end, including
+    /// the leading one
+    fn significant_bits(value: &WideFloat) -> usize {
+        let words = value.words();
+        let top = words.iter().rposition(|w| *w != 0).unwrap();
+        (top + 1) * 32 - words[top].leading_zeros() as usize
+    }
+
+    #[test]
+    fn automatic_precision_keeps_pixels_distinct() {
+        let mut view = view();
+        let bits = view.precision_bits();
+        assert!(1 << (bits - AUTO_PRECISION_MARGIN) >= 800);
+
+        // Zoom from 1x to 10^40, about 2^133
+        let mul = 1.5f64;
+        for _ in 0..(133.0 / mul.log2()).ceil() as usize {
+            view.zoom_with_anchor((mul - 1.0) as f32, Some(Point { x: 123.0, y: 321.0 }));
+            assert!(significant_bits(&view.coords().step) >= bits);
+        }
+        assert!(view.coords().size() > 5);
+    }
+
+    #[test]
+    fn zoom_anchor_keeps_its_fraction() {
+        let mut view = ViewState::default(Dimensions::new_nonzero(1280, 720), 1.0, Precision::Auto);
+        let anchor = Point { x: 123.4, y: 567.8 };
+        let plane_point = |view: &ViewState| {
+            let coords = view.coords();
+            let wide = |value: f32| WideFloat::from_f32(value, coords.size()).unwrap();
+            let (dx, dy) = coords.plane_offset(&wide(anchor.x), &wide(anchor.y));
+            (coords.x.clone() + &dx, coords.y.clone() + &dy)
+        };
+        let (mut re, mut im) = plane_point(&view);
+        for _ in 0..100 {
+            view.zoom_with_anchor(0.05, Some(anchor));
+        }
+
+        let size = view.coords().size() as isize;
+        re.change_precision(size - re.word_count() as isize);
+        im.change_precision(size - im.word_count() as isize);
+        let (after_re, after_im) = plane_point(&view);
+        let distance = |a: &WideFloat, b: &WideFloat| (a.clone() - b).max(b.clone() - a);
+        assert!(distance(&after_re, &re) < view.coords().step);
+        assert!(distance(&after_im, &im) < view.coords().step);
+    }
+
+    #[test]
+    fn animated_zoom_reaches_the_instant_one() {
+        let anchor = Some(Point { x: 123.4, y: 456.7 });
+        let mut view = view();
+        for _ in 0..100 {
+            view.zoom_with_anchor(1.0, anchor);
+        }
+        assert!(view.coords().size() > 2);
+        for delta in [1.0, -1.0] {
+            let mut instant = view.clone();
+            instant.zoom_with_anchor(delta, anchor);
+
+            view.animate_zoom(delta, anchor, 1000.0);
+            assert_eq!(
+                view.coords().step,
+                view.animation.as_ref().unwrap().start.step
+            );
+            let mut previous = view.coords().step.clone();
+            for frame in 1..20 {
+                assert!(view.tick(1000.0 + frame as f64 * 10.0));
+                let step = &view.coords().step;
+                if delta > 0.0 {
+                    assert!(*step < previous);
+                } else {
+                    assert!(*step > previous);
+                }
+                previous = step.clone();
+            }
+            assert!(!view.tick(1000.0 + ZOOM_ANIMATION_MS));
+            assert!(!view.is_animating());
+            assert_eq!(view.coords(), instant.coords());
+        }
+    }
+
+    #[test]
+    fn zooming_during_an_animation_continues_it() {
+        let mut view = view();
+        let start = view.coords().step.clone();
+        view.animate_zoom(1.0, None, 0.0);
+        view.tick(50.0);
+        view.animate_zoom(1.0, None, 50.0);
+        assert!(view.tick(100.0));
+        assert!(!view.tick(50.0 + ZOOM_ANIMATION_MS));
+
+        // Both ticks halve the step
+        let ratio = start.as_f32_approx() / view.coords().step.as_f32_approx();
+        assert!((ratio - 4.0).abs() < 1e-3, "{}", ratio);
+
+        // Other navigation stops the animation where it is
+        view.animate_zoom(1.0, None, 1000.0);
+        view.tick(1050.0);
+        let stopped = view.coords().step.clone();
+        view.move_by_screen_delta(1.0, 1.0);
+        assert!(!view.tick(1100.0));
+        assert_eq!(view.coords().step, stopped);
+    }
+
+    #[test]
+    fn automatic_precision_grows_while_zooming_in() {
+        let mut view = view();
+        let mut word_count = view.coords().size();
+        for _ in 0..60 {
+            view.zoom_with_anchor(1.0, Some(Point { x: 123.0, y: 321.0 }));
+            assert!(view.coords().step > 0);
+            assert!(view.coords().size() >= word_count);
+            word_count = view.coords().size();
+        }
+        assert!(word_count > 2);
+        assert!(significant_bits(&view.coords().step) >= view.precision_bits());
+    }
+
+    /// Asserts that the plane points `a` and `b` are less than `step` apart on both axes
+    fn assert_within(a: (WideFloat, WideFloat), b: (WideFloat, WideFloat), step: &WideFloat) {
+        let distance = |mut a: WideFloat, mut b: WideFloat| {
+            let size = a.word_count().max(b.word_count());
+            a.change_precision(size as isize - a.word_count() as isize);
+            b.change_precision(size as isize - b.word_count() as isize);
+            (a - &b).magnitude_log10()
+        };
+        let step = step.magnitude_log10().unwrap();
+        for distance in [distance(a.0, b.0), distance(a.1, b.1)] {
+            assert!(
+                distance.is_none_or(|d| d < step),
+                "{:?} >= {}",
+                distance,
+                step
+            );
+        }
+    }
+
+    #[test]
+    fn selected_corners_fill_the_view() {
+        for rotation in [0.0, 30.0] {
+            let mut view = view();
+            view.set_rotation(rotation);
+            let mut word_count = view.coords().size();
+            // Already of the view aspect ratio, so that the corners are whole pixels
+            let (min, max) = ((392, 245), (408, 255));
+            for _ in 0..15 {
+                let before = view.coords().clone();
+                let fitted = view.fit_rect(
+                    Point {
+                        x: max.0 as f32,
+                        y: min.1 as f32,
+                    },
+                    Point {
+                        x: min.0 as f32,
+                        y: max.1 as f32,
+                    },
+                );
+                assert_eq!(fitted, Ok(true));
+
+                let coords = view.coords();
+                assert!(coords.size() >= word_count);
+                word_count = coords.size();
+                assert_within(
+                    before.complex_at_pixel(min.0, min.1),
+                    coords.complex_at_pixel(0, 0),
+                    &coords.step,
+                );
+                assert_within(
+                    before.complex_at_pixel(max.0, max.1),
+                    coords.complex_at_pixel(800, 500),
+                    &coords.step,
+                );
+            }
+            assert!(word_count > 2);
+            assert!(significant_bits(&view.coords().step) >= view.precision_bits());
+        }
+    }
+
+    #[test]
+    fn selections_grow_to_the_view_aspect_ratio() {
+        let mut view = view();
+        let (min, max) = view
+            .selection(Point { x: 100.0, y: 100.0 }, Point { x: 180.0, y: 120.0 })
+            .unwrap();
+        assert_eq!((min.x, min.y, max.x, max.y), (100.0, 85.0, 180.0, 135.0));
+
+        // Tiny selections are clicks
+        let before = view.coords().clone();
+        for end in [
+            Point { x: 102.0, y: 97.0 },
+            Point {
+                x: f32::NAN,
+                y: 200.0,
+            },
+        ] {
+            assert_eq!(view.fit_rect(Point { x: 100.0, y: 100.0 }, end), Ok(false));
+        }
+        assert_eq!(view.coords(), &before);
+    }
+
+    #[test]
+    fn automatic_precision_follows_view_size() {
+        let mut view = view();
+        view.zoom_with_anchor(1.0, None);
+        let bits = view.precision_bits();
+        view.set_dimensions(Dimensions::new_nonzero(4000, 500));
+        assert_eq!(view.precision_bits(), bits + 2);
+        assert_eq!(view.coords().precision(), bits + 2);
+
+        view.set_precision(Precision::Manual(64));
+        view.set_dimensions(Dimensions::new_nonzero(100, 100));
+        assert_eq!(view.coords().precision(), 64);
+    }
+
+    #[test]
+    fn resizing_keeps_the_center() {
+        let small = Dimensions::new_nonzero(800, 600);
+        let large = Dimensions::new_nonzero(1920, 1080);
+        for scale_factor in [1.0, 1.5, 2.25] {
+            let mut view = ViewState::default(small, scale_factor, Precision::Auto);
+            view.move_by_screen_delta(13.0, -7.0);
+            for _ in 0..60 {
+                view.zoom_with_anchor(1.0, Some(Point { x: 100.0, y: 500.0 }));
+            }
+            view.set_rotation(30.0);
+            assert!(view.coords().size() > 2);
+            let step = view.coords().step.clone();
+            let center = view.location();
+
+            for dimensions in [large, small, large, small] {
+                view.set_dimensions(dimensions);
+                let location = view.location();
+                assert_eq!(location.re, center.re, "at {}", scale_factor);
+                assert_eq!(location.im, center.im, "at {}", scale_factor);
+                assert_eq!(view.coords().step, step);
+            }
+        }
+    }
+
+    #[test]
+    fn bookmarks_keep_the_center_in_other_views() {
+        let mut saved = ViewState::default(Dimensions::new_nonzero(800, 600), 1.0, Precision::Auto);
+        saved.set_fractal(FractalParams {
+            kind: crate::fractal::FractalKind::Celtic,
+            ..Default::default()
+        });
+        for _ in 0..60 {
+            saved.zoom_with_anchor(1.0, Some(Point { x: 100.0, y: 500.0 }));
+        }
+        saved.set_rotation(30.0);
+        let bookmark = saved.bookmark("Deep".to_owned(), 5000);
+        let center = saved.location();
+
+        let mut view =
+            ViewState::default(Dimensions::new_nonzero(1920, 1080), 2.0, Precision::Auto);
+        view.jump_to_bookmark(&bookmark).unwrap();
+        assert_eq!(view.fractal(), bookmark.fractal);
+        assert_eq!(view.coords().rotation(), 30.0);
+        let location = view.location();
+        assert_eq!(location.re, center.re);
+        assert_eq!(location.im, center.im);
+        // The same plane extent is shown across the shortest side
+        let (span, saved_span) = (location.span.unwrap(), center.span.unwrap());
+        let error = (span - &saved_span)
+            .magnitude_log10()
+            .unwrap_or(f64::NEG_INFINITY);
+        assert!(error < saved_span.magnitude_log10().unwrap() - 6.0);
+
+        assert!(view.undo());
+        assert_eq!(view.fractal(), FractalParams::default());
+    }
+
+    #[test]
+    fn resizing_a_reset_view_frames_the_fractal() {
+        let mut view = ViewState::default(Dimensions::new_nonzero(800, 600), 1.5, Precision::Auto);
+        let resized = Dimensions::new_nonzero(1920, 1080);
+        view.set_dimensions(resized);
+        let expected = ViewState::default(resized, 1.5, Precision::Auto);
+        assert_eq!(view.coords(), expected.coords());
+    }
+
+    #[test]
+    fn zoom_is_relative_to_the_default_framing() {
+        let mut view = view();
+        assert!(view.zoom_log10().unwrap().abs() < 1e-6);
+        view.zoom_with_anchor(9.0, None);
+        assert!((view.zoom_log10().unwrap() - 1.0).abs() < 1e-6);
+        // Far past the point where the step flushes to zero as a float
+        while view.coords().size() < 8 {
+            view.zoom_with_anchor(9.0, None);
+        }
+        assert_eq!(view.coords().step.as_f32_approx(), 0.0);
+        assert!(view.zoom_log10().unwrap() > 40.0);
+    }
+
+    #[test]
+    fn precision_is_parsed() {
+        for precision in [Precision::Auto, Precision::Manual(0), Precision::Manual(96)] {
+            assert_eq!(precision.to_string().parse(), Ok(precision));
+        }
+        assert!("manual".parse::<Precision>().is_err());
+    }
+
+    #[test]
+    fn too_deep_location_is_rejected() {
+        let mut view = view();
+        view.set_fractal(FractalParams {
+            inverted: true,
+            ..Default::default()
+        });
+        let before = view.coords().clone();
+        let location = Location {
+            re: WideFloat::from_f32(1.75, MAX_WORD_COUNT).unwrap(),
+            im: WideFloat::zero(MAX_WORD_COUNT),
+            span: Some(WideFloat::min_positive(MAX_WORD_COUNT, 0)),
+        };
+        assert!(view.jump_to(&location).is_err());
+        assert_eq!(view.coords(), &before);
+    }
+
+    #[test]
+    fn invalid_zoom_is_ignored() {
+        let mut view = view();
+        let before = view.coords().clone();
+        for delta in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -1e30] {
+            view.zoom_with_anchor(delta, None);
+            assert_eq!(view.coords(), &before);
+        }
     }
 }
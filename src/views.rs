@@ -0,0 +1,206 @@
+//! Layout of the views the window is divided into
+
+use std::ops::Range;
+
+use crate::primitives::{Dimensions, Point, Rect};
+use crate::view_state::ViewState;
+
+/// Views of the window. The window is either covered by a single view, or split vertically into
+/// two independent halves
+#[derive(Debug, Clone)]
+pub struct Views {
+    window: Dimensions,
+    views: Vec<ViewState>,
+    /// View that settings and, unless views are linked, navigation apply to
+    active: usize,
+    /// Mirror navigation to all views
+    linked: bool,
+}
+
+impl Views {
+    pub fn new(view: ViewState) -> Self {
+        Self {
+            window: view.dimensions(),
+            views: vec![view],
+            active: 0,
+            linked: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.views.len() > 1
+    }
+
+    /// Splits the window in two. The new right half starts as a copy of the active view
+    pub fn split(&mut self) {
+        if self.is_split() {
+            return;
+        }
+        self.views.push(self.views[self.active].clone());
+        self.set_window(self.window);
+    }
+
+    /// Leaves only the active view, covering the whole window
+    pub fn unsplit(&mut self) {
+        let view = self.views.swap_remove(self.active);
+        self.views = vec![view];
+        self.active = 0;
+        self.set_window(self.window);
+    }
+
+    /// Returns the region of the window covered by the view
+    pub fn rect(&self, index: usize) -> Rect {
+        if !self.is_split() {
+            return Rect::covering(self.window);
+        }
+        let left_width = self.window.width / 2;
+        let (x, width) = match index {
+            0 => (0, left_width),
+            _ => (left_width, self.window.width - left_width),
+        };
+        Rect {
+            x,
+            y: 0,
+            width,
+            height: self.window.height,
+        }
+    }
+
+    pub fn set_window(&mut self, window: Dimensions) {
+        self.window = window;
+        for index in 0..self.views.len() {
+            let dimensions = self.rect(index).dimensions();
+            self.views[index].set_dimensions(dimensions);
+        }
+    }
+
+    pub fn get(&self, index: usize) -> &ViewState {
+        &self.views[index]
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> &mut ViewState {
+        &mut self.views[index]
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn active_view(&self) -> &ViewState {
+        &self.views[self.active]
+    }
+
+    pub fn active_view_mut(&mut self) -> &mut ViewState {
+        &mut self.views[self.active]
+    }
+
+    /// Activates the view under the window `point`. Returns true if the active view has changed
+    pub fn activate_at(&mut self, point: Point) -> bool {
+        let Some(index) = (0..self.views.len()).find(|i| self.rect(*i).contains(point)) else {
+            return false;
+        };
+        let changed = index != self.active;
+        self.active = index;
+        changed
+    }
+
+    /// Converts the window `point` into a point within the active view
+    pub fn to_active(&self, point: Point) -> Point {
+        let rect = self.rect(self.active);
+        Point {
+            x: point.x - rect.x as f32,
+            y: point.y - rect.y as f32,
+        }
+    }
+
+    pub fn set_linked(&mut self, linked: bool) {
+        self.linked = linked;
+    }
+
+    /// Returns indices of the views navigation applies to
+    pub fn navigated(&self) -> Range<usize> {
+        if self.linked {
+            0..self.views.len()
+        } else {
+            self.active..self.active + 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view_state::Precision;
+
+    fn views(width: u32) -> Views {
+        let dimensions = Dimensions::new_nonzero(width, 500);
+        Views::new(ViewState::default(dimensions, 1.0, Precision::Auto))
+    }
+
+    fn point(x: f32) -> Point {
+        Point { x, y: 10.0 }
+    }
+
+    #[test]
+    fn halves_cover_the_window() {
+        for width in [1, 2, 801, 1280] {
+            let mut views = views(width);
+            assert_eq!(views.rect(0).width, width);
+
+            views.split();
+            let (left, right) = (views.rect(0), views.rect(1));
+            assert_eq!(left.x + left.width, right.x);
+            assert_eq!(right.x + right.width, width);
+            assert_eq!((left.height, right.height), (500, 500));
+            assert_eq!(views.get(1).dimensions().width, right.width.max(1));
+        }
+    }
+
+    #[test]
+    fn view_under_pointer_is_activated() {
+        let mut views = views(800);
+        assert!(!views.activate_at(point(700.0)));
+        views.split();
+        assert!(views.activate_at(point(700.0)));
+        assert_eq!(views.active(), 1);
+        assert!(!views.activate_at(point(450.0)));
+        assert!(!views.activate_at(point(900.0)));
+        assert_eq!(views.active(), 1);
+        assert_eq!(views.to_active(point(450.0)).x, 50.0);
+        assert!(views.activate_at(point(399.0)));
+        assert_eq!(views.active(), 0);
+    }
+
+    #[test]
+    fn unsplit_keeps_active_view() {
+        let mut views = views(800);
+        views.split();
+        views.activate_at(point(700.0));
+        views.active_view_mut().move_by_screen_delta(10.0, 0.0);
+        let right = views.active_view().location();
+        let step = views.active_view().coords().step.clone();
+
+        views.unsplit();
+        assert_eq!(views.len(), 1);
+        assert_eq!(views.active(), 0);
+        // The wider view keeps the center and the step
+        let location = views.get(0).location();
+        assert_eq!((location.re, location.im), (right.re, right.im));
+        assert_eq!(views.get(0).coords().step, step);
+        assert_eq!(views.get(0).dimensions().width, 800);
+    }
+
+    #[test]
+    fn linked_views_are_navigated_together() {
+        let mut views = views(800);
+        views.split();
+        views.activate_at(point(700.0));
+        assert_eq!(views.navigated(), 1..2);
+        views.set_linked(true);
+        assert_eq!(views.navigated(), 0..2);
+    }
+}
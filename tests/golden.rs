@@ -0,0 +1,337 @@
+//! Golden-image tests for the GPU renderer
+//!
+//! Each case is rendered to memory and its hash is compared against `tests/golden.txt`. Run with
+//! `BLESS=1` to regenerate the hashes after an intentional rendering change. The tests are skipped
+//! when no GPU adapter is available.
+
+use mandelbrot::{
+    ColorParams, Coordinates, Dimensions, FractalKind, FractalParams, HeadlessContext,
+    IterationParams, JuliaSeed, Palette, Preset,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const DIMENSIONS: Dimensions = Dimensions {
+    width: 160,
+    height: 100,
+};
+const DEPTH: u32 = 300;
+
+struct Case {
+    name: &'static str,
+    fractal: FractalParams,
+    iteration: IterationParams,
+    colors: ColorParams,
+    palette: Palette,
+    /// Center X, center Y and extent across the shortest side
+    framing: (f32, f32, f32),
+}
+
+fn cases() -> Vec<Case> {
+    let mandelbrot = FractalParams::default();
+    let fractal = |kind| FractalParams {
+        kind,
+        ..Default::default()
+    };
+    vec![
+        Case {
+            name: "mandelbrot_default",
+            fractal: mandelbrot.clone(),
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (0.0, 0.0, 4.0),
+        },
+        Case {
+            name: "mandelbrot_seahorse_valley",
+            fractal: mandelbrot,
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (-0.7436, 0.1318, 0.01),
+        },
+        Case {
+            name: "mandelbrot_inverted",
+            fractal: FractalParams {
+                inverted: true,
+                ..Default::default()
+            },
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (1.75, 0.0, 5.0),
+        },
+        Case {
+            name: "celtic_default",
+            fractal: fractal(FractalKind::Celtic),
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (-0.5, 0.0, 4.0),
+        },
+        Case {
+            name: "perpendicular_burning_ship_default",
+            fractal: fractal(FractalKind::PerpendicularBurningShip),
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (-0.5, 0.0, 4.0),
+        },
+        Case {
+            name: "multibrot_cubic",
+            fractal: FractalParams {
+                power: 3,
+                ..Default::default()
+            },
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (0.0, 0.0, 3.0),
+        },
+        Case {
+            name: "celtic_quartic",
+            fractal: FractalParams {
+                kind: FractalKind::Celtic,
+                power: 4,
+                ..Default::default()
+            },
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (0.0, 0.0, 3.0),
+        },
+        Case {
+            name: "julia_douady_rabbit",
+            fractal: FractalParams {
+                julia: JuliaSeed::from_f64(-0.123, 0.745),
+                ..Default::default()
+            },
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (0.0, 0.0, 4.0),
+        },
+        Case {
+            name: "mandelbrot_escape_radius_1000",
+            fractal: FractalParams::default(),
+            iteration: IterationParams {
+                escape_radius: 1000.0,
+                ..Default::default()
+            },
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (0.0, 0.0, 4.0),
+        },
+        Case {
+            name: "mandelbrot_distance_estimation",
+            fractal: FractalParams::default(),
+            iteration: IterationParams {
+                escape_radius: 1000.0,
+                distance_estimation: true,
+            },
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (-0.5, 0.0, 3.0),
+        },
+        Case {
+            name: "multibrot_cubic_escape_radius_1000",
+            fractal: FractalParams {
+                power: 3,
+                ..Default::default()
+            },
+            iteration: IterationParams {
+                escape_radius: 1000.0,
+                ..Default::default()
+            },
+            colors: ColorParams::default(),
+            palette: Palette::default(),
+            framing: (0.0, 0.0, 3.0),
+        },
+        Case {
+            name: "mandelbrot_smooth_colors",
+            fractal: FractalParams::default(),
+            iteration: IterationParams {
+                escape_radius: 1000.0,
+                ..Default::default()
+            },
+            colors: ColorParams {
+                smooth: true,
+                ..Default::default()
+            },
+            palette: Palette::default(),
+            framing: (0.0, 0.0, 4.0),
+        },
+        Case {
+            name: "mandelbrot_seahorse_valley_equalized",
+            fractal: FractalParams::default(),
+            iteration: IterationParams::default(),
+            colors: ColorParams {
+                equalize: true,
+                ..Default::default()
+            },
+            palette: Palette::default(),
+            framing: (-0.7436, 0.1318, 0.01),
+        },
+        Case {
+            name: "mandelbrot_fire_palette",
+            fractal: FractalParams::default(),
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Preset::Fire.palette(),
+            framing: (-0.5, 0.0, 3.0),
+        },
+        Case {
+            name: "mandelbrot_viridis_palette",
+            fractal: FractalParams::default(),
+            iteration: IterationParams::default(),
+            colors: ColorParams::default(),
+            palette: Preset::Viridis.palette(),
+            framing: (-0.5, 0.0, 3.0),
+        },
+    ]
+}
+
+fn coordinates((x, y, span): (f32, f32, f32)) -> Coordinates {
+    let step = span / DIMENSIONS.shortest_side() as f32;
+    // Framings are computed in f32, as the hashes were recorded with f32 coordinates
+    Coordinates::new(
+        (x - DIMENSIONS.width as f32 / 2.0 * step).into(),
+        (y - DIMENSIONS.height as f32 / 2.0 * step).into(),
+        step.into(),
+        10,
+    )
+    .unwrap()
+}
+
+/// 64 bit FNV-1a, stable across platforms and toolchains
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn golden_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden.txt")
+}
+
+fn read_golden() -> BTreeMap<String, String> {
+    std::fs::read_to_string(golden_path())
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(' '))
+        .map(|(name, hash)| (name.to_owned(), hash.trim().to_owned()))
+        .collect()
+}
+
+fn write_golden(hashes: &BTreeMap<String, String>) {
+    let mut contents = String::from("# Generated with BLESS=1 cargo test --test golden\n");
+    for (name, hash) in hashes {
+        contents.push_str(&format!("{name} {hash}\n"));
+    }
+    std::fs::write(golden_path(), contents).expect("Unable to write golden hashes");
+}
+
+/// Targets that encode the colors into sRGB themselves and ones that have the shader do it show
+/// the same pixels, at the sRGB gamma and corrected for another one
+#[test]
+fn srgb_targets_match() {
+    let mut context = match pollster::block_on(HeadlessContext::new()) {
+        Ok(context) => context,
+        Err(e) => {
+            eprintln!("Skipping sRGB target tests: {e}");
+            return;
+        }
+    };
+
+    for gamma in [2.2, 1.8] {
+        // Smooth grayscale colors are a gradient through every level
+        let colors = ColorParams {
+            smooth: true,
+            gamma,
+            ..Default::default()
+        };
+        let iteration = IterationParams {
+            escape_radius: 1000.0,
+            ..Default::default()
+        };
+        let mut render = |srgb| {
+            context.set_srgb_target(srgb);
+            context
+                .render_to_vec(
+                    &coordinates((-0.5, 0.0, 3.0)),
+                    FractalParams::default(),
+                    iteration,
+                    colors,
+                    &Preset::Grayscale.palette(),
+                    DIMENSIONS,
+                    DEPTH,
+                )
+                .unwrap()
+        };
+        let encoded = render(false);
+        let srgb = render(true);
+        let levels: std::collections::BTreeSet<_> = encoded.chunks_exact(4).map(|p| p[0]).collect();
+        assert!(levels.len() > 64, "Too few levels: {}", levels.len());
+        // Encoders of targets may round differently, APIs only require them to be exact to
+        // within about half a level
+        let off = encoded.iter().zip(&srgb).map(|(a, b)| a.abs_diff(*b)).max();
+        assert!(off <= Some(1), "Pixels differ by {off:?} at gamma {gamma}");
+    }
+}
+
+#[test]
+fn golden_images() {
+    let mut context = match pollster::block_on(HeadlessContext::new()) {
+        Ok(context) => context,
+        Err(e) => {
+            eprintln!("Skipping golden image tests: {e}");
+            return;
+        }
+    };
+
+    let bless = std::env::var_os("BLESS").is_some();
+    let mut golden = read_golden();
+    let mut mismatches = Vec::new();
+
+    for case in cases() {
+        let pixels = context
+            .render_to_vec(
+                &coordinates(case.framing),
+                case.fractal,
+                case.iteration,
+                case.colors,
+                &case.palette,
+                DIMENSIONS,
+                DEPTH,
+            )
+            .unwrap();
+        assert_eq!(
+            pixels.len(),
+            (DIMENSIONS.width * DIMENSIONS.height * 4) as usize
+        );
+        let actual = format!("{:016x}", hash(&pixels));
+
+        if bless {
+            golden.insert(case.name.to_owned(), actual);
+        } else {
+            match golden.get(case.name) {
+                Some(expected) if *expected == actual => {}
+                expected => mismatches.push(format!(
+                    "{}: expected {}, got {actual}",
+                    case.name,
+                    expected.map(String::as_str).unwrap_or("nothing"),
+                )),
+            }
+        }
+    }
+
+    if bless {
+        write_golden(&golden);
+    }
+    assert!(
+        mismatches.is_empty(),
+        "Golden image mismatch, rerun with BLESS=1 if the change is intended:\n{}",
+        mismatches.join("\n")
+    );
+}
@@ -0,0 +1,168 @@
+//! Checks of the GPU calculation against a CPU reference in f64
+//!
+//! The tests are skipped when no GPU adapter is available.
+
+use mandelbrot::{
+    Coordinates, Dimensions, FractalParams, HeadlessContext, IterationParams, SETTLED,
+};
+
+const DIMENSIONS: Dimensions = Dimensions {
+    width: 256,
+    height: 160,
+};
+/// A power of two, so that the pixel positions are exact in f64 and in the shader
+const STEP: f64 = 1.0 / 64.0;
+const ORIGIN: (f64, f64) = (-2.0, -1.25);
+const DEPTH: u32 = 10_000;
+
+/// Spiral near a Misiurewicz point, deep enough for the perturbation at six words
+const SPIRAL: (f64, f64) = (-0.77568377, 0.13646737);
+const SPIRAL_STEP: f64 = 1.0 / (1u64 << 30) as f64;
+const SPIRAL_DEPTH: u32 = 1000;
+
+/// Returns true if the Mandelbrot set iteration of `c` escapes the radius 2 within `depth`
+fn escapes((x, y): (f64, f64), depth: u32) -> bool {
+    let (mut zx, mut zy) = (x, y);
+    for _ in 0..depth {
+        if zx * zx + zy * zy >= 4.0 {
+            return true;
+        }
+        (zx, zy) = (zx * zx - zy * zy + x, 2.0 * zx * zy + y);
+    }
+    false
+}
+
+#[test]
+fn settled_pixels_never_escape() {
+    let mut context = match pollster::block_on(HeadlessContext::new()) {
+        Ok(context) => context,
+        Err(e) => {
+            eprintln!("Skipping reference tests: {e}");
+            return;
+        }
+    };
+
+    let coords = Coordinates::new(ORIGIN.0, ORIGIN.1, STEP, 10).unwrap();
+    let iterations = context
+        .iterations_to_vec(
+            &coords,
+            FractalParams::default(),
+            IterationParams::default(),
+            DIMENSIONS,
+            DEPTH,
+        )
+        .unwrap();
+    assert_eq!(
+        iterations.len(),
+        (DIMENSIONS.width * DIMENSIONS.height) as usize
+    );
+
+    let mut settled = 0;
+    for (index, &count) in iterations.iter().enumerate() {
+        if count != SETTLED {
+            continue;
+        }
+        settled += 1;
+        let (column, row) = (
+            index as u32 % DIMENSIONS.width,
+            index as u32 / DIMENSIONS.width,
+        );
+        let c = (
+            ORIGIN.0 + column as f64 * STEP,
+            ORIGIN.1 + row as f64 * STEP,
+        );
+        assert!(!escapes(c, DEPTH), "Pixel {column}, {row} at {c:?} escapes");
+    }
+    // Most of the set is found long before the depth
+    assert!(
+        settled > iterations.len() / 10,
+        "Only {settled} pixels have settled"
+    );
+}
+
+#[test]
+fn perturbation_matches_the_exact_calculation() {
+    let mut context = match pollster::block_on(HeadlessContext::new()) {
+        Ok(context) => context,
+        Err(e) => {
+            eprintln!("Skipping reference tests: {e}");
+            return;
+        }
+    };
+
+    let mut coords = Coordinates::new(
+        SPIRAL.0 - (DIMENSIONS.width / 2) as f64 * SPIRAL_STEP,
+        SPIRAL.1 - (DIMENSIONS.height / 2) as f64 * SPIRAL_STEP,
+        SPIRAL_STEP,
+        10,
+    )
+    .unwrap();
+    coords.set_word_count(6);
+    let calculate = |context: &mut HeadlessContext| {
+        context
+            .iterations_to_vec(
+                &coords,
+                FractalParams::default(),
+                IterationParams::default(),
+                DIMENSIONS,
+                SPIRAL_DEPTH,
+            )
+            .unwrap()
+    };
+    let exact = calculate(&mut context);
+    context.set_perturbation(true);
+    let perturbed = calculate(&mut context);
+
+    // The deltas round differently from the exact numbers, which moves a few escapes by an
+    // iteration or flips pixels right at the boundary
+    let matching = exact
+        .iter()
+        .zip(&perturbed)
+        .filter(|(exact, perturbed)| exact.abs_diff(**perturbed) <= 1)
+        .count();
+    assert!(
+        matching >= exact.len() * 99 / 100,
+        "Only {matching} of {} pixels match",
+        exact.len()
+    );
+}
+
+#[test]
+fn bands_match_a_single_dispatch() {
+    let mut context = match pollster::block_on(HeadlessContext::new()) {
+        Ok(context) => context,
+        Err(e) => {
+            eprintln!("Skipping reference tests: {e}");
+            return;
+        }
+    };
+
+    let shallow = Coordinates::new(ORIGIN.0, ORIGIN.1, STEP, 10).unwrap();
+    let mut deep = Coordinates::new(
+        SPIRAL.0 - (DIMENSIONS.width / 2) as f64 * SPIRAL_STEP,
+        SPIRAL.1 - (DIMENSIONS.height / 2) as f64 * SPIRAL_STEP,
+        SPIRAL_STEP,
+        10,
+    )
+    .unwrap();
+    deep.set_word_count(6);
+    context.set_perturbation(true);
+    for coords in [shallow, deep] {
+        let calculate = |context: &mut HeadlessContext| {
+            context
+                .iterations_to_vec(
+                    &coords,
+                    FractalParams::default(),
+                    IterationParams::default(),
+                    DIMENSIONS,
+                    SPIRAL_DEPTH,
+                )
+                .unwrap()
+        };
+        context.set_band_rows(None);
+        let whole = calculate(&mut context);
+        // The last band is shorter than the others
+        context.set_band_rows(Some(64));
+        assert!(whole == calculate(&mut context), "Bands change the result");
+    }
+}